@@ -945,7 +945,11 @@ impl PublicKey {
     }
 
     /// BIP341 derivation
-    fn derive_bip341(&self, ttr: &[u8]) -> Result<Self, InvalidTaprootHash> {
+    ///
+    /// Returns the Taproot output key obtained by tweaking this key with the
+    /// given Taproot tree root. An empty tree root corresponds to a key path
+    /// only output.
+    pub fn derive_bip341(&self, ttr: &[u8]) -> Result<Self, InvalidTaprootHash> {
         use k256::elliptic_curve::ops::MulByGenerator;
 
         let pk = self.serialize_sec1(true);
@@ -179,6 +179,39 @@ fn bitcoin_library_accepts_our_bip341_signatures() {
     }
 }
 
+#[test]
+fn should_derive_bip341_output_key() {
+    use bitcoin::{
+        schnorr::TapTweak,
+        secp256k1::{Secp256k1, XOnlyPublicKey},
+    };
+
+    // Test vector from BIP86 (key path only output)
+    let internal_key = PublicKey::deserialize_bip340(&hex!(
+        "cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115"
+    ))
+    .unwrap();
+    assert_eq!(
+        hex::encode(internal_key.derive_bip341(&[]).unwrap().serialize_bip340()),
+        "a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c"
+    );
+
+    let secp256k1 = Secp256k1::new();
+    let mut rng = test_rng();
+
+    for _trial in 0..100 {
+        let pk = PrivateKey::generate_using_rng(&mut rng).public_key();
+
+        let xonly = XOnlyPublicKey::from_slice(&pk.serialize_bip340()).unwrap();
+        let tweaked_key = xonly.tap_tweak(&secp256k1, None).0.to_inner();
+
+        assert_eq!(
+            pk.derive_bip341(&[]).unwrap().serialize_bip340(),
+            tweaked_key.serialize().to_vec()
+        );
+    }
+}
+
 #[test]
 fn should_accept_bip340_signatures_that_we_generate() {
     use rand::RngCore;
@@ -0,0 +1,38 @@
+use candid::{CandidType, Deserialize, Nat};
+use serde::Serialize;
+
+use super::super::icrc1::account::Account;
+
+/// The arguments for the
+/// [ICRC-103 `get_allowances`](https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-103/ICRC-103.md#icrc103_get_allowances)
+/// endpoint.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+pub struct GetAllowancesArgs {
+    /// The account whose allowances are listed; defaults to the default account of the caller.
+    pub from_account: Option<Account>,
+    /// The spender after which the listing starts (exclusive).
+    pub prev_spender: Option<Account>,
+    /// The maximum number of allowances to return.
+    pub take: Option<Nat>,
+}
+
+/// An allowance returned by the
+/// [ICRC-103 `get_allowances`](https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-103/ICRC-103.md#icrc103_get_allowances)
+/// endpoint.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Allowance {
+    pub from_account: Account,
+    pub to_spender: Account,
+    pub allowance: Nat,
+    pub expires_at: Option<u64>,
+}
+
+pub type Allowances = Vec<Allowance>;
+
+/// The error type for the
+/// [ICRC-103 `get_allowances`](https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-103/ICRC-103.md#icrc103_get_allowances)
+/// endpoint.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum GetAllowancesError {
+    GenericError { error_code: Nat, message: String },
+}
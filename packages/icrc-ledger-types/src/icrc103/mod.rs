@@ -0,0 +1,3 @@
+//! The [ICRC-103](https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-103/ICRC-103.md)
+//! Enhanced Allowance Query Mechanism with Pagination standard.
+pub mod get_allowances;
@@ -0,0 +1,3 @@
+//! The [ICRC-4](https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-4/ICRC-4.md)
+//! Batch Transfers standard.
+pub mod transfer_batch;
@@ -0,0 +1,57 @@
+use candid::{CandidType, Deserialize, Nat};
+use serde::Serialize;
+
+use super::super::icrc1::transfer::{BlockIndex, NumTokens, TransferArg, TransferError};
+
+/// The arguments for the
+/// [ICRC-4 `transfer_batch`](https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-4/ICRC-4.md#icrc4_transfer_batch)
+/// endpoint.
+pub type TransferBatchArgs = Vec<TransferArg>;
+
+/// Errors defined for the
+/// [ICRC-4 `transfer_batch`](https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-4/ICRC-4.md#icrc4_transfer_batch)
+/// endpoint.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TransferBatchError {
+    BadFee { expected_fee: NumTokens },
+    BadBurn { min_burn_amount: NumTokens },
+    InsufficientFunds { balance: NumTokens },
+    TooOld,
+    CreatedInFuture { ledger_time: u64 },
+    TemporarilyUnavailable,
+    Duplicate { duplicate_of: BlockIndex },
+    GenericError { error_code: Nat, message: String },
+    GenericBatchError { error_code: Nat, message: String },
+    TooManyRequests { limit: Nat },
+}
+
+impl From<TransferError> for TransferBatchError {
+    fn from(err: TransferError) -> Self {
+        match err {
+            TransferError::BadFee { expected_fee } => Self::BadFee { expected_fee },
+            TransferError::BadBurn { min_burn_amount } => Self::BadBurn { min_burn_amount },
+            TransferError::InsufficientFunds { balance } => Self::InsufficientFunds { balance },
+            TransferError::TooOld => Self::TooOld,
+            TransferError::CreatedInFuture { ledger_time } => Self::CreatedInFuture { ledger_time },
+            TransferError::TemporarilyUnavailable => Self::TemporarilyUnavailable,
+            TransferError::Duplicate { duplicate_of } => Self::Duplicate { duplicate_of },
+            TransferError::GenericError {
+                error_code,
+                message,
+            } => Self::GenericError {
+                error_code,
+                message,
+            },
+        }
+    }
+}
+
+/// The result of a single transfer of a batch.
+pub type TransferBatchResult = Result<BlockIndex, TransferBatchError>;
+
+/// The response type for the
+/// [ICRC-4 `transfer_batch`](https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-4/ICRC-4.md#icrc4_transfer_batch)
+/// endpoint: the i-th entry is the result of the i-th transfer of the batch
+/// or `None` if that transfer was not processed. A batch rejected as a whole
+/// (e.g., because it contains too many transfers) yields a single error entry.
+pub type TransferBatchResults = Vec<Option<TransferBatchResult>>;
@@ -532,6 +532,40 @@ pub struct InstanceConfig {
     pub nonmainnet_features: bool,
     pub log_level: Option<String>,
     pub bitcoind_addr: Option<Vec<SocketAddr>>,
+    /// If set, all state-changing requests to the instance are recorded
+    /// into a file at this path (see `RecordedEntry`).
+    pub recording_path: Option<PathBuf>,
+}
+
+/// A blob from the blob store used by a recorded request.
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RecordedBlob {
+    #[serde(deserialize_with = "base64::deserialize")]
+    #[serde(serialize_with = "base64::serialize")]
+    pub data: Vec<u8>,
+    pub gzip: bool,
+}
+
+/// A state-changing request to an instance.
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RecordedRequest {
+    /// The endpoint relative to the instance URL, e.g., `update/tick`.
+    pub endpoint: String,
+    pub content_type: Option<String>,
+    #[serde(deserialize_with = "base64::deserialize")]
+    #[serde(serialize_with = "base64::serialize")]
+    pub body: Vec<u8>,
+}
+
+/// An entry of an instance recording. A recording is stored as a file
+/// containing one JSON-encoded entry per line: the first entry
+/// is the configuration of the recorded instance and the subsequent entries
+/// are the state-changing requests to the instance in the order of their execution.
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub enum RecordedEntry {
+    Instance(InstanceConfig),
+    Blob(RecordedBlob),
+    Request(RecordedRequest),
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize, Default, JsonSchema)]
@@ -941,3 +975,394 @@ impl From<MockCanisterHttpResponse> for RawMockCanisterHttpResponse {
         }
     }
 }
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawCallInterceptor {
+    pub canister_id: RawCanisterId,
+    pub method: Option<String>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawInterceptedCall {
+    pub subnet_id: RawSubnetId,
+    pub call_id: u64,
+    pub sender: RawCanisterId,
+    pub receiver: RawCanisterId,
+    pub method: String,
+    #[serde(deserialize_with = "base64::deserialize")]
+    #[serde(serialize_with = "base64::serialize")]
+    pub payload: Vec<u8>,
+    pub cycles: u128,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct InterceptedCall {
+    pub subnet_id: Principal,
+    pub call_id: u64,
+    pub sender: Principal,
+    pub receiver: Principal,
+    pub method: String,
+    #[serde(deserialize_with = "base64::deserialize")]
+    #[serde(serialize_with = "base64::serialize")]
+    pub payload: Vec<u8>,
+    pub cycles: u128,
+}
+
+impl From<RawInterceptedCall> for InterceptedCall {
+    fn from(raw_intercepted_call: RawInterceptedCall) -> Self {
+        Self {
+            subnet_id: raw_intercepted_call.subnet_id.into(),
+            call_id: raw_intercepted_call.call_id,
+            sender: raw_intercepted_call.sender.into(),
+            receiver: raw_intercepted_call.receiver.into(),
+            method: raw_intercepted_call.method,
+            payload: raw_intercepted_call.payload,
+            cycles: raw_intercepted_call.cycles,
+        }
+    }
+}
+
+impl From<InterceptedCall> for RawInterceptedCall {
+    fn from(intercepted_call: InterceptedCall) -> Self {
+        Self {
+            subnet_id: intercepted_call.subnet_id.into(),
+            call_id: intercepted_call.call_id,
+            sender: intercepted_call.sender.into(),
+            receiver: intercepted_call.receiver.into(),
+            method: intercepted_call.method,
+            payload: intercepted_call.payload,
+            cycles: intercepted_call.cycles,
+        }
+    }
+}
+
+#[derive(
+    Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, JsonSchema,
+)]
+pub struct InterceptedCallReject {
+    pub reject_code: u64,
+    pub message: String,
+}
+
+#[derive(
+    Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, JsonSchema,
+)]
+pub enum InterceptedCallResponse {
+    InterceptedCallReply(
+        #[serde(deserialize_with = "base64::deserialize")]
+        #[serde(serialize_with = "base64::serialize")]
+        Vec<u8>,
+    ),
+    InterceptedCallReject(InterceptedCallReject),
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawMockInterceptedCallResponse {
+    pub subnet_id: RawSubnetId,
+    pub call_id: u64,
+    pub response: InterceptedCallResponse,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub struct MockInterceptedCallResponse {
+    pub subnet_id: Principal,
+    pub call_id: u64,
+    pub response: InterceptedCallResponse,
+}
+
+impl From<RawMockInterceptedCallResponse> for MockInterceptedCallResponse {
+    fn from(raw_mock_intercepted_call_response: RawMockInterceptedCallResponse) -> Self {
+        Self {
+            subnet_id: raw_mock_intercepted_call_response.subnet_id.into(),
+            call_id: raw_mock_intercepted_call_response.call_id,
+            response: raw_mock_intercepted_call_response.response,
+        }
+    }
+}
+
+impl From<MockInterceptedCallResponse> for RawMockInterceptedCallResponse {
+    fn from(mock_intercepted_call_response: MockInterceptedCallResponse) -> Self {
+        Self {
+            subnet_id: mock_intercepted_call_response.subnet_id.into(),
+            call_id: mock_intercepted_call_response.call_id,
+            response: mock_intercepted_call_response.response,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawDownloadCanisterSnapshot {
+    pub canister_id: RawCanisterId,
+    #[serde(deserialize_with = "base64::deserialize")]
+    #[serde(serialize_with = "base64::serialize")]
+    pub snapshot_id: Vec<u8>,
+    pub snapshot_path: PathBuf,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawUploadCanisterSnapshot {
+    pub canister_id: RawCanisterId,
+    pub snapshot_path: PathBuf,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawCanisterExecutionReport {
+    pub canister_id: RawCanisterId,
+    pub subnet_id: RawSubnetId,
+    pub instructions_executed: u64,
+    pub cycles_consumed_by_use_case: BTreeMap<String, u128>,
+    pub cycles_balance_change: i128,
+    pub wasm_memory_growth: i64,
+    pub stable_memory_growth: i64,
+    pub calls_made: u64,
+}
+
+/// Execution costs incurred by a single canister while processing a call.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct CanisterExecutionReport {
+    pub canister_id: Principal,
+    pub subnet_id: Principal,
+    /// The number of instructions executed by all messages (the call itself
+    /// and the callbacks of its downstream calls) of the canister.
+    pub instructions_executed: u64,
+    /// The cycles consumed by the canister, keyed by the use case
+    /// (e.g., `Instructions` or `RequestAndResponseTransmission`).
+    pub cycles_consumed_by_use_case: BTreeMap<String, u128>,
+    /// The change of the cycles balance of the canister
+    /// (including cycles attached to calls and refunds).
+    pub cycles_balance_change: i128,
+    /// The growth of the Wasm (heap) memory of the canister in bytes.
+    pub wasm_memory_growth: i64,
+    /// The growth of the stable memory of the canister in bytes.
+    pub stable_memory_growth: i64,
+    /// The number of downstream calls made by the canister.
+    pub calls_made: u64,
+}
+
+impl CanisterExecutionReport {
+    /// The total amount of cycles consumed by the canister.
+    pub fn cycles_consumed(&self) -> u128 {
+        self.cycles_consumed_by_use_case.values().sum()
+    }
+}
+
+impl From<RawCanisterExecutionReport> for CanisterExecutionReport {
+    fn from(raw_report: RawCanisterExecutionReport) -> Self {
+        Self {
+            canister_id: raw_report.canister_id.into(),
+            subnet_id: raw_report.subnet_id.into(),
+            instructions_executed: raw_report.instructions_executed,
+            cycles_consumed_by_use_case: raw_report.cycles_consumed_by_use_case,
+            cycles_balance_change: raw_report.cycles_balance_change,
+            wasm_memory_growth: raw_report.wasm_memory_growth,
+            stable_memory_growth: raw_report.stable_memory_growth,
+            calls_made: raw_report.calls_made,
+        }
+    }
+}
+
+impl From<CanisterExecutionReport> for RawCanisterExecutionReport {
+    fn from(report: CanisterExecutionReport) -> Self {
+        Self {
+            canister_id: report.canister_id.into(),
+            subnet_id: report.subnet_id.into(),
+            instructions_executed: report.instructions_executed,
+            cycles_consumed_by_use_case: report.cycles_consumed_by_use_case,
+            cycles_balance_change: report.cycles_balance_change,
+            wasm_memory_growth: report.wasm_memory_growth,
+            stable_memory_growth: report.stable_memory_growth,
+            calls_made: report.calls_made,
+        }
+    }
+}
+
+/// The kind of a message executed on behalf of a call.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, JsonSchema)]
+pub enum ExecutedMessageKind {
+    /// The call itself.
+    Call,
+    /// The response callback of a downstream call.
+    Response,
+}
+
+/// Execution costs incurred by a single message (across all its slices
+/// if its execution was split by deterministic time slicing).
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq, JsonSchema)]
+pub struct MessageExecutionReport {
+    pub kind: ExecutedMessageKind,
+    pub instructions_executed: u64,
+    /// The number of downstream calls made by the message.
+    pub calls_made: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawCallReport {
+    pub canister_id: RawCanisterId,
+    pub subnet_id: RawSubnetId,
+    pub method: String,
+    pub messages: Vec<MessageExecutionReport>,
+    pub calls: Vec<RawCallReport>,
+}
+
+/// A call in the call tree of an execution report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallReport {
+    /// The canister executing the call.
+    pub canister_id: Principal,
+    pub subnet_id: Principal,
+    pub method: String,
+    /// The messages executed by the canister on behalf of the call: the call itself
+    /// followed by the response callbacks of its downstream calls.
+    pub messages: Vec<MessageExecutionReport>,
+    /// The downstream calls in the order in which they were made.
+    pub calls: Vec<CallReport>,
+}
+
+impl CallReport {
+    /// The number of instructions executed by all messages of the call
+    /// (excluding its downstream calls).
+    pub fn instructions_executed(&self) -> u64 {
+        self.messages
+            .iter()
+            .map(|message| message.instructions_executed)
+            .sum()
+    }
+
+    /// The number of instructions executed by all messages of the call
+    /// and of its downstream calls (directly or transitively).
+    pub fn total_instructions_executed(&self) -> u64 {
+        self.instructions_executed()
+            + self
+                .calls
+                .iter()
+                .map(|call| call.total_instructions_executed())
+                .sum::<u64>()
+    }
+}
+
+impl From<RawCallReport> for CallReport {
+    fn from(raw_report: RawCallReport) -> Self {
+        Self {
+            canister_id: raw_report.canister_id.into(),
+            subnet_id: raw_report.subnet_id.into(),
+            method: raw_report.method,
+            messages: raw_report.messages,
+            calls: raw_report.calls.into_iter().map(|c| c.into()).collect(),
+        }
+    }
+}
+
+impl From<CallReport> for RawCallReport {
+    fn from(report: CallReport) -> Self {
+        Self {
+            canister_id: report.canister_id.into(),
+            subnet_id: report.subnet_id.into(),
+            method: report.method,
+            messages: report.messages,
+            calls: report.calls.into_iter().map(|c| c.into()).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawExecutionReport {
+    pub result: RawCanisterResult,
+    pub canisters: Vec<RawCanisterExecutionReport>,
+    pub call: RawCallReport,
+}
+
+/// The result of a call together with the execution costs it incurred.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExecutionReport {
+    pub result: Result<Vec<u8>, RejectResponse>,
+    /// The execution costs per canister: the first entry belongs to the
+    /// called canister and the remaining entries to all canisters
+    /// executing downstream calls (directly or transitively).
+    pub canisters: Vec<CanisterExecutionReport>,
+    /// The call tree with a per-message breakdown of the execution costs.
+    pub call: CallReport,
+}
+
+impl ExecutionReport {
+    /// The execution costs of the given canister, if it was involved in the call.
+    pub fn canister(&self, canister_id: Principal) -> Option<&CanisterExecutionReport> {
+        self.canisters
+            .iter()
+            .find(|report| report.canister_id == canister_id)
+    }
+
+    /// The total number of instructions executed by all canisters involved in the call.
+    pub fn total_instructions_executed(&self) -> u64 {
+        self.canisters
+            .iter()
+            .map(|report| report.instructions_executed)
+            .sum()
+    }
+
+    /// The total amount of cycles consumed by all canisters involved in the call.
+    pub fn total_cycles_consumed(&self) -> u128 {
+        self.canisters
+            .iter()
+            .map(|report| report.cycles_consumed())
+            .sum()
+    }
+}
+
+impl From<RawExecutionReport> for ExecutionReport {
+    fn from(raw_report: RawExecutionReport) -> Self {
+        Self {
+            result: raw_report.result.into(),
+            canisters: raw_report.canisters.into_iter().map(|c| c.into()).collect(),
+            call: raw_report.call.into(),
+        }
+    }
+}
+
+impl From<ExecutionReport> for RawExecutionReport {
+    fn from(report: ExecutionReport) -> Self {
+        Self {
+            result: report.result.into(),
+            canisters: report.canisters.into_iter().map(|c| c.into()).collect(),
+            call: report.call.into(),
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawSetXNetPaused {
+    pub from_subnet: RawSubnetId,
+    pub to_subnet: RawSubnetId,
+    pub paused: bool,
+}
+
+/// A fault affecting responses sent from canisters on one subnet
+/// to canisters on another subnet.
+#[derive(
+    Copy, Clone, Serialize, Deserialize, Debug, Hash, Eq, PartialEq, Ord, PartialOrd, JsonSchema,
+)]
+pub enum XNetResponseFault {
+    /// Every response is replaced by a reject response with reject code `SYS_TRANSIENT`
+    /// (or `SYS_UNKNOWN` for best-effort calls).
+    Drop,
+    /// Every response is delivered with a delay of the given number of rounds.
+    Delay { rounds: u64 },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawSetXNetResponseFault {
+    pub from_subnet: RawSubnetId,
+    pub to_subnet: RawSubnetId,
+    pub fault: Option<XNetResponseFault>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawSetSubnetStalled {
+    pub subnet_id: RawSubnetId,
+    pub stalled: bool,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+pub struct RawSetCanisterQueuesFull {
+    pub canister_id: RawCanisterId,
+    pub full: bool,
+}
@@ -54,9 +54,10 @@
 ///
 use crate::{
     common::rest::{
-        BlobCompression, BlobId, CanisterHttpRequest, ExtendedSubnetConfigSet, HttpsConfig,
-        InstanceId, MockCanisterHttpResponse, RawEffectivePrincipal, RawMessageId, SubnetId,
-        SubnetKind, SubnetSpec, Topology,
+        BlobCompression, BlobId, CanisterHttpRequest, ExecutionReport, ExtendedSubnetConfigSet,
+        HttpsConfig, InstanceId, InterceptedCall, MockCanisterHttpResponse,
+        MockInterceptedCallResponse, RawEffectivePrincipal, RawMessageId, RecordedEntry, SubnetId,
+        SubnetKind, SubnetSpec, Topology, XNetResponseFault,
     },
     nonblocking::PocketIc as PocketIcAsync,
 };
@@ -82,6 +83,7 @@ use std::{
     net::SocketAddr,
     path::PathBuf,
     process::Command,
+    str::FromStr,
     sync::{mpsc::channel, Arc},
     thread,
     thread::JoinHandle,
@@ -113,6 +115,8 @@ pub struct PocketIcBuilder {
     nonmainnet_features: bool,
     log_level: Option<Level>,
     bitcoind_addr: Option<Vec<SocketAddr>>,
+    recording_path: Option<PathBuf>,
+    replay: Option<(PathBuf, Option<usize>)>,
 }
 
 #[allow(clippy::new_without_default)]
@@ -127,6 +131,8 @@ impl PocketIcBuilder {
             nonmainnet_features: false,
             log_level: None,
             bitcoind_addr: None,
+            recording_path: None,
+            replay: None,
         }
     }
 
@@ -137,30 +143,85 @@ impl PocketIcBuilder {
     }
 
     pub fn build(self) -> PocketIc {
-        PocketIc::from_components(
-            self.config.unwrap_or_default(),
-            self.server_url,
-            self.server_binary,
-            self.max_request_time_ms,
-            self.state_dir,
-            self.nonmainnet_features,
-            self.log_level,
-            self.bitcoind_addr,
-        )
+        let (builder, replayed_entries) = self.load_replay();
+        let pic = PocketIc::from_components(
+            builder.config.unwrap_or_default(),
+            builder.server_url,
+            builder.server_binary,
+            builder.max_request_time_ms,
+            builder.state_dir,
+            builder.nonmainnet_features,
+            builder.log_level,
+            builder.bitcoind_addr,
+            builder.recording_path,
+        );
+        pic.replay(replayed_entries);
+        pic
     }
 
     pub async fn build_async(self) -> PocketIcAsync {
-        PocketIcAsync::from_components(
-            self.config.unwrap_or_default(),
-            self.server_url,
-            self.server_binary,
-            self.max_request_time_ms,
-            self.state_dir,
-            self.nonmainnet_features,
-            self.log_level,
-            self.bitcoind_addr,
+        let (builder, replayed_entries) = self.load_replay();
+        let pic = PocketIcAsync::from_components(
+            builder.config.unwrap_or_default(),
+            builder.server_url,
+            builder.server_binary,
+            builder.max_request_time_ms,
+            builder.state_dir,
+            builder.nonmainnet_features,
+            builder.log_level,
+            builder.bitcoind_addr,
+            builder.recording_path,
         )
-        .await
+        .await;
+        pic.replay(replayed_entries).await;
+        pic
+    }
+
+    /// Loads the recording to be replayed (if any), overrides the instance configuration
+    /// of this builder by the configuration of the recorded instance,
+    /// and returns the recorded entries to be replayed.
+    fn load_replay(mut self) -> (Self, Vec<RecordedEntry>) {
+        let Some((recording_path, num_requests)) = self.replay.take() else {
+            return (self, vec![]);
+        };
+        let recording = std::fs::read_to_string(&recording_path).unwrap_or_else(|e| {
+            panic!(
+                "Failed to read recording {}: {}",
+                recording_path.display(),
+                e
+            )
+        });
+        let mut entries = recording.lines().map(|line| {
+            serde_json::from_str::<RecordedEntry>(line)
+                .unwrap_or_else(|e| panic!("Failed to parse recorded entry: {}", e))
+        });
+        let Some(RecordedEntry::Instance(instance_config)) = entries.next() else {
+            panic!(
+                "Recording {} does not start with an instance configuration",
+                recording_path.display()
+            );
+        };
+        self.config = Some(instance_config.subnet_config_set);
+        self.state_dir = instance_config.state_dir;
+        self.nonmainnet_features = instance_config.nonmainnet_features;
+        self.log_level = instance_config
+            .log_level
+            .map(|log_level| Level::from_str(&log_level).unwrap());
+        self.bitcoind_addr = instance_config.bitcoind_addr;
+
+        let mut remaining_requests = num_requests.unwrap_or(usize::MAX);
+        let entries = entries
+            .take_while(|entry| {
+                if remaining_requests == 0 {
+                    return false;
+                }
+                if let RecordedEntry::Request(_) = entry {
+                    remaining_requests -= 1;
+                }
+                true
+            })
+            .collect();
+        (self, entries)
     }
 
     /// Provide the path to the PocketIC server binary used instead of the environment variable `POCKET_IC_BIN`.
@@ -206,6 +267,27 @@ impl PocketIcBuilder {
         }
     }
 
+    /// Record all state-changing requests to the new instance (e.g., ingress messages, ticks,
+    /// time changes, canister HTTP outcall mocks, and cycles top-ups) into a file at the given path
+    /// on the filesystem of the PocketIC server. The recording can be replayed into a fresh instance
+    /// using `PocketIcBuilder::with_replay`.
+    pub fn with_recording(mut self, recording_path: PathBuf) -> Self {
+        self.recording_path = Some(recording_path);
+        self
+    }
+
+    /// Replay a recording (see `PocketIcBuilder::with_recording`) into the new instance:
+    /// the new instance is created with the configuration of the recorded instance
+    /// (overriding the subnet configuration, state directory, nonmainnet features, log level,
+    /// and bitcoind addresses of this builder) and then the recorded requests are executed
+    /// in the order in which they were recorded.
+    /// If `num_requests` is provided, then only the first `num_requests` recorded requests
+    /// are replayed, e.g., to bisect the request at which two executions diverge.
+    pub fn with_replay(mut self, recording_path: PathBuf, num_requests: Option<usize>) -> Self {
+        self.replay = Some((recording_path, num_requests));
+        self
+    }
+
     /// Add an empty NNS subnet
     pub fn with_nns_subnet(mut self) -> Self {
         let mut config = self.config.unwrap_or_default();
@@ -416,6 +498,7 @@ impl PocketIc {
         nonmainnet_features: bool,
         log_level: Option<Level>,
         bitcoind_addr: Option<Vec<SocketAddr>>,
+        recording_path: Option<PathBuf>,
     ) -> Self {
         let (tx, rx) = channel();
         let thread = thread::spawn(move || {
@@ -437,6 +520,7 @@ impl PocketIc {
                 nonmainnet_features,
                 log_level,
                 bitcoind_addr,
+                recording_path,
             )
             .await
         });
@@ -448,6 +532,11 @@ impl PocketIc {
         }
     }
 
+    pub(crate) fn replay(&self, entries: Vec<RecordedEntry>) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.replay(entries).await })
+    }
+
     /// Returns the URL of the PocketIC server on which this PocketIC instance is running.
     pub fn get_server_url(&self) -> Url {
         self.pocket_ic.get_server_url()
@@ -495,6 +584,41 @@ impl PocketIc {
         runtime.block_on(async { self.pocket_ic.get_stable_memory(canister_id).await })
     }
 
+    /// Download a canister snapshot to a file on the filesystem of the PocketIC server.
+    /// The file can be uploaded as a snapshot of a (possibly different) canister
+    /// on a (possibly different) PocketIC instance using `upload_canister_snapshot`.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string(), snapshot_path = %snapshot_path.display()))]
+    pub fn download_canister_snapshot(
+        &self,
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+        snapshot_path: PathBuf,
+    ) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .download_canister_snapshot(canister_id, snapshot_id, snapshot_path)
+                .await
+        })
+    }
+
+    /// Upload a canister snapshot from a file on the filesystem of the PocketIC server
+    /// (obtained by `download_canister_snapshot`) as a new snapshot of the given canister.
+    /// Returns the ID of the new snapshot which can be loaded using `load_canister_snapshot`.
+    #[instrument(skip(self), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string(), snapshot_path = %snapshot_path.display()))]
+    pub fn upload_canister_snapshot(
+        &self,
+        canister_id: CanisterId,
+        snapshot_path: PathBuf,
+    ) -> Vec<u8> {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .upload_canister_snapshot(canister_id, snapshot_path)
+                .await
+        })
+    }
+
     /// List all instances and their status.
     #[instrument(ret)]
     pub fn list_instances() -> Vec<String> {
@@ -774,6 +898,43 @@ impl PocketIc {
         })
     }
 
+    /// Execute an update call on a canister and return the reply together with a report
+    /// of the execution costs (instructions, cycles, and memory growth) incurred
+    /// by the called canister and by all canisters executing downstream calls.
+    #[instrument(skip(self, payload), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string(), sender = %sender.to_string(), method = %method, payload_len = %payload.len()))]
+    pub fn update_call_with_report(
+        &self,
+        canister_id: CanisterId,
+        sender: Principal,
+        method: &str,
+        payload: Vec<u8>,
+    ) -> ExecutionReport {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .update_call_with_report(canister_id, sender, method, payload)
+                .await
+        })
+    }
+
+    /// Execute a query call on a canister and return the reply together with a report
+    /// of the instructions executed by the query (including composite query callees).
+    #[instrument(skip(self, payload), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string(), sender = %sender.to_string(), method = %method, payload_len = %payload.len()))]
+    pub fn query_call_with_report(
+        &self,
+        canister_id: CanisterId,
+        sender: Principal,
+        method: &str,
+        payload: Vec<u8>,
+    ) -> ExecutionReport {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .query_call_with_report(canister_id, sender, method, payload)
+                .await
+        })
+    }
+
     /// Execute a query call on a canister.
     #[instrument(skip(self, payload), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string(), sender = %sender.to_string(), method = %method, payload_len = %payload.len()))]
     pub fn query_call(
@@ -1232,6 +1393,127 @@ impl PocketIc {
                 .await
         })
     }
+
+    /// Intercept calls made by canisters to the given canister (and method, if provided).
+    /// Intercepted calls are not delivered to the callee, but can be retrieved
+    /// using `PocketIc::get_intercepted_calls` and must be responded to by the test driver
+    /// using `PocketIc::mock_intercepted_call_response`.
+    /// This way, a canister can be tested without deploying the canisters it calls
+    /// (e.g., the ICP ledger or the cycles minting canister).
+    /// Note that calls to canisters deployed on the same subnet as the caller
+    /// and calls to the management canister are not intercepted.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string()))]
+    pub fn add_call_interceptor(&self, canister_id: CanisterId, method: Option<String>) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .add_call_interceptor(canister_id, method)
+                .await
+        })
+    }
+
+    /// Stop intercepting calls made by canisters to the given canister (and method, if provided).
+    /// Calls that have already been intercepted remain pending.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string()))]
+    pub fn remove_call_interceptor(&self, canister_id: CanisterId, method: Option<String>) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .remove_call_interceptor(canister_id, method)
+                .await
+        })
+    }
+
+    /// Get the pending intercepted calls.
+    /// A call is intercepted in the round in which the calling canister executes
+    /// the message making the call.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id))]
+    pub fn get_intercepted_calls(&self) -> Vec<InterceptedCall> {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.get_intercepted_calls().await })
+    }
+
+    /// Mock a response to a pending intercepted call.
+    /// The response is executed by the calling canister in a subsequent round.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id))]
+    pub fn mock_intercepted_call_response(
+        &self,
+        mock_intercepted_call_response: MockInterceptedCallResponse,
+    ) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .mock_intercepted_call_response(mock_intercepted_call_response)
+                .await
+        })
+    }
+
+    /// Pause the delivery of XNet messages (requests and responses)
+    /// from the subnet `from_subnet` to the subnet `to_subnet`.
+    /// The messages remain in the outgoing stream of `from_subnet`
+    /// and are delivered after the delivery is resumed using `PocketIc::resume_xnet`.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id, from_subnet = %from_subnet.to_string(), to_subnet = %to_subnet.to_string()))]
+    pub fn pause_xnet(&self, from_subnet: SubnetId, to_subnet: SubnetId) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.pause_xnet(from_subnet, to_subnet).await })
+    }
+
+    /// Resume the delivery of XNet messages from the subnet `from_subnet`
+    /// to the subnet `to_subnet` paused using `PocketIc::pause_xnet`.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id, from_subnet = %from_subnet.to_string(), to_subnet = %to_subnet.to_string()))]
+    pub fn resume_xnet(&self, from_subnet: SubnetId, to_subnet: SubnetId) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.resume_xnet(from_subnet, to_subnet).await })
+    }
+
+    /// Drop or delay responses sent from canisters on the subnet `from_subnet`
+    /// to canisters on the (different) subnet `to_subnet`.
+    /// A dropped response is replaced by a reject response with reject code `SYS_TRANSIENT`
+    /// (or `SYS_UNKNOWN` for best-effort calls).
+    /// Passing `None` as `fault` stops affecting subsequent responses.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id, from_subnet = %from_subnet.to_string(), to_subnet = %to_subnet.to_string()))]
+    pub fn set_xnet_response_fault(
+        &self,
+        from_subnet: SubnetId,
+        to_subnet: SubnetId,
+        fault: Option<XNetResponseFault>,
+    ) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .set_xnet_response_fault(from_subnet, to_subnet, fault)
+                .await
+        })
+    }
+
+    /// Stall the given subnet: the subnet executes no rounds
+    /// (and thus makes no progress) until it is resumed using `PocketIc::resume_subnet`.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id, subnet_id = %subnet_id.to_string()))]
+    pub fn stall_subnet(&self, subnet_id: SubnetId) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.stall_subnet(subnet_id).await })
+    }
+
+    /// Resume a subnet stalled using `PocketIc::stall_subnet`.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id, subnet_id = %subnet_id.to_string()))]
+    pub fn resume_subnet(&self, subnet_id: SubnetId) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async { self.pocket_ic.resume_subnet(subnet_id).await })
+    }
+
+    /// Treat the input queues of the given canister as full (`full == true`) or not (`full == false`).
+    /// While the queues are treated as full, calls made by canisters on other subnets
+    /// to the given canister are rejected with reject code `SYS_TRANSIENT`.
+    /// Note that calls made by canisters deployed on the same subnet are not affected.
+    #[instrument(ret, skip(self), fields(instance_id=self.pocket_ic.instance_id, canister_id = %canister_id.to_string()))]
+    pub fn set_canister_queues_full(&self, canister_id: CanisterId, full: bool) {
+        let runtime = self.runtime.clone();
+        runtime.block_on(async {
+            self.pocket_ic
+                .set_canister_queues_full(canister_id, full)
+                .await
+        })
+    }
 }
 
 impl Default for PocketIc {
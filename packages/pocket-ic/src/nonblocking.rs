@@ -1,11 +1,16 @@
 use crate::common::rest::{
     ApiResponse, AutoProgressConfig, BlobCompression, BlobId, CanisterHttpRequest,
-    CreateHttpGatewayResponse, CreateInstanceResponse, ExtendedSubnetConfigSet, HttpGatewayBackend,
-    HttpGatewayConfig, HttpGatewayInfo, HttpsConfig, InstanceConfig, InstanceId,
-    MockCanisterHttpResponse, RawAddCycles, RawCanisterCall, RawCanisterHttpRequest, RawCanisterId,
-    RawCanisterResult, RawCycles, RawEffectivePrincipal, RawIngressStatusArgs, RawMessageId,
-    RawMockCanisterHttpResponse, RawPrincipalId, RawSetStableMemory, RawStableMemory, RawSubnetId,
-    RawTime, RawVerifyCanisterSigArg, SubnetId, TickConfigs, Topology,
+    CreateHttpGatewayResponse, CreateInstanceResponse, ExecutionReport, ExtendedSubnetConfigSet,
+    HttpGatewayBackend, HttpGatewayConfig, HttpGatewayInfo, HttpsConfig, InstanceConfig,
+    InstanceId, InterceptedCall, MockCanisterHttpResponse, MockInterceptedCallResponse,
+    RawAddCycles, RawCallInterceptor, RawCanisterCall, RawCanisterHttpRequest, RawCanisterId,
+    RawCanisterResult, RawCycles, RawDownloadCanisterSnapshot, RawEffectivePrincipal,
+    RawExecutionReport, RawIngressStatusArgs, RawInterceptedCall, RawMessageId,
+    RawMockCanisterHttpResponse, RawMockInterceptedCallResponse, RawPrincipalId,
+    RawSetCanisterQueuesFull, RawSetStableMemory, RawSetSubnetStalled, RawSetXNetPaused,
+    RawSetXNetResponseFault, RawStableMemory, RawSubnetId, RawTime, RawUploadCanisterSnapshot,
+    RawVerifyCanisterSigArg, RecordedBlob, RecordedEntry, RecordedRequest, SubnetId, TickConfigs,
+    Topology, XNetResponseFault,
 };
 pub use crate::DefaultEffectiveCanisterIdError;
 use crate::{start_or_reuse_server, IngressStatusResult, PocketIcBuilder, RejectResponse};
@@ -131,6 +136,7 @@ impl PocketIc {
         nonmainnet_features: bool,
         log_level: Option<Level>,
         bitcoind_addr: Option<Vec<SocketAddr>>,
+        recording_path: Option<PathBuf>,
     ) -> Self {
         let server_url = if let Some(server_url) = server_url {
             server_url
@@ -150,6 +156,7 @@ impl PocketIc {
             nonmainnet_features,
             log_level: log_level.map(|l| l.to_string()),
             bitcoind_addr,
+            recording_path,
         };
 
         let test_driver_pid = std::process::id();
@@ -182,6 +189,56 @@ impl PocketIc {
         }
     }
 
+    /// Replays recorded entries (see `PocketIcBuilder::with_replay`) on this instance.
+    pub(crate) async fn replay(&self, entries: Vec<RecordedEntry>) {
+        for entry in entries {
+            match entry {
+                RecordedEntry::Instance(_) => {
+                    panic!("Unexpected instance configuration in the middle of a recording")
+                }
+                RecordedEntry::Blob(RecordedBlob { data, gzip }) => {
+                    let compression = if gzip {
+                        BlobCompression::Gzip
+                    } else {
+                        BlobCompression::NoCompression
+                    };
+                    self.upload_blob(data, compression).await;
+                }
+                RecordedEntry::Request(RecordedRequest {
+                    endpoint,
+                    content_type,
+                    body,
+                }) => {
+                    let is_json = content_type
+                        .as_ref()
+                        .is_some_and(|content_type| content_type.starts_with("application/json"));
+                    if is_json {
+                        let body: serde_json::Value = serde_json::from_slice(&body)
+                            .expect("Failed to parse recorded request body");
+                        // Requests failing in the recorded execution are expected to fail here, too.
+                        if let Err((status, message)) =
+                            self.try_post::<serde_json::Value, _>(&endpoint, body).await
+                        {
+                            debug!(
+                                "instance_id={} Replayed request to {} failed with status {}: {}",
+                                self.instance_id, endpoint, status, message
+                            );
+                        }
+                    } else {
+                        let mut request = self
+                            .reqwest_client
+                            .post(self.instance_url().join(&endpoint).unwrap())
+                            .body(body);
+                        if let Some(content_type) = content_type {
+                            request = request.header(reqwest::header::CONTENT_TYPE, content_type);
+                        }
+                        request.send().await.expect("HTTP failure");
+                    }
+                }
+            }
+        }
+    }
+
     /// Returns the URL of the PocketIC server on which this PocketIC instance is running.
     pub fn get_server_url(&self) -> Url {
         self.server_url.clone()
@@ -251,6 +308,48 @@ impl PocketIc {
         blob
     }
 
+    /// Download a canister snapshot to a file on the filesystem of the PocketIC server.
+    /// The file can be uploaded as a snapshot of a (possibly different) canister
+    /// on a (possibly different) PocketIC instance using `upload_canister_snapshot`.
+    #[instrument(skip(self), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string(), snapshot_path = %snapshot_path.display()))]
+    pub async fn download_canister_snapshot(
+        &self,
+        canister_id: CanisterId,
+        snapshot_id: Vec<u8>,
+        snapshot_path: PathBuf,
+    ) {
+        let endpoint = "read/download_canister_snapshot";
+        self.post::<(), _>(
+            endpoint,
+            RawDownloadCanisterSnapshot {
+                canister_id: canister_id.into(),
+                snapshot_id,
+                snapshot_path,
+            },
+        )
+        .await
+    }
+
+    /// Upload a canister snapshot from a file on the filesystem of the PocketIC server
+    /// (obtained by `download_canister_snapshot`) as a new snapshot of the given canister.
+    /// Returns the ID of the new snapshot which can be loaded using `load_canister_snapshot`.
+    #[instrument(skip(self), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string(), snapshot_path = %snapshot_path.display()))]
+    pub async fn upload_canister_snapshot(
+        &self,
+        canister_id: CanisterId,
+        snapshot_path: PathBuf,
+    ) -> Vec<u8> {
+        let endpoint = "update/upload_canister_snapshot";
+        self.post(
+            endpoint,
+            RawUploadCanisterSnapshot {
+                canister_id: canister_id.into(),
+                snapshot_path,
+            },
+        )
+        .await
+    }
+
     /// List all instances and their status.
     #[instrument(ret)]
     pub async fn list_instances() -> Vec<String> {
@@ -726,6 +825,37 @@ impl PocketIc {
         .await
     }
 
+    /// Execute an update call on a canister and return the reply together with a report
+    /// of the execution costs (instructions, cycles, and memory growth) incurred
+    /// by the called canister and by all canisters executing downstream calls.
+    #[instrument(skip(self, payload), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string(), sender = %sender.to_string(), method = %method, payload_len = %payload.len()))]
+    pub async fn update_call_with_report(
+        &self,
+        canister_id: CanisterId,
+        sender: Principal,
+        method: &str,
+        payload: Vec<u8>,
+    ) -> ExecutionReport {
+        let endpoint = "update/execute_ingress_message_with_report";
+        self.canister_call_with_report(endpoint, canister_id, sender, method, payload)
+            .await
+    }
+
+    /// Execute a query call on a canister and return the reply together with a report
+    /// of the instructions executed by the query (including composite query callees).
+    #[instrument(skip(self, payload), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string(), sender = %sender.to_string(), method = %method, payload_len = %payload.len()))]
+    pub async fn query_call_with_report(
+        &self,
+        canister_id: CanisterId,
+        sender: Principal,
+        method: &str,
+        payload: Vec<u8>,
+    ) -> ExecutionReport {
+        let endpoint = "read/query_with_report";
+        self.canister_call_with_report(endpoint, canister_id, sender, method, payload)
+            .await
+    }
+
     /// Execute a query call on a canister explicitly specifying an effective principal to route the request:
     /// this API is useful for making generic query calls (including management canister query calls) without using dedicated functions from this library
     /// (e.g., making generic query calls in dfx to a PocketIC instance).
@@ -1544,6 +1674,26 @@ impl PocketIc {
         result.into()
     }
 
+    async fn canister_call_with_report(
+        &self,
+        endpoint: &str,
+        canister_id: CanisterId,
+        sender: Principal,
+        method: &str,
+        payload: Vec<u8>,
+    ) -> ExecutionReport {
+        let raw_canister_call = RawCanisterCall {
+            sender: sender.as_slice().to_vec(),
+            canister_id: canister_id.as_slice().to_vec(),
+            method: method.to_string(),
+            payload,
+            effective_principal: RawEffectivePrincipal::CanisterId(canister_id.as_slice().to_vec()),
+        };
+
+        let report: RawExecutionReport = self.post(endpoint, raw_canister_call).await;
+        report.into()
+    }
+
     pub async fn update_call_with_effective_principal(
         &self,
         canister_id: CanisterId,
@@ -1593,6 +1743,143 @@ impl PocketIc {
             mock_canister_http_response.into();
         self.post(endpoint, raw_mock_canister_http_response).await
     }
+
+    /// Intercept calls made by canisters to the given canister (and method, if provided).
+    /// Intercepted calls are not delivered to the callee, but can be retrieved
+    /// using `PocketIc::get_intercepted_calls` and must be responded to by the test driver
+    /// using `PocketIc::mock_intercepted_call_response`.
+    /// This way, a canister can be tested without deploying the canisters it calls
+    /// (e.g., the ICP ledger or the cycles minting canister).
+    /// Note that calls to canisters deployed on the same subnet as the caller
+    /// and calls to the management canister are not intercepted.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string()))]
+    pub async fn add_call_interceptor(&self, canister_id: CanisterId, method: Option<String>) {
+        let endpoint = "update/add_call_interceptor";
+        let raw_call_interceptor = RawCallInterceptor {
+            canister_id: canister_id.into(),
+            method,
+        };
+        self.post(endpoint, raw_call_interceptor).await
+    }
+
+    /// Stop intercepting calls made by canisters to the given canister (and method, if provided).
+    /// Calls that have already been intercepted remain pending.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string()))]
+    pub async fn remove_call_interceptor(&self, canister_id: CanisterId, method: Option<String>) {
+        let endpoint = "update/remove_call_interceptor";
+        let raw_call_interceptor = RawCallInterceptor {
+            canister_id: canister_id.into(),
+            method,
+        };
+        self.post(endpoint, raw_call_interceptor).await
+    }
+
+    /// Get the pending intercepted calls.
+    /// A call is intercepted in the round in which the calling canister executes
+    /// the message making the call.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id))]
+    pub async fn get_intercepted_calls(&self) -> Vec<InterceptedCall> {
+        let endpoint = "read/get_intercepted_calls";
+        let res: Vec<RawInterceptedCall> = self.get(endpoint).await;
+        res.into_iter().map(|r| r.into()).collect()
+    }
+
+    /// Mock a response to a pending intercepted call.
+    /// The response is executed by the calling canister in a subsequent round.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id))]
+    pub async fn mock_intercepted_call_response(
+        &self,
+        mock_intercepted_call_response: MockInterceptedCallResponse,
+    ) {
+        let endpoint = "update/mock_intercepted_call";
+        let raw_mock_intercepted_call_response: RawMockInterceptedCallResponse =
+            mock_intercepted_call_response.into();
+        self.post(endpoint, raw_mock_intercepted_call_response)
+            .await
+    }
+
+    /// Pause the delivery of XNet messages (requests and responses)
+    /// from the subnet `from_subnet` to the subnet `to_subnet`.
+    /// The messages remain in the outgoing stream of `from_subnet`
+    /// and are delivered after the delivery is resumed using `PocketIc::resume_xnet`.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id, from_subnet = %from_subnet.to_string(), to_subnet = %to_subnet.to_string()))]
+    pub async fn pause_xnet(&self, from_subnet: SubnetId, to_subnet: SubnetId) {
+        self.set_xnet_paused(from_subnet, to_subnet, true).await
+    }
+
+    /// Resume the delivery of XNet messages from the subnet `from_subnet`
+    /// to the subnet `to_subnet` paused using `PocketIc::pause_xnet`.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id, from_subnet = %from_subnet.to_string(), to_subnet = %to_subnet.to_string()))]
+    pub async fn resume_xnet(&self, from_subnet: SubnetId, to_subnet: SubnetId) {
+        self.set_xnet_paused(from_subnet, to_subnet, false).await
+    }
+
+    async fn set_xnet_paused(&self, from_subnet: SubnetId, to_subnet: SubnetId, paused: bool) {
+        let endpoint = "update/set_xnet_paused";
+        let raw_set_xnet_paused = RawSetXNetPaused {
+            from_subnet: from_subnet.into(),
+            to_subnet: to_subnet.into(),
+            paused,
+        };
+        self.post(endpoint, raw_set_xnet_paused).await
+    }
+
+    /// Drop or delay responses sent from canisters on the subnet `from_subnet`
+    /// to canisters on the (different) subnet `to_subnet`.
+    /// A dropped response is replaced by a reject response with reject code `SYS_TRANSIENT`
+    /// (or `SYS_UNKNOWN` for best-effort calls).
+    /// Passing `None` as `fault` stops affecting subsequent responses.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id, from_subnet = %from_subnet.to_string(), to_subnet = %to_subnet.to_string()))]
+    pub async fn set_xnet_response_fault(
+        &self,
+        from_subnet: SubnetId,
+        to_subnet: SubnetId,
+        fault: Option<XNetResponseFault>,
+    ) {
+        let endpoint = "update/set_xnet_response_fault";
+        let raw_set_xnet_response_fault = RawSetXNetResponseFault {
+            from_subnet: from_subnet.into(),
+            to_subnet: to_subnet.into(),
+            fault,
+        };
+        self.post(endpoint, raw_set_xnet_response_fault).await
+    }
+
+    /// Stall the given subnet: the subnet executes no rounds
+    /// (and thus makes no progress) until it is resumed using `PocketIc::resume_subnet`.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id, subnet_id = %subnet_id.to_string()))]
+    pub async fn stall_subnet(&self, subnet_id: SubnetId) {
+        self.set_subnet_stalled(subnet_id, true).await
+    }
+
+    /// Resume a subnet stalled using `PocketIc::stall_subnet`.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id, subnet_id = %subnet_id.to_string()))]
+    pub async fn resume_subnet(&self, subnet_id: SubnetId) {
+        self.set_subnet_stalled(subnet_id, false).await
+    }
+
+    async fn set_subnet_stalled(&self, subnet_id: SubnetId, stalled: bool) {
+        let endpoint = "update/set_subnet_stalled";
+        let raw_set_subnet_stalled = RawSetSubnetStalled {
+            subnet_id: subnet_id.into(),
+            stalled,
+        };
+        self.post(endpoint, raw_set_subnet_stalled).await
+    }
+
+    /// Treat the input queues of the given canister as full (`full == true`) or not (`full == false`).
+    /// While the queues are treated as full, calls made by canisters on other subnets
+    /// to the given canister are rejected with reject code `SYS_TRANSIENT`.
+    /// Note that calls made by canisters deployed on the same subnet are not affected.
+    #[instrument(ret, skip(self), fields(instance_id=self.instance_id, canister_id = %canister_id.to_string()))]
+    pub async fn set_canister_queues_full(&self, canister_id: CanisterId, full: bool) {
+        let endpoint = "update/set_canister_queues_full";
+        let raw_set_canister_queues_full = RawSetCanisterQueuesFull {
+            canister_id: canister_id.into(),
+            full,
+        };
+        self.post(endpoint, raw_set_canister_queues_full).await
+    }
 }
 
 /// Call a canister candid method, authenticated. The sender can be impersonated (i.e., the
@@ -12,8 +12,10 @@ use ic_transport_types::EnvelopeContent::{Call, ReadState};
 use pocket_ic::common::rest::{BlockmakerConfigs, RawSubnetBlockmaker, TickConfigs};
 use pocket_ic::{
     common::rest::{
-        BlobCompression, CanisterHttpReply, CanisterHttpResponse, MockCanisterHttpResponse,
-        RawEffectivePrincipal, RawMessageId, SubnetKind,
+        BlobCompression, CanisterHttpReply, CanisterHttpResponse, ExecutedMessageKind,
+        InterceptedCallReject, InterceptedCallResponse, MockCanisterHttpResponse,
+        MockInterceptedCallResponse, RawEffectivePrincipal, RawMessageId, SubnetKind,
+        XNetResponseFault,
     },
     query_candid, update_candid, DefaultEffectiveCanisterIdError, ErrorCode, IngressStatusResult,
     PocketIc, PocketIcBuilder, RejectCode,
@@ -886,6 +888,196 @@ fn test_xnet_call_and_create_canister_with_specified_id() {
     }
 }
 
+#[test]
+fn test_intercepted_calls() {
+    let pic = PocketIc::new();
+
+    let canister_id = pic.create_canister();
+    pic.add_cycles(canister_id, INIT_CYCLES);
+    pic.install_canister(canister_id, test_canister_wasm(), vec![], None);
+
+    // The callee is not deployed on the PocketIC instance.
+    let callee = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+    pic.add_call_interceptor(callee, Some("whoami".to_string()));
+
+    // The test canister calls the callee and the call is intercepted.
+    let call_id = pic
+        .submit_call(
+            canister_id,
+            Principal::anonymous(),
+            "whois",
+            Encode!(&callee).unwrap(),
+        )
+        .unwrap();
+    pic.tick();
+    let intercepted_calls = pic.get_intercepted_calls();
+    assert_eq!(intercepted_calls.len(), 1);
+    let intercepted_call = &intercepted_calls[0];
+    assert_eq!(intercepted_call.sender, canister_id);
+    assert_eq!(intercepted_call.receiver, callee);
+    assert_eq!(intercepted_call.method, "whoami");
+
+    // The test driver replies on behalf of the callee.
+    let mock_intercepted_call_response = MockInterceptedCallResponse {
+        subnet_id: intercepted_call.subnet_id,
+        call_id: intercepted_call.call_id,
+        response: InterceptedCallResponse::InterceptedCallReply(
+            Encode!(&"mocked callee".to_string()).unwrap(),
+        ),
+    };
+    pic.mock_intercepted_call_response(mock_intercepted_call_response);
+
+    // There should be no more pending intercepted calls.
+    assert!(pic.get_intercepted_calls().is_empty());
+
+    let reply = pic.await_call(call_id).unwrap();
+    assert_eq!(Decode!(&reply, String).unwrap(), "mocked callee");
+
+    // The test driver rejects the next intercepted call
+    // and then the test canister traps.
+    let call_id = pic
+        .submit_call(
+            canister_id,
+            Principal::anonymous(),
+            "whois",
+            Encode!(&callee).unwrap(),
+        )
+        .unwrap();
+    pic.tick();
+    let intercepted_calls = pic.get_intercepted_calls();
+    assert_eq!(intercepted_calls.len(), 1);
+    let mock_intercepted_call_response = MockInterceptedCallResponse {
+        subnet_id: intercepted_calls[0].subnet_id,
+        call_id: intercepted_calls[0].call_id,
+        response: InterceptedCallResponse::InterceptedCallReject(InterceptedCallReject {
+            reject_code: RejectCode::CanisterReject as u64,
+            message: "rejected by test driver".to_string(),
+        }),
+    };
+    pic.mock_intercepted_call_response(mock_intercepted_call_response);
+    let err = pic.await_call(call_id).unwrap_err();
+    assert_eq!(err.reject_code, RejectCode::CanisterError);
+    assert!(err.reject_message.contains("rejected by test driver"));
+
+    // Calls are not intercepted anymore after removing the call interceptor.
+    pic.remove_call_interceptor(callee, Some("whoami".to_string()));
+    let err = pic
+        .update_call(
+            canister_id,
+            Principal::anonymous(),
+            "whois",
+            Encode!(&callee).unwrap(),
+        )
+        .unwrap_err();
+    assert_eq!(err.reject_code, RejectCode::CanisterError);
+    assert!(pic.get_intercepted_calls().is_empty());
+}
+
+#[test]
+fn test_xnet_fault_injection() {
+    let pic = PocketIcBuilder::new()
+        .with_application_subnet()
+        .with_application_subnet()
+        .build();
+    let subnet_id_1 = pic.topology().get_app_subnets()[0];
+    let subnet_id_2 = pic.topology().get_app_subnets()[1];
+
+    // The test canister on the first subnet calls the test canister on the second subnet.
+    let caller = pic.create_canister_on_subnet(None, None, subnet_id_1);
+    pic.add_cycles(caller, INIT_CYCLES);
+    pic.install_canister(caller, test_canister_wasm(), vec![], None);
+    let callee = pic.create_canister_on_subnet(None, None, subnet_id_2);
+    pic.add_cycles(callee, INIT_CYCLES);
+    pic.install_canister(callee, test_canister_wasm(), vec![], None);
+    let submit_whois = || {
+        pic.submit_call(
+            caller,
+            Principal::anonymous(),
+            "whois",
+            Encode!(&callee).unwrap(),
+        )
+        .unwrap()
+    };
+
+    // The call does not complete while XNet messages are not delivered to the second subnet.
+    pic.pause_xnet(subnet_id_1, subnet_id_2);
+    let call_id = submit_whois();
+    for _ in 0..10 {
+        pic.tick();
+    }
+    assert!(pic.ingress_status(call_id.clone()).is_none());
+    pic.resume_xnet(subnet_id_1, subnet_id_2);
+    let reply = pic.await_call(call_id).unwrap();
+    assert_eq!(Decode!(&reply, String).unwrap(), callee.to_string());
+
+    // The call does not complete while the second subnet is stalled.
+    pic.stall_subnet(subnet_id_2);
+    let call_id = submit_whois();
+    for _ in 0..10 {
+        pic.tick();
+    }
+    assert!(pic.ingress_status(call_id.clone()).is_none());
+    pic.resume_subnet(subnet_id_2);
+    let reply = pic.await_call(call_id).unwrap();
+    assert_eq!(Decode!(&reply, String).unwrap(), callee.to_string());
+
+    // A delayed response is delivered after the delay.
+    pic.set_xnet_response_fault(
+        subnet_id_2,
+        subnet_id_1,
+        Some(XNetResponseFault::Delay { rounds: 20 }),
+    );
+    let call_id = submit_whois();
+    for _ in 0..10 {
+        pic.tick();
+    }
+    assert!(pic.ingress_status(call_id.clone()).is_none());
+    let reply = pic.await_call(call_id).unwrap();
+    assert_eq!(Decode!(&reply, String).unwrap(), callee.to_string());
+
+    // A dropped response is rejected with reject code `SYS_TRANSIENT`
+    // and then the test canister traps.
+    pic.set_xnet_response_fault(subnet_id_2, subnet_id_1, Some(XNetResponseFault::Drop));
+    let err = pic
+        .update_call(
+            caller,
+            Principal::anonymous(),
+            "whois",
+            Encode!(&callee).unwrap(),
+        )
+        .unwrap_err();
+    assert_eq!(err.reject_code, RejectCode::CanisterError);
+    assert!(err.reject_message.contains("SysTransient"));
+    assert!(err.reject_message.contains("dropped"));
+    pic.set_xnet_response_fault(subnet_id_2, subnet_id_1, None);
+
+    // A call to a canister whose queues are full is rejected with reject code `SYS_TRANSIENT`.
+    pic.set_canister_queues_full(callee, true);
+    let err = pic
+        .update_call(
+            caller,
+            Principal::anonymous(),
+            "whois",
+            Encode!(&callee).unwrap(),
+        )
+        .unwrap_err();
+    assert_eq!(err.reject_code, RejectCode::CanisterError);
+    assert!(err.reject_message.contains("SysTransient"));
+    assert!(err.reject_message.contains("input queue full"));
+
+    // Calls succeed once all faults are cleared.
+    pic.set_canister_queues_full(callee, false);
+    let reply = pic
+        .update_call(
+            caller,
+            Principal::anonymous(),
+            "whois",
+            Encode!(&callee).unwrap(),
+        )
+        .unwrap();
+    assert_eq!(Decode!(&reply, String).unwrap(), callee.to_string());
+}
+
 #[test]
 fn test_query_call_on_new_pocket_ic() {
     let pic = PocketIc::new();
@@ -1864,6 +2056,89 @@ fn test_canister_snapshots() {
     assert_eq!(snapshots[0].id, third_snapshot.id);
 }
 
+#[test]
+fn test_download_and_upload_canister_snapshot() {
+    let pic = PocketIc::new();
+    let canister_id = deploy_counter_canister(&pic);
+
+    // We bump the counter and take a snapshot.
+    let reply = call_counter_canister(&pic, canister_id, "write");
+    assert_eq!(reply, 1_u32.to_le_bytes().to_vec());
+    pic.stop_canister(canister_id, None).unwrap();
+    let snapshot = pic.take_canister_snapshot(canister_id, None, None).unwrap();
+    pic.start_canister(canister_id, None).unwrap();
+
+    // We download the snapshot to a file.
+    let snapshot_path = std::env::temp_dir().join(format!(
+        "pocket_ic_canister_snapshot_{}_{}",
+        std::process::id(),
+        canister_id
+    ));
+    pic.download_canister_snapshot(canister_id, snapshot.id, snapshot_path.clone());
+
+    // We upload the snapshot into a fresh canister on a different PocketIC instance.
+    let other_pic = PocketIc::new();
+    let other_canister_id = deploy_counter_canister(&other_pic);
+    let reply = call_counter_canister(&other_pic, other_canister_id, "read");
+    assert_eq!(reply, 0_u32.to_le_bytes().to_vec());
+    let uploaded_snapshot_id =
+        other_pic.upload_canister_snapshot(other_canister_id, snapshot_path.clone());
+    std::fs::remove_file(snapshot_path).unwrap();
+
+    // The uploaded snapshot is listed and can be loaded.
+    let snapshots = other_pic
+        .list_canister_snapshots(other_canister_id, None)
+        .unwrap();
+    assert_eq!(snapshots.len(), 1);
+    assert_eq!(snapshots[0].id, uploaded_snapshot_id);
+    assert_eq!(snapshots[0].taken_at_timestamp, snapshot.taken_at_timestamp);
+    other_pic.stop_canister(other_canister_id, None).unwrap();
+    other_pic
+        .load_canister_snapshot(other_canister_id, None, uploaded_snapshot_id)
+        .unwrap();
+    other_pic.start_canister(other_canister_id, None).unwrap();
+
+    // The counter has been restored from the snapshot.
+    let reply = call_counter_canister(&other_pic, other_canister_id, "read");
+    assert_eq!(reply, 1_u32.to_le_bytes().to_vec());
+}
+
+#[test]
+fn test_record_and_replay() {
+    let recording_path =
+        std::env::temp_dir().join(format!("pocket_ic_recording_{}", std::process::id()));
+
+    // We record a session deploying the counter canister and bumping the counter twice.
+    let pic = PocketIcBuilder::new()
+        .with_application_subnet()
+        .with_recording(recording_path.clone())
+        .build();
+    let canister_id = deploy_counter_canister(&pic);
+    call_counter_canister(&pic, canister_id, "write");
+    pic.advance_time(std::time::Duration::from_secs(60));
+    pic.tick();
+    let reply = call_counter_canister(&pic, canister_id, "write");
+    assert_eq!(reply, 2_u32.to_le_bytes().to_vec());
+    let time = pic.get_time();
+    drop(pic);
+
+    // Replaying the recording into a fresh instance yields the same state.
+    let replayed_pic = PocketIcBuilder::new()
+        .with_replay(recording_path.clone(), None)
+        .build();
+    assert_eq!(replayed_pic.get_time(), time);
+    let reply = call_counter_canister(&replayed_pic, canister_id, "read");
+    assert_eq!(reply, 2_u32.to_le_bytes().to_vec());
+
+    // Replaying no requests yields a fresh instance without the counter canister.
+    let replayed_pic = PocketIcBuilder::new()
+        .with_replay(recording_path.clone(), Some(0))
+        .build();
+    assert!(!replayed_pic.canister_exists(canister_id));
+
+    std::fs::remove_file(recording_path).unwrap();
+}
+
 #[test]
 fn test_wasm_chunk_store() {
     let pic = PocketIc::new();
@@ -2503,3 +2778,88 @@ fn test_http_methods() {
         }
     }
 }
+
+#[test]
+fn test_execution_report() {
+    let pic = PocketIc::new();
+
+    let caller = pic.create_canister();
+    let callee = pic.create_canister();
+    for canister_id in [caller, callee] {
+        pic.add_cycles(canister_id, INIT_CYCLES);
+        pic.install_canister(canister_id, test_canister_wasm(), vec![], None);
+    }
+
+    // The caller makes a downstream call to the callee.
+    let report = pic.update_call_with_report(
+        caller,
+        Principal::anonymous(),
+        "whois",
+        Encode!(&callee).unwrap(),
+    );
+    let reply = report.result.clone().unwrap();
+    assert_eq!(Decode!(&reply, String).unwrap(), callee.to_string());
+
+    // The report for the called canister comes first.
+    assert_eq!(report.canisters.len(), 2);
+    let caller_report = &report.canisters[0];
+    assert_eq!(caller_report.canister_id, caller);
+    assert_eq!(caller_report.calls_made, 1);
+    assert!(caller_report.instructions_executed > 0);
+    assert!(caller_report.cycles_consumed_by_use_case["Instructions"] > 0);
+    assert!(caller_report.cycles_balance_change < 0);
+    let callee_report = report.canister(callee).unwrap();
+    assert_eq!(callee_report.calls_made, 0);
+    assert!(callee_report.instructions_executed > 0);
+    assert_eq!(
+        report.total_instructions_executed(),
+        caller_report.instructions_executed + callee_report.instructions_executed
+    );
+
+    // The call tree breaks the instructions down per message.
+    let call = &report.call;
+    assert_eq!(call.canister_id, caller);
+    assert_eq!(call.method, "whois");
+    let kinds: Vec<_> = call.messages.iter().map(|message| message.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![ExecutedMessageKind::Call, ExecutedMessageKind::Response]
+    );
+    assert_eq!(call.messages[0].calls_made, 1);
+    assert_eq!(
+        call.instructions_executed(),
+        caller_report.instructions_executed
+    );
+    assert_eq!(call.calls.len(), 1);
+    let downstream_call = &call.calls[0];
+    assert_eq!(downstream_call.canister_id, callee);
+    assert!(downstream_call.calls.is_empty());
+    assert_eq!(
+        downstream_call.instructions_executed(),
+        callee_report.instructions_executed
+    );
+    assert_eq!(
+        call.total_instructions_executed(),
+        report.total_instructions_executed()
+    );
+
+    // The instructions executed by a query are reported, too.
+    let counter_canister_id = deploy_counter_canister(&pic);
+    let report = pic.query_call_with_report(
+        counter_canister_id,
+        Principal::anonymous(),
+        "read",
+        encode_one(()).unwrap(),
+    );
+    assert_eq!(report.result.unwrap(), 0_u32.to_le_bytes().to_vec());
+    assert_eq!(report.canisters.len(), 1);
+    assert_eq!(report.canisters[0].canister_id, counter_canister_id);
+    assert!(report.canisters[0].instructions_executed > 0);
+    assert_eq!(report.canisters[0].cycles_consumed(), 0);
+    assert_eq!(report.call.canister_id, counter_canister_id);
+    assert_eq!(report.call.messages.len(), 1);
+    assert_eq!(
+        report.call.instructions_executed(),
+        report.canisters[0].instructions_executed
+    );
+}
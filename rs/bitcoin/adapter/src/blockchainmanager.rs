@@ -1,5 +1,5 @@
 use crate::{
-    blockchainstate::{AddHeaderError, BlockchainState},
+    blockchainstate::{AddFilterError, AddHeaderError, BlockchainState, SerializedFilter},
     common::{BlockHeight, MINIMUM_VERSION_NUMBER},
     metrics::RouterMetrics,
     Channel, Command, ProcessBitcoinNetworkMessageError,
@@ -10,6 +10,8 @@ use bitcoin::{
     p2p::{
         message::{NetworkMessage, MAX_INV_SIZE},
         message_blockdata::{GetHeadersMessage, Inventory},
+        message_filter::{CFilter, GetCFilters},
+        ServiceFlags,
     },
     Block, BlockHash,
 };
@@ -42,6 +44,20 @@ const MAX_UNSOLICITED_HEADERS: usize = 20;
 /// to a peer at a time.
 const INV_PER_GET_DATA_REQUEST: u32 = 8;
 
+/// This constant is the maximum number of seconds to wait until we get response to the getcfilters request sent by us.
+const GETCFILTERS_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// This constant is the maximum number of "getcfilters" requests sent for the filter of a block.
+/// If two of them don't return the same filter, the block is returned without its filter.
+const MAX_GETCFILTERS_ATTEMPTS: u32 = 5;
+
+/// Max number of "getcfilters" requests that can be in-flight at any given time.
+const MAX_IN_FLIGHT_GETCFILTERS: usize = 100;
+
+/// The type of the BIP-158 basic filters.
+/// https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki#block-filters
+const BASIC_FILTER_TYPE: u8 = 0;
+
 /// Block locators. Consists of starting hashes and a stop hash.
 type Locators = (Vec<BlockHash>, BlockHash);
 
@@ -84,6 +100,22 @@ pub enum ReceivedBlockMessageError {
     BlockNotAdded,
 }
 
+/// The possible errors the `BlockchainManager::received_cfilter_message(...)` may produce.
+#[derive(Debug, Error)]
+enum ReceivedCFilterMessageError {
+    /// This variant represents when a message from an unknown peer.
+    #[error("Unknown peer")]
+    UnknownPeer,
+    /// This variant represents when a filter was received that was not requested.
+    #[error("Unknown filter")]
+    UnknownFilter,
+    #[error("Unsupported filter type {0}")]
+    UnsupportedFilterType(u8),
+    /// This variant represents that the filter did not match the block.
+    #[error("Failed to add filter: {0}")]
+    FilterNotAdded(AddFilterError),
+}
+
 /// This struct stores the information regarding a peer with respect to synchronizing the blockchain.
 /// This information is useful to keep track of the commands that have been sent to the peer,
 /// and how much blockchain state has already been synced with the peer.
@@ -131,6 +163,17 @@ struct GetDataRequestInfo {
     sent_at: Option<Instant>,
 }
 
+/// This struct stores the information related to a "getcfilters" request sent by the BlockChainManager.
+#[derive(Debug)]
+struct GetCFiltersRequestInfo {
+    /// This field stores the socket address of the Bitcoin node to which the request was sent.
+    socket: SocketAddr,
+    /// This field contains the time at which the getcfilters request was sent.
+    sent_at: Instant,
+    /// This field contains the number of requests sent for the filter so far, including this one.
+    attempts: u32,
+}
+
 /// The BlockChainManager struct handles interactions that involve the headers.
 pub struct BlockchainManager {
     /// This field contains the BlockchainState, which stores and manages
@@ -167,6 +210,24 @@ pub struct BlockchainManager {
     /// A block hash is removed when it is determined a peer can receive another `getdata` message.
     block_sync_queue: LinkedHashSet<BlockHash>,
 
+    /// This HashMap stores the information related to each getcfilters request
+    /// sent by the BlockChainManager. An entry is removed from this hashmap when
+    /// the corresponding "cfilter" response is received.
+    getcfilters_request_info: LinkedHashMap<BlockHash, GetCFiltersRequestInfo>,
+
+    /// This queue stores the hashes of the cached blocks whose BIP-158 filters have yet to be
+    /// requested, along with the number of requests sent for them so far.
+    ///
+    /// A block hash is added when the block is received and compact filters are enabled.
+    filter_sync_queue: LinkedHashMap<BlockHash, u32>,
+
+    /// This HashMap stores the filters received from a single peer so far, along with that peer.
+    ///
+    /// A peer can serve a filter that matches all the outputs of the block but not the scripts
+    /// of the spent outputs, which the adapter can't check without the UTXO set. Therefore, a filter
+    /// is only added to the `blockchain` once it has been served by two different peers.
+    filter_candidates: HashMap<BlockHash, (SocketAddr, SerializedFilter)>,
+
     /// This field contains a logger for the blockchain manager's use.
     logger: ReplicaLogger,
     metrics: RouterMetrics,
@@ -184,6 +245,17 @@ impl BlockchainManager {
         let peer_info = HashMap::new();
         let getdata_request_info = LinkedHashMap::new();
 
+        // The blocks restored from the persistent cache still need their filters.
+        let mut filter_sync_queue = LinkedHashMap::new();
+        {
+            let blockchain = blockchain.lock().unwrap();
+            if blockchain.compact_filters_enabled() {
+                for block_hash in blockchain.get_cached_blocks() {
+                    filter_sync_queue.insert(block_hash, 0);
+                }
+            }
+        }
+
         BlockchainManager {
             blockchain,
             peer_info,
@@ -192,6 +264,9 @@ impl BlockchainManager {
             getheaders_requests: HashMap::new(),
             catchup_headers: HashSet::new(),
             block_sync_queue: LinkedHashSet::new(),
+            getcfilters_request_info: LinkedHashMap::new(),
+            filter_sync_queue,
+            filter_candidates: HashMap::new(),
             logger,
             metrics,
         }
@@ -205,6 +280,9 @@ impl BlockchainManager {
 
         self.block_sync_queue.clear();
         self.getdata_request_info.clear();
+        self.filter_sync_queue.clear();
+        self.getcfilters_request_info.clear();
+        self.filter_candidates.clear();
         self.peer_info.clear();
         self.blockchain.lock().unwrap().clear_blocks();
     }
@@ -433,8 +511,14 @@ impl BlockchainManager {
             block_hash
         );
 
-        match self.blockchain.lock().unwrap().add_block(block.clone()) {
-            Ok(()) => Ok(()),
+        let mut blockchain = self.blockchain.lock().unwrap();
+        match blockchain.add_block(block.clone()) {
+            Ok(()) => {
+                if blockchain.compact_filters_enabled() {
+                    self.filter_sync_queue.insert(block_hash, 0);
+                }
+                Ok(())
+            }
             Err(err) => {
                 warn!(
                     self.logger,
@@ -445,6 +529,84 @@ impl BlockchainManager {
         }
     }
 
+    /// This function processes "cfilter" messages received from Bitcoin nodes
+    fn received_cfilter_message(
+        &mut self,
+        addr: &SocketAddr,
+        cfilter: &CFilter,
+    ) -> Result<(), ReceivedCFilterMessageError> {
+        if !self.peer_info.contains_key(addr) {
+            return Err(ReceivedCFilterMessageError::UnknownPeer);
+        }
+
+        if cfilter.filter_type != BASIC_FILTER_TYPE {
+            return Err(ReceivedCFilterMessageError::UnsupportedFilterType(
+                cfilter.filter_type,
+            ));
+        }
+
+        // Only the peer that the filter was requested from may serve it.
+        let request = match self.getcfilters_request_info.get(&cfilter.block_hash) {
+            Some(request) if request.socket == *addr => self
+                .getcfilters_request_info
+                .remove(&cfilter.block_hash)
+                .ok_or(ReceivedCFilterMessageError::UnknownFilter)?,
+            _ => return Err(ReceivedCFilterMessageError::UnknownFilter),
+        };
+
+        trace!(
+            self.logger,
+            "Received cfilter message from {} : Took {:?}sec. Block {:?}",
+            addr,
+            request.sent_at.elapsed(),
+            cfilter.block_hash
+        );
+
+        let mut blockchain = self.blockchain.lock().unwrap();
+        match blockchain.validate_filter(&cfilter.block_hash, &cfilter.filter) {
+            Ok(()) => {}
+            // The block has been pruned in the meantime, so its filter is no longer needed.
+            Err(AddFilterError::BlockNotCached(_)) => {
+                self.filter_candidates.remove(&cfilter.block_hash);
+                return Ok(());
+            }
+            Err(err) => {
+                // Retry with another peer.
+                self.filter_sync_queue
+                    .insert(cfilter.block_hash, request.attempts);
+                return Err(ReceivedCFilterMessageError::FilterNotAdded(err));
+            }
+        }
+
+        // The filter is only added once two different peers served the same one.
+        match self.filter_candidates.remove(&cfilter.block_hash) {
+            Some((peer, filter)) if peer != *addr && filter == cfilter.filter => {
+                match blockchain.add_filter(cfilter.block_hash, filter) {
+                    Ok(()) | Err(AddFilterError::BlockNotCached(_)) => Ok(()),
+                    Err(err) => Err(ReceivedCFilterMessageError::FilterNotAdded(err)),
+                }
+            }
+            candidate => {
+                if let Some((peer, _)) = candidate.filter(|(peer, _)| peer != addr) {
+                    // One of the peers serves a wrong filter, but it's unknown which one.
+                    // The next peer decides.
+                    warn!(
+                        self.logger,
+                        "Peers {} and {} served different filters for block {}",
+                        peer,
+                        addr,
+                        cfilter.block_hash
+                    );
+                }
+                self.filter_candidates
+                    .insert(cfilter.block_hash, (*addr, cfilter.filter.clone()));
+                self.filter_sync_queue
+                    .insert(cfilter.block_hash, request.attempts);
+                Ok(())
+            }
+        }
+    }
+
     /// This function adds a new peer to `peer_info`
     /// and initiates sync with the peer by sending `getheaders` message.
     fn add_peer(&mut self, channel: &mut impl Channel, addr: &SocketAddr) {
@@ -486,6 +648,18 @@ impl BlockchainManager {
             }
         }
 
+        // Retry the `getcfilters` requests that have been sent to the peer before.
+        let requests = self
+            .getcfilters_request_info
+            .iter()
+            .filter(|(_, request)| request.socket == *addr)
+            .map(|(block_hash, request)| (*block_hash, request.attempts))
+            .collect::<Vec<_>>();
+        for (block_hash, attempts) in requests {
+            self.getcfilters_request_info.remove(&block_hash);
+            self.filter_sync_queue.insert(block_hash, attempts);
+        }
+
         // Remove getheaders request sent to peer.
         self.getheaders_requests.remove(addr);
         // Unset catch-up flag
@@ -620,6 +794,102 @@ impl BlockchainManager {
         }
     }
 
+    /// Sends `getcfilters` requests for the BIP-158 filters of the received blocks to the peers
+    /// that serve them. If a filter can't be retrieved, the block is marked to be returned without it,
+    /// so that a lack of such peers doesn't stall the Bitcoin canister.
+    #[allow(clippy::indexing_slicing)]
+    fn sync_filters(&mut self, channel: &mut impl Channel) {
+        // Timeout requests so they may be retried again.
+        let timed_out = self
+            .getcfilters_request_info
+            .iter()
+            .filter(|(_, request)| request.sent_at.elapsed() > GETCFILTERS_REQUEST_TIMEOUT)
+            .map(|(block_hash, request)| (*block_hash, request.attempts))
+            .collect::<Vec<_>>();
+        for (block_hash, attempts) in timed_out {
+            self.getcfilters_request_info.remove(&block_hash);
+            self.filter_sync_queue.insert(block_hash, attempts);
+        }
+
+        if self.filter_sync_queue.is_empty() {
+            return;
+        }
+
+        let peers: Vec<SocketAddr> = self
+            .peer_info
+            .keys()
+            .filter(|addr| channel.has_services(addr, ServiceFlags::COMPACT_FILTERS))
+            .copied()
+            .collect();
+
+        let mut blockchain = self.blockchain.lock().unwrap();
+        let mut offset = self.round_robin_offset;
+        while let Some((block_hash, attempts)) = self.filter_sync_queue.pop_front() {
+            // The block may have been pruned in the meantime.
+            let height = match blockchain.get_cached_header(&block_hash) {
+                Some(header) if blockchain.get_block(&block_hash).is_some() => header.height,
+                _ => continue,
+            };
+
+            // The filter must be confirmed by another peer than the one that served it first.
+            let candidate_peer = self
+                .filter_candidates
+                .get(&block_hash)
+                .map(|(peer, _)| *peer);
+            let block_peers: Vec<SocketAddr> = peers
+                .iter()
+                .filter(|peer| Some(**peer) != candidate_peer)
+                .copied()
+                .collect();
+
+            if block_peers.is_empty() || attempts >= MAX_GETCFILTERS_ATTEMPTS {
+                debug!(
+                    self.logger,
+                    "Unable to retrieve the filter of block {}", block_hash
+                );
+                self.filter_candidates.remove(&block_hash);
+                blockchain.mark_filter_unavailable(block_hash);
+                continue;
+            }
+
+            if self.getcfilters_request_info.len() >= MAX_IN_FLIGHT_GETCFILTERS {
+                self.filter_sync_queue.insert(block_hash, attempts);
+                break;
+            }
+
+            // Spread the requests over the peers, so that a retry is sent to another peer.
+            let peer = block_peers[(offset + attempts as usize) % block_peers.len()];
+            offset = offset.wrapping_add(1);
+
+            trace!(
+                self.logger,
+                "Sending getcfilters to {} : Block {:?}",
+                peer,
+                block_hash
+            );
+
+            channel
+                .send(Command {
+                    address: Some(peer),
+                    message: NetworkMessage::GetCFilters(GetCFilters {
+                        filter_type: BASIC_FILTER_TYPE,
+                        start_height: height,
+                        stop_hash: block_hash,
+                    }),
+                })
+                .ok();
+
+            self.getcfilters_request_info.replace(
+                block_hash,
+                GetCFiltersRequestInfo {
+                    socket: peer,
+                    sent_at: Instant::now(),
+                    attempts: attempts + 1,
+                },
+            );
+        }
+    }
+
     /// This function is called by the adapter when a new event takes place.
     /// The event could be receiving "getheaders", "getdata", "inv" messages from bitcoin peers.
     /// The event could be change in connection status with a bitcoin peer.
@@ -654,6 +924,12 @@ impl BlockchainManager {
                     return Err(ProcessBitcoinNetworkMessageError::InvalidMessage);
                 }
             }
+            NetworkMessage::CFilter(cfilter) => {
+                if let Err(err) = self.received_cfilter_message(&addr, cfilter) {
+                    warn!(self.logger, "Received an invalid cfilter {}: {}", addr, err);
+                    return Err(ProcessBitcoinNetworkMessageError::InvalidMessage);
+                }
+            }
             _ => {}
         };
         Ok(())
@@ -690,6 +966,7 @@ impl BlockchainManager {
         }
 
         self.sync_blocks(channel);
+        self.sync_filters(channel);
         self.handle_getheaders_timeouts(channel);
     }
 
@@ -730,11 +1007,26 @@ impl BlockchainManager {
             self.block_sync_queue.retain(|b| {
                 blockchain.get_cached_header(b).map_or(0, |c| c.height) >= filter_height
             });
+
+            self.getcfilters_request_info.retain(|b, _| {
+                blockchain.get_cached_header(b).map_or(0, |c| c.height) >= filter_height
+            });
+
+            self.filter_sync_queue.retain(|b, _| {
+                blockchain.get_cached_header(b).map_or(0, |c| c.height) >= filter_height
+            });
+
+            self.filter_candidates.retain(|b, _| {
+                blockchain.get_cached_header(b).map_or(0, |c| c.height) >= filter_height
+            });
         };
 
         for block_hash in processed_block_hashes {
             self.getdata_request_info.remove(&block_hash);
             self.block_sync_queue.remove(&block_hash);
+            self.getcfilters_request_info.remove(&block_hash);
+            self.filter_sync_queue.remove(&block_hash);
+            self.filter_candidates.remove(&block_hash);
         }
     }
 
@@ -768,7 +1060,7 @@ pub mod test {
     use bitcoin::blockdata::constants::genesis_block;
     use bitcoin::consensus::deserialize;
     use bitcoin::Network;
-    use bitcoin::{p2p::message::NetworkMessage, BlockHash};
+    use bitcoin::{bip158::BlockFilter, p2p::message::NetworkMessage, BlockHash};
     use hex::FromHex;
     use ic_btc_adapter_test_utils::{
         generate_headers, generate_large_block_blockchain, BLOCK_1_ENCODED, BLOCK_2_ENCODED,
@@ -779,7 +1071,8 @@ pub mod test {
     use std::str::FromStr;
 
     fn create_blockchain_manager(config: &Config) -> (BlockHeader, BlockchainManager) {
-        let blockchain_state = BlockchainState::new(config, &MetricsRegistry::default());
+        let blockchain_state =
+            BlockchainState::new(config, &MetricsRegistry::default(), no_op_logger());
         (
             *blockchain_state.genesis(),
             BlockchainManager::new(
@@ -1108,6 +1401,168 @@ pub mod test {
         }
     }
 
+    /// Tests that the filters of the received blocks are requested from the peers serving them,
+    /// retried on invalid responses, only added once served by two peers and given up on without
+    /// such peers.
+    #[test]
+    fn test_sync_filters_and_received_cfilter_message_lifecycle() {
+        let peer_addr_1 = SocketAddr::from_str("127.0.0.1:8333").expect("bad address format");
+        let peer_addr_2 = SocketAddr::from_str("127.0.0.2:8333").expect("bad address format");
+        let mut channel = TestChannel::new(vec![peer_addr_1, peer_addr_2]);
+        channel.set_services(ServiceFlags::NETWORK | ServiceFlags::COMPACT_FILTERS);
+        let config = ConfigBuilder::new().with_compact_filters(true).build();
+        let (_, mut blockchain_manager) = create_blockchain_manager(&config);
+
+        // Mainnet block 00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048
+        let encoded_block_1 = Vec::from_hex(BLOCK_1_ENCODED).expect("unable to make vec from hex");
+        let block_1: Block = deserialize(&encoded_block_1).expect("failed to decoded block 1");
+        let block_1_hash = block_1.block_hash();
+        let filter_1 = BlockFilter::new_script_filter(&block_1, |outpoint| {
+            Err::<bitcoin::ScriptBuf, _>(bitcoin::bip158::Error::UtxoMissing(*outpoint))
+        })
+        .unwrap()
+        .content;
+        let cfilter = CFilter {
+            filter_type: BASIC_FILTER_TYPE,
+            block_hash: block_1_hash,
+            filter: filter_1.clone(),
+        };
+        let get_filter = |blockchain_manager: &BlockchainManager| {
+            blockchain_manager
+                .blockchain
+                .lock()
+                .unwrap()
+                .get_filter(&block_1_hash)
+        };
+        // Returns the peer that the filter of block 1 was requested from.
+        let requested_peer = |channel: &mut TestChannel| {
+            let command = channel.pop_back().expect("no command sent");
+            assert_eq!(
+                command.message,
+                NetworkMessage::GetCFilters(GetCFilters {
+                    filter_type: BASIC_FILTER_TYPE,
+                    start_height: 1,
+                    stop_hash: block_1_hash,
+                })
+            );
+            command.address.expect("no address")
+        };
+        let other_peer = |peer: SocketAddr| {
+            if peer == peer_addr_1 {
+                peer_addr_2
+            } else {
+                peer_addr_1
+            }
+        };
+
+        blockchain_manager
+            .blockchain
+            .lock()
+            .unwrap()
+            .add_headers(&[block_1.header]);
+        blockchain_manager.add_peer(&mut channel, &peer_addr_1);
+        blockchain_manager.add_peer(&mut channel, &peer_addr_2);
+        blockchain_manager.block_sync_queue.insert(block_1_hash);
+        blockchain_manager.sync_blocks(&mut channel);
+        assert!(blockchain_manager
+            .received_block_message(&peer_addr_1, &block_1)
+            .is_ok());
+        assert!(blockchain_manager
+            .filter_sync_queue
+            .contains_key(&block_1_hash));
+
+        blockchain_manager.sync_filters(&mut channel);
+        let peer = requested_peer(&mut channel);
+        assert!(blockchain_manager.filter_sync_queue.is_empty());
+
+        // Only the requested peer may serve the filter.
+        assert!(matches!(
+            blockchain_manager.received_cfilter_message(&other_peer(peer), &cfilter),
+            Err(ReceivedCFilterMessageError::UnknownFilter)
+        ));
+
+        // An invalid filter is rejected and requested again.
+        let result = blockchain_manager.received_cfilter_message(
+            &peer,
+            &CFilter {
+                filter_type: BASIC_FILTER_TYPE,
+                block_hash: block_1_hash,
+                filter: vec![0xff],
+            },
+        );
+        assert!(matches!(
+            result,
+            Err(ReceivedCFilterMessageError::FilterNotAdded(_))
+        ));
+        blockchain_manager.sync_filters(&mut channel);
+        let peer = requested_peer(&mut channel);
+
+        // A valid filter is only added once it's confirmed by another peer.
+        assert!(blockchain_manager
+            .received_cfilter_message(&peer, &cfilter)
+            .is_ok());
+        assert_eq!(get_filter(&blockchain_manager), None);
+        blockchain_manager.sync_filters(&mut channel);
+        let confirming_peer = requested_peer(&mut channel);
+        assert_eq!(confirming_peer, other_peer(peer));
+        assert!(blockchain_manager
+            .received_cfilter_message(&confirming_peer, &cfilter)
+            .is_ok());
+        assert_eq!(
+            get_filter(&blockchain_manager),
+            Some(Arc::new(filter_1.clone()))
+        );
+        assert!(blockchain_manager.filter_candidates.is_empty());
+
+        // The filter is no longer expected.
+        assert!(matches!(
+            blockchain_manager.received_cfilter_message(&confirming_peer, &cfilter),
+            Err(ReceivedCFilterMessageError::UnknownFilter)
+        ));
+
+        // Without another peer to confirm the filter, the block is returned without its filter.
+        blockchain_manager
+            .blockchain
+            .lock()
+            .unwrap()
+            .prune_blocks(&[block_1_hash]);
+        blockchain_manager
+            .blockchain
+            .lock()
+            .unwrap()
+            .add_block(block_1.clone())
+            .unwrap();
+        blockchain_manager.remove_peer(&peer_addr_2);
+        blockchain_manager.filter_sync_queue.insert(block_1_hash, 0);
+        blockchain_manager.sync_filters(&mut channel);
+        assert_eq!(requested_peer(&mut channel), peer_addr_1);
+        assert!(blockchain_manager
+            .received_cfilter_message(&peer_addr_1, &cfilter)
+            .is_ok());
+        let command_count = channel.command_count();
+        blockchain_manager.sync_filters(&mut channel);
+        assert_eq!(channel.command_count(), command_count);
+        assert_eq!(get_filter(&blockchain_manager), Some(Arc::new(vec![])));
+
+        // Without peers serving the filters, the block is returned without its filter.
+        blockchain_manager
+            .blockchain
+            .lock()
+            .unwrap()
+            .prune_blocks(&[block_1_hash]);
+        blockchain_manager
+            .blockchain
+            .lock()
+            .unwrap()
+            .add_block(block_1.clone())
+            .unwrap();
+        blockchain_manager.filter_sync_queue.insert(block_1_hash, 0);
+        channel.set_services(ServiceFlags::NETWORK);
+        blockchain_manager.sync_filters(&mut channel);
+        assert_eq!(channel.command_count(), command_count);
+        assert_eq!(get_filter(&blockchain_manager), Some(Arc::new(vec![])));
+    }
+
     /// This function tests to ensure that the BlockchainManager does not send out `getdata`
     /// requests when the block cache has reached the size threshold.
     #[test]
@@ -1,15 +1,24 @@
 //! The module is responsible for keeping track of the blockchain state.
 //!
-use crate::{common::BlockHeight, config::Config, metrics::BlockchainStateMetrics};
+use crate::{
+    common::BlockHeight,
+    config::Config,
+    metrics::BlockchainStateMetrics,
+    persistent_cache::{CacheWrite, CachedData, PersistentCache, PersistentCacheWriter},
+};
 use bitcoin::{
-    block::Header as BlockHeader, blockdata::constants::genesis_block, consensus::Encodable, Block,
-    BlockHash, Network,
+    bip158::BlockFilter,
+    block::Header as BlockHeader,
+    blockdata::constants::genesis_block,
+    consensus::{Decodable, Encodable},
+    Block, BlockHash, Network,
 };
 
 use bitcoin::Work;
 use ic_btc_validation::{validate_header, HeaderStore, ValidateHeaderError};
+use ic_logger::{warn, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, io, path::Path, sync::Arc};
 use thiserror::Error;
 
 /// The limit at which we should stop making additional requests for new blocks as the block cache
@@ -18,6 +27,10 @@ use thiserror::Error;
 const BLOCK_CACHE_THRESHOLD_BYTES: usize = 10 * ONE_MB;
 const ONE_MB: usize = 1_024 * 1_024;
 
+/// The persisted header log is compacted when it is opened and contains more than this number
+/// of headers that are not on the active chain, e.g. headers of stale forks.
+const MAX_PERSISTED_HEADERS_OFF_ACTIVE_CHAIN: usize = 1_000;
+
 /// Contains the necessary information about a tip.
 #[derive(Clone, Debug)]
 pub struct Tip {
@@ -91,8 +104,25 @@ pub enum AddBlockError {
     CouldNotSerialize(BlockHash, String),
 }
 
+#[derive(Debug, Error)]
+pub enum AddFilterError {
+    /// Used to indicate that the block of the filter is not in the block cache.
+    #[error("Received a filter for a block that is not cached: {0}")]
+    BlockNotCached(BlockHash),
+    /// Used to indicate that the filter is malformed or doesn't match the outputs of the block.
+    #[error("Received an invalid filter for block {0}")]
+    InvalidFilter(BlockHash),
+    /// Used to indicate that the cached block could not be deserialized.
+    #[error("Deserialization error for block {0} with error {1}")]
+    CouldNotDeserialize(BlockHash, String),
+}
+
 pub type SerializedBlock = Vec<u8>;
 
+/// A BIP-158 basic block filter. An empty filter means that the filter of the
+/// block is not available.
+pub type SerializedFilter = Vec<u8>;
+
 /// This struct is a cache of Bitcoin blockchain.
 /// The BlockChainState caches all the Bitcoin headers, some of the Bitcoin blocks.
 /// The BlockChainState also maintains the child relationhips between the headers.
@@ -107,17 +137,28 @@ pub struct BlockchainState {
     /// This field stores a hashmap containing BlockHash and the corresponding SerializedBlock.
     block_cache: HashMap<BlockHash, Arc<SerializedBlock>>,
 
+    /// This field stores the BIP-158 basic filters of the blocks in the `block_cache`.
+    filter_cache: HashMap<BlockHash, Arc<SerializedFilter>>,
+
+    /// Whether the blocks are only returned along with their filters.
+    compact_filters: bool,
+
     /// This field contains the known tips of the header cache.
     tips: Vec<Tip>,
 
     /// Used to determine how validation should be handled with `validate_header`.
     network: Network,
     metrics: BlockchainStateMetrics,
+
+    /// This field stores the on-disk copy of the headers and the cached blocks, if enabled.
+    /// It is dropped after a failed write, so that the copy is never missing headers in the middle.
+    persistent_cache: Option<PersistentCacheWriter>,
 }
 
 impl BlockchainState {
-    /// This function is used to create a new BlockChainState object.  
-    pub fn new(config: &Config, metrics_registry: &MetricsRegistry) -> Self {
+    /// This function is used to create a new BlockChainState object.
+    /// If a cache directory is configured, the headers and blocks persisted in it are restored.
+    pub fn new(config: &Config, metrics_registry: &MetricsRegistry, logger: ReplicaLogger) -> Self {
         // Create a header cache and inserting dummy header corresponding the `adapter_genesis_hash`.
         let genesis_block_header = genesis_block(config.network).header;
         let header_cache = init_cache_with_genesis(genesis_block_header);
@@ -128,13 +169,128 @@ impl BlockchainState {
             work: genesis_block_header.work(),
         }];
 
-        BlockchainState {
+        let mut state = BlockchainState {
             genesis_block_header,
             header_cache,
             block_cache,
+            filter_cache: HashMap::new(),
+            compact_filters: config.compact_filters,
             tips,
             network: config.network,
             metrics: BlockchainStateMetrics::new(metrics_registry),
+            persistent_cache: None,
+        };
+
+        if let Some(cache_dir) = &config.cache_dir {
+            // Each network has its own headers, so they are kept apart.
+            let dir = cache_dir.join(config.network.to_string());
+            let persistent_cache =
+                state
+                    .open_persistent_cache(&dir, logger)
+                    .unwrap_or_else(|err| {
+                        panic!(
+                            "Failed to open the persistent cache at {}: {}",
+                            dir.display(),
+                            err
+                        )
+                    });
+            state.persistent_cache = Some(persistent_cache);
+        }
+
+        state
+    }
+
+    /// Opens the persistent cache in the given directory and restores its contents.
+    fn open_persistent_cache(
+        &mut self,
+        dir: &Path,
+        logger: ReplicaLogger,
+    ) -> io::Result<PersistentCacheWriter> {
+        let (mut persistent_cache, cached_data) = PersistentCache::open(dir)?;
+        let num_persisted_headers = cached_data.headers.len();
+        let num_skipped_headers = self.restore(cached_data);
+
+        // Keep the log from growing with the headers of stale forks
+        // and drop the headers that are no longer valid.
+        let active_chain = self.get_active_chain_headers();
+        if num_skipped_headers > 0
+            || num_persisted_headers.saturating_sub(active_chain.len())
+                > MAX_PERSISTED_HEADERS_OFF_ACTIVE_CHAIN
+        {
+            warn!(
+                logger,
+                "Compacting the persisted headers: {} persisted, {} skipped, {} on the active chain",
+                num_persisted_headers,
+                num_skipped_headers,
+                active_chain.len()
+            );
+            persistent_cache.compact_headers(&active_chain)?;
+        }
+
+        PersistentCacheWriter::new(
+            persistent_cache,
+            logger,
+            self.metrics.persistent_cache_errors.clone(),
+        )
+    }
+
+    /// Restores the headers and blocks read from the persistent cache. The headers are validated
+    /// again, e.g. in case the log was tampered with, and the invalid ones are skipped along with
+    /// the duplicated ones. Returns the number of persisted headers that were skipped.
+    fn restore(&mut self, cached_data: CachedData) -> usize {
+        let mut num_skipped_headers = 0;
+        for header in cached_data.headers {
+            if self.get_cached_header(&header.block_hash()).is_some()
+                || validate_header(&self.network, self, &header).is_err()
+                || self.insert_header(header).is_err()
+            {
+                num_skipped_headers += 1;
+            }
+        }
+        self.tips.sort_unstable_by(|a, b| b.work.cmp(&a.work));
+        self.metrics.tips.set(self.tips.len() as i64);
+        self.metrics
+            .tip_height
+            .set(self.get_active_chain_tip().height.into());
+
+        for (block_hash, block) in cached_data.blocks {
+            if self.header_cache.contains_key(&block_hash) {
+                self.block_cache.insert(block_hash, Arc::new(block));
+            }
+        }
+        self.metrics
+            .block_cache_size
+            .set(self.get_block_cache_size() as i64);
+        self.metrics
+            .block_cache_elements
+            .set(self.block_cache.len() as i64);
+        num_skipped_headers
+    }
+
+    /// Returns the headers of the active chain, from the child of the genesis to the tip.
+    fn get_active_chain_headers(&self) -> Vec<BlockHeader> {
+        let genesis_hash = self.genesis_block_header.block_hash();
+        let mut headers = vec![];
+        let mut current = self.get_active_chain_tip().header;
+        while current.block_hash() != genesis_hash {
+            headers.push(current);
+            match self.get_cached_header(&current.prev_blockhash) {
+                Some(parent) => current = parent.header,
+                None => break,
+            }
+        }
+        headers.reverse();
+        headers
+    }
+
+    /// Sends the given write to the persistent cache, if enabled. The write is applied
+    /// in the background, so that the disk is not synced while holding the state.
+    /// The persistent cache is disabled once a write has failed.
+    fn persist(&mut self, write: CacheWrite) {
+        if let Some(persistent_cache) = self.persistent_cache.as_ref() {
+            if !persistent_cache.send(write) {
+                self.persistent_cache = None;
+            }
         }
     }
 
@@ -163,12 +319,14 @@ impl BlockchainState {
         headers: &[BlockHeader],
     ) -> (Vec<BlockHash>, Option<AddHeaderError>) {
         let mut block_hashes_of_added_headers = vec![];
+        let mut added_headers = vec![];
 
         let err = headers
             .iter()
             .try_for_each(|header| match self.add_header(*header) {
                 Ok(AddHeaderResult::HeaderAdded(block_hash)) => {
                     block_hashes_of_added_headers.push(block_hash);
+                    added_headers.push(*header);
                     Ok(())
                 }
                 Ok(AddHeaderResult::HeaderAlreadyExists) => Ok(()),
@@ -176,6 +334,10 @@ impl BlockchainState {
             })
             .err();
 
+        if !added_headers.is_empty() {
+            self.persist(CacheWrite::AppendHeaders(added_headers));
+        }
+
         // Sort the tips by the total work
         self.tips.sort_unstable_by(|a, b| b.work.cmp(&a.work));
         self.metrics.tips.set(self.tips.len() as i64);
@@ -186,8 +348,8 @@ impl BlockchainState {
         (block_hashes_of_added_headers, err)
     }
 
-    /// This method adds the input header to the `header_cache`.
-    #[allow(clippy::indexing_slicing)]
+    /// This method validates the input header and adds it to the `header_cache`.
+    /// The caller is responsible for persisting the added header.
     fn add_header(&mut self, header: BlockHeader) -> Result<AddHeaderResult, AddHeaderError> {
         let block_hash = header.block_hash();
 
@@ -201,6 +363,15 @@ impl BlockchainState {
             return Err(AddHeaderError::InvalidHeader(block_hash, err));
         }
 
+        self.insert_header(header)
+    }
+
+    /// This method adds the input header, which must not be cached yet, to the `header_cache`
+    /// and updates the tips.
+    #[allow(clippy::indexing_slicing)]
+    fn insert_header(&mut self, header: BlockHeader) -> Result<AddHeaderResult, AddHeaderError> {
+        let block_hash = header.block_hash();
+
         let parent = self
             .header_cache
             .get_mut(&header.prev_blockhash)
@@ -252,9 +423,12 @@ impl BlockchainState {
         }
 
         // If the block's header is not added before, then add the header into the `header_cache` first.
-        let _ = self
+        if let AddHeaderResult::HeaderAdded(_) = self
             .add_header(block.header)
-            .map_err(AddBlockError::Header)?;
+            .map_err(AddBlockError::Header)?
+        {
+            self.persist(CacheWrite::AppendHeaders(vec![block.header]));
+        }
         self.tips.sort_unstable_by(|a, b| b.work.cmp(&a.work));
 
         let mut serialized_block = vec![];
@@ -262,8 +436,9 @@ impl BlockchainState {
             .consensus_encode(&mut serialized_block)
             .map_err(|e| AddBlockError::CouldNotSerialize(block_hash, e.to_string()))?;
 
-        self.block_cache
-            .insert(block_hash, Arc::new(serialized_block));
+        let serialized_block = Arc::new(serialized_block);
+        self.persist(CacheWrite::StoreBlock(block_hash, serialized_block.clone()));
+        self.block_cache.insert(block_hash, serialized_block);
 
         self.metrics
             .block_cache_size
@@ -274,6 +449,80 @@ impl BlockchainState {
         Ok(())
     }
 
+    /// This method adds the BIP-158 basic filter of a cached block to the `filter_cache`
+    /// if it passes [`BlockchainState::validate_filter`].
+    pub fn add_filter(
+        &mut self,
+        block_hash: BlockHash,
+        filter: SerializedFilter,
+    ) -> Result<(), AddFilterError> {
+        self.validate_filter(&block_hash, &filter)?;
+
+        self.filter_cache.insert(block_hash, Arc::new(filter));
+        self.metrics
+            .filter_cache_elements
+            .set(self.filter_cache.len() as i64);
+        Ok(())
+    }
+
+    /// This method checks that the BIP-158 basic filter of a cached block matches all the output
+    /// scripts of the block. The scripts of the spent outputs can't be checked as the adapter
+    /// doesn't keep the UTXO set, so a filter must also be cross-checked with other peers.
+    pub fn validate_filter(
+        &self,
+        block_hash: &BlockHash,
+        filter: &SerializedFilter,
+    ) -> Result<(), AddFilterError> {
+        let block_hash = *block_hash;
+        let serialized_block = self
+            .block_cache
+            .get(&block_hash)
+            .ok_or(AddFilterError::BlockNotCached(block_hash))?;
+        let block = Block::consensus_decode(&mut serialized_block.as_slice())
+            .map_err(|e| AddFilterError::CouldNotDeserialize(block_hash, e.to_string()))?;
+
+        // The basic filter contains all the output scripts except the empty and OP_RETURN ones.
+        let scripts: Vec<&[u8]> = block
+            .txdata
+            .iter()
+            .flat_map(|tx| tx.output.iter())
+            .map(|output| output.script_pubkey.as_script())
+            .filter(|script| !script.is_empty() && !script.is_op_return())
+            .map(|script| script.as_bytes())
+            .collect();
+
+        if !scripts.is_empty()
+            && !matches!(
+                BlockFilter::new(filter).match_all(block_hash, scripts.into_iter()),
+                Ok(true)
+            )
+        {
+            return Err(AddFilterError::InvalidFilter(block_hash));
+        }
+
+        Ok(())
+    }
+
+    /// Records that the filter of a cached block could not be retrieved, so the block
+    /// is returned without it.
+    pub fn mark_filter_unavailable(&mut self, block_hash: BlockHash) {
+        if self.block_cache.contains_key(&block_hash) {
+            self.filter_cache
+                .entry(block_hash)
+                .or_insert_with(|| Arc::new(vec![]));
+        }
+    }
+
+    /// Returns the filter of the given block, if it has been retrieved.
+    pub fn get_filter(&self, block_hash: &BlockHash) -> Option<Arc<SerializedFilter>> {
+        self.filter_cache.get(block_hash).cloned()
+    }
+
+    /// Returns whether the blocks are only returned along with their filters.
+    pub fn compact_filters_enabled(&self) -> bool {
+        self.compact_filters
+    }
+
     /// This method returns the tip header with the highest cumulative work.
     #[allow(clippy::indexing_slicing)]
     pub fn get_active_chain_tip(&self) -> &Tip {
@@ -287,7 +536,10 @@ impl BlockchainState {
     /// block hashes.
     pub fn prune_blocks(&mut self, block_hashes: &[BlockHash]) {
         for block_hash in block_hashes {
-            self.block_cache.remove(block_hash);
+            if self.block_cache.remove(block_hash).is_some() {
+                self.persist(CacheWrite::RemoveBlock(*block_hash));
+            }
+            self.filter_cache.remove(block_hash);
         }
     }
 
@@ -350,6 +602,8 @@ impl BlockchainState {
     /// Used when the adapter is shutdown and no longer requires holding on to blocks.
     pub fn clear_blocks(&mut self) {
         self.block_cache = HashMap::new();
+        self.filter_cache = HashMap::new();
+        self.persist(CacheWrite::ClearBlocks);
     }
 
     pub(crate) fn is_block_cache_full(&self) -> bool {
@@ -380,6 +634,7 @@ impl HeaderStore for BlockchainState {
 #[cfg(test)]
 mod test {
     use bitcoin::{consensus::Decodable, Block, TxMerkleNode};
+    use ic_logger::replica_logger::no_op_logger;
     use ic_metrics::MetricsRegistry;
 
     use super::*;
@@ -391,7 +646,7 @@ mod test {
     fn test_get_block() {
         let test_state = TestState::setup();
         let config = ConfigBuilder::new().build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         state
             .add_block(test_state.block_1.clone())
@@ -419,7 +674,7 @@ mod test {
     #[test]
     fn test_adding_headers_successfully() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         let initial_header = state.genesis();
         let chain = generate_headers(initial_header.block_hash(), initial_header.time, 16, &[]);
@@ -443,7 +698,7 @@ mod test {
     #[test]
     fn test_adding_mainnet_headers_successfully() {
         let config = ConfigBuilder::new().with_network(Network::Bitcoin).build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         let headers_json = include_str!("../test_data/first_2500_mainnet_headers.json");
         let headers: Vec<BlockHeader> = serde_json::from_str(headers_json).unwrap();
@@ -466,7 +721,7 @@ mod test {
     #[test]
     fn test_adding_testnet_headers_successfully() {
         let config = ConfigBuilder::new().with_network(Network::Testnet).build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         let headers_json = include_str!("../test_data/first_2500_testnet_headers.json");
         let headers: Vec<BlockHeader> = serde_json::from_str(headers_json).unwrap();
@@ -488,7 +743,7 @@ mod test {
     /// cause 2 forks in the chain. The state should be able to determine what is the active tip.
     fn test_forks_when_adding_headers() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let initial_header = state.genesis();
 
         // Create an arbitrary chain and adding to the BlockchainState
@@ -528,7 +783,7 @@ mod test {
     #[test]
     fn test_adding_an_empty_headers_vector() {
         let config = ConfigBuilder::new().build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let chain = vec![];
         let (added_headers, maybe_err) = state.add_headers(&chain);
         assert!(maybe_err.is_none());
@@ -541,7 +796,7 @@ mod test {
     #[test]
     fn test_adding_headers_that_already_exist() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         let initial_header = state.genesis();
         let chain = generate_headers(initial_header.block_hash(), initial_header.time, 16, &[]);
@@ -565,7 +820,7 @@ mod test {
     #[test]
     fn test_adding_headers_with_an_invalid_header() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         let initial_header = state.genesis();
         let mut chain = generate_headers(initial_header.block_hash(), initial_header.time, 16, &[]);
@@ -594,7 +849,7 @@ mod test {
         let mut block_2 = block_2();
 
         let config = ConfigBuilder::new().build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         // Attempt to add block 2 to the cache before block 1's header has been added.
         let block_2_hash = block_2.header.block_hash();
@@ -623,7 +878,7 @@ mod test {
     fn test_pruning_blocks_from_the_cache() {
         let test_state = TestState::setup();
         let config = ConfigBuilder::new().build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let block_1_hash = test_state.block_1.block_hash();
         let block_2_hash = test_state.block_2.block_hash();
         state.add_block(test_state.block_1).unwrap();
@@ -640,7 +895,7 @@ mod test {
     fn test_pruning_blocks_below_a_given_height_from_the_cache() {
         let test_state = TestState::setup();
         let config = ConfigBuilder::new().build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let block_1_hash = test_state.block_1.block_hash();
         let block_2_hash = test_state.block_2.block_hash();
         state.add_block(test_state.block_1).unwrap();
@@ -657,7 +912,7 @@ mod test {
     fn test_block_cache_size() {
         let test_state = TestState::setup();
         let config = ConfigBuilder::new().build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         let block_cache_size = state.get_block_cache_size();
         assert_eq!(block_cache_size, 0);
@@ -676,7 +931,7 @@ mod test {
     #[test]
     fn test_sorted_tip() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let h1 = *state.genesis();
         // h1 - h2
         let h2 = generate_header(h1.block_hash(), h1.time, 0);
@@ -720,7 +975,7 @@ mod test {
     #[test]
     fn test_headerstore_get_cached_header() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let mut state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
 
         let initial_header = state.genesis();
         let chain = generate_headers(initial_header.block_hash(), initial_header.time, 2500, &[]);
@@ -741,4 +996,134 @@ mod test {
             }
         }
     }
+
+    /// Tests that a filter is only added for a cached block and if it matches the block.
+    #[test]
+    fn test_add_filter() {
+        let test_state = TestState::setup();
+        let config = ConfigBuilder::new().with_compact_filters(true).build();
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
+        assert!(state.compact_filters_enabled());
+
+        let block_1_hash = test_state.block_1.block_hash();
+        let block_2_hash = test_state.block_2.block_hash();
+        let filter = |block: &Block| {
+            BlockFilter::new_script_filter(block, |outpoint| {
+                Err::<bitcoin::ScriptBuf, _>(bitcoin::bip158::Error::UtxoMissing(*outpoint))
+            })
+            .unwrap()
+            .content
+        };
+        let filter_1 = filter(&test_state.block_1);
+        let filter_2 = filter(&test_state.block_2);
+
+        assert!(matches!(
+            state.add_filter(block_1_hash, filter_1.clone()),
+            Err(AddFilterError::BlockNotCached(_))
+        ));
+
+        state.add_block(test_state.block_1.clone()).unwrap();
+        assert!(matches!(
+            state.add_filter(block_1_hash, filter_2),
+            Err(AddFilterError::InvalidFilter(_))
+        ));
+        assert!(matches!(
+            state.add_filter(block_1_hash, vec![0xff]),
+            Err(AddFilterError::InvalidFilter(_))
+        ));
+        assert!(state.get_filter(&block_1_hash).is_none());
+
+        state.add_filter(block_1_hash, filter_1.clone()).unwrap();
+        assert_eq!(state.get_filter(&block_1_hash), Some(Arc::new(filter_1)));
+
+        // Only cached blocks can be marked.
+        state.mark_filter_unavailable(block_2_hash);
+        assert!(state.get_filter(&block_2_hash).is_none());
+        state.add_block(test_state.block_2.clone()).unwrap();
+        state.mark_filter_unavailable(block_2_hash);
+        assert_eq!(state.get_filter(&block_2_hash), Some(Arc::new(vec![])));
+
+        // Filters are pruned along with the blocks.
+        state.prune_blocks(&[block_1_hash]);
+        assert!(state.get_filter(&block_1_hash).is_none());
+        state.clear_blocks();
+        assert!(state.get_filter(&block_2_hash).is_none());
+    }
+
+    /// Tests that the headers and blocks are restored from the persistent cache.
+    #[test]
+    fn test_restore_from_persistent_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let test_state = TestState::setup();
+        let config = ConfigBuilder::new()
+            .with_cache_dir(dir.path().to_path_buf())
+            .build();
+        let block_1_hash = test_state.block_1.block_hash();
+        let block_2_hash = test_state.block_2.block_hash();
+
+        {
+            let mut state =
+                BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
+            state.add_block(test_state.block_1.clone()).unwrap();
+            state.add_block(test_state.block_2.clone()).unwrap();
+            state.prune_blocks(&[block_1_hash]);
+        }
+
+        let mut state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
+        let tip = state.get_active_chain_tip();
+        assert_eq!(tip.height, 2);
+        assert_eq!(tip.header.block_hash(), block_2_hash);
+        assert!(state.get_cached_header(&block_1_hash).is_some());
+        assert!(state.get_block(&block_1_hash).is_none());
+        let block = state.get_block(&block_2_hash).unwrap();
+        assert_eq!(
+            Block::consensus_decode(&mut block.as_slice()).unwrap(),
+            test_state.block_2
+        );
+
+        // The headers are not persisted twice.
+        state.add_headers(&[test_state.block_1.header, test_state.block_2.header]);
+        state.clear_blocks();
+        drop(state);
+
+        let state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
+        assert_eq!(state.get_active_chain_tip().height, 2);
+        assert!(state.get_cached_blocks().is_empty());
+        assert_eq!(
+            std::fs::metadata(dir.path().join("bitcoin").join("headers.log"))
+                .unwrap()
+                .len(),
+            2 * 84
+        );
+
+        // A persisted header is validated again when it is restored
+        // and the invalid ones are dropped from the log.
+        {
+            let (mut persistent_cache, _) =
+                PersistentCache::open(&dir.path().join("bitcoin")).unwrap();
+            let mut header = test_state.block_2.header;
+            header.nonce += 1;
+            assert!(header.validate_pow(header.target()).is_err());
+            persistent_cache.append_header(&header).unwrap();
+            persistent_cache.sync().unwrap();
+        }
+        let state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
+        assert_eq!(state.get_active_chain_tip().height, 2);
+        assert_eq!(state.header_cache.len(), 3);
+        assert_eq!(
+            std::fs::metadata(dir.path().join("bitcoin").join("headers.log"))
+                .unwrap()
+                .len(),
+            2 * 84
+        );
+        drop(state);
+
+        // The headers of another network are kept apart.
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_cache_dir(dir.path().to_path_buf())
+            .build();
+        let state = BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
+        assert_eq!(state.get_active_chain_tip().height, 0);
+    }
 }
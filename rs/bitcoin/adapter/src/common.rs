@@ -24,7 +24,7 @@ pub mod test_common {
         net::SocketAddr,
     };
 
-    use bitcoin::{consensus::deserialize, Block};
+    use bitcoin::{consensus::deserialize, p2p::ServiceFlags, Block};
     use hex::FromHex;
 
     use crate::{Channel, ChannelError, Command};
@@ -43,6 +43,8 @@ pub mod test_common {
         available_connections: Vec<SocketAddr>,
         /// The addresses that disconnect was called on.
         disconnected_addresses: HashSet<SocketAddr>,
+        /// The services advertised by all the connections.
+        services: ServiceFlags,
     }
 
     impl TestChannel {
@@ -51,6 +53,7 @@ pub mod test_common {
                 received_commands: VecDeque::new(),
                 available_connections,
                 disconnected_addresses: HashSet::new(),
+                services: ServiceFlags::NETWORK,
             }
         }
     }
@@ -74,6 +77,10 @@ pub mod test_common {
         pub fn add_address(&mut self, addr: SocketAddr) {
             self.available_connections.push(addr);
         }
+
+        pub fn set_services(&mut self, services: ServiceFlags) {
+            self.services = services;
+        }
     }
 
     impl Channel for TestChannel {
@@ -90,6 +97,10 @@ pub mod test_common {
                 .collect()
         }
 
+        fn has_services(&self, _addr: &SocketAddr, services: ServiceFlags) -> bool {
+            self.services.has(services)
+        }
+
         fn discard(&mut self, addr: &SocketAddr) {
             self.disconnected_addresses.insert(*addr);
         }
@@ -49,6 +49,15 @@ pub struct Config {
     /// Specifies the address limits used by the `AddressBook`.
     #[serde(default)]
     pub address_limits: (usize, usize),
+    /// When this field is set to `true`, the adapter fetches the BIP-158 basic filters
+    /// of the downloaded blocks from the nodes that serve them (BIP-157) and returns them
+    /// alongside the blocks. A filter is only returned if two different nodes served it.
+    #[serde(default)]
+    pub compact_filters: bool,
+    /// The directory where the headers and the cached blocks are persisted, so that they
+    /// don't need to be synced again after a restart. If not set, they are only kept in memory.
+    #[serde(default)]
+    pub cache_dir: Option<PathBuf>,
 }
 
 /// Set the default idle seconds to one hour.
@@ -94,6 +103,8 @@ impl Default for Config {
             logger: LoggerConfig::default(),
             incoming_source: Default::default(),
             address_limits: address_limits(Network::Bitcoin), // Address limits used for Bitcoin mainnet
+            compact_filters: false,
+            cache_dir: None,
         }
     }
 }
@@ -136,6 +147,16 @@ pub mod test {
             self
         }
 
+        pub fn with_compact_filters(mut self, compact_filters: bool) -> Self {
+            self.config.compact_filters = compact_filters;
+            self
+        }
+
+        pub fn with_cache_dir(mut self, cache_dir: PathBuf) -> Self {
+            self.config.cache_dir = Some(cache_dir);
+            self
+        }
+
         pub fn build(self) -> Config {
             self.config
         }
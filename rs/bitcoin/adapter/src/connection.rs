@@ -1,5 +1,5 @@
 use crate::addressbook::AddressEntry;
-use bitcoin::p2p::message::NetworkMessage;
+use bitcoin::p2p::{message::NetworkMessage, ServiceFlags};
 use std::time::{Duration, SystemTime};
 use thiserror::Error;
 use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
@@ -92,6 +92,8 @@ pub struct Connection {
     writer: UnboundedSender<NetworkMessage>,
     /// This field is used to track the current ping status.
     ping_state: PingState,
+    /// This field contains the services advertised by the BTC node in its `version` message.
+    services: ServiceFlags,
 }
 
 impl Connection {
@@ -114,6 +116,7 @@ impl Connection {
             ping_state: PingState::Idle {
                 last_pong_at: timestamp,
             },
+            services: ServiceFlags::NONE,
         }
     }
 
@@ -141,6 +144,17 @@ impl Connection {
         &self.state
     }
 
+    /// This function is used to get the services advertised by the BTC node.
+    pub fn services(&self) -> ServiceFlags {
+        self.services
+    }
+
+    /// This function is used to record the services the BTC node advertised
+    /// in its `version` message.
+    pub fn set_services(&mut self, services: ServiceFlags) {
+        self.services = services;
+    }
+
     /// This function is used to get the current ping state of the connection.
     pub fn ping_state(&self) -> &PingState {
         &self.ping_state
@@ -276,6 +290,7 @@ mod test {
                 state,
                 writer,
                 ping_state: PingState::Idle { last_pong_at },
+                services: ServiceFlags::NONE,
             }
         }
     }
@@ -432,6 +432,7 @@ impl ConnectionManager {
         let conn = self
             .get_connection(address)
             .map_err(|_| ProcessBitcoinNetworkMessageError::InvalidMessage)?;
+        conn.set_services(message.services);
         if !conn.is_seed() && !self.validate_received_version(message) {
             warn!(
                 self.logger,
@@ -617,6 +618,12 @@ impl Channel for ConnectionManager {
             .collect()
     }
 
+    fn has_services(&self, addr: &SocketAddr, services: ServiceFlags) -> bool {
+        self.connections
+            .get(addr)
+            .is_some_and(|conn| conn.services().has(services))
+    }
+
     fn discard(&mut self, addr: &SocketAddr) {
         self.internal_discard(addr);
     }
@@ -10,8 +10,11 @@ use tokio::sync::mpsc::Sender;
 use tonic::Status;
 
 use crate::{
-    blockchainstate::SerializedBlock, common::BlockHeight, config::Config,
-    metrics::GetSuccessorMetrics, BlockchainManagerRequest, BlockchainState,
+    blockchainstate::{SerializedBlock, SerializedFilter},
+    common::BlockHeight,
+    config::Config,
+    metrics::GetSuccessorMetrics,
+    BlockchainManagerRequest, BlockchainState,
 };
 
 // Max size of the `GetSuccessorsResponse` message.
@@ -77,6 +80,9 @@ pub struct GetSuccessorsResponse {
     pub blocks: Vec<Arc<SerializedBlock>>,
     /// Next set of headers to be sent to the canister.
     pub next: Vec<BlockHeader>,
+    /// BIP-158 basic filters of the blocks, in the same order as `blocks`.
+    /// Empty if compact filters are disabled.
+    pub filters: Vec<Arc<SerializedFilter>>,
 }
 /// Contains the functionality to respond to GetSuccessorsRequests via the RPC
 /// server.
@@ -118,7 +124,7 @@ impl GetSuccessorsHandler {
             .processed_block_hashes
             .observe(request.processed_block_hashes.len() as f64);
 
-        let (blocks, next, obsolete_blocks) = {
+        let (blocks, filters, next, obsolete_blocks) = {
             let state = self.state.lock().unwrap();
             let anchor_height = state
                 .get_cached_header(&request.anchor)
@@ -132,6 +138,14 @@ impl GetSuccessorsHandler {
                 allow_multiple_blocks,
                 self.network,
             );
+            let filters = if state.compact_filters_enabled() {
+                blocks
+                    .iter()
+                    .map(|(hash, _)| state.get_filter(hash).unwrap_or_default())
+                    .collect()
+            } else {
+                vec![]
+            };
             let next = get_next_headers(
                 &state,
                 &request.anchor,
@@ -149,12 +163,13 @@ impl GetSuccessorsHandler {
             if blocks.is_empty() && state.is_block_cache_full() {
                 obsolete_blocks.extend(state.get_cached_blocks())
             }
-            (blocks, next, obsolete_blocks)
+            (blocks, filters, next, obsolete_blocks)
         };
         let response_next = &next[..next.len().min(MAX_NEXT_BLOCK_HEADERS_LENGTH)];
         let response = GetSuccessorsResponse {
             blocks: blocks.into_iter().map(|(_, block)| block).collect(),
             next: response_next.to_vec(),
+            filters,
         };
         self.metrics
             .response_blocks
@@ -220,7 +235,17 @@ fn get_successor_blocks(
                 // We don't want to return orphaned blocks to the canister.
                 continue;
             };
-            let block_size = block.len();
+            // With compact filters enabled, a block is only returned along with its filter,
+            // which is treated as a part of the block.
+            let filter_size = if state.compact_filters_enabled() {
+                let Some(filter) = state.get_filter(block_hash) else {
+                    continue;
+                };
+                filter.len()
+            } else {
+                0
+            };
+            let block_size = block.len() + filter_size;
             // If we have at least one block in the response, and we can't fit another block, we stop.
             if response_block_size > 0
                 && (response_block_size + block_size > max_blocks_size
@@ -302,6 +327,7 @@ mod test {
     use std::sync::{Arc, Mutex};
 
     use bitcoin::{consensus::Decodable, Block, Network};
+    use ic_logger::replica_logger::no_op_logger;
     use ic_metrics::MetricsRegistry;
     use tokio::sync::mpsc::channel;
 
@@ -319,7 +345,8 @@ mod test {
     #[tokio::test]
     async fn test_get_successors() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let blockchain_state =
+            BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let genesis = *blockchain_state.genesis();
         let genesis_hash = genesis.block_hash();
         let (blockchain_manager_tx, _blockchain_manager_rx) =
@@ -428,7 +455,8 @@ mod test {
     #[tokio::test]
     async fn test_get_successors_wait_header_sync_regtest() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let blockchain_state =
+            BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let genesis = *blockchain_state.genesis();
         let genesis_hash = genesis.block_hash();
         let (blockchain_manager_tx, _blockchain_manager_rx) =
@@ -472,12 +500,69 @@ mod test {
         assert_eq!(response.next.len(), 3);
     }
 
+    /// This tests ensures that with compact filters enabled, blocks are only returned
+    /// once their filters are available.
+    #[tokio::test]
+    async fn test_get_successors_with_compact_filters() {
+        let config = ConfigBuilder::new()
+            .with_network(Network::Regtest)
+            .with_compact_filters(true)
+            .build();
+        let blockchain_state =
+            BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
+        let genesis = *blockchain_state.genesis();
+        let genesis_hash = genesis.block_hash();
+        let (blockchain_manager_tx, _blockchain_manager_rx) =
+            channel::<BlockchainManagerRequest>(10);
+        let handler = GetSuccessorsHandler::new(
+            &config,
+            Arc::new(Mutex::new(blockchain_state)),
+            blockchain_manager_tx,
+            &MetricsRegistry::default(),
+        );
+
+        // Set up the following chain:
+        // 0 -> 1 -> 2 -> 3
+        let main_chain = generate_headers(genesis_hash, genesis.time, 3, &[]);
+        let main_blocks = main_chain
+            .iter()
+            .map(|header| Block {
+                header: *header,
+                txdata: vec![],
+            })
+            .collect::<Vec<_>>();
+        // Blocks without transactions have empty filters.
+        let filter_1 = vec![0];
+        {
+            let mut blockchain = handler.state.lock().unwrap();
+            blockchain.add_headers(&main_chain);
+            for block in &main_blocks {
+                blockchain.add_block(block.clone()).expect("invalid block");
+            }
+            blockchain
+                .add_filter(main_blocks[0].block_hash(), filter_1.clone())
+                .expect("invalid filter");
+            blockchain.mark_filter_unavailable(main_blocks[1].block_hash());
+        }
+        let request = GetSuccessorsRequest {
+            anchor: genesis_hash,
+            processed_block_hashes: vec![],
+        };
+        let response = handler.get_successors(request).await.unwrap();
+
+        // Block 3 is held back until its filter is available.
+        assert_eq!(response.blocks.len(), 2);
+        assert_eq!(response.filters, vec![Arc::new(filter_1), Arc::new(vec![])]);
+        assert_eq!(response.next, vec![main_chain[2]]);
+    }
+
     /// This tests ensures that `BlockchainManager::handle_client_request(...)` returns multiple
     /// blocks from the main chain and a fork. Order should be preserved.
     #[tokio::test]
     async fn test_get_successors_multiple_blocks() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let blockchain_state =
+            BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let genesis = *blockchain_state.genesis();
         let genesis_hash = genesis.block_hash();
         let (blockchain_manager_tx, _blockchain_manager_rx) =
@@ -549,7 +634,8 @@ mod test {
     #[tokio::test]
     async fn test_get_successors_max_num_blocks() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let blockchain_state =
+            BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let genesis = *blockchain_state.genesis();
         let genesis_hash = genesis.block_hash();
         let (blockchain_manager_tx, _blockchain_manager_rx) =
@@ -586,7 +672,8 @@ mod test {
     #[tokio::test]
     async fn test_get_successors_multiple_blocks_out_of_order() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let blockchain_state =
+            BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let genesis = *blockchain_state.genesis();
         let genesis_hash = genesis.block_hash();
         let (blockchain_manager_tx, _blockchain_manager_rx) =
@@ -679,7 +766,8 @@ mod test {
     #[tokio::test]
     async fn test_get_successors_large_block() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let blockchain_state =
+            BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let genesis = *blockchain_state.genesis();
         let genesis_hash = genesis.block_hash();
         let (blockchain_manager_tx, _blockchain_manager_rx) =
@@ -742,7 +830,8 @@ mod test {
     #[tokio::test]
     async fn test_get_successors_many_blocks_until_size_cap_is_met() {
         let config = ConfigBuilder::new().with_network(Network::Regtest).build();
-        let blockchain_state = BlockchainState::new(&config, &MetricsRegistry::default());
+        let blockchain_state =
+            BlockchainState::new(&config, &MetricsRegistry::default(), no_op_logger());
         let genesis = *blockchain_state.genesis();
         let genesis_hash = genesis.block_hash();
         let (blockchain_manager_tx, _blockchain_manager_rx) =
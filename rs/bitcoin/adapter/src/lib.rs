@@ -4,7 +4,7 @@
 //! and publish transactions. Moreover, it interacts with the Bitcoin system
 //! component to provide blocks and collect outgoing transactions.
 
-use bitcoin::p2p::message::NetworkMessage;
+use bitcoin::p2p::{message::NetworkMessage, ServiceFlags};
 use bitcoin::{block::Header as BlockHeader, BlockHash};
 use ic_logger::ReplicaLogger;
 use ic_metrics::MetricsRegistry;
@@ -37,6 +37,9 @@ mod connection;
 /// BTC nodes.
 mod connectionmanager;
 mod metrics;
+/// This module contains the on-disk cache of the headers and blocks, which is used to
+/// restore the state of the Bitcoin ledger when the adapter restarts.
+mod persistent_cache;
 /// The module is responsible for awaiting messages from bitcoin peers and dispaching them
 /// to the correct component.
 mod router;
@@ -92,6 +95,10 @@ trait Channel {
     /// that have completed the version handshake.
     fn available_connections(&self) -> Vec<SocketAddr>;
 
+    /// This method is used to check whether the peer advertised the given services
+    /// in its `version` message.
+    fn has_services(&self, addr: &SocketAddr, services: ServiceFlags) -> bool;
+
     /// Used to disconnect from nodes that are misbehaving.
     fn discard(&mut self, addr: &SocketAddr);
 }
@@ -209,7 +216,11 @@ pub fn start_server(
     let (adapter_state, tx) = AdapterState::new(config.idle_seconds);
 
     let (blockchain_manager_tx, blockchain_manager_rx) = channel(100);
-    let blockchain_state = Arc::new(Mutex::new(BlockchainState::new(&config, metrics_registry)));
+    let blockchain_state = Arc::new(Mutex::new(BlockchainState::new(
+        &config,
+        metrics_registry,
+        log.clone(),
+    )));
     let (transaction_manager_tx, transaction_manager_rx) = channel(100);
 
     start_grpc_server(
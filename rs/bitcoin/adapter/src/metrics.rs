@@ -94,8 +94,10 @@ pub struct BlockchainStateMetrics {
     pub tip_height: IntGauge,
     pub block_cache_size: IntGauge,
     pub block_cache_elements: IntGauge,
+    pub filter_cache_elements: IntGauge,
     pub header_cache_size: IntGauge,
     pub tips: IntGauge,
+    pub persistent_cache_errors: IntCounter,
 }
 
 impl BlockchainStateMetrics {
@@ -108,11 +110,19 @@ impl BlockchainStateMetrics {
                 "block_cache_elements",
                 "Number of blocks currently stored in the block cache.",
             ),
+            filter_cache_elements: metrics_registry.int_gauge(
+                "filter_cache_elements",
+                "Number of compact block filters currently stored in the filter cache.",
+            ),
             header_cache_size: metrics_registry.int_gauge(
                 "header_cache_size",
                 "Number of headers stored in the adapter.",
             ),
             tips: metrics_registry.int_gauge("blockchain_tips", "Number of active tips."),
+            persistent_cache_errors: metrics_registry.int_counter(
+                "persistent_cache_errors_total",
+                "Number of errors while writing to the persistent cache, after which it is disabled.",
+            ),
         }
     }
 }
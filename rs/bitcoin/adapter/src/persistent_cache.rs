@@ -0,0 +1,482 @@
+//! The module is responsible for persisting the headers and the cached blocks of the
+//! [`BlockchainState`](crate::blockchainstate::BlockchainState) on disk, so that they don't need
+//! to be synced again from the Bitcoin network after the adapter restarts.
+//!
+//! The headers are stored in an append-only log of fixed-size records, each consisting of the
+//! consensus-encoded header followed by a checksum. A record that has only been partially written,
+//! e.g. because the adapter crashed, is detected when the cache is opened and the log is truncated
+//! to the last valid record. Every block is stored in its own file, which is first written to a
+//! temporary file and then atomically renamed, so a block file is either complete or missing.
+//! The log can be rewritten to drop the stale headers, see [`PersistentCache::compact_headers`].
+//!
+//! The writes, which are synced to disk, are applied by the [`PersistentCacheWriter`] on a
+//! dedicated thread, so that they don't block the callers.
+use crate::blockchainstate::SerializedBlock;
+use bitcoin::{
+    block::Header as BlockHeader,
+    consensus::{deserialize, serialize},
+    hashes::{sha256d, Hash},
+    Block, BlockHash,
+};
+use ic_logger::{error, ReplicaLogger};
+use prometheus::IntCounter;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        mpsc::{self, Sender},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+};
+
+const HEADERS_FILE_NAME: &str = "headers.log";
+const BLOCKS_DIR_NAME: &str = "blocks";
+const TMP_FILE_EXTENSION: &str = "tmp";
+
+const HEADER_SIZE: usize = 80;
+const CHECKSUM_SIZE: usize = 4;
+const RECORD_SIZE: usize = HEADER_SIZE + CHECKSUM_SIZE;
+
+/// The contents of the cache at the time it was opened.
+#[derive(Debug, Default)]
+pub struct CachedData {
+    /// The headers in the order they were appended.
+    pub headers: Vec<BlockHeader>,
+    /// The stored blocks along with their hashes.
+    pub blocks: Vec<(BlockHash, SerializedBlock)>,
+}
+
+/// A cache of the headers and blocks stored in a directory on disk.
+#[derive(Debug)]
+pub struct PersistentCache {
+    dir: PathBuf,
+    blocks_dir: PathBuf,
+    headers: BufWriter<File>,
+}
+
+/// Returns the first bytes of the double SHA-256 hash of the given data.
+fn checksum(data: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let [a, b, c, d, ..] = sha256d::Hash::hash(data).to_byte_array();
+    [a, b, c, d]
+}
+
+impl PersistentCache {
+    /// Opens the cache stored in the given directory, creating the directory if it doesn't exist.
+    /// Returns the cache along with its current contents.
+    pub fn open(dir: &Path) -> io::Result<(Self, CachedData)> {
+        let blocks_dir = dir.join(BLOCKS_DIR_NAME);
+        fs::create_dir_all(&blocks_dir)?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(HEADERS_FILE_NAME))?;
+        let (headers, valid_len) = read_headers(&mut file)?;
+        // Drop the partially written record at the end of the log, if any.
+        if file.metadata()?.len() != valid_len {
+            file.set_len(valid_len)?;
+            file.sync_data()?;
+        }
+        file.seek(SeekFrom::Start(valid_len))?;
+
+        let blocks = read_blocks(&blocks_dir)?;
+
+        Ok((
+            Self {
+                dir: dir.to_path_buf(),
+                blocks_dir,
+                headers: BufWriter::new(file),
+            },
+            CachedData { headers, blocks },
+        ))
+    }
+
+    /// Appends the header to the log. The header is only guaranteed to be persisted
+    /// after [`PersistentCache::sync`] is called.
+    pub fn append_header(&mut self, header: &BlockHeader) -> io::Result<()> {
+        self.headers.write_all(&record(header))
+    }
+
+    /// Persists the headers appended so far.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.headers.flush()?;
+        self.headers.get_ref().sync_data()
+    }
+
+    /// Replaces the log with one containing only the given headers, e.g. to drop the headers of
+    /// stale forks and the headers that are no longer valid. The new log is written to a temporary
+    /// file that atomically replaces the log, so the log is either the old or the new one.
+    pub fn compact_headers(&mut self, headers: &[BlockHeader]) -> io::Result<()> {
+        let path = self.dir.join(HEADERS_FILE_NAME);
+        let tmp_path = path.with_extension(TMP_FILE_EXTENSION);
+
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        for header in headers {
+            writer.write_all(&record(header))?;
+        }
+        let file = writer.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        File::open(&self.dir)?.sync_all()?;
+
+        let mut file = OpenOptions::new().read(true).write(true).open(&path)?;
+        file.seek(SeekFrom::End(0))?;
+        self.headers = BufWriter::new(file);
+        Ok(())
+    }
+
+    /// Stores the block, replacing the existing one with the same hash.
+    pub fn store_block(&self, block_hash: &BlockHash, block: &[u8]) -> io::Result<()> {
+        let path = self.block_path(block_hash);
+        let tmp_path = path.with_extension(TMP_FILE_EXTENSION);
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(block)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+        // Make sure that the rename itself is persisted.
+        File::open(&self.blocks_dir)?.sync_all()
+    }
+
+    /// Removes the block with the given hash, if it is stored.
+    pub fn remove_block(&self, block_hash: &BlockHash) -> io::Result<()> {
+        match fs::remove_file(self.block_path(block_hash)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// Removes all the stored blocks.
+    pub fn clear_blocks(&self) -> io::Result<()> {
+        for entry in fs::read_dir(&self.blocks_dir)? {
+            fs::remove_file(entry?.path())?;
+        }
+        Ok(())
+    }
+
+    fn block_path(&self, block_hash: &BlockHash) -> PathBuf {
+        self.blocks_dir.join(block_hash.to_string())
+    }
+}
+
+/// Returns the record of the given header in the log.
+fn record(header: &BlockHeader) -> Vec<u8> {
+    let mut record = serialize(header);
+    let checksum = checksum(&record);
+    record.extend_from_slice(&checksum);
+    record
+}
+
+/// A write to the [`PersistentCache`].
+#[derive(Debug)]
+pub enum CacheWrite {
+    /// Appends the headers to the log and syncs it.
+    AppendHeaders(Vec<BlockHeader>),
+    StoreBlock(BlockHash, Arc<SerializedBlock>),
+    RemoveBlock(BlockHash),
+    ClearBlocks,
+}
+
+/// Applies the writes to a [`PersistentCache`] on a dedicated thread in the order they are sent.
+/// After a failed write, the following writes are dropped, so that the log is never missing
+/// headers in the middle. Dropping the writer waits until the pending writes are applied.
+#[derive(Debug)]
+pub struct PersistentCacheWriter {
+    sender: Option<Sender<CacheWrite>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl PersistentCacheWriter {
+    pub fn new(
+        mut cache: PersistentCache,
+        logger: ReplicaLogger,
+        errors: IntCounter,
+    ) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::channel::<CacheWrite>();
+        let handle = thread::Builder::new()
+            .name("btc_adapter_persistent_cache".to_string())
+            .spawn(move || {
+                for write in receiver {
+                    let result = match write {
+                        CacheWrite::AppendHeaders(headers) => headers
+                            .iter()
+                            .try_for_each(|header| cache.append_header(header))
+                            .and_then(|()| cache.sync()),
+                        CacheWrite::StoreBlock(block_hash, block) => {
+                            cache.store_block(&block_hash, &block)
+                        }
+                        CacheWrite::RemoveBlock(block_hash) => cache.remove_block(&block_hash),
+                        CacheWrite::ClearBlocks => cache.clear_blocks(),
+                    };
+                    if let Err(err) = result {
+                        error!(
+                            logger,
+                            "Failed to write to the persistent cache, disabling it: {}", err
+                        );
+                        errors.inc();
+                        return;
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            sender: Some(sender),
+            handle: Some(handle),
+        })
+    }
+
+    /// Sends the write to the writer thread. Returns `false` if the writer has been disabled.
+    pub fn send(&self, write: CacheWrite) -> bool {
+        self.sender
+            .as_ref()
+            .is_some_and(|sender| sender.send(write).is_ok())
+    }
+}
+
+impl Drop for PersistentCacheWriter {
+    fn drop(&mut self) {
+        // Closing the channel stops the writer thread after the pending writes.
+        self.sender.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Reads the headers from the log until the end of the file or the first invalid record.
+/// Returns the headers along with the length of the valid part of the log.
+fn read_headers(file: &mut File) -> io::Result<(Vec<BlockHeader>, u64)> {
+    let mut reader = BufReader::new(file);
+    let mut headers = vec![];
+    let mut record = [0u8; RECORD_SIZE];
+    loop {
+        match reader.read_exact(&mut record) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        }
+
+        let (header, expected_checksum) = record.split_at(HEADER_SIZE);
+        if checksum(header) != expected_checksum {
+            break;
+        }
+        match deserialize::<BlockHeader>(header) {
+            Ok(header) => headers.push(header),
+            Err(_) => break,
+        }
+    }
+    let valid_len = (headers.len() * RECORD_SIZE) as u64;
+    Ok((headers, valid_len))
+}
+
+/// Reads the stored blocks. Files that don't contain a valid block with the hash they are named
+/// after are left over from an interrupted write or corrupted, and are removed.
+fn read_blocks(blocks_dir: &Path) -> io::Result<Vec<(BlockHash, SerializedBlock)>> {
+    let mut blocks = vec![];
+    for entry in fs::read_dir(blocks_dir)? {
+        let path = entry?.path();
+        let block_hash = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| BlockHash::from_str(name).ok());
+        let block = fs::read(&path)?;
+
+        match block_hash {
+            Some(block_hash)
+                if deserialize::<Block>(&block).is_ok_and(|block| {
+                    block.block_hash() == block_hash
+                        && (block.compute_merkle_root().is_none() || block.check_merkle_root())
+                }) =>
+            {
+                blocks.push((block_hash, block));
+            }
+            _ => fs::remove_file(&path)?,
+        }
+    }
+    Ok(blocks)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::common::test_common::TestState;
+    use ic_btc_adapter_test_utils::generate_headers;
+    use ic_logger::replica_logger::no_op_logger;
+    use tempfile::tempdir;
+
+    /// Tests that the headers and blocks survive reopening the cache.
+    #[test]
+    fn test_reopen() {
+        let dir = tempdir().unwrap();
+        let test_state = TestState::setup();
+        let headers = generate_headers(BlockHash::all_zeros(), 0, 5, &[]);
+        let block_1 = serialize(&test_state.block_1);
+        let block_2 = serialize(&test_state.block_2);
+
+        {
+            let (mut cache, data) = PersistentCache::open(dir.path()).unwrap();
+            assert!(data.headers.is_empty());
+            assert!(data.blocks.is_empty());
+
+            for header in &headers {
+                cache.append_header(header).unwrap();
+            }
+            cache.sync().unwrap();
+            cache
+                .store_block(&test_state.block_1.block_hash(), &block_1)
+                .unwrap();
+            cache
+                .store_block(&test_state.block_2.block_hash(), &block_2)
+                .unwrap();
+            cache
+                .remove_block(&test_state.block_2.block_hash())
+                .unwrap();
+            // Removing a block that isn't stored is fine.
+            cache
+                .remove_block(&test_state.block_2.block_hash())
+                .unwrap();
+        }
+
+        let (mut cache, data) = PersistentCache::open(dir.path()).unwrap();
+        assert_eq!(data.headers, headers);
+        assert_eq!(
+            data.blocks,
+            vec![(test_state.block_1.block_hash(), block_1)]
+        );
+
+        cache.clear_blocks().unwrap();
+        let more_headers = generate_headers(headers[4].block_hash(), headers[4].time, 2, &[]);
+        for header in &more_headers {
+            cache.append_header(header).unwrap();
+        }
+        cache.sync().unwrap();
+        drop(cache);
+
+        let (_, data) = PersistentCache::open(dir.path()).unwrap();
+        assert_eq!(data.headers, [headers, more_headers].concat());
+        assert!(data.blocks.is_empty());
+    }
+
+    /// Tests that the compacted log only contains the given headers and can be appended to.
+    #[test]
+    fn test_compact_headers() {
+        let dir = tempdir().unwrap();
+        let headers = generate_headers(BlockHash::all_zeros(), 0, 5, &[]);
+        let hashes: Vec<_> = headers.iter().map(|header| header.block_hash()).collect();
+        let fork = generate_headers(headers[1].block_hash(), headers[1].time, 3, &hashes);
+
+        let (mut cache, _) = PersistentCache::open(dir.path()).unwrap();
+        for header in headers.iter().chain(fork.iter()) {
+            cache.append_header(header).unwrap();
+        }
+        cache.sync().unwrap();
+
+        cache.compact_headers(&headers[..4]).unwrap();
+        cache.append_header(&headers[4]).unwrap();
+        cache.sync().unwrap();
+        drop(cache);
+
+        let (_, data) = PersistentCache::open(dir.path()).unwrap();
+        assert_eq!(data.headers, headers);
+        // The temporary file has been renamed.
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    /// Tests that the writes are applied in order by the writer and that
+    /// the writer is disabled after a failed write.
+    #[test]
+    fn test_writer() {
+        let dir = tempdir().unwrap();
+        let test_state = TestState::setup();
+        let headers = generate_headers(BlockHash::all_zeros(), 0, 3, &[]);
+        let block_1 = Arc::new(serialize(&test_state.block_1));
+        let errors = IntCounter::new("errors", "errors").unwrap();
+
+        let (cache, _) = PersistentCache::open(dir.path()).unwrap();
+        let writer = PersistentCacheWriter::new(cache, no_op_logger(), errors.clone()).unwrap();
+        assert!(writer.send(CacheWrite::AppendHeaders(headers.clone())));
+        assert!(writer.send(CacheWrite::StoreBlock(
+            test_state.block_1.block_hash(),
+            block_1.clone()
+        )));
+        drop(writer);
+
+        let (cache, data) = PersistentCache::open(dir.path()).unwrap();
+        assert_eq!(data.headers, headers);
+        assert_eq!(
+            data.blocks,
+            vec![(test_state.block_1.block_hash(), block_1.to_vec())]
+        );
+
+        let writer = PersistentCacheWriter::new(cache, no_op_logger(), errors.clone()).unwrap();
+        fs::remove_dir_all(dir.path().join(BLOCKS_DIR_NAME)).unwrap();
+        assert!(writer.send(CacheWrite::StoreBlock(
+            test_state.block_2.block_hash(),
+            block_1
+        )));
+        // Wait until the writer thread has stopped.
+        while writer.send(CacheWrite::ClearBlocks) {
+            thread::yield_now();
+        }
+        assert_eq!(errors.get(), 1);
+    }
+
+    /// Tests that partially written headers and blocks are dropped when the cache is opened.
+    #[test]
+    fn test_recovery_from_partial_writes() {
+        let dir = tempdir().unwrap();
+        let test_state = TestState::setup();
+        let headers = generate_headers(BlockHash::all_zeros(), 0, 3, &[]);
+        let block_1 = serialize(&test_state.block_1);
+
+        {
+            let (mut cache, _) = PersistentCache::open(dir.path()).unwrap();
+            for header in &headers {
+                cache.append_header(header).unwrap();
+            }
+            cache.sync().unwrap();
+        }
+
+        // Simulate a crash while the last header was being written.
+        let headers_path = dir.path().join(HEADERS_FILE_NAME);
+        let file = OpenOptions::new().write(true).open(&headers_path).unwrap();
+        file.set_len((3 * RECORD_SIZE - 1) as u64).unwrap();
+        drop(file);
+        // Simulate a crash while a block was being written.
+        let blocks_dir = dir.path().join(BLOCKS_DIR_NAME);
+        let block_path = blocks_dir.join(test_state.block_1.block_hash().to_string());
+        fs::write(
+            block_path.with_extension(TMP_FILE_EXTENSION),
+            &block_1[..block_1.len() / 2],
+        )
+        .unwrap();
+        fs::write(&block_path, &block_1[..block_1.len() / 2]).unwrap();
+
+        let (mut cache, data) = PersistentCache::open(dir.path()).unwrap();
+        assert_eq!(data.headers, headers[..2]);
+        assert!(data.blocks.is_empty());
+        assert_eq!(fs::read_dir(&blocks_dir).unwrap().count(), 0);
+        assert_eq!(
+            fs::metadata(&headers_path).unwrap().len(),
+            (2 * RECORD_SIZE) as u64
+        );
+
+        // New headers are appended after the last valid one.
+        cache.append_header(&headers[2]).unwrap();
+        cache.sync().unwrap();
+        drop(cache);
+
+        // A corrupted record invalidates the rest of the log.
+        let mut log = fs::read(&headers_path).unwrap();
+        log[RECORD_SIZE + 1] ^= 0xff;
+        fs::write(&headers_path, log).unwrap();
+
+        let (_, data) = PersistentCache::open(dir.path()).unwrap();
+        assert_eq!(data.headers, headers[..1]);
+    }
+}
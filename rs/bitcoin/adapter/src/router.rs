@@ -49,13 +49,19 @@ pub fn start_main_event_loop(
 
     tokio::task::spawn(async move {
         let mut tick_interval = interval(Duration::from_millis(100));
+        // The managers start out idle, so they only need to be made idle after having been active.
+        // This keeps the blocks restored from the persistent cache until the first request.
+        let mut was_active = false;
 
         loop {
             if adapter_state.is_idle() {
-                connection_manager.make_idle();
-                blockchain_manager.make_idle();
+                if was_active {
+                    connection_manager.make_idle();
+                    blockchain_manager.make_idle();
+                }
                 adapter_state.active().await;
             }
+            was_active = true;
 
             // We do a select over tokio::sync::mpsc::Receiver::recv, tokio::sync::mpsc::UnboundedReceiver::recv,
             // tokio::time::Interval::tick which are all cancellation safe.
@@ -69,7 +69,17 @@ impl TryFrom<GetSuccessorsResponse> for BtcServiceGetSuccessorsResponse {
                 .map_err(|_| Status::unknown("Failed to encode block header!"))?;
             next.push(encoded_block_header);
         }
-        Ok(BtcServiceGetSuccessorsResponse { blocks, next })
+        let filters = response
+            .filters
+            .into_iter()
+            .map(Arc::unwrap_or_clone)
+            .collect();
+
+        Ok(BtcServiceGetSuccessorsResponse {
+            blocks,
+            next,
+            filters,
+        })
     }
 }
 
@@ -37,6 +37,33 @@ pub enum BitcoinAddress {
     P2sh([u8; 20]),
 }
 
+/// The type of the deposit address derived for an account.
+#[derive(
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Debug,
+    Default,
+    Deserialize,
+    Serialize,
+    candid::CandidType,
+)]
+pub enum DepositAddressType {
+    /// Pay to witness public key hash address derived from the tECDSA key.
+    /// See BIP-173.
+    #[default]
+    #[serde(rename = "p2wpkh_v0")]
+    P2wpkhV0,
+    /// Pay to taproot address with a key path only output derived from the
+    /// threshold BIP-340 key.
+    /// See BIP-341.
+    #[serde(rename = "p2tr_v1")]
+    P2trV1,
+}
+
 #[derive(Copy, Clone, Eq, PartialEq)]
 enum WitnessVersion {
     V0 = 0,
@@ -148,6 +175,30 @@ pub fn account_to_bitcoin_address(
     BitcoinAddress::P2wpkhV0(crate::tx::hash160(&pk))
 }
 
+/// Constructs the P2TR address corresponding to the specified account.
+///
+/// The address commits to the tweaked public key of the account without a
+/// script path, so the deposited funds can only be spent with a BIP-340
+/// signature, see BIP-86.
+pub fn account_to_p2tr_address(
+    bip340_public_key: &ECDSAPublicKey,
+    account: &Account,
+) -> BitcoinAddress {
+    use ic_secp256k1::PublicKey;
+
+    let pk = derive_public_key(bip340_public_key, account).public_key;
+    let output_key = PublicKey::deserialize_sec1(&pk)
+        .expect("BUG: failed to parse a derived public key")
+        .derive_bip341(&[])
+        .expect("BUG: failed to tweak a derived public key");
+    BitcoinAddress::P2trV1(
+        output_key
+            .serialize_bip340()
+            .try_into()
+            .expect("BUG: BIP-340 public keys are 32 bytes long"),
+    )
+}
+
 fn encode_bech32(network: Network, hash: &[u8], version: WitnessVersion) -> String {
     use bech32::u5;
 
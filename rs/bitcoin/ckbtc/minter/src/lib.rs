@@ -1,4 +1,4 @@
-use crate::address::BitcoinAddress;
+use crate::address::{BitcoinAddress, DepositAddressType};
 use crate::logs::{P0, P1};
 use crate::management::CallError;
 use crate::queries::WithdrawalFee;
@@ -75,6 +75,27 @@ pub const CKBTC_LEDGER_MEMO_SIZE: u16 = 80;
 /// when building transactions.
 pub const UTXOS_COUNT_THRESHOLD: usize = 1_000;
 
+/// The fee rate at which the minter expects to spend its UTXOs in the long run.
+/// If the current fee rate is lower, spending more inputs now is cheaper than
+/// spending them later, so the UTXO selection favours more inputs.
+pub const LONG_TERM_FEE_PER_VBYTE: MillisatoshiPerByte = 10_000;
+
+/// The maximum fee rate at which the minter consolidates its UTXOs.
+pub const MAX_CONSOLIDATION_FEE_PER_VBYTE: MillisatoshiPerByte = 5_000;
+
+/// The maximum number of UTXOs merged by a single consolidation transaction.
+pub const MAX_CONSOLIDATION_INPUTS: usize = 50;
+
+/// Having a sequence number lower than (0xffffffff - 1) signals the use of replacement by fee.
+/// It allows us to increase the fee of a transaction already sent to the mempool.
+/// The rbf option is used in `resubmit_retrieve_btc`.
+/// https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki
+const SEQUENCE_RBF_ENABLED: u32 = 0xfffffffd;
+
+/// The size of a P2WPKH transaction input and output, see [tx_vsize_estimate].
+const INPUT_SIZE_VBYTES: u64 = 68;
+const OUTPUT_SIZE_VBYTES: u64 = 31;
+
 pub const IC_CANISTER_RUNTIME: IcCanisterRuntime = IcCanisterRuntime {};
 
 #[derive(Clone, Debug, Deserialize, serde::Serialize)]
@@ -119,7 +140,7 @@ struct SignTxRequest {
     ecdsa_public_key: ECDSAPublicKey,
     unsigned_tx: tx::UnsignedTransaction,
     change_output: state::ChangeOutput,
-    outpoint_account: BTreeMap<OutPoint, Account>,
+    outpoint_account: BTreeMap<OutPoint, (Account, DepositAddressType)>,
     /// The original requests that we keep around to place back to the queue
     /// if the signature fails.
     requests: Vec<state::RetrieveBtcRequest>,
@@ -434,6 +455,152 @@ async fn submit_pending_requests() {
     }
 }
 
+/// Merges some of the minter's UTXOs into a single one if the minter manages
+/// too many UTXOs and the Bitcoin fees are low. Fewer UTXOs make withdrawals
+/// cheaper because they need fewer inputs.
+async fn consolidate_utxos() {
+    // Consolidate the UTXOs one transaction at a time so that most of them
+    // remain available for withdrawals.
+    if state::read_state(|s| {
+        s.available_utxos.len() <= UTXOS_COUNT_THRESHOLD
+            || s.submitted_transactions
+                .iter()
+                .any(|tx| tx.requests.is_empty())
+    }) {
+        return;
+    }
+
+    let fee_millisatoshi_per_vbyte = match estimate_fee_per_vbyte().await {
+        Some(fee) if fee <= MAX_CONSOLIDATION_FEE_PER_VBYTE => fee,
+        _ => return,
+    };
+
+    let main_account = Account {
+        owner: ic_cdk::id(),
+        subaccount: None,
+    };
+
+    let ecdsa_public_key = updates::get_btc_address::init_ecdsa_public_key().await;
+    let main_address = address::account_to_bitcoin_address(&ecdsa_public_key, &main_account);
+
+    let maybe_sign_request = state::mutate_state(|s| {
+        let utxos =
+            consolidation_utxos_selection(&mut s.available_utxos, fee_millisatoshi_per_vbyte);
+
+        if utxos.is_empty() {
+            return None;
+        }
+
+        let budget = s.consolidation_fee_budget();
+        match build_consolidation_transaction(&utxos, main_address, fee_millisatoshi_per_vbyte) {
+            // The minter pays the consolidation fees from the fees it charged its users.
+            Ok((_, change_output))
+                if utxos.iter().map(|u| u.value).sum::<u64>() - change_output.value > budget =>
+            {
+                log!(
+                    P1,
+                    "[consolidate_utxos]: the consolidation fee exceeds the remaining budget {}",
+                    budget
+                );
+                for utxo in utxos {
+                    assert!(s.available_utxos.insert(utxo));
+                }
+                None
+            }
+            Ok((unsigned_tx, change_output)) => Some(SignTxRequest {
+                key_name: s.ecdsa_key_name.clone(),
+                ecdsa_public_key,
+                change_output,
+                outpoint_account: filter_output_accounts(s, &unsigned_tx),
+                network: s.btc_network,
+                unsigned_tx,
+                requests: vec![],
+                utxos,
+            }),
+            Err(err) => {
+                log!(
+                    P1,
+                    "[consolidate_utxos]: failed to build a consolidation transaction: {:?}",
+                    err
+                );
+                for utxo in utxos {
+                    assert!(s.available_utxos.insert(utxo));
+                }
+                None
+            }
+        }
+    });
+
+    if let Some(req) = maybe_sign_request {
+        log!(
+            P1,
+            "[consolidate_utxos]: signing a new transaction: {}",
+            hex::encode(tx::encode_into(&req.unsigned_tx, Vec::new()))
+        );
+
+        // This guard ensures that we return the UTXOs back to the state if the
+        // signing or sending a transaction fails or panics.
+        let utxos_guard = guard(req.utxos, |utxos| {
+            undo_sign_request(vec![], utxos);
+        });
+
+        let txid = req.unsigned_tx.txid();
+
+        match sign_transaction(
+            req.key_name,
+            &req.ecdsa_public_key,
+            &req.outpoint_account,
+            req.unsigned_tx,
+        )
+        .await
+        {
+            Ok(signed_tx) => match management::send_transaction(&signed_tx, req.network).await {
+                Ok(()) => {
+                    log!(
+                        P0,
+                        "[consolidate_utxos]: sent transaction {} merging {} UTXOs",
+                        &txid,
+                        utxos_guard.len(),
+                    );
+
+                    // Defuse the guard because we sent the transaction
+                    // successfully.
+                    let used_utxos = ScopeGuard::into_inner(utxos_guard);
+
+                    state::mutate_state(|s| {
+                        state::audit::sent_consolidation_transaction(
+                            s,
+                            state::SubmittedBtcTransaction {
+                                requests: vec![],
+                                txid,
+                                used_utxos,
+                                change_output: Some(req.change_output),
+                                submitted_at: ic_cdk::api::time(),
+                                fee_per_vbyte: Some(fee_millisatoshi_per_vbyte),
+                            },
+                            &IC_CANISTER_RUNTIME,
+                        );
+                    });
+                }
+                Err(err) => {
+                    log!(
+                        P0,
+                        "[consolidate_utxos]: failed to send a Bitcoin transaction: {}",
+                        err
+                    );
+                }
+            },
+            Err(err) => {
+                log!(
+                    P0,
+                    "[consolidate_utxos]: failed to sign a Bitcoin transaction: {}",
+                    err
+                );
+            }
+        }
+    }
+}
+
 fn finalization_time_estimate(min_confirmations: u32, network: Network) -> Duration {
     Duration::from_nanos(
         min_confirmations as u64
@@ -505,7 +672,14 @@ async fn finalize_requests() {
 
     state::mutate_state(|s| {
         if !new_utxos.is_empty() {
-            state::audit::add_utxos(s, None, main_account, new_utxos, &IC_CANISTER_RUNTIME);
+            state::audit::add_utxos(
+                s,
+                None,
+                main_account,
+                DepositAddressType::P2wpkhV0,
+                new_utxos,
+                &IC_CANISTER_RUNTIME,
+            );
         }
         for txid in &confirmed_transactions {
             state::audit::confirm_transaction(s, txid, &IC_CANISTER_RUNTIME);
@@ -605,8 +779,6 @@ async fn finalize_requests() {
     let key_name = state::read_state(|s| s.ecdsa_key_name.clone());
 
     for (old_txid, submitted_tx) in maybe_finalized_transactions {
-        let mut utxos: BTreeSet<_> = submitted_tx.used_utxos.iter().cloned().collect();
-
         let tx_fee_per_vbyte = match submitted_tx.fee_per_vbyte {
             Some(prev_fee) => {
                 // Ensure that the fee is at least min relay fee higher than the previous
@@ -616,18 +788,28 @@ async fn finalize_requests() {
             None => fee_per_vbyte,
         };
 
-        let outputs = submitted_tx
-            .requests
-            .iter()
-            .map(|req| (req.address.clone(), req.amount))
-            .collect();
+        // The replacement transaction must spend the same inputs as the stuck one.
+        let maybe_tx = if submitted_tx.requests.is_empty() {
+            build_consolidation_transaction(
+                &submitted_tx.used_utxos,
+                main_address.clone(),
+                tx_fee_per_vbyte,
+            )
+        } else {
+            let outputs = submitted_tx
+                .requests
+                .iter()
+                .map(|req| (req.address.clone(), req.amount))
+                .collect();
+            build_unsigned_transaction_from_inputs(
+                &submitted_tx.used_utxos,
+                outputs,
+                main_address.clone(),
+                tx_fee_per_vbyte,
+            )
+        };
 
-        let (unsigned_tx, change_output, used_utxos) = match build_unsigned_transaction(
-            &mut utxos,
-            outputs,
-            main_address.clone(),
-            tx_fee_per_vbyte,
-        ) {
+        let (unsigned_tx, change_output) = match maybe_tx {
             Ok(tx) => tx,
             // If it's impossible to build a new transaction, the fees probably became too high.
             // Let's ignore this transaction and wait for fees to go down.
@@ -642,13 +824,26 @@ async fn finalize_requests() {
             }
         };
 
-        let outpoint_account = state::read_state(|s| filter_output_accounts(s, &unsigned_tx));
+        if submitted_tx.requests.is_empty() {
+            let old_value = submitted_tx
+                .change_output
+                .as_ref()
+                .map_or(0, |out| out.value);
+            let additional_fee = old_value.saturating_sub(change_output.value);
+            let budget = state::read_state(|s| s.consolidation_fee_budget());
+            if additional_fee > budget {
+                log!(
+                    P1,
+                    "[finalize_requests]: cannot replace stuck consolidation transaction {}: the additional fee {} exceeds the remaining budget {}",
+                    &submitted_tx.txid,
+                    additional_fee,
+                    budget
+                );
+                continue;
+            }
+        }
 
-        assert!(
-            utxos.is_empty(),
-            "build_unsigned_transaction didn't use all inputs"
-        );
-        assert_eq!(used_utxos.len(), submitted_tx.used_utxos.len());
+        let outpoint_account = state::read_state(|s| filter_output_accounts(s, &unsigned_tx));
 
         let new_txid = unsigned_tx.txid();
 
@@ -694,7 +889,7 @@ async fn finalize_requests() {
                 );
                 let new_tx = state::SubmittedBtcTransaction {
                     requests: submitted_tx.requests,
-                    used_utxos,
+                    used_utxos: submitted_tx.used_utxos,
                     txid: new_txid,
                     submitted_at: ic_cdk::api::time(),
                     change_output: Some(change_output),
@@ -717,32 +912,38 @@ async fn finalize_requests() {
     }
 }
 
-/// Builds the minimal OutPoint -> Account map required to sign a transaction.
+/// Builds the minimal OutPoint -> (Account, DepositAddressType) map required
+/// to sign a transaction.
 fn filter_output_accounts(
     state: &state::CkBtcMinterState,
     unsigned_tx: &tx::UnsignedTransaction,
-) -> BTreeMap<OutPoint, Account> {
+) -> BTreeMap<OutPoint, (Account, DepositAddressType)> {
     unsigned_tx
         .inputs
         .iter()
         .map(|input| {
             (
                 input.previous_output.clone(),
-                *state
-                    .outpoint_account
-                    .get(&input.previous_output)
-                    .unwrap_or_else(|| {
-                        panic!(
-                            "bug: missing account for output point {:?}",
-                            input.previous_output
-                        )
-                    }),
+                (
+                    *state
+                        .outpoint_account
+                        .get(&input.previous_output)
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "bug: missing account for output point {:?}",
+                                input.previous_output
+                            )
+                        }),
+                    state.deposit_address_type(&input.previous_output),
+                ),
             )
         })
         .collect()
 }
 
-/// The algorithm greedily selects the smallest UTXO(s) with a value that is at least the given `target` in a first step.
+/// The algorithm selects the UTXO(s) with a value that is at least the given `target` in a first step.
+/// It looks for the selection with the least waste (see [branch_and_bound]) and falls back to
+/// selecting the UTXOs greedily (see [greedy]) if there is no such selection.
 ///
 /// If the minter manages more than [UTXOS_COUNT_THRESHOLD], it will then try to match the number of inputs with the
 /// number of outputs + 1 (where the additional output corresponds to the change output).
@@ -756,8 +957,22 @@ fn utxos_selection(
     target: u64,
     available_utxos: &mut BTreeSet<Utxo>,
     output_count: usize,
+    fee_per_vbyte: u64,
 ) -> Vec<Utxo> {
-    let mut input_utxos = greedy(target, available_utxos);
+    let (mut input_utxos, selections) =
+        match branch_and_bound(target, available_utxos, fee_per_vbyte) {
+            Some(solution) => {
+                for utxo in solution.iter() {
+                    assert!(available_utxos.remove(utxo));
+                }
+                (solution, &metrics::UTXO_SELECTIONS_BNB)
+            }
+            None => (
+                greedy(target, available_utxos),
+                &metrics::UTXO_SELECTIONS_GREEDY,
+            ),
+        };
+    selections.with(|cell| cell.set(cell.get() + 1));
 
     if input_utxos.is_empty() {
         return vec![];
@@ -777,6 +992,144 @@ fn utxos_selection(
     input_utxos
 }
 
+/// Searches for the subset of UTXOs with the least waste whose total value is at least `target`.
+///
+/// The minter always adds a change output to its transactions (see
+/// [build_unsigned_transaction_from_inputs]), so the cost of creating the change output and of
+/// spending it later doesn't depend on the selection. The value in excess of the target isn't lost
+/// either: it goes to the change output. However, the change is unavailable until the transaction
+/// is finalized, so the algorithm keeps the excess as small as it can.
+///
+/// The waste of a selection is the value in excess of the target plus the difference between
+/// the cost of spending the selected UTXOs at the current fee rate and at [LONG_TERM_FEE_PER_VBYTE].
+/// Thus the algorithm prefers fewer inputs when the fees are high and more inputs when they are low.
+///
+/// Only UTXOs worth more than the cost of spending them are considered. The search is a
+/// depth-first traversal of the inclusion/omission tree of the UTXOs sorted by decreasing value,
+/// see <https://murch.one/erhardt2016coinselection.pdf>. It explores at most a fixed number of
+/// selections and returns `None` if the UTXOs worth spending don't add up to the target.
+///
+/// The function doesn't modify `available_utxos`.
+///
+/// POSTCONDITION: solution.is_some() ⇒ target ≤ sum(u.value for u in solution)
+fn branch_and_bound(
+    target: u64,
+    available_utxos: &BTreeSet<Utxo>,
+    fee_per_vbyte: u64,
+) -> Option<Vec<Utxo>> {
+    const MAX_TRIES: usize = 100_000;
+
+    let input_fee = INPUT_SIZE_VBYTES * fee_per_vbyte / 1000;
+    let long_term_input_fee = INPUT_SIZE_VBYTES * LONG_TERM_FEE_PER_VBYTE / 1000;
+    let input_waste = input_fee as i64 - long_term_input_fee as i64;
+
+    // Explore the largest UTXOs first.
+    let mut pool: Vec<&Utxo> = available_utxos
+        .iter()
+        .filter(|u| u.value > input_fee)
+        .collect();
+    pool.sort_by(|a, b| b.value.cmp(&a.value));
+
+    // The total value of the UTXOs that have not been explored on the current branch.
+    let mut lookahead: u64 = pool.iter().map(|u| u.value).sum();
+    if target == 0 || lookahead < target {
+        return None;
+    }
+
+    let mut selection: Vec<usize> = vec![];
+    let mut selection_value = 0;
+    let mut selection_waste = 0;
+    let mut best: Option<(Vec<usize>, i64)> = None;
+    let mut index = 0;
+
+    for _ in 0..MAX_TRIES {
+        let backtrack = if selection_value + lookahead < target
+            || (input_waste > 0
+                && best
+                    .as_ref()
+                    .is_some_and(|(_, best_waste)| selection_waste > *best_waste))
+        {
+            // The branch cannot reach the target or cannot reduce the waste because each
+            // additional input increases it.
+            true
+        } else if selection_value >= target {
+            let waste = selection_waste + (selection_value - target) as i64;
+            if best
+                .as_ref()
+                .is_none_or(|(_, best_waste)| waste <= *best_waste)
+            {
+                best = Some((selection.clone(), waste));
+            }
+            true
+        } else {
+            false
+        };
+
+        if backtrack {
+            let Some(last) = selection.pop() else {
+                // The whole tree has been explored.
+                break;
+            };
+            // Continue with the branch that omits the last selected UTXO.
+            for utxo in &pool[last + 1..index] {
+                lookahead += utxo.value;
+            }
+            selection_value -= pool[last].value;
+            selection_waste -= input_waste;
+            index = last + 1;
+        } else {
+            // The lookahead is positive, so there is a UTXO to explore.
+            let utxo = pool[index];
+            lookahead -= utxo.value;
+            // Omitting a UTXO and including another one of the same value leads to the
+            // selections that have already been explored, so skip the inclusion branch.
+            if selection.last().is_none_or(|last| last + 1 == index)
+                || pool[index - 1].value != utxo.value
+            {
+                selection.push(index);
+                selection_value += utxo.value;
+                selection_waste += input_waste;
+            }
+            index += 1;
+        }
+    }
+
+    best.map(|(selection, _)| selection.into_iter().map(|i| pool[i].clone()).collect())
+}
+
+/// Selects the UTXOs to merge in a consolidation transaction and removes them from the
+/// available set: the smallest UTXOs that are worth more than the cost of spending them,
+/// up to [MAX_CONSOLIDATION_INPUTS].
+///
+/// Returns an empty vector if the minter manages at most [UTXOS_COUNT_THRESHOLD] UTXOs
+/// or if there are less than two UTXOs to merge.
+fn consolidation_utxos_selection(
+    available_utxos: &mut BTreeSet<Utxo>,
+    fee_per_vbyte: u64,
+) -> Vec<Utxo> {
+    if available_utxos.len() <= UTXOS_COUNT_THRESHOLD {
+        return vec![];
+    }
+
+    let input_fee = INPUT_SIZE_VBYTES * fee_per_vbyte / 1000;
+    let mut candidates: Vec<Utxo> = available_utxos
+        .iter()
+        .filter(|u| u.value > input_fee)
+        .cloned()
+        .collect();
+    candidates.sort_by_key(|u| u.value);
+    candidates.truncate(MAX_CONSOLIDATION_INPUTS);
+
+    if candidates.len() < 2 {
+        return vec![];
+    }
+
+    for utxo in candidates.iter() {
+        assert!(available_utxos.remove(utxo));
+    }
+    candidates
+}
+
 /// Selects a subset of UTXOs with the specified total target value and removes
 /// the selected UTXOs from the available set.
 ///
@@ -825,35 +1178,91 @@ fn greedy(target: u64, available_utxos: &mut BTreeSet<Utxo>) -> Vec<Utxo> {
 pub async fn sign_transaction(
     key_name: String,
     ecdsa_public_key: &ECDSAPublicKey,
-    output_account: &BTreeMap<tx::OutPoint, Account>,
+    output_account: &BTreeMap<tx::OutPoint, (Account, DepositAddressType)>,
     unsigned_tx: tx::UnsignedTransaction,
 ) -> Result<tx::SignedTransaction, CallError> {
     use crate::address::{derivation_path, derive_public_key};
 
+    let input_accounts: Vec<_> = unsigned_tx
+        .inputs
+        .iter()
+        .map(|input| {
+            output_account
+                .get(&input.previous_output)
+                .unwrap_or_else(|| {
+                    panic!("bug: no account for outpoint {:?}", input.previous_output)
+                })
+        })
+        .collect();
+
+    let bip340_public_key = if input_accounts
+        .iter()
+        .any(|(_, address_type)| *address_type == DepositAddressType::P2trV1)
+    {
+        Some(updates::get_btc_address::try_init_bip340_public_key().await?)
+    } else {
+        None
+    };
+
+    let spent_addresses: Vec<_> = input_accounts
+        .iter()
+        .map(|(account, address_type)| match address_type {
+            DepositAddressType::P2wpkhV0 => {
+                address::account_to_bitcoin_address(ecdsa_public_key, account)
+            }
+            DepositAddressType::P2trV1 => address::account_to_p2tr_address(
+                bip340_public_key
+                    .as_ref()
+                    .expect("bug: the BIP-340 public key must be initialized"),
+                account,
+            ),
+        })
+        .collect();
+
     let mut signed_inputs = Vec::with_capacity(unsigned_tx.inputs.len());
-    let sighasher = tx::TxSigHasher::new(&unsigned_tx);
-    for input in &unsigned_tx.inputs {
+    let sighasher = tx::TxSigHasher::new(&unsigned_tx, &spent_addresses);
+    for (index, (input, (account, address_type))) in unsigned_tx
+        .inputs
+        .iter()
+        .zip(input_accounts.iter())
+        .enumerate()
+    {
         let outpoint = &input.previous_output;
+        let path = DerivationPath::new(derivation_path(account));
 
-        let account = output_account
-            .get(outpoint)
-            .unwrap_or_else(|| panic!("bug: no account for outpoint {:?}", outpoint));
+        let witness = match address_type {
+            DepositAddressType::P2wpkhV0 => {
+                let pubkey = ByteBuf::from(derive_public_key(ecdsa_public_key, account).public_key);
+                let pkhash = tx::hash160(&pubkey);
 
-        let path = derivation_path(account);
-        let pubkey = ByteBuf::from(derive_public_key(ecdsa_public_key, account).public_key);
-        let pkhash = tx::hash160(&pubkey);
+                let sighash = sighasher.sighash(input, &pkhash);
 
-        let sighash = sighasher.sighash(input, &pkhash);
+                let sec1_signature =
+                    management::sign_with_ecdsa(key_name.clone(), path, sighash).await?;
 
-        let sec1_signature =
-            management::sign_with_ecdsa(key_name.clone(), DerivationPath::new(path), sighash)
-                .await?;
+                tx::InputWitness::P2wpkh {
+                    signature: signature::EncodedSignature::from_sec1(&sec1_signature),
+                    pubkey,
+                }
+            }
+            DepositAddressType::P2trV1 => {
+                let sighash = sighasher.taproot_sighash(index);
+
+                let signature =
+                    management::sign_with_bip341(key_name.clone(), path, sighash).await?;
+
+                tx::InputWitness::P2trKeyPath {
+                    signature: signature.try_into().unwrap_or_else(|sig: Vec<u8>| {
+                        panic!("bug: unexpected BIP-340 signature length {}", sig.len())
+                    }),
+                }
+            }
+        };
 
         signed_inputs.push(tx::SignedInput {
-            signature: signature::EncodedSignature::from_sec1(&sec1_signature),
-            pubkey,
             previous_output: outpoint.clone(),
             sequence: input.sequence,
+            witness,
         });
     }
     Ok(tx::SignedTransaction {
@@ -871,8 +1280,12 @@ pub fn fake_sign(unsigned_tx: &tx::UnsignedTransaction) -> tx::SignedTransaction
             .map(|unsigned_input| tx::SignedInput {
                 previous_output: unsigned_input.previous_output.clone(),
                 sequence: unsigned_input.sequence,
-                signature: signature::EncodedSignature::fake(),
-                pubkey: ByteBuf::from(vec![0u8; tx::PUBKEY_LEN]),
+                // P2WPKH witnesses are larger than P2TR key path witnesses,
+                // so the estimate covers inputs of both types.
+                witness: tx::InputWitness::P2wpkh {
+                    signature: signature::EncodedSignature::fake(),
+                    pubkey: ByteBuf::from(vec![0u8; tx::PUBKEY_LEN]),
+                },
             })
             .collect(),
         outputs: unsigned_tx.outputs.clone(),
@@ -950,15 +1363,9 @@ pub fn build_unsigned_transaction(
 ) -> Result<(tx::UnsignedTransaction, state::ChangeOutput, Vec<Utxo>), BuildTxError> {
     assert!(!outputs.is_empty());
 
-    /// Having a sequence number lower than (0xffffffff - 1) signals the use of replacement by fee.
-    /// It allows us to increase the fee of a transaction already sent to the mempool.
-    /// The rbf option is used in `resubmit_retrieve_btc`.
-    /// https://github.com/bitcoin/bips/blob/master/bip-0125.mediawiki
-    const SEQUENCE_RBF_ENABLED: u32 = 0xfffffffd;
-
     let amount = outputs.iter().map(|(_, amount)| amount).sum::<u64>();
 
-    let input_utxos = utxos_selection(amount, minter_utxos, outputs.len());
+    let input_utxos = utxos_selection(amount, minter_utxos, outputs.len(), fee_per_vbyte);
 
     if input_utxos.is_empty() {
         return Err(BuildTxError::NotEnoughFunds);
@@ -972,11 +1379,40 @@ pub fn build_unsigned_transaction(
         }
     });
 
-    let inputs_value = utxos_guard.iter().map(|u| u.value).sum::<u64>();
+    let (unsigned_tx, change_output) =
+        build_unsigned_transaction_from_inputs(&utxos_guard, outputs, main_address, fee_per_vbyte)?;
 
-    debug_assert!(inputs_value >= amount);
+    Ok((
+        unsigned_tx,
+        change_output,
+        ScopeGuard::into_inner(utxos_guard),
+    ))
+}
 
-    let minter_fee = evaluate_minter_fee(utxos_guard.len() as u64, (outputs.len() + 1) as u64);
+/// Builds a transaction that spends exactly the specified UTXOs and pays the
+/// specified outputs, see [build_unsigned_transaction] for the properties of
+/// the result. Stuck transactions are rebuilt with this function so that the
+/// replacement spends the same inputs.
+///
+/// # Panics
+///
+/// This function panics if `outputs` is empty.
+pub fn build_unsigned_transaction_from_inputs(
+    input_utxos: &[Utxo],
+    outputs: Vec<(BitcoinAddress, Satoshi)>,
+    main_address: BitcoinAddress,
+    fee_per_vbyte: u64,
+) -> Result<(tx::UnsignedTransaction, state::ChangeOutput), BuildTxError> {
+    assert!(!outputs.is_empty());
+
+    let amount = outputs.iter().map(|(_, amount)| amount).sum::<u64>();
+    let inputs_value = input_utxos.iter().map(|u| u.value).sum::<u64>();
+
+    if inputs_value < amount {
+        return Err(BuildTxError::NotEnoughFunds);
+    }
+
+    let minter_fee = evaluate_minter_fee(input_utxos.len() as u64, (outputs.len() + 1) as u64);
 
     let change = inputs_value - amount;
     let change_output = state::ChangeOutput {
@@ -1002,7 +1438,7 @@ pub fn build_unsigned_transaction(
     );
 
     let mut unsigned_tx = tx::UnsignedTransaction {
-        inputs: utxos_guard
+        inputs: input_utxos
             .iter()
             .map(|utxo| tx::UnsignedInput {
                 previous_output: utxo.outpoint.clone(),
@@ -1045,11 +1481,56 @@ pub fn build_unsigned_transaction(
         fee + unsigned_tx.outputs.iter().map(|u| u.value).sum::<u64>()
     );
 
-    Ok((
-        unsigned_tx,
-        change_output,
-        ScopeGuard::into_inner(utxos_guard),
-    ))
+    Ok((unsigned_tx, change_output))
+}
+
+/// Builds a transaction that merges the specified UTXOs into a single output
+/// to the minter's main address. The minter pays the transaction fee from its
+/// own funds, which it can afford since it charges a fee for every input it
+/// spends on behalf of its users.
+///
+/// # Panics
+///
+/// This function panics if `input_utxos` is empty.
+pub fn build_consolidation_transaction(
+    input_utxos: &[Utxo],
+    main_address: BitcoinAddress,
+    fee_per_vbyte: u64,
+) -> Result<(tx::UnsignedTransaction, state::ChangeOutput), BuildTxError> {
+    assert!(!input_utxos.is_empty());
+
+    let inputs_value = input_utxos.iter().map(|u| u.value).sum::<u64>();
+
+    let mut unsigned_tx = tx::UnsignedTransaction {
+        inputs: input_utxos
+            .iter()
+            .map(|utxo| tx::UnsignedInput {
+                previous_output: utxo.outpoint.clone(),
+                value: utxo.value,
+                sequence: SEQUENCE_RBF_ENABLED,
+            })
+            .collect(),
+        outputs: vec![tx::TxOut {
+            address: main_address,
+            value: inputs_value,
+        }],
+        lock_time: 0,
+    };
+
+    let tx_vsize = fake_sign(&unsigned_tx).vsize();
+    let fee = (tx_vsize as u64 * fee_per_vbyte) / 1000;
+
+    if fee + MINTER_ADDRESS_DUST_LIMIT > inputs_value {
+        return Err(BuildTxError::AmountTooLow);
+    }
+
+    let change_output = state::ChangeOutput {
+        vout: 0,
+        value: inputs_value - fee,
+    };
+    unsigned_tx.outputs[0].value = change_output.value;
+
+    Ok((unsigned_tx, change_output))
 }
 
 pub fn evaluate_minter_fee(num_inputs: u64, num_outputs: u64) -> Satoshi {
@@ -1097,8 +1578,6 @@ pub fn tx_vsize_estimate(input_count: u64, output_count: u64) -> u64 {
     // for the transaction structure and
     // https://bitcoin.stackexchange.com/questions/92587/calculate-transaction-fee-for-external-addresses-which-doesnt-belong-to-my-loca/92600#92600
     // for transaction size estimate.
-    const INPUT_SIZE_VBYTES: u64 = 68;
-    const OUTPUT_SIZE_VBYTES: u64 = 31;
     const TX_OVERHEAD_VBYTES: u64 = 11;
 
     input_count * INPUT_SIZE_VBYTES + output_count * OUTPUT_SIZE_VBYTES + TX_OVERHEAD_VBYTES
@@ -1125,8 +1604,12 @@ pub fn estimate_retrieve_btc_fee(
             // should get the exact number of inputs that the minter
             // will use.
             let mut utxos = available_utxos.clone();
-            let selected_utxos =
-                utxos_selection(amount, &mut utxos, DEFAULT_OUTPUT_COUNT as usize - 1);
+            let selected_utxos = utxos_selection(
+                amount,
+                &mut utxos,
+                DEFAULT_OUTPUT_COUNT as usize - 1,
+                median_fee_millisatoshi_per_vbyte,
+            );
 
             if !selected_utxos.is_empty() {
                 selected_utxos.len() as u64
@@ -14,8 +14,8 @@ use ic_ckbtc_minter::state::{
 };
 use ic_ckbtc_minter::tasks::{schedule_now, TaskType};
 use ic_ckbtc_minter::updates::retrieve_btc::{
-    RetrieveBtcArgs, RetrieveBtcError, RetrieveBtcOk, RetrieveBtcWithApprovalArgs,
-    RetrieveBtcWithApprovalError,
+    RetrieveBtcArgs, RetrieveBtcBatchArgs, RetrieveBtcError, RetrieveBtcOk,
+    RetrieveBtcWithApprovalArgs, RetrieveBtcWithApprovalError,
 };
 use ic_ckbtc_minter::updates::{
     self,
@@ -50,6 +50,7 @@ fn init(args: MinterArg) {
 fn setup_tasks() {
     schedule_now(TaskType::ProcessLogic, &IC_CANISTER_RUNTIME);
     schedule_now(TaskType::RefreshFeePercentiles, &IC_CANISTER_RUNTIME);
+    schedule_now(TaskType::ConsolidateUtxos, &IC_CANISTER_RUNTIME);
 }
 
 #[cfg(feature = "self_check")]
@@ -156,6 +157,15 @@ async fn retrieve_btc_with_approval(
     check_postcondition(updates::retrieve_btc::retrieve_btc_with_approval(args).await)
 }
 
+#[update]
+async fn retrieve_btc_batch(
+    args: RetrieveBtcBatchArgs,
+) -> Result<Vec<Result<RetrieveBtcOk, RetrieveBtcWithApprovalError>>, RetrieveBtcWithApprovalError>
+{
+    check_anonymous_caller();
+    check_postcondition(updates::retrieve_btc::retrieve_btc_batch(args).await)
+}
+
 #[query]
 fn retrieve_btc_status(req: RetrieveBtcStatusRequest) -> RetrieveBtcStatus {
     read_state(|s| s.retrieve_btc_status(req.block_index))
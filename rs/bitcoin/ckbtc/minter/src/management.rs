@@ -18,6 +18,8 @@ use ic_cdk::api::{
 };
 use ic_management_canister_types_private::{
     DerivationPath, ECDSAPublicKeyArgs, ECDSAPublicKeyResponse, EcdsaCurve, EcdsaKeyId,
+    SchnorrAlgorithm, SchnorrKeyId, SchnorrPublicKeyArgs, SchnorrPublicKeyResponse,
+    SignWithBip341Aux, SignWithSchnorrArgs, SignWithSchnorrAux, SignWithSchnorrReply,
 };
 use serde::de::DeserializeOwned;
 use serde_bytes::ByteBuf;
@@ -347,6 +349,61 @@ pub async fn sign_with_ecdsa(
     }
 }
 
+/// The number of cycles attached to a `sign_with_schnorr` call, which matches
+/// the fee charged for signing on a 34-node subnet.
+const SIGN_WITH_SCHNORR_FEE: u64 = 26_153_846_153;
+
+/// Fetches the BIP-340 public key of the minter from the threshold Schnorr API.
+pub async fn schnorr_public_key(
+    key_name: String,
+    derivation_path: DerivationPath,
+) -> Result<ECDSAPublicKey, CallError> {
+    call(
+        "schnorr_public_key",
+        /*payment=*/ 0,
+        &SchnorrPublicKeyArgs {
+            canister_id: None,
+            derivation_path,
+            key_id: SchnorrKeyId {
+                algorithm: SchnorrAlgorithm::Bip340Secp256k1,
+                name: key_name,
+            },
+        },
+    )
+    .await
+    .map(|response: SchnorrPublicKeyResponse| ECDSAPublicKey {
+        public_key: response.public_key,
+        chain_code: response.chain_code,
+    })
+}
+
+/// Signs a message using the threshold Schnorr API with the key tweaked
+/// for a Taproot key path spend, see BIP-341.
+pub async fn sign_with_bip341(
+    key_name: String,
+    derivation_path: DerivationPath,
+    message: [u8; 32],
+) -> Result<Vec<u8>, CallError> {
+    call(
+        "sign_with_schnorr",
+        SIGN_WITH_SCHNORR_FEE,
+        &SignWithSchnorrArgs {
+            message: message.to_vec(),
+            derivation_path,
+            key_id: SchnorrKeyId {
+                algorithm: SchnorrAlgorithm::Bip340Secp256k1,
+                name: key_name,
+            },
+            // An empty Merkle root commits to a key path only output.
+            aux: Some(SignWithSchnorrAux::Bip341(SignWithBip341Aux {
+                merkle_root_hash: ByteBuf::new(),
+            })),
+        },
+    )
+    .await
+    .map(|reply: SignWithSchnorrReply| reply.signature)
+}
+
 /// Check if the given Bitcoin address is blocked.
 pub async fn check_withdrawal_destination_address(
     btc_checker_principal: Principal,
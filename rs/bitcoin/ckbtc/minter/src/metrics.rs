@@ -10,6 +10,8 @@ pub type NumUtxoPages = u32;
 thread_local! {
     pub static GET_UTXOS_CLIENT_CALLS: Cell<u64> = Cell::default();
     pub static GET_UTXOS_MINTER_CALLS: Cell<u64> = Cell::default();
+    pub static UTXO_SELECTIONS_BNB: Cell<u64> = Cell::default();
+    pub static UTXO_SELECTIONS_GREEDY: Cell<u64> = Cell::default();
     pub static UPDATE_CALL_LATENCY: RefCell<BTreeMap<NumUtxoPages,LatencyHistogram>> = RefCell::default();
     pub static GET_UTXOS_CALL_LATENCY: RefCell<BTreeMap<(NumUtxoPages, CallSource),LatencyHistogram>> = RefCell::default();
     pub static GET_UTXOS_RESULT_SIZE: RefCell<BTreeMap<CallSource,NumUtxosHistogram>> = RefCell::default();
@@ -207,6 +209,17 @@ pub fn encode_metrics(
             state::read_state(|s| s.stuck_transactions.len() as f64),
         )?;
 
+    metrics.encode_gauge(
+        "ckbtc_minter_consolidation_transaction_count",
+        state::read_state(|s| {
+            s.submitted_transactions
+                .iter()
+                .filter(|tx| tx.requests.is_empty())
+                .count()
+        }) as f64,
+        "Total count of non-finalized transactions merging the minter's UTXOs.",
+    )?;
+
     metrics.encode_gauge(
         "ckbtc_minter_longest_resubmission_chain_size",
         state::read_state(|s| s.longest_resubmission_chain_size() as f64),
@@ -225,6 +238,24 @@ pub fn encode_metrics(
         "Total number of finalized retrieve_btc requests.",
     )?;
 
+    metrics.encode_counter(
+        "ckbtc_minter_consolidated_utxos",
+        state::read_state(|s| s.consolidated_utxos_count) as f64,
+        "Total number of UTXOs merged by consolidation transactions.",
+    )?;
+
+    metrics.encode_counter(
+        "ckbtc_minter_accrued_minter_fees",
+        state::read_state(|s| s.accrued_minter_fees) as f64,
+        "Total amount of fees (in satoshi) the minter charged for the transactions it sent.",
+    )?;
+
+    metrics.encode_counter(
+        "ckbtc_minter_consolidation_fees_spent",
+        state::read_state(|s| s.consolidation_fees_spent) as f64,
+        "Total amount of Bitcoin fees (in satoshi) the minter paid for consolidation transactions.",
+    )?;
+
     metrics.encode_counter(
         "ckbtc_minter_minted_tokens",
         state::read_state(|s| s.tokens_minted) as f64,
@@ -269,6 +300,17 @@ pub fn encode_metrics(
         .value(&[("source", "client")], GET_UTXOS_CLIENT_CALLS.get() as f64)?
         .value(&[("source", "minter")], GET_UTXOS_MINTER_CALLS.get() as f64)?;
 
+    metrics
+        .counter_vec(
+            "ckbtc_minter_utxo_selections",
+            "Number of UTXO selections the minter made, labeled by the algorithm that found the solution.",
+        )?
+        .value(
+            &[("algorithm", "branch_and_bound")],
+            UTXO_SELECTIONS_BNB.get() as f64,
+        )?
+        .value(&[("algorithm", "greedy")], UTXO_SELECTIONS_GREEDY.get() as f64)?;
+
     metrics.encode_gauge(
         "ckbtc_minter_btc_balance",
         state::read_state(|s| s.get_total_btc_managed()) as f64,
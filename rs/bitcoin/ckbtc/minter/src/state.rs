@@ -20,7 +20,10 @@ use crate::lifecycle::upgrade::UpgradeArgs;
 use crate::logs::P0;
 use crate::state::invariants::{CheckInvariants, CheckInvariantsImpl};
 use crate::updates::update_balance::SuspendedUtxo;
-use crate::{address::BitcoinAddress, ECDSAPublicKey, Timestamp};
+use crate::{
+    address::{BitcoinAddress, DepositAddressType},
+    ECDSAPublicKey, Timestamp,
+};
 use candid::{CandidType, Deserialize, Principal};
 use ic_base_types::CanisterId;
 pub use ic_btc_interface::Network;
@@ -64,6 +67,12 @@ pub struct RetrieveBtcRequest {
     #[serde(rename = "reimbursement_account")]
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reimbursement_account: Option<Account>,
+    /// The block index of the first request accepted by the same
+    /// retrieve_btc_batch call. Requests of a batch are always
+    /// included in the same transaction.
+    #[serde(rename = "batch_id")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_id: Option<u64>,
 }
 
 /// A transaction output storing the minter's change.
@@ -94,6 +103,37 @@ pub struct SubmittedBtcTransaction {
     pub fee_per_vbyte: Option<u64>,
 }
 
+impl SubmittedBtcTransaction {
+    fn inputs_value(&self) -> u64 {
+        self.used_utxos.iter().map(|u| u.value).sum()
+    }
+
+    /// Returns the fee (in satoshi) that the minter charged for this
+    /// transaction. The minter keeps the fee in the change output, on top of
+    /// the value of the inputs in excess of the requested amounts.
+    pub fn minter_fee(&self) -> u64 {
+        if self.requests.is_empty() {
+            return 0;
+        }
+        let requested_amount = self.requests.iter().map(|r| r.amount).sum::<u64>();
+        let change = self.inputs_value().saturating_sub(requested_amount);
+        self.change_output
+            .as_ref()
+            .map_or(0, |out| out.value.saturating_sub(change))
+    }
+
+    /// Returns the Bitcoin fee (in satoshi) that the minter paid from its own
+    /// UTXOs if this is a consolidation transaction and zero otherwise.
+    pub fn consolidation_fee(&self) -> u64 {
+        if !self.requests.is_empty() {
+            return 0;
+        }
+        self.change_output
+            .as_ref()
+            .map_or(0, |out| self.inputs_value().saturating_sub(out.value))
+    }
+}
+
 /// Pairs a retrieve_btc request with its outcome.
 #[derive(Clone, Eq, PartialEq, Debug, Deserialize, Serialize)]
 pub struct FinalizedBtcRetrieval {
@@ -282,6 +322,9 @@ pub struct CkBtcMinterState {
     /// The Minter ECDSA public key
     pub ecdsa_public_key: Option<ECDSAPublicKey>,
 
+    /// The Minter BIP-340 public key backing P2TR deposit addresses
+    pub bip340_public_key: Option<ECDSAPublicKey>,
+
     /// The minimum number of confirmations on the Bitcoin chain.
     pub min_confirmations: u32,
 
@@ -332,6 +375,17 @@ pub struct CkBtcMinterState {
     /// The total number of finalized requests.
     pub finalized_requests_count: u64,
 
+    /// The total number of UTXOs merged by consolidation transactions.
+    pub consolidated_utxos_count: u64,
+
+    /// The total amount of fees (in satoshi) the minter charged for the
+    /// transactions it sent on behalf of its users.
+    pub accrued_minter_fees: u64,
+
+    /// The total amount of Bitcoin fees (in satoshi) the minter paid from its
+    /// own UTXOs for consolidation transactions.
+    pub consolidation_fees_spent: u64,
+
     /// The total amount of ckBTC minted.
     pub tokens_minted: u64,
 
@@ -351,6 +405,10 @@ pub struct CkBtcMinterState {
     /// belong.
     pub outpoint_account: BTreeMap<OutPoint, Account>,
 
+    /// The set of output points locked by P2TR deposit addresses.
+    /// All other output points are locked by P2WPKH addresses.
+    pub p2tr_outpoints: BTreeSet<OutPoint>,
+
     /// The map of known addresses to their utxos.
     pub utxos_state_addresses: BTreeMap<Account, BTreeSet<Utxo>>,
 
@@ -368,6 +426,11 @@ pub struct CkBtcMinterState {
 
     pub is_distributing_fee: bool,
 
+    /// The batches of retrieve_btc requests (identified by their batch id)
+    /// that the minter is still burning. The minter doesn't submit the
+    /// requests of such a batch until it has burned all of them.
+    pub retrieve_btc_batches_in_progress: BTreeSet<u64>,
+
     /// The mode in which the minter runs.
     pub mode: Mode,
 
@@ -537,6 +600,30 @@ impl CkBtcMinterState {
         }
     }
 
+    /// Same as [add_utxos](Self::add_utxos), but also records the type of the
+    /// deposit address that received the UTXOs.
+    pub(crate) fn add_deposit_utxos<I: CheckInvariants>(
+        &mut self,
+        account: Account,
+        address_type: DepositAddressType,
+        utxos: Vec<Utxo>,
+    ) {
+        if address_type == DepositAddressType::P2trV1 {
+            self.p2tr_outpoints
+                .extend(utxos.iter().map(|utxo| utxo.outpoint.clone()));
+        }
+        self.add_utxos::<I>(account, utxos)
+    }
+
+    /// Returns the type of the address that locks the specified output point.
+    pub fn deposit_address_type(&self, outpoint: &OutPoint) -> DepositAddressType {
+        if self.p2tr_outpoints.contains(outpoint) {
+            DepositAddressType::P2trV1
+        } else {
+            DepositAddressType::P2wpkhV0
+        }
+    }
+
     pub fn retrieve_btc_status_v2_by_account(
         &self,
         target: Option<Account>,
@@ -648,13 +735,24 @@ impl CkBtcMinterState {
         let available_utxos_value = self.available_utxos.iter().map(|u| u.value).sum::<u64>();
         let mut batch = vec![];
         let mut tx_amount = 0;
-        for req in std::mem::take(&mut self.pending_retrieve_btc_requests) {
-            if available_utxos_value < req.amount + tx_amount || batch.len() >= max_size {
-                // Put this request back to the queue until we have enough liquid UTXOs.
-                self.pending_retrieve_btc_requests.push(req);
+        let pending = std::mem::take(&mut self.pending_retrieve_btc_requests);
+        // Requests accepted by the same retrieve_btc_batch call are adjacent in
+        // the queue, and they are either all included in the batch or none is.
+        for group in pending.chunk_by(|l, r| l.batch_id.is_some() && l.batch_id == r.batch_id) {
+            let group_amount = group.iter().map(|req| req.amount).sum::<u64>();
+            let is_in_progress = group[0]
+                .batch_id
+                .is_some_and(|batch_id| self.retrieve_btc_batches_in_progress.contains(&batch_id));
+            if is_in_progress
+                || available_utxos_value < group_amount + tx_amount
+                || batch.len() + group.len() > max_size
+            {
+                // Put these requests back to the queue until we have enough liquid UTXOs
+                // and all the requests of the batch are burned.
+                self.pending_retrieve_btc_requests.extend_from_slice(group);
             } else {
-                tx_amount += req.amount;
-                batch.push(req);
+                tx_amount += group_amount;
+                batch.extend_from_slice(group);
             }
         }
 
@@ -682,6 +780,7 @@ impl CkBtcMinterState {
     }
 
     fn forget_utxo(&mut self, utxo: &Utxo) {
+        self.p2tr_outpoints.remove(&utxo.outpoint);
         if let Some(account) = self.outpoint_account.remove(&utxo.outpoint) {
             if self.update_balance_accounts.contains(&account) {
                 self.finalized_utxos
@@ -811,6 +910,11 @@ impl CkBtcMinterState {
         // tx points to the old transaction now.
         debug_assert_eq!(&tx.txid, old_txid);
 
+        // The replacement of a consolidation transaction pays a higher fee
+        // from the minter's UTXOs.
+        let new_fee = self.submitted_transactions[pos].consolidation_fee();
+        self.consolidation_fees_spent += new_fee.saturating_sub(tx.consolidation_fee());
+
         self.stuck_transactions.push(tx);
         self.replacement_txid.insert(*old_txid, new_txid);
         self.rev_replacement_txid.insert(new_txid, *old_txid);
@@ -876,14 +980,34 @@ impl CkBtcMinterState {
     /// This function panics if the new request breaks the request ordering in
     /// the queue.
     pub fn push_back_pending_request(&mut self, request: RetrieveBtcRequest) {
-        if let Some(last_req) = self.pending_retrieve_btc_requests.last() {
-            assert!(last_req.received_at <= request.received_at);
-        }
         self.tokens_burned += request.amount;
         if let Some(kyt_provider) = request.kyt_provider {
             *self.owed_kyt_amount.entry(kyt_provider).or_insert(0) += self.check_fee;
         }
-        self.pending_retrieve_btc_requests.push(request);
+        self.insert_pending_request(request);
+    }
+
+    /// Adds a retrieve_btc request to the queue. A request that belongs to a
+    /// batch goes right after the requests of the same batch that are already
+    /// in the queue, so that the requests of a batch are adjacent.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the new request breaks the request ordering in
+    /// the queue.
+    pub(crate) fn insert_pending_request(&mut self, request: RetrieveBtcRequest) {
+        let queue = &mut self.pending_retrieve_btc_requests;
+        let pos = request
+            .batch_id
+            .and_then(|batch_id| queue.iter().rposition(|r| r.batch_id == Some(batch_id)))
+            .map_or(queue.len(), |last| last + 1);
+        if let Some(prev_req) = pos.checked_sub(1).map(|i| &queue[i]) {
+            assert!(prev_req.received_at <= request.received_at);
+        }
+        if let Some(next_req) = queue.get(pos) {
+            assert!(request.received_at <= next_req.received_at);
+        }
+        queue.insert(pos, request);
     }
 
     /// Records a BTC transaction as submitted and updates statuses of all
@@ -898,9 +1022,27 @@ impl CkBtcMinterState {
             assert!(!self.has_pending_request(req.block_index));
             self.requests_in_flight.remove(&req.block_index);
         }
+        self.accrued_minter_fees += tx.minter_fee();
         self.submitted_transactions.push(tx);
     }
 
+    /// Pushes a transaction that merges some of the minter's UTXOs into a
+    /// single one to the list of submitted transactions.
+    pub fn push_consolidation_transaction(&mut self, tx: SubmittedBtcTransaction) {
+        debug_assert!(tx.requests.is_empty());
+        self.consolidated_utxos_count += tx.used_utxos.len() as u64;
+        self.consolidation_fees_spent += tx.consolidation_fee();
+        self.submitted_transactions.push(tx);
+    }
+
+    /// Returns the amount (in satoshi) that the minter can still spend on the
+    /// fees of consolidation transactions, i.e., the minter fees it accrued
+    /// minus the fees it already paid for consolidations.
+    pub fn consolidation_fee_budget(&self) -> u64 {
+        self.accrued_minter_fees
+            .saturating_sub(self.consolidation_fees_spent)
+    }
+
     /// Marks the specified retrieve_btc request as finalized.
     ///
     /// # Panics
@@ -1174,6 +1316,11 @@ impl CkBtcMinterState {
             other.utxos_state_addresses,
             "utxos_state_addresses do not match"
         );
+        ensure_eq!(
+            self.p2tr_outpoints,
+            other.p2tr_outpoints,
+            "p2tr_outpoints do not match"
+        );
         {
             let SuspendedUtxos {
                 utxos_without_account,
@@ -1463,6 +1610,7 @@ impl From<InitArgs> for CkBtcMinterState {
             btc_network: args.btc_network.into(),
             ecdsa_key_name: args.ecdsa_key_name,
             ecdsa_public_key: None,
+            bip340_public_key: None,
             min_confirmations: args
                 .min_confirmations
                 .unwrap_or(crate::lifecycle::init::DEFAULT_MIN_CONFIRMATIONS),
@@ -1481,16 +1629,21 @@ impl From<InitArgs> for CkBtcMinterState {
             stuck_transactions: Default::default(),
             finalized_requests: VecDeque::with_capacity(MAX_FINALIZED_REQUESTS),
             finalized_requests_count: 0,
+            consolidated_utxos_count: 0,
+            accrued_minter_fees: 0,
+            consolidation_fees_spent: 0,
             tokens_minted: 0,
             tokens_burned: 0,
             ledger_id: args.ledger_id,
             btc_checker_principal: args.btc_checker_principal,
             available_utxos: Default::default(),
             outpoint_account: Default::default(),
+            p2tr_outpoints: Default::default(),
             utxos_state_addresses: Default::default(),
             finalized_utxos: Default::default(),
             is_timer_running: false,
             is_distributing_fee: false,
+            retrieve_btc_batches_in_progress: Default::default(),
             mode: args.mode,
             last_fee_per_vbyte: vec![1; 100],
             check_fee: args
@@ -4,6 +4,7 @@ use super::{
     eventlog::EventType, CkBtcMinterState, FinalizedBtcRetrieval, FinalizedStatus,
     RetrieveBtcRequest, SubmittedBtcTransaction, SuspendedReason,
 };
+use crate::address::DepositAddressType;
 use crate::state::invariants::CheckInvariantsImpl;
 use crate::storage::record_event;
 use crate::{CanisterRuntime, Timestamp};
@@ -20,7 +21,7 @@ pub fn accept_retrieve_btc_request<R: CanisterRuntime>(
         EventType::AcceptedRetrieveBtcRequest(request.clone()),
         runtime,
     );
-    state.pending_retrieve_btc_requests.push(request.clone());
+    state.insert_pending_request(request.clone());
     if let Some(account) = request.reimbursement_account {
         state
             .retrieve_btc_account_to_block_indices
@@ -37,6 +38,7 @@ pub fn add_utxos<R: CanisterRuntime>(
     state: &mut CkBtcMinterState,
     mint_txid: Option<u64>,
     account: Account,
+    address_type: DepositAddressType,
     utxos: Vec<Utxo>,
     runtime: &R,
 ) {
@@ -45,11 +47,15 @@ pub fn add_utxos<R: CanisterRuntime>(
             mint_txid,
             to_account: account,
             utxos: utxos.clone(),
+            address_type: match address_type {
+                DepositAddressType::P2wpkhV0 => None,
+                DepositAddressType::P2trV1 => Some(address_type),
+            },
         },
         runtime,
     );
 
-    state.add_utxos::<CheckInvariantsImpl>(account, utxos);
+    state.add_deposit_utxos::<CheckInvariantsImpl>(account, address_type, utxos);
 }
 
 pub fn remove_retrieve_btc_request<R: CanisterRuntime>(
@@ -90,6 +96,30 @@ pub fn sent_transaction<R: CanisterRuntime>(
     state.push_submitted_transaction(tx);
 }
 
+pub fn sent_consolidation_transaction<R: CanisterRuntime>(
+    state: &mut CkBtcMinterState,
+    tx: SubmittedBtcTransaction,
+    runtime: &R,
+) {
+    record_event(
+        EventType::SentConsolidationTransaction {
+            txid: tx.txid,
+            utxos: tx.used_utxos.clone(),
+            change_output: tx
+                .change_output
+                .clone()
+                .expect("bug: consolidation transactions must have a change output"),
+            submitted_at: tx.submitted_at,
+            fee_per_vbyte: tx
+                .fee_per_vbyte
+                .expect("bug: consolidation transactions must have a fee"),
+        },
+        runtime,
+    );
+
+    state.push_consolidation_transaction(tx);
+}
+
 pub fn confirm_transaction<R: CanisterRuntime>(
     state: &mut CkBtcMinterState,
     txid: &Txid,
@@ -1,3 +1,4 @@
+use crate::address::DepositAddressType;
 use crate::lifecycle::init::InitArgs;
 use crate::lifecycle::upgrade::UpgradeArgs;
 use crate::state::invariants::CheckInvariants;
@@ -53,6 +54,11 @@ mod event {
             to_account: Account,
             #[serde(rename = "utxos")]
             utxos: Vec<Utxo>,
+            /// The type of the deposit address that received the UTXOs.
+            /// None if the UTXOs were received to a P2WPKH address.
+            #[serde(rename = "address_type")]
+            #[serde(skip_serializing_if = "Option::is_none")]
+            address_type: Option<DepositAddressType>,
         },
 
         /// Indicates that the minter accepted a new retrieve_btc request.
@@ -116,6 +122,27 @@ mod event {
             fee_per_vbyte: u64,
         },
 
+        /// Indicates that the minter sent out a new transaction to the Bitcoin network
+        /// that merges some of its UTXOs into a single one.
+        #[serde(rename = "sent_consolidation_transaction")]
+        SentConsolidationTransaction {
+            /// The Txid of the Bitcoin transaction.
+            #[serde(rename = "txid")]
+            txid: Txid,
+            /// UTXOs merged by the transaction.
+            #[serde(rename = "utxos")]
+            utxos: Vec<Utxo>,
+            /// The output with the merged UTXOs.
+            #[serde(rename = "change_output")]
+            change_output: ChangeOutput,
+            /// The IC time at which the minter submitted the transaction.
+            #[serde(rename = "submitted_at")]
+            submitted_at: u64,
+            /// The fee per vbyte (in millisatoshi) that we used for the transaction.
+            #[serde(rename = "fee")]
+            fee_per_vbyte: u64,
+        },
+
         /// Indicates that the minter received enough confirmations for a bitcoin
         /// transaction.
         #[serde(rename = "confirmed_transaction")]
@@ -273,8 +300,11 @@ pub fn replay<I: CheckInvariants>(
                 state.upgrade(args);
             }
             EventType::ReceivedUtxos {
-                to_account, utxos, ..
-            } => state.add_utxos::<I>(to_account, utxos),
+                to_account,
+                utxos,
+                address_type,
+                ..
+            } => state.add_deposit_utxos::<I>(to_account, address_type.unwrap_or_default(), utxos),
             EventType::AcceptedRetrieveBtcRequest(req) => {
                 if let Some(account) = req.reimbursement_account {
                     state
@@ -328,6 +358,25 @@ pub fn replay<I: CheckInvariants>(
                     submitted_at,
                 });
             }
+            EventType::SentConsolidationTransaction {
+                txid,
+                utxos,
+                change_output,
+                submitted_at,
+                fee_per_vbyte,
+            } => {
+                for utxo in utxos.iter() {
+                    state.available_utxos.remove(utxo);
+                }
+                state.push_consolidation_transaction(SubmittedBtcTransaction {
+                    requests: vec![],
+                    txid,
+                    used_utxos: utxos,
+                    fee_per_vbyte: Some(fee_per_vbyte),
+                    change_output: Some(change_output),
+                    submitted_at,
+                });
+            }
             EventType::ReplacedBtcTransaction {
                 old_txid,
                 new_txid,
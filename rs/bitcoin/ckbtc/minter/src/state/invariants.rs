@@ -26,6 +26,14 @@ impl CheckInvariants for CheckInvariantsImpl {
             );
         }
 
+        for outpoint in state.p2tr_outpoints.iter() {
+            ensure!(
+                state.outpoint_account.contains_key(outpoint),
+                "the output_account map is missing an entry for P2TR outpoint {:?}",
+                outpoint
+            );
+        }
+
         for (addr, utxos) in state.utxos_state_addresses.iter() {
             for utxo in utxos.iter() {
                 ensure_eq!(
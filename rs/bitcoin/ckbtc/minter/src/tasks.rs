@@ -1,6 +1,9 @@
 #[cfg(test)]
 mod tests;
-use crate::{estimate_fee_per_vbyte, finalize_requests, submit_pending_requests, CanisterRuntime};
+use crate::{
+    consolidate_utxos, estimate_fee_per_vbyte, finalize_requests, submit_pending_requests,
+    CanisterRuntime,
+};
 use scopeguard::guard;
 use std::cell::{Cell, RefCell};
 use std::collections::{BTreeMap, BTreeSet};
@@ -15,6 +18,7 @@ thread_local! {
 pub enum TaskType {
     ProcessLogic,
     RefreshFeePercentiles,
+    ConsolidateUtxos,
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
@@ -159,5 +163,17 @@ pub(crate) async fn run_task<R: CanisterRuntime>(task: Task, runtime: R) {
             };
             let _ = estimate_fee_per_vbyte().await;
         }
+        TaskType::ConsolidateUtxos => {
+            const CONSOLIDATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+            let _enqueue_followup_guard = guard((), |_| {
+                schedule_after(CONSOLIDATION_INTERVAL, TaskType::ConsolidateUtxos, &runtime)
+            });
+
+            let _guard = match crate::guard::TimerLogicGuard::new() {
+                Some(guard) => guard,
+                None => return,
+            };
+            consolidate_utxos().await;
+        }
     }
 }
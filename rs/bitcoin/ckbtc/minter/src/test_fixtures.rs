@@ -145,14 +145,14 @@ pub mod mock {
 
 pub mod arbitrary {
     use crate::{
-        address::BitcoinAddress,
+        address::{BitcoinAddress, DepositAddressType},
         signature::EncodedSignature,
         state::{
             eventlog::{Event, EventType},
             ChangeOutput, Mode, ReimbursementReason, RetrieveBtcRequest, SuspendedReason,
         },
         tx,
-        tx::{SignedInput, TxOut, UnsignedInput},
+        tx::{InputWitness, SignedInput, TxOut, UnsignedInput},
     };
     use candid::Principal;
     pub use event::event_type;
@@ -228,6 +228,7 @@ pub mod arbitrary {
             received_at: 1569975147000..2069975147000u64,
             kyt_provider: option::of(principal()),
             reimbursement_account: option::of(account()),
+            batch_id: option::of(any::<u64>()),
         })
     }
 
@@ -277,15 +278,34 @@ pub mod arbitrary {
         })
     }
 
+    pub fn input_witness() -> impl Strategy<Value = InputWitness> {
+        prop_oneof![
+            prop_struct!(InputWitness::P2wpkh {
+                signature: encoded_signature(),
+                pubkey: pvec(any::<u8>(), tx::PUBKEY_LEN).prop_map(ByteBuf::from),
+            }),
+            prop_struct!(InputWitness::P2trKeyPath {
+                signature: pvec(any::<u8>(), tx::BIP340_SIGNATURE_LEN)
+                    .prop_map(|sig| sig.try_into().unwrap()),
+            }),
+        ]
+    }
+
     pub fn signed_input() -> impl Strategy<Value = SignedInput> {
         prop_struct!(SignedInput {
             previous_output: outpoint(),
             sequence: any::<u32>(),
-            signature: encoded_signature(),
-            pubkey: pvec(any::<u8>(), tx::PUBKEY_LEN).prop_map(ByteBuf::from),
+            witness: input_witness(),
         })
     }
 
+    pub fn deposit_address_type() -> impl Strategy<Value = DepositAddressType> {
+        prop_oneof![
+            Just(DepositAddressType::P2wpkhV0),
+            Just(DepositAddressType::P2trV1),
+        ]
+    }
+
     pub fn address() -> impl Strategy<Value = BitcoinAddress> {
         prop_oneof![
             uniform20(any::<u8>()).prop_map(BitcoinAddress::P2wpkhV0),
@@ -380,6 +400,7 @@ pub mod arbitrary {
                     mint_txid: option::of(any::<u64>()),
                     to_account: account(),
                     utxos: pvec(utxo(amount()), 0..10_000),
+                    address_type: option::of(deposit_address_type()),
                 }),
                 prop_struct!(EventType::RemovedRetrieveBtcRequest {
                     block_index: any::<u64>()
@@ -399,6 +420,13 @@ pub mod arbitrary {
                     submitted_at: any::<u64>(),
                     fee_per_vbyte: any::<u64>(),
                 }),
+                prop_struct!(EventType::SentConsolidationTransaction {
+                    txid: txid(),
+                    utxos: pvec(utxo(amount()), 0..10_000),
+                    change_output: change_output(),
+                    submitted_at: any::<u64>(),
+                    fee_per_vbyte: any::<u64>(),
+                }),
                 prop_struct!(EventType::ConfirmedBtcTransaction { txid: txid() }),
                 prop_struct!(EventType::CheckedUtxo {
                     utxo: utxo(amount()),
@@ -1,6 +1,8 @@
 use crate::{
     address::BitcoinAddress,
-    build_unsigned_transaction, estimate_retrieve_btc_fee, evaluate_minter_fee, fake_sign, greedy,
+    branch_and_bound, build_consolidation_transaction, build_unsigned_transaction,
+    consolidation_utxos_selection, estimate_retrieve_btc_fee, evaluate_minter_fee, fake_sign,
+    greedy,
     lifecycle::init::InitArgs,
     state::invariants::CheckInvariantsImpl,
     state::{
@@ -8,7 +10,7 @@ use crate::{
         SubmittedBtcTransaction,
     },
     test_fixtures::arbitrary,
-    tx, BuildTxError, MINTER_ADDRESS_DUST_LIMIT,
+    tx, BuildTxError, LONG_TERM_FEE_PER_VBYTE, MAX_CONSOLIDATION_INPUTS, MINTER_ADDRESS_DUST_LIMIT,
 };
 use bitcoin::network::constants::Network as BtcNetwork;
 use bitcoin::util::psbt::serialize::{Deserialize, Serialize};
@@ -168,10 +170,12 @@ fn signed_tx_to_bitcoin_tx(tx: &tx::SignedTransaction) -> bitcoin::Transaction {
                 },
                 sequence: txin.sequence,
                 script_sig: bitcoin::Script::default(),
-                witness: bitcoin::Witness::from_vec(vec![
-                    txin.signature.as_slice().to_vec(),
-                    txin.pubkey.to_vec(),
-                ]),
+                witness: bitcoin::Witness::from_vec(match &txin.witness {
+                    tx::InputWitness::P2wpkh { signature, pubkey } => {
+                        vec![signature.as_slice().to_vec(), pubkey.to_vec()]
+                    }
+                    tx::InputWitness::P2trKeyPath { signature } => vec![signature.to_vec()],
+                }),
             })
             .collect(),
         output: tx
@@ -185,6 +189,49 @@ fn signed_tx_to_bitcoin_tx(tx: &tx::SignedTransaction) -> bitcoin::Transaction {
     }
 }
 
+#[test]
+fn should_derive_p2tr_address_spendable_with_bip341_signatures() {
+    use crate::address::{account_to_p2tr_address, derivation_path};
+    use crate::ECDSAPublicKey;
+    use ic_secp256k1::{DerivationIndex, DerivationPath, PrivateKey, PublicKey};
+
+    let master_key = PrivateKey::generate_from_seed(&[42; 32]);
+    let chain_code = [7; 32];
+    let bip340_public_key = ECDSAPublicKey {
+        public_key: master_key.public_key().serialize_sec1(true),
+        chain_code: chain_code.to_vec(),
+    };
+    let account = Account {
+        owner: Principal::from_slice(&[1; 29]),
+        subaccount: Some([2; 32]),
+    };
+
+    let output_key = match account_to_p2tr_address(&bip340_public_key, &account) {
+        BitcoinAddress::P2trV1(output_key) => output_key,
+        address => panic!("expected a P2TR address, got {:?}", address),
+    };
+    assert!(account_to_p2tr_address(&bip340_public_key, &account)
+        .display(Network::Mainnet)
+        .starts_with("bc1p"));
+
+    // The threshold Schnorr API signs with the derived key tweaked with an empty
+    // Merkle root, the signature must verify against the output key.
+    let path = DerivationPath::new(
+        derivation_path(&account)
+            .into_iter()
+            .map(|x| DerivationIndex(x.into_vec()))
+            .collect(),
+    );
+    let (derived_key, _) = master_key.derive_subkey_with_chain_code(&path, &chain_code);
+    let sighash = [3; 32];
+    let signature = derived_key
+        .sign_message_with_bip341_no_rng(&sighash, &[])
+        .unwrap();
+
+    let output_key = PublicKey::deserialize_bip340(&output_key).unwrap();
+    assert!(output_key.verify_bip340_signature(&sighash, &signature));
+}
+
 #[test]
 fn greedy_smoke_test() {
     let mut utxos: BTreeSet<Utxo> = (1..10u64).map(dummy_utxo_from_value).collect();
@@ -196,6 +243,200 @@ fn greedy_smoke_test() {
     assert_eq!(res[1].value, 6_u64);
 }
 
+#[test]
+fn branch_and_bound_should_minimize_excess() {
+    let utxos: BTreeSet<Utxo> = [100_000, 60_000, 50_000, 40_000, 10_000]
+        .into_iter()
+        .map(dummy_utxo_from_value)
+        .collect();
+
+    let solution = branch_and_bound(90_000, &utxos, 10_000).expect("failed to find a solution");
+
+    assert_eq!(solution.iter().map(|u| u.value).sum::<u64>(), 90_000);
+}
+
+#[test]
+fn branch_and_bound_should_minimize_waste() {
+    let utxos: BTreeSet<Utxo> = [50_000, 30_000, 20_000]
+        .into_iter()
+        .map(dummy_utxo_from_value)
+        .collect();
+    let values = |solution: Vec<Utxo>| solution.iter().map(|u| u.value).collect::<Vec<_>>();
+
+    // Spending inputs is expensive when the fees are high.
+    let solution = branch_and_bound(50_000, &utxos, 5 * LONG_TERM_FEE_PER_VBYTE).unwrap();
+    assert_eq!(values(solution), vec![50_000]);
+
+    // Spending inputs now is cheaper than later when the fees are low.
+    let solution = branch_and_bound(50_000, &utxos, LONG_TERM_FEE_PER_VBYTE / 10).unwrap();
+    assert_eq!(values(solution), vec![30_000, 20_000]);
+}
+
+#[test]
+fn branch_and_bound_should_select_utxos_with_excess() {
+    let utxos: BTreeSet<Utxo> = [100_000, 60_000]
+        .into_iter()
+        .map(dummy_utxo_from_value)
+        .collect();
+
+    // The excess goes to the change output.
+    let solution = branch_and_bound(50_000, &utxos, 10_000).expect("failed to find a solution");
+    assert_eq!(
+        solution.iter().map(|u| u.value).collect::<Vec<_>>(),
+        vec![60_000]
+    );
+}
+
+#[test]
+fn branch_and_bound_should_fail_without_enough_funds() {
+    let utxos: BTreeSet<Utxo> = [100_000, 60_000, 100]
+        .into_iter()
+        .map(dummy_utxo_from_value)
+        .collect();
+
+    assert_eq!(branch_and_bound(200_000, &utxos, 10_000), None);
+    // The last UTXO is worth less than the cost of spending it.
+    assert_eq!(branch_and_bound(160_050, &utxos, 10_000), None);
+}
+
+#[test]
+fn should_select_smallest_utxos_for_consolidation() {
+    let mut available_utxos: BTreeSet<Utxo> = (0..=crate::UTXOS_COUNT_THRESHOLD as u64)
+        .map(|i| Utxo {
+            outpoint: OutPoint {
+                txid: [9; 32].into(),
+                vout: i as u32,
+            },
+            value: i,
+            height: 10,
+        })
+        .collect();
+    let fee_per_vbyte = 5_000;
+    // The UTXOs worth less than 68 vbytes * 5 sat/vbyte = 340 satoshi are not worth spending.
+    let expected_values: Vec<u64> = (341..341 + MAX_CONSOLIDATION_INPUTS as u64).collect();
+
+    let utxos = consolidation_utxos_selection(&mut available_utxos, fee_per_vbyte);
+
+    assert_eq!(
+        utxos.iter().map(|u| u.value).collect::<Vec<_>>(),
+        expected_values
+    );
+    assert!(utxos.iter().all(|u| !available_utxos.contains(u)));
+
+    // The minter doesn't manage too many UTXOs anymore.
+    assert!(available_utxos.len() <= crate::UTXOS_COUNT_THRESHOLD);
+    assert_eq!(
+        consolidation_utxos_selection(&mut available_utxos, fee_per_vbyte),
+        vec![]
+    );
+}
+
+#[test]
+fn should_build_consolidation_transaction() {
+    let utxos: Vec<Utxo> = (1..=10)
+        .map(|i| dummy_utxo_from_value(i * 10_000))
+        .collect();
+    let minter_addr = BitcoinAddress::P2wpkhV0([0; 20]);
+    let fee_per_vbyte = 2_000;
+
+    let (tx, change_output) =
+        build_consolidation_transaction(&utxos, minter_addr.clone(), fee_per_vbyte)
+            .expect("failed to build a transaction");
+    let fee = fake_sign(&tx).vsize() as u64 * fee_per_vbyte / 1000;
+
+    assert_eq!(tx.inputs.len(), utxos.len());
+    assert_eq!(
+        tx.outputs,
+        vec![tx::TxOut {
+            address: minter_addr.clone(),
+            value: 550_000 - fee,
+        }]
+    );
+    assert_eq!(
+        change_output,
+        ChangeOutput {
+            vout: 0,
+            value: 550_000 - fee
+        }
+    );
+
+    let dust = vec![dummy_utxo_from_value(400), dummy_utxo_from_value(500)];
+    assert_eq!(
+        build_consolidation_transaction(&dust, minter_addr, 10_000).unwrap_err(),
+        BuildTxError::AmountTooLow
+    );
+}
+
+#[test]
+fn should_track_consolidation_fee_budget() {
+    let mut state = CkBtcMinterState::from(default_init_args());
+    let minter_addr = BitcoinAddress::P2wpkhV0([0; 20]);
+    let fee_per_vbyte = 2_000;
+
+    let mut available_utxos: BTreeSet<Utxo> = btreeset! {
+        dummy_utxo_from_value(1_000_000),
+        dummy_utxo_from_value(2_000_000),
+    };
+    let request = RetrieveBtcRequest {
+        amount: 2_500_000,
+        address: BitcoinAddress::P2wpkhV0([1; 20]),
+        block_index: 0,
+        received_at: 0,
+        kyt_provider: None,
+        reimbursement_account: None,
+        batch_id: None,
+    };
+    let (tx, change_output, used_utxos) = build_unsigned_transaction(
+        &mut available_utxos,
+        vec![(request.address.clone(), request.amount)],
+        minter_addr.clone(),
+        fee_per_vbyte,
+    )
+    .expect("failed to build a transaction");
+    state.push_submitted_transaction(SubmittedBtcTransaction {
+        requests: vec![request],
+        txid: tx.txid(),
+        used_utxos,
+        submitted_at: 0,
+        change_output: Some(change_output),
+        fee_per_vbyte: Some(fee_per_vbyte),
+    });
+
+    let minter_fee = evaluate_minter_fee(2, 2);
+    assert_eq!(state.accrued_minter_fees, minter_fee);
+    assert_eq!(state.consolidation_fee_budget(), minter_fee);
+
+    let utxos: Vec<Utxo> = (1..=3).map(|i| dummy_utxo_from_value(i * 10_000)).collect();
+    let consolidation = |fee_per_vbyte: u64| {
+        let (tx, change_output) =
+            build_consolidation_transaction(&utxos, minter_addr.clone(), fee_per_vbyte)
+                .expect("failed to build a transaction");
+        SubmittedBtcTransaction {
+            requests: vec![],
+            txid: tx.txid(),
+            used_utxos: utxos.clone(),
+            submitted_at: 0,
+            change_output: Some(change_output),
+            fee_per_vbyte: Some(fee_per_vbyte),
+        }
+    };
+
+    let tx = consolidation(500);
+    let txid = tx.txid;
+    let fee = tx.consolidation_fee();
+    state.push_consolidation_transaction(tx);
+    assert_eq!(state.consolidation_fees_spent, fee);
+    assert_eq!(state.consolidation_fee_budget(), minter_fee - fee);
+
+    // The replacement transaction pays a higher fee.
+    let replacement = consolidation(1_000);
+    let new_fee = replacement.consolidation_fee();
+    assert!(new_fee > fee);
+    state.replace_transaction(&txid, replacement);
+    assert_eq!(state.consolidation_fees_spent, new_fee);
+    assert_eq!(state.accrued_minter_fees, minter_fee);
+}
+
 #[test]
 fn should_have_same_input_and_output_count() {
     let mut available_utxos = BTreeSet::new();
@@ -482,6 +723,45 @@ proptest! {
         );
     }
 
+    #[test]
+    fn branch_and_bound_solution_properties(
+        values in pvec(1u64..1_000_000_000, 1..20),
+        target in 1u64..1_000_000_000,
+        fee_per_vbyte in 1_000u64..100_000,
+    ) {
+        let utxos: BTreeSet<Utxo> = values
+            .into_iter()
+            .map(dummy_utxo_from_value)
+            .collect();
+        let input_fee = 68 * fee_per_vbyte / 1000;
+        let spendable_value = utxos
+            .iter()
+            .filter(|u| u.value > input_fee)
+            .map(|u| u.value)
+            .sum::<u64>();
+
+        let solution = branch_and_bound(target, &utxos, fee_per_vbyte);
+        prop_assert_eq!(
+            solution.is_some(),
+            spendable_value >= target,
+            "branch_and_bound() must find a solution iff the UTXOs worth spending reach the target"
+        );
+
+        if let Some(solution) = solution {
+            let total = solution.iter().map(|u| u.value).sum::<u64>();
+
+            prop_assert!(
+                total >= target,
+                "branch_and_bound() must reach the specified target amount"
+            );
+
+            prop_assert!(
+                solution.iter().all(|u| utxos.contains(u)),
+                "branch_and_bound() must select utxos from the available set"
+            );
+        }
+    }
+
     #[test]
     fn greedy_does_not_modify_input_when_fails(
         values in pvec(1u64..1_000_000_000, 1..10),
@@ -541,10 +821,14 @@ proptest! {
                 sequence: *seq,
             })
             .collect();
+        let spent_addresses: Vec<_> = inputs_data
+            .iter()
+            .map(|(_, _, pubkey)| BitcoinAddress::P2wpkhV0(tx::hash160(pubkey)))
+            .collect();
         let arb_tx = tx::UnsignedTransaction { inputs, outputs, lock_time };
         let btc_tx = unsigned_tx_to_bitcoin_tx(&arb_tx);
 
-        let sighasher = tx::TxSigHasher::new(&arb_tx);
+        let sighasher = tx::TxSigHasher::new(&arb_tx, &spent_addresses);
         let mut btc_sighasher = bitcoin::util::sighash::SighashCache::new(&btc_tx);
 
         for (i, (utxo, _, pubkey)) in inputs_data.iter().enumerate() {
@@ -565,6 +849,57 @@ proptest! {
         }
     }
 
+    #[test]
+    fn unsigned_tx_taproot_sighash_model(
+        inputs_data in pvec(
+            (
+                arbitrary::utxo(5_000u64..1_000_000_000),
+                any::<u32>(),
+                arbitrary::address(),
+            ),
+            1..20
+        ),
+        outputs in pvec(arbitrary::tx_out(), 1..20),
+        lock_time in any::<u32>(),
+    ) {
+        let inputs: Vec<tx::UnsignedInput> = inputs_data
+            .iter()
+            .map(|(utxo, seq, _)| tx::UnsignedInput {
+                previous_output: utxo.outpoint.clone(),
+                value: utxo.value,
+                sequence: *seq,
+            })
+            .collect();
+        let spent_addresses: Vec<_> = inputs_data
+            .iter()
+            .map(|(_, _, address)| address.clone())
+            .collect();
+        let prevouts: Vec<bitcoin::TxOut> = inputs_data
+            .iter()
+            .map(|(utxo, _, address)| bitcoin::TxOut {
+                value: utxo.value,
+                script_pubkey: address_to_script_pubkey(address),
+            })
+            .collect();
+        let arb_tx = tx::UnsignedTransaction { inputs, outputs, lock_time };
+        let btc_tx = unsigned_tx_to_bitcoin_tx(&arb_tx);
+
+        let sighasher = tx::TxSigHasher::new(&arb_tx, &spent_addresses);
+        let mut btc_sighasher = bitcoin::util::sighash::SighashCache::new(&btc_tx);
+
+        for i in 0..inputs_data.len() {
+            let sighash = sighasher.taproot_sighash(i);
+            let btc_sighash = btc_sighasher
+                .taproot_key_spend_signature_hash(
+                    i,
+                    &bitcoin::util::sighash::Prevouts::All(&prevouts),
+                    bitcoin::SchnorrSighashType::Default,
+                )
+                .expect("failed to compute taproot sighash");
+            prop_assert_eq!(hex::encode(sighash), hex::encode(btc_sighash));
+        }
+    }
+
     #[test]
     fn signed_tx_encoding_model(
         inputs in pvec(arbitrary::signed_input(), 1..20),
@@ -1026,6 +1361,7 @@ fn can_form_a_batch_conditions() {
         received_at: 10000,
         kyt_provider: None,
         reimbursement_account: None,
+        batch_id: None,
     };
     state.pending_retrieve_btc_requests.push(req);
     // One request, >= min_pending, pass.
@@ -1052,12 +1388,110 @@ fn can_form_a_batch_conditions() {
         received_at: 10501,
         kyt_provider: None,
         reimbursement_account: None,
+        batch_id: None,
     };
     state.pending_retrieve_btc_requests.push(req);
     // Two request, long enough since last_transaction_submission_time, pass.
     assert!(state.can_form_a_batch(10, 10600));
 }
 
+#[test]
+fn build_batch_keeps_batched_requests_together() {
+    let mut state = CkBtcMinterState::from(InitArgs {
+        retrieve_btc_min_amount: 1,
+        ..default_init_args()
+    });
+    state.available_utxos.insert(dummy_utxo_from_value(10));
+
+    let request = |block_index: u64, amount: u64, batch_id: Option<u64>| RetrieveBtcRequest {
+        amount,
+        address: BitcoinAddress::P2wpkhV0([0; 20]),
+        block_index,
+        received_at: 10_000,
+        kyt_provider: None,
+        reimbursement_account: None,
+        batch_id,
+    };
+    state.pending_retrieve_btc_requests = vec![
+        request(0, 2, None),
+        request(1, 4, Some(1)),
+        request(2, 5, Some(1)),
+        request(3, 3, Some(3)),
+        request(4, 3, Some(3)),
+        request(5, 1, None),
+    ];
+
+    // The first retrieve_btc_batch doesn't fit, the second one does.
+    let batch = state.build_batch(3);
+    assert_eq!(
+        batch.iter().map(|req| req.block_index).collect::<Vec<_>>(),
+        vec![0, 3, 4]
+    );
+    assert_eq!(
+        state
+            .pending_retrieve_btc_requests
+            .iter()
+            .map(|req| req.block_index)
+            .collect::<Vec<_>>(),
+        vec![1, 2, 5]
+    );
+
+    // A retrieve_btc_batch is never split to respect the size limit.
+    let batch = state.build_batch(1);
+    assert_eq!(
+        batch.iter().map(|req| req.block_index).collect::<Vec<_>>(),
+        vec![5]
+    );
+}
+
+#[test]
+fn build_batch_holds_back_batches_in_progress() {
+    let mut state = CkBtcMinterState::from(InitArgs {
+        retrieve_btc_min_amount: 1,
+        ..default_init_args()
+    });
+    state.available_utxos.insert(dummy_utxo_from_value(10));
+
+    let request = |block_index: u64, received_at: u64, batch_id: Option<u64>| RetrieveBtcRequest {
+        amount: 1,
+        address: BitcoinAddress::P2wpkhV0([0; 20]),
+        block_index,
+        received_at,
+        kyt_provider: None,
+        reimbursement_account: None,
+        batch_id,
+    };
+
+    // The minter burns the requests of a batch one after the other while it
+    // accepts other requests.
+    state.retrieve_btc_batches_in_progress.insert(1);
+    state.insert_pending_request(request(0, 10, None));
+    state.insert_pending_request(request(1, 20, Some(1)));
+    state.insert_pending_request(request(2, 30, None));
+    state.insert_pending_request(request(3, 20, Some(1)));
+    assert_eq!(
+        state
+            .pending_retrieve_btc_requests
+            .iter()
+            .map(|req| req.block_index)
+            .collect::<Vec<_>>(),
+        vec![0, 1, 3, 2]
+    );
+
+    let batch = state.build_batch(10);
+    assert_eq!(
+        batch.iter().map(|req| req.block_index).collect::<Vec<_>>(),
+        vec![0, 2]
+    );
+
+    state.retrieve_btc_batches_in_progress.remove(&1);
+    let batch = state.build_batch(10);
+    assert_eq!(
+        batch.iter().map(|req| req.block_index).collect::<Vec<_>>(),
+        vec![1, 3]
+    );
+}
+
 #[test]
 fn test_build_account_to_utxos_table_pagination() {
     use crate::dashboard;
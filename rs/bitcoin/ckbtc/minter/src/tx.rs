@@ -1,5 +1,5 @@
-//! This module contains definitions of Bitcoin P2PKWH transactions and rules to
-//! encode them into a byte stream.
+//! This module contains definitions of Bitcoin transactions spending P2WPKH
+//! and P2TR outputs and rules to encode them into a byte stream.
 
 use crate::address::BitcoinAddress;
 use crate::signature::EncodedSignature;
@@ -16,12 +16,18 @@ pub const TX_VERSION: u32 = 2;
 /// The length of the public key.
 pub const PUBKEY_LEN: usize = 32;
 
+/// The length of a BIP-340 signature.
+pub const BIP340_SIGNATURE_LEN: usize = 64;
+
 // The marker indicating the segregated witness encoding.
 const MARKER: u8 = 0;
 // The flags for the segregated witness encoding.
 const FLAGS: u8 = 1;
 // The signature applies to all inputs and outputs.
 pub const SIGHASH_ALL: u32 = 1;
+// Same as SIGHASH_ALL for Taproot inputs, but the signature omits the sighash type byte.
+// See https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#common-signature-message.
+pub const SIGHASH_DEFAULT: u8 = 0;
 
 /// Bitcoin script opcodes.
 mod ops {
@@ -196,14 +202,30 @@ pub fn write_compact_size(n: usize, buf: &mut impl Buffer) {
     }
 }
 
+/// The data that unlocks an input in the witness part of the transaction.
+#[derive(Eq, PartialEq, Debug)]
+pub enum InputWitness {
+    /// Spends a P2WPKH output: <signature> <pubkey>.
+    /// See BIP-141.
+    P2wpkh {
+        signature: EncodedSignature,
+        // The public key bytes.
+        // Must be PUBKEY_LEN bytes long.
+        pubkey: ByteBuf,
+    },
+    /// Spends a P2TR output using the key path: <signature>.
+    /// See BIP-341.
+    P2trKeyPath {
+        // The BIP-340 signature of the SIGHASH_DEFAULT message.
+        signature: [u8; BIP340_SIGNATURE_LEN],
+    },
+}
+
 #[derive(Eq, PartialEq, Debug)]
 pub struct SignedInput {
     pub previous_output: OutPoint,
     pub sequence: u32,
-    pub signature: EncodedSignature,
-    // The public key bytes.
-    // Must be PUBKEY_LEN bytes long.
-    pub pubkey: ByteBuf,
+    pub witness: InputWitness,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -249,44 +271,95 @@ pub fn encode_p2sh_script_code(script_hash: &[u8; 20], buf: &mut impl Buffer) {
     buf.write(&[ops::EQUAL][..]);
 }
 
+/// Computes SHA256(tag) || SHA256(tag) || data as defined in BIP-340.
+fn tagged_hash(tag: &str, data: &[u8]) -> [u8; 32] {
+    let tag_hash = Sha256::hash(tag.as_bytes());
+    let mut hasher = Sha256::new();
+    hasher.write(&tag_hash);
+    hasher.write(&tag_hash);
+    hasher.write(data);
+    hasher.finish()
+}
+
 pub struct TxSigHasher<'a> {
     tx: &'a UnsignedTransaction,
+    // The BIP-143 hashes are the double SHA256 of the data, the BIP-341
+    // hashes are the single SHA256 of the same data.
+    sha_prevouts: [u8; 32],
+    sha_sequences: [u8; 32],
+    sha_outputs: [u8; 32],
+    sha_amounts: [u8; 32],
+    sha_scriptpubkeys: [u8; 32],
     hash_prevouts: [u8; 32],
     hash_sequence: [u8; 32],
     hash_outputs: [u8; 32],
 }
 
 impl<'a> TxSigHasher<'a> {
-    pub fn new(tx: &'a UnsignedTransaction) -> Self {
-        let hash_prevouts = {
+    /// Creates a sighasher for the transaction spending outputs locked by
+    /// the specified addresses, one address per transaction input.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the number of addresses does not match the
+    /// number of transaction inputs.
+    pub fn new(tx: &'a UnsignedTransaction, spent_addresses: &[BitcoinAddress]) -> Self {
+        assert_eq!(
+            tx.inputs.len(),
+            spent_addresses.len(),
+            "bug: each transaction input must have a spent address"
+        );
+
+        let sha_prevouts = {
             let mut hasher = Sha256::new();
             for input in tx.inputs.iter() {
                 input.previous_output.encode(&mut hasher);
             }
-            Sha256::hash(&hasher.finish())
+            hasher.finish()
         };
 
-        let hash_sequence = {
+        let sha_sequences = {
             let mut hasher = Sha256::new();
             for input in tx.inputs.iter() {
                 input.sequence.encode(&mut hasher);
             }
-            Sha256::hash(&hasher.finish())
+            hasher.finish()
         };
 
-        let hash_outputs = {
+        let sha_outputs = {
             let mut hasher = Sha256::new();
             for output in tx.outputs.iter() {
                 output.encode(&mut hasher);
             }
-            Sha256::hash(&hasher.finish())
+            hasher.finish()
+        };
+
+        let sha_amounts = {
+            let mut hasher = Sha256::new();
+            for input in tx.inputs.iter() {
+                input.value.encode(&mut hasher);
+            }
+            hasher.finish()
+        };
+
+        let sha_scriptpubkeys = {
+            let mut hasher = Sha256::new();
+            for address in spent_addresses {
+                encode_address_script_pubkey(address, &mut hasher);
+            }
+            hasher.finish()
         };
 
         Self {
             tx,
-            hash_prevouts,
-            hash_sequence,
-            hash_outputs,
+            sha_prevouts,
+            sha_sequences,
+            sha_outputs,
+            sha_amounts,
+            sha_scriptpubkeys,
+            hash_prevouts: Sha256::hash(&sha_prevouts),
+            hash_sequence: Sha256::hash(&sha_sequences),
+            hash_outputs: Sha256::hash(&sha_outputs),
         }
     }
 
@@ -338,6 +411,51 @@ impl<'a> TxSigHasher<'a> {
         self.encode_sighash_data(input, pkhash, &mut hasher);
         Sha256::hash(&hasher.finish())
     }
+
+    /// Encodes the signature message of the input with the specified index
+    /// for a Taproot key path spend with the SIGHASH_DEFAULT type.
+    pub fn encode_taproot_sighash_data(&self, input_index: usize, buf: &mut impl Buffer) {
+        debug_assert!(input_index < self.tx.inputs.len());
+
+        // Spec:
+        // https://github.com/bitcoin/bips/blob/master/bip-0341.mediawiki#common-signature-message
+        //
+        //      1. sighash epoch (1 byte), see BIP-341 footnote 20
+        buf.write(&[0]);
+        //      2. hash_type (1 byte)
+        buf.write(&[SIGHASH_DEFAULT]);
+        //      3. nVersion of the transaction (4-byte little endian)
+        TX_VERSION.encode(buf);
+        //      4. nLocktime of the transaction (4-byte little endian)
+        self.tx.lock_time.encode(buf);
+        //      5. sha_prevouts (32-byte hash)
+        buf.write(&self.sha_prevouts[..]);
+        //      6. sha_amounts (32-byte hash)
+        buf.write(&self.sha_amounts[..]);
+        //      7. sha_scriptpubkeys (32-byte hash)
+        buf.write(&self.sha_scriptpubkeys[..]);
+        //      8. sha_sequences (32-byte hash)
+        buf.write(&self.sha_sequences[..]);
+        //      9. sha_outputs (32-byte hash)
+        buf.write(&self.sha_outputs[..]);
+        //     10. spend_type (1 byte): key path spend without annex
+        buf.write(&[0]);
+        //     11. input_index (4-byte little endian)
+        (input_index as u32).encode(buf);
+    }
+
+    /// Returns the bytes that the input with the specified index needs to sign
+    /// for a Taproot key path spend.
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the `input_index` is invalid transaction input index.
+    pub fn taproot_sighash(&self, input_index: usize) -> [u8; 32] {
+        assert!(input_index < self.tx.inputs.len());
+        let mut buf = Vec::<u8>::new();
+        self.encode_taproot_sighash_data(input_index, &mut buf);
+        tagged_hash("TapSighash", &buf)
+    }
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -509,6 +627,17 @@ impl Encode for SignedInput {
     }
 }
 
+impl Encode for InputWitness {
+    fn encode(&self, buf: &mut impl Buffer) {
+        match self {
+            Self::P2wpkh { signature, pubkey } => {
+                [Bytes::new(signature.as_slice()), Bytes::new(pubkey)][..].encode(buf)
+            }
+            Self::P2trKeyPath { signature } => [Bytes::new(signature)][..].encode(buf),
+        }
+    }
+}
+
 impl Encode for TxOut {
     fn encode(&self, buf: &mut impl Buffer) {
         self.value.encode(buf);
@@ -538,11 +667,7 @@ impl Encode for SignedTransaction {
         self.inputs.encode(buf);
         self.outputs.encode(buf);
         for txin in self.inputs.iter() {
-            [
-                Bytes::new(txin.signature.as_slice()),
-                Bytes::new(&txin.pubkey),
-            ][..]
-                .encode(buf);
+            txin.witness.encode(buf);
         }
         self.lock_time.encode(buf)
     }
@@ -1,5 +1,7 @@
 use crate::{
+    address::DepositAddressType,
     logs::P1,
+    management::CallError,
     state::{mutate_state, read_state, CkBtcMinterState},
     ECDSAPublicKey,
 };
@@ -13,6 +15,9 @@ use serde::Serialize;
 pub struct GetBtcAddressArgs {
     pub owner: Option<Principal>,
     pub subaccount: Option<Subaccount>,
+    /// The type of the deposit address.
+    /// The minter returns a P2WPKH address if the type is None.
+    pub address_type: Option<DepositAddressType>,
 }
 
 /// PRECONDITION: s.ecdsa_public_key.is_some()
@@ -26,22 +31,59 @@ pub fn account_to_p2wpkh_address_from_state(s: &CkBtcMinterState, account: &Acco
     )
 }
 
+/// PRECONDITION: s.bip340_public_key.is_some()
+pub fn account_to_p2tr_address_from_state(s: &CkBtcMinterState, account: &Account) -> String {
+    crate::address::account_to_p2tr_address(
+        s.bip340_public_key
+            .as_ref()
+            .expect("bug: the BIP-340 public key must be initialized"),
+        account,
+    )
+    .display(s.btc_network)
+}
+
+/// Returns the deposit address of the specified type for the account.
+///
+/// PRECONDITION: the public key corresponding to the address type is initialized,
+/// see [init_deposit_public_key].
+pub fn account_to_deposit_address_from_state(
+    s: &CkBtcMinterState,
+    account: &Account,
+    address_type: DepositAddressType,
+) -> String {
+    match address_type {
+        DepositAddressType::P2wpkhV0 => account_to_p2wpkh_address_from_state(s, account),
+        DepositAddressType::P2trV1 => account_to_p2tr_address_from_state(s, account),
+    }
+}
+
 pub async fn get_btc_address(args: GetBtcAddressArgs) -> String {
     let owner = args.owner.unwrap_or_else(ic_cdk::caller);
+    let address_type = args.address_type.unwrap_or_default();
 
-    init_ecdsa_public_key().await;
+    init_deposit_public_key(address_type).await;
 
     read_state(|s| {
-        account_to_p2wpkh_address_from_state(
+        account_to_deposit_address_from_state(
             s,
             &Account {
                 owner,
                 subaccount: args.subaccount,
             },
+            address_type,
         )
     })
 }
 
+/// Initializes the Minter public key used to derive deposit addresses of the
+/// specified type.
+pub async fn init_deposit_public_key(address_type: DepositAddressType) -> ECDSAPublicKey {
+    match address_type {
+        DepositAddressType::P2wpkhV0 => init_ecdsa_public_key().await,
+        DepositAddressType::P2trV1 => init_bip340_public_key().await,
+    }
+}
+
 /// Initializes the Minter ECDSA public key. This function must be called
 /// before any endpoint runs its logic.
 pub async fn init_ecdsa_public_key() -> ECDSAPublicKey {
@@ -66,6 +108,37 @@ pub async fn init_ecdsa_public_key() -> ECDSAPublicKey {
     ecdsa_public_key
 }
 
+/// Initializes the Minter BIP-340 public key that backs P2TR deposit
+/// addresses.
+pub async fn init_bip340_public_key() -> ECDSAPublicKey {
+    try_init_bip340_public_key()
+        .await
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("failed to retrieve BIP-340 public key: {e}")))
+}
+
+/// Same as [init_bip340_public_key], but returns an error instead of trapping
+/// if the minter fails to fetch the key.
+pub async fn try_init_bip340_public_key() -> Result<ECDSAPublicKey, CallError> {
+    if let Some(key) = read_state(|s| s.bip340_public_key.clone()) {
+        return Ok(key);
+    };
+    // The threshold Schnorr key shares the name with the ECDSA key.
+    let key_name = read_state(|s| s.ecdsa_key_name.clone());
+    log!(P1, "Fetching the BIP-340 public key {}", &key_name);
+    let bip340_public_key =
+        crate::management::schnorr_public_key(key_name, DerivationPath::new(vec![])).await?;
+    log!(
+        P1,
+        "BIP-340 public key set to {}, chain code to {}",
+        hex::encode(&bip340_public_key.public_key),
+        hex::encode(&bip340_public_key.chain_code)
+    );
+    mutate_state(|s| {
+        s.bip340_public_key = Some(bip340_public_key.clone());
+    });
+    Ok(bip340_public_key)
+}
+
 #[cfg(test)]
 mod tests {
     use ic_btc_interface::Network;
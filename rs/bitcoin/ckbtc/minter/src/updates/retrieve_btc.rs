@@ -21,6 +21,8 @@ use icrc_ledger_types::icrc1::transfer::Memo;
 use icrc_ledger_types::icrc1::transfer::{TransferArg, TransferError};
 use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
 use num_traits::cast::ToPrimitive;
+use scopeguard::guard;
+use std::collections::BTreeSet;
 
 const MAX_CONCURRENT_PENDING_REQUESTS: usize = 1000;
 
@@ -47,6 +49,26 @@ pub struct RetrieveBtcWithApprovalArgs {
     pub from_subaccount: Option<Subaccount>,
 }
 
+/// A single withdrawal of the [retrieve_btc_batch] endpoint.
+#[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub struct RetrieveBtcBatchItem {
+    // amount to retrieve in satoshi
+    pub amount: u64,
+
+    // address where to send bitcoins
+    pub address: String,
+}
+
+/// The arguments of the [retrieve_btc_batch] endpoint.
+#[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub struct RetrieveBtcBatchArgs {
+    // withdrawals to include in the same Bitcoin transaction
+    pub requests: Vec<RetrieveBtcBatchItem>,
+
+    // The subaccount to burn ckBTC from.
+    pub from_subaccount: Option<Subaccount>,
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
 pub struct RetrieveBtcOk {
     // the index of the burn block on the ckbtc ledger
@@ -57,6 +79,8 @@ pub enum ErrorCode {
     // The retrieval address didn't pass the Bitcoin check.
     TaintedAddress = 1,
     CheckCallFailed = 2,
+    // The batch is empty or contains too many requests.
+    InvalidBatch = 3,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
@@ -230,6 +254,7 @@ pub async fn retrieve_btc(args: RetrieveBtcArgs) -> Result<RetrieveBtcOk, Retrie
             owner: caller,
             subaccount: None,
         }),
+        batch_id: None,
     };
 
     log!(
@@ -299,26 +324,7 @@ pub async fn retrieve_btc_with_approval(
             .into()
     });
 
-    match check_address(btc_checker_principal, parsed_address.display(btc_network)).await {
-        Err(error) => {
-            return Err(RetrieveBtcWithApprovalError::GenericError {
-                error_message: format!(
-                    "Failed to call Bitcoin checker canister with error: {:?}",
-                    error
-                ),
-                error_code: ErrorCode::CheckCallFailed as u64,
-            })
-        }
-        Ok(status) => match status {
-            BtcAddressCheckStatus::Tainted => {
-                return Err(RetrieveBtcWithApprovalError::GenericError {
-                    error_message: "Destination address is tainted".to_string(),
-                    error_code: ErrorCode::TaintedAddress as u64,
-                });
-            }
-            BtcAddressCheckStatus::Clean => {}
-        },
-    }
+    check_destination_address(btc_checker_principal, parsed_address.display(btc_network)).await?;
 
     let burn_memo_icrc2 = BurnMemo::Convert {
         address: Some(&args.address),
@@ -342,6 +348,7 @@ pub async fn retrieve_btc_with_approval(
             owner: caller,
             subaccount: args.from_subaccount,
         }),
+        batch_id: None,
     };
 
     mutate_state(|s| state::audit::accept_retrieve_btc_request(s, request, &IC_CANISTER_RUNTIME));
@@ -356,6 +363,157 @@ pub async fn retrieve_btc_with_approval(
     Ok(RetrieveBtcOk { block_index })
 }
 
+/// Submits several withdrawals that the minter includes in the same Bitcoin
+/// transaction.
+///
+/// The minter validates all the requests before burning any ckBTC. Each
+/// request is then deliberately burned with its own `icrc2_transfer_from`
+/// call, against the single approval of the caller: the burn block index is
+/// the identifier of a withdrawal in [retrieve_btc_status], in the event log
+/// and for reimbursements, so every request needs its own burn. This costs
+/// one ledger call per request and the batch can partially fail. The minter
+/// accepts each request as soon as it is burned, but it submits the requests
+/// of the batch only once it has burned all of them. If a burn fails, the
+/// minter doesn't attempt to burn the remaining requests of the batch, and
+/// the requests burned so far are processed as usual.
+pub async fn retrieve_btc_batch(
+    args: RetrieveBtcBatchArgs,
+) -> Result<Vec<Result<RetrieveBtcOk, RetrieveBtcWithApprovalError>>, RetrieveBtcWithApprovalError>
+{
+    let caller = ic_cdk::caller();
+
+    state::read_state(|s| s.mode.is_withdrawal_available_for(&caller))
+        .map_err(RetrieveBtcWithApprovalError::TemporarilyUnavailable)?;
+
+    if args.requests.is_empty() || args.requests.len() > crate::MAX_REQUESTS_PER_BATCH {
+        return Err(RetrieveBtcWithApprovalError::GenericError {
+            error_message: format!(
+                "The batch must contain between 1 and {} requests",
+                crate::MAX_REQUESTS_PER_BATCH
+            ),
+            error_code: ErrorCode::InvalidBatch as u64,
+        });
+    }
+
+    let ecdsa_public_key = init_ecdsa_public_key().await;
+    let main_address = account_to_bitcoin_address(
+        &ecdsa_public_key,
+        &Account {
+            owner: ic_cdk::id(),
+            subaccount: None,
+        },
+    );
+
+    let (min_retrieve_amount, btc_network) =
+        read_state(|s| (s.fee_based_retrieve_btc_min_amount, s.btc_network));
+    if args
+        .requests
+        .iter()
+        .any(|req| req.address == main_address.display(btc_network))
+    {
+        ic_cdk::trap("illegal retrieve_btc target");
+    }
+    let caller_account = Account {
+        owner: caller,
+        subaccount: args.from_subaccount,
+    };
+    let _guard = retrieve_btc_guard(caller_account)?;
+    let mut parsed_addresses = Vec::with_capacity(args.requests.len());
+    for req in &args.requests {
+        if req.amount < min_retrieve_amount {
+            return Err(RetrieveBtcWithApprovalError::AmountTooLow(
+                min_retrieve_amount,
+            ));
+        }
+        parsed_addresses.push(BitcoinAddress::parse(&req.address, btc_network)?);
+    }
+    if read_state(|s| {
+        s.count_incomplete_retrieve_btc_requests() + args.requests.len()
+            > MAX_CONCURRENT_PENDING_REQUESTS
+    }) {
+        return Err(RetrieveBtcWithApprovalError::TemporarilyUnavailable(
+            "too many pending retrieve_btc requests".to_string(),
+        ));
+    }
+
+    let btc_checker_principal = read_state(|s| {
+        s.btc_checker_principal
+            .expect("BUG: upgrade procedure must ensure that the Bitcoin checker principal is set")
+            .get()
+            .into()
+    });
+    let distinct_addresses: BTreeSet<String> = parsed_addresses
+        .iter()
+        .map(|address| address.display(btc_network))
+        .collect();
+    for address in distinct_addresses {
+        check_destination_address(btc_checker_principal, address).await?;
+    }
+
+    let mut results = Vec::with_capacity(args.requests.len());
+    // The batch id is the burn block index of the first request of the batch.
+    // The guard holds the requests of the batch back until all of them are
+    // burned so that the minter includes them in the same transaction.
+    let mut batch_guard = None;
+    for (req, address) in args.requests.iter().zip(parsed_addresses) {
+        if results.last().is_some_and(Result::is_err) {
+            results.push(Err(RetrieveBtcWithApprovalError::TemporarilyUnavailable(
+                "not processed because a previous request of the batch failed".to_string(),
+            )));
+            continue;
+        }
+        let burn_memo_icrc2 = BurnMemo::Convert {
+            address: Some(&req.address),
+            kyt_fee: None,
+            status: None,
+        };
+        let result = burn_ckbtcs_icrc2(
+            caller_account,
+            req.amount,
+            crate::memo::encode(&burn_memo_icrc2).into(),
+        )
+        .await;
+        if let Ok(block_index) = result {
+            let (batch_id, received_at) = **batch_guard.get_or_insert_with(|| {
+                mutate_state(|s| s.retrieve_btc_batches_in_progress.insert(block_index));
+                guard((block_index, ic_cdk::api::time()), |(batch_id, _)| {
+                    mutate_state(|s| s.retrieve_btc_batches_in_progress.remove(&batch_id));
+                })
+            });
+            // Accept the request in the same message execution as the burn so
+            // that the minter keeps track of the burned tokens even if a later
+            // call of the batch fails.
+            let request = RetrieveBtcRequest {
+                amount: req.amount,
+                address,
+                block_index,
+                received_at,
+                kyt_provider: None,
+                reimbursement_account: Some(caller_account),
+                batch_id: Some(batch_id),
+            };
+            mutate_state(|s| {
+                state::audit::accept_retrieve_btc_request(s, request, &IC_CANISTER_RUNTIME)
+            });
+        }
+        results.push(result.map(|block_index| RetrieveBtcOk { block_index }));
+    }
+
+    if let Some(batch_guard) = batch_guard {
+        let (batch_id, _) = *batch_guard;
+        drop(batch_guard);
+        log!(
+            P1,
+            "accepted a batch of {} retrieve btc requests (batch_id = {})",
+            results.iter().filter(|result| result.is_ok()).count(),
+            batch_id
+        );
+        schedule_now(TaskType::ProcessLogic, &IC_CANISTER_RUNTIME);
+    }
+
+    Ok(results)
+}
+
 async fn balance_of(user: Principal) -> Result<u64, RetrieveBtcError> {
     let client = ICRC1Client {
         runtime: CdkRuntime,
@@ -517,6 +675,26 @@ async fn burn_ckbtcs_icrc2(
     }
 }
 
+async fn check_destination_address(
+    btc_checker_principal: Principal,
+    address: String,
+) -> Result<(), RetrieveBtcWithApprovalError> {
+    match check_address(btc_checker_principal, address).await {
+        Err(error) => Err(RetrieveBtcWithApprovalError::GenericError {
+            error_message: format!(
+                "Failed to call Bitcoin checker canister with error: {:?}",
+                error
+            ),
+            error_code: ErrorCode::CheckCallFailed as u64,
+        }),
+        Ok(BtcAddressCheckStatus::Tainted) => Err(RetrieveBtcWithApprovalError::GenericError {
+            error_message: "Destination address is tainted".to_string(),
+            error_code: ErrorCode::TaintedAddress as u64,
+        }),
+        Ok(BtcAddressCheckStatus::Clean) => Ok(()),
+    }
+}
+
 /// The outcome of a Bitcoin address check.
 #[derive(Copy, Clone, Eq, PartialEq, Debug, serde::Deserialize, serde::Serialize)]
 pub enum BtcAddressCheckStatus {
@@ -63,6 +63,7 @@ mod update_balance {
             UpdateBalanceArgs {
                 owner: Some(account.owner),
                 subaccount: account.subaccount,
+                address_type: None,
             },
             &runtime,
         )
@@ -120,6 +121,7 @@ mod update_balance {
             UpdateBalanceArgs {
                 owner: Some(account.owner),
                 subaccount: account.subaccount,
+                address_type: None,
             },
             &runtime,
         )
@@ -142,6 +144,7 @@ mod update_balance {
                     mint_txid: Some(1),
                     to_account: account,
                     utxos: vec![ignored_utxo],
+                    address_type: None,
                 },
             ],
         );
@@ -190,6 +193,7 @@ mod update_balance {
             UpdateBalanceArgs {
                 owner: Some(account.owner),
                 subaccount: account.subaccount,
+                address_type: None,
             },
             &runtime,
         )
@@ -212,6 +216,7 @@ mod update_balance {
                     mint_txid: Some(1),
                     to_account: account,
                     utxos: vec![quarantined_utxo],
+                    address_type: None,
                 },
             ],
         );
@@ -259,6 +264,7 @@ mod update_balance {
             UpdateBalanceArgs {
                 owner: Some(account.owner),
                 subaccount: account.subaccount,
+                address_type: None,
             },
             &runtime,
         )
@@ -283,6 +289,7 @@ mod update_balance {
                     mint_txid: Some(1),
                     to_account: account,
                     utxos: vec![utxo],
+                    address_type: None,
                 },
             ],
         );
@@ -300,6 +307,7 @@ mod update_balance {
             let update_balance_args = UpdateBalanceArgs {
                 owner: Some(account.owner),
                 subaccount: account.subaccount,
+                address_type: None,
             };
             let mut runtime = MockCanisterRuntime::new();
             mock_schedule_now_process_logic(&mut runtime);
@@ -545,6 +553,7 @@ mod update_balance {
         let update_balance_args = UpdateBalanceArgs {
             owner: Some(account.owner),
             subaccount: account.subaccount,
+            address_type: None,
         };
         mock_get_utxos_for_account(&mut runtime, account, vec![utxo.clone()]);
 
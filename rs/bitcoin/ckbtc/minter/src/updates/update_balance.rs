@@ -17,9 +17,10 @@ use serde::Serialize;
 // many cycles.
 const MAX_CHECK_TRANSACTION_RETRY: usize = 10;
 
-use super::get_btc_address::init_ecdsa_public_key;
+use super::get_btc_address::init_deposit_public_key;
 
 use crate::{
+    address::DepositAddressType,
     guard::{balance_update_guard, GuardError},
     management::{get_utxos, CallError, CallSource},
     metrics::observe_update_call_latency,
@@ -37,6 +38,9 @@ pub struct UpdateBalanceArgs {
     pub owner: Option<Principal>,
     /// The desired subaccount on the ledger, if any.
     pub subaccount: Option<Subaccount>,
+    /// The type of the deposit address to check for new UTXOs.
+    /// The minter checks the P2WPKH address if the type is None.
+    pub address_type: Option<DepositAddressType>,
 }
 
 /// The outcome of UTXO processing.
@@ -158,7 +162,8 @@ pub async fn update_balance<R: CanisterRuntime>(
     state::read_state(|s| s.mode.is_deposit_available_for(&caller))
         .map_err(UpdateBalanceError::TemporarilyUnavailable)?;
 
-    init_ecdsa_public_key().await;
+    let address_type = args.address_type.unwrap_or_default();
+    init_deposit_public_key(address_type).await;
 
     let caller_account = Account {
         owner: args.owner.unwrap_or(caller),
@@ -167,7 +172,7 @@ pub async fn update_balance<R: CanisterRuntime>(
     let _guard = balance_update_guard(caller_account)?;
 
     let address = state::read_state(|s| {
-        get_btc_address::account_to_p2wpkh_address_from_state(s, &caller_account)
+        get_btc_address::account_to_deposit_address_from_state(s, &caller_account, address_type)
     });
 
     let (btc_network, min_confirmations) =
@@ -317,6 +322,7 @@ pub async fn update_balance<R: CanisterRuntime>(
                         s,
                         Some(block_index),
                         caller_account,
+                        address_type,
                         vec![utxo.clone()],
                         runtime,
                     )
@@ -127,6 +127,7 @@ async fn should_not_grow_number_of_useless_events() {
             mint_txid: None,
             to_account: file.minter_canister_id().into(),
             utxos: vec![],
+            address_type: None,
         };
 
         let useless_events_indexes =
@@ -16,8 +16,8 @@ use ic_ckbtc_minter::queries::{EstimateFeeArg, RetrieveBtcStatusRequest, Withdra
 use ic_ckbtc_minter::state::{BtcRetrievalStatusV2, Mode, RetrieveBtcStatus, RetrieveBtcStatusV2};
 use ic_ckbtc_minter::updates::get_btc_address::GetBtcAddressArgs;
 use ic_ckbtc_minter::updates::retrieve_btc::{
-    ErrorCode, RetrieveBtcArgs, RetrieveBtcError, RetrieveBtcOk, RetrieveBtcWithApprovalArgs,
-    RetrieveBtcWithApprovalError,
+    ErrorCode, RetrieveBtcArgs, RetrieveBtcBatchArgs, RetrieveBtcBatchItem, RetrieveBtcError,
+    RetrieveBtcOk, RetrieveBtcWithApprovalArgs, RetrieveBtcWithApprovalError,
 };
 use ic_ckbtc_minter::updates::update_balance::{
     PendingUtxo, UpdateBalanceArgs, UpdateBalanceError, UtxoStatus,
@@ -246,6 +246,7 @@ fn test_upgrade_read_only() {
     let update_balance_args = UpdateBalanceArgs {
         owner: None,
         subaccount: None,
+        address_type: None,
     };
     let res = env
         .execute_ingress_as(
@@ -312,6 +313,7 @@ fn test_upgrade_restricted() {
     let update_balance_args = UpdateBalanceArgs {
         owner: None,
         subaccount: None,
+        address_type: None,
     };
     let res = env
         .execute_ingress_as(
@@ -356,6 +358,7 @@ fn test_upgrade_restricted() {
     let update_balance_args = UpdateBalanceArgs {
         owner: None,
         subaccount: None,
+        address_type: None,
     };
 
     let res = env
@@ -399,6 +402,7 @@ fn test_no_new_utxos() {
     let update_balance_args = UpdateBalanceArgs {
         owner: None,
         subaccount: None,
+        address_type: None,
     };
     let res = ckbtc
         .env
@@ -469,6 +473,7 @@ fn update_balance_should_return_correct_confirmations() {
     let update_balance_args = UpdateBalanceArgs {
         owner: None,
         subaccount: None,
+        address_type: None,
     };
 
     let res = ckbtc
@@ -514,6 +519,7 @@ fn test_illegal_caller() {
     let update_balance_args = UpdateBalanceArgs {
         owner: Some(Principal::from_str(&minter_id.get().to_string()).unwrap()),
         subaccount: None,
+        address_type: None,
     };
     // This call should panick
     let res = env.execute_ingress_as(
@@ -571,6 +577,7 @@ fn test_minter() {
         &GetBtcAddressArgs {
             owner: None,
             subaccount: None,
+            address_type: None,
         },
     );
     let address_1 = Address::from_str(&btc_address_1).expect("invalid Bitcoin address");
@@ -580,6 +587,7 @@ fn test_minter() {
         &GetBtcAddressArgs {
             owner: None,
             subaccount: Some([1; 32]),
+            address_type: None,
         },
     );
     let address_2 = Address::from_str(&btc_address_2).expect("invalid Bitcoin address");
@@ -742,6 +750,7 @@ impl CkBtcSetup {
                         Encode!(&GetBtcAddressArgs {
                             owner: Some(account.owner),
                             subaccount: account.subaccount,
+                            address_type: None,
                         })
                         .unwrap(),
                     )
@@ -833,6 +842,7 @@ impl CkBtcSetup {
                         Encode!(&UpdateBalanceArgs {
                             owner: Some(account.owner),
                             subaccount: account.subaccount,
+                            address_type: None,
                         })
                         .unwrap()
                     )
@@ -875,6 +885,7 @@ impl CkBtcSetup {
                         Encode!(&UpdateBalanceArgs {
                             owner: Some(account.owner),
                             subaccount: account.subaccount,
+                            address_type: None,
                         })
                         .unwrap()
                     )
@@ -1006,6 +1017,37 @@ impl CkBtcSetup {
         ).unwrap()
     }
 
+    pub fn retrieve_btc_batch(
+        &self,
+        requests: Vec<RetrieveBtcBatchItem>,
+        from_subaccount: Option<[u8; 32]>,
+    ) -> Result<
+        Vec<Result<RetrieveBtcOk, RetrieveBtcWithApprovalError>>,
+        RetrieveBtcWithApprovalError,
+    > {
+        Decode!(
+            &assert_reply(
+                self.env
+                    .execute_ingress_as(
+                        self.caller,
+                        self.minter_id,
+                        "retrieve_btc_batch",
+                        Encode!(&RetrieveBtcBatchArgs {
+                            requests,
+                            from_subaccount
+                        })
+                        .unwrap()
+                    )
+                    .expect("failed to execute retrieve_btc_batch request")
+            ),
+            Result<
+                Vec<Result<RetrieveBtcOk, RetrieveBtcWithApprovalError>>,
+                RetrieveBtcWithApprovalError,
+            >
+        )
+        .unwrap()
+    }
+
     pub fn retrieve_btc_status(&self, block_index: u64) -> RetrieveBtcStatus {
         Decode!(
             &assert_reply(
@@ -1910,6 +1952,161 @@ fn test_retrieve_btc_with_approval() {
     assert_eq!(ckbtc.await_finalization(block_index, 10), txid);
 }
 
+#[test]
+fn test_retrieve_btc_batch() {
+    let ckbtc = CkBtcSetup::new();
+
+    // Step 1: deposit ckBTC
+
+    let deposit_value = 100_000_000;
+    let utxo = Utxo {
+        height: 0,
+        outpoint: OutPoint {
+            txid: range_to_txid(1..=32),
+            vout: 1,
+        },
+        value: deposit_value,
+    };
+
+    let user = Principal::from(ckbtc.caller);
+
+    ckbtc.deposit_utxo(user, utxo);
+    assert_eq!(ckbtc.balance_of(user), Nat::from(deposit_value - CHECK_FEE));
+
+    // Step 2: request a batch of withdrawals
+
+    assert_matches!(
+        ckbtc.retrieve_btc_batch(vec![], None),
+        Err(RetrieveBtcWithApprovalError::GenericError { error_code, .. })
+            if error_code == ErrorCode::InvalidBatch as u64
+    );
+
+    let requests = vec![
+        RetrieveBtcBatchItem {
+            address: WITHDRAWAL_ADDRESS.to_string(),
+            amount: 20_000_000,
+        },
+        RetrieveBtcBatchItem {
+            address: "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string(),
+            amount: 30_000_000,
+        },
+    ];
+    ckbtc.approve_minter(user, 50_000_000, None);
+
+    let block_indices: Vec<u64> = ckbtc
+        .retrieve_btc_batch(requests, None)
+        .expect("retrieve_btc_batch failed")
+        .into_iter()
+        .map(|result| {
+            result
+                .expect("retrieve_btc_batch request failed")
+                .block_index
+        })
+        .collect();
+    assert_eq!(block_indices.len(), 2);
+    assert_ne!(block_indices[0], block_indices[1]);
+    assert_eq!(
+        ckbtc.balance_of(user),
+        Nat::from(deposit_value - CHECK_FEE - 50_000_000 - TRANSFER_FEE)
+    );
+
+    ckbtc.env.advance_time(MAX_TIME_IN_QUEUE);
+
+    // Step 3: wait for both requests to be submitted in the same transaction
+
+    let txid = ckbtc.await_btc_transaction(block_indices[0], 10);
+    assert_eq!(ckbtc.await_btc_transaction(block_indices[1], 10), txid);
+    let mempool = ckbtc.mempool();
+    let tx = mempool
+        .get(&txid)
+        .expect("the mempool does not contain the withdrawal transaction");
+    assert_eq!(3, tx.output.len());
+
+    // Step 4: confirm the transaction
+
+    ckbtc.finalize_transaction(tx);
+    for block_index in block_indices {
+        assert_eq!(ckbtc.await_finalization(block_index, 10), txid);
+    }
+}
+
+#[test]
+fn test_retrieve_btc_batch_partial_failure() {
+    let ckbtc = CkBtcSetup::new();
+
+    // Step 1: deposit ckBTC
+
+    let deposit_value = 100_000_000;
+    let utxo = Utxo {
+        height: 0,
+        outpoint: OutPoint {
+            txid: range_to_txid(1..=32),
+            vout: 1,
+        },
+        value: deposit_value,
+    };
+
+    let user = Principal::from(ckbtc.caller);
+
+    ckbtc.deposit_utxo(user, utxo);
+    assert_eq!(ckbtc.balance_of(user), Nat::from(deposit_value - CHECK_FEE));
+
+    // Step 2: request a batch of withdrawals exceeding the approved amount
+
+    let requests = vec![
+        RetrieveBtcBatchItem {
+            address: WITHDRAWAL_ADDRESS.to_string(),
+            amount: 20_000_000,
+        },
+        RetrieveBtcBatchItem {
+            address: WITHDRAWAL_ADDRESS.to_string(),
+            amount: 30_000_000,
+        },
+        RetrieveBtcBatchItem {
+            address: WITHDRAWAL_ADDRESS.to_string(),
+            amount: 10_000_000,
+        },
+    ];
+    ckbtc.approve_minter(user, 25_000_000, None);
+
+    let results = ckbtc
+        .retrieve_btc_batch(requests, None)
+        .expect("retrieve_btc_batch failed");
+    assert_eq!(results.len(), 3);
+    let block_index = results[0]
+        .as_ref()
+        .expect("the first request should be burned")
+        .block_index;
+    assert_matches!(
+        results[1],
+        Err(RetrieveBtcWithApprovalError::InsufficientAllowance { allowance })
+            if allowance == 5_000_000
+    );
+    assert_matches!(
+        results[2],
+        Err(RetrieveBtcWithApprovalError::TemporarilyUnavailable(_))
+    );
+    // Only the first request was burned.
+    assert_eq!(
+        ckbtc.balance_of(user),
+        Nat::from(deposit_value - CHECK_FEE - 20_000_000 - TRANSFER_FEE)
+    );
+
+    ckbtc.env.advance_time(MAX_TIME_IN_QUEUE);
+
+    // Step 3: the burned request is withdrawn as usual
+
+    let txid = ckbtc.await_btc_transaction(block_index, 10);
+    let mempool = ckbtc.mempool();
+    let tx = mempool
+        .get(&txid)
+        .expect("the mempool does not contain the withdrawal transaction");
+    assert_eq!(2, tx.output.len());
+
+    ckbtc.finalize_transaction(tx);
+    assert_eq!(ckbtc.await_finalization(block_index, 10), txid);
+}
+
 #[test]
 fn test_retrieve_btc_with_approval_from_subaccount() {
     let ckbtc = CkBtcSetup::new();
@@ -111,6 +111,7 @@ impl RpcAdapterClient<BitcoinAdapterRequestWrapper> for BitcoinAdapterClientImpl
                                 GetSuccessorsResponseComplete {
                                     blocks: inner.blocks,
                                     next: inner.next,
+                                    filters: inner.filters,
                                 },
                             )
                         })
@@ -123,5 +123,6 @@ fn prop_get_successors_response_complete() -> impl Strategy<Value = GetSuccessor
             // TODO: Multiple blocks
             blocks: vec![blocks],
             next: vec![next],
+            filters: vec![],
         })
 }
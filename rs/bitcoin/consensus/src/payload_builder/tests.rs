@@ -141,6 +141,7 @@ fn can_successfully_create_bitcoin_payload() {
                     GetSuccessorsResponseComplete {
                         blocks: vec![],
                         next: vec![],
+                        filters: vec![],
                     },
                 ))
             });
@@ -172,6 +173,7 @@ fn can_successfully_create_bitcoin_payload() {
                         GetSuccessorsResponseComplete {
                             blocks: vec![],
                             next: vec![],
+                            filters: vec![],
                         },
                     ),
                     callback_id: 0,
@@ -205,6 +207,7 @@ fn includes_responses_in_the_payload() {
                     GetSuccessorsResponseComplete {
                         blocks: vec![],
                         next: vec![],
+                        filters: vec![],
                     },
                 ))
             });
@@ -243,6 +246,7 @@ fn includes_responses_in_the_payload() {
                             GetSuccessorsResponseComplete {
                                 blocks: vec![],
                                 next: vec![],
+                                filters: vec![],
                             },
                         ),
                         callback_id: 0,
@@ -301,6 +305,7 @@ fn includes_only_responses_for_callback_ids_not_seen_in_past_payloads() {
                 GetSuccessorsResponseComplete {
                     blocks: vec![],
                     next: vec![],
+                    filters: vec![],
                 },
             ))
         });
@@ -334,6 +339,7 @@ fn includes_only_responses_for_callback_ids_not_seen_in_past_payloads() {
                         GetSuccessorsResponseComplete {
                             blocks: vec![],
                             next: vec![],
+                            filters: vec![],
                         },
                     ),
                     callback_id: 0,
@@ -357,6 +363,7 @@ fn includes_only_responses_for_callback_ids_not_seen_in_past_payloads() {
                         GetSuccessorsResponseComplete {
                             blocks: vec![],
                             next: vec![],
+                            filters: vec![],
                         },
                     ),
                     callback_id: 1,
@@ -404,6 +411,7 @@ fn bitcoin_payload_builder_fits_largest_blocks() {
                 GetSuccessorsResponseComplete {
                     blocks: vec![vec![0; MAX_BLOCK_PAYLOAD_SIZE.get() as usize]],
                     next: vec![vec![0; 80]],
+                    filters: vec![],
                 },
             ))
         });
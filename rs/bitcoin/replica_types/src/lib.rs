@@ -617,6 +617,10 @@ pub type BlockBlob = Vec<u8>;
 // A blob representing a block header in the standard bitcoin format.
 type BlockHeaderBlob = Vec<u8>;
 
+// A blob representing a BIP-158 basic block filter. An empty blob means that
+// the filter of the corresponding block is not available.
+pub type BlockFilterBlob = Vec<u8>;
+
 type BlockHash = Vec<u8>;
 
 type PageNumber = u8;
@@ -692,12 +696,14 @@ impl TryFrom<v1::GetSuccessorsRequestInitial> for GetSuccessorsRequestInitial {
 ///   complete : record {
 ///     blocks: vec blob;
 ///     next: vec blob;
+///     filters: vec blob;
 ///   };
 ///
 ///   partial : record {
 ///     partial_block: blob;
 ///     next: vec blob;
 ///     remaining_follow_ups: nat8;
+///     filters: vec blob;
 ///   };
 ///
 ///   follow_up : blob;
@@ -722,12 +728,15 @@ pub enum GetSuccessorsResponse {
 pub struct GetSuccessorsResponseComplete {
     pub blocks: Vec<BlockBlob>,
     pub next: Vec<BlockHeaderBlob>,
+    /// BIP-158 basic filters of the blocks, in the same order as `blocks`.
+    /// Empty if the adapter doesn't serve compact block filters.
+    pub filters: Vec<BlockFilterBlob>,
 }
 
 impl GetSuccessorsResponseComplete {
     /// Returns the size of this `SendTransactionResponse` in bytes.
     pub fn count_bytes(&self) -> usize {
-        self.count_blocks_bytes() + self.count_next_bytes()
+        self.count_blocks_bytes() + self.count_next_bytes() + self.count_filters_bytes()
     }
 
     pub fn count_blocks_bytes(&self) -> usize {
@@ -737,6 +746,10 @@ impl GetSuccessorsResponseComplete {
     pub fn count_next_bytes(&self) -> usize {
         self.next.iter().map(|n| n.len()).sum::<usize>()
     }
+
+    pub fn count_filters_bytes(&self) -> usize {
+        self.filters.iter().map(|f| f.len()).sum::<usize>()
+    }
 }
 
 impl From<&GetSuccessorsResponseComplete> for v1::GetSuccessorsResponseComplete {
@@ -744,6 +757,7 @@ impl From<&GetSuccessorsResponseComplete> for v1::GetSuccessorsResponseComplete
         v1::GetSuccessorsResponseComplete {
             blocks: request.blocks.clone(),
             next: request.next.clone(),
+            filters: request.filters.clone(),
         }
     }
 }
@@ -754,6 +768,7 @@ impl TryFrom<v1::GetSuccessorsResponseComplete> for GetSuccessorsResponseComplet
         Ok(GetSuccessorsResponseComplete {
             blocks: response.blocks,
             next: response.next,
+            filters: response.filters,
         })
     }
 }
@@ -769,6 +784,9 @@ pub struct GetSuccessorsResponsePartial {
     /// The remaining number of follow ups to this response, which can retrieved
     /// via `FollowUp` requests.
     pub remaining_follow_ups: u8,
+
+    /// BIP-158 basic filter of the partial block, if available.
+    pub filters: Vec<BlockFilterBlob>,
 }
 
 #[cfg(test)]
@@ -781,6 +799,7 @@ mod test {
             GetSuccessorsResponseComplete {
                 blocks: vec![],
                 next: vec![],
+                filters: vec![],
             }
             .count_bytes(),
             0
@@ -790,9 +809,20 @@ mod test {
             GetSuccessorsResponseComplete {
                 blocks: vec![vec![1, 2, 3], vec![4, 5, 6]],
                 next: vec![vec![7, 8, 9, 10], vec![11, 12]],
+                filters: vec![],
             }
             .count_bytes(),
             12
         );
+
+        assert_eq!(
+            GetSuccessorsResponseComplete {
+                blocks: vec![vec![1, 2, 3], vec![4, 5, 6]],
+                next: vec![vec![7, 8, 9, 10], vec![11, 12]],
+                filters: vec![vec![13], vec![14, 15]],
+            }
+            .count_bytes(),
+            15
+        );
     }
 }
@@ -1,4 +1,9 @@
-use std::{fmt, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use anyhow::{anyhow, Context, Error};
 use axum::{
@@ -16,6 +21,8 @@ use http::{
 };
 use ic_bn_lib::http::body::buffer_body;
 use moka::future::{Cache as MokaCache, CacheBuilder as MokaCacheBuilder};
+use tokio::sync::watch;
+use tracing::warn;
 
 use crate::routes::{ApiError, RequestContext};
 
@@ -52,6 +59,10 @@ pub enum CacheStatus {
     Disabled,
     Bypass(CacheBypassReason),
     Hit,
+    // Stale entry served while it's being refreshed in the background
+    Stale,
+    // Response fetched by another concurrent identical request
+    Coalesced,
     Miss,
 }
 
@@ -69,6 +80,8 @@ impl fmt::Display for CacheStatus {
             Self::Disabled => write!(f, "DISABLED"),
             Self::Bypass(_) => write!(f, "BYPASS"),
             Self::Hit => write!(f, "HIT"),
+            Self::Stale => write!(f, "STALE"),
+            Self::Coalesced => write!(f, "COALESCED"),
             Self::Miss => write!(f, "MISS"),
         }
     }
@@ -80,13 +93,62 @@ struct CacheItem {
     version: Version,
     headers: HeaderMap,
     body: Bytes,
+    // After this moment the item is stale and can be served only while it's being refreshed
+    fresh_until: Instant,
+}
+
+impl CacheItem {
+    fn to_response(&self) -> Response {
+        let mut builder = Response::builder()
+            .status(self.status)
+            .version(self.version);
+
+        *builder.headers_mut().unwrap() = self.headers.clone();
+        builder.body(Body::from(self.body.clone())).unwrap()
+    }
+}
+
+// Requests that are currently being fetched from upstream.
+// The sender publishes the fetched item if it was cacheable, it's dropped otherwise.
+type InflightMap = Mutex<HashMap<Arc<RequestContext>, watch::Receiver<Option<CacheItem>>>>;
+
+// Marks a request as being fetched from upstream, removes the mark when dropped
+struct InflightGuard {
+    inflight: Arc<InflightMap>,
+    ctx: Arc<RequestContext>,
+    tx: watch::Sender<Option<CacheItem>>,
+}
+
+impl InflightGuard {
+    // Passes the fetched item (if any) to the requests waiting for it
+    fn complete(self, item: Option<CacheItem>) {
+        if item.is_some() {
+            self.tx.send_replace(item);
+        }
+    }
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.inflight.lock().unwrap().remove(&self.ctx);
+    }
+}
+
+enum Inflight {
+    // No other request is being fetched, the caller should fetch it
+    Leader(InflightGuard),
+    // An identical request is being fetched, the caller can wait for it
+    Follower(watch::Receiver<Option<CacheItem>>),
 }
 
 #[derive(Clone)]
 pub struct Cache {
     cache: MokaCache<Arc<RequestContext>, CacheItem>,
     max_item_size: u64,
+    ttl: Duration,
     cache_non_anonymous: bool,
+    coalesce: bool,
+    inflight: Arc<InflightMap>,
 }
 
 // Estimate rough amount of bytes that cache entry takes in memory
@@ -111,11 +173,17 @@ fn weigh_entry(k: &Arc<RequestContext>, v: &CacheItem) -> u32 {
 // If this is exceeded then some items would be purged.
 // We assume that a cache item's cost is a number of bytes it takes in memory.
 impl Cache {
+    // Entries are served from the cache for `ttl` and then for `stale_while_revalidate`
+    // while a single background request refreshes them.
+    // If `coalesce` is set then concurrent identical requests that miss the cache
+    // wait for a single upstream request instead of sending their own.
     pub fn new(
         cache_size: u64,
         max_item_size: u64,
         ttl: Duration,
+        stale_while_revalidate: Duration,
         cache_non_anonymous: bool,
+        coalesce: bool,
     ) -> Result<Self, Error> {
         if max_item_size >= cache_size {
             return Err(anyhow!(
@@ -124,46 +192,116 @@ impl Cache {
         }
 
         let cache = MokaCacheBuilder::new(cache_size)
-            .time_to_live(ttl)
+            .time_to_live(ttl + stale_while_revalidate)
             .weigher(weigh_entry)
             .build();
 
         Ok(Self {
             cache,
             max_item_size,
+            ttl,
             cache_non_anonymous,
+            coalesce,
+            inflight: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
     // Stores the response components in the cache
     // Response itself cannot be stored since it's not cloneable, so we have to rebuild it
-    async fn store(&self, ctx: Arc<RequestContext>, parts: &response::Parts, body: Bytes) {
+    async fn store(
+        &self,
+        ctx: Arc<RequestContext>,
+        parts: &response::Parts,
+        body: Bytes,
+    ) -> CacheItem {
         let item = CacheItem {
             status: parts.status,
             version: parts.version,
             headers: parts.headers.clone(),
             body,
+            fresh_until: Instant::now() + self.ttl,
         };
 
         // Insert the response into the cache & wait for it to persist there
-        self.cache.insert(ctx, item).await;
+        self.cache.insert(ctx, item.clone()).await;
+        item
+    }
+
+    // Looks up the request in the cache, returns the response and whether it's fresh
+    async fn lookup(&self, ctx: &RequestContext) -> Option<(Response, bool)> {
+        let item = self.cache.get(ctx).await?;
+
+        // If an item was found -> construct a response from the cached data
+        Some((item.to_response(), item.fresh_until > Instant::now()))
     }
 
-    // Looks up the request in the cache
-    async fn lookup(&self, ctx: &RequestContext) -> Option<Response> {
-        let item = match self.cache.get(ctx).await {
+    // Registers the request as being fetched from upstream unless an identical one already is
+    fn inflight(&self, ctx: &Arc<RequestContext>) -> Inflight {
+        let mut inflight = self.inflight.lock().unwrap();
+        if let Some(rx) = inflight.get(ctx) {
+            return Inflight::Follower(rx.clone());
+        }
+
+        let (tx, rx) = watch::channel(None);
+        inflight.insert(ctx.clone(), rx);
+
+        Inflight::Leader(InflightGuard {
+            inflight: self.inflight.clone(),
+            ctx: ctx.clone(),
+            tx,
+        })
+    }
+
+    // Sends the request upstream and caches the response if possible.
+    // Returns the response and the cached item, if any.
+    async fn fetch(
+        &self,
+        ctx: Arc<RequestContext>,
+        request: Request,
+        next: Next,
+    ) -> Result<(Response, Option<CacheItem>), ApiError> {
+        let response = next.run(request).await;
+
+        // Do not cache non-2xx responses
+        if !response.status().is_success() {
+            return Ok((
+                CacheStatus::Bypass(CacheBypassReason::HTTPError).with_response(response),
+                None,
+            ));
+        }
+
+        // Do not cache responses that have no known size (probably streaming etc)
+        let body_size = match response.body().size_hint().exact() {
             Some(v) => v,
-            None => return None,
+            None => {
+                return Ok((
+                    CacheStatus::Bypass(CacheBypassReason::SizeUnknown).with_response(response),
+                    None,
+                ))
+            }
         };
 
-        // If an item was found -> construct a response from the cached data
-        let mut builder = Response::builder()
-            .status(item.status)
-            .version(item.version);
+        // Do not cache items larger than configured
+        if body_size > self.max_item_size {
+            return Ok((
+                CacheStatus::Bypass(CacheBypassReason::TooBig).with_response(response),
+                None,
+            ));
+        }
+
+        // Buffer entire response body to be able to cache it
+        let (parts, body) = response.into_parts();
+        let body = buffer_body(body, body_size as usize, Duration::from_secs(60))
+            .await
+            .context("unable to read body")?;
 
-        *builder.headers_mut().unwrap() = item.headers;
+        // Insert the response into the cache
+        let item = self.store(ctx, &parts, body.clone()).await;
 
-        Some(builder.body(Body::from(item.body)).unwrap())
+        // Reconstruct the response from components
+        let response = Response::from_parts(parts, Body::from(body));
+
+        Ok((CacheStatus::Miss.with_response(response), Some(item)))
     }
 
     pub fn size(&self) -> u64 {
@@ -221,44 +359,54 @@ pub async fn cache_middleware(
     }
 
     // Try to look up the request in the cache
-    if let Some(v) = cache.lookup(&ctx).await {
-        return Ok(CacheStatus::Hit.with_response(v));
-    }
-
-    // If not found - pass the request down the stack
-    let response = next.run(request).await;
-
-    // Do not cache non-2xx responses
-    if !response.status().is_success() {
-        return Ok(CacheStatus::Bypass(CacheBypassReason::HTTPError).with_response(response));
-    }
+    if let Some((response, fresh)) = cache.lookup(&ctx).await {
+        if fresh {
+            return Ok(CacheStatus::Hit.with_response(response));
+        }
 
-    // Do not cache responses that have no known size (probably streaming etc)
-    let body_size = match response.body().size_hint().exact() {
-        Some(v) => v,
-        None => {
-            return Ok(CacheStatus::Bypass(CacheBypassReason::SizeUnknown).with_response(response))
+        // The entry is stale: serve it and refresh it in the background
+        // unless an identical request is already being fetched
+        if let Inflight::Leader(guard) = cache.inflight(&ctx) {
+            let cache = cache.clone();
+            tokio::spawn(async move {
+                match cache.fetch(ctx, request, next).await {
+                    Ok((_, item)) => guard.complete(item),
+                    Err(e) => warn!("Cache: unable to refresh stale entry: {e}"),
+                }
+            });
         }
-    };
 
-    // Do not cache items larger than configured
-    if body_size > cache.max_item_size {
-        return Ok(CacheStatus::Bypass(CacheBypassReason::TooBig).with_response(response));
+        return Ok(CacheStatus::Stale.with_response(response));
     }
 
-    // Buffer entire response body to be able to cache it
-    let (parts, body) = response.into_parts();
-    let body = buffer_body(body, body_size as usize, Duration::from_secs(60))
-        .await
-        .context("unable to read body")?;
+    if !cache.coalesce {
+        let (response, _) = cache.fetch(ctx, request, next).await?;
+        return Ok(response);
+    }
 
-    // Insert the response into the cache
-    cache.store(ctx, &parts, body.clone()).await;
+    match cache.inflight(&ctx) {
+        Inflight::Leader(guard) => {
+            let (response, item) = cache.fetch(ctx, request, next).await?;
+            guard.complete(item);
+            Ok(response)
+        }
 
-    // Reconstruct the response from components
-    let response = Response::from_parts(parts, Body::from(body));
+        Inflight::Follower(mut rx) => {
+            // Wait for the identical request to complete.
+            // If its response wasn't cacheable then send our own request.
+            let item = rx
+                .wait_for(Option::is_some)
+                .await
+                .ok()
+                .and_then(|x| x.clone());
+            if let Some(item) = item {
+                return Ok(CacheStatus::Coalesced.with_response(item.to_response()));
+            }
 
-    Ok(CacheStatus::Miss.with_response(response))
+            let (response, _) = cache.fetch(ctx, request, next).await?;
+            Ok(response)
+        }
+    }
 }
 
 #[cfg(test)]
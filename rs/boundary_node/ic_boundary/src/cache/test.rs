@@ -1,10 +1,13 @@
 use super::*;
 
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
 
 use axum::{
-    body::Body, http::Request, middleware, response::IntoResponse, routing::method_routing::post,
-    Extension, Router,
+    body::Body, extract::State, http::Request, middleware, response::IntoResponse,
+    routing::method_routing::post, Extension, Router,
 };
 use candid::Principal;
 use http::header::HeaderValue;
@@ -66,15 +69,48 @@ async fn handler(
     (status_code, "a".repeat(size as usize))
 }
 
+// Same as `handler` but slow and counting the requests that reach it
+async fn counting_handler(
+    State(counter): State<Arc<AtomicUsize>>,
+    Extension(size): Extension<u64>,
+    Extension(status_code): Extension<StatusCode>,
+) -> impl IntoResponse {
+    counter.fetch_add(1, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    (status_code, "a".repeat(size as usize))
+}
+
+fn counting_app(cache: Arc<Cache>, counter: Arc<AtomicUsize>) -> Router {
+    Router::new()
+        .route("/", post(counting_handler))
+        .with_state(counter)
+        .layer(middleware::from_fn_with_state(cache, cache_middleware))
+}
+
+async fn call(app: &Router, req: Request<Body>) -> CacheStatus {
+    let res = app.clone().call(req).await.unwrap();
+    res.extensions().get::<CacheStatus>().cloned().unwrap()
+}
+
 #[tokio::test]
 async fn test_cache() -> Result<(), Error> {
     // Check that we fail if item size >= max size
-    assert!(Cache::new(1024, 1024, Duration::from_secs(60), false).is_err());
+    assert!(Cache::new(
+        1024,
+        1024,
+        Duration::from_secs(60),
+        Duration::ZERO,
+        false,
+        false
+    )
+    .is_err());
 
     let cache = Cache::new(
         MAX_MEM_SIZE,
         MAX_RESP_SIZE,
         Duration::from_secs(3600),
+        Duration::ZERO,
+        false,
         false,
     )?;
     let cache = Arc::new(cache);
@@ -203,3 +239,124 @@ async fn test_cache() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_cache_stale_while_revalidate() -> Result<(), Error> {
+    let ttl = Duration::from_millis(500);
+    let cache = Arc::new(Cache::new(
+        MAX_MEM_SIZE,
+        MAX_RESP_SIZE,
+        ttl,
+        Duration::from_secs(3600),
+        false,
+        false,
+    )?);
+    let counter = Arc::new(AtomicUsize::new(0));
+    let app = counting_app(cache, counter.clone());
+
+    assert_eq!(
+        call(&app, gen_request(CANISTER_1, false)).await,
+        CacheStatus::Miss
+    );
+    assert_eq!(
+        call(&app, gen_request(CANISTER_1, false)).await,
+        CacheStatus::Hit
+    );
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+    // Once expired the entry is still served while a single request refreshes it
+    tokio::time::sleep(ttl).await;
+    for _ in 0..5 {
+        assert_eq!(
+            call(&app, gen_request(CANISTER_1, false)).await,
+            CacheStatus::Stale
+        );
+    }
+
+    // Wait for the refresh to complete
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(counter.load(Ordering::SeqCst), 2);
+    assert_eq!(
+        call(&app, gen_request(CANISTER_1, false)).await,
+        CacheStatus::Hit
+    );
+
+    // Without stale-while-revalidate expired entries are fetched again
+    let cache = Arc::new(Cache::new(
+        MAX_MEM_SIZE,
+        MAX_RESP_SIZE,
+        ttl,
+        Duration::ZERO,
+        false,
+        false,
+    )?);
+    let app = counting_app(cache, counter.clone());
+
+    assert_eq!(
+        call(&app, gen_request(CANISTER_1, false)).await,
+        CacheStatus::Miss
+    );
+    tokio::time::sleep(ttl).await;
+    assert_eq!(
+        call(&app, gen_request(CANISTER_1, false)).await,
+        CacheStatus::Miss
+    );
+    assert_eq!(counter.load(Ordering::SeqCst), 4);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cache_coalescing() -> Result<(), Error> {
+    let cache = Arc::new(Cache::new(
+        MAX_MEM_SIZE,
+        MAX_RESP_SIZE,
+        Duration::from_secs(3600),
+        Duration::ZERO,
+        false,
+        true,
+    )?);
+    let counter = Arc::new(AtomicUsize::new(0));
+    let app = counting_app(cache.clone(), counter.clone());
+
+    // Concurrent identical requests cause a single upstream request
+    let statuses =
+        futures::future::join_all((0..10).map(|_| call(&app, gen_request(CANISTER_1, false))))
+            .await;
+    assert_eq!(counter.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        statuses.iter().filter(|&x| *x == CacheStatus::Miss).count(),
+        1
+    );
+    assert_eq!(
+        statuses
+            .iter()
+            .filter(|&x| *x == CacheStatus::Coalesced)
+            .count(),
+        9
+    );
+
+    // Requests with uncacheable responses are not coalesced
+    cache.clear().await;
+    counter.store(0, Ordering::SeqCst);
+    let statuses = futures::future::join_all((0..10).map(|_| {
+        call(
+            &app,
+            gen_request_with_params(
+                CANISTER_2,
+                false,
+                DEFAULT_SIZE,
+                0,
+                true,
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+        )
+    }))
+    .await;
+    assert_eq!(counter.load(Ordering::SeqCst), 10);
+    assert!(statuses
+        .iter()
+        .all(|x| *x == CacheStatus::Bypass(CacheBypassReason::HTTPError)));
+
+    Ok(())
+}
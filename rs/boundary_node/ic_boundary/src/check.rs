@@ -73,12 +73,15 @@ struct NodeState {
     healthy: bool,
     height: u64,
     avg_latency_secs: f64,
+    error_rate: f64,
 }
 
 // Send node's state message to the SubnetActor after this number of health checks have passed.
 const CHECKS_MSG_PERIODICITY: usize = 10;
 // Send node's state message to the SubnetActor, if node's latency has deviated from the average by more than this threshold value.
 const LATENCY_CHANGE_THRESHOLD: f64 = 0.15;
+// Weight of the latest health check in the exponentially weighted error rate of the node.
+const ERROR_RATE_EWMA_ALPHA: f64 = 0.1;
 
 // NodeActor periodically runs the health checking with given interval and sends the NodeState down to
 // SubnetActor when it changes
@@ -90,6 +93,7 @@ struct NodeActor {
     checker: Arc<dyn Check>,
     state: Option<NodeState>,
     avg_mov_latency: LatencyMovAvg,
+    error_rate: f64,
     checks_counter: usize,
 }
 
@@ -109,6 +113,7 @@ impl NodeActor {
             checker,
             state: None,
             avg_mov_latency: LatencyMovAvg::new(),
+            error_rate: 0.0,
             checks_counter: 0,
         }
     }
@@ -132,11 +137,16 @@ impl NodeActor {
             Err(_) => (false, 0, 0.0),
         };
 
-        // Note: initially we update only the health field. height and avg latency are updated conditionally.
+        let failed = if res.is_err() { 1.0 } else { 0.0 };
+        self.error_rate =
+            ERROR_RATE_EWMA_ALPHA * failed + (1.0 - ERROR_RATE_EWMA_ALPHA) * self.error_rate;
+
+        // Note: initially we update only the health field. height, avg latency & error rate are updated conditionally.
         let mut new_state = self.state.unwrap_or_else(|| NodeState {
             healthy,
             height,
             avg_latency_secs: self.avg_mov_latency.get_average(),
+            error_rate: self.error_rate,
         });
         new_state.healthy = healthy;
 
@@ -147,13 +157,14 @@ impl NodeActor {
             // reset the counter
             self.checks_counter = 0;
             new_state.avg_latency_secs = self.avg_mov_latency.get_average();
+            new_state.error_rate = self.error_rate;
             new_state.height = height;
         }
 
         // Send the state down the line if either:
         // - health has changed
         // - conditionally updated height has changed
-        // - conditionally updated avg latency has changed
+        // - conditionally updated avg latency or error rate has changed
         if Some(new_state) != self.state {
             self.state = Some(new_state);
             // It can never fail in our case
@@ -180,6 +191,14 @@ impl NodeActor {
     }
 }
 
+// Node's Eq compares only the IDs, so check the stats used for routing separately
+fn nodes_changed(a: &[Arc<Node>], b: &[Arc<Node>]) -> bool {
+    a.len() != b.len()
+        || a.iter().zip(b).any(|(x, y)| {
+            x != y || x.avg_latency_secs != y.avg_latency_secs || x.error_rate != y.error_rate
+        })
+}
+
 // SubnetActor spawns NodeActors, receives their state, computes minimum height for the subnet and sends the
 // Subnet with healthy nodes down to GlobalActor when the health state changes
 struct SubnetActor {
@@ -299,12 +318,17 @@ impl SubnetActor {
             .map(|(node, state)| {
                 let mut node = (*node).clone();
                 node.avg_latency_secs = state.avg_latency_secs;
+                node.error_rate = state.error_rate;
                 Arc::new(node)
             })
             .collect::<Vec<_>>();
 
-        // See if the healthy nodes set changed
-        if self.healthy_nodes.is_none() || &nodes != self.healthy_nodes.as_ref().unwrap() {
+        // See if the healthy nodes set or their stats changed
+        if self
+            .healthy_nodes
+            .as_ref()
+            .is_none_or(|x| nodes_changed(x, &nodes))
+        {
             self.healthy_nodes = Some(nodes.clone());
 
             // Publish the new subnet
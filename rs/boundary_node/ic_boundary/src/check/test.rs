@@ -56,6 +56,7 @@ pub fn generate_custom_registry_snapshot(
                     .0
                     .certificate_der,
                 avg_latency_secs: f64::MAX,
+                error_rate: 0.0,
             };
             let node = Arc::new(node);
 
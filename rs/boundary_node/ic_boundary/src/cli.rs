@@ -1,5 +1,5 @@
 use candid::Principal;
-use clap::{Args, Parser};
+use clap::{Args, Parser, Subcommand};
 use humantime::parse_duration;
 use ic_bn_lib::{
     http::{
@@ -21,6 +21,9 @@ use crate::core::{AUTHOR_NAME, SERVICE_NAME};
 #[clap(name = SERVICE_NAME)]
 #[clap(author = AUTHOR_NAME)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[command(flatten, next_help_heading = "Listen")]
     pub listen: Listen,
 
@@ -74,6 +77,33 @@ pub struct Cli {
     pub misc: Misc,
 }
 
+#[derive(Subcommand)]
+pub enum Command {
+    /// Replay a recorded request log offline against the rate-limit rules and the routing table
+    /// built from a registry snapshot, and report the rule hits and the routing distribution
+    Replay(Replay),
+}
+
+#[derive(Args)]
+pub struct Replay {
+    /// Request log in JSON lines format, as written by the request logging.
+    /// Sender & remote address are only used if they were logged without anonymization.
+    #[clap(long)]
+    pub replay_log: PathBuf,
+
+    /// Registry snapshot in JSON format, see --registry-snapshot-dump-path
+    #[clap(long)]
+    pub replay_registry_snapshot: PathBuf,
+
+    /// Rate-limit rules in YAML format, same as for --rate-limit-generic-file
+    #[clap(long)]
+    pub replay_rate_limit_rules: Option<PathBuf>,
+
+    /// Scale to apply to the rate-limit rules, i.e. the number of API BNs that the traffic is spread over
+    #[clap(long, default_value = "1", value_parser = clap::value_parser!(u32).range(1..))]
+    pub replay_scale: u32,
+}
+
 #[derive(Args)]
 pub struct Registry {
     /// Comma separated list of NNS URLs to bootstrap the registry
@@ -105,6 +135,11 @@ pub struct Registry {
     /// Minimum snapshot version age to be useful for initial publishing
     #[clap(env, long, default_value = "10s", value_parser = parse_duration)]
     pub registry_min_version_age: Duration,
+
+    /// Write each new registry snapshot to this file in JSON format.
+    /// It can be used later for the offline replay.
+    #[clap(env, long)]
+    pub registry_snapshot_dump_path: Option<PathBuf>,
 }
 
 #[derive(Args)]
@@ -175,6 +210,18 @@ pub struct Health {
     /// Fraction of subnets that should be healthy to consider our node healthy
     #[clap(env, long, default_value = "0.51")]
     pub health_subnets_alive_threshold: f64,
+
+    /// Exclude the nodes with the average health check latency higher than
+    /// the subnet's median multiplied by this factor from the routing table.
+    /// At most (n-1)/3 nodes of a subnet are excluded.
+    #[clap(env, long)]
+    pub health_outlier_latency_factor: Option<f64>,
+
+    /// Exclude the nodes with the health check error rate (exponentially weighted, 0.0-1.0)
+    /// higher than this from the routing table.
+    /// At most (n-1)/3 nodes of a subnet are excluded.
+    #[clap(env, long)]
+    pub health_outlier_error_rate: Option<f64>,
 }
 
 #[derive(Args)]
@@ -323,9 +370,19 @@ pub struct Cache {
     #[clap(env, long, default_value = "1s", value_parser = parse_duration)]
     pub cache_ttl: Duration,
 
+    /// For how long an expired cache entry can still be served while a single
+    /// background request refreshes it. Zero disables stale-while-revalidate.
+    #[clap(env, long, default_value = "0s", value_parser = parse_duration)]
+    pub cache_stale_while_revalidate: Duration,
+
     /// Whether to cache non-anonymous requests
     #[clap(env, long, default_value = "false")]
     pub cache_non_anonymous: bool,
+
+    /// Whether concurrent identical requests that miss the cache should wait
+    /// for a single upstream request instead of sending their own
+    #[clap(env, long, default_value = "false")]
+    pub cache_coalescing: bool,
 }
 
 #[derive(Args)]
@@ -343,6 +400,12 @@ pub struct Retry {
     /// Whether to use latency-based routing for /call
     #[clap(env, long, default_value = "false")]
     pub retry_disable_latency_routing: bool,
+
+    /// Pick the nodes for all request types using the power of two choices
+    /// weighted by the health check latency and error rate.
+    /// Takes precedence over the latency-based routing for /call
+    #[clap(env, long, default_value = "false")]
+    pub retry_p2c_routing: bool,
 }
 
 #[derive(Args)]
@@ -54,7 +54,7 @@ use crate::{
     bouncer,
     cache::{cache_middleware, Cache},
     check::{Checker, Runner as CheckRunner},
-    cli::Cli,
+    cli::{Cli, Command},
     dns::DnsResolver,
     firewall::{FirewallGenerator, SystemdReloader},
     geoip,
@@ -63,8 +63,9 @@ use crate::{
         MetricParamsPersist, MetricParamsSnapshot, MetricsCache, MetricsRunner, WithMetrics,
         WithMetricsCheck, WithMetricsPersist, WithMetricsSnapshot, HTTP_DURATION_BUCKETS,
     },
-    persist::{Persist, Persister, Routes},
+    persist::{OutlierEjection, Persist, Persister, Routes},
     rate_limiting::{generic, RateLimit},
+    replay,
     retry::{retry_request, RetryParams},
     routes::{self, ErrorCause, Health, Lookup, Proxy, ProxyRouter, RootKey},
     salt_fetcher::AnonymizationSaltFetcher,
@@ -102,6 +103,10 @@ pub fn decoder_config() -> DecoderConfig {
 }
 
 pub async fn main(cli: Cli) -> Result<(), Error> {
+    if let Some(Command::Replay(v)) = &cli.command {
+        return replay::main(v).await;
+    }
+
     if cli.http_client.http_client_timeout_connect > cli.health.health_check_timeout {
         panic!("Health check timeout should be longer than HTTP client connect timeout");
     }
@@ -194,7 +199,15 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
     let http_client = Arc::new(http_client);
 
     // Setup registry-related stuff
-    let persister = Persister::new(Arc::clone(&routing_table));
+    let mut persister = Persister::new(Arc::clone(&routing_table));
+    if cli.health.health_outlier_latency_factor.is_some()
+        || cli.health.health_outlier_error_rate.is_some()
+    {
+        persister.set_outlier_ejection(OutlierEjection {
+            latency_factor: cli.health.health_outlier_latency_factor,
+            error_rate: cli.health.health_outlier_error_rate,
+        });
+    }
 
     // Snapshot update notification channels
     let (channel_snapshot_send, channel_snapshot_recv) = tokio::sync::watch::channel(None);
@@ -269,7 +282,9 @@ pub async fn main(cli: Cli) -> Result<(), Error> {
                 x,
                 cli.cache.cache_max_item_size,
                 cli.cache.cache_ttl,
+                cli.cache.cache_stale_while_revalidate,
                 cli.cache.cache_non_anonymous,
+                cli.cache.cache_coalescing,
             )
             .expect("unable to initialize cache"),
         )
@@ -629,6 +644,10 @@ fn setup_registry(
                 snapshotter.set_persister(persister);
             }
 
+            if let Some(v) = &cli.registry.registry_snapshot_dump_path {
+                snapshotter.set_dump_path(v.clone());
+            }
+
             snapshotter
         },
         MetricParamsSnapshot::new(metrics_registry),
@@ -926,6 +945,7 @@ pub fn setup_router(
             retry_count: cli.retry.retry_count as usize,
             retry_update_call: cli.retry.retry_update_call,
             disable_latency_routing: cli.retry.retry_disable_latency_routing,
+            p2c_routing: cli.retry.retry_p2c_routing,
         },
         retry_request,
     );
@@ -10,6 +10,7 @@ mod http;
 mod metrics;
 mod persist;
 mod rate_limiting;
+mod replay;
 mod retry;
 mod routes;
 mod salt_fetcher;
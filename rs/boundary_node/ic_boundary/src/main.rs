@@ -17,6 +17,7 @@ mod log;
 mod metrics;
 mod persist;
 mod rate_limiting;
+mod replay;
 mod retry;
 mod routes;
 mod salt_fetcher;
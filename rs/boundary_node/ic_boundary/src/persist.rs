@@ -4,8 +4,8 @@ use arc_swap::ArcSwapOption;
 use async_trait::async_trait;
 use candid::Principal;
 use ethnum::u256;
-use rand::seq::SliceRandom;
-use tracing::{debug, error};
+use rand::{seq::SliceRandom, Rng};
+use tracing::{debug, error, warn};
 
 use crate::{
     metrics::{MetricParamsPersist, WithMetricsPersist},
@@ -13,6 +13,63 @@ use crate::{
     snapshot::{Node, Subnet},
 };
 
+// How much the error rate of a node inflates its latency when comparing nodes.
+// E.g. a node failing 10% of health checks is treated as being 2x slower.
+const ERROR_RATE_PENALTY: f64 = 10.0;
+
+// Score of the node used for routing, lower is better
+fn node_score(node: &Node) -> f64 {
+    node.avg_latency_secs * (1.0 + ERROR_RATE_PENALTY * node.error_rate)
+}
+
+/// Parameters for excluding outlier nodes from the routing table.
+/// Nodes are ejected if they exceed any of the configured thresholds.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct OutlierEjection {
+    /// Eject nodes with the average latency higher than the subnet's median times this factor
+    pub latency_factor: Option<f64>,
+    /// Eject nodes with the health check error rate higher than this
+    pub error_rate: Option<f64>,
+}
+
+impl OutlierEjection {
+    // Removes outliers from the list of nodes sorted by latency.
+    // At most `(n - 1) / 3` nodes are ejected, the ones with the worst score first,
+    // so that a subnet always keeps enough nodes to serve the requests.
+    fn eject(&self, nodes: &mut Vec<Arc<Node>>) {
+        if nodes.is_empty() {
+            return;
+        }
+
+        let max_ejected = (nodes.len() - 1) / 3;
+        let median_latency = nodes[nodes.len() / 2].avg_latency_secs;
+
+        let is_outlier = |node: &Node| {
+            self.latency_factor
+                .is_some_and(|x| node.avg_latency_secs > median_latency * x)
+                || self.error_rate.is_some_and(|x| node.error_rate > x)
+        };
+
+        let mut outliers = nodes
+            .iter()
+            .filter(|x| is_outlier(x))
+            .cloned()
+            .collect::<Vec<_>>();
+
+        outliers.sort_by(|a, b| node_score(b).total_cmp(&node_score(a)));
+        outliers.truncate(max_ejected);
+
+        for node in &outliers {
+            warn!(
+                "Ejecting outlier node {} ({}) in subnet {}: avg latency {:.3}s (median {:.3}s), error rate {:.3}",
+                node.id, node, node.subnet_id, node.avg_latency_secs, median_latency, node.error_rate
+            );
+        }
+
+        nodes.retain(|x| !outliers.contains(x));
+    }
+}
+
 #[derive(Copy, Clone)]
 pub struct PersistResults {
     pub ranges_old: u32,
@@ -94,6 +151,34 @@ impl RouteSubnet {
 
         Ok(picked_nodes)
     }
+
+    // Picks up to n nodes using the power of two choices:
+    // each node is the one with the better score out of two randomly sampled ones.
+    // This sends most of the requests to the fast nodes without overloading the fastest one.
+    pub fn pick_nodes_p2c(&self, n: usize) -> Result<Vec<Arc<Node>>, ErrorCause> {
+        let mut rng = rand::thread_rng();
+        let mut candidates = self.nodes.iter().collect::<Vec<_>>();
+        let mut picked_nodes = Vec::with_capacity(n.min(candidates.len()));
+
+        while picked_nodes.len() < n && !candidates.is_empty() {
+            let a = rng.gen_range(0..candidates.len());
+            let b = rng.gen_range(0..candidates.len());
+
+            let idx = if node_score(candidates[b]) < node_score(candidates[a]) {
+                b
+            } else {
+                a
+            };
+
+            picked_nodes.push(Arc::clone(candidates.swap_remove(idx)));
+        }
+
+        if picked_nodes.is_empty() {
+            return Err(ErrorCause::NoHealthyNodes);
+        }
+
+        Ok(picked_nodes)
+    }
 }
 
 #[derive(Eq, PartialEq, Debug)]
@@ -151,11 +236,19 @@ pub trait Persist: Send + Sync {
 
 pub struct Persister {
     published_routes: Arc<ArcSwapOption<Routes>>,
+    outlier_ejection: Option<OutlierEjection>,
 }
 
 impl Persister {
     pub fn new(published_routes: Arc<ArcSwapOption<Routes>>) -> Self {
-        Self { published_routes }
+        Self {
+            published_routes,
+            outlier_ejection: None,
+        }
+    }
+
+    pub fn set_outlier_ejection(&mut self, outlier_ejection: OutlierEjection) {
+        self.outlier_ejection = Some(outlier_ejection);
     }
 }
 
@@ -178,6 +271,10 @@ impl Persist for Persister {
                 // Sort nodes by latency before publishing to avoid sorting on each retry_request() call.
                 nodes.sort_by(|a, b| a.avg_latency_secs.partial_cmp(&b.avg_latency_secs).unwrap());
 
+                if let Some(v) = &self.outlier_ejection {
+                    v.eject(&mut nodes);
+                }
+
                 subnet.ranges.into_iter().map(move |range| {
                     Arc::new(RouteSubnet {
                         id: subnet.id,
@@ -1,4 +1,7 @@
-use super::{principal_bytes_to_u256, Persist, PersistStatus, Persister, RouteSubnet, Routes};
+use super::{
+    principal_bytes_to_u256, OutlierEjection, Persist, PersistStatus, Persister, RouteSubnet,
+    Routes,
+};
 
 use std::{
     collections::HashMap,
@@ -71,6 +74,7 @@ pub fn node(i: u64, subnet_id: Principal) -> Arc<Node> {
             .0
             .certificate_der,
         avg_latency_secs: f64::MAX,
+        error_rate: 0.0,
     })
 }
 
@@ -306,3 +310,139 @@ fn test_lookup() -> Result<(), Error> {
 
     Ok(())
 }
+
+// Generates a subnet with nodes having given latencies and error rates
+fn generate_subnet_with_stats(stats: &[(f64, f64)]) -> Subnet {
+    let subnet_id =
+        Principal::from_text("tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe")
+            .unwrap();
+
+    let nodes = stats
+        .iter()
+        .enumerate()
+        .map(|(i, (latency, error_rate))| {
+            let mut node = (*node(i as u64, subnet_id)).clone();
+            node.avg_latency_secs = *latency;
+            node.error_rate = *error_rate;
+            Arc::new(node)
+        })
+        .collect::<Vec<_>>();
+
+    Subnet {
+        id: subnet_id,
+        subnet_type: SubnetType::Application,
+        ranges: vec![CanisterRange {
+            start: Principal::from_text("f7crg-kabae").unwrap(),
+            end: Principal::from_text("sxiki-5ygae-aq").unwrap(),
+        }],
+        nodes,
+        replica_version: "7742d96ddd30aa6b607c9d2d4093a7b714f5b25b".to_string(),
+    }
+}
+
+#[test]
+fn test_outlier_ejection() -> Result<(), Error> {
+    let subnet = generate_subnet_with_stats(&[
+        (0.1, 0.0),
+        (0.1, 0.0),
+        (0.2, 0.0),
+        (0.1, 0.5),
+        (2.0, 0.0),
+        (0.3, 0.0),
+        (5.0, 0.0),
+    ]);
+    let (slow1, slow2, flaky) = (subnet.nodes[4].id, subnet.nodes[6].id, subnet.nodes[3].id);
+
+    let persisted_nodes = |ejection: Option<OutlierEjection>| {
+        let rt = Arc::new(ArcSwapOption::empty());
+        let mut persister = Persister::new(Arc::clone(&rt));
+        if let Some(v) = ejection {
+            persister.set_outlier_ejection(v);
+        }
+
+        persister.persist(vec![subnet.clone()]);
+        rt.load_full().unwrap().subnets[0]
+            .nodes
+            .iter()
+            .map(|x| x.id)
+            .collect::<Vec<_>>()
+    };
+
+    // No ejection by default
+    assert_eq!(persisted_nodes(None).len(), 7);
+
+    // Latency outliers are ejected
+    let nodes = persisted_nodes(Some(OutlierEjection {
+        latency_factor: Some(5.0),
+        error_rate: None,
+    }));
+    assert_eq!(nodes.len(), 5);
+    assert!(!nodes.contains(&slow1));
+    assert!(!nodes.contains(&slow2));
+
+    // Error rate outliers are ejected
+    let nodes = persisted_nodes(Some(OutlierEjection {
+        latency_factor: None,
+        error_rate: Some(0.2),
+    }));
+    assert_eq!(nodes.len(), 6);
+    assert!(!nodes.contains(&flaky));
+
+    // At most (n-1)/3 nodes are ejected, the worst ones first
+    let nodes = persisted_nodes(Some(OutlierEjection {
+        latency_factor: Some(1.5),
+        error_rate: Some(0.2),
+    }));
+    assert_eq!(nodes.len(), 5);
+    assert!(!nodes.contains(&slow1));
+    assert!(!nodes.contains(&slow2));
+    assert!(nodes.contains(&flaky));
+
+    Ok(())
+}
+
+#[test]
+fn test_pick_nodes_p2c() -> Result<(), Error> {
+    let subnet = generate_subnet_with_stats(&[(0.1, 0.0), (0.1, 0.5), (1.0, 0.0)]);
+    let (fast, flaky, slow) = (subnet.nodes[0].id, subnet.nodes[1].id, subnet.nodes[2].id);
+
+    let subnet = RouteSubnet {
+        id: subnet.id,
+        range_start: u256::ZERO,
+        range_end: u256::MAX,
+        nodes: subnet.nodes,
+    };
+
+    // All the nodes are returned if requested, without duplicates
+    let mut nodes = subnet
+        .pick_nodes_p2c(10)?
+        .iter()
+        .map(|x| x.id)
+        .collect::<Vec<_>>();
+    nodes.sort();
+    let mut expected = vec![fast, flaky, slow];
+    expected.sort();
+    assert_eq!(nodes, expected);
+
+    // The node with the best score is picked most often
+    let mut counts = HashMap::new();
+    for _ in 0..3000 {
+        let node = subnet.pick_nodes_p2c(1)?[0].id;
+        *counts.entry(node).or_insert(0) += 1;
+    }
+
+    // The best node wins if it's sampled at least once: 1 - (2/3)^2 = 5/9
+    assert!(counts[&fast] > 1400);
+    // The worst node is picked only if it's sampled twice: (1/3)^2 = 1/9
+    assert!(counts.get(&slow).copied().unwrap_or(0) < 500);
+    assert!(counts.get(&flaky).copied().unwrap_or(0) > counts.get(&slow).copied().unwrap_or(0));
+
+    // Empty subnet
+    let subnet = RouteSubnet {
+        nodes: vec![],
+        ..subnet
+    };
+    assert!(subnet.pick_nodes_p2c(1).is_err());
+
+    Ok(())
+}
@@ -5,11 +5,9 @@ use async_trait::async_trait;
 use candid::{Decode, Encode};
 use ic_canister_client::Agent;
 use ic_types::CanisterId;
-use rate_limits_api::{v1::RateLimitRule, GetConfigResponse, Version};
+use rate_limits_api::{v1, v2, v2::RateLimitRule, GetConfigResponse, Version};
 use tokio::fs;
 
-const SCHEMA_VERSION: u64 = 1;
-
 fn nonce() -> Vec<u8> {
     SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -101,11 +99,12 @@ impl FetchesRules for CanisterFetcher {
             .context("failed to decode candid response")?
             .map_err(|e| anyhow!("failed to get config: {e:?}"))?;
 
-        if response.config.schema_version != SCHEMA_VERSION {
+        let schema_version = response.config.schema_version;
+        if schema_version != v1::SCHEMA_VERSION && schema_version != v2::SCHEMA_VERSION {
             return Err(anyhow!(
-                "incorrect schema version (got {}, expected {})",
-                response.config.schema_version,
-                SCHEMA_VERSION
+                "incorrect schema version (got {schema_version}, expected {} or {})",
+                v1::SCHEMA_VERSION,
+                v2::SCHEMA_VERSION
             ));
         }
 
@@ -128,8 +127,14 @@ impl FetchesRules for CanisterFetcher {
                     ));
                 };
 
-                let rule = RateLimitRule::from_bytes_yaml(&raw)
-                    .context(format!("unable to decode raw rule with id {}", x.rule_id))?;
+                // v1 rules are decoded using the v1 schema and then converted,
+                // so that they can't enable any of the v2 features
+                let rule = if schema_version == v1::SCHEMA_VERSION {
+                    v1::RateLimitRule::from_bytes_yaml(&raw).map(RateLimitRule::from)
+                } else {
+                    RateLimitRule::from_bytes_yaml(&raw)
+                }
+                .context(format!("unable to decode raw rule with id {}", x.rule_id))?;
 
                 Ok(rule)
             })
@@ -160,7 +165,7 @@ mod test {
                 version: 1,
                 active_since: 0,
                 config: OutputConfig {
-                    schema_version: SCHEMA_VERSION,
+                    schema_version: v1::SCHEMA_VERSION,
                     is_redacted: false,
                     rules: vec![
                         OutputRule {
@@ -192,6 +197,7 @@ mod test {
                                 canister_id: aaaaa-aa
                                 methods_regex: ^foo|bax$
                                 limit: 10/1m
+                                shadow: true
                             "}.into()),
                             description: None
                         },
@@ -203,6 +209,54 @@ mod test {
         }
     }
 
+    struct FakeConfigFetcherOkV2;
+
+    #[async_trait]
+    impl FetchesConfig for FakeConfigFetcherOkV2 {
+        async fn fetch_config(&self) -> Result<Vec<u8>, Error> {
+            let resp: GetConfigResponse = Ok(ConfigResponse {
+                version: 1,
+                active_since: 0,
+                config: OutputConfig {
+                    schema_version: v2::SCHEMA_VERSION,
+                    is_redacted: false,
+                    rules: vec![
+                        OutputRule {
+                            rule_id: "foobar".into(),
+                            incident_id: "barfoo".into(),
+                            rule_raw: Some(
+                                indoc! {"
+                                canister_id: aaaaa-aa
+                                key_by: sender
+                                limit: 10/1m
+                                burst: 20
+                            "}
+                                .into(),
+                            ),
+                            description: None,
+                        },
+                        OutputRule {
+                            rule_id: "foobaz".into(),
+                            incident_id: "barfoo".into(),
+                            rule_raw: Some(
+                                indoc! {"
+                                canister_id: 5s2ji-faaaa-aaaaa-qaaaq-cai
+                                key_by: canister_method
+                                limit: 1/10s
+                                shadow: true
+                            "}
+                                .into(),
+                            ),
+                            description: None,
+                        },
+                    ],
+                },
+            });
+
+            Ok(Encode!(&resp).unwrap())
+        }
+    }
+
     struct FakeConfigFetcherBadSchema;
 
     #[async_trait]
@@ -212,7 +266,7 @@ mod test {
                 version: 1,
                 active_since: 0,
                 config: OutputConfig {
-                    schema_version: SCHEMA_VERSION + 1,
+                    schema_version: v2::SCHEMA_VERSION + 1,
                     is_redacted: false,
                     rules: vec![],
                 },
@@ -231,7 +285,7 @@ mod test {
                 version: 1,
                 active_since: 0,
                 config: OutputConfig {
-                    schema_version: SCHEMA_VERSION,
+                    schema_version: v1::SCHEMA_VERSION,
                     is_redacted: false,
                     rules: vec![OutputRule {
                         rule_id: "foobar".into(),
@@ -272,7 +326,10 @@ mod test {
                     request_types: None,
                     ip_prefix_group: None,
                     ip: None,
+                    key_by: None,
                     limit: v1::Action::Block,
+                    burst: None,
+                    shadow: false,
                 },
                 RateLimitRule {
                     canister_id: Some(principal!("5s2ji-faaaa-aaaaa-qaaaq-cai")),
@@ -283,7 +340,10 @@ mod test {
                     request_types: None,
                     ip_prefix_group: None,
                     ip: None,
+                    key_by: None,
                     limit: v1::Action::Limit(1, Duration::from_secs(10)),
+                    burst: None,
+                    shadow: false,
                 },
                 RateLimitRule {
                     canister_id: Some(principal!("aaaaa-aa")),
@@ -292,9 +352,36 @@ mod test {
                     request_types: None,
                     ip_prefix_group: None,
                     ip: None,
+                    key_by: None,
                     limit: v1::Action::Limit(10, Duration::from_secs(60)),
+                    burst: None,
+                    shadow: false,
                 }
             ]
         );
+
+        // Check correct v2 rules parsing
+        let canister_fetcher = CanisterFetcher(Arc::new(FakeConfigFetcherOkV2));
+        let rules = canister_fetcher.fetch_rules().await.unwrap();
+
+        assert_eq!(
+            rules,
+            vec![
+                RateLimitRule {
+                    canister_id: Some(principal!("aaaaa-aa")),
+                    key_by: Some(v2::KeyBy::Sender),
+                    limit: v2::Action::Limit(10, Duration::from_secs(60)),
+                    burst: Some(20),
+                    ..Default::default()
+                },
+                RateLimitRule {
+                    canister_id: Some(principal!("5s2ji-faaaa-aaaaa-qaaaq-cai")),
+                    key_by: Some(v2::KeyBy::CanisterMethod),
+                    limit: v2::Action::Limit(1, Duration::from_secs(10)),
+                    shadow: true,
+                    ..Default::default()
+                },
+            ]
+        );
     }
 }
@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     net::IpAddr,
     path::PathBuf,
     sync::{
@@ -27,8 +28,11 @@ use prometheus::{
     register_int_counter_vec_with_registry, register_int_gauge_with_registry, IntCounterVec,
     IntGauge, Registry,
 };
-use rate_limits_api::v1::{Action, IpPrefixes, RateLimitRule, RequestType as RequestTypeRule};
+use rate_limits_api::v2::{
+    Action, IpPrefixes, KeyBy, RateLimitRule, RequestType as RequestTypeRule,
+};
 use ratelimit::Ratelimiter;
+use serde::Serialize;
 use strum::{Display, IntoStaticStr};
 #[allow(clippy::disallowed_types)]
 use tokio::sync::{watch, Mutex};
@@ -63,7 +67,7 @@ fn convert_request_type(rt: RequestType) -> RequestTypeRule {
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display, IntoStaticStr)]
-enum Decision {
+pub enum Decision {
     Pass,
     Block,
     Limit,
@@ -72,15 +76,84 @@ enum Decision {
 pub struct Context<'a> {
     subnet_id: Principal,
     canister_id: Option<Principal>,
+    sender: Option<Principal>,
     method: Option<&'a str>,
     request_type: RequestType,
     ip: IpAddr,
 }
 
+impl<'a> Context<'a> {
+    pub fn new(
+        subnet_id: Principal,
+        canister_id: Option<Principal>,
+        sender: Option<Principal>,
+        method: Option<&'a str>,
+        request_type: RequestType,
+        ip: IpAddr,
+    ) -> Self {
+        Self {
+            subnet_id,
+            canister_id,
+            sender,
+            method,
+            request_type,
+            ip,
+        }
+    }
+}
+
+// Returns the network of given IP address according to the prefixes
+fn ip_net(ip: IpAddr, prefixes: &IpPrefixes) -> IpNet {
+    let prefix = match ip {
+        IpAddr::V4(_) => prefixes.v4,
+        IpAddr::V6(_) => prefixes.v6,
+    };
+
+    // We assume that the prefix is correct, assert is safe
+    IpNet::new_assert(ip, prefix)
+}
+
+// Scales the rule limits, e.g. according to the number of API BNs
+fn scale_rule(mut rule: RateLimitRule, scale: u32) -> RateLimitRule {
+    if let Action::Limit(n, d) = rule.limit {
+        // Make sure the limit doesn't go below 1
+        let limit = (n / scale).max(1);
+        rule.limit = Action::Limit(limit, d);
+        rule.burst = rule.burst.map(|x| (x / scale).max(1));
+    }
+
+    rule
+}
+
 #[derive(Clone)]
 enum Limiter {
     Single(Arc<Ratelimiter>),
     Sharded(Arc<ShardedRatelimiter<IpNet>>, IpPrefixes),
+    ShardedSender(Arc<ShardedRatelimiter<Principal>>),
+    ShardedCanisterMethod(Arc<ShardedRatelimiter<(Option<Principal>, Option<String>)>>),
+}
+
+impl Limiter {
+    fn acquire(&self, ctx: &Context) -> bool {
+        match self {
+            Self::Single(v) => v.try_wait().is_ok(),
+            Self::Sharded(v, prefixes) => v.acquire(ip_net(ctx.ip, prefixes)),
+            // Requests without a sender are accounted as anonymous ones
+            Self::ShardedSender(v) => v.acquire(ctx.sender.unwrap_or(Principal::anonymous())),
+            Self::ShardedCanisterMethod(v) => {
+                v.acquire((ctx.canister_id, ctx.method.map(|x| x.to_string())))
+            }
+        }
+    }
+
+    fn shards_count(&self) -> Option<u64> {
+        match self {
+            Self::Single(_) => None,
+            Self::Sharded(v, _) => Some(v.shards_count()),
+            Self::ShardedSender(v) => Some(v.shards_count()),
+            Self::ShardedCanisterMethod(v) => Some(v.shards_count()),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -97,43 +170,52 @@ impl PartialEq for Bucket {
 impl Eq for Bucket {}
 
 impl Bucket {
-    fn evaluate(&self, ctx: &Context) -> Option<Decision> {
+    // Checks if the request satisfies the conditions of the rule
+    fn matches(&self, ctx: &Context) -> bool {
         if let Some(v) = self.rule.subnet_id {
             if ctx.subnet_id != v {
-                return None;
+                return false;
             }
         }
 
         if let Some(v) = self.rule.canister_id {
             if let Some(x) = ctx.canister_id {
                 if x != v {
-                    return None;
+                    return false;
                 }
             }
         }
 
         if let Some(v) = &self.rule.request_types {
             if !v.contains(&convert_request_type(ctx.request_type)) {
-                return None;
+                return false;
             }
         }
 
         if let Some(rgx) = &self.rule.methods_regex {
             if let Some(v) = ctx.method {
                 if !rgx.is_match(v) {
-                    return None;
+                    return false;
                 }
             } else {
-                return None;
+                return false;
             }
         }
 
         if let Some(v) = self.rule.ip {
             if !v.contains(&ctx.ip) {
-                return None;
+                return false;
             }
         }
 
+        true
+    }
+
+    fn evaluate(&self, ctx: &Context) -> Option<Decision> {
+        if !self.matches(ctx) {
+            return None;
+        }
+
         if self.rule.limit == Action::Pass {
             return Some(Decision::Pass);
         } else if self.rule.limit == Action::Block {
@@ -141,19 +223,7 @@ impl Bucket {
         }
 
         if let Some(limiter) = &self.limiter {
-            let allowed = match limiter {
-                Limiter::Single(v) => v.try_wait().is_ok(),
-                Limiter::Sharded(v, prefix) => {
-                    let prefix = match ctx.ip {
-                        IpAddr::V4(_) => prefix.v4,
-                        IpAddr::V6(_) => prefix.v6,
-                    };
-
-                    // We assume that the prefix is correct, assert is safe
-                    let net = IpNet::new_assert(ctx.ip, prefix);
-                    v.acquire(net)
-                }
-            };
+            let allowed = limiter.acquire(ctx);
 
             return Some(if allowed {
                 Decision::Pass
@@ -181,6 +251,7 @@ struct Metrics {
     active_rules: IntGauge,
     fetches: IntCounterVec,
     decisions: IntCounterVec,
+    shadow_decisions: IntCounterVec,
     shards_count: IntGauge,
 }
 
@@ -224,6 +295,16 @@ impl Metrics {
             )
             .unwrap(),
 
+            shadow_decisions: register_int_counter_vec_with_registry!(
+                format!("generic_limiter_shadow_decisions"),
+                format!(
+                    "Count of decisions made by the rules in shadow mode, they are not enforced"
+                ),
+                &["rule", "decision"],
+                registry
+            )
+            .unwrap(),
+
             shards_count: register_int_gauge_with_registry!(
                 format!("generic_limiter_shards_count"),
                 format!("Number of dynamic shards if the corresponding rules are used"),
@@ -305,13 +386,9 @@ impl GenericLimiter {
         rules
             .into_iter()
             .enumerate()
-            .map(|(idx, mut rule)| {
+            .map(|(idx, rule)| {
                 // Scale the rule limit accordingly
-                if let Action::Limit(n, d) = rule.limit {
-                    // Make sure the limit doesn't go below 1
-                    let limit = (n / scale).max(1);
-                    rule.limit = Action::Limit(limit, d);
-                }
+                let rule = scale_rule(rule, scale);
 
                 // Check if the same rule exists in the same position.
                 // If yes, then copy over the old limiter to avoid resetting it.
@@ -321,20 +398,42 @@ impl GenericLimiter {
                     }
                 }
 
-                let limiter = if let Action::Limit(limit, duration) = &rule.limit {
-                    Some(if let Some(v) = &rule.ip_prefix_group {
-                        Limiter::Sharded(
+                let limiter = if let Action::Limit(limit, duration) = rule.limit {
+                    // Without an explicit burst the bucket holds exactly one period worth of tokens
+                    let burst = rule.burst.unwrap_or(limit);
+
+                    Some(match (&rule.ip_prefix_group, rule.key_by) {
+                        (Some(v), _) => Limiter::Sharded(
                             Arc::new(ShardedRatelimiter::new(
-                                *limit,
-                                *limit,
-                                *duration,
+                                limit,
+                                burst,
+                                duration,
                                 self.opts.tti,
                                 self.opts.max_shards,
                             )),
                             *v,
-                        )
-                    } else {
-                        Limiter::Single(Arc::new(create_ratelimiter(*limit, *limit, *duration)))
+                        ),
+                        (None, Some(KeyBy::Sender)) => {
+                            Limiter::ShardedSender(Arc::new(ShardedRatelimiter::new(
+                                limit,
+                                burst,
+                                duration,
+                                self.opts.tti,
+                                self.opts.max_shards,
+                            )))
+                        }
+                        (None, Some(KeyBy::CanisterMethod)) => {
+                            Limiter::ShardedCanisterMethod(Arc::new(ShardedRatelimiter::new(
+                                limit,
+                                burst,
+                                duration,
+                                self.opts.tti,
+                                self.opts.max_shards,
+                            )))
+                        }
+                        (None, None) => {
+                            Limiter::Single(Arc::new(create_ratelimiter(limit, burst, duration)))
+                        }
                     })
                 } else {
                     None
@@ -391,8 +490,20 @@ impl GenericLimiter {
             return Decision::Pass;
         }
 
-        for b in self.buckets.load_full().as_ref() {
+        for (idx, b) in self.buckets.load_full().iter().enumerate() {
             if let Some(v) = b.evaluate(&ctx) {
+                // Shadow rules only record what they would do in the metrics and let the evaluation continue.
+                // They are not logged since this is the hot path of every request.
+                if b.rule.shadow {
+                    let decision_str: &'static str = v.into();
+                    self.metrics
+                        .shadow_decisions
+                        .with_label_values(&[&idx.to_string(), decision_str])
+                        .inc();
+
+                    continue;
+                }
+
                 return v;
             }
         }
@@ -406,17 +517,163 @@ impl GenericLimiter {
         self.buckets
             .load_full()
             .iter()
-            .filter_map(|x| {
-                if let Some(Limiter::Sharded(v, _)) = &x.limiter {
-                    Some(v.shards_count())
-                } else {
-                    None
-                }
-            })
+            .filter_map(|x| x.limiter.as_ref().and_then(|v| v.shards_count()))
             .sum()
     }
 }
 
+// Token bucket that is refilled according to the given timestamps instead of the wall clock.
+// Same as the one created by `create_ratelimiter`, it starts full and whole tokens are added
+// one per refill interval, i.e. partially elapsed intervals don't add any tokens.
+struct SimulatedBucket {
+    tokens: u64,
+    updated: Duration,
+}
+
+impl SimulatedBucket {
+    fn acquire(&mut self, interval: Duration, burst: u64, now: Duration) -> bool {
+        if now > self.updated {
+            if interval.is_zero() {
+                self.tokens = burst;
+                self.updated = now;
+            } else {
+                let intervals = (now - self.updated).as_nanos() / interval.as_nanos();
+                self.tokens = (self.tokens as u128 + intervals).min(burst as u128) as u64;
+                self.updated += Duration::from_nanos((intervals * interval.as_nanos()) as u64);
+            }
+        }
+
+        if self.tokens == 0 {
+            return false;
+        }
+
+        self.tokens -= 1;
+        true
+    }
+}
+
+// Key of the limiter shard that the request is accounted to
+fn shard_key(rule: &RateLimitRule, ctx: &Context) -> String {
+    if let Some(v) = &rule.ip_prefix_group {
+        return ip_net(ctx.ip, v).to_string();
+    }
+
+    match rule.key_by {
+        // Requests without a sender are accounted as anonymous ones
+        Some(KeyBy::Sender) => ctx.sender.unwrap_or(Principal::anonymous()).to_string(),
+        Some(KeyBy::CanisterMethod) => format!("{:?}/{:?}", ctx.canister_id, ctx.method),
+        None => String::new(),
+    }
+}
+
+/// Decisions made by a rule during the simulation
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RuleStats {
+    pub rule: String,
+    pub shadow: bool,
+    pub pass: u64,
+    pub block: u64,
+    pub limit: u64,
+}
+
+/// Evaluates the rules against the requests offline, e.g. to replay recorded requests.
+/// The requests are matched by the same code as in the GenericLimiter, but the rate limits
+/// are applied according to the timestamps of the requests instead of the wall clock.
+///
+/// The results are only as precise as the timestamps: with the 1s resolution of the request
+/// logs all the requests logged within the same second are evaluated at the same instant.
+/// A burst that the running service sees spread over a second (and partially refills in
+/// the meantime) is thus seen as instantaneous, so the simulation can limit requests that
+/// the GenericLimiter let through for rules with a refill interval below 1s.
+pub struct Simulator {
+    buckets: Vec<Bucket>,
+    shards: Vec<HashMap<String, SimulatedBucket>>,
+    stats: Vec<RuleStats>,
+}
+
+impl Simulator {
+    pub fn new(rules: Vec<RateLimitRule>, scale: u32) -> Self {
+        let buckets = rules
+            .into_iter()
+            .map(|x| Bucket {
+                rule: scale_rule(x, scale),
+                limiter: None,
+            })
+            .collect::<Vec<_>>();
+
+        let stats = buckets
+            .iter()
+            .map(|x| RuleStats {
+                rule: x.rule.to_string(),
+                shadow: x.rule.shadow,
+                ..Default::default()
+            })
+            .collect();
+
+        Self {
+            shards: buckets.iter().map(|_| HashMap::new()).collect(),
+            buckets,
+            stats,
+        }
+    }
+
+    /// Evaluates the request made at the given time since some fixed point, e.g. UNIX epoch
+    pub fn evaluate(&mut self, ctx: &Context, now: Duration) -> Decision {
+        // Always allow access from localhost, same as the GenericLimiter
+        if ctx.ip.is_loopback() {
+            return Decision::Pass;
+        }
+
+        for (idx, b) in self.buckets.iter().enumerate() {
+            if !b.matches(ctx) {
+                continue;
+            }
+
+            let decision = match b.rule.limit {
+                Action::Pass => Decision::Pass,
+                Action::Block => Decision::Block,
+                Action::Limit(limit, duration) => {
+                    // Same parameters as the ones given to `create_ratelimiter`
+                    let burst = b.rule.burst.unwrap_or(limit) as u64;
+                    let interval = duration.checked_div(limit).unwrap_or(Duration::ZERO);
+
+                    let bucket = self.shards[idx].entry(shard_key(&b.rule, ctx)).or_insert(
+                        SimulatedBucket {
+                            tokens: burst,
+                            updated: now,
+                        },
+                    );
+
+                    if bucket.acquire(interval, burst, now) {
+                        Decision::Pass
+                    } else {
+                        Decision::Limit
+                    }
+                }
+            };
+
+            let stats = &mut self.stats[idx];
+            match decision {
+                Decision::Pass => stats.pass += 1,
+                Decision::Block => stats.block += 1,
+                Decision::Limit => stats.limit += 1,
+            }
+
+            // Shadow rules only record what they would do and let the evaluation continue
+            if !b.rule.shadow {
+                return decision;
+            }
+        }
+
+        // No rules / no match -> pass
+        Decision::Pass
+    }
+
+    pub fn stats(&self) -> &[RuleStats] {
+        &self.stats
+    }
+}
+
 #[async_trait]
 impl Run for Arc<GenericLimiter> {
     async fn run(&mut self) -> Result<(), Error> {
@@ -473,6 +730,7 @@ pub async fn middleware(
     let ctx = Context {
         subnet_id: subnet.id,
         canister_id: canister_id.map(|x| x.get().into()),
+        sender: ctx.sender,
         method: ctx.method_name.as_deref(),
         request_type: ctx.request_type,
         ip: conn_info.remote_addr.ip(),
@@ -601,6 +859,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("foo"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -615,6 +874,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -659,6 +919,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id0),
+                    sender: None,
                     method: None,
                     request_type: RequestType::Query,
                     ip: ip_local4,
@@ -671,6 +932,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id0),
+                    sender: None,
                     method: None,
                     request_type: RequestType::Query,
                     ip: ip_local6,
@@ -686,6 +948,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("foo"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -700,6 +963,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -716,6 +980,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id: subnet_id2,
                     canister_id: Some(id2),
+                    sender: None,
                     method: Some("foo"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -730,6 +995,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id: subnet_id2,
                     canister_id: Some(id2),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -743,6 +1009,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id: subnet_id2,
                     canister_id: Some(id2),
+                    sender: None,
                     method: Some("lol"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -755,6 +1022,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id: subnet_id2,
                     canister_id: Some(id2),
+                    sender: None,
                     method: Some("rofl"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -769,6 +1037,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id2),
+                    sender: None,
                     method: Some("baz"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -784,6 +1053,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id3),
+                    sender: None,
                     method: Some("rofl"),
                     request_type: RequestType::Call,
                     ip: ip1,
@@ -797,6 +1067,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id3),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Call,
                     ip: ip1,
@@ -812,6 +1083,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id3),
+                    sender: None,
                     method: Some("baz"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -825,6 +1097,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id3),
+                    sender: None,
                     method: Some("zob"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -841,6 +1114,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id3),
+                    sender: None,
                     method: None,
                     request_type: RequestType::ReadState,
                     ip: ip1,
@@ -854,6 +1128,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id3),
+                    sender: None,
                     method: None,
                     request_type: RequestType::ReadState,
                     ip: ip1,
@@ -868,6 +1143,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id3),
+                    sender: None,
                     method: None,
                     request_type: RequestType::ReadState,
                     ip: ip2,
@@ -881,6 +1157,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id3),
+                    sender: None,
                     method: None,
                     request_type: RequestType::ReadState,
                     ip: ip2,
@@ -898,6 +1175,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("foo"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -912,6 +1190,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -929,6 +1208,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("foo"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -943,6 +1223,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -959,6 +1240,7 @@ mod test {
             limiter.evaluate(Context {
                 subnet_id,
                 canister_id: Some(id1),
+                sender: None,
                 method: Some("foo"),
                 request_type: RequestType::Query,
                 ip: ip1,
@@ -972,6 +1254,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -991,6 +1274,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("foo"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -1005,6 +1289,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -1024,6 +1309,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("foo"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -1038,6 +1324,7 @@ mod test {
                 limiter.evaluate(Context {
                     subnet_id,
                     canister_id: Some(id1),
+                    sender: None,
                     method: Some("bar"),
                     request_type: RequestType::Query,
                     ip: ip1,
@@ -1046,4 +1333,223 @@ mod test {
             );
         }
     }
+
+    #[tokio::test]
+    async fn test_ratelimit_v2() {
+        let ip1 = IpAddr::from_str("10.0.0.1").unwrap();
+
+        let id1 = principal!("aaaaa-aa");
+        let id2 = principal!("5s2ji-faaaa-aaaaa-qaaaq-cai");
+        let id3 = principal!("qoctq-giaaa-aaaaa-aaaea-cai");
+
+        let sender1 = principal!("pawub-syaaa-aaaam-qb7zq-cai");
+        let sender2 = principal!("2vxsx-fae");
+
+        let subnet_id =
+            principal!("3hhby-wmtmw-umt4t-7ieyg-bbiig-xiylg-sblrt-voxgt-bqckd-a75bf-rqe");
+
+        let rules = indoc! {"
+        - canister_id: aaaaa-aa
+          key_by: sender
+          limit: 10/1h
+          burst: 20
+
+        - canister_id: 5s2ji-faaaa-aaaaa-qaaaq-cai
+          key_by: canister_method
+          limit: 10/1h
+
+        - canister_id: qoctq-giaaa-aaaaa-aaaea-cai
+          limit: block
+          shadow: true
+
+        - canister_id: qoctq-giaaa-aaaaa-aaaea-cai
+          limit: 5/1h
+          shadow: true
+
+        - canister_id: qoctq-giaaa-aaaaa-aaaea-cai
+          methods_regex: ^foo$
+          limit: 10/1h
+        "};
+
+        let rules: Vec<RateLimitRule> = serde_yaml::from_str(rules).unwrap();
+        let opts = Options {
+            tti: Duration::from_secs(10),
+            max_shards: 10000,
+            poll_interval: Duration::from_secs(30),
+            autoscale: false,
+        };
+
+        let (_, rx) = watch::channel(None);
+        let limiter = Arc::new(GenericLimiter::new_with_fetcher(
+            Arc::new(TestFetcher(rules.clone())),
+            opts,
+            rx,
+            &Registry::new(),
+        ));
+        assert!(limiter.refresh().await.is_ok());
+
+        let ctx = |canister_id, sender, method| Context {
+            subnet_id,
+            canister_id: Some(canister_id),
+            sender,
+            method,
+            request_type: RequestType::Call,
+            ip: ip1,
+        };
+
+        // Check per-sender limiting with burst
+        // 20 pass for each sender
+        for sender in [sender1, sender2] {
+            for _ in 0..20 {
+                assert_eq!(
+                    limiter.evaluate(ctx(id1, Some(sender), Some("foo"))),
+                    Decision::Pass
+                );
+            }
+            // then all limited
+            for _ in 0..10 {
+                assert_eq!(
+                    limiter.evaluate(ctx(id1, Some(sender), Some("foo"))),
+                    Decision::Limit
+                );
+            }
+        }
+
+        // Requests without a sender are accounted as anonymous ones, which is sender2
+        assert_eq!(
+            limiter.evaluate(ctx(id1, None, Some("foo"))),
+            Decision::Limit
+        );
+
+        // Check per-canister-method limiting
+        // 10 pass for each method
+        for method in ["foo", "bar"] {
+            for _ in 0..10 {
+                assert_eq!(
+                    limiter.evaluate(ctx(id2, Some(sender1), Some(method))),
+                    Decision::Pass
+                );
+            }
+            // then all limited
+            for _ in 0..10 {
+                assert_eq!(
+                    limiter.evaluate(ctx(id2, Some(sender2), Some(method))),
+                    Decision::Limit
+                );
+            }
+        }
+
+        // Check that shadow rules are not enforced but recorded,
+        // and that the evaluation falls through to the next rule
+        for _ in 0..10 {
+            assert_eq!(
+                limiter.evaluate(ctx(id3, Some(sender1), Some("foo"))),
+                Decision::Pass
+            );
+        }
+        assert_eq!(
+            limiter.evaluate(ctx(id3, Some(sender1), Some("foo"))),
+            Decision::Limit
+        );
+
+        let shadow = |rule: &str, decision: &str| {
+            limiter
+                .metrics
+                .shadow_decisions
+                .with_label_values(&[rule, decision])
+                .get()
+        };
+        assert_eq!(shadow("2", "Block"), 11);
+        assert_eq!(shadow("3", "Pass"), 5);
+        assert_eq!(shadow("3", "Limit"), 6);
+
+        // Check that the burst is scaled too
+        limiter.apply_rules(rules, 2);
+        for _ in 0..10 {
+            assert_eq!(
+                limiter.evaluate(ctx(id1, Some(sender1), Some("foo"))),
+                Decision::Pass
+            );
+        }
+        assert_eq!(
+            limiter.evaluate(ctx(id1, Some(sender1), Some("foo"))),
+            Decision::Limit
+        );
+    }
+
+    #[test]
+    fn test_simulator() {
+        let ip1 = IpAddr::from_str("10.0.0.1").unwrap();
+        let id1 = principal!("aaaaa-aa");
+        let id2 = principal!("5s2ji-faaaa-aaaaa-qaaaq-cai");
+        let sender1 = principal!("pawub-syaaa-aaaam-qb7zq-cai");
+        let sender2 = principal!("qoctq-giaaa-aaaaa-aaaea-cai");
+        let subnet_id =
+            principal!("3hhby-wmtmw-umt4t-7ieyg-bbiig-xiylg-sblrt-voxgt-bqckd-a75bf-rqe");
+
+        let rules = indoc! {"
+        - canister_id: aaaaa-aa
+          limit: block
+          shadow: true
+
+        - canister_id: aaaaa-aa
+          key_by: sender
+          limit: 2/1s
+          burst: 4
+
+        - canister_id: 5s2ji-faaaa-aaaaa-qaaaq-cai
+          limit: 10/1s
+        "};
+
+        let rules: Vec<RateLimitRule> = serde_yaml::from_str(rules).unwrap();
+        let mut sim = Simulator::new(rules, 2);
+
+        let ctx = |canister_id, sender| {
+            Context::new(
+                subnet_id,
+                Some(canister_id),
+                Some(sender),
+                Some("foo"),
+                RequestType::Query,
+                ip1,
+            )
+        };
+
+        // Burst is scaled to 2, so 2 pass & then limited
+        let now = Duration::from_secs(1000);
+        for _ in 0..2 {
+            assert_eq!(sim.evaluate(&ctx(id1, sender1), now), Decision::Pass);
+        }
+        assert_eq!(sim.evaluate(&ctx(id1, sender1), now), Decision::Limit);
+
+        // Other sender has its own bucket
+        assert_eq!(sim.evaluate(&ctx(id1, sender2), now), Decision::Pass);
+
+        // The bucket is refilled with 1 token per second according to the given time
+        let now = now + Duration::from_secs(1);
+        assert_eq!(sim.evaluate(&ctx(id1, sender1), now), Decision::Pass);
+        assert_eq!(sim.evaluate(&ctx(id1, sender1), now), Decision::Limit);
+
+        // Time going backwards doesn't refill the bucket
+        let now = now - Duration::from_secs(10);
+        assert_eq!(sim.evaluate(&ctx(id1, sender1), now), Decision::Limit);
+
+        // Unmatched requests pass
+        assert_eq!(
+            sim.evaluate(&ctx(principal!("2vxsx-fae"), sender1), now),
+            Decision::Pass
+        );
+
+        for _ in 0..5 {
+            assert_eq!(sim.evaluate(&ctx(id2, sender1), now), Decision::Pass);
+        }
+        assert_eq!(sim.evaluate(&ctx(id2, sender1), now), Decision::Limit);
+
+        let stats = sim.stats();
+        assert_eq!(stats.len(), 3);
+        assert!(stats[0].shadow);
+        assert_eq!((stats[0].pass, stats[0].block, stats[0].limit), (0, 7, 0));
+        assert_eq!((stats[1].pass, stats[1].block, stats[1].limit), (4, 0, 3));
+        assert_eq!((stats[2].pass, stats[2].block, stats[2].limit), (5, 0, 1));
+    }
 }
@@ -0,0 +1,186 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufRead, BufReader},
+    net::{IpAddr, Ipv6Addr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Context as _, Error};
+use arc_swap::ArcSwapOption;
+use candid::Principal;
+use ic_bn_lib::types::RequestType;
+use rate_limits_api::v2::RateLimitRule;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cli,
+    persist::{Persist, PersistStatus, Persister},
+    rate_limiting::generic::{Context, RuleStats, Simulator},
+    snapshot::RegistrySnapshot,
+};
+
+// Fields of the request log entry that are used for the replay
+#[derive(Deserialize)]
+struct LogEntry {
+    timestamp: u64,
+    request_type: Option<String>,
+    canister_id: Option<String>,
+    subnet_id: Option<String>,
+    method: Option<String>,
+    sender: Option<String>,
+    remote_addr: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SubnetStats {
+    pub subnet_id: String,
+    pub nodes: usize,
+    pub requests: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Report {
+    // Number of replayed requests
+    pub requests: u64,
+    // Number of log lines that are not request log entries
+    pub skipped: u64,
+    // Number of requests that can't be routed to any subnet
+    pub unroutable: u64,
+    // Final decisions of the rate limiter
+    pub decisions: BTreeMap<String, u64>,
+    pub rules: Vec<RuleStats>,
+    // Subnets sorted by the number of requests routed to them
+    pub subnets: Vec<SubnetStats>,
+}
+
+fn parse_request_type(v: &str) -> RequestType {
+    match v {
+        "query" => RequestType::Query,
+        "call" => RequestType::Call,
+        "sync_call" => RequestType::SyncCall,
+        "read_state" => RequestType::ReadState,
+        "read_state_subnet" => RequestType::ReadStateSubnet,
+        _ => RequestType::Unknown,
+    }
+}
+
+fn parse_principal(v: &Option<String>) -> Option<Principal> {
+    v.as_ref().and_then(|x| Principal::from_text(x).ok())
+}
+
+/// Replays the request log against the routing table built from the snapshot and the rate-limit rules.
+/// Requests are routed & rate-limited in the same way as by the running service,
+/// except that the rate limits are applied according to the timestamps in the log.
+pub fn replay(
+    log: impl BufRead,
+    snapshot: &RegistrySnapshot,
+    rules: Vec<RateLimitRule>,
+    scale: u32,
+) -> Result<Report, Error> {
+    let routes = Arc::new(ArcSwapOption::empty());
+    let persister = Persister::new(Arc::clone(&routes));
+    if matches!(
+        persister.persist(snapshot.subnets.clone()),
+        PersistStatus::SkippedEmpty
+    ) {
+        return Err(anyhow!("registry snapshot has no subnets"));
+    }
+    // Persisted above, so it's always Some()
+    let routes = routes.load_full().unwrap();
+
+    let mut simulator = Simulator::new(rules, scale);
+    let mut report = Report::default();
+    let mut subnet_requests = HashMap::new();
+
+    for line in log.lines() {
+        let line = line.context("unable to read log")?;
+
+        // Skip everything that is not a request log entry
+        let Ok(entry) = serde_json::from_str::<LogEntry>(&line) else {
+            report.skipped += 1;
+            continue;
+        };
+        let Some(request_type) = entry.request_type.as_deref().map(parse_request_type) else {
+            report.skipped += 1;
+            continue;
+        };
+
+        report.requests += 1;
+
+        let canister_id = parse_principal(&entry.canister_id);
+        let subnet = if request_type == RequestType::ReadStateSubnet {
+            parse_principal(&entry.subnet_id).and_then(|x| routes.lookup_by_id(x))
+        } else {
+            canister_id.and_then(|x| routes.lookup_by_canister_id(x))
+        };
+
+        // Requests that can't be routed never reach the rate limiter
+        let Some(subnet) = subnet else {
+            report.unroutable += 1;
+            continue;
+        };
+        *subnet_requests.entry(subnet.id).or_insert(0) += 1;
+
+        // Anonymized values can't be parsed and are treated as unknown
+        let ip = entry
+            .remote_addr
+            .as_ref()
+            .and_then(|x| x.parse::<IpAddr>().ok())
+            .unwrap_or(IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+
+        let ctx = Context::new(
+            subnet.id,
+            canister_id,
+            parse_principal(&entry.sender),
+            entry.method.as_deref(),
+            request_type,
+            ip,
+        );
+
+        let decision = simulator.evaluate(&ctx, Duration::from_secs(entry.timestamp));
+        *report.decisions.entry(decision.to_string()).or_insert(0) += 1;
+    }
+
+    report.rules = simulator.stats().to_vec();
+    report.subnets = snapshot
+        .subnets
+        .iter()
+        .map(|x| SubnetStats {
+            subnet_id: x.id.to_string(),
+            nodes: x.nodes.len(),
+            requests: subnet_requests.get(&x.id).copied().unwrap_or(0),
+        })
+        .collect();
+    report.subnets.sort_by(|a, b| {
+        b.requests
+            .cmp(&a.requests)
+            .then(a.subnet_id.cmp(&b.subnet_id))
+    });
+
+    Ok(report)
+}
+
+pub async fn main(args: &cli::Replay) -> Result<(), Error> {
+    let snapshot = std::fs::read(&args.replay_registry_snapshot)
+        .context("unable to read registry snapshot")?;
+    let snapshot = RegistrySnapshot::from_json(&snapshot)?;
+
+    let rules = match &args.replay_rate_limit_rules {
+        Some(v) => {
+            let data = std::fs::read(v).context("unable to read rate-limit rules")?;
+            serde_yaml::from_slice(&data).context("unable to parse rate-limit rules")?
+        }
+        None => vec![],
+    };
+
+    let log = File::open(&args.replay_log).context("unable to open request log")?;
+    let report = replay(BufReader::new(log), &snapshot, rules, args.replay_scale)?;
+
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod test;
@@ -0,0 +1,101 @@
+use super::*;
+
+use indoc::indoc;
+
+use crate::persist::test::generate_test_subnets;
+
+fn generate_test_snapshot() -> RegistrySnapshot {
+    let snapshot = RegistrySnapshot {
+        version: 1,
+        timestamp: 123,
+        nns_public_key: vec![],
+        subnets: generate_test_subnets(0),
+        nodes: HashMap::new(),
+        api_bns: vec![],
+    };
+
+    // Make sure that the snapshot survives the roundtrip
+    RegistrySnapshot::from_json(&snapshot.to_json().unwrap()).unwrap()
+}
+
+#[test]
+fn test_replay() -> Result<(), Error> {
+    let snapshot = generate_test_snapshot();
+    assert_eq!(snapshot.nodes.len(), 3);
+
+    let rules = indoc! {"
+    - canister_id: ryjl3-tyaaa-aaaaa-aaaba-cai
+      limit: 2/1m
+
+    - canister_id: 2b2k4-rqaaa-aaaaa-qaatq-cai
+      methods_regex: ^foo$
+      limit: block
+      shadow: true
+    "};
+    let rules: Vec<RateLimitRule> = serde_yaml::from_str(rules)?;
+
+    let log = indoc! {r#"
+    {"timestamp":100,"request_type":"query","canister_id":"ryjl3-tyaaa-aaaaa-aaaba-cai","method":"foo","sender":"2vxsx-fae","remote_addr":"10.0.0.1"}
+    {"timestamp":100,"request_type":"query","canister_id":"ryjl3-tyaaa-aaaaa-aaaba-cai","method":"foo","sender":"2vxsx-fae","remote_addr":"10.0.0.1"}
+    {"timestamp":100,"request_type":"query","canister_id":"ryjl3-tyaaa-aaaaa-aaaba-cai","method":"foo","sender":"2vxsx-fae","remote_addr":"10.0.0.1"}
+    {"timestamp":160,"request_type":"query","canister_id":"ryjl3-tyaaa-aaaaa-aaaba-cai","method":"foo","sender":"2vxsx-fae","remote_addr":"10.0.0.1"}
+    {"timestamp":160,"request_type":"call","canister_id":"2b2k4-rqaaa-aaaaa-qaatq-cai","method":"foo","sender":"N/A","remote_addr":"deadbeef"}
+    {"timestamp":160,"request_type":"read_state_subnet","subnet_id":"uzr34-akd3s-xrdag-3ql62-ocgoh-ld2ao-tamcv-54e7j-krwgb-2gm4z-oqe"}
+    {"timestamp":160,"request_type":"call","canister_id":"aaaaa-aa","method":"foo"}
+    {"timestamp":160,"level":"WARN","message":"foobar"}
+    foobar
+    "#};
+
+    let report = replay(log.as_bytes(), &snapshot, rules, 1)?;
+
+    assert_eq!(report.requests, 7);
+    assert_eq!(report.skipped, 2);
+    assert_eq!(report.unroutable, 1);
+    assert_eq!(
+        report.decisions,
+        BTreeMap::from([("Pass".into(), 5), ("Limit".into(), 1)])
+    );
+
+    // Third request is limited, the fourth one gets a token refilled after a minute
+    assert_eq!((report.rules[0].pass, report.rules[0].limit), (3, 1));
+    // Shadow rule records the block, but the request passes
+    assert!(report.rules[1].shadow);
+    assert_eq!(report.rules[1].block, 1);
+
+    assert_eq!(
+        report.subnets,
+        vec![
+            SubnetStats {
+                subnet_id: "tdb26-jop6k-aogll-7ltgs-eruif-6kk7m-qpktf-gdiqx-mxtrf-vb5e6-eqe".into(),
+                nodes: 1,
+                requests: 4,
+            },
+            SubnetStats {
+                subnet_id: "snjp4-xlbw4-mnbog-ddwy6-6ckfd-2w5a2-eipqo-7l436-pxqkh-l6fuv-vae".into(),
+                nodes: 1,
+                requests: 1,
+            },
+            SubnetStats {
+                subnet_id: "uzr34-akd3s-xrdag-3ql62-ocgoh-ld2ao-tamcv-54e7j-krwgb-2gm4z-oqe".into(),
+                nodes: 1,
+                requests: 1,
+            },
+        ]
+    );
+
+    // Rate limits are scaled down
+    let rules = indoc! {"
+    - canister_id: ryjl3-tyaaa-aaaaa-aaaba-cai
+      limit: 2/1m
+    "};
+    let rules: Vec<RateLimitRule> = serde_yaml::from_str(rules)?;
+    let report = replay(log.as_bytes(), &snapshot, rules, 2)?;
+    assert_eq!((report.rules[0].pass, report.rules[0].limit), (2, 2));
+
+    // Empty snapshot
+    let mut snapshot = snapshot;
+    snapshot.subnets.clear();
+    assert!(replay(log.as_bytes(), &snapshot, vec![], 1).is_err());
+
+    Ok(())
+}
@@ -20,6 +20,7 @@ pub struct RetryParams {
     pub retry_count: usize,
     pub retry_update_call: bool,
     pub disable_latency_routing: bool,
+    pub p2c_routing: bool,
 }
 
 #[derive(Clone)]
@@ -60,7 +61,9 @@ pub async fn retry_request(
     next: Next,
 ) -> Result<impl IntoResponse, ApiError> {
     // Select up to 1+retry_count nodes from the subnet if there are any
-    let nodes = if !params.disable_latency_routing && (ctx.request_type.is_call()) {
+    let nodes = if params.p2c_routing {
+        subnet.pick_nodes_p2c(1 + params.retry_count)?
+    } else if !params.disable_latency_routing && (ctx.request_type.is_call()) {
         let factor = subnet.fault_tolerance_factor() + 1;
         subnet.pick_n_out_of_m_closest(1 + params.retry_count, factor)?
     } else {
@@ -78,6 +78,7 @@ async fn test_retry() -> Result<(), Error> {
                 retry_count: 3,
                 retry_update_call: false,
                 disable_latency_routing: true,
+                p2c_routing: false,
             },
             retry_request,
         ));
@@ -144,6 +145,7 @@ async fn test_retry() -> Result<(), Error> {
                 retry_count: 3,
                 retry_update_call: true,
                 disable_latency_routing: true,
+                p2c_routing: false,
             },
             retry_request,
         ));
@@ -2,6 +2,7 @@ use std::{
     collections::HashMap,
     fmt,
     net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
     str::FromStr,
     sync::Arc,
     time::{Duration, Instant},
@@ -21,6 +22,7 @@ use ic_registry_client_helpers::{
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_types::{NodeId, PrincipalId, RegistryVersion, SubnetId};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tokio::sync::watch;
 use tracing::{debug, warn};
 use url::{ParseError, Url};
@@ -36,7 +38,7 @@ use crate::{
 // Some magical prefix that the public key should have
 const DER_PREFIX: &[u8; 37] = b"\x30\x81\x82\x30\x1d\x06\x0d\x2b\x06\x01\x04\x01\x82\xdc\x7c\x05\x03\x01\x02\x01\x06\x0c\x2b\x06\x01\x04\x01\x82\xdc\x7c\x05\x03\x02\x01\x03\x61\x00";
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Node {
     pub id: Principal,
     pub subnet_id: Principal,
@@ -45,6 +47,8 @@ pub struct Node {
     pub port: u16,
     pub tls_certificate: Vec<u8>,
     pub avg_latency_secs: f64,
+    // Fraction of failed health checks, exponentially weighted
+    pub error_rate: f64,
 }
 
 // Lightweight Eq, just compare principals
@@ -87,7 +91,7 @@ impl Node {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
 pub struct ApiBoundaryNode {
     pub id: Principal,
@@ -95,17 +99,34 @@ pub struct ApiBoundaryNode {
     pub port: u16,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct CanisterRange {
     pub start: Principal,
     pub end: Principal,
 }
 
-#[derive(Clone, Debug)]
+// Serde helpers for a list of shared nodes
+mod arc_nodes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(nodes: &[Arc<Node>], s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq(nodes.iter().map(|x| x.as_ref()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<Arc<Node>>, D::Error> {
+        Ok(Vec::<Node>::deserialize(d)?
+            .into_iter()
+            .map(Arc::new)
+            .collect())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Subnet {
     pub id: Principal,
     pub subnet_type: SubnetType,
     pub ranges: Vec<CanisterRange>,
+    #[serde(with = "arc_nodes")]
     pub nodes: Vec<Arc<Node>>,
     pub replica_version: String,
 }
@@ -140,16 +161,38 @@ pub trait Snapshot: Send + Sync {
     fn snapshot(&mut self) -> Result<SnapshotResult, Error>;
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RegistrySnapshot {
     pub version: u64,
     pub timestamp: u64,
     pub nns_public_key: Vec<u8>,
     pub subnets: Vec<Subnet>,
+    // Rebuilt from the subnets when deserializing
+    #[serde(skip)]
     pub nodes: HashMap<String, Arc<Node>>,
     pub api_bns: Vec<ApiBoundaryNode>,
 }
 
+impl RegistrySnapshot {
+    pub fn to_json(&self) -> Result<Vec<u8>, Error> {
+        serde_json::to_vec(self).context("unable to serialize snapshot")
+    }
+
+    pub fn from_json(data: &[u8]) -> Result<Self, Error> {
+        let mut snapshot: Self =
+            serde_json::from_slice(data).context("unable to deserialize snapshot")?;
+
+        snapshot.nodes = snapshot
+            .subnets
+            .iter()
+            .flat_map(|x| x.nodes.iter())
+            .map(|x| (x.id.to_string(), x.clone()))
+            .collect();
+
+        Ok(snapshot)
+    }
+}
+
 pub struct Snapshotter {
     published_registry_snapshot: Arc<ArcSwapOption<RegistrySnapshot>>,
     channel_notify: watch::Sender<Option<Arc<RegistrySnapshot>>>,
@@ -159,6 +202,7 @@ pub struct Snapshotter {
     last_version_change: Instant,
     min_version_age: Duration,
     persister: Option<SnapshotPersister>,
+    dump_path: Option<PathBuf>,
 }
 
 pub struct SnapshotInfo {
@@ -195,6 +239,7 @@ impl Snapshotter {
             last_version_change: Instant::now(),
             min_version_age,
             persister: None,
+            dump_path: None,
         }
     }
 
@@ -202,6 +247,10 @@ impl Snapshotter {
         self.persister = Some(persister);
     }
 
+    pub fn set_dump_path(&mut self, path: PathBuf) {
+        self.dump_path = Some(path);
+    }
+
     fn get_api_boundary_nodes(
         &self,
         version: RegistryVersion,
@@ -346,6 +395,7 @@ impl Snapshotter {
                                 .context("unable to parse IP address")?,
                             port: http_endpoint.port as u16, // Port is u16 anyway
                             tls_certificate: cert.certificate_der,
+                            error_rate: 0.0,
                         };
                         let node = Arc::new(node);
 
@@ -443,17 +493,41 @@ impl Snapshot for Snapshotter {
         self.published_registry_snapshot
             .store(Some(snapshot_arc.clone()));
         self.registry_version_published = Some(version);
-        self.channel_notify.send_replace(Some(snapshot_arc));
+        self.channel_notify.send_replace(Some(snapshot_arc.clone()));
 
         // Persist the firewall rules if configured
         if let Some(v) = &self.persister {
             v.persist(snapshot)?;
         }
 
+        // Dump the snapshot if configured, e.g. for the offline replay.
+        // This is done last and is best-effort: failing to dump the snapshot
+        // must not affect the published snapshot nor the firewall rules.
+        if let Some(v) = &self.dump_path {
+            if let Err(e) = dump_snapshot(&snapshot_arc, v) {
+                warn!(
+                    "Unable to dump the registry snapshot to {}: {e:#}",
+                    v.display()
+                );
+            }
+        }
+
         Ok(SnapshotResult::Published(result))
     }
 }
 
+// Writes the snapshot atomically so that readers never see a partially written file
+fn dump_snapshot(snapshot: &RegistrySnapshot, path: &Path) -> Result<(), Error> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    std::fs::write(&tmp, snapshot.to_json()?).context("unable to write temporary file")?;
+    std::fs::rename(&tmp, path).context("unable to rename temporary file")?;
+
+    Ok(())
+}
+
 #[async_trait]
 impl<T: Snapshot> Run for WithMetricsSnapshot<T> {
     async fn run(&mut self) -> Result<(), Error> {
@@ -531,6 +605,7 @@ pub fn generate_stub_subnet(nodes: Vec<SocketAddr>) -> Subnet {
                 addr: x.ip(),
                 port: x.port(),
                 tls_certificate: vec![],
+                error_rate: 0.0,
             })
         })
         .collect::<Vec<_>>();
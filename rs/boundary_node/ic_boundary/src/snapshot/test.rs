@@ -60,3 +60,40 @@ async fn test_routing_table() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[test]
+fn test_dump_snapshot() -> Result<(), Error> {
+    let (snapshot, _, _) = test_registry_snapshot(2, 2);
+
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("snapshot.json");
+
+    // Existing file is replaced and the temporary file is renamed away
+    std::fs::write(&path, b"garbage")?;
+    dump_snapshot(&snapshot, &path)?;
+    assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+
+    let dumped = RegistrySnapshot::from_json(&std::fs::read(&path)?)?;
+    assert_eq!(dumped.version, snapshot.version);
+    assert_eq!(dumped.subnets.len(), 2);
+    assert_eq!(dumped.nodes.len(), 4);
+
+    // Failing to write the dump doesn't prevent the snapshot from being published
+    let published = Arc::new(ArcSwapOption::empty());
+    let (reg, _, _) = create_fake_registry_client(1, 1, None);
+    let (channel_send, _) = watch::channel(None);
+    let mut snapshotter = Snapshotter::new(
+        Arc::clone(&published),
+        channel_send,
+        Arc::new(reg),
+        Duration::ZERO,
+    );
+    snapshotter.set_dump_path(dir.path().join("missing").join("snapshot.json"));
+    assert!(matches!(
+        snapshotter.snapshot()?,
+        SnapshotResult::Published(_)
+    ));
+    assert!(published.load().is_some());
+
+    Ok(())
+}
@@ -1,9 +1,10 @@
 use candid::CandidType;
 use candid::Principal;
-use schema_versions::v1::RateLimitRule;
+use schema_versions::v2::RateLimitRule;
 use serde::{Deserialize, Serialize};
 mod schema_versions;
 pub use schema_versions::v1;
+pub use schema_versions::v2;
 
 pub type Version = u64;
 pub type Timestamp = u64;
@@ -0,0 +1,382 @@
+use std::fmt::{self, Display};
+
+use candid::Principal;
+use ipnet::IpNet;
+use regex::Regex;
+use serde::{
+    de::{Deserializer, Error},
+    ser::Serializer,
+    Deserialize, Serialize,
+};
+
+use super::v1;
+pub use super::v1::{Action, IpPrefixes, RequestType};
+
+pub const SCHEMA_VERSION: u64 = 2;
+
+/// Defines what the requests matching a rule are grouped by.
+/// Each group gets its own rate limiter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyBy {
+    /// Sender principal of the request
+    Sender,
+    /// Canister ID & method name of the request
+    CanisterMethod,
+}
+
+impl fmt::Display for KeyBy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sender => write!(f, "sender"),
+            Self::CanisterMethod => write!(f, "canister_method"),
+        }
+    }
+}
+
+/// Defines the rate-limit rule to be stored in the canister.
+///
+/// Compared to v1 it adds:
+/// - `key_by`: apply the limit per sender principal or per canister & method
+/// - `burst`: the capacity of the token bucket, defaults to the count in `limit`
+/// - `shadow`: only record the decisions that the rule would make without enforcing them
+#[derive(Clone, Deserialize, Serialize, Debug, Default)]
+#[serde(remote = "Self")]
+pub struct RateLimitRule {
+    pub canister_id: Option<Principal>,
+    pub subnet_id: Option<Principal>,
+    #[serde(default, with = "serde_regex")]
+    pub methods_regex: Option<Regex>,
+    pub ip: Option<IpNet>,
+    pub request_types: Option<Vec<RequestType>>,
+    pub ip_prefix_group: Option<IpPrefixes>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_by: Option<KeyBy>,
+    pub limit: Action,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub shadow: bool,
+}
+
+/// Regex does not implement Eq, so do it manually
+impl PartialEq for RateLimitRule {
+    fn eq(&self, other: &Self) -> bool {
+        self.methods_regex.as_ref().map(|x| x.as_str())
+            == other.methods_regex.as_ref().map(|x| x.as_str())
+            && self.request_types == other.request_types
+            && self.canister_id == other.canister_id
+            && self.subnet_id == other.subnet_id
+            && self.ip == other.ip
+            && self.ip_prefix_group == other.ip_prefix_group
+            && self.key_by == other.key_by
+            && self.limit == other.limit
+            && self.burst == other.burst
+            && self.shadow == other.shadow
+    }
+}
+impl Eq for RateLimitRule {}
+
+impl<'de> Deserialize<'de> for RateLimitRule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let this = Self::deserialize(deserializer)?;
+        let is_limit = matches!(this.limit, Action::Limit(_, _));
+
+        if this.ip_prefix_group.is_some() && !is_limit {
+            return Err(D::Error::custom(
+                "ip_prefix_group only makes sense with 'limit' set to an actual ratelimit",
+            ));
+        }
+
+        if this.key_by.is_some() && !is_limit {
+            return Err(D::Error::custom(
+                "key_by only makes sense with 'limit' set to an actual ratelimit",
+            ));
+        }
+
+        if this.key_by.is_some() && this.ip_prefix_group.is_some() {
+            return Err(D::Error::custom(
+                "key_by and ip_prefix_group cannot be used together",
+            ));
+        }
+
+        if let Some(v) = this.burst {
+            if !is_limit {
+                return Err(D::Error::custom(
+                    "burst only makes sense with 'limit' set to an actual ratelimit",
+                ));
+            }
+
+            if v == 0 {
+                return Err(D::Error::custom("burst should be > 0"));
+            }
+        }
+
+        if this.canister_id.is_none()
+            && this.subnet_id.is_none()
+            && this.methods_regex.is_none()
+            && this.request_types.is_none()
+            && this.ip.is_none()
+        {
+            return Err(D::Error::custom(
+                "at least one filtering condition must be specified",
+            ));
+        }
+
+        Ok(this)
+    }
+}
+
+impl Serialize for RateLimitRule {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Self::serialize(self, serializer)
+    }
+}
+
+impl std::fmt::Display for RateLimitRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "CanisterID: {}, SubnetID: {}, Request Types: {:?}, Methods: {}, IP: {}, IP Prefix: {}, Key By: {}, Limit: {}, Burst: {}, Shadow: {}",
+            format_option(&self.canister_id),
+            format_option(&self.subnet_id),
+            self.request_types,
+            format_option(&self.methods_regex),
+            format_option(&self.ip),
+            format_option(&self.ip_prefix_group),
+            format_option(&self.key_by),
+            self.limit,
+            format_option(&self.burst),
+            self.shadow,
+        )
+    }
+}
+
+fn format_option<T: Display>(v: &Option<T>) -> String {
+    match v {
+        Some(p) => p.to_string(),
+        None => "None".to_string(),
+    }
+}
+
+/// Every v1 rule is a valid v2 rule
+impl From<v1::RateLimitRule> for RateLimitRule {
+    fn from(v: v1::RateLimitRule) -> Self {
+        Self {
+            canister_id: v.canister_id,
+            subnet_id: v.subnet_id,
+            methods_regex: v.methods_regex,
+            ip: v.ip,
+            request_types: v.request_types,
+            ip_prefix_group: v.ip_prefix_group,
+            key_by: None,
+            limit: v.limit,
+            burst: None,
+            shadow: false,
+        }
+    }
+}
+
+impl RateLimitRule {
+    pub fn to_bytes_json(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+
+    pub fn to_bytes_yaml(&self) -> Result<Vec<u8>, serde_yaml::Error> {
+        serde_yaml::to_string(self).map(|x| x.into())
+    }
+
+    pub fn from_bytes_json(bytes: &[u8]) -> Result<Self, serde_json::Error> {
+        serde_json::from_slice(bytes)
+    }
+
+    pub fn from_bytes_yaml(bytes: &[u8]) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_slice(bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn test_rules() {
+        let rules = indoc! {"
+        - canister_id: aaaaa-aa
+          methods_regex: ^.*$
+          key_by: sender
+          limit: 100/1s
+          burst: 300
+
+        - canister_id: 5s2ji-faaaa-aaaaa-qaaaq-cai
+          key_by: canister_method
+          limit: 60/1m
+          shadow: true
+
+        - canister_id: 5s2ji-faaaa-aaaaa-qaaaq-cai
+          methods_regex: ^(foo|bar)$
+          limit: block
+          shadow: true
+
+        - canister_id: 5s2ji-faaaa-aaaaa-qaaaq-cai
+          ip_prefix_group:
+            v4: 24
+            v6: 64
+          limit: 90/1m
+        "};
+
+        let rules: Vec<RateLimitRule> = serde_yaml::from_str(rules).unwrap();
+
+        assert_eq!(
+            rules,
+            vec![
+                RateLimitRule {
+                    canister_id: Some(Principal::from_text("aaaaa-aa").unwrap()),
+                    methods_regex: Some(Regex::new("^.*$").unwrap()),
+                    key_by: Some(KeyBy::Sender),
+                    limit: Action::Limit(100, Duration::from_secs(1)),
+                    burst: Some(300),
+                    ..Default::default()
+                },
+                RateLimitRule {
+                    canister_id: Some(Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap()),
+                    key_by: Some(KeyBy::CanisterMethod),
+                    limit: Action::Limit(60, Duration::from_secs(60)),
+                    shadow: true,
+                    ..Default::default()
+                },
+                RateLimitRule {
+                    canister_id: Some(Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap()),
+                    methods_regex: Some(Regex::new("^(foo|bar)$").unwrap()),
+                    limit: Action::Block,
+                    shadow: true,
+                    ..Default::default()
+                },
+                RateLimitRule {
+                    canister_id: Some(Principal::from_text("5s2ji-faaaa-aaaaa-qaaaq-cai").unwrap()),
+                    ip_prefix_group: Some(IpPrefixes { v4: 24, v6: 64 }),
+                    limit: Action::Limit(90, Duration::from_secs(60)),
+                    ..Default::default()
+                },
+            ]
+        );
+
+        // Check that the rules survive the roundtrip
+        for rule in rules {
+            let bytes = rule.to_bytes_json().unwrap();
+            assert_eq!(RateLimitRule::from_bytes_json(&bytes).unwrap(), rule);
+            let bytes = rule.to_bytes_yaml().unwrap();
+            assert_eq!(RateLimitRule::from_bytes_yaml(&bytes).unwrap(), rule);
+        }
+
+        // key_by with block
+        let rule_raw = indoc! {"
+        canister_id: aaaaa-aa
+        key_by: sender
+        limit: block
+        "};
+
+        assert!(RateLimitRule::from_bytes_yaml(rule_raw.as_bytes())
+            .unwrap_err()
+            .to_string()
+            .contains("key_by only makes sense with"));
+
+        // key_by with ip prefixes
+        let rule_raw = indoc! {"
+        canister_id: aaaaa-aa
+        key_by: sender
+        ip_prefix_group:
+          v4: 24
+          v6: 64
+        limit: 10/1s
+        "};
+
+        assert!(RateLimitRule::from_bytes_yaml(rule_raw.as_bytes())
+            .unwrap_err()
+            .to_string()
+            .contains("cannot be used together"));
+
+        // Bad key
+        let rule_raw = indoc! {"
+        canister_id: aaaaa-aa
+        key_by: foobar
+        limit: 10/1s
+        "};
+
+        assert!(RateLimitRule::from_bytes_yaml(rule_raw.as_bytes())
+            .unwrap_err()
+            .to_string()
+            .contains("key_by"));
+
+        // burst with pass
+        let rule_raw = indoc! {"
+        canister_id: aaaaa-aa
+        limit: pass
+        burst: 10
+        "};
+
+        assert!(RateLimitRule::from_bytes_yaml(rule_raw.as_bytes())
+            .unwrap_err()
+            .to_string()
+            .contains("burst only makes sense with"));
+
+        // Zero burst
+        let rule_raw = indoc! {"
+        canister_id: aaaaa-aa
+        limit: 10/1s
+        burst: 0
+        "};
+
+        assert!(RateLimitRule::from_bytes_yaml(rule_raw.as_bytes())
+            .unwrap_err()
+            .to_string()
+            .contains("burst should be > 0"));
+
+        // No conditions
+        let rule_raw = indoc! {"
+        key_by: sender
+        limit: 100/1s
+        "};
+
+        assert!(RateLimitRule::from_bytes_yaml(rule_raw.as_bytes())
+            .unwrap_err()
+            .to_string()
+            .contains("at least one filtering condition must be"));
+    }
+
+    #[test]
+    fn test_from_v1() {
+        let rule_raw = indoc! {"
+        canister_id: aaaaa-aa
+        methods_regex: ^foo$
+        ip_prefix_group:
+          v4: 24
+          v6: 64
+        limit: 100/1s
+        "};
+
+        let rule_v1 = v1::RateLimitRule::from_bytes_yaml(rule_raw.as_bytes()).unwrap();
+        let rule_v2 = RateLimitRule::from_bytes_yaml(rule_raw.as_bytes()).unwrap();
+
+        // v1 rules parse as v2 rules with the new features disabled
+        assert_eq!(RateLimitRule::from(rule_v1.clone()), rule_v2);
+        assert_eq!(rule_v2.key_by, None);
+        assert_eq!(rule_v2.burst, None);
+        assert!(!rule_v2.shadow);
+
+        // v2 rules without the new features serialize the same way as v1 ones
+        assert_eq!(
+            rule_v1.to_bytes_json().unwrap(),
+            rule_v2.to_bytes_json().unwrap()
+        );
+    }
+}
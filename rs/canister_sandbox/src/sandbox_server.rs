@@ -175,7 +175,8 @@ mod tests {
         messages::{CallContextId, RequestMetadata},
         methods::{FuncRef, WasmMethod},
         time::Time,
-        CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumBytes, NumInstructions,
+        CanisterTimer, ComputeAllocation, Cycles, EnvironmentVariables, MemoryAllocation, NumBytes,
+        NumInstructions,
     };
     use ic_wasm_types::BinaryEncodedWasm;
     use mockall::*;
@@ -240,6 +241,7 @@ mod tests {
             CanisterTimer::Inactive,
             0,
             BTreeSet::from([controller]),
+            EnvironmentVariables::default(),
             RequestMetadata::new(0, Time::from_nanos_since_unix_epoch(0)),
             caller,
             0,
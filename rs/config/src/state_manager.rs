@@ -6,6 +6,10 @@ use std::path::PathBuf;
 pub struct LsmtConfig {
     /// Number of pages per shard in sharded overlays; u64::MAX if unlimited.
     pub shard_num_pages: u64,
+    /// Number of heights after which a shard that has not been written to is compressed on
+    /// disk; `None` if cold shards are never compressed.
+    #[serde(default)]
+    pub compress_cold_shards_after_heights: Option<u64>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, Deserialize, Serialize)]
@@ -49,5 +53,6 @@ pub fn lsmt_config_default() -> LsmtConfig {
         // DO NOT CHANGE after LSMT is enabled, as it would crash the new replica trying to merge
         // old data.
         shard_num_pages: 10 * 1024 * 1024,
+        compress_cold_shards_after_heights: None,
     }
 }
@@ -381,6 +381,7 @@ pub(crate) mod test {
                 GetSuccessorsResponseComplete {
                     blocks: vec![],
                     next: vec![],
+                    filters: vec![],
                 },
             ),
             callback_id: 0,
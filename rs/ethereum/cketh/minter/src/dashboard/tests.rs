@@ -1145,6 +1145,8 @@ fn initial_state() -> State {
         minimum_withdrawal_amount: Nat::from(10_000_000_000_000_000_u64),
         next_transaction_nonce: TransactionNonce::ZERO.into(),
         last_scraped_block_number: candid::Nat::from(INITIAL_LAST_SCRAPED_BLOCK_NUMBER),
+        evm_rpc_id: None,
+        l1_data_fee_allowance: None,
     })
     .expect("valid init args")
 }
@@ -1441,6 +1443,7 @@ fn cketh_withdrawal_flow(
         gas_used: signed_tx.transaction().gas_limit,
         status: tx_status,
         transaction_hash: signed_tx.hash(),
+        l1_fee: None,
     };
     (
         withdrawal_request.into(),
@@ -1473,6 +1476,7 @@ fn ckerc20_withdrawal_flow(
         gas_fee,
         GasAmount::from(65_000_u32),
         EthereumNetwork::Sepolia,
+        Wei::ZERO,
     )
     .unwrap();
     let dummy_signature = Eip1559Signature {
@@ -1494,6 +1498,7 @@ fn ckerc20_withdrawal_flow(
         gas_used: signed_tx.transaction().gas_limit,
         status: tx_status,
         transaction_hash: signed_tx.hash(),
+        l1_fee: None,
     };
     (
         withdrawal_request.into(),
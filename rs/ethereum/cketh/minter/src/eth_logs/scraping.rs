@@ -11,6 +11,11 @@ use ic_ethereum_types::Address;
 use std::iter::once;
 
 /// Trait for managing log scraping.
+///
+/// Logs are scraped up to the last observed block at the minter's `ethereum_block_height`,
+/// which must be final on the minter's network
+/// (see [`crate::lifecycle::EthereumNetwork::is_final_block_tag`]),
+/// so log scraping itself does not depend on the network.
 pub trait LogScraping {
     /// The unique identifier for this log scraping.
     const ID: LogScrapingId;
@@ -14,15 +14,16 @@ use evm_rpc_client::{
     Block as EvmBlock, BlockTag as EvmBlockTag, ConsensusStrategy, EvmRpcClient,
     FeeHistory as EvmFeeHistory, FeeHistoryArgs as EvmFeeHistoryArgs,
     GetLogsArgs as EvmGetLogsArgs, GetTransactionCountArgs as EvmGetTransactionCountArgs, Hex20,
-    Hex32, IcRuntime, LogEntry as EvmLogEntry, MultiRpcResult as EvmMultiRpcResult, Nat256,
-    OverrideRpcConfig, RpcConfig as EvmRpcConfig, RpcError as EvmRpcError,
-    RpcResult as EvmRpcResult, SendRawTransactionStatus as EvmSendRawTransactionStatus,
+    Hex32, IcRuntime, L2MainnetService, LogEntry as EvmLogEntry,
+    MultiRpcResult as EvmMultiRpcResult, Nat256, OverrideRpcConfig, RpcConfig as EvmRpcConfig,
+    RpcError as EvmRpcError, RpcResult as EvmRpcResult, RpcService,
+    SendRawTransactionStatus as EvmSendRawTransactionStatus,
     TransactionReceipt as EvmTransactionReceipt,
 };
 use ic_canister_log::log;
 use ic_ethereum_types::Address;
 use num_traits::ToPrimitive;
-use serde::{de::DeserializeOwned, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
 use std::convert::Infallible;
 use std::fmt::{Debug, Display};
@@ -37,6 +38,12 @@ mod tests;
 // We expect most of the calls to contain zero events.
 const ETH_GET_LOGS_INITIAL_RESPONSE_SIZE_ESTIMATE: u64 = 100;
 const TOTAL_NUMBER_OF_PROVIDERS: u8 = 4;
+const L2_MAINNET_SERVICES: [L2MainnetService; 4] = [
+    L2MainnetService::Alchemy,
+    L2MainnetService::Ankr,
+    L2MainnetService::BlockPi,
+    L2MainnetService::PublicNode,
+];
 
 #[derive(Debug)]
 pub struct EthRpcClient {
@@ -67,11 +74,17 @@ impl EthRpcClient {
                     EthSepoliaService::Alchemy,
                     EthSepoliaService::Ankr,
                 ])),
+                EthereumNetwork::ArbitrumOne => {
+                    EvmRpcServices::ArbitrumOne(Some(L2_MAINNET_SERVICES.to_vec()))
+                }
+                EthereumNetwork::Base => {
+                    EvmRpcServices::BaseMainnet(Some(L2_MAINNET_SERVICES.to_vec()))
+                }
+                EthereumNetwork::Optimism => {
+                    EvmRpcServices::OptimismMainnet(Some(L2_MAINNET_SERVICES.to_vec()))
+                }
             };
-            let min_threshold = match client.chain {
-                EthereumNetwork::Mainnet => 3_u8,
-                EthereumNetwork::Sepolia => 2_u8,
-            };
+            let min_threshold = client.min_threshold();
             assert!(
                 min_threshold <= TOTAL_NUMBER_OF_PROVIDERS,
                 "BUG: min_threshold too high"
@@ -107,10 +120,27 @@ impl EthRpcClient {
         client
     }
 
+    /// The minimum number of providers that must agree on a response.
+    fn min_threshold(&self) -> u8 {
+        match self.chain {
+            EthereumNetwork::Mainnet
+            | EthereumNetwork::ArbitrumOne
+            | EthereumNetwork::Base
+            | EthereumNetwork::Optimism => 3_u8,
+            EthereumNetwork::Sepolia => 2_u8,
+        }
+    }
+
     fn providers(&self) -> &[RpcNodeProvider] {
         match self.chain {
             EthereumNetwork::Mainnet => &MAINNET_PROVIDERS,
             EthereumNetwork::Sepolia => &SEPOLIA_PROVIDERS,
+            EthereumNetwork::ArbitrumOne | EthereumNetwork::Base | EthereumNetwork::Optimism => {
+                panic!(
+                    "BUG: {} is only reachable through the EVM RPC canister",
+                    self.chain
+                )
+            }
         }
     }
 
@@ -230,7 +260,10 @@ impl EthRpcClient {
 
         let expected_block_size = match self.chain {
             EthereumNetwork::Sepolia => 12 * 1024,
-            EthereumNetwork::Mainnet => 24 * 1024,
+            EthereumNetwork::Mainnet
+            | EthereumNetwork::ArbitrumOne
+            | EthereumNetwork::Base
+            | EthereumNetwork::Optimism => 24 * 1024,
         };
 
         let results: MultiCallResults<Block> = self
@@ -251,11 +284,24 @@ impl EthRpcClient {
         tx_hash: Hash,
     ) -> Result<Option<TransactionReceipt>, MultiCallError<Option<TransactionReceipt>>> {
         if let Some(evm_rpc_client) = &self.evm_rpc_client {
-            return evm_rpc_client
+            let result: Result<Option<TransactionReceipt>, _> = evm_rpc_client
                 .eth_get_transaction_receipt(tx_hash.to_string())
                 .await
                 .reduce()
                 .into();
+            return match result {
+                Ok(Some(receipt)) if self.chain.charges_l1_data_fee() => {
+                    // Without the L1 data fee, the receipt is treated as not yet available.
+                    Ok(self
+                        .eth_get_l1_fee(evm_rpc_client, tx_hash)
+                        .await
+                        .map(|l1_fee| TransactionReceipt {
+                            l1_fee: Some(l1_fee),
+                            ..receipt
+                        }))
+                }
+                result => result,
+            };
         }
         let results: MultiCallResults<Option<TransactionReceipt>> = self
             .parallel_call(
@@ -267,6 +313,81 @@ impl EthRpcClient {
         results.reduce().into()
     }
 
+    /// Retrieves the L1 data fee of a transaction on an OP Stack chain.
+    ///
+    /// The EVM RPC canister drops the `l1Fee` field of transaction receipts, so the
+    /// minter requests the raw receipt from each provider and returns the L1 data fee
+    /// only if at least [`Self::min_threshold`] providers agree on it.
+    async fn eth_get_l1_fee(
+        &self,
+        evm_rpc_client: &EvmRpcClient<IcRuntime, PrintProxySink>,
+        tx_hash: Hash,
+    ) -> Option<Wei> {
+        #[derive(Debug, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct L1FeeReceipt {
+            l1_fee: Wei,
+        }
+        // A raw receipt of a withdrawal transaction is at most a few kilobytes.
+        const MAX_RESPONSE_BYTES: u64 = 4 * 1024 + HEADER_SIZE_LIMIT;
+
+        let payload = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "eth_getTransactionReceipt",
+            "params": [tx_hash],
+            "id": 0,
+        })
+        .to_string();
+        let results = futures::future::join_all(L2_MAINNET_SERVICES.iter().map(|service| {
+            evm_rpc_client.request(
+                self.l2_rpc_service(*service),
+                payload.clone(),
+                MAX_RESPONSE_BYTES,
+            )
+        }))
+        .await;
+
+        let mut l1_fees: BTreeMap<Wei, u8> = BTreeMap::new();
+        for result in results {
+            let reply = result.map_err(|e| format!("{e:?}")).and_then(|response| {
+                serde_json::from_str::<eth_rpc::JsonRpcReply<Option<L1FeeReceipt>>>(&response)
+                    .map_err(|e| format!("failed to decode response {response}: {e}"))
+            });
+            match reply {
+                Ok(eth_rpc::JsonRpcReply {
+                    result: eth_rpc::JsonRpcResult::Result(Some(receipt)),
+                    ..
+                }) => *l1_fees.entry(receipt.l1_fee).or_default() += 1,
+                reply => log!(
+                    DEBUG,
+                    "[eth_get_l1_fee]: no L1 data fee for transaction {tx_hash}: {reply:?}"
+                ),
+            }
+        }
+        let l1_fee = l1_fees
+            .iter()
+            .find(|(_, count)| **count >= self.min_threshold())
+            .map(|(l1_fee, _)| *l1_fee);
+        if l1_fee.is_none() {
+            log!(
+                INFO,
+                "[eth_get_l1_fee]: providers don't agree on the L1 data fee of transaction {tx_hash}: {l1_fees:?}"
+            );
+        }
+        l1_fee
+    }
+
+    fn l2_rpc_service(&self, service: L2MainnetService) -> RpcService {
+        match self.chain {
+            EthereumNetwork::ArbitrumOne => RpcService::ArbitrumOne(service),
+            EthereumNetwork::Base => RpcService::BaseMainnet(service),
+            EthereumNetwork::Optimism => RpcService::OptimismMainnet(service),
+            EthereumNetwork::Mainnet | EthereumNetwork::Sepolia => {
+                panic!("BUG: {} is not a layer 2 network", self.chain)
+            }
+        }
+    }
+
     pub async fn eth_fee_history(
         &self,
         params: FeeHistoryParams,
@@ -762,6 +883,7 @@ impl Reduce for EvmMultiRpcResult<Option<EvmTransactionReceipt>> {
                                 .ok_or("invalid transaction status")?,
                         )?,
                         transaction_hash: Hash(evm_receipt.transaction_hash.into()),
+                        l1_fee: None,
                     })
                 })
                 .transpose()
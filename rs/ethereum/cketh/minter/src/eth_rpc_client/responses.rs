@@ -30,12 +30,24 @@ pub struct TransactionReceipt {
     /// The hash of the transaction
     #[n(5)]
     pub transaction_hash: Hash,
+
+    /// The fee for posting the transaction on Ethereum that OP Stack chains
+    /// charge on top of the gas fee, see
+    /// https://docs.optimism.io/stack/transactions/fees#l1-data-fee
+    #[n(6)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_fee: Option<Wei>,
 }
 
 impl TransactionReceipt {
+    /// The total fee paid by the sender of the transaction, including the L1 data fee, if any.
     pub fn effective_transaction_fee(&self) -> Wei {
-        self.effective_gas_price
+        let gas_fee = self
+            .effective_gas_price
             .transaction_cost(self.gas_used)
+            .expect("ERROR: overflow during transaction fee calculation");
+        gas_fee
+            .checked_add(self.l1_fee.unwrap_or(Wei::ZERO))
             .expect("ERROR: overflow during transaction fee calculation")
     }
 }
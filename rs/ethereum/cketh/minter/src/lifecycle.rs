@@ -1,6 +1,8 @@
 //! Module dealing with the lifecycle methods of the ckETH Minter.
+use crate::eth_rpc::BlockTag;
 use crate::lifecycle::init::InitArg;
 use crate::lifecycle::upgrade::UpgradeArg;
+use crate::numeric::{GasAmount, WeiPerGas};
 use candid::{CandidType, Deserialize};
 use minicbor::{Decode, Encode};
 use std::fmt::{Display, Formatter};
@@ -29,6 +31,12 @@ pub enum EthereumNetwork {
     #[n(11155111)]
     #[default]
     Sepolia,
+    #[n(42161)]
+    ArbitrumOne,
+    #[n(8453)]
+    Base,
+    #[n(10)]
+    Optimism,
 }
 
 impl EthereumNetwork {
@@ -36,8 +44,79 @@ impl EthereumNetwork {
         match self {
             EthereumNetwork::Mainnet => 1,
             EthereumNetwork::Sepolia => 11155111,
+            EthereumNetwork::ArbitrumOne => 42161,
+            EthereumNetwork::Base => 8453,
+            EthereumNetwork::Optimism => 10,
         }
     }
+
+    /// Whether the network is a rollup that settles on Ethereum.
+    /// Such networks are only reachable through the EVM RPC canister.
+    pub fn is_layer_2(&self) -> bool {
+        match self {
+            EthereumNetwork::Mainnet | EthereumNetwork::Sepolia => false,
+            EthereumNetwork::ArbitrumOne | EthereumNetwork::Base | EthereumNetwork::Optimism => {
+                true
+            }
+        }
+    }
+
+    /// Lower bound on the `max_priority_fee_per_gas` of transactions sent by the minter.
+    pub fn min_max_priority_fee_per_gas(&self) -> WeiPerGas {
+        match self {
+            // average value between the `minSuggestedMaxPriorityFeePerGas`
+            // used by Metamask, see
+            // https://github.com/MetaMask/core/blob/f5a4f52e17f407c6411e4ef9bd6685aab184b91d/packages/gas-fee-controller/src/fetchGasEstimatesViaEthFeeHistory/calculateGasFeeEstimatesForPriorityLevels.ts#L14
+            EthereumNetwork::Mainnet | EthereumNetwork::Sepolia => {
+                WeiPerGas::new(1_500_000_000) //1.5 gwei
+            }
+            // The Arbitrum sequencer orders transactions on a first-come first-served basis
+            // and ignores the priority fee, see
+            // https://docs.arbitrum.io/how-arbitrum-works/gas-fees#tips-in-l2
+            EthereumNetwork::ArbitrumOne => WeiPerGas::ZERO,
+            // OP Stack chains order transactions by priority fee, but blocks are rarely full.
+            EthereumNetwork::Base | EthereumNetwork::Optimism => {
+                WeiPerGas::new(1_000_000) //0.001 gwei
+            }
+        }
+    }
+
+    /// Gas added to the gas limit of every transaction to cover the cost of posting it on Ethereum.
+    ///
+    /// On Arbitrum, this cost is charged in L2 gas, so a simple ETH transfer
+    /// uses more than 21_000 gas, see
+    /// https://docs.arbitrum.io/build-decentralized-apps/how-to-estimate-gas
+    pub fn l1_gas_allowance(&self) -> GasAmount {
+        match self {
+            EthereumNetwork::ArbitrumOne => GasAmount::new(100_000),
+            EthereumNetwork::Mainnet
+            | EthereumNetwork::Sepolia
+            | EthereumNetwork::Base
+            | EthereumNetwork::Optimism => GasAmount::ZERO,
+        }
+    }
+
+    /// Whether transactions pay an L1 data fee on top of the gas fee.
+    pub fn charges_l1_data_fee(&self) -> bool {
+        match self {
+            EthereumNetwork::Base | EthereumNetwork::Optimism => true,
+            EthereumNetwork::Mainnet | EthereumNetwork::Sepolia | EthereumNetwork::ArbitrumOne => {
+                false
+            }
+        }
+    }
+
+    /// Whether the minter may scrape logs up to the given block.
+    ///
+    /// Rollup blocks tagged `latest` or `safe` only have the sequencer's soft
+    /// confirmation or are part of a batch posted in an Ethereum block that is not
+    /// finalized yet, so they can still be reorganized. On these networks, the
+    /// minter only considers blocks tagged `finalized`, which the nodes of each
+    /// network derive from the finalized Ethereum blocks. The minter doesn't wait
+    /// for an additional number of confirmations on top of the block tag.
+    pub fn is_final_block_tag(&self, block_tag: BlockTag) -> bool {
+        !self.is_layer_2() || block_tag == BlockTag::Finalized
+    }
 }
 
 impl TryFrom<u64> for EthereumNetwork {
@@ -47,6 +126,9 @@ impl TryFrom<u64> for EthereumNetwork {
         match value {
             1 => Ok(EthereumNetwork::Mainnet),
             11155111 => Ok(EthereumNetwork::Sepolia),
+            42161 => Ok(EthereumNetwork::ArbitrumOne),
+            8453 => Ok(EthereumNetwork::Base),
+            10 => Ok(EthereumNetwork::Optimism),
             _ => Err("Unknown Ethereum Network".to_string()),
         }
     }
@@ -57,6 +139,9 @@ impl Display for EthereumNetwork {
         match self {
             EthereumNetwork::Mainnet => write!(f, "Ethereum Mainnet"),
             EthereumNetwork::Sepolia => write!(f, "Ethereum Testnet Sepolia"),
+            EthereumNetwork::ArbitrumOne => write!(f, "Arbitrum One"),
+            EthereumNetwork::Base => write!(f, "Base"),
+            EthereumNetwork::Optimism => write!(f, "OP Mainnet"),
         }
     }
 }
@@ -29,6 +29,10 @@ pub struct InitArg {
     pub next_transaction_nonce: Nat,
     #[cbor(n(8), with = "icrc_cbor::nat")]
     pub last_scraped_block_number: Nat,
+    #[cbor(n(9), with = "icrc_cbor::principal::option")]
+    pub evm_rpc_id: Option<Principal>,
+    #[cbor(n(10), with = "icrc_cbor::nat::option")]
+    pub l1_data_fee_allowance: Option<Nat>,
 }
 
 impl TryFrom<InitArg> for State {
@@ -43,6 +47,8 @@ impl TryFrom<InitArg> for State {
             minimum_withdrawal_amount,
             next_transaction_nonce,
             last_scraped_block_number,
+            evm_rpc_id,
+            l1_data_fee_allowance,
         }: InitArg,
     ) -> Result<Self, Self::Error> {
         use std::str::FromStr;
@@ -52,6 +58,11 @@ impl TryFrom<InitArg> for State {
         let minimum_withdrawal_amount = Wei::try_from(minimum_withdrawal_amount).map_err(|e| {
             InvalidStateError::InvalidMinimumWithdrawalAmount(format!("ERROR: {}", e))
         })?;
+        let l1_data_fee_allowance = l1_data_fee_allowance
+            .map(Wei::try_from)
+            .transpose()
+            .map_err(|e| InvalidStateError::InvalidL1DataFeeAllowance(format!("ERROR: {}", e)))?
+            .unwrap_or(Wei::ZERO);
         let eth_helper_contract_address = ethereum_contract_address
             .map(|a| Address::from_str(&a))
             .transpose()
@@ -85,6 +96,7 @@ impl TryFrom<InitArg> for State {
             eth_transactions: EthTransactions::new(initial_nonce),
             cketh_ledger_id: ledger_id,
             cketh_minimum_withdrawal_amount: minimum_withdrawal_amount,
+            l1_data_fee_allowance,
             ethereum_block_height: BlockTag::from(ethereum_block_height),
             first_scraped_block_number,
             last_observed_block_number: None,
@@ -98,7 +110,7 @@ impl TryFrom<InitArg> for State {
             http_request_counter: 0,
             last_transaction_price_estimate: None,
             ledger_suite_orchestrator_id: None,
-            evm_rpc_id: None,
+            evm_rpc_id,
             ckerc20_tokens: Default::default(),
             erc20_balances: Default::default(),
             log_scrapings,
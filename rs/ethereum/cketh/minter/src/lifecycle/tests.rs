@@ -1,5 +1,7 @@
 mod init {
+    use crate::endpoints::CandidBlockTag;
     use crate::lifecycle::init::InitArg;
+    use crate::lifecycle::EthereumNetwork;
     use crate::numeric::{TransactionNonce, Wei};
     use crate::state::eth_logs_scraping::LogScrapingId;
     use crate::state::{InvalidStateError, State};
@@ -73,6 +75,105 @@ mod init {
         );
     }
 
+    #[test]
+    fn should_require_evm_rpc_id_on_layer_2_networks() {
+        for ethereum_network in [
+            EthereumNetwork::ArbitrumOne,
+            EthereumNetwork::Base,
+            EthereumNetwork::Optimism,
+        ] {
+            assert_matches!(
+                State::try_from(InitArg {
+                    ethereum_network,
+                    evm_rpc_id: None,
+                    ethereum_block_height: CandidBlockTag::Finalized,
+                    ..valid_init_arg()
+                }),
+                Err(InvalidStateError::InvalidEvmRpcId(_))
+            );
+
+            let evm_rpc_id = Principal::from_text("7hfb6-caaaa-aaaar-qadga-cai").unwrap();
+            let state = State::try_from(InitArg {
+                ethereum_network,
+                evm_rpc_id: Some(evm_rpc_id),
+                ethereum_block_height: CandidBlockTag::Finalized,
+                l1_data_fee_allowance: ethereum_network
+                    .charges_l1_data_fee()
+                    .then(|| Nat::from(10_000_000_000_000_u64)),
+                ..valid_init_arg()
+            })
+            .expect("valid init args");
+            assert_eq!(state.ethereum_network, ethereum_network);
+            assert_eq!(state.evm_rpc_id, Some(evm_rpc_id));
+        }
+    }
+
+    #[test]
+    fn should_require_finalized_block_height_on_layer_2_networks() {
+        let evm_rpc_id = Principal::from_text("7hfb6-caaaa-aaaar-qadga-cai").unwrap();
+        for ethereum_network in [
+            EthereumNetwork::ArbitrumOne,
+            EthereumNetwork::Base,
+            EthereumNetwork::Optimism,
+        ] {
+            for ethereum_block_height in [CandidBlockTag::Latest, CandidBlockTag::Safe] {
+                assert_matches!(
+                    State::try_from(InitArg {
+                        ethereum_network,
+                        evm_rpc_id: Some(evm_rpc_id),
+                        ethereum_block_height,
+                        ..valid_init_arg()
+                    }),
+                    Err(InvalidStateError::InvalidEthereumBlockHeight(_))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn should_require_l1_data_fee_allowance_on_op_stack_networks() {
+        let evm_rpc_id = Principal::from_text("7hfb6-caaaa-aaaar-qadga-cai").unwrap();
+        for ethereum_network in [EthereumNetwork::Base, EthereumNetwork::Optimism] {
+            assert_matches!(
+                State::try_from(InitArg {
+                    ethereum_network,
+                    evm_rpc_id: Some(evm_rpc_id),
+                    ethereum_block_height: CandidBlockTag::Finalized,
+                    l1_data_fee_allowance: None,
+                    ..valid_init_arg()
+                }),
+                Err(InvalidStateError::InvalidL1DataFeeAllowance(_))
+            );
+
+            let state = State::try_from(InitArg {
+                ethereum_network,
+                evm_rpc_id: Some(evm_rpc_id),
+                ethereum_block_height: CandidBlockTag::Finalized,
+                l1_data_fee_allowance: Some(Nat::from(10_000_000_000_000_u64)),
+                ..valid_init_arg()
+            })
+            .expect("valid init args");
+            assert_eq!(state.l1_data_fee_allowance, Wei::new(10_000_000_000_000));
+        }
+
+        for ethereum_network in [
+            EthereumNetwork::Mainnet,
+            EthereumNetwork::Sepolia,
+            EthereumNetwork::ArbitrumOne,
+        ] {
+            assert_matches!(
+                State::try_from(InitArg {
+                    ethereum_network,
+                    evm_rpc_id: Some(evm_rpc_id),
+                    ethereum_block_height: CandidBlockTag::Finalized,
+                    l1_data_fee_allowance: Some(Nat::from(1_u8)),
+                    ..valid_init_arg()
+                }),
+                Err(InvalidStateError::InvalidL1DataFeeAllowance(_))
+            );
+        }
+    }
+
     #[test]
     fn should_succeed() {
         let init_arg = valid_init_arg();
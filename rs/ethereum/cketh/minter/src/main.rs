@@ -38,8 +38,8 @@ use ic_cketh_minter::state::{
 };
 use ic_cketh_minter::tx::lazy_refresh_gas_fee_estimate;
 use ic_cketh_minter::withdraw::{
-    process_reimbursement, process_retrieve_eth_requests, CKERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
-    CKETH_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+    process_reimbursement, process_retrieve_eth_requests, withdrawal_gas_limit,
+    CKERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT, CKETH_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
 };
 use ic_cketh_minter::{endpoints, erc20};
 use ic_cketh_minter::{
@@ -168,7 +168,8 @@ async fn smart_contract_address() -> String {
 async fn eip_1559_transaction_price(
     token: Option<Eip1559TransactionPriceArg>,
 ) -> Eip1559TransactionPrice {
-    let gas_limit = match token {
+    let ethereum_network = read_state(State::ethereum_network);
+    let execution_gas_limit = match token {
         None => CKETH_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
         Some(Eip1559TransactionPriceArg { ckerc20_ledger_id }) => {
             match read_state(|s| s.find_ck_erc20_token_by_ledger_id(&ckerc20_ledger_id)) {
@@ -186,9 +187,16 @@ async fn eip_1559_transaction_price(
             }
         }
     };
+    let gas_limit = withdrawal_gas_limit(ethereum_network, execution_gas_limit);
     match read_state(|s| s.last_transaction_price_estimate.clone()) {
         Some((ts, estimate)) => {
-            let mut result = Eip1559TransactionPrice::from(estimate.to_price(gas_limit));
+            let price = estimate.to_price(gas_limit);
+            let max_transaction_fee = price
+                .max_transaction_fee()
+                .checked_add(read_state(State::l1_data_fee_allowance))
+                .unwrap_or(Wei::MAX);
+            let mut result = Eip1559TransactionPrice::from(price);
+            result.max_transaction_fee = max_transaction_fee.into();
             result.timestamp = Some(ts);
             result
         }
@@ -544,12 +552,18 @@ async fn withdraw_erc20(
 }
 
 async fn estimate_erc20_transaction_fee() -> Option<Wei> {
+    let ethereum_network = read_state(State::ethereum_network);
     lazy_refresh_gas_fee_estimate()
         .await
         .map(|gas_fee_estimate| {
             gas_fee_estimate
-                .to_price(CKERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT)
+                .to_price(withdrawal_gas_limit(
+                    ethereum_network,
+                    CKERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
+                ))
                 .max_transaction_fee()
+                .checked_add(read_state(State::l1_data_fee_allowance))
+                .unwrap_or(Wei::MAX)
         })
 }
 
@@ -58,6 +58,15 @@ pub struct State {
     pub log_scrapings: LogScrapings,
     pub ecdsa_public_key: Option<EcdsaPublicKeyResponse>,
     pub cketh_minimum_withdrawal_amount: Wei,
+
+    /// Amount reserved from every withdrawal to cover the cost of posting the transaction on Ethereum.
+    ///
+    /// On OP Stack chains, this cost is an L1 data fee that is deducted from the sender's balance
+    /// on top of the gas fee, see https://docs.optimism.io/stack/transactions/fees#l1-data-fee
+    /// The actual fee is only known once the transaction is finalized and is returned by
+    /// `eth_getTransactionReceipt`. If it exceeds the allowance, the minter pays the difference
+    /// from the fees that it charged but did not spend.
+    pub l1_data_fee_allowance: Wei,
     pub ethereum_block_height: BlockTag,
     pub first_scraped_block_number: BlockNumber,
     pub last_observed_block_number: Option<BlockNumber>,
@@ -112,6 +121,9 @@ pub enum InvalidStateError {
     InvalidMinimumWithdrawalAmount(String),
     InvalidLastScrapedBlockNumber(String),
     InvalidLastErc20ScrapedBlockNumber(String),
+    InvalidEvmRpcId(String),
+    InvalidEthereumBlockHeight(String),
+    InvalidL1DataFeeAllowance(String),
 }
 
 #[derive(Clone, Eq, PartialEq, Debug)]
@@ -159,7 +171,10 @@ impl State {
             ));
         }
         let cketh_ledger_transfer_fee = match self.ethereum_network {
-            EthereumNetwork::Mainnet => Wei::new(2_000_000_000_000),
+            EthereumNetwork::Mainnet
+            | EthereumNetwork::ArbitrumOne
+            | EthereumNetwork::Base
+            | EthereumNetwork::Optimism => Wei::new(2_000_000_000_000),
             EthereumNetwork::Sepolia => Wei::new(10_000_000_000),
         };
         if self.cketh_minimum_withdrawal_amount < cketh_ledger_transfer_fee {
@@ -169,6 +184,27 @@ impl State {
                     .to_string(),
             ));
         }
+        if self.ethereum_network.is_layer_2() && self.evm_rpc_id.is_none() {
+            return Err(InvalidStateError::InvalidEvmRpcId(format!(
+                "evm_rpc_id must be set to use {}",
+                self.ethereum_network
+            )));
+        }
+        if !self
+            .ethereum_network
+            .is_final_block_tag(self.ethereum_block_height)
+        {
+            return Err(InvalidStateError::InvalidEthereumBlockHeight(format!(
+                "ethereum_block_height must be finalized to use {}",
+                self.ethereum_network
+            )));
+        }
+        if self.ethereum_network.charges_l1_data_fee() != (self.l1_data_fee_allowance > Wei::ZERO) {
+            return Err(InvalidStateError::InvalidL1DataFeeAllowance(format!(
+                "l1_data_fee_allowance must be positive if and only if {} charges an L1 data fee",
+                self.ethereum_network
+            )));
+        }
         Ok(())
     }
 
@@ -359,9 +395,6 @@ impl State {
                 .expect("BUG: withdrawal amount MUST always be at least the transaction amount"),
             WithdrawalRequest::CkErc20(req) => req.max_transaction_fee,
         };
-        let unspent_tx_fee = charged_tx_fee.checked_sub(tx_fee).expect(
-            "BUG: charged transaction fee MUST always be at least the effective transaction fee",
-        );
         let debited_amount = match receipt.status {
             TransactionStatus::Success => tx
                 .transaction()
@@ -372,7 +405,18 @@ impl State {
         };
         self.eth_balance.eth_balance_sub(debited_amount);
         self.eth_balance.total_effective_tx_fees_add(tx_fee);
-        self.eth_balance.total_unspent_tx_fees_add(unspent_tx_fee);
+        match charged_tx_fee.checked_sub(tx_fee) {
+            Some(unspent_tx_fee) => self.eth_balance.total_unspent_tx_fees_add(unspent_tx_fee),
+            // The L1 data fee charged on OP Stack networks is only known once the transaction
+            // is finalized and may exceed the `l1_data_fee_allowance`, in which case the
+            // minter pays the difference out of the fees unspent by previous withdrawals.
+            // The allowance must be set so that this difference is always covered.
+            None => self.eth_balance.total_unspent_tx_fees_sub(
+                tx_fee
+                    .checked_sub(charged_tx_fee)
+                    .expect("BUG: tx_fee is greater than charged_tx_fee"),
+            ),
+        }
 
         if receipt.status == TransactionStatus::Success && !tx.transaction_data().is_empty() {
             let TransactionCallData::Erc20Transfer { to: _, value } = TransactionCallData::decode(
@@ -445,6 +489,10 @@ impl State {
         self.ethereum_network
     }
 
+    pub const fn l1_data_fee_allowance(&self) -> Wei {
+        self.l1_data_fee_allowance
+    }
+
     pub const fn ethereum_block_height(&self) -> BlockTag {
         self.ethereum_block_height
     }
@@ -463,6 +511,7 @@ impl State {
             evm_rpc_id,
             deposit_with_subaccount_helper_contract_address,
             last_deposit_with_subaccount_scraped_block_number,
+            l1_data_fee_allowance,
         } = upgrade_args;
         if let Some(nonce) = next_transaction_nonce {
             let nonce = TransactionNonce::try_from(nonce)
@@ -475,6 +524,11 @@ impl State {
             })?;
             self.cketh_minimum_withdrawal_amount = minimum_withdrawal_amount;
         }
+        if let Some(amount) = l1_data_fee_allowance {
+            self.l1_data_fee_allowance = Wei::try_from(amount).map_err(|e| {
+                InvalidStateError::InvalidL1DataFeeAllowance(format!("ERROR: {}", e))
+            })?;
+        }
         if let Some(address) = ethereum_contract_address {
             let eth_helper_contract_address = Address::from_str(&address).map_err(|e| {
                 InvalidStateError::InvalidEthereumContractAddress(format!("ERROR: {}", e))
@@ -562,6 +616,7 @@ impl State {
             self.cketh_minimum_withdrawal_amount,
             other.cketh_minimum_withdrawal_amount
         );
+        ensure_eq!(self.l1_data_fee_allowance, other.l1_data_fee_allowance);
         ensure_eq!(
             self.first_scraped_block_number,
             other.first_scraped_block_number
@@ -726,6 +781,18 @@ impl EthBalance {
             })
     }
 
+    fn total_unspent_tx_fees_sub(&mut self, value: Wei) {
+        self.total_unspent_tx_fees = self
+            .total_unspent_tx_fees
+            .checked_sub(value)
+            .unwrap_or_else(|| {
+                panic!(
+                    "BUG: underflow when subtracting {} from {}",
+                    value, self.total_unspent_tx_fees
+                )
+            })
+    }
+
     pub fn eth_balance(&self) -> Wei {
         self.eth_balance
     }
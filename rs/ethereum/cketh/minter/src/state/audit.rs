@@ -78,9 +78,12 @@ pub fn apply_state_transition(state: &mut State, payload: &EventType) {
             withdrawal_id,
             transaction,
         } => {
-            state
-                .eth_transactions
-                .record_created_transaction(*withdrawal_id, transaction.clone());
+            let l1_data_fee_allowance = state.l1_data_fee_allowance();
+            state.eth_transactions.record_created_transaction(
+                *withdrawal_id,
+                transaction.clone(),
+                l1_data_fee_allowance,
+            );
         }
         EventType::SignedTransaction {
             withdrawal_id: _,
@@ -352,6 +352,7 @@ impl GetEventsFile {
                             CandidTransactionStatus::Failure => TransactionStatus::Failure,
                         },
                         transaction_hash: transaction_receipt.transaction_hash.parse().unwrap(),
+                        l1_fee: None,
                     },
                 },
                 EventPayload::ReimbursedEthWithdrawal {
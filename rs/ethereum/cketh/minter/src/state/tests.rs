@@ -576,6 +576,8 @@ prop_compose! {
         ledger_id in arb_principal(),
         ecdsa_key_name in "[a-z_]*",
         last_scraped_block_number in arb_nat(),
+        evm_rpc_id in proptest::option::of(arb_principal()),
+        l1_data_fee_allowance in proptest::option::of(arb_nat()),
     ) -> InitArg {
         InitArg {
             ethereum_network: EthereumNetwork::Sepolia,
@@ -586,6 +588,8 @@ prop_compose! {
             minimum_withdrawal_amount,
             next_transaction_nonce,
             last_scraped_block_number,
+            evm_rpc_id,
+            l1_data_fee_allowance,
         }
     }
 }
@@ -602,6 +606,7 @@ prop_compose! {
         evm_rpc_id in proptest::option::of(arb_principal()),
         deposit_with_subaccount_helper_contract_address in proptest::option::of(arb_address()),
         last_deposit_with_subaccount_scraped_block_number in proptest::option::of(arb_nat()),
+        l1_data_fee_allowance in proptest::option::of(arb_nat()),
     ) -> UpgradeArg {
         UpgradeArg {
             ethereum_contract_address: contract_address.map(|addr| addr.to_string()),
@@ -613,7 +618,8 @@ prop_compose! {
             last_erc20_scraped_block_number,
             evm_rpc_id,
             deposit_with_subaccount_helper_contract_address: deposit_with_subaccount_helper_contract_address.map(|addr| addr.to_string()),
-            last_deposit_with_subaccount_scraped_block_number
+            last_deposit_with_subaccount_scraped_block_number,
+            l1_data_fee_allowance,
         }
     }
 }
@@ -723,6 +729,7 @@ prop_compose! {
         gas_used in arb_checked_amount_of(),
         status in arb_transaction_status(),
         transaction_hash in arb_hash(),
+        l1_fee in proptest::option::of(arb_checked_amount_of()),
     ) -> TransactionReceipt {
         TransactionReceipt {
             block_hash,
@@ -731,6 +738,7 @@ prop_compose! {
             gas_used,
             status,
             transaction_hash,
+            l1_fee,
         }
     }
 }
@@ -950,6 +958,7 @@ fn state_equivalence() {
                     "0x06afc3c693dc2ba2c19b5c287c4dddce040d766bea5fd13c8a7268b04aa94f2d"
                         .parse()
                         .unwrap(),
+                l1_fee: None,
             })
             .expect("valid receipt"),
         ),
@@ -997,6 +1006,7 @@ fn state_equivalence() {
             chain_code: vec![2; 32],
         }),
         cketh_minimum_withdrawal_amount: Wei::new(1_000_000_000_000_000),
+        l1_data_fee_allowance: Wei::ZERO,
         ethereum_block_height: BlockTag::Finalized,
         first_scraped_block_number: BlockNumber::new(1_000_001),
         last_observed_block_number: Some(BlockNumber::new(2_000_000)),
@@ -1097,6 +1107,15 @@ fn state_equivalence() {
         "changing essential fields should break equivalence",
     );
 
+    assert_ne!(
+        Ok(()),
+        state.is_equivalent_to(&State {
+            l1_data_fee_allowance: Wei::new(1),
+            ..state.clone()
+        }),
+        "changing essential fields should break equivalence",
+    );
+
     assert_ne!(
         Ok(()),
         state.is_equivalent_to(&State {
@@ -1292,6 +1311,7 @@ mod eth_balance {
     use crate::state::tests::{initial_state, received_eth_event};
     use crate::state::transactions::{create_transaction, EthWithdrawalRequest, WithdrawalRequest};
     use crate::state::{EthBalance, State};
+    use crate::test_fixtures::expect_panic_with_message;
     use crate::tx::{Eip1559Signature, SignedEip1559TransactionRequest};
     use maplit::btreemap;
 
@@ -1456,6 +1476,69 @@ mod eth_balance {
         );
     }
 
+    #[test]
+    fn should_pay_l1_data_fee_exceeding_allowance_from_unspent_tx_fees() {
+        let mut state = initial_state();
+        apply_state_transition(
+            &mut state,
+            &EventType::AcceptedDeposit(received_eth_event()),
+        );
+        let withdrawal_request = |ledger_burn_index: u64| EthWithdrawalRequest {
+            withdrawal_amount: Wei::new(1_000_000_000_000_000),
+            destination: "0xb44B5e756A894775FC32EDdf3314Bb1B1944dC34"
+                .parse()
+                .unwrap(),
+            ledger_burn_index: LedgerBurnIndex::new(ledger_burn_index),
+            from: "k2t6j-2nvnp-4zjm3-25dtz-6xhaa-c7boj-5gayf-oj3xs-i43lp-teztq-6ae"
+                .parse()
+                .unwrap(),
+            from_subaccount: None,
+            created_at: Some(1699527697000000000),
+        };
+
+        WithdrawalFlow::for_request(withdrawal_request(0)).apply(&mut state);
+        let balance_before = state.eth_balance.clone();
+        let unspent_tx_fee = balance_before.total_unspent_tx_fees();
+        let charged_tx_fee = unspent_tx_fee.checked_add(Wei::new(21_000)).unwrap();
+        let excess_tx_fee = Wei::new(10_000);
+        assert!(unspent_tx_fee > excess_tx_fee);
+
+        let receipt = WithdrawalFlow {
+            nonce: TransactionNonce::new(1),
+            l1_fee: Some(unspent_tx_fee.checked_add(excess_tx_fee).unwrap()),
+            ..WithdrawalFlow::for_request(withdrawal_request(1))
+        }
+        .apply(&mut state);
+        assert_eq!(
+            receipt.effective_transaction_fee(),
+            charged_tx_fee.checked_add(excess_tx_fee).unwrap()
+        );
+        assert_eq!(
+            state.eth_balance.total_unspent_tx_fees(),
+            unspent_tx_fee.checked_sub(excess_tx_fee).unwrap()
+        );
+        assert_eq!(
+            state.eth_balance.total_effective_tx_fees(),
+            balance_before
+                .total_effective_tx_fees()
+                .checked_add(receipt.effective_transaction_fee())
+                .unwrap()
+        );
+
+        let unspent_tx_fee = state.eth_balance.total_unspent_tx_fees();
+        expect_panic_with_message(
+            || {
+                WithdrawalFlow {
+                    nonce: TransactionNonce::new(2),
+                    l1_fee: Some(unspent_tx_fee.checked_add(Wei::new(1_000_000_000)).unwrap()),
+                    ..WithdrawalFlow::for_request(withdrawal_request(2))
+                }
+                .apply(&mut state)
+            },
+            "BUG: underflow when subtracting",
+        );
+    }
+
     #[test]
     fn should_update_after_successful_and_failed_erc20_withdrawal() {
         let mut state_before_withdrawal = initial_erc20_state();
@@ -1555,6 +1638,7 @@ mod eth_balance {
         gas_limit: GasAmount,
         effective_gas_price: WeiPerGas,
         effective_gas_used: GasAmount,
+        l1_fee: Option<Wei>,
         tx_status: TransactionStatus,
     }
 
@@ -1570,6 +1654,7 @@ mod eth_balance {
                 gas_limit: GasAmount::from(21_000_u32),
                 effective_gas_price: WeiPerGas::ONE,
                 effective_gas_used: GasAmount::from(21_000_u32),
+                l1_fee: None,
                 tx_status: TransactionStatus::Success,
             }
         }
@@ -1591,6 +1676,7 @@ mod eth_balance {
                 self.tx_fee,
                 self.gas_limit,
                 EthereumNetwork::Sepolia,
+                Wei::ZERO,
             )
             .expect("BUG: failed to create transaction");
             apply_state_transition(
@@ -1625,6 +1711,7 @@ mod eth_balance {
                 gas_used: self.effective_gas_used,
                 status: self.tx_status,
                 transaction_hash: signed_tx.hash(),
+                l1_fee: self.l1_fee,
             };
             apply_state_transition(
                 state,
@@ -486,6 +486,7 @@ impl EthTransactions {
         &mut self,
         withdrawal_id: LedgerBurnIndex,
         transaction: Eip1559TransactionRequest,
+        l1_data_fee_allowance: Wei,
     ) {
         let withdrawal_request = self
             .pending_withdrawal_requests
@@ -525,14 +526,21 @@ impl EthTransactions {
             .checked_increment()
             .expect("Transaction nonce overflow");
         self.remove_withdrawal_request(&withdrawal_request);
+        // The amount reserved for the L1 data fee cannot be used to pay for gas when resubmitting.
         let transaction_request = TransactionRequest {
             transaction,
             resubmission: match &withdrawal_request {
                 WithdrawalRequest::CkEth(cketh) => ResubmissionStrategy::ReduceEthAmount {
-                    withdrawal_amount: cketh.withdrawal_amount,
+                    withdrawal_amount: cketh
+                        .withdrawal_amount
+                        .checked_sub(l1_data_fee_allowance)
+                        .expect("BUG: withdrawal amount should cover the L1 data fee allowance"),
                 },
                 WithdrawalRequest::CkErc20(ckerc20) => ResubmissionStrategy::GuaranteeEthAmount {
-                    allowed_max_transaction_fee: ckerc20.max_transaction_fee,
+                    allowed_max_transaction_fee: ckerc20
+                        .max_transaction_fee
+                        .checked_sub(l1_data_fee_allowance)
+                        .expect("BUG: transaction fee should cover the L1 data fee allowance"),
                 },
             },
         };
@@ -1104,15 +1112,21 @@ pub fn create_transaction(
     gas_fee_estimate: GasFeeEstimate,
     gas_limit: GasAmount,
     ethereum_network: EthereumNetwork,
+    l1_data_fee_allowance: Wei,
 ) -> Result<Eip1559TransactionRequest, CreateTransactionError> {
     assert!(
         gas_limit > GasAmount::ZERO,
         "BUG: gas limit should be non-zero"
     );
+    // Part of the withdrawal is kept by the minter to pay for the L1 data fee, if any,
+    // which is charged on top of the gas fees.
     match withdrawal_request {
         WithdrawalRequest::CkEth(request) => {
             let transaction_price = gas_fee_estimate.to_price(gas_limit);
-            let max_transaction_fee = transaction_price.max_transaction_fee();
+            let max_transaction_fee = transaction_price
+                .max_transaction_fee()
+                .checked_add(l1_data_fee_allowance)
+                .unwrap_or(Wei::MAX);
             let tx_amount = match request.withdrawal_amount.checked_sub(max_transaction_fee) {
                 Some(tx_amount) => tx_amount,
                 None => {
@@ -1143,19 +1157,24 @@ pub fn create_transaction(
             // the transaction could still make it as long as `transaction.max_fee_per_gas >=  block.base_fee_per_gas`,
             // since the `priority_fee_per_gas` received by the miner is capped to (see https://eips.ethereum.org/EIPS/eip-1559)
             // min(transaction.max_priority_fee_per_gas, transaction.max_fee_per_gas - block.base_fee_per_gas).
-            let request_max_fee_per_gas = request
-                .max_transaction_fee
-                .into_wei_per_gas(gas_limit)
-                .expect("BUG: gas_limit should be non-zero");
             let actual_min_max_fee_per_gas = gas_fee_estimate.min_max_fee_per_gas();
-            if actual_min_max_fee_per_gas > request_max_fee_per_gas {
-                return Err(CreateTransactionError::InsufficientTransactionFee {
+            let insufficient_transaction_fee =
+                || CreateTransactionError::InsufficientTransactionFee {
                     cketh_ledger_burn_index: request.cketh_ledger_burn_index,
                     allowed_max_transaction_fee: request.max_transaction_fee,
                     actual_max_transaction_fee: actual_min_max_fee_per_gas
                         .transaction_cost(gas_limit)
+                        .and_then(|gas_fee| gas_fee.checked_add(l1_data_fee_allowance))
                         .unwrap_or(Wei::MAX),
-                });
+                };
+            let request_max_fee_per_gas = request
+                .max_transaction_fee
+                .checked_sub(l1_data_fee_allowance)
+                .ok_or_else(insufficient_transaction_fee)?
+                .into_wei_per_gas(gas_limit)
+                .expect("BUG: gas_limit should be non-zero");
+            if actual_min_max_fee_per_gas > request_max_fee_per_gas {
+                return Err(insufficient_transaction_fee());
             }
             Ok(Eip1559TransactionRequest {
                 chain_id: ethereum_network.chain_id(),
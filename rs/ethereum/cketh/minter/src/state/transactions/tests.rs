@@ -324,14 +324,15 @@ mod eth_transactions {
                 &withdrawal_request.clone(),
                 TransactionNonce::ZERO,
                 gas_fee_estimate(),
-                estimate_gas_limit(&withdrawal_request),
+                estimate_gas_limit(EthereumNetwork::Sepolia, &withdrawal_request),
                 EthereumNetwork::Sepolia,
+                Wei::ZERO,
             )
             .unwrap();
 
             let burn_index = withdrawal_request.cketh_ledger_burn_index();
             expect_panic_with_message(
-                || transactions.record_created_transaction(burn_index, tx),
+                || transactions.record_created_transaction(burn_index, tx, Wei::ZERO),
                 &format!("withdrawal request {} not found", burn_index),
             );
         }
@@ -345,8 +346,9 @@ mod eth_transactions {
                 &withdrawal_request.clone().into(),
                 TransactionNonce::ZERO,
                 gas_fee_estimate(),
-                estimate_gas_limit(&withdrawal_request.clone().into()),
+                estimate_gas_limit(EthereumNetwork::Sepolia, &withdrawal_request.clone().into()),
                 EthereumNetwork::Sepolia,
+                Wei::ZERO,
             )
             .unwrap();
 
@@ -360,6 +362,7 @@ mod eth_transactions {
                     transactions.record_created_transaction(
                         withdrawal_request.ledger_burn_index,
                         tx_with_wrong_destination,
+                        Wei::ZERO,
                     )
                 },
                 "destination mismatch",
@@ -378,6 +381,7 @@ mod eth_transactions {
                     transactions.record_created_transaction(
                         withdrawal_request.ledger_burn_index,
                         tx_with_wrong_amount,
+                        Wei::ZERO,
                     )
                 },
                 "amount deducted from transaction fees",
@@ -396,8 +400,9 @@ mod eth_transactions {
                 &withdrawal_request.clone().into(),
                 TransactionNonce::ZERO,
                 gas_fee_estimate(),
-                estimate_gas_limit(&withdrawal_request.clone().into()),
+                estimate_gas_limit(EthereumNetwork::Sepolia, &withdrawal_request.clone().into()),
                 EthereumNetwork::Sepolia,
+                Wei::ZERO,
             )
             .unwrap();
             let tx_mixing_payee_address_with_erc20_address = Eip1559TransactionRequest {
@@ -410,6 +415,7 @@ mod eth_transactions {
                     transactions.record_created_transaction(
                         withdrawal_request.cketh_ledger_burn_index,
                         tx_mixing_payee_address_with_erc20_address,
+                        Wei::ZERO,
                     )
                 },
                 "destination mismatch",
@@ -425,6 +431,7 @@ mod eth_transactions {
                     transactions.record_created_transaction(
                         withdrawal_request.cketh_ledger_burn_index,
                         tx_with_wrong_amount,
+                        Wei::ZERO,
                     )
                 },
                 "amount should be zero",
@@ -446,11 +453,12 @@ mod eth_transactions {
                     gas_fee_estimate(),
                     CKETH_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
                     EthereumNetwork::Sepolia,
+                    Wei::ZERO,
                 )
                 .unwrap();
 
                 expect_panic_with_message(
-                    || transactions.record_created_transaction(withdrawal_request.cketh_ledger_burn_index(), tx_with_wrong_nonce),
+                    || transactions.record_created_transaction(withdrawal_request.cketh_ledger_burn_index(), tx_with_wrong_nonce, Wei::ZERO),
                     "nonce mismatch",
                 );
             }
@@ -2293,6 +2301,7 @@ mod create_transaction {
                 gas_fee.clone(),
                 gas_limit,
                 EthereumNetwork::Sepolia,
+                Wei::ZERO,
             );
             prop_assert_eq!(
                 result,
@@ -2315,6 +2324,7 @@ mod create_transaction {
                 gas_fee,
                 gas_limit,
                 EthereumNetwork::Sepolia,
+                Wei::ZERO,
             );
             prop_assert_eq!(
                 result,
@@ -2351,6 +2361,7 @@ mod create_transaction {
                 gas_fee,
                 gas_limit,
                 EthereumNetwork::Sepolia,
+                Wei::ZERO,
             );
 
             prop_assert_eq!(result, Ok(Eip1559TransactionRequest {
@@ -2405,6 +2416,7 @@ mod create_transaction {
                 gas_fee.clone(),
                 gas_limit,
                 EthereumNetwork::Mainnet,
+                Wei::ZERO,
             ).unwrap();
             let tx_max_fee_per_gas = result.max_fee_per_gas;
             let max_tx_fee = tx_max_fee_per_gas.transaction_cost(gas_limit).unwrap();
@@ -2425,6 +2437,55 @@ mod create_transaction {
         }
     }
 
+    #[test]
+    fn should_keep_l1_data_fee_allowance_on_op_stack_networks() {
+        let gas_fee = gas_fee_estimate();
+        let gas_limit = CKETH_WITHDRAWAL_TRANSACTION_GAS_LIMIT;
+        let max_gas_fee = gas_fee.clone().to_price(gas_limit).max_transaction_fee();
+        let cketh_ledger_burn_index = LedgerBurnIndex::new(15);
+        let l1_data_fee_allowance = Wei::new(10_000_000_000_000);
+
+        for network in [EthereumNetwork::Base, EthereumNetwork::Optimism] {
+            let max_transaction_fee = max_gas_fee.checked_add(l1_data_fee_allowance).unwrap();
+
+            let withdrawal_request = EthWithdrawalRequest {
+                withdrawal_amount: max_transaction_fee.checked_add(Wei::ONE).unwrap(),
+                ..cketh_withdrawal_request_with_index(cketh_ledger_burn_index)
+            };
+            let tx = create_transaction(
+                &withdrawal_request.into(),
+                TransactionNonce::ZERO,
+                gas_fee.clone(),
+                gas_limit,
+                network,
+                l1_data_fee_allowance,
+            )
+            .unwrap();
+            assert_eq!(tx.chain_id, network.chain_id());
+            assert_eq!(tx.amount, Wei::ONE);
+
+            let withdrawal_request = EthWithdrawalRequest {
+                withdrawal_amount: max_gas_fee,
+                ..cketh_withdrawal_request_with_index(cketh_ledger_burn_index)
+            };
+            assert_eq!(
+                create_transaction(
+                    &withdrawal_request.into(),
+                    TransactionNonce::ZERO,
+                    gas_fee.clone(),
+                    gas_limit,
+                    network,
+                    l1_data_fee_allowance,
+                ),
+                Err(CreateTransactionError::InsufficientTransactionFee {
+                    cketh_ledger_burn_index,
+                    allowed_max_transaction_fee: max_gas_fee,
+                    actual_max_transaction_fee: max_transaction_fee,
+                })
+            );
+        }
+    }
+
     proptest! {
          #[test]
          fn should_encode_decode_transaction_call_data(to in arb_address(), value in arb_checked_amount_of()) {
@@ -2444,7 +2505,7 @@ mod create_transaction {
 
 mod withdrawal_flow {
     use super::arbitrary::{arb_checked_amount_of, arb_gas_fee_estimate, arb_withdrawal_request};
-    use crate::numeric::TransactionNonce;
+    use crate::numeric::{TransactionNonce, Wei};
     use crate::state::transactions::tests::sign_transaction;
     use crate::state::transactions::{create_transaction, EthTransactions, EthereumNetwork};
     use crate::withdraw::estimate_gas_limit;
@@ -2474,10 +2535,11 @@ mod withdrawal_flow {
                     &request,
                     nonce,
                     gas_fee_estimate.clone(),
-                    estimate_gas_limit(&request),
+                    estimate_gas_limit(EthereumNetwork::Sepolia, &request),
                     EthereumNetwork::Sepolia,
+                    Wei::ZERO,
                 ){
-                    wrapped_txs.borrow_mut().record_created_transaction(request.cketh_ledger_burn_index(), created_tx);
+                    wrapped_txs.borrow_mut().record_created_transaction(request.cketh_ledger_burn_index(), created_tx, Wei::ZERO);
                 }
             }
 
@@ -2854,11 +2916,16 @@ fn create_and_record_transaction<R: Into<WithdrawalRequest>>(
         &withdrawal_request,
         transactions.next_transaction_nonce(),
         gas_fee_estimate,
-        estimate_gas_limit(&withdrawal_request),
+        estimate_gas_limit(EthereumNetwork::Sepolia, &withdrawal_request),
         EthereumNetwork::Sepolia,
+        Wei::ZERO,
     )
     .expect("failed to create transaction");
-    transactions.record_created_transaction(withdrawal_request.cketh_ledger_burn_index(), tx);
+    transactions.record_created_transaction(
+        withdrawal_request.cketh_ledger_burn_index(),
+        tx,
+        Wei::ZERO,
+    );
     transactions
         .created_tx
         .get_alt(&burn_index)
@@ -2908,6 +2975,7 @@ fn transaction_receipt(
         gas_used: signed_tx.transaction().gas_limit,
         status,
         transaction_hash: signed_tx.hash(),
+        l1_fee: None,
     }
 }
 
@@ -43,6 +43,8 @@ pub fn valid_init_arg() -> InitArg {
         minimum_withdrawal_amount: Nat::from(10_000_000_000_000_000_u64),
         next_transaction_nonce: Default::default(),
         last_scraped_block_number: Default::default(),
+        evm_rpc_id: None,
+        l1_data_fee_allowance: None,
     }
 }
 
@@ -234,6 +236,7 @@ pub mod arb {
                         gas_used,
                         status,
                         transaction_hash,
+                        l1_fee: None,
                     }
                 },
             )
@@ -5,9 +5,10 @@ use crate::eth_rpc::{BlockSpec, BlockTag, FeeHistory, FeeHistoryParams, Hash, Qu
 use crate::eth_rpc_client::responses::{TransactionReceipt, TransactionStatus};
 use crate::eth_rpc_client::{EthRpcClient, MultiCallError};
 use crate::guard::TimerGuard;
+use crate::lifecycle::EthereumNetwork;
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::{BlockNumber, GasAmount, TransactionNonce, Wei, WeiPerGas};
-use crate::state::{lazy_call_ecdsa_public_key, mutate_state, read_state, TaskType};
+use crate::state::{lazy_call_ecdsa_public_key, mutate_state, read_state, State, TaskType};
 use ethnum::u256;
 use ic_canister_log::log;
 use ic_ethereum_types::Address;
@@ -622,22 +623,23 @@ pub async fn lazy_refresh_gas_fee_estimate() -> Option<GasFeeEstimate> {
             }
         };
 
-        let gas_fee_estimate = match estimate_transaction_fee(&fee_history) {
-            Ok(estimate) => {
-                mutate_state(|s| {
-                    s.last_transaction_price_estimate =
-                        Some((ic_cdk::api::time(), estimate.clone()));
-                });
-                estimate
-            }
-            Err(e) => {
-                log!(
-                    INFO,
-                    "[refresh_gas_fee_estimate]: Failed estimating gas fee: {e:?}",
-                );
-                return None;
-            }
-        };
+        let gas_fee_estimate =
+            match estimate_transaction_fee(&fee_history, read_state(State::ethereum_network)) {
+                Ok(estimate) => {
+                    mutate_state(|s| {
+                        s.last_transaction_price_estimate =
+                            Some((ic_cdk::api::time(), estimate.clone()));
+                    });
+                    estimate
+                }
+                Err(e) => {
+                    log!(
+                        INFO,
+                        "[refresh_gas_fee_estimate]: Failed estimating gas fee: {e:?}",
+                    );
+                    return None;
+                }
+            };
         log!(
             INFO,
             "[refresh_gas_fee_estimate]: Estimated transaction fee: {:?}",
@@ -679,11 +681,8 @@ pub enum TransactionFeeEstimationError {
 /// the estimate remains valid for the next few blocks, see `<https://www.blocknative.com/blog/eip-1559-fees>`.
 pub fn estimate_transaction_fee(
     fee_history: &FeeHistory,
+    ethereum_network: EthereumNetwork,
 ) -> Result<GasFeeEstimate, TransactionFeeEstimationError> {
-    // average value between the `minSuggestedMaxPriorityFeePerGas`
-    // used by Metamask, see
-    // https://github.com/MetaMask/core/blob/f5a4f52e17f407c6411e4ef9bd6685aab184b91d/packages/gas-fee-controller/src/fetchGasEstimatesViaEthFeeHistory/calculateGasFeeEstimatesForPriorityLevels.ts#L14
-    const MIN_MAX_PRIORITY_FEE_PER_GAS: WeiPerGas = WeiPerGas::new(1_500_000_000); //1.5 gwei
     let base_fee_per_gas_next_block = *fee_history.base_fee_per_gas.last().ok_or(
         TransactionFeeEstimationError::InvalidFeeHistory(
             "base_fee_per_gas should not be empty to be able to evaluate transaction price"
@@ -696,7 +695,7 @@ pub fn estimate_transaction_fee(
             **median(&mut rewards).ok_or(TransactionFeeEstimationError::InvalidFeeHistory(
                 "should be non-empty with rewards of the last 5 blocks".to_string(),
             ))?;
-        historic_max_priority_fee_per_gas.max(MIN_MAX_PRIORITY_FEE_PER_GAS)
+        historic_max_priority_fee_per_gas.max(ethereum_network.min_max_priority_fee_per_gas())
     };
     let gas_fee_estimate = GasFeeEstimate {
         base_fee_per_gas: base_fee_per_gas_next_block,
@@ -3,6 +3,7 @@ use proptest::strategy::Strategy;
 
 mod estimate_transaction_price {
     use crate::eth_rpc::FeeHistory;
+    use crate::lifecycle::EthereumNetwork;
     use crate::numeric::{BlockNumber, WeiPerGas};
     use crate::tx::{estimate_transaction_fee, GasFeeEstimate, TransactionFeeEstimationError};
     use assert_matches::assert_matches;
@@ -26,7 +27,7 @@ mod estimate_transaction_price {
             };
             let fee_history = fee_history(base_fee_per_gas, reward);
 
-            let result = estimate_transaction_fee(&fee_history);
+            let result = estimate_transaction_fee(&fee_history, EthereumNetwork::Mainnet);
 
             prop_assert_eq!(
                 result,
@@ -38,6 +39,27 @@ mod estimate_transaction_price {
         }
     }
 
+    #[test]
+    fn should_use_network_specific_min_max_priority_fee_per_gas() {
+        let fee_history = fee_history(vec![10_000_000_u64; 6], vec![0_u8; 5]);
+
+        for (network, expected_max_priority_fee_per_gas) in [
+            (EthereumNetwork::Mainnet, 1_500_000_000_u64),
+            (EthereumNetwork::Sepolia, 1_500_000_000_u64),
+            (EthereumNetwork::ArbitrumOne, 0),
+            (EthereumNetwork::Base, 1_000_000),
+            (EthereumNetwork::Optimism, 1_000_000),
+        ] {
+            assert_eq!(
+                estimate_transaction_fee(&fee_history, network),
+                Ok(GasFeeEstimate {
+                    base_fee_per_gas: WeiPerGas::from(10_000_000_u64),
+                    max_priority_fee_per_gas: WeiPerGas::from(expected_max_priority_fee_per_gas),
+                })
+            );
+        }
+    }
+
     #[test]
     fn should_fail_when_base_fee_per_gas_overflows() {
         let fee_history = fee_history(
@@ -52,7 +74,7 @@ mod estimate_transaction_price {
             vec![0_u8, 0, 0, 0, 0],
         );
 
-        let result = estimate_transaction_fee(&fee_history);
+        let result = estimate_transaction_fee(&fee_history, EthereumNetwork::Mainnet);
 
         assert_matches!(result, Err(TransactionFeeEstimationError::Overflow(_)));
     }
@@ -60,7 +82,7 @@ mod estimate_transaction_price {
     #[test]
     fn should_fail_when_max_priority_fee_per_gas_overflows() {
         let fee_history = fee_history(vec![0_u8, 0, 0, 0, 0, 1], [WeiPerGas::MAX; 5].to_vec());
-        let result = estimate_transaction_fee(&fee_history);
+        let result = estimate_transaction_fee(&fee_history, EthereumNetwork::Mainnet);
         assert_matches!(result, Err(TransactionFeeEstimationError::Overflow(_)));
     }
 
@@ -4,6 +4,7 @@ use crate::eth_rpc_client::responses::TransactionReceipt;
 use crate::eth_rpc_client::EthRpcClient;
 use crate::eth_rpc_client::MultiCallError;
 use crate::guard::TimerGuard;
+use crate::lifecycle::EthereumNetwork;
 use crate::logs::{DEBUG, INFO};
 use crate::numeric::{GasAmount, LedgerBurnIndex, LedgerMintIndex, TransactionCount};
 use crate::state::audit::{process_event, EventType};
@@ -237,14 +238,16 @@ fn create_transactions_batch(gas_fee_estimate: GasFeeEstimate) {
     }) {
         log!(DEBUG, "[create_transactions_batch]: processing {request:?}",);
         let ethereum_network = read_state(State::ethereum_network);
+        let l1_data_fee_allowance = read_state(State::l1_data_fee_allowance);
         let nonce = read_state(|s| s.eth_transactions.next_transaction_nonce());
-        let gas_limit = estimate_gas_limit(&request);
+        let gas_limit = estimate_gas_limit(ethereum_network, &request);
         match create_transaction(
             &request,
             nonce,
             gas_fee_estimate.clone(),
             gas_limit,
             ethereum_network,
+            l1_data_fee_allowance,
         ) {
             Ok(transaction) => {
                 log!(
@@ -277,11 +280,26 @@ fn create_transactions_batch(gas_fee_estimate: GasFeeEstimate) {
     }
 }
 
-pub fn estimate_gas_limit(withdrawal_request: &WithdrawalRequest) -> GasAmount {
-    match withdrawal_request {
+pub fn estimate_gas_limit(
+    ethereum_network: EthereumNetwork,
+    withdrawal_request: &WithdrawalRequest,
+) -> GasAmount {
+    let execution_gas_limit = match withdrawal_request {
         WithdrawalRequest::CkEth(_) => CKETH_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
         WithdrawalRequest::CkErc20(_) => CKERC20_WITHDRAWAL_TRANSACTION_GAS_LIMIT,
-    }
+    };
+    withdrawal_gas_limit(ethereum_network, execution_gas_limit)
+}
+
+/// Adds to the gas needed to execute a withdrawal transaction
+/// the gas that the network charges to post it on Ethereum, if any.
+pub fn withdrawal_gas_limit(
+    ethereum_network: EthereumNetwork,
+    execution_gas_limit: GasAmount,
+) -> GasAmount {
+    execution_gas_limit
+        .checked_add(ethereum_network.l1_gas_allowance())
+        .expect("BUG: gas limit overflow")
 }
 
 async fn sign_transactions_batch() {
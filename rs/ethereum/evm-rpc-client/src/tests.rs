@@ -106,6 +106,34 @@ async fn should_not_retry_when_max_num_retries_reached() {
     assert_eq!(result, expected_result);
 }
 
+#[tokio::test]
+async fn should_send_raw_request_to_single_provider() {
+    use crate::{L2MainnetService, RpcService};
+
+    let mut runtime = MockRuntime::new();
+    let min_attached_cycles = 3_000_000_000_u128;
+    let service = RpcService::BaseMainnet(L2MainnetService::PublicNode);
+    let payload = r#"{"jsonrpc":"2.0","method":"eth_chainId","params":[],"id":1}"#.to_string();
+    let expected_result = Ok(r#"{"jsonrpc":"2.0","result":"0x2105","id":1}"#.to_string());
+    let expected_args = (service.clone(), payload.clone(), 1024_u64);
+    runtime
+        .expect_call::<_, Result<String, RpcError>>()
+        .times(1)
+        .withf(
+            move |_, method, args: &(RpcService, String, u64), attached_cycles| {
+                method == "request"
+                    && args == &expected_args
+                    && attached_cycles == &min_attached_cycles
+            },
+        )
+        .return_const(Ok(expected_result.clone()));
+
+    let client = test_client(runtime, min_attached_cycles, 3);
+    let result = client.request(service, payload, 1024).await;
+
+    assert_eq!(result, expected_result);
+}
+
 mod max_expected_too_few_cycles_error {
     use super::*;
     use crate::max_expected_too_few_cycles_error;
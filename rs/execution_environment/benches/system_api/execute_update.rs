@@ -688,6 +688,26 @@ pub fn execute_update_bench(c: &mut Criterion) {
             ),
             518000006,
         ),
+        common::Benchmark(
+            "wasm32/ic0_set_query_cache_max_age()".into(),
+            Module::Test.from_ic0(
+                "set_query_cache_max_age",
+                Param1(0_i64),
+                Result::No,
+                Wasm64::Disabled,
+            ),
+            517000006,
+        ),
+        common::Benchmark(
+            "wasm64/ic0_set_query_cache_max_age()".into(),
+            Module::Test.from_ic0(
+                "set_query_cache_max_age",
+                Param1(0_i64),
+                Result::No,
+                Wasm64::Enabled,
+            ),
+            517000006,
+        ),
         common::Benchmark(
             "wasm32/ic0_performance_counter()".into(),
             Module::Test.from_ic0(
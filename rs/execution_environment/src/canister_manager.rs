@@ -19,13 +19,15 @@ use ic_error_types::{ErrorCode, RejectCode, UserError};
 use ic_interfaces::execution_environment::{IngressHistoryWriter, SubnetAvailableMemory};
 use ic_logger::{error, fatal, info, ReplicaLogger};
 use ic_management_canister_types_private::{
-    CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallModeV2, CanisterSnapshotResponse,
-    CanisterStatusResultV2, CanisterStatusType, ChunkHash, Method as Ic00Method, StoredChunksReply,
-    UploadChunkReply,
+    CanisterChangeDetails, CanisterChangeOrigin, CanisterInstallModeV2, CanisterSnapshotDataKind,
+    CanisterSnapshotDataOffset, CanisterSnapshotResponse, CanisterStatusResultV2,
+    CanisterStatusType, ChunkHash, EnvironmentVariable, Method as Ic00Method,
+    ReadCanisterSnapshotDataResponse, StoredChunksReply, UploadCanisterSnapshotDataArgs,
+    UploadCanisterSnapshotMetadataArgs, UploadCanisterSnapshotMetadataResponse, UploadChunkReply,
 };
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
 use ic_replicated_state::{
-    canister_snapshots::CanisterSnapshot,
+    canister_snapshots::{CanisterSnapshot, ExecutionStateSnapshot, PageMemory, SnapshotSource},
     canister_state::{
         execution_state::Memory,
         execution_state::WasmExecutionMode,
@@ -33,14 +35,16 @@ use ic_replicated_state::{
             wasm_chunk_store::{self, WasmChunkStore},
             CyclesUseCase, ReservationError,
         },
-        NextExecution,
+        NextExecution, WASM_PAGE_SIZE_IN_BYTES,
     },
     metadata_state::subnet_call_context_manager::InstallCodeCallId,
-    page_map::PageAllocatorFileDescriptor,
-    CallOrigin, CanisterState, MessageMemoryUsage, NetworkTopology, ReplicatedState,
-    SchedulerState, SystemState,
+    num_bytes_try_from,
+    page_map::{Buffer, PageAllocatorFileDescriptor},
+    CallOrigin, CanisterState, Global, MessageMemoryUsage, NetworkTopology, NumWasmPages, PageMap,
+    ReplicatedState, SchedulerState, SystemState,
 };
-use ic_system_api::ExecutionParameters;
+use ic_sys::PAGE_SIZE;
+use ic_system_api::{ExecutionParameters, CERTIFIED_DATA_MAX_LENGTH};
 use ic_types::{
     ingress::{IngressState, IngressStatus},
     messages::{
@@ -52,15 +56,19 @@ use ic_types::{
     NumInstructions, PrincipalId, SnapshotId, SubnetId, Time,
 };
 use ic_wasm_transform::Module;
-use ic_wasm_types::WasmHash;
+use ic_wasm_types::{CanisterModule, WasmHash};
 use num_traits::{SaturatingAdd, SaturatingSub};
 use prometheus::IntCounter;
 use std::path::PathBuf;
-use std::{convert::TryFrom, str::FromStr, sync::Arc};
+use std::{convert::TryFrom, ops::Range, str::FromStr, sync::Arc};
 
 use types::*;
 pub(crate) mod types;
 
+/// The maximum size of a slice of canister snapshot data that can be
+/// read or uploaded in a single call.
+const MAX_SNAPSHOT_DATA_SLICE_SIZE: u64 = 2_000_000;
+
 /// The entity responsible for managing canisters (creation, installing, etc.)
 pub(crate) struct CanisterManager {
     hypervisor: Arc<Hypervisor>,
@@ -157,7 +165,10 @@ impl CanisterManager {
             | Ok(Ic00Method::LoadCanisterSnapshot)
             | Ok(Ic00Method::ListCanisterSnapshots)
             | Ok(Ic00Method::DeleteCanisterSnapshot)
-            | Ok(Ic00Method::ReadCanisterSnapshotMetadata) => {
+            | Ok(Ic00Method::ReadCanisterSnapshotMetadata)
+            | Ok(Ic00Method::ReadCanisterSnapshotData)
+            | Ok(Ic00Method::UploadCanisterSnapshotMetadata)
+            | Ok(Ic00Method::UploadCanisterSnapshotData) => {
                 match effective_canister_id {
                     Some(canister_id) => {
                         let canister = state.canister_state(&canister_id).ok_or_else(|| UserError::new(
@@ -214,9 +225,13 @@ impl CanisterManager {
         canister_cycles_balance: Cycles,
         subnet_size: usize,
     ) -> Result<ValidatedCanisterSettings, CanisterManagerError> {
+        // The environment variables are the only memory used by a new canister.
+        let memory_usage = settings
+            .environment_variables()
+            .map_or(NumBytes::new(0), |variables| variables.memory_usage());
         validate_canister_settings(
             settings,
-            NumBytes::new(0),
+            memory_usage,
             MessageMemoryUsage::ZERO,
             MemoryAllocation::BestEffort,
             subnet_available_memory,
@@ -291,6 +306,9 @@ impl CanisterManager {
         if let Some(wasm_memory_limit) = settings.wasm_memory_limit() {
             canister.system_state.wasm_memory_limit = Some(wasm_memory_limit);
         }
+        if let Some(environment_variables) = settings.environment_variables() {
+            canister.system_state.environment_variables = environment_variables.clone();
+        }
     }
 
     /// Tries to apply the requested settings on the canister identified by
@@ -309,9 +327,17 @@ impl CanisterManager {
 
         validate_controller(canister, &sender)?;
 
+        // The new environment variables replace the current ones in the memory usage.
+        let new_usage = match settings.environment_variables() {
+            Some(variables) => {
+                canister.memory_usage() - canister.environment_variables_memory_usage()
+                    + variables.memory_usage()
+            }
+            None => canister.memory_usage(),
+        };
         let validated_settings = validate_canister_settings(
             settings,
-            canister.memory_usage(),
+            new_usage,
             canister.message_memory_usage(),
             canister.memory_allocation(),
             &round_limits.subnet_available_memory,
@@ -347,7 +373,7 @@ impl CanisterManager {
                 .saturating_sub(old_compute_allocation - new_compute_allocation);
         }
 
-        let new_usage = old_usage;
+        debug_assert_eq!(new_usage, canister.memory_usage());
         let new_mem = canister.memory_allocation().allocated_bytes(new_usage);
         if new_mem >= old_mem {
             // Settings were validated before so this should always succeed.
@@ -759,6 +785,12 @@ impl CanisterManager {
         let log_visibility = canister.system_state.log_visibility.clone();
         let wasm_memory_limit = canister.system_state.wasm_memory_limit;
         let wasm_memory_threshold = canister.system_state.wasm_memory_threshold;
+        let environment_variables = canister
+            .system_state
+            .environment_variables
+            .iter()
+            .map(|(name, value)| EnvironmentVariable::new(name, value))
+            .collect();
 
         Ok(CanisterStatusResultV2::new(
             canister.status(),
@@ -805,6 +837,7 @@ impl CanisterManager {
                 .egress_payload_size,
             wasm_memory_limit.map(|x| x.get()),
             wasm_memory_threshold.get(),
+            environment_variables,
         ))
     }
 
@@ -1486,54 +1519,11 @@ impl CanisterManager {
             return (Err(err), NumInstructions::new(0));
         };
 
-        let replace_snapshot_size = match replace_snapshot {
-            // Check that replace snapshot ID exists if provided.
-            Some(replace_snapshot) => {
-                match state.canister_snapshots.get(replace_snapshot) {
-                    None => {
-                        // If not found, the operation fails due to invalid parameters.
-                        return (
-                            Err(CanisterManagerError::CanisterSnapshotNotFound {
-                                canister_id: canister.canister_id(),
-                                snapshot_id: replace_snapshot,
-                            }),
-                            NumInstructions::new(0),
-                        );
-                    }
-                    Some(snapshot) => {
-                        // Verify the provided replacement snapshot belongs to this canister.
-                        if snapshot.canister_id() != canister.canister_id() {
-                            return (
-                                Err(CanisterManagerError::CanisterSnapshotInvalidOwnership {
-                                    canister_id: canister.canister_id(),
-                                    snapshot_id: replace_snapshot,
-                                }),
-                                NumInstructions::new(0),
-                            );
-                        }
-                        snapshot.size()
-                    }
-                }
-            }
-            // No replace snapshot ID provided, check whether the maximum number of snapshots
-            // has been reached.
-            None => {
-                if state
-                    .canister_snapshots
-                    .count_by_canister(&canister.canister_id())
-                    >= self.config.max_number_of_snapshots_per_canister
-                {
-                    return (
-                        Err(CanisterManagerError::CanisterSnapshotLimitExceeded {
-                            canister_id: canister.canister_id(),
-                            limit: self.config.max_number_of_snapshots_per_canister,
-                        }),
-                        NumInstructions::new(0),
-                    );
-                }
-                0.into()
-            }
-        };
+        let replace_snapshot_size =
+            match self.validate_replace_snapshot(canister, replace_snapshot, state) {
+                Ok(size) => size,
+                Err(err) => return (Err(err), NumInstructions::new(0)),
+            };
 
         if self.config.rate_limiting_of_heap_delta == FlagStatus::Enabled
             && canister.scheduler_state.heap_delta_debit >= self.config.heap_delta_rate_limit
@@ -1597,6 +1587,75 @@ impl CanisterManager {
             Err(err) => return (Err(err), instructions),
         };
 
+        let snapshot_id = self.store_snapshot(
+            canister,
+            replace_snapshot,
+            replace_snapshot_size,
+            new_snapshot,
+            state,
+            round_limits,
+        );
+        (
+            Ok(CanisterSnapshotResponse::new(
+                &snapshot_id,
+                state.time().as_nanos_since_unix_epoch(),
+                new_snapshot_size,
+            )),
+            instructions,
+        )
+    }
+
+    /// Checks that the snapshot identified by `replace_snapshot`, if provided,
+    /// exists and belongs to the canister. Otherwise, checks that the canister
+    /// has not reached the maximum number of snapshots.
+    ///
+    /// Returns the size of the snapshot to be replaced.
+    fn validate_replace_snapshot(
+        &self,
+        canister: &CanisterState,
+        replace_snapshot: Option<SnapshotId>,
+        state: &ReplicatedState,
+    ) -> Result<NumBytes, CanisterManagerError> {
+        match replace_snapshot {
+            // Check that replace snapshot ID exists if provided.
+            Some(replace_snapshot) => {
+                let snapshot = get_snapshot(canister.canister_id(), replace_snapshot, state)?;
+                Ok(snapshot.size())
+            }
+            // No replace snapshot ID provided, check whether the maximum number of snapshots
+            // has been reached.
+            None => {
+                if state
+                    .canister_snapshots
+                    .count_by_canister(&canister.canister_id())
+                    >= self.config.max_number_of_snapshots_per_canister
+                {
+                    return Err(CanisterManagerError::CanisterSnapshotLimitExceeded {
+                        canister_id: canister.canister_id(),
+                        limit: self.config.max_number_of_snapshots_per_canister,
+                    });
+                }
+                Ok(0.into())
+            }
+        }
+    }
+
+    /// Inserts the new snapshot into `ReplicatedState`, deleting the snapshot
+    /// identified by `replace_snapshot` ID first, if any.
+    ///
+    /// The caller must have already checked that the subnet has enough memory
+    /// available for the new snapshot.
+    fn store_snapshot(
+        &self,
+        canister: &mut CanisterState,
+        replace_snapshot: Option<SnapshotId>,
+        replace_snapshot_size: NumBytes,
+        new_snapshot: CanisterSnapshot,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+    ) -> SnapshotId {
+        let new_snapshot_size = new_snapshot.size();
+
         // Delete old snapshot identified by `replace_snapshot` ID.
         if let Some(replace_snapshot) = replace_snapshot {
             state.canister_snapshots.remove(replace_snapshot);
@@ -1646,16 +1705,446 @@ impl CanisterManager {
             .system_state
             .snapshots_memory_usage
             .saturating_add(&new_snapshot_size);
+        snapshot_id
+    }
+
+    /// Returns an error if the canister is heap delta rate limited.
+    fn validate_heap_delta_rate_limit(
+        &self,
+        canister: &CanisterState,
+    ) -> Result<(), CanisterManagerError> {
+        if self.config.rate_limiting_of_heap_delta == FlagStatus::Enabled
+            && canister.scheduler_state.heap_delta_debit >= self.config.heap_delta_rate_limit
+        {
+            return Err(CanisterManagerError::CanisterHeapDeltaRateLimited {
+                canister_id: canister.canister_id(),
+                value: canister.scheduler_state.heap_delta_debit,
+                limit: self.config.heap_delta_rate_limit,
+            });
+        }
+        Ok(())
+    }
+
+    /// Charges the canister for reading or writing `num_bytes` of snapshot data.
+    fn charge_for_snapshot_data(
+        &self,
+        subnet_size: usize,
+        sender: PrincipalId,
+        canister: &mut CanisterState,
+        num_bytes: NumBytes,
+    ) -> Result<NumInstructions, CanisterManagerError> {
+        let instructions = self
+            .config
+            .canister_snapshot_baseline_instructions
+            .saturating_add(&num_bytes.get().into());
+        self.cycles_account_manager
+            .consume_cycles_for_instructions(
+                &sender,
+                canister,
+                instructions,
+                subnet_size,
+                // The number of instructions charged only depends on the constant set fee
+                // and the amount of data, so it does not matter if this is a Wasm64 or Wasm32 module.
+                WasmExecutionMode::Wasm32,
+            )
+            .map_err(CanisterManagerError::CanisterSnapshotNotEnoughCycles)?;
+        Ok(instructions)
+    }
+
+    /// Returns a slice of the data of a canister snapshot, so that the snapshot
+    /// can be downloaded in chunks, e.g. to be stored off-chain.
+    ///
+    /// Reading the snapshot data can only be initiated by the controllers.
+    pub(crate) fn read_snapshot_data(
+        &self,
+        subnet_size: usize,
+        sender: PrincipalId,
+        canister: &mut CanisterState,
+        snapshot_id: SnapshotId,
+        kind: CanisterSnapshotDataKind,
+        state: &ReplicatedState,
+    ) -> (
+        Result<ReadCanisterSnapshotDataResponse, CanisterManagerError>,
+        NumInstructions,
+    ) {
+        let canister_id = canister.canister_id();
+        // Check sender is a controller.
+        if let Err(err) = validate_controller(canister, &sender) {
+            return (Err(err), NumInstructions::new(0));
+        }
+
+        let snapshot = match get_snapshot(canister_id, snapshot_id, state) {
+            Ok(snapshot) => snapshot,
+            Err(err) => return (Err(err), NumInstructions::new(0)),
+        };
+        let invalid_data = |message: String| CanisterManagerError::CanisterSnapshotInvalidData {
+            canister_id,
+            message,
+        };
+
+        let chunk = match kind {
+            CanisterSnapshotDataKind::WasmModule { offset, size } => {
+                match validate_slice(offset, size, snapshot.wasm_module_size()) {
+                    Ok(range) => read_wasm_module(snapshot.canister_module(), range),
+                    Err(message) => return (Err(invalid_data(message)), NumInstructions::new(0)),
+                }
+            }
+            CanisterSnapshotDataKind::MainMemory { offset, size } => {
+                match read_page_memory(snapshot.wasm_memory(), offset, size) {
+                    Ok(chunk) => chunk,
+                    Err(message) => return (Err(invalid_data(message)), NumInstructions::new(0)),
+                }
+            }
+            CanisterSnapshotDataKind::StableMemory { offset, size } => {
+                match read_page_memory(snapshot.stable_memory(), offset, size) {
+                    Ok(chunk) => chunk,
+                    Err(message) => return (Err(invalid_data(message)), NumInstructions::new(0)),
+                }
+            }
+            CanisterSnapshotDataKind::WasmChunk { hash } => {
+                let chunk = <[u8; 32]>::try_from(hash.as_slice())
+                    .ok()
+                    .and_then(|hash| snapshot.chunk_store().get_chunk_data(&hash))
+                    .map(|pages| pages.flatten().copied().collect::<Vec<u8>>());
+                match chunk {
+                    Some(chunk) => chunk,
+                    None => {
+                        return (
+                            Err(invalid_data(format!(
+                                "Wasm chunk with hash {} not found",
+                                hex::encode(&hash)
+                            ))),
+                            NumInstructions::new(0),
+                        )
+                    }
+                }
+            }
+        };
+
+        match self.charge_for_snapshot_data(
+            subnet_size,
+            sender,
+            canister,
+            NumBytes::from(chunk.len() as u64),
+        ) {
+            Ok(instructions) => (
+                Ok(ReadCanisterSnapshotDataResponse::new(chunk)),
+                instructions,
+            ),
+            Err(err) => (Err(err), NumInstructions::new(0)),
+        }
+    }
+
+    /// Creates a new canister snapshot from the metadata uploaded by the user.
+    ///
+    /// The memories of the new snapshot are zero-filled, and the Wasm module and
+    /// the chunk store are empty. The actual data is expected to be uploaded with
+    /// subsequent calls to `write_snapshot_data`.
+    ///
+    /// Uploading a canister snapshot can only be initiated by the controllers.
+    /// The `replace_snapshot` parameter behaves as in `take_canister_snapshot`.
+    pub(crate) fn create_snapshot_from_metadata(
+        &self,
+        subnet_size: usize,
+        sender: PrincipalId,
+        canister: &mut CanisterState,
+        args: UploadCanisterSnapshotMetadataArgs,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+        resource_saturation: &ResourceSaturation,
+    ) -> (
+        Result<UploadCanisterSnapshotMetadataResponse, CanisterManagerError>,
+        NumInstructions,
+    ) {
+        // Check sender is a controller.
+        if let Err(err) = validate_controller(canister, &sender) {
+            return (Err(err), NumInstructions::new(0));
+        }
+
+        let replace_snapshot = args.replace_snapshot();
+        let replace_snapshot_size =
+            match self.validate_replace_snapshot(canister, replace_snapshot, state) {
+                Ok(size) => size,
+                Err(err) => return (Err(err), NumInstructions::new(0)),
+            };
+
+        if let Err(err) = self.validate_heap_delta_rate_limit(canister) {
+            return (Err(err), NumInstructions::new(0));
+        }
+
+        let (wasm_memory_size, stable_memory_size) = match validate_snapshot_metadata(
+            &args,
+            self.config.wasm_max_size,
+            self.config.max_wasm_memory_size,
+            self.config.max_stable_memory_size,
+        ) {
+            Ok(sizes) => sizes,
+            Err(message) => {
+                return (
+                    Err(CanisterManagerError::CanisterSnapshotInvalidData {
+                        canister_id: canister.canister_id(),
+                        message,
+                    }),
+                    NumInstructions::new(0),
+                )
+            }
+        };
+
+        // We use 8 bytes per global, like for the execution state.
+        let metadata_size = NumBytes::from(
+            (8 * args.exported_globals.len() as u64)
+                .saturating_add(args.certified_data.len() as u64),
+        );
+        let new_snapshot_size = NumBytes::from(
+            args.wasm_module_size
+                .saturating_add(args.wasm_memory_size)
+                .saturating_add(args.stable_memory_size)
+                .saturating_add(metadata_size.get()),
+        );
+
+        let old_memory_usage = canister.memory_usage();
+        let new_memory_usage = canister
+            .memory_usage()
+            .saturating_add(&new_snapshot_size)
+            .saturating_sub(&replace_snapshot_size);
+        if let Err(err) = self.memory_usage_checks(
+            subnet_size,
+            canister,
+            round_limits,
+            new_memory_usage,
+            old_memory_usage,
+            resource_saturation,
+        ) {
+            return (Err(err), NumInstructions::new(0));
+        }
+
+        // Charge for creating the snapshot from the metadata. The remaining data
+        // is charged for when it is uploaded.
+        let instructions =
+            match self.charge_for_snapshot_data(subnet_size, sender, canister, metadata_size) {
+                Ok(instructions) => instructions,
+                Err(err) => return (Err(err), NumInstructions::new(0)),
+            };
+
+        let source = SnapshotSource::MetadataUpload {
+            wasm_module_size: args.wasm_module_size,
+        };
+        let execution_snapshot = ExecutionStateSnapshot {
+            wasm_binary: CanisterModule::new(vec![]),
+            exported_globals: args
+                .exported_globals
+                .into_iter()
+                .map(|global| global.into())
+                .collect(),
+            stable_memory: PageMemory {
+                page_map: PageMap::new(Arc::clone(&self.fd_factory)),
+                size: stable_memory_size,
+            },
+            wasm_memory: PageMemory {
+                page_map: PageMap::new(Arc::clone(&self.fd_factory)),
+                size: wasm_memory_size,
+            },
+        };
+        let new_snapshot = CanisterSnapshot::new(
+            canister.canister_id(),
+            state.time(),
+            canister.system_state.canister_version,
+            args.certified_data,
+            WasmChunkStore::new(Arc::clone(&self.fd_factory)),
+            execution_snapshot,
+            new_snapshot_size,
+            source,
+        );
+
+        let snapshot_id = self.store_snapshot(
+            canister,
+            replace_snapshot,
+            replace_snapshot_size,
+            new_snapshot,
+            state,
+            round_limits,
+        );
         (
-            Ok(CanisterSnapshotResponse::new(
-                &snapshot_id,
-                state.time().as_nanos_since_unix_epoch(),
-                new_snapshot_size,
-            )),
+            Ok(UploadCanisterSnapshotMetadataResponse::new(&snapshot_id)),
             instructions,
         )
     }
 
+    /// Writes a slice of data into a canister snapshot, so that a snapshot
+    /// created with `create_snapshot_from_metadata` can be uploaded in chunks.
+    ///
+    /// The data is written into the Wasm module or one of the memories at
+    /// the given offset, or inserted as a new chunk into the chunk store.
+    /// Snapshots taken from a canister cannot be modified.
+    ///
+    /// Uploading the snapshot data can only be initiated by the controllers.
+    pub(crate) fn write_snapshot_data(
+        &self,
+        subnet_size: usize,
+        sender: PrincipalId,
+        canister: &mut CanisterState,
+        args: UploadCanisterSnapshotDataArgs,
+        state: &mut ReplicatedState,
+        round_limits: &mut RoundLimits,
+        resource_saturation: &ResourceSaturation,
+    ) -> (Result<(), CanisterManagerError>, NumInstructions) {
+        let canister_id = canister.canister_id();
+        // Check sender is a controller.
+        if let Err(err) = validate_controller(canister, &sender) {
+            return (Err(err), NumInstructions::new(0));
+        }
+
+        let snapshot_id = args.get_snapshot_id();
+        let snapshot = match get_snapshot(canister_id, snapshot_id, state) {
+            Ok(snapshot) => snapshot,
+            Err(err) => return (Err(err), NumInstructions::new(0)),
+        };
+        match snapshot.source() {
+            SnapshotSource::MetadataUpload { .. } => {}
+            SnapshotSource::TakenFromCanister => {
+                return (
+                    Err(CanisterManagerError::CanisterSnapshotImmutable {
+                        canister_id,
+                        snapshot_id,
+                    }),
+                    NumInstructions::new(0),
+                )
+            }
+        }
+
+        if let Err(err) = self.validate_heap_delta_rate_limit(canister) {
+            return (Err(err), NumInstructions::new(0));
+        }
+
+        let chunk = args.chunk;
+        let chunk_len = chunk.len() as u64;
+        let validation = match &args.kind {
+            CanisterSnapshotDataOffset::WasmModule { offset } => {
+                validate_slice(*offset, chunk_len, snapshot.wasm_module_size()).map(|_| ())
+            }
+            CanisterSnapshotDataOffset::MainMemory { offset } => {
+                validate_page_memory_slice(snapshot.wasm_memory(), *offset, chunk_len)
+            }
+            CanisterSnapshotDataOffset::StableMemory { offset } => {
+                validate_page_memory_slice(snapshot.stable_memory(), *offset, chunk_len)
+            }
+            CanisterSnapshotDataOffset::WasmChunk => snapshot
+                .chunk_store()
+                .can_insert_chunk(self.config.wasm_chunk_store_max_size, &chunk),
+        };
+        if let Err(message) = validation {
+            return (
+                Err(CanisterManagerError::CanisterSnapshotInvalidData {
+                    canister_id,
+                    message,
+                }),
+                NumInstructions::new(0),
+            );
+        }
+
+        // Only inserting a chunk into the chunk store grows the snapshot.
+        let memory_increase = match &args.kind {
+            CanisterSnapshotDataOffset::WasmChunk => wasm_chunk_store::chunk_size(),
+            CanisterSnapshotDataOffset::WasmModule { .. }
+            | CanisterSnapshotDataOffset::MainMemory { .. }
+            | CanisterSnapshotDataOffset::StableMemory { .. } => NumBytes::from(0),
+        };
+        // The Wasm module is not backed by a `PageMap`, so writing into it
+        // produces no heap delta.
+        let heap_delta = match &args.kind {
+            CanisterSnapshotDataOffset::WasmModule { .. } => NumBytes::from(0),
+            CanisterSnapshotDataOffset::MainMemory { offset }
+            | CanisterSnapshotDataOffset::StableMemory { offset } => {
+                page_memory_heap_delta(*offset, chunk_len)
+            }
+            CanisterSnapshotDataOffset::WasmChunk => wasm_chunk_store::chunk_size(),
+        };
+        let old_memory_usage = canister.memory_usage();
+        let new_memory_usage = old_memory_usage.saturating_add(&memory_increase);
+        if let Err(err) = self.memory_usage_checks(
+            subnet_size,
+            canister,
+            round_limits,
+            new_memory_usage,
+            old_memory_usage,
+            resource_saturation,
+        ) {
+            return (Err(err), NumInstructions::new(0));
+        }
+
+        // Charge for copying the data as well as for the heap delta it produces.
+        let instructions = match self.charge_for_snapshot_data(
+            subnet_size,
+            sender,
+            canister,
+            NumBytes::from(chunk_len) + heap_delta,
+        ) {
+            Ok(instructions) => instructions,
+            Err(err) => return (Err(err), NumInstructions::new(0)),
+        };
+
+        // All the writes below were validated above, so they cannot fail.
+        let wasm_chunk_store_max_size = self.config.wasm_chunk_store_max_size;
+        state
+            .canister_snapshots
+            .update(snapshot_id, |snapshot| match args.kind {
+                CanisterSnapshotDataOffset::WasmModule { offset } => snapshot
+                    .execution_snapshot_mut()
+                    .wasm_binary
+                    .write(&chunk, offset as usize),
+                CanisterSnapshotDataOffset::MainMemory { offset } => write_page_memory(
+                    &mut snapshot.execution_snapshot_mut().wasm_memory,
+                    &chunk,
+                    offset,
+                ),
+                CanisterSnapshotDataOffset::StableMemory { offset } => write_page_memory(
+                    &mut snapshot.execution_snapshot_mut().stable_memory,
+                    &chunk,
+                    offset,
+                ),
+                CanisterSnapshotDataOffset::WasmChunk => {
+                    snapshot
+                        .insert_chunk(wasm_chunk_store_max_size, &chunk)
+                        .expect(
+                            "Error: Insert chunk cannot fail after checking `can_insert_chunk`",
+                        );
+                }
+            })
+            .expect("Error: The snapshot was found above");
+
+        if memory_increase.get() > 0 {
+            // Actually deduct memory from the subnet. It's safe to unwrap
+            // here because we already checked the available memory above.
+            round_limits.subnet_available_memory
+                .try_decrement(memory_increase, NumBytes::from(0), NumBytes::from(0))
+                .expect("Error: Cannot fail to decrement SubnetAvailableMemory after checking for availability");
+            canister.system_state.snapshots_memory_usage = canister
+                .system_state
+                .snapshots_memory_usage
+                .saturating_add(&memory_increase);
+            // Confirm that `snapshots_memory_usage` is updated correctly.
+            debug_assert_eq!(
+                canister.system_state.snapshots_memory_usage,
+                state
+                    .canister_snapshots
+                    .compute_memory_usage_by_canister(canister_id),
+            );
+        }
+
+        if self.config.rate_limiting_of_heap_delta == FlagStatus::Enabled {
+            canister.scheduler_state.heap_delta_debit = canister
+                .scheduler_state
+                .heap_delta_debit
+                .saturating_add(&heap_delta);
+        }
+        state.metadata.heap_delta_estimate = state
+            .metadata
+            .heap_delta_estimate
+            .saturating_add(&heap_delta);
+
+        (Ok(()), instructions)
+    }
+
     pub(crate) fn load_canister_snapshot(
         &self,
         subnet_size: usize,
@@ -1757,7 +2246,15 @@ impl CanisterManager {
 
         let (instructions_used, new_execution_state) = {
             let execution_snapshot = snapshot.execution_snapshot();
-            let new_wasm_hash = WasmHash::from(&execution_snapshot.wasm_binary);
+            // The Wasm module of an uploaded snapshot only spans the slices
+            // written so far, so it is padded with zeros to its declared size.
+            let mut wasm_binary = execution_snapshot.wasm_binary.clone();
+            let wasm_module_size = snapshot.wasm_module_size() as usize;
+            if wasm_binary.len() < wasm_module_size {
+                wasm_binary.write(&[], wasm_module_size);
+            }
+            wasm_binary.finalize_hash();
+            let new_wasm_hash = WasmHash::from(&wasm_binary);
             let compilation_cost_handling = if state
                 .metadata
                 .expected_compiled_wasms
@@ -1769,7 +2266,7 @@ impl CanisterManager {
             };
 
             let (instructions_used, new_execution_state) = self.hypervisor.create_execution_state(
-                execution_snapshot.wasm_binary.clone(),
+                wasm_binary,
                 "NOT_USED".into(),
                 canister_id,
                 round_limits,
@@ -1784,6 +2281,20 @@ impl CanisterManager {
                 }
             };
 
+            // Snapshots uploaded by the user might come with globals that do not
+            // match the ones exported by the Wasm module.
+            if let Err(message) = validate_exported_globals(
+                &new_execution_state.exported_globals,
+                &execution_snapshot.exported_globals,
+            ) {
+                return (
+                    Err(CanisterManagerError::CanisterSnapshotInvalidData {
+                        canister_id,
+                        message,
+                    }),
+                    instructions_used,
+                );
+            }
             new_execution_state.exported_globals = execution_snapshot.exported_globals.clone();
             new_execution_state.stable_memory = Memory::from(&execution_snapshot.stable_memory);
             new_execution_state.wasm_memory = Memory::from(&execution_snapshot.wasm_memory);
@@ -2068,5 +2579,173 @@ pub fn uninstall_canister(
     reject_responses
 }
 
+/// Returns the snapshot identified by `snapshot_id` if it exists and belongs
+/// to the given canister.
+fn get_snapshot(
+    canister_id: CanisterId,
+    snapshot_id: SnapshotId,
+    state: &ReplicatedState,
+) -> Result<&Arc<CanisterSnapshot>, CanisterManagerError> {
+    match state.canister_snapshots.get(snapshot_id) {
+        // If not found, the operation fails due to invalid parameters.
+        None => Err(CanisterManagerError::CanisterSnapshotNotFound {
+            canister_id,
+            snapshot_id,
+        }),
+        // Verify the provided snapshot ID belongs to this canister.
+        Some(snapshot) if snapshot.canister_id() != canister_id => {
+            Err(CanisterManagerError::CanisterSnapshotInvalidOwnership {
+                canister_id,
+                snapshot_id,
+            })
+        }
+        Some(snapshot) => Ok(snapshot),
+    }
+}
+
+/// Checks that the metadata uploaded by the user describes a valid snapshot.
+///
+/// Returns the sizes of the Wasm memory and the stable memory in Wasm pages.
+fn validate_snapshot_metadata(
+    args: &UploadCanisterSnapshotMetadataArgs,
+    max_wasm_module_size: NumBytes,
+    max_wasm_memory_size: NumBytes,
+    max_stable_memory_size: NumBytes,
+) -> Result<(NumWasmPages, NumWasmPages), String> {
+    for (name, size, max_size) in [
+        ("Wasm module", args.wasm_module_size, max_wasm_module_size),
+        ("Wasm memory", args.wasm_memory_size, max_wasm_memory_size),
+        (
+            "Stable memory",
+            args.stable_memory_size,
+            max_stable_memory_size,
+        ),
+    ] {
+        if size > max_size.get() {
+            return Err(format!(
+                "{} size {} exceeds the maximum of {}",
+                name, size, max_size
+            ));
+        }
+    }
+    if args.certified_data.len() > CERTIFIED_DATA_MAX_LENGTH {
+        return Err(format!(
+            "Certified data size {} exceeds the maximum of {}",
+            args.certified_data.len(),
+            CERTIFIED_DATA_MAX_LENGTH
+        ));
+    }
+    let to_wasm_pages = |name: &str, bytes: u64| {
+        if bytes % WASM_PAGE_SIZE_IN_BYTES as u64 != 0 {
+            return Err(format!(
+                "{} size {} is not a multiple of the Wasm page size {}",
+                name, bytes, WASM_PAGE_SIZE_IN_BYTES
+            ));
+        }
+        Ok(NumWasmPages::new(
+            (bytes / WASM_PAGE_SIZE_IN_BYTES as u64) as usize,
+        ))
+    };
+    Ok((
+        to_wasm_pages("Wasm memory", args.wasm_memory_size)?,
+        to_wasm_pages("Stable memory", args.stable_memory_size)?,
+    ))
+}
+
+/// Checks that the globals of a snapshot have the same types as the globals
+/// exported by the Wasm module of the snapshot.
+fn validate_exported_globals(
+    module_globals: &[Global],
+    snapshot_globals: &[Global],
+) -> Result<(), String> {
+    if module_globals.len() != snapshot_globals.len() {
+        return Err(format!(
+            "Wasm module exports {} globals, but the snapshot contains {}",
+            module_globals.len(),
+            snapshot_globals.len()
+        ));
+    }
+    for (index, (module_global, snapshot_global)) in
+        module_globals.iter().zip(snapshot_globals).enumerate()
+    {
+        if module_global.type_name() != snapshot_global.type_name() {
+            return Err(format!(
+                "Global {} has type {} in the Wasm module, but type {} in the snapshot",
+                index,
+                module_global.type_name(),
+                snapshot_global.type_name()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a slice of snapshot data of the given `size` at the given
+/// `offset` fits into data of length `len` and does not exceed the maximum
+/// size of a slice. Returns the range of the slice.
+fn validate_slice(offset: u64, size: u64, len: u64) -> Result<Range<usize>, String> {
+    if size > MAX_SNAPSHOT_DATA_SLICE_SIZE {
+        return Err(format!(
+            "Slice size {} exceeds the maximum of {}",
+            size, MAX_SNAPSHOT_DATA_SLICE_SIZE
+        ));
+    }
+    match offset.checked_add(size) {
+        Some(end) if end <= len => Ok(offset as usize..end as usize),
+        _ => Err(format!(
+            "Slice at offset {} of size {} is out of bounds of data of size {}",
+            offset, size, len
+        )),
+    }
+}
+
+/// Like `validate_slice` for a slice of a snapshot memory.
+fn validate_page_memory_slice(memory: &PageMemory, offset: u64, size: u64) -> Result<(), String> {
+    let len = num_bytes_try_from(memory.size)?;
+    validate_slice(offset, size, len.get()).map(|_| ())
+}
+
+/// Reads a slice of a snapshot memory.
+fn read_page_memory(memory: &PageMemory, offset: u64, size: u64) -> Result<Vec<u8>, String> {
+    validate_page_memory_slice(memory, offset, size)?;
+    let mut chunk = vec![0; size as usize];
+    Buffer::new(memory.page_map.clone()).read(&mut chunk, offset as usize);
+    Ok(chunk)
+}
+
+/// Reads a slice of a snapshot Wasm module. The module of an uploaded snapshot
+/// only spans the slices written so far, so the rest of the slice is zero-filled.
+fn read_wasm_module(module: &CanisterModule, range: Range<usize>) -> Vec<u8> {
+    let mut chunk = vec![0; range.len()];
+    let module = module.as_slice();
+    let end = range.end.min(module.len());
+    if range.start < end {
+        chunk[..end - range.start].copy_from_slice(&module[range.start..end]);
+    }
+    chunk
+}
+
+/// Returns the heap delta produced by writing a slice of the given `size` at
+/// the given `offset` into a snapshot memory, i.e. the size of all the OS
+/// pages touched by the write.
+fn page_memory_heap_delta(offset: u64, size: u64) -> NumBytes {
+    if size == 0 {
+        return NumBytes::from(0);
+    }
+    let page_size = PAGE_SIZE as u64;
+    let first_page = offset / page_size;
+    let last_page = (offset + size - 1) / page_size;
+    NumBytes::from((last_page - first_page + 1) * page_size)
+}
+
+/// Writes a slice into a snapshot memory. The slice must have been validated
+/// with `validate_page_memory_slice`.
+fn write_page_memory(memory: &mut PageMemory, chunk: &[u8], offset: u64) {
+    let mut buffer = Buffer::new(memory.page_map.clone());
+    buffer.write(chunk, offset as usize);
+    let dirty_pages = buffer.dirty_pages().collect::<Vec<_>>();
+    memory.page_map.update(&dirty_pages);
+}
+
 #[cfg(test)]
 pub(crate) mod tests;
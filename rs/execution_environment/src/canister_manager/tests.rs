@@ -298,6 +298,9 @@ fn canister_manager_config(
         SchedulerConfig::application_subnet().canister_snapshot_baseline_instructions,
         DEFAULT_WASM_MEMORY_LIMIT,
         MAX_NUMBER_OF_SNAPSHOTS_PER_CANISTER,
+        ic_config::embedders::Config::default().wasm_max_size,
+        ic_config::embedders::Config::default().max_wasm_memory_size,
+        ic_config::embedders::Config::default().max_stable_memory_size,
     )
 }
 
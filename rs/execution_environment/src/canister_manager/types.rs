@@ -92,6 +92,9 @@ pub(crate) struct CanisterMgrConfig {
     pub(crate) canister_snapshot_baseline_instructions: NumInstructions,
     pub(crate) default_wasm_memory_limit: NumBytes,
     pub(crate) max_number_of_snapshots_per_canister: usize,
+    pub(crate) wasm_max_size: NumBytes,
+    pub(crate) max_wasm_memory_size: NumBytes,
+    pub(crate) max_stable_memory_size: NumBytes,
 }
 
 impl CanisterMgrConfig {
@@ -115,6 +118,9 @@ impl CanisterMgrConfig {
         canister_snapshot_baseline_instructions: NumInstructions,
         default_wasm_memory_limit: NumBytes,
         max_number_of_snapshots_per_canister: usize,
+        wasm_max_size: NumBytes,
+        max_wasm_memory_size: NumBytes,
+        max_stable_memory_size: NumBytes,
     ) -> Self {
         Self {
             subnet_memory_capacity,
@@ -135,6 +141,9 @@ impl CanisterMgrConfig {
             canister_snapshot_baseline_instructions,
             default_wasm_memory_limit,
             max_number_of_snapshots_per_canister,
+            wasm_max_size,
+            max_wasm_memory_size,
+            max_stable_memory_size,
         }
     }
 }
@@ -478,6 +487,14 @@ pub(crate) enum CanisterManagerError {
         limit: usize,
     },
     CanisterSnapshotNotEnoughCycles(CanisterOutOfCyclesError),
+    CanisterSnapshotInvalidData {
+        canister_id: CanisterId,
+        message: String,
+    },
+    CanisterSnapshotImmutable {
+        canister_id: CanisterId,
+        snapshot_id: SnapshotId,
+    },
     LongExecutionAlreadyInProgress {
         canister_id: CanisterId,
     },
@@ -648,6 +665,17 @@ impl AsErrorHelp for CanisterManagerError {
                 suggestion: "".to_string(),
                 doc_link: "".to_string(),
             },
+            CanisterManagerError::CanisterSnapshotInvalidData { .. } => ErrorHelp::UserError {
+                suggestion: "Check that the uploaded data fits into the snapshot metadata."
+                    .to_string(),
+                doc_link: "".to_string(),
+            },
+            CanisterManagerError::CanisterSnapshotImmutable { .. } => ErrorHelp::UserError {
+                suggestion:
+                    "Upload the snapshot metadata first to create a snapshot that can be modified."
+                        .to_string(),
+                doc_link: "".to_string(),
+            },
             CanisterManagerError::LongExecutionAlreadyInProgress { .. } => ErrorHelp::UserError {
                 suggestion: "Try waiting for the long execution to complete.".to_string(),
                 doc_link: doc_ref("long-execution-already-in-progress"),
@@ -962,6 +990,22 @@ impl From<CanisterManagerError> for UserError {
                     format!("Canister snapshotting failed with `{}`{additional_help}", err),
                 )
             }
+            CanisterSnapshotInvalidData { canister_id, message } => {
+                Self::new(
+                    ErrorCode::InvalidManagementPayload,
+                    format!(
+                        "Invalid snapshot data for canister {}: {}.{additional_help}", canister_id, message,
+                    )
+                )
+            }
+            CanisterSnapshotImmutable { canister_id, snapshot_id } => {
+                Self::new(
+                    ErrorCode::InvalidManagementPayload,
+                    format!(
+                        "The snapshot {} of canister {} was not created from uploaded metadata and cannot be modified.{additional_help}", snapshot_id, canister_id,
+                    )
+                )
+            }
             LongExecutionAlreadyInProgress { canister_id } => {
                 Self::new(
                     ErrorCode::CanisterRejectedMessage,
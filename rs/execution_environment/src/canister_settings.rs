@@ -2,13 +2,16 @@ use ic_base_types::{NumBytes, NumSeconds};
 use ic_cycles_account_manager::{CyclesAccountManager, ResourceSaturation};
 use ic_error_types::{ErrorCode, UserError};
 use ic_interfaces::execution_environment::SubnetAvailableMemory;
-use ic_management_canister_types_private::{CanisterSettingsArgs, LogVisibilityV2};
+use ic_management_canister_types_private::{
+    CanisterSettingsArgs, EnvironmentVariable, LogVisibilityV2,
+};
 use ic_replicated_state::MessageMemoryUsage;
 use ic_types::{
-    ComputeAllocation, Cycles, InvalidComputeAllocationError, InvalidMemoryAllocationError,
-    MemoryAllocation, PrincipalId,
+    ComputeAllocation, Cycles, EnvironmentVariables, InvalidComputeAllocationError,
+    InvalidMemoryAllocationError, MemoryAllocation, PrincipalId,
 };
 use num_traits::{cast::ToPrimitive, SaturatingSub};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 
 use crate::canister_manager::types::CanisterManagerError;
@@ -16,6 +19,12 @@ use crate::canister_manager::types::CanisterManagerError;
 /// These limit comes from the spec and is not expected to change,
 /// which is why it is not part of the replica config.
 const MAX_WASM_MEMORY_LIMIT: u64 = 1 << 48;
+/// The maximum number of environment variables of a canister.
+pub(crate) const MAX_ENVIRONMENT_VARIABLES: usize = 20;
+/// The maximum length in bytes of the name of an environment variable.
+pub(crate) const MAX_ENVIRONMENT_VARIABLE_NAME_LENGTH: usize = 128;
+/// The maximum length in bytes of the value of an environment variable.
+pub(crate) const MAX_ENVIRONMENT_VARIABLE_VALUE_LENGTH: usize = 128;
 /// Struct used for decoding CanisterSettingsArgs
 #[derive(Default)]
 pub(crate) struct CanisterSettings {
@@ -28,6 +37,7 @@ pub(crate) struct CanisterSettings {
     pub(crate) reserved_cycles_limit: Option<Cycles>,
     pub(crate) log_visibility: Option<LogVisibilityV2>,
     pub(crate) wasm_memory_limit: Option<NumBytes>,
+    pub(crate) environment_variables: Option<EnvironmentVariables>,
 }
 
 impl CanisterSettings {
@@ -40,6 +50,7 @@ impl CanisterSettings {
         reserved_cycles_limit: Option<Cycles>,
         log_visibility: Option<LogVisibilityV2>,
         wasm_memory_limit: Option<NumBytes>,
+        environment_variables: Option<EnvironmentVariables>,
     ) -> Self {
         Self {
             controllers,
@@ -50,6 +61,7 @@ impl CanisterSettings {
             reserved_cycles_limit,
             log_visibility,
             wasm_memory_limit,
+            environment_variables,
         }
     }
 
@@ -84,6 +96,10 @@ impl CanisterSettings {
     pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
         self.wasm_memory_limit
     }
+
+    pub fn environment_variables(&self) -> Option<&EnvironmentVariables> {
+        self.environment_variables.as_ref()
+    }
 }
 
 impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
@@ -147,6 +163,11 @@ impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
             None => None,
         };
 
+        let environment_variables = match input.environment_variables {
+            Some(variables) => Some(validate_environment_variables(variables)?),
+            None => None,
+        };
+
         Ok(CanisterSettings::new(
             input
                 .controllers
@@ -158,10 +179,43 @@ impl TryFrom<CanisterSettingsArgs> for CanisterSettings {
             reserved_cycles_limit,
             input.log_visibility,
             wasm_memory_limit,
+            environment_variables,
         ))
     }
 }
 
+/// Checks the number and the lengths of the given environment variables and
+/// that their names are non-empty and unique.
+fn validate_environment_variables(
+    variables: Vec<EnvironmentVariable>,
+) -> Result<EnvironmentVariables, UpdateSettingsError> {
+    if variables.len() > MAX_ENVIRONMENT_VARIABLES {
+        return Err(UpdateSettingsError::TooManyEnvironmentVariables {
+            provided: variables.len(),
+        });
+    }
+    let mut result = BTreeMap::new();
+    for EnvironmentVariable { name, value } in variables {
+        if name.is_empty() {
+            return Err(UpdateSettingsError::EmptyEnvironmentVariableName);
+        }
+        if name.len() > MAX_ENVIRONMENT_VARIABLE_NAME_LENGTH {
+            return Err(UpdateSettingsError::EnvironmentVariableNameTooLong { length: name.len() });
+        }
+        if value.len() > MAX_ENVIRONMENT_VARIABLE_VALUE_LENGTH {
+            return Err(UpdateSettingsError::EnvironmentVariableValueTooLong {
+                name,
+                length: value.len(),
+            });
+        }
+        if result.contains_key(&name) {
+            return Err(UpdateSettingsError::DuplicateEnvironmentVariable { name });
+        }
+        result.insert(name, value);
+    }
+    Ok(EnvironmentVariables::new(result))
+}
+
 impl TryFrom<Option<CanisterSettingsArgs>> for CanisterSettings {
     type Error = UpdateSettingsError;
 
@@ -182,6 +236,7 @@ pub(crate) struct CanisterSettingsBuilder {
     reserved_cycles_limit: Option<Cycles>,
     log_visibility: Option<LogVisibilityV2>,
     wasm_memory_limit: Option<NumBytes>,
+    environment_variables: Option<EnvironmentVariables>,
 }
 
 #[allow(dead_code)]
@@ -196,6 +251,7 @@ impl CanisterSettingsBuilder {
             reserved_cycles_limit: None,
             log_visibility: None,
             wasm_memory_limit: None,
+            environment_variables: None,
         }
     }
 
@@ -209,6 +265,7 @@ impl CanisterSettingsBuilder {
             reserved_cycles_limit: self.reserved_cycles_limit,
             log_visibility: self.log_visibility,
             wasm_memory_limit: self.wasm_memory_limit,
+            environment_variables: self.environment_variables,
         }
     }
 
@@ -267,6 +324,13 @@ impl CanisterSettingsBuilder {
             ..self
         }
     }
+
+    pub fn with_environment_variables(self, environment_variables: EnvironmentVariables) -> Self {
+        Self {
+            environment_variables: Some(environment_variables),
+            ..self
+        }
+    }
 }
 
 pub enum UpdateSettingsError {
@@ -276,6 +340,11 @@ pub enum UpdateSettingsError {
     ReservedCyclesLimitOutOfRange { provided: candid::Nat },
     WasmMemoryLimitOutOfRange { provided: candid::Nat },
     WasmMemoryThresholdOutOfRange { provided: candid::Nat },
+    TooManyEnvironmentVariables { provided: usize },
+    EmptyEnvironmentVariableName,
+    EnvironmentVariableNameTooLong { length: usize },
+    EnvironmentVariableValueTooLong { name: String, length: usize },
+    DuplicateEnvironmentVariable { name: String },
 }
 
 impl From<UpdateSettingsError> for UserError {
@@ -325,6 +394,38 @@ impl From<UpdateSettingsError> for UserError {
                     provided
                 ),
             ),
+            UpdateSettingsError::TooManyEnvironmentVariables { provided } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "At most {} environment variables are allowed, got {}",
+                    MAX_ENVIRONMENT_VARIABLES, provided
+                ),
+            ),
+            UpdateSettingsError::EmptyEnvironmentVariableName => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                "Environment variable name must not be empty".to_string(),
+            ),
+            UpdateSettingsError::EnvironmentVariableNameTooLong { length } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!(
+                    "Environment variable name expected to be at most {} bytes long, got {}",
+                    MAX_ENVIRONMENT_VARIABLE_NAME_LENGTH, length
+                ),
+            ),
+            UpdateSettingsError::EnvironmentVariableValueTooLong { name, length } => {
+                UserError::new(
+                    ErrorCode::CanisterContractViolation,
+                    format!(
+                        "Value of environment variable {} expected to be at most {} bytes long, \
+                        got {}",
+                        name, MAX_ENVIRONMENT_VARIABLE_VALUE_LENGTH, length
+                    ),
+                )
+            }
+            UpdateSettingsError::DuplicateEnvironmentVariable { name } => UserError::new(
+                ErrorCode::CanisterContractViolation,
+                format!("Duplicate environment variable {}", name),
+            ),
         }
     }
 }
@@ -351,6 +452,7 @@ pub(crate) struct ValidatedCanisterSettings {
     reservation_cycles: Cycles,
     log_visibility: Option<LogVisibilityV2>,
     wasm_memory_limit: Option<NumBytes>,
+    environment_variables: Option<EnvironmentVariables>,
 }
 
 impl ValidatedCanisterSettings {
@@ -389,6 +491,10 @@ impl ValidatedCanisterSettings {
     pub fn wasm_memory_limit(&self) -> Option<NumBytes> {
         self.wasm_memory_limit
     }
+
+    pub fn environment_variables(&self) -> Option<&EnvironmentVariables> {
+        self.environment_variables.as_ref()
+    }
 }
 
 /// Validates the new canisters settings:
@@ -581,5 +687,6 @@ pub(crate) fn validate_canister_settings(
         reservation_cycles,
         log_visibility: settings.log_visibility().cloned(),
         wasm_memory_limit: settings.wasm_memory_limit(),
+        environment_variables: settings.environment_variables().cloned(),
     })
 }
@@ -14,6 +14,7 @@ use crate::{
     execution_environment_metrics::{
         ExecutionEnvironmentMetrics, SUBMITTED_OUTCOME_LABEL, SUCCESS_STATUS_LABEL,
     },
+    execution_tracer::{next_callback_id, ExecutedMessage, ExecutionTracer},
     hypervisor::Hypervisor,
     ic00_permissions::Ic00MethodPermissions,
     metrics::{CallTreeMetrics, CallTreeMetricsImpl, IngressFilterMetrics},
@@ -43,10 +44,11 @@ use ic_management_canister_types_private::{
     EmptyBlob, InstallChunkedCodeArgs, InstallCodeArgsV2, ListCanisterSnapshotArgs,
     LoadCanisterSnapshotArgs, MasterPublicKeyId, Method as Ic00Method, NodeMetricsHistoryArgs,
     Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs,
-    ReadCanisterSnapshotMetadataArgs, ReshareChainKeyArgs, SchnorrAlgorithm, SchnorrPublicKeyArgs,
-    SchnorrPublicKeyResponse, SetupInitialDKGArgs, SignWithECDSAArgs, SignWithSchnorrArgs,
-    SignWithSchnorrAux, StoredChunksArgs, SubnetInfoArgs, SubnetInfoResponse,
-    TakeCanisterSnapshotArgs, UninstallCodeArgs, UpdateSettingsArgs, UploadChunkArgs,
+    ReadCanisterSnapshotDataArgs, ReadCanisterSnapshotMetadataArgs, ReshareChainKeyArgs,
+    SchnorrAlgorithm, SchnorrPublicKeyArgs, SchnorrPublicKeyResponse, SetupInitialDKGArgs,
+    SignWithECDSAArgs, SignWithSchnorrArgs, SignWithSchnorrAux, StoredChunksArgs, SubnetInfoArgs,
+    SubnetInfoResponse, TakeCanisterSnapshotArgs, UninstallCodeArgs, UpdateSettingsArgs,
+    UploadCanisterSnapshotDataArgs, UploadCanisterSnapshotMetadataArgs, UploadChunkArgs,
     VetKdDeriveKeyArgs, VetKdPublicKeyArgs, VetKdPublicKeyResult, IC_00,
 };
 use ic_metrics::MetricsRegistry;
@@ -343,6 +345,9 @@ pub struct ExecutionEnvironment {
     // the number of scheduler cores.
     resource_saturation_scaling: usize,
     deallocator_thread: DeallocatorThread,
+    // Traces the executions of canister messages outside of the replicated state
+    // (disabled by default).
+    execution_tracer: Arc<ExecutionTracer>,
 }
 
 /// This is a helper enum that indicates whether the current DTS execution of
@@ -408,6 +413,9 @@ impl ExecutionEnvironment {
             canister_snapshot_baseline_instructions,
             config.default_wasm_memory_limit,
             config.max_number_of_snapshots_per_canister,
+            config.embedders_config.wasm_max_size,
+            config.embedders_config.max_wasm_memory_size,
+            config.embedders_config.max_stable_memory_size,
         );
         let metrics = ExecutionEnvironmentMetrics::new(metrics_registry);
         let canister_manager = CanisterManager::new(
@@ -439,9 +447,16 @@ impl ExecutionEnvironment {
             paused_execution_registry: Default::default(),
             resource_saturation_scaling,
             deallocator_thread,
+            execution_tracer: Arc::new(ExecutionTracer::default()),
         }
     }
 
+    /// Returns the tracer of canister message executions
+    /// (shared with the query handler, see `ExecutionServices`).
+    pub fn execution_tracer(&self) -> &Arc<ExecutionTracer> {
+        &self.execution_tracer
+    }
+
     pub fn state_changes_error(&self) -> &IntCounter {
         &self.metrics.state_changes_error
     }
@@ -1655,6 +1670,86 @@ impl ExecutionEnvironment {
                 }
             }
 
+            Ok(Ic00Method::ReadCanisterSnapshotData) => {
+                match ReadCanisterSnapshotDataArgs::decode(payload) {
+                    Err(err) => ExecuteSubnetMessageResult::Finished {
+                        response: Err(err),
+                        refund: msg.take_cycles(),
+                    },
+                    Ok(args) => {
+                        let canister_id = args.get_canister_id();
+                        let (result, instructions_used) = self.read_snapshot_data(
+                            *msg.sender(),
+                            &mut state,
+                            args,
+                            registry_settings.subnet_size,
+                        );
+                        let msg_result = ExecuteSubnetMessageResult::Finished {
+                            response: result.map(|res| (res, Some(canister_id))),
+                            refund: msg.take_cycles(),
+                        };
+
+                        let state =
+                            self.finish_subnet_message_execution(state, msg, msg_result, since);
+                        return (state, Some(instructions_used));
+                    }
+                }
+            }
+
+            Ok(Ic00Method::UploadCanisterSnapshotMetadata) => {
+                match UploadCanisterSnapshotMetadataArgs::decode(payload) {
+                    Err(err) => ExecuteSubnetMessageResult::Finished {
+                        response: Err(err),
+                        refund: msg.take_cycles(),
+                    },
+                    Ok(args) => {
+                        let canister_id = args.get_canister_id();
+                        let (result, instructions_used) = self.upload_snapshot_metadata(
+                            *msg.sender(),
+                            &mut state,
+                            args,
+                            registry_settings.subnet_size,
+                            round_limits,
+                        );
+                        let msg_result = ExecuteSubnetMessageResult::Finished {
+                            response: result.map(|res| (res, Some(canister_id))),
+                            refund: msg.take_cycles(),
+                        };
+
+                        let state =
+                            self.finish_subnet_message_execution(state, msg, msg_result, since);
+                        return (state, Some(instructions_used));
+                    }
+                }
+            }
+
+            Ok(Ic00Method::UploadCanisterSnapshotData) => {
+                match UploadCanisterSnapshotDataArgs::decode(payload) {
+                    Err(err) => ExecuteSubnetMessageResult::Finished {
+                        response: Err(err),
+                        refund: msg.take_cycles(),
+                    },
+                    Ok(args) => {
+                        let canister_id = args.get_canister_id();
+                        let (result, instructions_used) = self.upload_snapshot_data(
+                            *msg.sender(),
+                            &mut state,
+                            args,
+                            registry_settings.subnet_size,
+                            round_limits,
+                        );
+                        let msg_result = ExecuteSubnetMessageResult::Finished {
+                            response: result.map(|res| (res, Some(canister_id))),
+                            refund: msg.take_cycles(),
+                        };
+
+                        let state =
+                            self.finish_subnet_message_execution(state, msg, msg_result, since);
+                        return (state, Some(instructions_used));
+                    }
+                }
+            }
+
             Ok(Ic00Method::ReadCanisterSnapshotMetadata) => {
                 // TODO: EXC-1955
                 #[allow(clippy::bind_instead_of_map)]
@@ -2394,6 +2489,135 @@ impl ExecutionEnvironment {
         result
     }
 
+    /// Reads a slice of the data of the specified canister snapshot.
+    fn read_snapshot_data(
+        &self,
+        sender: PrincipalId,
+        state: &mut ReplicatedState,
+        args: ReadCanisterSnapshotDataArgs,
+        subnet_size: usize,
+    ) -> (Result<Vec<u8>, UserError>, NumInstructions) {
+        let canister_id = args.get_canister_id();
+        // Take canister out.
+        let mut canister = match state.take_canister_state(&canister_id) {
+            None => {
+                return (
+                    Err(UserError::new(
+                        ErrorCode::CanisterNotFound,
+                        format!("Canister {} not found.", &canister_id),
+                    )),
+                    NumInstructions::new(0),
+                )
+            }
+            Some(canister) => canister,
+        };
+
+        let snapshot_id = args.get_snapshot_id();
+        let (result, instructions_used) = self.canister_manager.read_snapshot_data(
+            subnet_size,
+            sender,
+            &mut canister,
+            snapshot_id,
+            args.kind,
+            state,
+        );
+        // Put canister back.
+        state.put_canister_state(canister);
+
+        match result {
+            Ok(response) => (Ok(response.encode()), instructions_used),
+            Err(err) => (Err(err.into()), instructions_used),
+        }
+    }
+
+    /// Creates a new canister snapshot from the uploaded metadata.
+    fn upload_snapshot_metadata(
+        &self,
+        sender: PrincipalId,
+        state: &mut ReplicatedState,
+        args: UploadCanisterSnapshotMetadataArgs,
+        subnet_size: usize,
+        round_limits: &mut RoundLimits,
+    ) -> (Result<Vec<u8>, UserError>, NumInstructions) {
+        let canister_id = args.get_canister_id();
+        // Take canister out.
+        let mut canister = match state.take_canister_state(&canister_id) {
+            None => {
+                return (
+                    Err(UserError::new(
+                        ErrorCode::CanisterNotFound,
+                        format!("Canister {} not found.", &canister_id),
+                    )),
+                    NumInstructions::new(0),
+                )
+            }
+            Some(canister) => canister,
+        };
+
+        let resource_saturation =
+            self.subnet_memory_saturation(&round_limits.subnet_available_memory);
+        let (result, instructions_used) = self.canister_manager.create_snapshot_from_metadata(
+            subnet_size,
+            sender,
+            &mut canister,
+            args,
+            state,
+            round_limits,
+            &resource_saturation,
+        );
+        // Put canister back.
+        state.put_canister_state(canister);
+
+        match result {
+            Ok(response) => (Ok(response.encode()), instructions_used),
+            Err(err) => (Err(err.into()), instructions_used),
+        }
+    }
+
+    /// Writes a slice of uploaded data into the specified canister snapshot.
+    fn upload_snapshot_data(
+        &self,
+        sender: PrincipalId,
+        state: &mut ReplicatedState,
+        args: UploadCanisterSnapshotDataArgs,
+        subnet_size: usize,
+        round_limits: &mut RoundLimits,
+    ) -> (Result<Vec<u8>, UserError>, NumInstructions) {
+        let canister_id = args.get_canister_id();
+        // Take canister out.
+        let mut canister = match state.take_canister_state(&canister_id) {
+            None => {
+                return (
+                    Err(UserError::new(
+                        ErrorCode::CanisterNotFound,
+                        format!("Canister {} not found.", &canister_id),
+                    )),
+                    NumInstructions::new(0),
+                )
+            }
+            Some(canister) => canister,
+        };
+
+        let resource_saturation =
+            self.subnet_memory_saturation(&round_limits.subnet_available_memory);
+        let (result, instructions_used) = self.canister_manager.write_snapshot_data(
+            subnet_size,
+            sender,
+            &mut canister,
+            args,
+            state,
+            round_limits,
+            &resource_saturation,
+        );
+        // Put canister back.
+        state.put_canister_state(canister);
+
+        match result {
+            Ok(()) => (Ok(EmptyBlob.encode()), instructions_used),
+            Err(err) => (Err(err.into()), instructions_used),
+        }
+    }
+
     fn node_metrics_history(
         &self,
         state: &ReplicatedState,
@@ -3995,6 +4219,11 @@ fn execute_canister_input(
     subnet_size: usize,
 ) -> ExecuteCanisterResult {
     let info = input.to_string();
+    let traced_message = exec_env
+        .execution_tracer
+        .is_enabled()
+        .then(|| ExecutedMessage::from(&input));
+    let next_callback_id_before = next_callback_id(&canister);
     let result = exec_env.execute_canister_input(
         canister,
         instruction_limits,
@@ -4007,6 +4236,14 @@ fn execute_canister_input(
         subnet_size,
     );
     let (canister, instructions_used, heap_delta, ingress_status) = exec_env.process_result(result);
+    if let Some(message) = traced_message {
+        exec_env.execution_tracer.trace(
+            &canister,
+            message,
+            instructions_used.unwrap_or_default(),
+            next_callback_id_before,
+        );
+    }
     ExecuteCanisterResult {
         canister,
         instructions_used,
@@ -4045,6 +4282,11 @@ pub fn execute_canister(
         Some(task) => match task {
             ExecutionTask::PausedExecution { id, .. } => {
                 let paused = exec_env.take_paused_execution(id).unwrap();
+                let traced_message = exec_env
+                    .execution_tracer
+                    .is_enabled()
+                    .then(|| ExecutedMessage::from(&paused.input()));
+                let next_callback_id_before = next_callback_id(&canister);
                 let round_counters = RoundCounters {
                     execution_refund_error: &exec_env.metrics.execution_cycles_refund_error,
                     state_changes_error: &exec_env.metrics.state_changes_error,
@@ -4073,6 +4315,14 @@ pub fn execute_canister(
                 );
                 let (canister, instructions_used, heap_delta, ingress_status) =
                     exec_env.process_result(result);
+                if let Some(message) = traced_message {
+                    exec_env.execution_tracer.trace(
+                        &canister,
+                        message,
+                        instructions_used.unwrap_or_default(),
+                        next_callback_id_before,
+                    );
+                }
                 return ExecuteCanisterResult {
                     canister,
                     instructions_used,
@@ -6,11 +6,11 @@ use ic_management_canister_types_private::{
     self as ic00, BitcoinGetUtxosArgs, BitcoinNetwork, BoundedHttpHeaders, CanisterChange,
     CanisterHttpRequestArgs, CanisterIdRecord, CanisterSettingsArgsBuilder, CanisterStatusResultV2,
     CanisterStatusType, ClearChunkStoreArgs, DerivationPath, EcdsaKeyId, EmptyBlob,
-    FetchCanisterLogsRequest, HttpMethod, LogVisibilityV2, MasterPublicKeyId, Method,
-    OnLowWasmMemoryHookStatus, Payload as Ic00Payload, ProvisionalCreateCanisterWithCyclesArgs,
-    ProvisionalTopUpCanisterArgs, SchnorrAlgorithm, SchnorrKeyId, TakeCanisterSnapshotArgs,
-    TransformContext, TransformFunc, UpdateSettingsArgs, UploadChunkArgs, VetKdCurve, VetKdKeyId,
-    IC_00,
+    EnvironmentVariable, FetchCanisterLogsRequest, HttpMethod, LogVisibilityV2, MasterPublicKeyId,
+    Method, OnLowWasmMemoryHookStatus, Payload as Ic00Payload,
+    ProvisionalCreateCanisterWithCyclesArgs, ProvisionalTopUpCanisterArgs, SchnorrAlgorithm,
+    SchnorrKeyId, TakeCanisterSnapshotArgs, TransformContext, TransformFunc, UpdateSettingsArgs,
+    UploadChunkArgs, VetKdCurve, VetKdKeyId, IC_00,
 };
 use ic_registry_routing_table::{canister_id_into_u64, CanisterIdRange, RoutingTable};
 use ic_registry_subnet_type::SubnetType;
@@ -896,6 +896,136 @@ fn get_canister_status(
     }
 }
 
+#[test]
+fn update_settings_sets_environment_variables() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.canister_update_environment_variables(
+        canister_id,
+        vec![
+            EnvironmentVariable::new("B_KEY", "value_b"),
+            EnvironmentVariable::new("A_KEY", "value_a"),
+        ],
+    )
+    .unwrap();
+
+    // The variables are reported sorted by name.
+    let csr = get_canister_status(&mut test, canister_id);
+    assert_eq!(
+        csr.settings().environment_variables(),
+        &[
+            EnvironmentVariable::new("A_KEY", "value_a"),
+            EnvironmentVariable::new("B_KEY", "value_b"),
+        ]
+    );
+
+    // Updating other settings keeps the variables.
+    test.canister_update_reserved_cycles_limit(canister_id, Cycles::new(1))
+        .unwrap();
+    let csr = get_canister_status(&mut test, canister_id);
+    assert_eq!(csr.settings().environment_variables().len(), 2);
+
+    // An empty list removes all the variables.
+    test.canister_update_environment_variables(canister_id, vec![])
+        .unwrap();
+    let csr = get_canister_status(&mut test, canister_id);
+    assert!(csr.settings().environment_variables().is_empty());
+}
+
+#[test]
+fn environment_variables_count_towards_memory_usage() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    let initial_memory_usage = test.canister_state(canister_id).memory_usage();
+
+    test.canister_update_environment_variables(
+        canister_id,
+        vec![EnvironmentVariable::new("KEY", "value")],
+    )
+    .unwrap();
+    assert_eq!(
+        test.canister_state(canister_id).memory_usage(),
+        initial_memory_usage + NumBytes::from(8)
+    );
+
+    // Replacing the variables replaces their memory usage.
+    test.canister_update_environment_variables(
+        canister_id,
+        vec![EnvironmentVariable::new("K", "v")],
+    )
+    .unwrap();
+    assert_eq!(
+        test.canister_state(canister_id).memory_usage(),
+        initial_memory_usage + NumBytes::from(2)
+    );
+}
+
+#[test]
+fn update_settings_rejects_invalid_environment_variables() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let canister_id = test.universal_canister().unwrap();
+    test.canister_update_environment_variables(
+        canister_id,
+        vec![EnvironmentVariable::new("KEY", "value")],
+    )
+    .unwrap();
+
+    let err = test
+        .canister_update_environment_variables(
+            canister_id,
+            vec![
+                EnvironmentVariable::new("KEY", "value_1"),
+                EnvironmentVariable::new("KEY", "value_2"),
+            ],
+        )
+        .unwrap_err();
+    err.assert_contains(
+        ErrorCode::CanisterContractViolation,
+        "Duplicate environment variable KEY",
+    );
+
+    let err = test
+        .canister_update_environment_variables(
+            canister_id,
+            (0..21)
+                .map(|i| EnvironmentVariable::new(format!("KEY_{}", i), "value"))
+                .collect(),
+        )
+        .unwrap_err();
+    err.assert_contains(
+        ErrorCode::CanisterContractViolation,
+        "At most 20 environment variables are allowed, got 21",
+    );
+
+    let err = test
+        .canister_update_environment_variables(
+            canister_id,
+            vec![EnvironmentVariable::new("KEY", "x".repeat(129))],
+        )
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::CanisterContractViolation);
+
+    let err = test
+        .canister_update_environment_variables(
+            canister_id,
+            vec![EnvironmentVariable::new("", "value")],
+        )
+        .unwrap_err();
+    err.assert_contains(
+        ErrorCode::CanisterContractViolation,
+        "Environment variable name must not be empty",
+    );
+
+    // The variables are not modified by a failed update.
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .environment_variables
+            .get("KEY"),
+        Some("value")
+    );
+}
+
 #[test]
 fn get_canister_status_memory_metrics() {
     let mut test = ExecutionTestBuilder::new().build();
@@ -1,14 +1,18 @@
 use assert_matches::assert_matches;
 use candid::{Decode, Encode};
 use ic_base_types::NumBytes;
+use ic_config::embedders::Config as EmbeddersConfig;
 use ic_config::subnet_config::SubnetConfig;
 use ic_cycles_account_manager::ResourceSaturation;
 use ic_error_types::{ErrorCode, RejectCode};
 use ic_management_canister_types_private::{
     self as ic00, CanisterChange, CanisterChangeDetails, CanisterSettingsArgsBuilder,
-    CanisterSnapshotResponse, ClearChunkStoreArgs, DeleteCanisterSnapshotArgs,
-    ListCanisterSnapshotArgs, LoadCanisterSnapshotArgs, Method, OnLowWasmMemoryHookStatus,
-    Payload as Ic00Payload, TakeCanisterSnapshotArgs, UpdateSettingsArgs, UploadChunkArgs,
+    CanisterSnapshotDataKind, CanisterSnapshotDataOffset, CanisterSnapshotResponse,
+    ClearChunkStoreArgs, DeleteCanisterSnapshotArgs, ListCanisterSnapshotArgs,
+    LoadCanisterSnapshotArgs, Method, OnLowWasmMemoryHookStatus, Payload as Ic00Payload,
+    ReadCanisterSnapshotDataArgs, ReadCanisterSnapshotDataResponse, TakeCanisterSnapshotArgs,
+    UpdateSettingsArgs, UploadCanisterSnapshotDataArgs, UploadCanisterSnapshotMetadataArgs,
+    UploadCanisterSnapshotMetadataResponse, UploadChunkArgs,
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
@@ -17,7 +21,7 @@ use ic_replicated_state::{
         execution_state::{WasmBinary, WasmExecutionMode},
         system_state::CyclesUseCase,
     },
-    CanisterState, ExecutionState, SchedulerState,
+    CanisterState, ExecutionState, Global, SchedulerState,
 };
 use ic_test_utilities_execution_environment::{
     cycles_reserved_for_app_and_verified_app_subnets, get_output_messages, ExecutionTest,
@@ -36,6 +40,7 @@ use serde_bytes::ByteBuf;
 use std::borrow::Borrow;
 
 const WASM_EXECUTION_MODE: WasmExecutionMode = WasmExecutionMode::Wasm32;
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
 
 #[test]
 fn take_canister_snapshot_decode_round_trip() {
@@ -1995,6 +2000,478 @@ fn snapshot_must_include_globals() {
     assert_eq!(result, WasmResult::Reply(vec![1, 0, 0, 0]));
 }
 
+#[test]
+fn read_canister_snapshot_data_decode_fails() {
+    let canister_id = canister_test_id(4);
+    let args = ic00::ReadCanisterSnapshotDataArgs {
+        canister_id: canister_id.get(),
+        snapshot_id: vec![4, 5, 6, 6], // Invalid snapshot ID.
+        kind: CanisterSnapshotDataKind::WasmModule { offset: 0, size: 1 },
+    };
+    let err = ReadCanisterSnapshotDataArgs::decode(args.encode().as_slice()).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidManagementPayload);
+
+    let args = ic00::UploadCanisterSnapshotDataArgs {
+        canister_id: canister_id.get(),
+        snapshot_id: vec![4, 5, 6, 6], // Invalid snapshot ID.
+        kind: CanisterSnapshotDataOffset::WasmChunk,
+        chunk: vec![],
+    };
+    let err = UploadCanisterSnapshotDataArgs::decode(args.encode().as_slice()).unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidManagementPayload);
+}
+
+fn helper_read_snapshot_data(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    snapshot_id: SnapshotId,
+    kind: CanisterSnapshotDataKind,
+) -> Vec<u8> {
+    let args = ReadCanisterSnapshotDataArgs::new(canister_id, snapshot_id, kind);
+    let result = test
+        .subnet_message("read_canister_snapshot_data", args.encode())
+        .unwrap();
+    ReadCanisterSnapshotDataResponse::decode(&result.bytes())
+        .unwrap()
+        .chunk
+}
+
+fn helper_upload_snapshot_data(
+    test: &mut ExecutionTest,
+    canister_id: CanisterId,
+    snapshot_id: SnapshotId,
+    kind: CanisterSnapshotDataOffset,
+    chunk: Vec<u8>,
+) {
+    let args = UploadCanisterSnapshotDataArgs::new(canister_id, snapshot_id, kind, chunk);
+    test.subnet_message("upload_canister_snapshot_data", args.encode())
+        .unwrap();
+}
+
+fn to_ic00_global(global: &Global) -> ic00::Global {
+    match global {
+        Global::I32(value) => ic00::Global::I32(*value),
+        Global::I64(value) => ic00::Global::I64(*value),
+        Global::F32(value) => ic00::Global::F32(*value),
+        Global::F64(value) => ic00::Global::F64(*value),
+        Global::V128(value) => ic00::Global::V128(*value),
+    }
+}
+
+#[test]
+fn download_and_upload_canister_snapshot_round_trip() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000);
+    const SLICE_SIZE: u64 = 1_000_000;
+    let mut test = ExecutionTestBuilder::new()
+        .with_max_snapshots_per_canister(2)
+        .build();
+
+    let canister_id = test
+        .canister_from_cycles_and_binary(CYCLES, UNIVERSAL_CANISTER_WASM.to_vec())
+        .unwrap();
+
+    // Write to stable memory and upload a chunk.
+    test.ingress(
+        canister_id,
+        "update",
+        wasm()
+            .stable_grow(1)
+            .stable_write(0, b"snapshot")
+            .reply()
+            .build(),
+    )
+    .unwrap();
+    let upload_args = UploadChunkArgs {
+        canister_id: canister_id.into(),
+        chunk: vec![1, 2, 3, 4, 5],
+    };
+    let result = test.subnet_message("upload_chunk", upload_args.encode());
+    assert!(result.is_ok());
+
+    let (snapshot_id, _) = helper_take_snapshot(&mut test, canister_id);
+    let snapshot = test
+        .state()
+        .canister_snapshots
+        .get(snapshot_id)
+        .unwrap()
+        .clone();
+    let wasm_module_size = snapshot.canister_module().len() as u64;
+    let wasm_memory_size = snapshot.wasm_memory().size.get() as u64 * WASM_PAGE_SIZE;
+    let stable_memory_size = snapshot.stable_memory().size.get() as u64 * WASM_PAGE_SIZE;
+
+    // Upload the metadata of the snapshot as a new snapshot.
+    let args = UploadCanisterSnapshotMetadataArgs::new(
+        canister_id,
+        None,
+        wasm_module_size,
+        snapshot
+            .exported_globals()
+            .iter()
+            .map(to_ic00_global)
+            .collect(),
+        wasm_memory_size,
+        stable_memory_size,
+        snapshot.certified_data().clone(),
+    );
+    let result = test
+        .subnet_message("upload_canister_snapshot_metadata", args.encode())
+        .unwrap();
+    let uploaded_snapshot_id = UploadCanisterSnapshotMetadataResponse::decode(&result.bytes())
+        .unwrap()
+        .get_snapshot_id();
+    assert_ne!(uploaded_snapshot_id, snapshot_id);
+
+    // Download the data of the snapshot in slices and upload it into the new snapshot.
+    for offset in (0..wasm_module_size).step_by(SLICE_SIZE as usize) {
+        let size = SLICE_SIZE.min(wasm_module_size - offset);
+        let chunk = helper_read_snapshot_data(
+            &mut test,
+            canister_id,
+            snapshot_id,
+            CanisterSnapshotDataKind::WasmModule { offset, size },
+        );
+        helper_upload_snapshot_data(
+            &mut test,
+            canister_id,
+            uploaded_snapshot_id,
+            CanisterSnapshotDataOffset::WasmModule { offset },
+            chunk,
+        );
+    }
+    for offset in (0..wasm_memory_size).step_by(SLICE_SIZE as usize) {
+        let size = SLICE_SIZE.min(wasm_memory_size - offset);
+        let chunk = helper_read_snapshot_data(
+            &mut test,
+            canister_id,
+            snapshot_id,
+            CanisterSnapshotDataKind::MainMemory { offset, size },
+        );
+        helper_upload_snapshot_data(
+            &mut test,
+            canister_id,
+            uploaded_snapshot_id,
+            CanisterSnapshotDataOffset::MainMemory { offset },
+            chunk,
+        );
+    }
+    for offset in (0..stable_memory_size).step_by(SLICE_SIZE as usize) {
+        let size = SLICE_SIZE.min(stable_memory_size - offset);
+        let chunk = helper_read_snapshot_data(
+            &mut test,
+            canister_id,
+            snapshot_id,
+            CanisterSnapshotDataKind::StableMemory { offset, size },
+        );
+        helper_upload_snapshot_data(
+            &mut test,
+            canister_id,
+            uploaded_snapshot_id,
+            CanisterSnapshotDataOffset::StableMemory { offset },
+            chunk,
+        );
+    }
+    for hash in snapshot.chunk_store().keys() {
+        let chunk = helper_read_snapshot_data(
+            &mut test,
+            canister_id,
+            snapshot_id,
+            CanisterSnapshotDataKind::WasmChunk {
+                hash: hash.to_vec(),
+            },
+        );
+        assert_eq!(chunk, vec![1, 2, 3, 4, 5]);
+        helper_upload_snapshot_data(
+            &mut test,
+            canister_id,
+            uploaded_snapshot_id,
+            CanisterSnapshotDataOffset::WasmChunk,
+            chunk,
+        );
+    }
+
+    // The uploaded snapshot has the same contents as the original one.
+    let uploaded_snapshot = test
+        .state()
+        .canister_snapshots
+        .get(uploaded_snapshot_id)
+        .unwrap();
+    assert_eq!(
+        uploaded_snapshot.canister_module(),
+        snapshot.canister_module()
+    );
+    assert_eq!(
+        uploaded_snapshot.exported_globals(),
+        snapshot.exported_globals()
+    );
+    assert_eq!(
+        uploaded_snapshot.wasm_memory().size,
+        snapshot.wasm_memory().size
+    );
+    assert_eq!(
+        uploaded_snapshot.stable_memory().size,
+        snapshot.stable_memory().size
+    );
+    assert_eq!(uploaded_snapshot.chunk_store(), snapshot.chunk_store());
+    // Confirm that `snapshots_memory_usage` is updated correctly.
+    assert_eq!(
+        test.canister_state(canister_id)
+            .system_state
+            .snapshots_memory_usage,
+        test.state()
+            .canister_snapshots
+            .compute_memory_usage_by_canister(canister_id),
+    );
+
+    // Overwrite stable memory and load the uploaded snapshot.
+    test.ingress(
+        canister_id,
+        "update",
+        wasm().stable_write(0, b"modified").reply().build(),
+    )
+    .unwrap();
+    helper_load_snapshot(&mut test, canister_id, uploaded_snapshot_id);
+
+    let result = test
+        .ingress(
+            canister_id,
+            "update",
+            wasm().stable_read(0, 8).append_and_reply().build(),
+        )
+        .unwrap();
+    assert_eq!(result, WasmResult::Reply(b"snapshot".to_vec()));
+}
+
+#[test]
+fn upload_canister_snapshot_data_fails_when_out_of_bounds() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000);
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let canister_id = test
+        .canister_from_cycles_and_binary(CYCLES, UNIVERSAL_CANISTER_WASM.to_vec())
+        .unwrap();
+
+    let args = UploadCanisterSnapshotMetadataArgs::new(
+        canister_id,
+        None,
+        10,
+        vec![],
+        WASM_PAGE_SIZE,
+        0,
+        vec![],
+    );
+    let result = test
+        .subnet_message("upload_canister_snapshot_metadata", args.encode())
+        .unwrap();
+    let snapshot_id = UploadCanisterSnapshotMetadataResponse::decode(&result.bytes())
+        .unwrap()
+        .get_snapshot_id();
+
+    for (kind, chunk) in [
+        (
+            CanisterSnapshotDataOffset::WasmModule { offset: 8 },
+            vec![0; 3],
+        ),
+        (
+            CanisterSnapshotDataOffset::MainMemory {
+                offset: WASM_PAGE_SIZE,
+            },
+            vec![0; 1],
+        ),
+        (
+            CanisterSnapshotDataOffset::StableMemory { offset: 0 },
+            vec![0; 1],
+        ),
+        (
+            CanisterSnapshotDataOffset::MainMemory { offset: 0 },
+            vec![0; 2_000_001],
+        ),
+    ] {
+        let args = UploadCanisterSnapshotDataArgs::new(canister_id, snapshot_id, kind, chunk);
+        let err = test
+            .subnet_message("upload_canister_snapshot_data", args.encode())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidManagementPayload);
+    }
+
+    // Reading out of bounds fails as well.
+    let args = ReadCanisterSnapshotDataArgs::new(
+        canister_id,
+        snapshot_id,
+        CanisterSnapshotDataKind::WasmModule {
+            offset: 0,
+            size: 11,
+        },
+    );
+    let err = test
+        .subnet_message("read_canister_snapshot_data", args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidManagementPayload);
+}
+
+#[test]
+fn upload_canister_snapshot_metadata_fails_with_invalid_memory_size() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000);
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let canister_id = test
+        .canister_from_cycles_and_binary(CYCLES, UNIVERSAL_CANISTER_WASM.to_vec())
+        .unwrap();
+
+    // The memory size must be a multiple of the Wasm page size.
+    let args =
+        UploadCanisterSnapshotMetadataArgs::new(canister_id, None, 10, vec![], 1000, 0, vec![]);
+    let err = test
+        .subnet_message("upload_canister_snapshot_metadata", args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidManagementPayload);
+    assert_eq!(test.state().canister_snapshots.count(), 0);
+}
+
+#[test]
+fn upload_canister_snapshot_metadata_fails_when_sizes_exceed_limits() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000);
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let canister_id = test
+        .canister_from_cycles_and_binary(CYCLES, UNIVERSAL_CANISTER_WASM.to_vec())
+        .unwrap();
+
+    let config = EmbeddersConfig::default();
+    for (wasm_module_size, wasm_memory_size, stable_memory_size) in [
+        (config.wasm_max_size.get() + 1, 0, 0),
+        (10, config.max_wasm_memory_size.get() + WASM_PAGE_SIZE, 0),
+        (10, 0, config.max_stable_memory_size.get() + WASM_PAGE_SIZE),
+    ] {
+        let args = UploadCanisterSnapshotMetadataArgs::new(
+            canister_id,
+            None,
+            wasm_module_size,
+            vec![],
+            wasm_memory_size,
+            stable_memory_size,
+            vec![],
+        );
+        let err = test
+            .subnet_message("upload_canister_snapshot_metadata", args.encode())
+            .unwrap_err();
+        assert_eq!(err.code(), ErrorCode::InvalidManagementPayload);
+    }
+    assert_eq!(test.state().canister_snapshots.count(), 0);
+}
+
+#[test]
+fn upload_canister_snapshot_data_fails_for_taken_snapshot() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000);
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let canister_id = test
+        .canister_from_cycles_and_binary(CYCLES, UNIVERSAL_CANISTER_WASM.to_vec())
+        .unwrap();
+    let (snapshot_id, _) = helper_take_snapshot(&mut test, canister_id);
+    let snapshot = test
+        .state()
+        .canister_snapshots
+        .get(snapshot_id)
+        .unwrap()
+        .clone();
+
+    let args = UploadCanisterSnapshotDataArgs::new(
+        canister_id,
+        snapshot_id,
+        CanisterSnapshotDataOffset::WasmModule { offset: 0 },
+        vec![0; 8],
+    );
+    let err = test
+        .subnet_message("upload_canister_snapshot_data", args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidManagementPayload);
+    // The snapshot is not modified.
+    assert_eq!(
+        test.state()
+            .canister_snapshots
+            .get(snapshot_id)
+            .unwrap()
+            .canister_module(),
+        snapshot.canister_module()
+    );
+}
+
+#[test]
+fn read_uploaded_canister_snapshot_module_is_zero_padded() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000);
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let canister_id = test
+        .canister_from_cycles_and_binary(CYCLES, UNIVERSAL_CANISTER_WASM.to_vec())
+        .unwrap();
+
+    let args = UploadCanisterSnapshotMetadataArgs::new(canister_id, None, 10, vec![], 0, 0, vec![]);
+    let result = test
+        .subnet_message("upload_canister_snapshot_metadata", args.encode())
+        .unwrap();
+    let snapshot_id = UploadCanisterSnapshotMetadataResponse::decode(&result.bytes())
+        .unwrap()
+        .get_snapshot_id();
+    helper_upload_snapshot_data(
+        &mut test,
+        canister_id,
+        snapshot_id,
+        CanisterSnapshotDataOffset::WasmModule { offset: 2 },
+        vec![1, 2, 3],
+    );
+
+    let chunk = helper_read_snapshot_data(
+        &mut test,
+        canister_id,
+        snapshot_id,
+        CanisterSnapshotDataKind::WasmModule {
+            offset: 0,
+            size: 10,
+        },
+    );
+    assert_eq!(chunk, vec![0, 0, 1, 2, 3, 0, 0, 0, 0, 0]);
+}
+
+#[test]
+fn load_uploaded_canister_snapshot_fails_with_mismatching_globals() {
+    const CYCLES: Cycles = Cycles::new(1_000_000_000_000);
+    let mut test = ExecutionTestBuilder::new().build();
+
+    let canister_id = test
+        .canister_from_cycles_and_binary(CYCLES, UNIVERSAL_CANISTER_WASM.to_vec())
+        .unwrap();
+
+    // Upload a snapshot with a valid Wasm module, but without any globals.
+    let args = UploadCanisterSnapshotMetadataArgs::new(
+        canister_id,
+        None,
+        UNIVERSAL_CANISTER_WASM.len() as u64,
+        vec![],
+        0,
+        0,
+        vec![],
+    );
+    let result = test
+        .subnet_message("upload_canister_snapshot_metadata", args.encode())
+        .unwrap();
+    let snapshot_id = UploadCanisterSnapshotMetadataResponse::decode(&result.bytes())
+        .unwrap()
+        .get_snapshot_id();
+    helper_upload_snapshot_data(
+        &mut test,
+        canister_id,
+        snapshot_id,
+        CanisterSnapshotDataOffset::WasmModule { offset: 0 },
+        UNIVERSAL_CANISTER_WASM.to_vec(),
+    );
+
+    let args = LoadCanisterSnapshotArgs::new(canister_id, snapshot_id, None);
+    let err = test
+        .subnet_message("load_canister_snapshot", args.encode())
+        .unwrap_err();
+    assert_eq!(err.code(), ErrorCode::InvalidManagementPayload);
+}
+
 /// Early warning system / stumbling block forcing the authors of changes adding
 /// or removing canister state fields to think about and/or ask the Execution
 /// team to think about any repercussions to the canister snapshot logic.
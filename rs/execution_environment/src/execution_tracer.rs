@@ -0,0 +1,126 @@
+use ic_replicated_state::CanisterState;
+use ic_types::{
+    messages::{CallbackId, CanisterMessage, CanisterMessageOrTask, MessageId},
+    CanisterId, NumInstructions,
+};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+/// A message (or task) executed by a canister.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum ExecutedMessage {
+    /// An ingress message.
+    Ingress {
+        message_id: MessageId,
+        method_name: String,
+    },
+    /// A query call made by a user (executed in non-replicated mode).
+    UserQuery { method_name: String },
+    /// A call made by another canister (or by the same canister).
+    Request {
+        sender: CanisterId,
+        sender_reply_callback: CallbackId,
+        method_name: String,
+    },
+    /// A response to a call made by the canister.
+    Response {
+        originator_reply_callback: CallbackId,
+    },
+    /// A heartbeat, a global timer, or another system task.
+    Task,
+}
+
+impl From<&CanisterMessageOrTask> for ExecutedMessage {
+    fn from(input: &CanisterMessageOrTask) -> Self {
+        match input {
+            CanisterMessageOrTask::Message(CanisterMessage::Ingress(ingress)) => Self::Ingress {
+                message_id: ingress.message_id.clone(),
+                method_name: ingress.method_name.clone(),
+            },
+            CanisterMessageOrTask::Message(CanisterMessage::Request(request)) => Self::Request {
+                sender: request.sender,
+                sender_reply_callback: request.sender_reply_callback,
+                method_name: request.method_name.clone(),
+            },
+            CanisterMessageOrTask::Message(CanisterMessage::Response(response)) => Self::Response {
+                originator_reply_callback: response.originator_reply_callback,
+            },
+            CanisterMessageOrTask::Task(_) => Self::Task,
+        }
+    }
+}
+
+/// A single execution (or a single slice of a long execution)
+/// of a message by a canister.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TracedExecution {
+    pub canister_id: CanisterId,
+    pub message: ExecutedMessage,
+    pub instructions_used: NumInstructions,
+    /// The callbacks registered by the execution, i.e., one callback
+    /// per call made by the canister while executing the message.
+    pub callbacks: Vec<CallbackId>,
+}
+
+/// Traces the executions of canister messages in replicated and non-replicated
+/// (query) mode outside of the replicated state.
+///
+/// Tracing is disabled by default and only meant to be enabled in tests,
+/// e.g., to report the execution costs of a call in PocketIC.
+#[derive(Default)]
+pub struct ExecutionTracer {
+    enabled: AtomicBool,
+    executions: Mutex<Vec<TracedExecution>>,
+}
+
+impl ExecutionTracer {
+    /// Starts tracing executions.
+    pub fn start(&self) {
+        self.executions.lock().unwrap().clear();
+        self.enabled.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops tracing executions and returns the executions traced
+    /// since tracing was started in the order in which they finished.
+    pub fn stop(&self) -> Vec<TracedExecution> {
+        self.enabled.store(false, Ordering::SeqCst);
+        std::mem::take(&mut *self.executions.lock().unwrap())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Traces an execution of the given message by the given canister.
+    /// The callbacks registered by the execution are those with IDs starting
+    /// at `next_callback_id_before`, i.e., the next callback ID
+    /// of the canister before the execution.
+    pub(crate) fn trace(
+        &self,
+        canister: &CanisterState,
+        message: ExecutedMessage,
+        instructions_used: NumInstructions,
+        next_callback_id_before: u64,
+    ) {
+        let callbacks = (next_callback_id_before..next_callback_id(canister))
+            .map(CallbackId::from)
+            .collect();
+        self.executions.lock().unwrap().push(TracedExecution {
+            canister_id: canister.canister_id(),
+            message,
+            instructions_used,
+            callbacks,
+        });
+    }
+}
+
+/// Returns the ID of the next callback to be registered by the given canister.
+pub(crate) fn next_callback_id(canister: &CanisterState) -> u64 {
+    canister
+        .system_state
+        .call_context_manager()
+        .map(|call_context_manager| call_context_manager.next_callback_id())
+        .unwrap_or_default()
+}
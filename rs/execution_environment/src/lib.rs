@@ -4,6 +4,7 @@ mod canister_settings;
 pub mod execution;
 mod execution_environment;
 mod execution_environment_metrics;
+mod execution_tracer;
 mod history;
 mod hypervisor;
 mod ic00_permissions;
@@ -19,6 +20,7 @@ pub use execution_environment::{
     as_num_instructions, as_round_instructions, execute_canister, CompilationCostHandling,
     ExecuteMessageResult, ExecutionEnvironment, ExecutionResponse, RoundInstructions, RoundLimits,
 };
+pub use execution_tracer::{ExecutedMessage, ExecutionTracer, TracedExecution};
 pub use history::{IngressHistoryReaderImpl, IngressHistoryWriterImpl};
 pub use hypervisor::{Hypervisor, HypervisorMetrics};
 use ic_base_types::PrincipalId;
@@ -87,6 +89,7 @@ pub struct ExecutionServices {
     pub https_outcalls_service: QueryExecutionService,
     pub scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
     pub query_stats_payload_builder: QueryStatsPayloadBuilderParams,
+    pub execution_tracer: Arc<ExecutionTracer>,
 }
 
 impl ExecutionServices {
@@ -157,6 +160,7 @@ impl ExecutionServices {
             scheduler_config.max_instructions_per_message_without_dts,
             Arc::clone(&cycles_account_manager),
             query_stats_collector,
+            Arc::clone(exec_env.execution_tracer()),
         ));
 
         let query_scheduler = QueryScheduler::new(
@@ -214,6 +218,7 @@ impl ExecutionServices {
             https_outcalls_service,
             scheduler,
             query_stats_payload_builder,
+            execution_tracer: Arc::clone(exec_env.execution_tracer()),
         }
     }
 
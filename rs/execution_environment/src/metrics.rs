@@ -17,6 +17,7 @@ pub(crate) const SYSTEM_API_DATA_CERTIFICATE_COPY: &str = "data_certificate_copy
 pub(crate) const SYSTEM_API_CANISTER_CYCLE_BALANCE: &str = "canister_cycle_balance";
 pub(crate) const SYSTEM_API_CANISTER_CYCLE_BALANCE128: &str = "canister_cycle_balance128";
 pub(crate) const SYSTEM_API_TIME: &str = "time";
+pub(crate) const SYSTEM_API_SET_QUERY_CACHE_MAX_AGE: &str = "set_query_cache_max_age";
 
 const LABEL_CLASS: &str = "class";
 const LABEL_VALUE_BEST_EFFORT: &str = "best_effort";
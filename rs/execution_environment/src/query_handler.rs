@@ -51,12 +51,20 @@ use tower::{util::BoxCloneService, Service};
 
 pub(crate) use self::query_scheduler::{QueryScheduler, QuerySchedulerFlag};
 use ic_management_canister_types_private::{
-    FetchCanisterLogsRequest, FetchCanisterLogsResponse, LogVisibilityV2, Payload, QueryMethod,
+    CanisterLogContentFilter, CanisterLogFilter, CanisterLogRecord, FetchCanisterLogsRequest,
+    FetchCanisterLogsResponse, LogVisibilityV2, Payload, QueryMethod,
 };
+use regex::bytes::{Regex, RegexBuilder};
 
 const DISTRIKT_SUBNET_PRINCIPAL: &str =
     "shefu-t3kr5-t5q3w-mqmdq-jabyv-vyvtf-cyyey-3kmo4-toyln-emubw-4qe";
 
+/// The maximum length of a regex used to filter canister log records.
+const MAX_CANISTER_LOG_REGEX_LEN: usize = 1024;
+
+/// The maximum size of the compiled regex used to filter canister log records.
+const MAX_CANISTER_LOG_REGEX_SIZE: usize = 1 << 20;
+
 /// Convert an object into CBOR binary.
 fn into_cbor<R: Serialize>(r: &R) -> Vec<u8> {
     let mut ser = serde_cbor::Serializer::new(Vec::new());
@@ -111,6 +119,7 @@ pub struct InternalHttpQueryHandler {
     cycles_account_manager: Arc<CyclesAccountManager>,
     local_query_execution_stats: QueryStatsCollector,
     query_cache: query_cache::QueryCache,
+    execution_tracer: Arc<ExecutionTracer>,
 }
 
 #[derive(Clone)]
@@ -153,6 +162,7 @@ impl InternalHttpQueryHandler {
         max_instructions_per_query: NumInstructions,
         cycles_account_manager: Arc<CyclesAccountManager>,
         local_query_execution_stats: QueryStatsCollector,
+        execution_tracer: Arc<ExecutionTracer>,
     ) -> Self {
         let query_cache_capacity = config.query_cache_capacity;
         let query_max_expiry_time = config.query_cache_max_expiry_time;
@@ -173,6 +183,7 @@ impl InternalHttpQueryHandler {
                 query_max_expiry_time,
                 query_data_certificate_expiry_time,
             ),
+            execution_tracer,
         }
     }
 
@@ -239,7 +250,11 @@ impl InternalHttpQueryHandler {
         // Check the query cache first (if the query caching is enabled).
         // If a valid cache entry found, the result will be immediately returned.
         // Otherwise, the key will be kept for the `push` below.
-        let cache_entry_key = if self.config.query_caching == FlagStatus::Enabled {
+        // The query cache is bypassed while executions are traced
+        // since cached results would not be traced.
+        let cache_entry_key = if self.config.query_caching == FlagStatus::Enabled
+            && !self.execution_tracer.is_enabled()
+        {
             let key = query_cache::EntryKey::from(&query);
             let state = state.get_ref().as_ref();
             if let Some(result) =
@@ -286,6 +301,7 @@ impl InternalHttpQueryHandler {
             &self.metrics.query_critical_error,
             query_stats_collector,
             Arc::clone(&self.cycles_account_manager),
+            &self.execution_tracer,
         );
 
         let result = context.run(query, &self.metrics, &measurement_scope);
@@ -300,7 +316,7 @@ impl InternalHttpQueryHandler {
             let stats = context.evaluated_canister_stats();
             let errors = context.transient_errors();
             self.query_cache
-                .push(key, &result, state, counters, stats, errors);
+                .push(key, &result, state, &counters, stats, errors);
         }
         result
     }
@@ -334,18 +350,100 @@ fn fetch_canister_logs(
         )),
     }?;
 
+    let filter = LogRecordFilter::new(args.filter.unwrap_or_default())?;
+    let page_size = match args.page_size {
+        Some(0) => {
+            return Err(UserError::new(
+                ErrorCode::InvalidManagementPayload,
+                "Page size of fetch_canister_logs must be positive",
+            ))
+        }
+        Some(page_size) => usize::try_from(page_size).unwrap_or(usize::MAX),
+        None => usize::MAX,
+    };
+
+    let mut matching_records = canister
+        .system_state
+        .canister_log
+        .records()
+        .iter()
+        .filter(|record| filter.matches(record));
+    let canister_log_records: Vec<_> = matching_records.by_ref().take(page_size).cloned().collect();
     let response = FetchCanisterLogsResponse {
-        canister_log_records: canister
-            .system_state
-            .canister_log
-            .records()
-            .iter()
-            .cloned()
-            .collect(),
+        canister_log_records,
+        next_idx: matching_records.next().map(|record| record.idx),
     };
     Ok(WasmResult::Reply(Encode!(&response).unwrap()))
 }
 
+/// The filter of `fetch_canister_logs` with the content filter prepared for
+/// matching.
+struct LogRecordFilter {
+    filter: CanisterLogFilter,
+    content: Option<ContentMatcher>,
+}
+
+enum ContentMatcher {
+    Substring(Vec<u8>),
+    Regex(Regex),
+}
+
+impl LogRecordFilter {
+    fn new(filter: CanisterLogFilter) -> Result<Self, UserError> {
+        let content = match &filter.content {
+            None => None,
+            Some(CanisterLogContentFilter::Substring(substring)) => {
+                Some(ContentMatcher::Substring(substring.as_bytes().to_vec()))
+            }
+            Some(CanisterLogContentFilter::Regex(pattern)) => {
+                if pattern.len() > MAX_CANISTER_LOG_REGEX_LEN {
+                    return Err(UserError::new(
+                        ErrorCode::InvalidManagementPayload,
+                        format!(
+                            "Content regex of fetch_canister_logs is {} bytes long, \
+                            exceeding the maximum of {} bytes",
+                            pattern.len(),
+                            MAX_CANISTER_LOG_REGEX_LEN
+                        ),
+                    ));
+                }
+                let regex = RegexBuilder::new(pattern)
+                    .size_limit(MAX_CANISTER_LOG_REGEX_SIZE)
+                    .build()
+                    .map_err(|err| {
+                        UserError::new(
+                            ErrorCode::InvalidManagementPayload,
+                            format!("Invalid content regex of fetch_canister_logs: {err}"),
+                        )
+                    })?;
+                Some(ContentMatcher::Regex(regex))
+            }
+        };
+        Ok(Self { filter, content })
+    }
+
+    fn matches(&self, record: &CanisterLogRecord) -> bool {
+        self.filter
+            .idx
+            .is_none_or(|range| range.contains(record.idx))
+            && self
+                .filter
+                .timestamp_nanos
+                .is_none_or(|range| range.contains(record.timestamp_nanos))
+            && match &self.content {
+                None => true,
+                Some(ContentMatcher::Substring(substring)) => {
+                    substring.is_empty()
+                        || record
+                            .content
+                            .windows(substring.len())
+                            .any(|window| window == substring.as_slice())
+                }
+                Some(ContentMatcher::Regex(regex)) => regex.is_match(&record.content),
+            }
+    }
+}
+
 impl HttpQueryHandler {
     pub(crate) fn new_service(
         internal: Arc<InternalHttpQueryHandler>,
@@ -25,6 +25,7 @@ pub(crate) struct QueryCacheMetrics {
     pub hits: IntCounter,
     pub hits_with_ignored_time: IntCounter,
     pub hits_with_ignored_canister_balance: IntCounter,
+    pub hits_with_max_age: IntCounter,
     pub misses: IntCounter,
     pub evicted_entries: IntCounter,
     pub evicted_entries_duration: Histogram,
@@ -57,6 +58,11 @@ impl QueryCacheMetrics {
                 "execution_query_cache_hits_with_ignored_canister_balance_total",
                 "The total number of cache hits into entries with ignored canister balance",
             ),
+            hits_with_max_age: metrics_registry.int_counter(
+                "execution_query_cache_hits_with_max_age_total",
+                "The total number of cache hits into entries with ignored canister \
+                        version or balance changes due to the query cache max age",
+            ),
             misses: metrics_registry.int_counter(
                 "execution_query_cache_misses_total",
                 "The total number of replica side query cache misses",
@@ -168,7 +174,8 @@ impl From<&Query> for EntryKey {
 /// Query Cache entry environment metadata captured before the query execution.
 ///
 /// The cache entry is valid as long as the metadata is unchanged,
-/// or it can be proven that the query does not depend on the change.
+/// or it can be proven that the query does not depend on the change,
+/// or the query allowed to ignore the change using `ic0.set_query_cache_max_age()`.
 #[derive(PartialEq)]
 pub(crate) struct EntryEnv {
     /// The consensus-determined time when the query is executed.
@@ -213,6 +220,9 @@ pub(crate) struct EntryValue {
     ignore_batch_time: bool,
     /// If set, the canister balance changes might be ignored.
     ignore_canister_balances: bool,
+    /// If set, the canister version and balance changes might be ignored
+    /// for up to `max_age` after the query execution.
+    max_age: Option<Duration>,
 }
 
 impl MemoryDiskBytes for EntryValue {
@@ -238,12 +248,15 @@ impl EntryValue {
         // It's safe to ignore `canister_balance` changes if the query never checks the balance.
         let ignore_canister_balances = system_api_call_counters.canister_cycle_balance == 0
             && system_api_call_counters.canister_cycle_balance128 == 0;
+        // The query might allow its result to be stale for up to `max_age`.
+        let max_age = system_api_call_counters.query_cache_max_age;
         EntryValue {
             env,
             result,
             includes_data_certificate,
             ignore_batch_time,
             ignore_canister_balances,
+            max_age,
         }
     }
 
@@ -280,13 +293,17 @@ impl EntryValue {
         let is_expired = self.is_expired(now, max_expiry_time);
         let is_expired_data_certificate =
             self.is_expired_data_certificate(now, data_certificate_expiry_time);
+        let is_within_max_age = self.is_within_max_age(now);
+        let canister_versions_are_valid = all_canister_versions_are_valid || is_within_max_age;
+        let canister_balances_are_valid =
+            all_canister_balances_are_valid || self.ignore_canister_balances || is_within_max_age;
 
         // Check if the cache entry value is valid.
         if !is_expired
             && !is_expired_data_certificate
             && (self.env.batch_time == now || self.ignore_batch_time)
-            && all_canister_versions_are_valid
-            && (all_canister_balances_are_valid || self.ignore_canister_balances)
+            && canister_versions_are_valid
+            && canister_balances_are_valid
         {
             // The value is still valid.
             metrics.hits.inc();
@@ -305,6 +322,11 @@ impl EntryValue {
             if !all_canister_balances_are_valid && self.ignore_canister_balances {
                 metrics.hits_with_ignored_canister_balance.inc();
             }
+            if !all_canister_versions_are_valid
+                || !(all_canister_balances_are_valid || self.ignore_canister_balances)
+            {
+                metrics.hits_with_max_age.inc();
+            }
             true
         } else {
             // The value is invalid.
@@ -325,10 +347,10 @@ impl EntryValue {
             if !(self.env.batch_time == now || self.ignore_batch_time) {
                 metrics.invalidated_entries_by_time.inc();
             }
-            if !all_canister_versions_are_valid {
+            if !canister_versions_are_valid {
                 metrics.invalidated_entries_by_canister_version.inc();
             }
-            if !(all_canister_balances_are_valid || self.ignore_canister_balances) {
+            if !canister_balances_are_valid {
                 metrics.invalidated_entries_by_canister_balance.inc();
             }
             false
@@ -365,6 +387,19 @@ impl EntryValue {
         false
     }
 
+    /// Check whether the cache entry is still within the max age set by every
+    /// evaluated canister using the `ic0.set_query_cache_max_age()` System API call.
+    ///
+    /// Within the max age, the cached result is returned even if the evaluated
+    /// canisters changed their versions or balances. The entry is still subject
+    /// to all the other expiration and invalidation rules.
+    fn is_within_max_age(&self, now: Time) -> bool {
+        match self.max_age {
+            Some(max_age) => now.saturating_duration_since(self.env.batch_time) <= max_age,
+            None => false,
+        }
+    }
+
     fn elapsed_seconds(&self, now: Time) -> f64 {
         now.saturating_duration_since(self.env.batch_time)
             .as_secs_f64()
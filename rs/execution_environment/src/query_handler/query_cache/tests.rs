@@ -26,6 +26,8 @@ const MAX_EXPIRY_TIME: Duration = Duration::from_secs(10);
 const MORE_THAN_MAX_EXPIRY_TIME: Duration = Duration::from_secs(11);
 const DATA_CERTIFICATE_EXPIRY_TIME: Duration = Duration::from_secs(2);
 const MORE_THAN_DATA_CERTIFICATE_EXPIRY_TIME: Duration = Duration::from_secs(3);
+const MAX_AGE: Duration = Duration::from_secs(5);
+const MORE_THAN_MAX_AGE: Duration = Duration::from_secs(6);
 const ITERATIONS: usize = 5;
 const REPLY_SIZE: usize = 10_000;
 const BIG_REPLY_SIZE: usize = 1_000_000;
@@ -58,6 +60,41 @@ const QUERY_CACHE_WAT: &str = r#"
     (export "canister_query f2" (func $f))
 )"#;
 
+/// The max ages below must be in sync with `MAX_AGE` and `MAX_EXPIRY_TIME`.
+const QUERY_CACHE_MAX_AGE_WAT: &str = r#"
+(module
+    (import "ic0" "msg_reply" (func $msg_reply))
+    (import "ic0" "msg_reply_data_append"
+        (func $msg_reply_data_append (param i32 i32)))
+    (import "ic0" "canister_cycle_balance" (func $canister_cycle_balance (result i64)))
+    (import "ic0" "set_query_cache_max_age" (func $set_query_cache_max_age (param i64)))
+
+    (memory 1)
+    (data (i32.const 0) "42")
+
+    (func $reply
+        (call $msg_reply_data_append (i32.const 0) (i32.const 2))
+        (call $msg_reply)
+    )
+
+    (func (export "canister_query max_age")
+        (call $set_query_cache_max_age (i64.const 7))
+        (call $set_query_cache_max_age (i64.const 5))
+        (call $reply)
+    )
+
+    (func (export "canister_query max_age_balance")
+        (call $set_query_cache_max_age (i64.const 5))
+        (drop (call $canister_cycle_balance))
+        (call $reply)
+    )
+
+    (func (export "canister_query max_age_above_max_expiry_time")
+        (call $set_query_cache_max_age (i64.const 1000))
+        (call $reply)
+    )
+)"#;
+
 fn downcast_query_handler(query_handler: &dyn std::any::Any) -> &InternalHttpQueryHandler {
     // SAFETY:
     //
@@ -747,6 +784,157 @@ fn query_cache_returns_different_results_on_combined_invalidation() {
     });
 }
 
+#[test]
+fn query_cache_ignores_canister_version_changes_within_max_age() {
+    let mut test = builder_with_query_cache_expiry_times().build();
+    let id = test.canister_from_wat(QUERY_CACHE_MAX_AGE_WAT).unwrap();
+
+    let res_1 = test.non_replicated_query(id, "max_age", vec![]);
+    assert_eq!(res_1, Ok(WasmResult::Reply(b"42".to_vec())));
+    let m = &query_handler(&test).metrics.query_system_api_calls;
+    assert_eq!(
+        2,
+        m.with_label_values(&[metrics::SYSTEM_API_SET_QUERY_CACHE_MAX_AGE])
+            .get()
+    );
+
+    // Bump up the version and change the time within the max age.
+    test.canister_state_mut(id).system_state.canister_version += 1;
+    test.state_mut().metadata.batch_time += MAX_AGE;
+
+    let res_2 = test.non_replicated_query(id, "max_age", vec![]);
+    let m = query_cache_metrics(&test);
+    assert_eq!(1, m.misses.get());
+    assert_eq!(1, m.hits.get());
+    assert_eq!(1, m.hits_with_max_age.get());
+    assert_eq!(0, m.invalidated_entries.get());
+    assert_eq!(res_1, res_2);
+}
+
+#[test]
+fn query_cache_ignores_canister_balance_changes_within_max_age() {
+    let mut test = builder_with_query_cache_expiry_times().build();
+    let id = test.canister_from_wat(QUERY_CACHE_MAX_AGE_WAT).unwrap();
+
+    let res_1 = test.non_replicated_query(id, "max_age_balance", vec![]);
+    assert_eq!(res_1, Ok(WasmResult::Reply(b"42".to_vec())));
+
+    // Change the canister balance.
+    test.canister_state_mut(id)
+        .system_state
+        .remove_cycles(1_u64.into(), CyclesUseCase::Memory);
+
+    let res_2 = test.non_replicated_query(id, "max_age_balance", vec![]);
+    let m = query_cache_metrics(&test);
+    assert_eq!(1, m.misses.get());
+    assert_eq!(1, m.hits.get());
+    assert_eq!(0, m.hits_with_ignored_canister_balance.get());
+    assert_eq!(1, m.hits_with_max_age.get());
+    assert_eq!(res_1, res_2);
+}
+
+#[test]
+fn query_cache_returns_different_results_for_different_canister_versions_after_max_age() {
+    let mut test = builder_with_query_cache_expiry_times().build();
+    let id = test.canister_from_wat(QUERY_CACHE_MAX_AGE_WAT).unwrap();
+
+    let res_1 = test.non_replicated_query(id, "max_age", vec![]);
+    assert_eq!(res_1, Ok(WasmResult::Reply(b"42".to_vec())));
+
+    // The time change alone does not invalidate the entry.
+    test.state_mut().metadata.batch_time += MORE_THAN_MAX_AGE;
+    let res_2 = test.non_replicated_query(id, "max_age", vec![]);
+    let m = query_cache_metrics(&test);
+    assert_eq!(1, m.misses.get());
+    assert_eq!(1, m.hits.get());
+    assert_eq!(0, m.hits_with_max_age.get());
+    assert_eq!(res_1, res_2);
+
+    // Bump up the version after the max age.
+    test.canister_state_mut(id).system_state.canister_version += 1;
+
+    let res_3 = test.non_replicated_query(id, "max_age", vec![]);
+    let m = query_cache_metrics(&test);
+    assert_eq!(2, m.misses.get());
+    assert_eq!(1, m.hits.get());
+    assert_eq!(0, m.hits_with_max_age.get());
+    assert_eq!(1, m.invalidated_entries.get());
+    assert_eq!(1, m.invalidated_entries_by_canister_version.get());
+    assert_eq!(res_1, res_3);
+}
+
+#[test]
+fn query_cache_max_age_does_not_extend_max_expiry_time() {
+    let mut test = builder_with_query_cache_expiry_times().build();
+    let id = test.canister_from_wat(QUERY_CACHE_MAX_AGE_WAT).unwrap();
+
+    let res_1 = test.non_replicated_query(id, "max_age_above_max_expiry_time", vec![]);
+    assert_eq!(res_1, Ok(WasmResult::Reply(b"42".to_vec())));
+
+    test.state_mut().metadata.batch_time += MORE_THAN_MAX_EXPIRY_TIME;
+
+    let res_2 = test.non_replicated_query(id, "max_age_above_max_expiry_time", vec![]);
+    let m = query_cache_metrics(&test);
+    assert_eq!(2, m.misses.get());
+    assert_eq!(0, m.hits.get());
+    assert_eq!(1, m.invalidated_entries.get());
+    assert_eq!(1, m.invalidated_entries_by_max_expiry_time.get());
+    assert_eq!(0, m.invalidated_entries_by_canister_version.get());
+    assert_eq!(res_1, res_2);
+}
+
+#[test]
+fn query_cache_entry_value_uses_smallest_max_age() {
+    let current_time = time::GENESIS;
+    let mut counters = SystemApiCallCounters {
+        query_cache_max_age: Some(Duration::from_secs(7)),
+        ..Default::default()
+    };
+    counters.saturating_add(SystemApiCallCounters {
+        query_cache_max_age: Some(MAX_AGE),
+        ..Default::default()
+    });
+    let entry_value = EntryValue::new(
+        EntryEnv {
+            batch_time: current_time,
+            canisters_versions_balances_stats: vec![],
+        },
+        Result::Ok(WasmResult::Reply(vec![])),
+        &counters,
+    );
+    assert!(entry_value.is_within_max_age(current_time + MAX_AGE));
+    assert!(!entry_value.is_within_max_age(current_time + MORE_THAN_MAX_AGE));
+
+    // Counters without a max age drop the max age.
+    counters.saturating_add(SystemApiCallCounters::default());
+    assert_eq!(counters.query_cache_max_age, None);
+}
+
+#[test]
+fn composite_query_cache_ignores_max_age_set_only_by_callee() {
+    let mut test = builder_with_query_cache_expiry_times().build();
+    let a_id = test.universal_canister().unwrap();
+    let b_id = test.canister_from_wat(QUERY_CACHE_MAX_AGE_WAT).unwrap();
+    // Only the callee canister B sets the max age.
+    let a = wasm().call_simple(b_id, "max_age", call_args()).build();
+
+    let res_1 = test.non_replicated_query(a_id, "composite_query", a.clone());
+    assert_eq!(res_1, Ok(WasmResult::Reply(b"42".to_vec())));
+
+    // Bump up the caller version and change the time within the callee max age.
+    test.canister_state_mut(a_id).system_state.canister_version += 1;
+    test.state_mut().metadata.batch_time += MAX_AGE;
+
+    let res_2 = test.non_replicated_query(a_id, "composite_query", a);
+    let m = query_cache_metrics(&test);
+    assert_eq!(2, m.misses.get());
+    assert_eq!(0, m.hits.get());
+    assert_eq!(0, m.hits_with_max_age.get());
+    assert_eq!(1, m.invalidated_entries.get());
+    assert_eq!(1, m.invalidated_entries_by_canister_version.get());
+    assert_eq!(res_1, res_2);
+}
+
 #[test]
 fn query_cache_frees_memory_after_invalidated_entries() {
     static BIG_RESPONSE_SIZE: usize = 1_000_000;
@@ -1491,6 +1679,12 @@ fn query_cache_future_proof_test() {
         | SystemApiCallId::DataCertificatePresent
         | SystemApiCallId::DataCertificateSize
         | SystemApiCallId::DebugPrint
+        | SystemApiCallId::EnvVarCount
+        | SystemApiCallId::EnvVarNameCopy
+        | SystemApiCallId::EnvVarNameExists
+        | SystemApiCallId::EnvVarNameSize
+        | SystemApiCallId::EnvVarValueCopy
+        | SystemApiCallId::EnvVarValueSize
         | SystemApiCallId::GlobalTimerSet
         | SystemApiCallId::InReplicatedExecution
         | SystemApiCallId::IsController
@@ -1517,6 +1711,7 @@ fn query_cache_future_proof_test() {
         | SystemApiCallId::MsgReplyDataAppend
         | SystemApiCallId::OutOfInstructions
         | SystemApiCallId::PerformanceCounter
+        | SystemApiCallId::SetQueryCacheMaxAge
         | SystemApiCallId::SubnetSelfSize
         | SystemApiCallId::SubnetSelfCopy
         | SystemApiCallId::Stable64Grow
@@ -1545,6 +1740,8 @@ fn query_cache_future_proof_test() {
             //   call dependent on canister balance.
             // * Changes in `canister_version` always invalidate cache entries.
             //   This includes update calls, configuration changes, upgrades...
+            //   The only exception is `ic0.set_query_cache_max_age()`, with which
+            //   the query explicitly allows a stale result for a limited time.
             //
             // If you introduce a new System API call that depends on
             // time or balance or a new Canister property that should
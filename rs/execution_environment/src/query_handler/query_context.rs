@@ -3,11 +3,12 @@ use crate::{
     execution::common::{self, validate_method},
     execution::nonreplicated_query::execute_non_replicated_query,
     execution_environment::{as_round_instructions, RoundLimits},
+    execution_tracer::{next_callback_id, ExecutedMessage, ExecutionTracer},
     hypervisor::Hypervisor,
     metrics::{
         CallTreeMetricsNoOp, MeasurementScope, QueryHandlerMetrics, QUERY_HANDLER_CRITICAL_ERROR,
         SYSTEM_API_CANISTER_CYCLE_BALANCE, SYSTEM_API_CANISTER_CYCLE_BALANCE128,
-        SYSTEM_API_DATA_CERTIFICATE_COPY, SYSTEM_API_TIME,
+        SYSTEM_API_DATA_CERTIFICATE_COPY, SYSTEM_API_SET_QUERY_CACHE_MAX_AGE, SYSTEM_API_TIME,
     },
     NonReplicatedQueryKind, RoundInstructions,
 };
@@ -112,12 +113,16 @@ pub(super) struct QueryContext<'a> {
     local_query_execution_stats: Option<&'a QueryStatsCollector>,
     /// How many times each tracked System API call was invoked during the query execution.
     system_api_call_counters: SystemApiCallCounters,
+    /// The smallest max age set by `ic0.set_query_cache_max_age()` for each evaluated
+    /// canister that set one.
+    query_cache_max_ages: BTreeMap<CanisterId, Duration>,
     /// A map of canister IDs evaluated and executed at least once in this query context
     /// with their stats. The information is used by the query cache for composite queries.
     evaluated_canister_stats: BTreeMap<CanisterId, QueryStats>,
     /// The number of transient errors.
     transient_errors: usize,
     cycles_account_manager: Arc<CyclesAccountManager>,
+    execution_tracer: &'a ExecutionTracer,
 }
 
 impl<'a> QueryContext<'a> {
@@ -144,6 +149,7 @@ impl<'a> QueryContext<'a> {
         query_critical_error: &'a IntCounter,
         local_query_execution_stats: Option<&'a QueryStatsCollector>,
         cycles_account_manager: Arc<CyclesAccountManager>,
+        execution_tracer: &'a ExecutionTracer,
     ) -> Self {
         let network_topology = Arc::new(state.get_ref().metadata.network_topology.clone());
         let round_limits = RoundLimits {
@@ -176,11 +182,13 @@ impl<'a> QueryContext<'a> {
             query_critical_error,
             local_query_execution_stats,
             system_api_call_counters: SystemApiCallCounters::default(),
+            query_cache_max_ages: BTreeMap::new(),
             // If the `context.run()` returns an error and hence the empty evaluated IDs set,
             // the original canister ID should always be tracked for changes.
             evaluated_canister_stats: BTreeMap::from([(canister_id, QueryStats::default())]),
             transient_errors: 0,
             cycles_account_manager,
+            execution_tracer,
         }
     }
 
@@ -434,6 +442,22 @@ impl<'a> QueryContext<'a> {
         let execution_parameters = self.execution_parameters(&canister, instruction_limits);
 
         let data_certificate = self.get_data_certificate(&canister.canister_id());
+        let traced_message = self
+            .execution_tracer
+            .is_enabled()
+            .then(|| match &query_kind {
+                NonReplicatedQueryKind::Stateful {
+                    call_origin: CallOrigin::CanisterQuery(sender, sender_reply_callback),
+                } => ExecutedMessage::Request {
+                    sender: *sender,
+                    sender_reply_callback: *sender_reply_callback,
+                    method_name: method_name.name(),
+                },
+                _ => ExecutedMessage::UserQuery {
+                    method_name: method_name.name(),
+                },
+            });
+        let next_callback_id_before = next_callback_id(&canister);
         let (mut canister, instructions_left, result, call_context_id, system_api_call_counters) =
             execute_non_replicated_query(
                 query_kind,
@@ -448,8 +472,16 @@ impl<'a> QueryContext<'a> {
                 &mut self.round_limits,
                 self.query_critical_error,
             );
-        self.add_system_api_call_counters(system_api_call_counters);
+        self.add_system_api_call_counters(canister.canister_id(), system_api_call_counters);
         let instructions_executed = instruction_limit - instructions_left;
+        if let Some(message) = traced_message {
+            self.execution_tracer.trace(
+                &canister,
+                message,
+                instructions_executed,
+                next_callback_id_before,
+            );
+        }
 
         let ingress_payload_size = method_payload.len();
         let egress_payload_size = match &result {
@@ -492,8 +524,18 @@ impl<'a> QueryContext<'a> {
         (canister, result)
     }
 
-    /// Adds up System API call counters.
-    fn add_system_api_call_counters(&mut self, system_api_call_counters: SystemApiCallCounters) {
+    /// Adds up System API call counters of an execution of the canister `canister_id`.
+    fn add_system_api_call_counters(
+        &mut self,
+        canister_id: CanisterId,
+        system_api_call_counters: SystemApiCallCounters,
+    ) {
+        if let Some(max_age) = system_api_call_counters.query_cache_max_age {
+            self.query_cache_max_ages
+                .entry(canister_id)
+                .and_modify(|m| *m = (*m).min(max_age))
+                .or_insert(max_age);
+        }
         self.system_api_call_counters
             .saturating_add(system_api_call_counters);
     }
@@ -554,6 +596,9 @@ impl<'a> QueryContext<'a> {
         query_system_api_calls
             .with_label_values(&[SYSTEM_API_TIME])
             .inc_by(self.system_api_call_counters.time as u64);
+        query_system_api_calls
+            .with_label_values(&[SYSTEM_API_SET_QUERY_CACHE_MAX_AGE])
+            .inc_by(self.system_api_call_counters.set_query_cache_max_age as u64);
 
         // Observe the number evaluated canisters in the corresponding metrics.
         metrics
@@ -598,6 +643,7 @@ impl<'a> QueryContext<'a> {
 
         let call_responded = call_context.has_responded();
         let call_origin = call_context.call_origin().clone();
+        let next_callback_id_before = next_callback_id(&canister);
         // Validate that the canister has an `ExecutionState`.
         if canister.execution_state.is_none() {
             let action = self.finish(
@@ -672,7 +718,7 @@ impl<'a> QueryContext<'a> {
             call_context.time(),
         );
 
-        self.add_system_api_call_counters(output.system_api_call_counters);
+        self.add_system_api_call_counters(canister_id, output.system_api_call_counters);
         canister.execution_state = Some(output_execution_state);
         execution_parameters
             .instruction_limits
@@ -716,6 +762,16 @@ impl<'a> QueryContext<'a> {
                 .get()
                 .saturating_sub(instructions_left.get()),
         );
+        if self.execution_tracer.is_enabled() {
+            self.execution_tracer.trace(
+                &canister,
+                ExecutedMessage::Response {
+                    originator_reply_callback: callback_id,
+                },
+                instructions_used,
+                next_callback_id_before,
+            );
+        }
         let action = self.finish(
             &mut canister,
             call_context_id,
@@ -776,7 +832,10 @@ impl<'a> QueryContext<'a> {
                 time,
             );
 
-        self.add_system_api_call_counters(cleanup_output.system_api_call_counters);
+        self.add_system_api_call_counters(
+            canister.canister_id(),
+            cleanup_output.system_api_call_counters,
+        );
         canister.execution_state = Some(output_execution_state);
         match cleanup_output.wasm_result {
             Ok(_) => {
@@ -1119,8 +1178,20 @@ impl<'a> QueryContext<'a> {
     }
 
     /// Returns how many times each tracked System API call was invoked.
-    pub fn system_api_call_counters(&self) -> &SystemApiCallCounters {
-        &self.system_api_call_counters
+    ///
+    /// The query cache max age is the smallest max age set by the evaluated canisters,
+    /// if every evaluated canister set one in any of its executions.
+    pub fn system_api_call_counters(&self) -> SystemApiCallCounters {
+        let query_cache_max_age = self
+            .evaluated_canister_stats
+            .keys()
+            .map(|canister_id| self.query_cache_max_ages.get(canister_id).copied())
+            .collect::<Option<Vec<_>>>()
+            .and_then(|max_ages| max_ages.into_iter().min());
+        SystemApiCallCounters {
+            query_cache_max_age,
+            ..self.system_api_call_counters.clone()
+        }
     }
 
     /// Returns a list of actually executed canisters with their stats.
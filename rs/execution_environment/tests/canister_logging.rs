@@ -2,7 +2,8 @@ use ic_base_types::PrincipalId;
 use ic_config::execution_environment::Config as ExecutionConfig;
 use ic_config::subnet_config::SubnetConfig;
 use ic_management_canister_types_private::{
-    self as ic00, BoundedAllowedViewers, CanisterIdRecord, CanisterInstallMode, CanisterLogRecord,
+    self as ic00, BoundedAllowedViewers, CanisterIdRecord, CanisterInstallMode,
+    CanisterLogContentFilter, CanisterLogFilter, CanisterLogRange, CanisterLogRecord,
     CanisterSettingsArgs, CanisterSettingsArgsBuilder, DataSize, EmptyBlob,
     FetchCanisterLogsRequest, FetchCanisterLogsResponse, LogVisibilityV2, Payload,
 };
@@ -43,6 +44,7 @@ fn canister_log_response(data: Vec<(u64, u64, Vec<u8>)>) -> FetchCanisterLogsRes
                 content,
             })
             .collect(),
+        next_idx: None,
     }
 }
 
@@ -122,12 +124,20 @@ fn fetch_canister_logs(
     env: &StateMachine,
     sender: PrincipalId,
     canister_id: CanisterId,
+) -> Result<WasmResult, UserError> {
+    fetch_canister_logs_with_request(env, sender, FetchCanisterLogsRequest::new(canister_id))
+}
+
+fn fetch_canister_logs_with_request(
+    env: &StateMachine,
+    sender: PrincipalId,
+    request: FetchCanisterLogsRequest,
 ) -> Result<WasmResult, UserError> {
     env.query_as(
         sender,
         CanisterId::ic_00(),
         "fetch_canister_logs",
-        FetchCanisterLogsRequest::new(canister_id).encode(),
+        request.encode(),
     )
 }
 
@@ -198,6 +208,7 @@ fn test_fetch_canister_logs_via_query_call() {
         Ok(WasmResult::Reply(
             FetchCanisterLogsResponse {
                 canister_log_records: vec![],
+                next_idx: None,
             }
             .encode(),
         ))
@@ -282,6 +293,7 @@ fn test_log_visibility_of_fetch_canister_logs() {
     let ok = Ok(WasmResult::Reply(
         FetchCanisterLogsResponse {
             canister_log_records: vec![],
+            next_idx: None,
         }
         .encode(),
     ));
@@ -455,6 +467,154 @@ fn test_canister_log_record_index_increment_for_different_calls() {
     );
 }
 
+#[test]
+fn test_fetch_canister_logs_with_filter() {
+    // Test that only the log records matching all criteria of the filter are returned.
+    let (env, canister_id, controller) = setup_with_controller(
+        wat_canister()
+            .update(
+                "test1",
+                wat_fn().debug_print(b"info: a").debug_print(b"error: b"),
+            )
+            .update(
+                "test2",
+                wat_fn().debug_print(b"info: c").debug_print(b"error: d"),
+            )
+            .build_wasm(),
+    );
+    env.advance_time(Duration::from_secs(1));
+    let timestamp_01 = system_time_to_nanos(env.time());
+    let _ = env.execute_ingress(canister_id, "test1", vec![]);
+    env.advance_time(Duration::from_nanos(123_456));
+    let timestamp_23 = system_time_to_nanos(env.time());
+    let _ = env.execute_ingress(canister_id, "test2", vec![]);
+
+    let fetch = |filter: CanisterLogFilter| {
+        let request = FetchCanisterLogsRequest::new(canister_id).with_filter(filter);
+        FetchCanisterLogsResponse::decode(&get_reply(fetch_canister_logs_with_request(
+            &env, controller, request,
+        )))
+        .unwrap()
+    };
+
+    assert_eq!(
+        fetch(CanisterLogFilter {
+            idx: Some(CanisterLogRange::new(1, 3)),
+            ..Default::default()
+        }),
+        canister_log_response(vec![
+            (1, timestamp_01, b"error: b".to_vec()),
+            (2, timestamp_23, b"info: c".to_vec()),
+        ])
+    );
+    assert_eq!(
+        fetch(CanisterLogFilter {
+            timestamp_nanos: Some(CanisterLogRange::new(timestamp_23, u64::MAX)),
+            ..Default::default()
+        }),
+        canister_log_response(vec![
+            (2, timestamp_23, b"info: c".to_vec()),
+            (3, timestamp_23, b"error: d".to_vec()),
+        ])
+    );
+    assert_eq!(
+        fetch(CanisterLogFilter {
+            content: Some(CanisterLogContentFilter::Substring("error".to_string())),
+            ..Default::default()
+        }),
+        canister_log_response(vec![
+            (1, timestamp_01, b"error: b".to_vec()),
+            (3, timestamp_23, b"error: d".to_vec()),
+        ])
+    );
+    assert_eq!(
+        fetch(CanisterLogFilter {
+            idx: Some(CanisterLogRange::new(0, 3)),
+            timestamp_nanos: None,
+            content: Some(CanisterLogContentFilter::Regex(
+                "^(info|error): [cd]$".to_string()
+            )),
+        }),
+        canister_log_response(vec![(2, timestamp_23, b"info: c".to_vec())])
+    );
+}
+
+#[test]
+fn test_fetch_canister_logs_with_pagination() {
+    // Test that fetching the log page by page returns all the matching log records.
+    let (env, canister_id, controller) = setup_with_controller(
+        wat_canister()
+            .update(
+                "test",
+                wat_fn()
+                    .debug_print(b"message 0")
+                    .debug_print(b"skipped 1")
+                    .debug_print(b"message 2")
+                    .debug_print(b"message 3")
+                    .debug_print(b"skipped 4"),
+            )
+            .build_wasm(),
+    );
+    let _ = env.execute_ingress(canister_id, "test", vec![]);
+
+    let mut pages = vec![];
+    let mut start = 0;
+    loop {
+        let request = FetchCanisterLogsRequest::new(canister_id)
+            .with_filter(CanisterLogFilter {
+                idx: Some(CanisterLogRange::new(start, u64::MAX)),
+                timestamp_nanos: None,
+                content: Some(CanisterLogContentFilter::Substring("message".to_string())),
+            })
+            .with_page_size(2);
+        let response = FetchCanisterLogsResponse::decode(&get_reply(
+            fetch_canister_logs_with_request(&env, controller, request),
+        ))
+        .unwrap();
+        pages.push(
+            response
+                .canister_log_records
+                .iter()
+                .map(|record| record.idx)
+                .collect::<Vec<_>>(),
+        );
+        match response.next_idx {
+            Some(next_idx) => start = next_idx,
+            None => break,
+        }
+    }
+    assert_eq!(pages, vec![vec![0, 2], vec![3]]);
+}
+
+#[test]
+fn test_fetch_canister_logs_with_invalid_request() {
+    let (env, canister_id, controller) = setup_with_controller(wat_canister().build_wasm());
+
+    let result = fetch_canister_logs_with_request(
+        &env,
+        controller,
+        FetchCanisterLogsRequest::new(canister_id).with_page_size(0),
+    );
+    assert_eq!(
+        result.unwrap_err().code(),
+        ErrorCode::InvalidManagementPayload
+    );
+
+    let result = fetch_canister_logs_with_request(
+        &env,
+        controller,
+        FetchCanisterLogsRequest::new(canister_id).with_filter(CanisterLogFilter {
+            content: Some(CanisterLogContentFilter::Regex("(".to_string())),
+            ..Default::default()
+        }),
+    );
+    let error = result.unwrap_err();
+    assert_eq!(error.code(), ErrorCode::InvalidManagementPayload);
+    assert!(error
+        .description()
+        .contains("Invalid content regex of fetch_canister_logs"));
+}
+
 #[test]
 fn test_canister_log_record_index_increment_after_node_restart() {
     // Test that the index of the log records is incremented for each log message
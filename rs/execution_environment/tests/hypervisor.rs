@@ -10,8 +10,8 @@ use ic_error_types::{ErrorCode, RejectCode};
 use ic_interfaces::execution_environment::{HypervisorError, SubnetAvailableMemory};
 use ic_management_canister_types_private::{
     CanisterChange, CanisterHttpResponsePayload, CanisterStatusType, CanisterUpgradeOptions,
-    EcdsaCurve, EcdsaKeyId, MasterPublicKeyId, SchnorrAlgorithm, SchnorrKeyId, VetKdCurve,
-    VetKdKeyId,
+    EcdsaCurve, EcdsaKeyId, EnvironmentVariable, MasterPublicKeyId, SchnorrAlgorithm, SchnorrKeyId,
+    VetKdCurve, VetKdKeyId,
 };
 use ic_nns_constants::CYCLES_MINTING_CANISTER_ID;
 use ic_registry_subnet_type::SubnetType;
@@ -2086,6 +2086,139 @@ fn ic0_subnet_self_copy_works() {
     );
 }
 
+#[test]
+fn ic0_env_var_count_and_name_work() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "env_var_count"
+                (func $env_var_count (result i32))
+            )
+            (import "ic0" "env_var_name_size"
+                (func $env_var_name_size (param i32) (result i32))
+            )
+            (import "ic0" "env_var_name_copy"
+                (func $env_var_name_copy (param i32 i32 i32 i32))
+            )
+            (import "ic0" "msg_reply" (func $msg_reply))
+            (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32)))
+            (func (export "canister_update test")
+                ;; heap[0..4] = $env_var_count()
+                (i32.store (i32.const 0) (call $env_var_count))
+                ;; heap[4..8] = $env_var_name_size(1)
+                (i32.store (i32.const 4) (call $env_var_name_size (i32.const 1)))
+                ;; heap[8..13] = name of the variable at index 1
+                (call $env_var_name_copy (i32.const 1) (i32.const 8) (i32.const 0) (i32.const 5))
+                ;; return heap[0..13]
+                (call $msg_reply_data_append (i32.const 0) (i32.const 13))
+                (call $msg_reply)
+            )
+            (memory 1 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    test.canister_update_environment_variables(
+        canister_id,
+        vec![
+            EnvironmentVariable::new("B_KEY", "value_b"),
+            EnvironmentVariable::new("A_KEY", "value_a"),
+        ],
+    )
+    .unwrap();
+    let result = test.ingress(canister_id, "test", vec![]).unwrap();
+    let mut expected = vec![2, 0, 0, 0, 5, 0, 0, 0];
+    expected.extend_from_slice(b"B_KEY");
+    assert_eq!(WasmResult::Reply(expected), result);
+}
+
+#[test]
+fn ic0_env_var_value_works() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "env_var_name_exists"
+                (func $env_var_name_exists (param i32 i32) (result i32))
+            )
+            (import "ic0" "env_var_value_size"
+                (func $env_var_value_size (param i32 i32) (result i32))
+            )
+            (import "ic0" "env_var_value_copy"
+                (func $env_var_value_copy (param i32 i32 i32 i32 i32))
+            )
+            (import "ic0" "msg_reply" (func $msg_reply))
+            (import "ic0" "msg_reply_data_append"
+            (func $msg_reply_data_append (param i32 i32)))
+            (func (export "canister_update test")
+                ;; heap[0..4] = $env_var_name_exists("A_KEY")
+                (i32.store (i32.const 0) (call $env_var_name_exists (i32.const 100) (i32.const 5)))
+                ;; heap[4..8] = $env_var_name_exists("MISSING")
+                (i32.store (i32.const 4) (call $env_var_name_exists (i32.const 105) (i32.const 7)))
+                ;; heap[8..12] = $env_var_value_size("A_KEY")
+                (i32.store (i32.const 8) (call $env_var_value_size (i32.const 100) (i32.const 5)))
+                ;; heap[12..19] = value of "A_KEY"
+                (call $env_var_value_copy (i32.const 100) (i32.const 5) (i32.const 12) (i32.const 0) (i32.const 7))
+                ;; return heap[0..19]
+                (call $msg_reply_data_append (i32.const 0) (i32.const 19))
+                (call $msg_reply)
+            )
+            (memory 1 1)
+            (data (i32.const 100) "A_KEYMISSING")
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    test.canister_update_environment_variables(
+        canister_id,
+        vec![EnvironmentVariable::new("A_KEY", "value_a")],
+    )
+    .unwrap();
+    let result = test.ingress(canister_id, "test", vec![]).unwrap();
+    let mut expected = vec![1, 0, 0, 0, 0, 0, 0, 0, 7, 0, 0, 0];
+    expected.extend_from_slice(b"value_a");
+    assert_eq!(WasmResult::Reply(expected), result);
+}
+
+#[test]
+fn ic0_env_var_name_size_fails_for_out_of_bounds_index() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "env_var_name_size"
+                (func $env_var_name_size (param i32) (result i32))
+            )
+            (func (export "canister_update test")
+                (drop (call $env_var_name_size (i32.const 0)))
+            )
+            (memory 1 1)
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    let err = test.ingress(canister_id, "test", vec![]).unwrap_err();
+    err.assert_contains(
+        ErrorCode::CanisterContractViolation,
+        "ic0_env_var_name_size failed because the index 0 is out of bounds",
+    );
+}
+
+#[test]
+fn ic0_env_var_value_size_fails_for_unknown_variable() {
+    let mut test = ExecutionTestBuilder::new().build();
+    let wat = r#"
+        (module
+            (import "ic0" "env_var_value_size"
+                (func $env_var_value_size (param i32 i32) (result i32))
+            )
+            (func (export "canister_update test")
+                (drop (call $env_var_value_size (i32.const 100) (i32.const 7)))
+            )
+            (memory 1 1)
+            (data (i32.const 100) "MISSING")
+        )"#;
+    let canister_id = test.canister_from_wat(wat).unwrap();
+    let err = test.ingress(canister_id, "test", vec![]).unwrap_err();
+    err.assert_contains(
+        ErrorCode::CanisterContractViolation,
+        "ic0_env_var_value_size failed because the environment variable does not exist",
+    );
+}
+
 #[test]
 fn ic0_call_has_no_effect_on_trap() {
     let mut test = ExecutionTestBuilder::new().build();
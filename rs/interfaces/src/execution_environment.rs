@@ -21,6 +21,7 @@ use std::{
     convert::{Infallible, TryFrom},
     fmt, ops,
     sync::Arc,
+    time::Duration,
 };
 use strum_macros::EnumIter;
 use thiserror::Error;
@@ -180,6 +181,18 @@ pub enum SystemApiCallId {
     DataCertificateSize,
     /// Tracker for `ic0.debug_print()`
     DebugPrint,
+    /// Tracker for `ic0.env_var_count()`
+    EnvVarCount,
+    /// Tracker for `ic0.env_var_name_copy()`
+    EnvVarNameCopy,
+    /// Tracker for `ic0.env_var_name_exists()`
+    EnvVarNameExists,
+    /// Tracker for `ic0.env_var_name_size()`
+    EnvVarNameSize,
+    /// Tracker for `ic0.env_var_value_copy()`
+    EnvVarValueCopy,
+    /// Tracker for `ic0.env_var_value_size()`
+    EnvVarValueSize,
     /// Tracker for `ic0.global_timer_set()`
     GlobalTimerSet,
     /// Tracker for `ic0.in_replicated_execution()`
@@ -232,6 +245,8 @@ pub enum SystemApiCallId {
     OutOfInstructions,
     /// Tracker for `ic0.performance_counter()`
     PerformanceCounter,
+    /// Tracker for `ic0.set_query_cache_max_age()`
+    SetQueryCacheMaxAge,
     /// Tracker for `ic0.subnet_self_size()`
     SubnetSelfSize,
     /// Tracker for `ic0.subnet_self_copy()`
@@ -274,9 +289,17 @@ pub struct SystemApiCallCounters {
     pub canister_liquid_cycle_balance128: usize,
     /// Counter for `ic0.time()`
     pub time: usize,
+    /// Counter for `ic0.set_query_cache_max_age()`
+    pub set_query_cache_max_age: usize,
+    /// The smallest max age set by `ic0.set_query_cache_max_age()`, if any.
+    pub query_cache_max_age: Option<Duration>,
 }
 
 impl SystemApiCallCounters {
+    /// Adds up the counters of `rhs`.
+    ///
+    /// The result keeps a max age only if both `self` and `rhs` set one, as a result
+    /// combining both executions may only be stale if each of them allows it.
     pub fn saturating_add(&mut self, rhs: Self) {
         self.data_certificate_copy = self
             .data_certificate_copy
@@ -291,6 +314,13 @@ impl SystemApiCallCounters {
             .canister_liquid_cycle_balance128
             .saturating_add(rhs.canister_liquid_cycle_balance128);
         self.time = self.time.saturating_add(rhs.time);
+        self.set_query_cache_max_age = self
+            .set_query_cache_max_age
+            .saturating_add(rhs.set_query_cache_max_age);
+        self.query_cache_max_age = match (self.query_cache_max_age, rhs.query_cache_max_age) {
+            (Some(lhs), Some(rhs)) => Some(lhs.min(rhs)),
+            _ => None,
+        };
     }
 }
 
@@ -958,6 +988,14 @@ pub trait SystemApi {
     /// The canister can set a global one-off timer at the specific time.
     fn ic0_global_timer_set(&mut self, time: Time) -> HypervisorResult<Time>;
 
+    /// Allows the replica side query cache to return the result of the
+    /// current query for up to `max_age`, even if the versions or balances
+    /// of the evaluated canisters change in the meantime.
+    ///
+    /// If called multiple times, the smallest `max_age` applies. For composite
+    /// queries, this also includes the calls made by the other evaluated canisters.
+    fn ic0_set_query_cache_max_age(&mut self, max_age: Duration) -> HypervisorResult<()>;
+
     /// The canister can query the IC for its version.
     fn ic0_canister_version(&self) -> HypervisorResult<u64>;
 
@@ -1342,6 +1380,63 @@ pub trait SystemApi {
         size: usize,
         heap: &mut [u8],
     ) -> HypervisorResult<()>;
+
+    /// Returns the number of environment variables of the canister.
+    fn ic0_env_var_count(&self) -> HypervisorResult<usize>;
+
+    /// Returns the size of the name of the environment variable at the given
+    /// index. The variables are sorted by name.
+    ///
+    /// Traps if the index is out of bounds.
+    fn ic0_env_var_name_size(&self, index: usize) -> HypervisorResult<usize>;
+
+    /// Copies the name of the environment variable at the given index to the
+    /// canister's heap at the location specified by `dst` and `offset`.
+    ///
+    /// Traps if the index is out of bounds.
+    fn ic0_env_var_name_copy(
+        &self,
+        index: usize,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
+
+    /// Returns 1 if an environment variable with the name stored in the
+    /// canister's heap at `name_src` exists and 0 otherwise.
+    fn ic0_env_var_name_exists(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<i32>;
+
+    /// Returns the size of the value of the environment variable with the
+    /// name stored in the canister's heap at `name_src`.
+    ///
+    /// Traps if no variable with the given name exists.
+    fn ic0_env_var_value_size(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<usize>;
+
+    /// Copies the value of the environment variable with the name stored in
+    /// the canister's heap at `name_src` to the canister's heap at the
+    /// location specified by `dst` and `offset`.
+    ///
+    /// Traps if no variable with the given name exists.
+    fn ic0_env_var_value_copy(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()>;
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
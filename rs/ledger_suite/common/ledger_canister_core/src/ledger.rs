@@ -211,6 +211,21 @@ pub fn apply_transaction<L>(
     now: TimeStamp,
     effective_fee: L::Tokens,
 ) -> Result<(BlockIndex, HashOf<EncodedBlock>), TransferError<L::Tokens>>
+where
+    L: LedgerData,
+{
+    apply_transaction_in_batch(ledger, transaction, now, effective_fee, None)
+}
+
+/// Adds a new block with the specified transaction to the ledger and marks it as part
+/// of the batch starting at `batch_block_index` (see [BlockType::with_batch_block_index]).
+pub fn apply_transaction_in_batch<L>(
+    ledger: &mut L,
+    transaction: L::Transaction,
+    now: TimeStamp,
+    effective_fee: L::Tokens,
+    batch_block_index: Option<BlockIndex>,
+) -> Result<(BlockIndex, HashOf<EncodedBlock>), TransferError<L::Tokens>>
 where
     L: LedgerData,
 {
@@ -273,6 +288,10 @@ where
         effective_fee,
         fee_collector,
     );
+    let block = match batch_block_index {
+        Some(batch_block_index) => block.with_batch_block_index(batch_block_index),
+        None => block,
+    };
     let block_timestamp = block.timestamp();
 
     let height = ledger
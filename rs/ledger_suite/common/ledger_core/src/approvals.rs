@@ -2,6 +2,7 @@ use crate::timestamp::TimeStamp;
 use crate::tokens::{CheckedSub, TokensType, Zero};
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Bound;
 
 #[cfg(test)]
 mod tests;
@@ -61,6 +62,15 @@ pub trait AllowancesData {
         &mut self,
     ) -> Option<((Self::AccountId, Self::AccountId), Allowance<Self::Tokens>)>;
 
+    /// Returns at most `max_results` allowances in ascending order
+    /// of their (account, spender) pairs, starting at the given bound.
+    #[allow(clippy::type_complexity)]
+    fn get_cursor_allowances(
+        &self,
+        start: Bound<(Self::AccountId, Self::AccountId)>,
+        max_results: usize,
+    ) -> Vec<((Self::AccountId, Self::AccountId), Allowance<Self::Tokens>)>;
+
     fn len_allowances(&self) -> usize;
 
     fn len_expirations(&self) -> usize;
@@ -148,6 +158,18 @@ where
         self.allowances.pop_first()
     }
 
+    fn get_cursor_allowances(
+        &self,
+        start: Bound<(Self::AccountId, Self::AccountId)>,
+        max_results: usize,
+    ) -> Vec<((Self::AccountId, Self::AccountId), Allowance<Self::Tokens>)> {
+        self.allowances
+            .range((start, Bound::Unbounded))
+            .take(max_results)
+            .map(|(account_spender, allowance)| (account_spender.clone(), allowance.clone()))
+            .collect()
+    }
+
     fn len_allowances(&self) -> usize {
         self.allowances.len()
     }
@@ -333,6 +355,46 @@ where
         })
     }
 
+    /// Returns at most `max_results` allowances that have not expired
+    /// in ascending order of their (account, spender) pairs, starting at the given bound
+    /// and stopping at the first pair for which `take_while` returns `false`.
+    #[allow(clippy::type_complexity)]
+    pub fn get_allowances(
+        &self,
+        start: Bound<(AD::AccountId, AD::AccountId)>,
+        take_while: impl Fn(&(AD::AccountId, AD::AccountId)) -> bool,
+        max_results: usize,
+        now: TimeStamp,
+    ) -> Vec<((AD::AccountId, AD::AccountId), Allowance<AD::Tokens>)> {
+        let mut result = vec![];
+        let mut start = start;
+        // Expired allowances are pruned lazily and thus we might need
+        // to fetch more than `max_results` allowances.
+        while result.len() < max_results {
+            let batch_size = max_results - result.len();
+            let batch = self
+                .allowances_data
+                .get_cursor_allowances(start, batch_size);
+            let num_fetched = batch.len();
+            match batch.last() {
+                Some((account_spender, _)) => start = Bound::Excluded(account_spender.clone()),
+                None => break,
+            }
+            for (account_spender, allowance) in batch {
+                if !take_while(&account_spender) {
+                    return result;
+                }
+                if allowance.expires_at.unwrap_or_else(remote_future) > now {
+                    result.push((account_spender, allowance));
+                }
+            }
+            if num_fetched < batch_size {
+                break;
+            }
+        }
+        result
+    }
+
     /// Returns the number of approvals.
     pub fn get_num_approvals(&self) -> usize {
         self.allowances_data.len_allowances()
@@ -535,3 +535,72 @@ fn expected_allowance_if_zero_no_approval() {
         }
     );
 }
+
+#[test]
+fn allowance_table_get_allowances() {
+    let mut table = TestAllowanceTable::default();
+
+    for spender in 2..6 {
+        table
+            .approve(
+                &Account(1),
+                &Account(spender),
+                tokens(spender),
+                None,
+                ts(1),
+                None,
+            )
+            .unwrap();
+    }
+    // This allowance expires before the allowances are listed.
+    table
+        .approve(
+            &Account(1),
+            &Account(6),
+            tokens(6),
+            Some(ts(5)),
+            ts(1),
+            None,
+        )
+        .unwrap();
+    table
+        .approve(&Account(1), &Account(7), tokens(7), None, ts(1), None)
+        .unwrap();
+    table
+        .approve(&Account(2), &Account(1), tokens(1), None, ts(1), None)
+        .unwrap();
+
+    let is_account_1 = |(account, _): &(Account, Account)| *account == Account(1);
+    let spenders = |allowances: Vec<((Account, Account), Allowance<Tokens>)>| {
+        allowances
+            .into_iter()
+            .map(|((_, spender), _)| spender.0)
+            .collect::<Vec<_>>()
+    };
+
+    let start = Bound::Included((Account(1), Account(0)));
+    assert_eq!(
+        spenders(table.get_allowances(start.clone(), is_account_1, 10, ts(10))),
+        vec![2, 3, 4, 5, 7]
+    );
+    assert_eq!(
+        spenders(table.get_allowances(start, is_account_1, 2, ts(10))),
+        vec![2, 3]
+    );
+    // The expired allowance is skipped without shortening the page.
+    let start = Bound::Excluded((Account(1), Account(4)));
+    assert_eq!(
+        spenders(table.get_allowances(start, is_account_1, 2, ts(10))),
+        vec![5, 7]
+    );
+    // The expired allowance is listed before it expires.
+    let start = Bound::Excluded((Account(1), Account(5)));
+    assert_eq!(
+        spenders(table.get_allowances(start, is_account_1, 10, ts(2))),
+        vec![6, 7]
+    );
+    let start = Bound::Excluded((Account(1), Account(7)));
+    assert!(table
+        .get_allowances(start, is_account_1, 10, ts(10))
+        .is_empty());
+}
@@ -115,4 +115,12 @@ pub trait BlockType: Sized + Clone {
 
     /// Returns the time at which the ledger constructed this block.
     fn timestamp(&self) -> TimeStamp;
+
+    /// Marks this block as part of a batch of transactions applied by a single call,
+    /// identified by the index of the first block of the batch.
+    ///
+    /// Block types without a batch encoding return the block unchanged.
+    fn with_batch_block_index(self, _batch_block_index: BlockIndex) -> Self {
+        self
+    }
 }
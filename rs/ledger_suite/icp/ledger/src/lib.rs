@@ -737,6 +737,20 @@ impl AllowancesData for StableAllowancesData {
         panic!("The method `pop_first_allowance` should not be called for StableAllowancesData")
     }
 
+    fn get_cursor_allowances(
+        &self,
+        start: std::ops::Bound<(Self::AccountId, Self::AccountId)>,
+        max_results: usize,
+    ) -> Vec<((Self::AccountId, Self::AccountId), Allowance<Self::Tokens>)> {
+        ALLOWANCES_MEMORY.with_borrow(|allowances| {
+            allowances
+                .range((start, std::ops::Bound::Unbounded))
+                .take(max_results)
+                .map(|(account_spender, allowance)| (account_spender, allowance.into()))
+                .collect()
+        })
+    }
+
     fn len_allowances(&self) -> usize {
         ALLOWANCES_MEMORY
             .with_borrow(|allowances| allowances.len())
@@ -106,6 +106,7 @@ fn test_icrc3_get_blocks() {
             timestamp,
             fee_collector: None,
             fee_collector_block_index: None,
+            batch_block_index: None,
             transaction: Transaction {
                 operation,
                 created_at_time: None,
@@ -255,6 +256,7 @@ fn test_icrc3_get_blocks_number_of_blocks_limit() {
             timestamp: 0,
             fee_collector: None,
             fee_collector_block_index: None,
+            batch_block_index: None,
             transaction: Transaction {
                 operation: Operation::Mint {
                     to: Account::from(Principal::anonymous()),
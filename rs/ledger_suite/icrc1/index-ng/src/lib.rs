@@ -1,6 +1,6 @@
 use candid::{CandidType, Deserialize, Nat, Principal};
 use icrc_ledger_types::icrc1::account::{Account, Subaccount};
-use icrc_ledger_types::icrc1::transfer::BlockIndex;
+use icrc_ledger_types::icrc1::transfer::{BlockIndex, Memo};
 use icrc_ledger_types::icrc3::blocks::GenericBlock;
 use icrc_ledger_types::icrc3::transactions::Transaction;
 
@@ -44,6 +44,38 @@ pub struct GetAccountTransactionsArgs {
     pub start: Option<BlockIndex>,
     // Maximum number of transactions to fetch.
     pub max_results: Nat,
+    // If set then only the transactions matching the filter are returned.
+    #[serde(default)]
+    pub filter: Option<TransactionFilter>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub enum TransactionKind {
+    Mint,
+    Burn,
+    Transfer,
+    Approve,
+}
+
+/// A filter on the transactions of an account. A transaction matches the filter
+/// if it matches all the criteria that are set.
+#[derive(Clone, Eq, PartialEq, Debug, Default, CandidType, Deserialize)]
+pub struct TransactionFilter {
+    // The transaction is of one of the given kinds.
+    pub kinds: Option<Vec<TransactionKind>>,
+    // The transaction involves the given account as sender,
+    // receiver or spender.
+    pub counterparty: Option<Account>,
+    // The amount of the transaction is at least min_amount.
+    pub min_amount: Option<Nat>,
+    // The amount of the transaction is at most max_amount.
+    pub max_amount: Option<Nat>,
+    // The timestamp of the transaction is at least min_timestamp.
+    pub min_timestamp: Option<u64>,
+    // The timestamp of the transaction is at most max_timestamp.
+    pub max_timestamp: Option<u64>,
+    // The memo of the transaction starts with memo_prefix.
+    pub memo_prefix: Option<Memo>,
 }
 
 #[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
@@ -58,6 +90,13 @@ pub struct GetAccountTransactionsResponse {
     pub transactions: Vec<TransactionWithId>,
     // The txid of the oldest transaction the account has
     pub oldest_tx_id: Option<BlockIndex>,
+    // The txid of the last transaction examined by the request.
+    // A filtered request examines a bounded number of transactions
+    // and can return less than max_results transactions even if
+    // there are more matching transactions. Clients should use
+    // this txid as start of the next request.
+    #[serde(default)]
+    pub last_scanned_tx_id: Option<BlockIndex>,
 }
 
 #[derive(Eq, PartialEq, Debug, CandidType, Deserialize)]
@@ -78,6 +117,111 @@ pub struct ListSubaccountsArgs {
     pub start: Option<Subaccount>,
 }
 
+#[derive(Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub struct GetAccountAllowancesArgs {
+    pub account: Account,
+    // The last spender seen by the client for the given account.
+    // This spender is excluded in the result.
+    // If None then the results will start from the first
+    // spender in natural order.
+    pub start: Option<Account>,
+    // Maximum number of allowances to fetch.
+    pub max_results: Nat,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub struct AccountAllowance {
+    pub spender: Account,
+    pub allowance: Nat,
+    pub expires_at: Option<u64>,
+    // The index of the block that granted a non-zero allowance
+    // to the spender after it had none.
+    pub since_block: BlockIndex,
+    // The timestamp of the block at since_block.
+    pub since_timestamp: u64,
+    // The index of the most recent approve block for the spender.
+    pub last_approve_block: BlockIndex,
+}
+
+#[derive(Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub struct GetAllowanceHistoryArgs {
+    pub account: Account,
+    pub spender: Account,
+    // The txid of the last transaction seen by the client.
+    // If None then the results will start from the most recent
+    // txid. If set then the results will start from the next
+    // most recent txid after start (start won't be included).
+    pub start: Option<BlockIndex>,
+    // Maximum number of transactions to fetch.
+    pub max_results: Nat,
+}
+
+/// The maximum number of subscriptions that the index keeps.
+pub const MAX_SUBSCRIPTIONS: u64 = 1_000;
+
+/// The maximum number of subscriptions that a single subscriber can register.
+pub const MAX_SUBSCRIPTIONS_PER_SUBSCRIBER: u64 = 10;
+
+/// The cycles that a subscriber must attach to [subscribe]. The fee pays
+/// for the notifications sent by the index and makes it expensive to
+/// exhaust the [MAX_SUBSCRIPTIONS] slots.
+pub const SUBSCRIPTION_FEE: u128 = 1_000_000_000_000;
+
+#[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub struct SubscribeArgs {
+    // The principal whose accounts are watched.
+    pub owner: Principal,
+    // If set then only the accounts of owner whose subaccount starts
+    // with this prefix are watched. The default subaccount is the
+    // subaccount made of 32 zero bytes.
+    pub subaccount_prefix: Option<Vec<u8>>,
+    // The method of the subscriber canister that is called with
+    // a BlockNotification for each new block touching a watched account.
+    pub method: String,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub enum SubscribeError {
+    // Only canisters can subscribe because notifications are sent
+    // to the subscriber.
+    CallerNotACanister,
+    InvalidArgument { message: String },
+    TooManySubscriptions { limit: u64 },
+    // The call must attach at least `required` cycles.
+    InsufficientCycles { required: Nat },
+}
+
+pub type SubscribeResult = Result<u64, SubscribeError>;
+
+#[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub struct Subscription {
+    pub id: u64,
+    pub subscriber: Principal,
+    pub owner: Principal,
+    pub subaccount_prefix: Option<Vec<u8>>,
+    pub method: String,
+    // The number of notifications sent to the subscriber.
+    pub notifications_sent: u64,
+    // The number of notifications that could not be sent,
+    // e.g., because the output queue of the index was full.
+    pub notifications_failed: u64,
+    // The index of the last block notified to the subscriber.
+    pub last_notified_block: Option<BlockIndex>,
+}
+
+/// The argument of the one-way call that the index makes to a subscriber.
+/// Notifications are best effort: the index doesn't retry failed
+/// notifications and the subscriber must use [get_account_transactions]
+/// to recover from missed ones.
+#[derive(Clone, Eq, PartialEq, Debug, CandidType, Deserialize)]
+pub struct BlockNotification {
+    pub subscription_id: u64,
+    // The watched account touched by the block.
+    pub account: Account,
+    pub block_index: BlockIndex,
+    pub transaction: Transaction,
+}
+
 #[derive(Eq, PartialEq, Debug, CandidType, Deserialize)]
 pub struct Status {
     pub num_blocks_synced: BlockIndex,
@@ -1,18 +1,23 @@
 use candid::{candid_method, CandidType, Decode, Encode, Nat, Principal};
+use ic_base_types::{PrincipalId, PrincipalIdClass};
 use ic_canister_log::{export as export_logs, log};
 use ic_canister_profiler::{measure_span, SpanName, SpanStats};
 use ic_canisters_http_types::{HttpRequest, HttpResponse, HttpResponseBuilder};
 use ic_cdk::trap;
-use ic_cdk_macros::{init, post_upgrade, query};
+use ic_cdk_macros::{init, post_upgrade, query, update};
 use ic_cdk_timers::TimerId;
 use ic_crypto_sha2::Sha256;
 use ic_icrc1::blocks::{encoded_block_to_generic_block, generic_block_to_encoded_block};
 use ic_icrc1::endpoints::StandardRecord;
 use ic_icrc1::{Block, Operation};
 use ic_icrc1_index_ng::{
-    FeeCollectorRanges, GetAccountTransactionsArgs, GetAccountTransactionsResponse,
-    GetAccountTransactionsResult, GetBlocksMethod, IndexArg, InitArg, ListSubaccountsArgs, Log,
-    LogEntry, Status, TransactionWithId, UpgradeArg, DEFAULT_MAX_BLOCKS_PER_RESPONSE,
+    AccountAllowance, BlockNotification, FeeCollectorRanges, GetAccountAllowancesArgs,
+    GetAccountTransactionsArgs, GetAccountTransactionsResponse, GetAccountTransactionsResult,
+    GetAllowanceHistoryArgs, GetBlocksMethod, IndexArg, InitArg, ListSubaccountsArgs, Log,
+    LogEntry, Status, SubscribeArgs, SubscribeError, SubscribeResult, Subscription,
+    TransactionFilter, TransactionKind, TransactionWithId, UpgradeArg,
+    DEFAULT_MAX_BLOCKS_PER_RESPONSE, MAX_SUBSCRIPTIONS, MAX_SUBSCRIPTIONS_PER_SUBSCRIBER,
+    SUBSCRIPTION_FEE,
 };
 use ic_ledger_canister_core::runtime::heap_memory_size_bytes;
 use ic_ledger_core::block::{BlockIndex as BlockIndex64, BlockType, EncodedBlock};
@@ -42,7 +47,7 @@ use std::convert::TryFrom;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::io::Read;
-use std::ops::Bound::{Excluded, Included};
+use std::ops::Bound::{Excluded, Included, Unbounded};
 use std::ops::Range;
 use std::time::Duration;
 
@@ -55,9 +60,37 @@ const BLOCK_LOG_INDEX_MEMORY_ID: MemoryId = MemoryId::new(1);
 const BLOCK_LOG_DATA_MEMORY_ID: MemoryId = MemoryId::new(2);
 const ACCOUNT_BLOCK_IDS_MEMORY_ID: MemoryId = MemoryId::new(3);
 const ACCOUNT_DATA_MEMORY_ID: MemoryId = MemoryId::new(4);
+const ALLOWANCES_MEMORY_ID: MemoryId = MemoryId::new(5);
+const ALLOWANCE_BLOCK_IDS_MEMORY_ID: MemoryId = MemoryId::new(6);
+const SUBSCRIPTIONS_MEMORY_ID: MemoryId = MemoryId::new(7);
+const SUBSCRIBER_SUBSCRIPTIONS_MEMORY_ID: MemoryId = MemoryId::new(8);
+const NOTIFICATION_QUEUE_MEMORY_ID: MemoryId = MemoryId::new(9);
+
+/// The maximum length of the name of the method notified to a subscriber.
+const MAX_SUBSCRIPTION_METHOD_LENGTH: usize = 100;
+
+/// The maximum number of notifications waiting to be sent. Notifications
+/// that don't fit in the queue are counted as failed.
+const MAX_QUEUED_NOTIFICATIONS: u64 = 10_000;
+
+/// The maximum number of notifications sent by a single [build_index] run.
+const MAX_NOTIFICATIONS_PER_ROUND: u64 = 100;
 
 const DEFAULT_RETRIEVE_BLOCKS_FROM_LEDGER_INTERVAL: Duration = Duration::from_secs(1);
 
+/// The maximum number of already indexed blocks processed by a single
+/// [backfill_allowances] call.
+const MAX_BLOCKS_TO_BACKFILL_ALLOWANCES: u64 = 10_000;
+
+/// The number of instructions after which a [backfill_allowances] call stops
+/// and schedules another call to resume from the next block. This keeps every
+/// call well below the instruction limit of a single message.
+const MAX_INSTRUCTIONS_TO_BACKFILL_ALLOWANCES: u64 = 1_000_000_000;
+
+/// The maximum number of transactions examined by a single filtered
+/// [get_account_transactions] request.
+const MAX_TRANSACTIONS_SCANNED_PER_FILTERED_REQUEST: usize = 10_000;
+
 #[cfg(not(feature = "u256-tokens"))]
 type Tokens = ic_icrc1_tokens_u64::U64;
 
@@ -77,6 +110,34 @@ type AccountBlockIdsMap = StableBTreeMap<AccountBlockIdsMapKey, (), VM>;
 type AccountDataMapKey = (AccountDataType, (Blob<29>, [u8; 32]));
 type AccountDataMap = StableBTreeMap<AccountDataMapKey, Tokens, VM>;
 
+// The key is the (owner account, spender account) pair where both accounts
+// are represented as principal of type Blob<29> and the effective subaccount
+type AllowancesMapKey = ((Blob<29>, [u8; 32]), (Blob<29>, [u8; 32]));
+type AllowancesMap = StableBTreeMap<AllowancesMapKey, AllowanceData, VM>;
+
+// The (owner account, spender account) pair is hashed to save space.
+// As for the account block ids, the block indexes are stored in reverse order.
+type AllowanceBlockIdsMapKey = ([u8; Sha256::DIGEST_LEN], Reverse<u64>);
+type AllowanceBlockIdsMap = StableBTreeMap<AllowanceBlockIdsMapKey, (), VM>;
+
+// The subscriptions are keyed by the watched principal, represented as
+// Blob<29>, so that the subscriptions of an account can be found without
+// scanning all of them, and by the subscription id.
+type SubscriptionsMapKey = (Blob<29>, u64);
+type SubscriptionsMap = StableBTreeMap<SubscriptionsMapKey, SubscriptionData, VM>;
+
+// The subscriptions are also indexed by the subscriber and the subscription
+// id. The value is the watched principal, i.e., the first component of the
+// key in the [SubscriptionsMap].
+type SubscriberSubscriptionsMapKey = (Blob<29>, u64);
+type SubscriberSubscriptionsMap = StableBTreeMap<SubscriberSubscriptionsMapKey, Blob<29>, VM>;
+
+// The notifications waiting to be sent are keyed by block index and
+// subscription id so that they are sent in the order of the blocks.
+// The value is the watched account touched by the block.
+type NotificationQueueKey = (u64, u64);
+type NotificationQueue = StableBTreeMap<NotificationQueueKey, (Blob<29>, [u8; 32]), VM>;
+
 thread_local! {
     /// Static memory manager to manage the memory available for stable structures.
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> = RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
@@ -104,6 +165,32 @@ thread_local! {
         RefCell::new(AccountDataMap::init(memory_manager.get(ACCOUNT_DATA_MEMORY_ID)))
     });
 
+    /// Map that contains the current allowance of each (account, spender) pair.
+    static ALLOWANCES: RefCell<AllowancesMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(AllowancesMap::init(memory_manager.get(ALLOWANCES_MEMORY_ID)))
+    });
+
+    /// Map that contains the ids of the blocks that changed the allowance
+    /// of an (account, spender) pair. The pair is hashed to save space.
+    static ALLOWANCE_BLOCK_IDS: RefCell<AllowanceBlockIdsMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(AllowanceBlockIdsMap::init(memory_manager.get(ALLOWANCE_BLOCK_IDS_MEMORY_ID)))
+    });
+
+    /// Map that contains the subscriptions to the blocks of the accounts.
+    static SUBSCRIPTIONS: RefCell<SubscriptionsMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(SubscriptionsMap::init(memory_manager.get(SUBSCRIPTIONS_MEMORY_ID)))
+    });
+
+    /// Map that contains the subscriptions of each subscriber.
+    static SUBSCRIBER_SUBSCRIPTIONS: RefCell<SubscriberSubscriptionsMap> = with_memory_manager(|memory_manager| {
+        RefCell::new(SubscriberSubscriptionsMap::init(memory_manager.get(SUBSCRIBER_SUBSCRIPTIONS_MEMORY_ID)))
+    });
+
+    /// Queue of the notifications waiting to be sent to the subscribers.
+    static NOTIFICATION_QUEUE: RefCell<NotificationQueue> = with_memory_manager(|memory_manager| {
+        RefCell::new(NotificationQueue::init(memory_manager.get(NOTIFICATION_QUEUE_MEMORY_ID)))
+    });
+
     /// Profiling data to understand cycles usage
     static PROFILING_DATA: RefCell<SpanStats> = RefCell::new(SpanStats::default());
 
@@ -136,6 +223,21 @@ struct State {
     /// index. Lower values will result in a more responsive UI, but higher costs due to increased
     /// cycle burn for the index, ledger and archive(s).
     retrieve_blocks_from_ledger_interval: Option<Duration>,
+
+    /// The index of the next already indexed block whose allowance changes
+    /// must be processed, or `None` if the allowances are up to date with the
+    /// block log. Indexes upgraded from a version that did not track
+    /// allowances start from the first block.
+    #[serde(default = "allowances_backfill_from_genesis")]
+    allowances_backfill_next_block: Option<BlockIndex64>,
+
+    /// The id of the next subscription.
+    #[serde(default)]
+    next_subscription_id: u64,
+}
+
+fn allowances_backfill_from_genesis() -> Option<BlockIndex64> {
+    Some(0)
 }
 
 impl State {
@@ -157,6 +259,8 @@ impl Default for State {
             fee_collectors: Default::default(),
             last_fee: None,
             retrieve_blocks_from_ledger_interval: None,
+            allowances_backfill_next_block: None,
+            next_subscription_id: 0,
         }
     }
 }
@@ -208,6 +312,61 @@ impl Storable for AccountDataType {
     };
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+struct AllowanceData {
+    amount: Tokens,
+    expires_at: Option<u64>,
+    since_block: BlockIndex64,
+    since_timestamp: u64,
+    last_approve_block: BlockIndex64,
+}
+
+impl Storable for AllowanceData {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut buf = vec![];
+        ciborium::ser::into_writer(self, &mut buf).expect("failed to encode allowance data");
+        Cow::Owned(buf)
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        ciborium::de::from_reader(&bytes[..]).expect("failed to decode allowance data")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize, Serialize)]
+struct SubscriptionData {
+    subscriber: Principal,
+    subaccount_prefix: Option<Vec<u8>>,
+    method: String,
+    notifications_sent: u64,
+    notifications_failed: u64,
+    last_notified_block: Option<BlockIndex64>,
+}
+
+impl SubscriptionData {
+    fn watches(&self, account: &Account) -> bool {
+        self.subaccount_prefix.as_ref().map_or(true, |prefix| {
+            account.effective_subaccount().starts_with(prefix)
+        })
+    }
+}
+
+impl Storable for SubscriptionData {
+    fn to_bytes(&self) -> Cow<[u8]> {
+        let mut buf = vec![];
+        ciborium::ser::into_writer(self, &mut buf).expect("failed to encode subscription data");
+        Cow::Owned(buf)
+    }
+
+    fn from_bytes(bytes: Cow<'_, [u8]>) -> Self {
+        ciborium::de::from_reader(&bytes[..]).expect("failed to decode subscription data")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 // Ephemeral data that doesn't need to be saved between upgrades
 #[derive(Clone, Debug, Default)]
 struct Cache {
@@ -259,6 +418,31 @@ fn with_account_data<R>(f: impl FnOnce(&mut AccountDataMap) -> R) -> R {
     ACCOUNT_DATA.with(|cell| f(&mut cell.borrow_mut()))
 }
 
+/// A helper function to access the allowances.
+fn with_allowances<R>(f: impl FnOnce(&mut AllowancesMap) -> R) -> R {
+    ALLOWANCES.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+/// A helper function to access the allowance block ids.
+fn with_allowance_block_ids<R>(f: impl FnOnce(&mut AllowanceBlockIdsMap) -> R) -> R {
+    ALLOWANCE_BLOCK_IDS.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+/// A helper function to access the subscriptions.
+fn with_subscriptions<R>(f: impl FnOnce(&mut SubscriptionsMap) -> R) -> R {
+    SUBSCRIPTIONS.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+/// A helper function to access the subscriptions of each subscriber.
+fn with_subscriber_subscriptions<R>(f: impl FnOnce(&mut SubscriberSubscriptionsMap) -> R) -> R {
+    SUBSCRIBER_SUBSCRIPTIONS.with(|cell| f(&mut cell.borrow_mut()))
+}
+
+/// A helper function to access the queue of notifications.
+fn with_notification_queue<R>(f: impl FnOnce(&mut NotificationQueue) -> R) -> R {
+    NOTIFICATION_QUEUE.with(|cell| f(&mut cell.borrow_mut()))
+}
+
 /// A helper function that returns a decoded block stored in the
 /// block log at the given index or None if there is no block at that index.
 /// This function can trap if the index at the given block cannot be decoded
@@ -287,11 +471,16 @@ fn change_balance(account: Account, f: impl FnOnce(Tokens) -> Tokens) {
 }
 
 fn balance_key(account: Account) -> (AccountDataType, (Blob<29>, [u8; 32])) {
+    (AccountDataType::Balance, account_key(account))
+}
+
+fn account_key(account: Account) -> (Blob<29>, [u8; 32]) {
     let owner = Blob::try_from(account.owner.as_slice()).unwrap();
-    (
-        AccountDataType::Balance,
-        (owner, *account.effective_subaccount()),
-    )
+    (owner, *account.effective_subaccount())
+}
+
+fn allowance_key(account: Account, spender: Account) -> AllowancesMapKey {
+    (account_key(account), account_key(spender))
 }
 
 #[init]
@@ -381,6 +570,9 @@ fn post_upgrade(index_arg: Option<IndexArg>) {
         let _maybe_first_key_value = account_data.first_key_value();
     });
 
+    // resume processing the allowance changes of already indexed blocks (if any)
+    set_backfill_allowances_timer();
+
     // set the first build_index to be called after init
     set_build_index_timer(with_state(|state| {
         state.retrieve_blocks_from_ledger_interval()
@@ -574,6 +766,7 @@ pub async fn build_index() -> Option<()> {
         GetBlocksMethod::GetBlocks => fetch_blocks_via_get_blocks().await?,
         GetBlocksMethod::ICRC3GetBlocks => fetch_blocks_via_icrc3().await?,
     };
+    send_notifications();
     let retrieve_blocks_from_ledger_interval =
         with_state(|state| state.retrieve_blocks_from_ledger_interval());
     log!(
@@ -711,6 +904,15 @@ fn append_block(block_index: BlockIndex64, block: GenericBlock) {
 
         // change the balance of the involved accounts
         process_balance_changes(block_index, &decoded_block);
+
+        // change the allowances of the involved (account, spender) pairs
+        // unless they are still being rebuilt from older blocks
+        if with_state(|state| state.allowances_backfill_next_block.is_none()) {
+            process_allowance_changes(block_index, &decoded_block);
+        }
+
+        // queue the notifications to the subscribers of the involved accounts
+        queue_notifications(block_index, &decoded_block);
     });
 }
 
@@ -845,6 +1047,283 @@ fn process_balance_changes(block_index: BlockIndex64, block: &Block<Tokens>) {
     );
 }
 
+fn process_allowance_changes(block_index: BlockIndex64, block: &Block<Tokens>) {
+    measure_span(
+        &PROFILING_DATA,
+        "append_blocks.process_allowance_changes",
+        move || match block.transaction.operation {
+            Operation::Approve {
+                from,
+                spender,
+                amount,
+                expires_at,
+                ..
+            } => {
+                with_allowance_block_ids(|allowance_block_ids| {
+                    allowance_block_ids
+                        .insert(allowance_block_ids_key(from, spender, block_index), ())
+                });
+                let key = allowance_key(from, spender);
+                if amount.is_zero() {
+                    with_allowances(|allowances| allowances.remove(&key));
+                    return;
+                }
+                // The Ledger treats an expired allowance as if it didn't exist.
+                let (since_block, since_timestamp) =
+                    match with_allowances(|allowances| allowances.get(&key)) {
+                        Some(current)
+                            if current
+                                .expires_at
+                                .is_none_or(|expires_at| expires_at > block.timestamp) =>
+                        {
+                            (current.since_block, current.since_timestamp)
+                        }
+                        _ => (block_index, block.timestamp),
+                    };
+                with_allowances(|allowances| {
+                    allowances.insert(
+                        key,
+                        AllowanceData {
+                            amount,
+                            expires_at,
+                            since_block,
+                            since_timestamp,
+                            last_approve_block: block_index,
+                        },
+                    )
+                });
+            }
+            Operation::Transfer {
+                from,
+                spender: Some(spender),
+                amount,
+                fee,
+                ..
+            } if from != spender => {
+                let fee = block.effective_fee.or(fee).unwrap_or_else(|| {
+                    ic_cdk::trap(&format!(
+                        "Block {} is of type Transfer but has no fee or effective fee!",
+                        block_index
+                    ))
+                });
+                let used_allowance = amount.checked_add(&fee).unwrap_or_else(|| {
+                    ic_cdk::trap(&format!(
+                        "token amount overflow while indexing block {block_index}"
+                    ))
+                });
+                use_allowance(block_index, from, spender, used_allowance);
+            }
+            Operation::Burn {
+                from,
+                spender: Some(spender),
+                amount,
+            } if from != spender => use_allowance(block_index, from, spender, amount),
+            _ => {}
+        },
+    );
+}
+
+fn use_allowance(block_index: BlockIndex64, account: Account, spender: Account, amount: Tokens) {
+    with_allowance_block_ids(|allowance_block_ids| {
+        allowance_block_ids.insert(allowance_block_ids_key(account, spender, block_index), ())
+    });
+    // The ledger has already validated the block and thus an inconsistency here can only
+    // stem from an inconsistent view of the allowances in the index. We log it rather than
+    // trapping because trapping would stop the index from indexing any further blocks.
+    let key = allowance_key(account, spender);
+    let Some(mut allowance) = with_allowances(|allowances| allowances.get(&key)) else {
+        log!(
+            P0,
+            "[use_allowance]: block {} uses the allowance of spender {} for account {} but there is no such allowance",
+            block_index,
+            spender,
+            account
+        );
+        return;
+    };
+    allowance.amount = allowance.amount.checked_sub(&amount).unwrap_or_else(|| {
+        log!(
+            P0,
+            "[use_allowance]: block {} uses amount {} of the allowance {} of spender {} for account {}, setting the allowance to zero",
+            block_index,
+            amount,
+            allowance.amount,
+            spender,
+            account
+        );
+        Tokens::zero()
+    });
+    with_allowances(|allowances| {
+        if allowance.amount.is_zero() {
+            allowances.remove(&key);
+        } else {
+            allowances.insert(key, allowance);
+        }
+    });
+}
+
+fn set_backfill_allowances_timer() {
+    if with_state(|state| state.allowances_backfill_next_block.is_some()) {
+        ic_cdk_timers::set_timer(Duration::ZERO, backfill_allowances);
+    }
+}
+
+/// Processes the allowance changes of blocks that were indexed before the
+/// index tracked allowances, e.g., after an upgrade from an older version.
+/// Every call processes a bounded number of blocks, records the next block
+/// to process in the state, and schedules another call until the allowances
+/// are up to date with the block log.
+fn backfill_allowances() {
+    let Some(start) = with_state(|state| state.allowances_backfill_next_block) else {
+        return;
+    };
+    let num_blocks = with_blocks(|blocks| blocks.len());
+    let max_end = num_blocks.min(start.saturating_add(MAX_BLOCKS_TO_BACKFILL_ALLOWANCES));
+    let mut end = start;
+    while end < max_end
+        && ic_cdk::api::instruction_counter() < MAX_INSTRUCTIONS_TO_BACKFILL_ALLOWANCES
+    {
+        let block = get_decoded_block(end).unwrap_or_else(|| {
+            trap(&format!(
+                "Block {} not found in the block log while backfilling allowances",
+                end
+            ))
+        });
+        process_allowance_changes(end, &block);
+        end += 1;
+    }
+    let next_block = if end == num_blocks {
+        log!(
+            P1,
+            "[backfill_allowances]: allowances are up to date with block {}",
+            end
+        );
+        None
+    } else {
+        log!(
+            P1,
+            "[backfill_allowances]: processed blocks {} to {}, resuming from block {}",
+            start,
+            end,
+            end
+        );
+        Some(end)
+    };
+    mutate_state(|state| state.allowances_backfill_next_block = next_block);
+    set_backfill_allowances_timer();
+}
+
+fn queue_notifications(block_index: BlockIndex64, block: &Block<Tokens>) {
+    measure_span(
+        &PROFILING_DATA,
+        "append_blocks.queue_notifications",
+        move || {
+            let mut accounts = get_accounts(block);
+            if let Operation::Approve { spender, .. } = block.transaction.operation {
+                accounts.push(spender);
+            }
+            for account in accounts {
+                let owner = Blob::try_from(account.owner.as_slice()).unwrap();
+                let subscriptions = with_subscriptions(|subscriptions| {
+                    subscriptions
+                        .range((owner, 0)..=(owner, u64::MAX))
+                        .filter(|(_, subscription)| subscription.watches(&account))
+                        .collect::<Vec<_>>()
+                });
+                for ((_, subscription_id), mut subscription) in subscriptions {
+                    let key = (block_index, subscription_id);
+                    // A block is notified once per subscription even if it
+                    // touches more than one of the watched accounts.
+                    if with_notification_queue(|queue| queue.contains_key(&key)) {
+                        continue;
+                    }
+                    if with_notification_queue(|queue| queue.len()) >= MAX_QUEUED_NOTIFICATIONS {
+                        log!(
+                            P1,
+                            "[queue_notifications]: the notification queue is full, dropping block {} for subscription {}",
+                            block_index,
+                            subscription_id
+                        );
+                        subscription.notifications_failed += 1;
+                        with_subscriptions(|subscriptions| {
+                            subscriptions.insert((owner, subscription_id), subscription)
+                        });
+                        continue;
+                    }
+                    with_notification_queue(|queue| {
+                        queue.insert(key, (owner, *account.effective_subaccount()))
+                    });
+                }
+            }
+        },
+    );
+}
+
+/// Sends at most [MAX_NOTIFICATIONS_PER_ROUND] queued notifications.
+/// Notifications are best effort: a notification that cannot be sent
+/// is counted as failed and dropped.
+fn send_notifications() {
+    let queued = with_notification_queue(|queue| {
+        queue
+            .iter()
+            .take(MAX_NOTIFICATIONS_PER_ROUND as usize)
+            .collect::<Vec<_>>()
+    });
+    for ((block_index, subscription_id), (owner, subaccount)) in queued {
+        with_notification_queue(|queue| queue.remove(&(block_index, subscription_id)));
+        let key = (owner, subscription_id);
+        // The subscription may have been removed after the notification was queued.
+        let Some(mut subscription) = with_subscriptions(|subscriptions| subscriptions.get(&key))
+        else {
+            continue;
+        };
+        let block = get_decoded_block(block_index).unwrap_or_else(|| {
+            trap(&format!(
+                "Block {} of a queued notification not found",
+                block_index
+            ))
+        });
+        let notification = BlockNotification {
+            subscription_id,
+            account: Account {
+                owner: Principal::from_slice(owner.as_slice()),
+                subaccount: Some(subaccount),
+            },
+            block_index: block_index.into(),
+            transaction: block.into(),
+        };
+        let result = ic_cdk::api::call::notify(
+            subscription.subscriber,
+            &subscription.method,
+            (notification,),
+        );
+        subscription.last_notified_block = Some(block_index);
+        let failed = match result {
+            Ok(()) => {
+                subscription.notifications_sent += 1;
+                false
+            }
+            Err(code) => {
+                log!(
+                    P1,
+                    "[send_notifications]: failed to notify block {} to subscription {}. Error code: {:?}",
+                    block_index,
+                    subscription_id,
+                    code
+                );
+                subscription.notifications_failed += 1;
+                true
+            }
+        };
+        with_subscriptions(|subscriptions| subscriptions.insert(key, subscription));
+        // The output queue of the index is most likely full,
+        // try again with the next notifications in the next round.
+        if failed {
+            break;
+        }
+    }
+}
+
 fn debit(block_index: BlockIndex64, account: Account, amount: Tokens) {
     change_balance(account, |balance| {
         balance.checked_sub(&amount).unwrap_or_else(|| {
@@ -920,6 +1399,21 @@ fn account_block_ids_key(account: Account, block_index: BlockIndex64) -> Account
     (account_sha256(account), Reverse(block_index))
 }
 
+fn allowance_sha256(account: Account, spender: Account) -> [u8; Sha256::DIGEST_LEN] {
+    let mut hasher = Sha256::new();
+    account.hash(&mut hasher);
+    spender.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn allowance_block_ids_key(
+    account: Account,
+    spender: Account,
+    block_index: BlockIndex64,
+) -> AllowanceBlockIdsMapKey {
+    (allowance_sha256(account, spender), Reverse(block_index))
+}
+
 fn decode_icrc1_block(_txid: u64, bytes: Vec<u8>) -> GenericBlock {
     let encoded_block = EncodedBlock::from(bytes);
     encoded_block_to_generic_block(&encoded_block)
@@ -970,43 +1464,107 @@ fn get_account_transactions(arg: GetAccountTransactionsArgs) -> GetAccountTransa
     let start = arg
         .start
         .map_or(u64::MAX, |n| n.0.to_u64().expect("start must be a u64!"));
+    // A filtered request may have to skip many transactions before finding
+    // the matching ones, so it scans more than max_results transactions.
+    let max_scanned = if arg.filter.is_some() {
+        MAX_TRANSACTIONS_SCANNED_PER_FILTERED_REQUEST
+    } else {
+        length
+    };
     let key = account_block_ids_key(arg.account, start);
     let mut transactions = vec![];
-    let indices = with_account_block_ids(|account_block_ids| {
-        account_block_ids
+    let mut last_scanned_tx_id = None;
+    with_account_block_ids(|account_block_ids| {
+        let indices = account_block_ids
             .range(key..)
             // old txs of the requested account and skip the start index
             .take_while(|(k, _)| k.0 == key.0)
             .filter(|(k, _)| k.1 .0 < start)
-            .take(length)
-            .map(|(k, _)| k.1 .0)
-            .collect::<Vec<BlockIndex64>>()
-    });
-    for id in indices {
-        let block = with_blocks(|blocks| {
-            blocks.get(id).unwrap_or_else(|| {
+            .take(max_scanned)
+            .map(|(k, _)| k.1 .0);
+        for id in indices {
+            if transactions.len() >= length {
+                break;
+            }
+            last_scanned_tx_id = Some(id);
+            let block = get_decoded_block(id).unwrap_or_else(|| {
                 trap(&format!(
                     "Block {} not found in the block log, account blocks map is corrupted!",
                     id
                 ))
-            })
-        });
-        let transaction = encoded_block_bytes_to_flat_transaction(id, block);
-        let transaction_with_idx = TransactionWithId {
-            id: id.into(),
-            transaction,
-        };
-        transactions.push(transaction_with_idx);
-    }
+            });
+            if arg
+                .filter
+                .as_ref()
+                .is_none_or(|filter| matches_filter(&block, filter))
+            {
+                transactions.push(TransactionWithId {
+                    id: id.into(),
+                    transaction: block.into(),
+                });
+            }
+        }
+    });
     let oldest_tx_id = get_oldest_tx_id(arg.account).map(|tx_id| tx_id.into());
     let balance = get_balance(arg.account).into();
     Ok(GetAccountTransactionsResponse {
         balance,
         transactions,
         oldest_tx_id,
+        last_scanned_tx_id: last_scanned_tx_id.map(|tx_id| tx_id.into()),
     })
 }
 
+fn matches_filter(block: &Block<Tokens>, filter: &TransactionFilter) -> bool {
+    let (kind, amount) = match block.transaction.operation {
+        Operation::Mint { amount, .. } => (TransactionKind::Mint, amount),
+        Operation::Burn { amount, .. } => (TransactionKind::Burn, amount),
+        Operation::Transfer { amount, .. } => (TransactionKind::Transfer, amount),
+        Operation::Approve { amount, .. } => (TransactionKind::Approve, amount),
+    };
+    if let Some(kinds) = &filter.kinds {
+        if !kinds.contains(&kind) {
+            return false;
+        }
+    }
+    if let Some(counterparty) = filter.counterparty {
+        let spender = match block.transaction.operation {
+            Operation::Transfer { spender, .. } | Operation::Burn { spender, .. } => spender,
+            Operation::Approve { spender, .. } => Some(spender),
+            Operation::Mint { .. } => None,
+        };
+        if !get_accounts(block)
+            .into_iter()
+            .chain(spender)
+            .any(|account| account == counterparty)
+        {
+            return false;
+        }
+    }
+    let amount: Nat = amount.into();
+    if filter.min_amount.as_ref().is_some_and(|min| &amount < min)
+        || filter.max_amount.as_ref().is_some_and(|max| &amount > max)
+    {
+        return false;
+    }
+    if filter
+        .min_timestamp
+        .is_some_and(|min| block.timestamp < min)
+        || filter
+            .max_timestamp
+            .is_some_and(|max| block.timestamp > max)
+    {
+        return false;
+    }
+    if let Some(memo_prefix) = &filter.memo_prefix {
+        match &block.transaction.memo {
+            Some(memo) if memo.0.starts_with(&memo_prefix.0) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
 fn encoded_block_bytes_to_flat_transaction(
     block_index: BlockIndex64,
     block: Vec<u8>,
@@ -1078,6 +1636,211 @@ fn list_subaccounts(args: ListSubaccountsArgs) -> Vec<Subaccount> {
     })
 }
 
+#[query]
+#[candid_method(query)]
+fn get_account_allowances(args: GetAccountAllowancesArgs) -> Vec<AccountAllowance> {
+    let length = args
+        .max_results
+        .0
+        .to_u64()
+        .expect("The length must be a u64!")
+        .min(with_state(|opts| opts.max_blocks_per_response))
+        .min(usize::MAX as u64) as usize;
+    let owner_key = account_key(args.account);
+    let start_key = match args.start {
+        Some(spender) => Excluded(allowance_key(args.account, spender)),
+        None => Included((owner_key, (Blob::try_from(&[][..]).unwrap(), [0u8; 32]))),
+    };
+    let now = ic_cdk::api::time();
+    with_allowances(|allowances| {
+        allowances
+            .range((start_key, Unbounded))
+            .take_while(|((account, _), _)| account == &owner_key)
+            // the Ledger doesn't report expired allowances
+            .filter(|(_, allowance)| {
+                allowance
+                    .expires_at
+                    .is_none_or(|expires_at| expires_at > now)
+            })
+            .take(length)
+            .map(
+                |((_, (spender_owner, spender_subaccount)), allowance)| AccountAllowance {
+                    spender: Account {
+                        owner: Principal::from_slice(spender_owner.as_slice()),
+                        subaccount: Some(spender_subaccount),
+                    },
+                    allowance: allowance.amount.into(),
+                    expires_at: allowance.expires_at,
+                    since_block: allowance.since_block.into(),
+                    since_timestamp: allowance.since_timestamp,
+                    last_approve_block: allowance.last_approve_block.into(),
+                },
+            )
+            .collect()
+    })
+}
+
+#[query]
+#[candid_method(query)]
+fn get_allowance_history(args: GetAllowanceHistoryArgs) -> Vec<TransactionWithId> {
+    let length = args
+        .max_results
+        .0
+        .to_u64()
+        .expect("The length must be a u64!")
+        .min(with_state(|opts| opts.max_blocks_per_response))
+        .min(usize::MAX as u64) as usize;
+    let start = args
+        .start
+        .map_or(u64::MAX, |n| n.0.to_u64().expect("start must be a u64!"));
+    let key = allowance_block_ids_key(args.account, args.spender, start);
+    let indices = with_allowance_block_ids(|allowance_block_ids| {
+        allowance_block_ids
+            .range(key..)
+            // old txs of the requested pair and skip the start index
+            .take_while(|(k, _)| k.0 == key.0)
+            .filter(|(k, _)| k.1 .0 < start)
+            .take(length)
+            .map(|(k, _)| k.1 .0)
+            .collect::<Vec<BlockIndex64>>()
+    });
+    indices
+        .into_iter()
+        .map(|id| {
+            let block = with_blocks(|blocks| {
+                blocks.get(id).unwrap_or_else(|| {
+                    trap(&format!(
+                        "Block {} not found in the block log, allowance blocks map is corrupted!",
+                        id
+                    ))
+                })
+            });
+            TransactionWithId {
+                id: id.into(),
+                transaction: encoded_block_bytes_to_flat_transaction(id, block),
+            }
+        })
+        .collect()
+}
+
+#[update]
+#[candid_method(update)]
+fn subscribe(args: SubscribeArgs) -> SubscribeResult {
+    let subscriber = ic_cdk::caller();
+    if PrincipalId(subscriber).class() != Ok(PrincipalIdClass::Opaque) {
+        return Err(SubscribeError::CallerNotACanister);
+    }
+    if args.method.is_empty() || args.method.len() > MAX_SUBSCRIPTION_METHOD_LENGTH {
+        return Err(SubscribeError::InvalidArgument {
+            message: format!(
+                "the method name must be between 1 and {} bytes long",
+                MAX_SUBSCRIPTION_METHOD_LENGTH
+            ),
+        });
+    }
+    if args
+        .subaccount_prefix
+        .as_ref()
+        .is_some_and(|prefix| prefix.len() > 32)
+    {
+        return Err(SubscribeError::InvalidArgument {
+            message: "the subaccount prefix must be at most 32 bytes long".to_string(),
+        });
+    }
+    let subscriber_key = Blob::try_from(subscriber.as_slice()).unwrap();
+    let num_subscriptions = with_subscriptions(|subscriptions| subscriptions.len());
+    let num_subscriber_subscriptions = with_subscriber_subscriptions(|subscriber_subscriptions| {
+        subscriber_subscriptions
+            .range((subscriber_key, 0)..=(subscriber_key, u64::MAX))
+            .count() as u64
+    });
+    if num_subscriptions >= MAX_SUBSCRIPTIONS {
+        return Err(SubscribeError::TooManySubscriptions {
+            limit: MAX_SUBSCRIPTIONS,
+        });
+    }
+    if num_subscriber_subscriptions >= MAX_SUBSCRIPTIONS_PER_SUBSCRIBER {
+        return Err(SubscribeError::TooManySubscriptions {
+            limit: MAX_SUBSCRIPTIONS_PER_SUBSCRIBER,
+        });
+    }
+    // The fee is only accepted once the subscription is going to be
+    // registered, otherwise the attached cycles are refunded.
+    if ic_cdk::api::call::msg_cycles_available128() < SUBSCRIPTION_FEE {
+        return Err(SubscribeError::InsufficientCycles {
+            required: Nat::from(SUBSCRIPTION_FEE),
+        });
+    }
+    ic_cdk::api::call::msg_cycles_accept128(SUBSCRIPTION_FEE);
+    let id = with_state(|state| state.next_subscription_id);
+    mutate_state(|state| state.next_subscription_id += 1);
+    let owner = Blob::try_from(args.owner.as_slice()).unwrap();
+    with_subscriptions(|subscriptions| {
+        subscriptions.insert(
+            (owner, id),
+            SubscriptionData {
+                subscriber,
+                subaccount_prefix: args.subaccount_prefix,
+                method: args.method,
+                notifications_sent: 0,
+                notifications_failed: 0,
+                last_notified_block: None,
+            },
+        )
+    });
+    with_subscriber_subscriptions(|subscriber_subscriptions| {
+        subscriber_subscriptions.insert((subscriber_key, id), owner)
+    });
+    Ok(id)
+}
+
+#[update]
+#[candid_method(update)]
+fn unsubscribe(subscription_id: u64) -> bool {
+    let subscriber = Blob::try_from(ic_cdk::caller().as_slice()).unwrap();
+    match with_subscriber_subscriptions(|subscriber_subscriptions| {
+        subscriber_subscriptions.remove(&(subscriber, subscription_id))
+    }) {
+        Some(owner) => {
+            with_subscriptions(|subscriptions| subscriptions.remove(&(owner, subscription_id)))
+                .is_some()
+        }
+        None => false,
+    }
+}
+
+#[query]
+#[candid_method(query)]
+fn list_subscriptions() -> Vec<Subscription> {
+    let subscriber = ic_cdk::caller();
+    let subscriber_key = Blob::try_from(subscriber.as_slice()).unwrap();
+    let keys = with_subscriber_subscriptions(|subscriber_subscriptions| {
+        subscriber_subscriptions
+            .range((subscriber_key, 0)..=(subscriber_key, u64::MAX))
+            .map(|((_, id), owner)| (owner, id))
+            .collect::<Vec<_>>()
+    });
+    with_subscriptions(|subscriptions| {
+        keys.into_iter()
+            .filter_map(|key| {
+                subscriptions
+                    .get(&key)
+                    .map(|subscription| (key, subscription))
+            })
+            .map(|((owner, id), subscription)| Subscription {
+                id,
+                subscriber,
+                owner: Principal::from_slice(owner.as_slice()),
+                subaccount_prefix: subscription.subaccount_prefix,
+                method: subscription.method,
+                notifications_sent: subscription.notifications_sent,
+                notifications_failed: subscription.notifications_failed,
+                last_notified_block: subscription.last_notified_block.map(|block| block.into()),
+            })
+            .collect()
+    })
+}
+
 #[query(hidden = true, decoding_quota = 10000)]
 fn http_request(req: HttpRequest) -> HttpResponse {
     if req.path() == "/metrics" {
@@ -1152,6 +1915,16 @@ pub fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> st
             .min(f64::MAX as u128) as f64,
         "Last amount of time waited between two transactions fetch.",
     )?;
+    w.encode_gauge(
+        "index_number_of_subscriptions",
+        with_subscriptions(|subscriptions| subscriptions.len()) as f64,
+        "Total number of subscriptions to the blocks of the accounts.",
+    )?;
+    w.encode_gauge(
+        "index_number_of_queued_notifications",
+        with_notification_queue(|queue| queue.len()) as f64,
+        "Number of notifications waiting to be sent to the subscribers.",
+    )?;
     PROFILING_DATA.with(|cell| -> std::io::Result<()> {
         cell.borrow().record_metrics(w.histogram_vec(
             "index_ng_profile_instructions",
@@ -7,21 +7,27 @@ use candid::{Decode, Encode, Nat, Principal};
 use ic_agent::identity::Identity;
 use ic_base_types::{CanisterId, PrincipalId};
 use ic_icrc1_index_ng::{
-    FeeCollectorRanges, GetAccountTransactionsArgs, GetAccountTransactionsResponse,
-    GetAccountTransactionsResult, GetBlocksResponse, IndexArg, InitArg as IndexInitArg,
-    ListSubaccountsArgs, TransactionWithId, DEFAULT_MAX_BLOCKS_PER_RESPONSE,
+    AccountAllowance, FeeCollectorRanges, GetAccountAllowancesArgs, GetAccountTransactionsArgs,
+    GetAccountTransactionsResponse, GetAccountTransactionsResult, GetAllowanceHistoryArgs,
+    GetBlocksResponse, IndexArg, InitArg as IndexInitArg, ListSubaccountsArgs, SubscribeArgs,
+    SubscribeError, SubscribeResult, Subscription, TransactionFilter, TransactionKind,
+    TransactionWithId, DEFAULT_MAX_BLOCKS_PER_RESPONSE, SUBSCRIPTION_FEE,
 };
 use ic_icrc1_ledger::{ChangeFeeCollector, LedgerArgument, UpgradeArgs as LedgerUpgradeArgs};
 use ic_icrc1_test_utils::{
     minter_identity, valid_transactions_strategy, ArgWithCaller, LedgerEndpointArg,
 };
 use ic_rosetta_test_utils::test_http_request_decoding_quota;
-use ic_state_machine_tests::StateMachine;
+use ic_state_machine_tests::{StateMachine, WasmResult};
+use ic_types::Cycles;
+use ic_universal_canister::{call_args, wasm, UNIVERSAL_CANISTER_WASM};
 use icrc_ledger_types::icrc1::account::{Account, Subaccount};
-use icrc_ledger_types::icrc1::transfer::{BlockIndex, TransferArg, TransferError};
+use icrc_ledger_types::icrc1::transfer::{BlockIndex, Memo, TransferArg, TransferError};
 use icrc_ledger_types::icrc2::approve::{ApproveArgs, ApproveError};
+use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
 use icrc_ledger_types::icrc3::blocks::GetBlocksRequest;
 use icrc_ledger_types::icrc3::transactions::{Mint, Transaction, Transfer};
+use icrc_ledger_types::icrc4::transfer_batch::{TransferBatchArgs, TransferBatchResults};
 use num_traits::cast::ToPrimitive;
 use proptest::test_runner::{Config as TestRunnerConfig, TestRunner};
 use std::collections::HashSet;
@@ -188,6 +194,25 @@ fn transfer(
     icrc1_transfer(env, ledger_id, owner.into(), req)
 }
 
+fn icrc4_transfer_batch(
+    env: &StateMachine,
+    ledger_id: CanisterId,
+    caller: PrincipalId,
+    args: TransferBatchArgs,
+) -> TransferBatchResults {
+    let req = Encode!(&args).expect("Failed to encode TransferBatchArgs");
+    let res = env
+        .execute_ingress_as(caller, ledger_id, "icrc4_transfer_batch", req)
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to transfer tokens in a batch. caller:{} args:{:?} error:{}",
+                caller, args, e
+            )
+        })
+        .bytes();
+    Decode!(&res, TransferBatchResults).expect("Failed to decode TransferBatchResults")
+}
+
 fn icrc2_approve(
     env: &StateMachine,
     ledger_id: CanisterId,
@@ -237,17 +262,113 @@ fn approve(
         .unwrap()
 }
 
+fn transfer_from(
+    env: &StateMachine,
+    ledger_id: CanisterId,
+    from: Account,
+    to: Account,
+    spender: Account,
+    amount: u64,
+) -> BlockIndex {
+    let arg = TransferFromArgs {
+        spender_subaccount: spender.subaccount,
+        from,
+        to,
+        amount: amount.into(),
+        fee: None,
+        memo: None,
+        created_at_time: None,
+    };
+    let req = Encode!(&arg).expect("Failed to encode TransferFromArgs");
+    let res = env
+        .execute_ingress_as(
+            PrincipalId(spender.owner),
+            ledger_id,
+            "icrc2_transfer_from",
+            req,
+        )
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to transfer_from tokens. spender:{} arg:{:?} error:{}",
+                spender, arg, e
+            )
+        })
+        .bytes();
+    Decode!(&res, Result<BlockIndex, TransferFromError>)
+        .expect("Failed to decode Result<BlockIndex, TransferFromError>")
+        .unwrap_or_else(|e| {
+            panic!(
+                "Failed to transfer_from tokens. spender:{} arg:{:?} error:{:?}",
+                spender, arg, e
+            )
+        })
+}
+
+fn get_account_allowances(
+    env: &StateMachine,
+    index_id: CanisterId,
+    account: Account,
+    start: Option<Account>,
+    max_results: u64,
+) -> Vec<AccountAllowance> {
+    let req = GetAccountAllowancesArgs {
+        account,
+        start,
+        max_results: max_results.into(),
+    };
+    let req = Encode!(&req).expect("Failed to encode GetAccountAllowancesArgs");
+    let res = env
+        .execute_ingress(index_id, "get_account_allowances", req)
+        .expect("Failed to get_account_allowances")
+        .bytes();
+    Decode!(&res, Vec<AccountAllowance>).expect("Failed to decode Vec<AccountAllowance>")
+}
+
+fn get_allowance_history(
+    env: &StateMachine,
+    index_id: CanisterId,
+    account: Account,
+    spender: Account,
+    start: Option<u64>,
+    max_results: u64,
+) -> Vec<TransactionWithId> {
+    let req = GetAllowanceHistoryArgs {
+        account,
+        spender,
+        start: start.map(|n| n.into()),
+        max_results: max_results.into(),
+    };
+    let req = Encode!(&req).expect("Failed to encode GetAllowanceHistoryArgs");
+    let res = env
+        .execute_ingress(index_id, "get_allowance_history", req)
+        .expect("Failed to get_allowance_history")
+        .bytes();
+    Decode!(&res, Vec<TransactionWithId>).expect("Failed to decode Vec<TransactionWithId>")
+}
+
 fn get_account_transactions(
     env: &StateMachine,
     index_id: CanisterId,
     account: Account,
     start: Option<u64>,
     max_results: u64,
+) -> GetAccountTransactionsResponse {
+    get_filtered_account_transactions(env, index_id, account, start, max_results, None)
+}
+
+fn get_filtered_account_transactions(
+    env: &StateMachine,
+    index_id: CanisterId,
+    account: Account,
+    start: Option<u64>,
+    max_results: u64,
+    filter: Option<TransactionFilter>,
 ) -> GetAccountTransactionsResponse {
     let req = GetAccountTransactionsArgs {
         account,
         start: start.map(|n| n.into()),
         max_results: max_results.into(),
+        filter,
     };
     let req = Encode!(&req).expect("Failed to encode GetAccountTransactionsArgs");
     let res = env
@@ -283,6 +404,84 @@ fn list_subaccounts(
     .expect("failed to decode list_subaccounts response")
 }
 
+fn subscribe(
+    env: &StateMachine,
+    index: CanisterId,
+    caller: PrincipalId,
+    args: SubscribeArgs,
+) -> SubscribeResult {
+    Decode!(
+        &env.execute_ingress_as(caller, index, "subscribe", Encode!(&args).unwrap())
+            .expect("failed to subscribe")
+            .bytes(),
+        SubscribeResult
+    )
+    .expect("failed to decode subscribe response")
+}
+
+// Subscribes via the given universal canister attaching the given cycles.
+fn subscribe_with_cycles(
+    env: &StateMachine,
+    index: CanisterId,
+    subscriber: CanisterId,
+    args: SubscribeArgs,
+    cycles: u128,
+) -> SubscribeResult {
+    let payload = wasm()
+        .call_with_cycles(
+            index,
+            "subscribe",
+            call_args()
+                .other_side(Encode!(&args).unwrap())
+                .on_reject(wasm().reject_message().reject()),
+            Cycles::new(cycles),
+        )
+        .build();
+    match env
+        .execute_ingress(subscriber, "update", payload)
+        .expect("failed to subscribe")
+    {
+        WasmResult::Reply(bytes) => {
+            Decode!(&bytes, SubscribeResult).expect("failed to decode subscribe response")
+        }
+        WasmResult::Reject(reject) => panic!("subscribe was rejected: {}", reject),
+    }
+}
+
+fn unsubscribe(
+    env: &StateMachine,
+    index: CanisterId,
+    caller: PrincipalId,
+    subscription_id: u64,
+) -> bool {
+    Decode!(
+        &env.execute_ingress_as(
+            caller,
+            index,
+            "unsubscribe",
+            Encode!(&subscription_id).unwrap()
+        )
+        .expect("failed to unsubscribe")
+        .bytes(),
+        bool
+    )
+    .expect("failed to decode unsubscribe response")
+}
+
+fn list_subscriptions(
+    env: &StateMachine,
+    index: CanisterId,
+    caller: PrincipalId,
+) -> Vec<Subscription> {
+    Decode!(
+        &env.execute_ingress_as(caller, index, "list_subscriptions", Encode!(&()).unwrap())
+            .expect("failed to list_subscriptions")
+            .bytes(),
+        Vec<Subscription>
+    )
+    .expect("failed to decode list_subscriptions response")
+}
+
 fn get_fee_collectors_ranges(env: &StateMachine, index: CanisterId) -> FeeCollectorRanges {
     Decode!(
         &env.execute_ingress(index, "get_fee_collectors_ranges", Encode!(&()).unwrap())
@@ -746,6 +945,204 @@ fn test_get_account_transactions_pagination() {
     }
 }
 
+#[test]
+fn test_get_account_transactions_filter() {
+    let env = &StateMachine::new();
+    let minter = minter_identity().sender().unwrap();
+    let ledger_id = install_ledger(
+        env,
+        vec![(account(1, 0), 10_000_000)], // txid: 0
+        default_archive_options(),
+        None,
+        minter,
+    );
+    let index_id = install_index_ng(env, index_init_arg_without_interval(ledger_id));
+
+    let transfer_with_memo = |from: Account, to: Account, amount: u64, memo: &[u8]| {
+        let arg = TransferArg {
+            from_subaccount: from.subaccount,
+            to,
+            amount: amount.into(),
+            created_at_time: None,
+            fee: None,
+            memo: Some(Memo::from(memo.to_vec())),
+        };
+        icrc1_transfer(env, ledger_id, PrincipalId(from.owner), arg)
+    };
+
+    transfer(env, ledger_id, account(1, 0), account(2, 0), 100_000); // txid: 1
+    transfer(env, ledger_id, account(1, 0), account(3, 0), 200_000); // txid: 2
+    env.advance_time(Duration::from_secs(60));
+    transfer_with_memo(account(3, 0), account(1, 0), 50_000, b"invoice-42"); // txid: 3
+    transfer_with_memo(account(2, 0), account(1, 0), 30_000, b"invoice-43"); // txid: 4
+    approve(env, ledger_id, account(1, 0), account(4, 0), 1_000); // txid: 5
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+
+    let filtered_ids = |start: Option<u64>, max_results: u64, filter: TransactionFilter| {
+        get_filtered_account_transactions(
+            env,
+            index_id,
+            account(1, 0),
+            start,
+            max_results,
+            Some(filter),
+        )
+        .transactions
+        .into_iter()
+        .map(|tx| tx.id.0.to_u64().unwrap())
+        .collect::<Vec<_>>()
+    };
+
+    // Filter by kind.
+    let kinds = |kinds: Vec<TransactionKind>| TransactionFilter {
+        kinds: Some(kinds),
+        ..Default::default()
+    };
+    assert_eq!(
+        filtered_ids(None, u64::MAX, kinds(vec![TransactionKind::Mint])),
+        vec![0]
+    );
+    assert_eq!(
+        filtered_ids(None, u64::MAX, kinds(vec![TransactionKind::Approve])),
+        vec![5]
+    );
+    assert_eq!(
+        filtered_ids(
+            None,
+            u64::MAX,
+            kinds(vec![TransactionKind::Mint, TransactionKind::Approve])
+        ),
+        vec![5, 0]
+    );
+
+    // Filter by counterparty, alone and combined with the kind.
+    let counterparty = |counterparty: Account| TransactionFilter {
+        counterparty: Some(counterparty),
+        ..Default::default()
+    };
+    assert_eq!(
+        filtered_ids(None, u64::MAX, counterparty(account(3, 0))),
+        vec![3, 2]
+    );
+    assert_eq!(
+        filtered_ids(None, u64::MAX, counterparty(account(4, 0))),
+        vec![5]
+    );
+    assert_eq!(
+        filtered_ids(
+            None,
+            u64::MAX,
+            TransactionFilter {
+                kinds: Some(vec![TransactionKind::Transfer]),
+                ..counterparty(account(2, 0))
+            }
+        ),
+        vec![4, 1]
+    );
+
+    // Filter by amount range, bounds included.
+    assert_eq!(
+        filtered_ids(
+            None,
+            u64::MAX,
+            TransactionFilter {
+                min_amount: Some(100_000u64.into()),
+                ..Default::default()
+            }
+        ),
+        vec![2, 1, 0]
+    );
+    assert_eq!(
+        filtered_ids(
+            None,
+            u64::MAX,
+            TransactionFilter {
+                min_amount: Some(30_000u64.into()),
+                max_amount: Some(100_000u64.into()),
+                ..Default::default()
+            }
+        ),
+        vec![4, 3, 1]
+    );
+
+    // Filter by timestamp range, bounds included.
+    let all_txs = get_account_transactions(env, index_id, account(1, 0), None, u64::MAX);
+    let timestamp_of = |id: u64| {
+        all_txs
+            .transactions
+            .iter()
+            .find(|tx| tx.id == id)
+            .unwrap()
+            .transaction
+            .timestamp
+    };
+    assert_eq!(
+        filtered_ids(
+            None,
+            u64::MAX,
+            TransactionFilter {
+                min_timestamp: Some(timestamp_of(3)),
+                ..Default::default()
+            }
+        ),
+        vec![5, 4, 3]
+    );
+    assert_eq!(
+        filtered_ids(
+            None,
+            u64::MAX,
+            TransactionFilter {
+                max_timestamp: Some(timestamp_of(2)),
+                ..Default::default()
+            }
+        ),
+        vec![2, 1, 0]
+    );
+
+    // Filter by memo prefix.
+    let memo_prefix = |prefix: &[u8]| TransactionFilter {
+        memo_prefix: Some(Memo::from(prefix.to_vec())),
+        ..Default::default()
+    };
+    assert_eq!(
+        filtered_ids(None, u64::MAX, memo_prefix(b"invoice-")),
+        vec![4, 3]
+    );
+    assert_eq!(
+        filtered_ids(None, u64::MAX, memo_prefix(b"invoice-42")),
+        vec![3]
+    );
+    assert_eq!(
+        filtered_ids(None, u64::MAX, memo_prefix(b"invoice-42-1")),
+        Vec::<u64>::new()
+    );
+
+    // Paginate through the filtered transactions.
+    let res = get_filtered_account_transactions(
+        env,
+        index_id,
+        account(1, 0),
+        None,
+        1,
+        Some(memo_prefix(b"invoice-")),
+    );
+    assert_eq!(res.transactions.len(), 1);
+    assert_eq!(res.last_scanned_tx_id, Some(4u8.into()));
+    assert_eq!(filtered_ids(Some(4), 1, memo_prefix(b"invoice-")), vec![3]);
+
+    // The last scanned txid is set even if no transaction matches.
+    let res = get_filtered_account_transactions(
+        env,
+        index_id,
+        account(1, 0),
+        None,
+        u64::MAX,
+        Some(kinds(vec![TransactionKind::Burn])),
+    );
+    assert_eq!(res.transactions, vec![]);
+    assert_eq!(res.last_scanned_tx_id, Some(0u8.into()));
+}
+
 #[test]
 fn test_icrc1_balance_of() {
     // 1 case only because the test is expensive to run.
@@ -889,6 +1286,172 @@ fn test_list_subaccounts() {
     assert_eq!(expected_batch_2, batch_2);
 }
 
+#[test]
+fn test_subscriptions() {
+    let env = &StateMachine::new();
+    let minter = minter_identity().sender().unwrap();
+    let ledger_id = install_ledger(
+        env,
+        vec![(account(1, 0), 10_000_000)], // txid: 0
+        default_archive_options(),
+        None,
+        minter,
+    );
+    let index_id = install_index_ng(env, index_init_arg_without_interval(ledger_id));
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+
+    let subscriber_id = env
+        .install_canister_with_cycles(
+            UNIVERSAL_CANISTER_WASM.to_vec(),
+            vec![],
+            None,
+            Cycles::new(100 * SUBSCRIPTION_FEE),
+        )
+        .unwrap();
+    let subscriber = PrincipalId::from(subscriber_id);
+    let watch_owner_1 = SubscribeArgs {
+        owner: account(1, 0).owner,
+        subaccount_prefix: None,
+        method: "on_block".to_string(),
+    };
+    // Watch account(2, 1) but not account(2, 2).
+    let watch_account_2_1 = SubscribeArgs {
+        owner: account(2, 1).owner,
+        subaccount_prefix: Some(account(2, 1).subaccount.unwrap()[..16].to_vec()),
+        method: "on_block".to_string(),
+    };
+
+    // Only canisters can subscribe.
+    assert_eq!(
+        subscribe(
+            env,
+            index_id,
+            PrincipalId::new_user_test_id(1),
+            watch_owner_1.clone()
+        ),
+        Err(SubscribeError::CallerNotACanister)
+    );
+    assert!(matches!(
+        subscribe(
+            env,
+            index_id,
+            subscriber,
+            SubscribeArgs {
+                method: "".to_string(),
+                ..watch_owner_1.clone()
+            }
+        ),
+        Err(SubscribeError::InvalidArgument { .. })
+    ));
+    assert!(matches!(
+        subscribe(
+            env,
+            index_id,
+            subscriber,
+            SubscribeArgs {
+                subaccount_prefix: Some(vec![0; 33]),
+                ..watch_owner_1.clone()
+            }
+        ),
+        Err(SubscribeError::InvalidArgument { .. })
+    ));
+    // The subscription fee must be attached.
+    assert_eq!(
+        subscribe_with_cycles(
+            env,
+            index_id,
+            subscriber_id,
+            watch_owner_1.clone(),
+            SUBSCRIPTION_FEE - 1
+        ),
+        Err(SubscribeError::InsufficientCycles {
+            required: Nat::from(SUBSCRIPTION_FEE)
+        })
+    );
+
+    let index_balance = env.cycle_balance(index_id);
+    let id_1 = subscribe_with_cycles(
+        env,
+        index_id,
+        subscriber_id,
+        watch_owner_1.clone(),
+        SUBSCRIPTION_FEE,
+    )
+    .unwrap();
+    let id_2 = subscribe_with_cycles(
+        env,
+        index_id,
+        subscriber_id,
+        watch_account_2_1.clone(),
+        2 * SUBSCRIPTION_FEE,
+    )
+    .unwrap();
+    assert_ne!(id_1, id_2);
+    // Only the fee is accepted, the rest of the attached cycles is refunded.
+    assert!(
+        env.cycle_balance(index_id) - index_balance < 2 * SUBSCRIPTION_FEE + SUBSCRIPTION_FEE / 2
+    );
+    assert!(
+        env.cycle_balance(index_id) - index_balance > 2 * SUBSCRIPTION_FEE - SUBSCRIPTION_FEE / 2
+    );
+
+    transfer(env, ledger_id, account(1, 0), account(2, 1), 100_000); // txid: 1
+    transfer(env, ledger_id, account(1, 0), account(2, 2), 100_000); // txid: 2
+    transfer(env, ledger_id, account(1, 0), account(1, 5), 100_000); // txid: 3
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+
+    // Blocks already indexed when subscribing are not notified and a block
+    // touching two watched accounts is notified once.
+    let subscription_1 = Subscription {
+        id: id_1,
+        subscriber: subscriber.0,
+        owner: watch_owner_1.owner,
+        subaccount_prefix: watch_owner_1.subaccount_prefix,
+        method: watch_owner_1.method,
+        notifications_sent: 3,
+        notifications_failed: 0,
+        last_notified_block: Some(3u8.into()),
+    };
+    let subscription_2 = Subscription {
+        id: id_2,
+        subscriber: subscriber.0,
+        owner: watch_account_2_1.owner,
+        subaccount_prefix: watch_account_2_1.subaccount_prefix,
+        method: watch_account_2_1.method,
+        notifications_sent: 1,
+        notifications_failed: 0,
+        last_notified_block: Some(1u8.into()),
+    };
+    let mut subscriptions = list_subscriptions(env, index_id, subscriber);
+    subscriptions.sort_by_key(|subscription| subscription.id);
+    assert_eq!(subscriptions, vec![subscription_1.clone(), subscription_2]);
+    assert_eq!(
+        list_subscriptions(env, index_id, PrincipalId::new_user_test_id(1)),
+        vec![]
+    );
+
+    // Only the subscriber can unsubscribe.
+    assert!(!unsubscribe(
+        env,
+        index_id,
+        PrincipalId::new_user_test_id(1),
+        id_2
+    ));
+    assert!(unsubscribe(env, index_id, subscriber, id_2));
+    assert!(!unsubscribe(env, index_id, subscriber, id_2));
+
+    transfer(env, ledger_id, account(1, 0), account(2, 1), 100_000); // txid: 4
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+    assert_eq!(
+        list_subscriptions(env, index_id, subscriber),
+        vec![Subscription {
+            notifications_sent: 4,
+            last_notified_block: Some(4u8.into()),
+            ..subscription_1
+        }]
+    );
+}
+
 #[test]
 fn test_post_upgrade_start_timer() {
     let env = &StateMachine::new();
@@ -991,6 +1554,127 @@ fn test_oldest_tx_id() {
     assert_eq!(get_fee_collectors_ranges(env, index_id).ranges, vec![]);
 }
 
+#[test]
+fn test_get_account_allowances() {
+    let env = &StateMachine::new();
+    let minter = minter_identity().sender().unwrap();
+    let ledger_id = install_ledger(
+        env,
+        vec![(account(1, 0), 10_000_000)], // txid: 0
+        default_archive_options(),
+        None,
+        minter,
+    );
+    let index_id = install_index_ng(env, index_init_arg_without_interval(ledger_id));
+
+    approve(env, ledger_id, account(1, 0), account(2, 0), 1_000_000); // txid: 1
+    approve(env, ledger_id, account(1, 0), account(3, 0), 500_000); // txid: 2
+    transfer_from(
+        env,
+        ledger_id,
+        account(1, 0),
+        account(4, 0),
+        account(2, 0),
+        100_000,
+    ); // txid: 3
+    approve(env, ledger_id, account(1, 0), account(2, 0), 2_000_000); // txid: 4
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+
+    let approve_timestamp = |id: u64| {
+        get_allowance_history(env, index_id, account(1, 0), account(2, 0), None, u64::MAX)
+            .into_iter()
+            .chain(get_allowance_history(
+                env,
+                index_id,
+                account(1, 0),
+                account(3, 0),
+                None,
+                u64::MAX,
+            ))
+            .find(|tx| tx.id == id)
+            .unwrap()
+            .transaction
+            .timestamp
+    };
+
+    // The re-approval of account(2, 0) keeps the block at which it was
+    // first granted an allowance.
+    let mut allowances = get_account_allowances(env, index_id, account(1, 0), None, u64::MAX);
+    allowances.sort_by_key(|allowance| allowance.since_block.clone());
+    assert_eq!(
+        allowances,
+        vec![
+            AccountAllowance {
+                spender: account(2, 0),
+                allowance: 2_000_000u64.into(),
+                expires_at: None,
+                since_block: 1u8.into(),
+                since_timestamp: approve_timestamp(1),
+                last_approve_block: 4u8.into(),
+            },
+            AccountAllowance {
+                spender: account(3, 0),
+                allowance: 500_000u64.into(),
+                expires_at: None,
+                since_block: 2u8.into(),
+                since_timestamp: approve_timestamp(2),
+                last_approve_block: 2u8.into(),
+            },
+        ]
+    );
+
+    // Pagination skips the start spender.
+    let all_allowances = get_account_allowances(env, index_id, account(1, 0), None, u64::MAX);
+    let first_page = get_account_allowances(env, index_id, account(1, 0), None, 1);
+    assert_eq!(first_page, all_allowances[..1]);
+    let second_page = get_account_allowances(
+        env,
+        index_id,
+        account(1, 0),
+        Some(first_page[0].spender),
+        u64::MAX,
+    );
+    assert_eq!(second_page, all_allowances[1..]);
+
+    // Spenders have no allowances on their own accounts.
+    assert_eq!(
+        get_account_allowances(env, index_id, account(2, 0), None, u64::MAX),
+        vec![]
+    );
+
+    // Using up the whole allowance removes it.
+    transfer_from(
+        env,
+        ledger_id,
+        account(1, 0),
+        account(4, 0),
+        account(3, 0),
+        500_000 - FEE,
+    ); // txid: 5
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+    let allowances = get_account_allowances(env, index_id, account(1, 0), None, u64::MAX);
+    assert_eq!(
+        allowances
+            .iter()
+            .map(|allowance| allowance.spender)
+            .collect::<Vec<_>>(),
+        vec![account(2, 0)]
+    );
+
+    // The history contains the approvals and the transfers of the spender,
+    // from the most recent.
+    let history_ids = |spender: Account, start: Option<u64>| {
+        get_allowance_history(env, index_id, account(1, 0), spender, start, u64::MAX)
+            .into_iter()
+            .map(|tx| tx.id.0.to_u64().unwrap())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(history_ids(account(2, 0), None), vec![4, 3, 1]);
+    assert_eq!(history_ids(account(2, 0), Some(4)), vec![3, 1]);
+    assert_eq!(history_ids(account(3, 0), None), vec![5, 2]);
+    assert_eq!(history_ids(account(4, 0), None), Vec::<u64>::new());
+}
+
 #[track_caller]
 fn assert_contain_same_elements<T: Debug + Eq + Hash>(vl: Vec<T>, vr: Vec<T>) {
     assert_eq!(
@@ -1102,6 +1786,57 @@ fn test_fee_collector() {
     );
 }
 
+#[test]
+fn test_transfer_batch() {
+    let env = &StateMachine::new();
+    let minter = minter_identity().sender().unwrap();
+    let ledger_id = install_ledger(
+        env,
+        vec![(account(1, 0), 10_000_000)], // txid: 0
+        default_archive_options(),
+        None,
+        minter,
+    );
+    let index_id = install_index_ng(env, index_init_arg_without_interval(ledger_id));
+
+    transfer(env, ledger_id, account(1, 0), account(2, 0), 100_000); // txid: 1
+    let transfer_arg = |to: Account, amount: u64| TransferArg {
+        from_subaccount: None,
+        to,
+        amount: amount.into(),
+        created_at_time: None,
+        fee: None,
+        memo: None,
+    };
+    let results = icrc4_transfer_batch(
+        env,
+        ledger_id,
+        account(1, 0).owner.into(),
+        vec![
+            transfer_arg(account(2, 0), 200_000),    // txid: 2
+            transfer_arg(account(3, 0), 20_000_000), // insufficient funds
+            transfer_arg(account(3, 0), 300_000),    // txid: 3
+        ],
+    );
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0], Some(Ok(Nat::from(2u64))));
+    assert!(matches!(results[1], Some(Err(_))));
+    assert_eq!(results[2], Some(Ok(Nat::from(3u64))));
+
+    // The index decodes the blocks of the batch like any other blocks.
+    wait_until_sync_is_completed(env, index_id, ledger_id);
+    assert_ledger_index_parity(env, ledger_id, index_id);
+    for account in [account(1, 0), account(2, 0), account(3, 0)] {
+        assert_eq!(
+            icrc1_balance_of(env, ledger_id, account),
+            icrc1_balance_of(env, index_id, account)
+        );
+    }
+    let txs = get_account_transactions(env, index_id, account(3, 0), None, u64::MAX);
+    assert_eq!(txs.transactions.len(), 1);
+    assert_eq!(txs.transactions[0].id, Nat::from(3u64));
+}
+
 #[test]
 fn test_index_ledger_coherence() {
     let mut runner = TestRunner::new(TestRunnerConfig::with_cases(1));
@@ -22,8 +22,8 @@ use ic_icrc1_ledger::{
 };
 use ic_icrc1_ledger::{InitArgs, Ledger, LedgerArgument, LedgerField, LedgerState};
 use ic_ledger_canister_core::ledger::{
-    apply_transaction, archive_blocks, LedgerAccess, LedgerContext, LedgerData,
-    TransferError as CoreTransferError,
+    apply_transaction, apply_transaction_in_batch, archive_blocks, LedgerAccess, LedgerContext,
+    LedgerData, TransferError as CoreTransferError,
 };
 use ic_ledger_canister_core::runtime::heap_memory_size_bytes;
 use ic_ledger_core::block::BlockIndex;
@@ -50,7 +50,11 @@ use icrc_ledger_types::{
 };
 use icrc_ledger_types::{
     icrc1::account::Account,
+    icrc103::get_allowances::{
+        Allowance as Allowance103, Allowances, GetAllowancesArgs, GetAllowancesError,
+    },
     icrc2::allowance::{Allowance, AllowanceArgs},
+    icrc4::transfer_batch::{TransferBatchArgs, TransferBatchError, TransferBatchResults},
 };
 use icrc_ledger_types::{
     icrc1::transfer::Memo,
@@ -68,10 +72,16 @@ use serde_bytes::ByteBuf;
 use std::{
     cell::RefCell,
     io::{Read, Write},
+    ops::Bound,
     time::Duration,
 };
 
 const MAX_MESSAGE_SIZE: u64 = 1024 * 1024;
+const MAX_TAKE_ALLOWANCES: u64 = 500;
+const MAX_TRANSFERS_PER_BATCH: u64 = 100;
+const MAX_BALANCES_PER_BATCH: u64 = 1_000;
+/// The error code of the generic errors returned for individual transfers of a batch.
+const BATCH_GENERIC_ERROR_CODE: u64 = 0;
 
 #[cfg(not(feature = "u256-tokens"))]
 pub type Tokens = ic_icrc1_tokens_u64::U64;
@@ -576,6 +586,7 @@ async fn execute_transfer(
         amount,
         memo,
         created_at_time,
+        None,
     )?;
 
     // NB. we need to set the certified data before the first async call to make sure that the
@@ -594,6 +605,7 @@ fn execute_transfer_not_async(
     amount: Nat,
     memo: Option<Memo>,
     created_at_time: Option<u64>,
+    batch_block_index: Option<BlockIndex>,
 ) -> Result<BlockIndex, ic_ledger_canister_core::ledger::TransferError<Tokens>> {
     Access::with_ledger_mut(|ledger| {
         let now = TimeStamp::from_nanos_since_unix_epoch(ic_cdk::api::time());
@@ -684,7 +696,8 @@ fn execute_transfer_not_async(
             )
         };
 
-        let (block_idx, _) = apply_transaction(ledger, tx, now, effective_fee)?;
+        let (block_idx, _) =
+            apply_transaction_in_batch(ledger, tx, now, effective_fee, batch_block_index)?;
         Ok(block_idx)
     })
 }
@@ -717,6 +730,104 @@ async fn icrc1_transfer(arg: TransferArg) -> Result<Nat, TransferError> {
     })
 }
 
+#[update]
+#[candid_method(update)]
+async fn icrc4_transfer_batch(args: TransferBatchArgs) -> TransferBatchResults {
+    panic_if_not_ready();
+    if args.len() as u64 > MAX_TRANSFERS_PER_BATCH {
+        return vec![Some(Err(TransferBatchError::TooManyRequests {
+            limit: Nat::from(MAX_TRANSFERS_PER_BATCH),
+        }))];
+    }
+    let caller = ic_cdk::api::caller();
+    let max_memo_length = Access::with_ledger(|ledger| ledger.max_memo_length()) as usize;
+    // Every transfer is applied (and deduplicated) on its own and results in its own block.
+    // All blocks of the batch record the index of the first block of the batch so that
+    // the index and Rosetta can tell which transfers were made in the same batch.
+    let mut batch_block_index = None;
+    let results = args
+        .into_iter()
+        .map(|arg| {
+            if let Some(memo) = arg.memo.as_ref() {
+                if memo.0.len() > max_memo_length {
+                    return Some(Err(TransferBatchError::GenericError {
+                        error_code: Nat::from(BATCH_GENERIC_ERROR_CODE),
+                        message: format!(
+                            "the memo field size of {} bytes is above the allowed limit of {} bytes",
+                            memo.0.len(),
+                            max_memo_length
+                        ),
+                    }));
+                }
+            }
+            let from_account = Account {
+                owner: caller,
+                subaccount: arg.from_subaccount,
+            };
+            let next_block_index = Access::with_ledger(|ledger| ledger.blockchain().chain_length());
+            let result = execute_transfer_not_async(
+                from_account,
+                arg.to,
+                None,
+                arg.fee,
+                arg.amount,
+                arg.memo,
+                arg.created_at_time,
+                Some(batch_block_index.unwrap_or(next_block_index)),
+            )
+            .map(|block_index| {
+                batch_block_index.get_or_insert(block_index);
+                Nat::from(block_index)
+            })
+            .map_err(|err| match TransferError::try_from(convert_transfer_error(err)) {
+                Ok(err) => TransferBatchError::from(err),
+                Err(message) => TransferBatchError::GenericError {
+                    error_code: Nat::from(BATCH_GENERIC_ERROR_CODE),
+                    message,
+                },
+            });
+            Some(result)
+        })
+        .collect();
+
+    // NB. we need to set the certified data before the first async call to make sure that the
+    // blockchain state agrees with the certificate while archiving is in progress.
+    ic_cdk::api::set_certified_data(&Access::with_ledger(Ledger::root_hash));
+
+    archive_blocks::<Access>(&LOG, MAX_MESSAGE_SIZE).await;
+    results
+}
+
+#[query]
+#[candid_method(query)]
+fn icrc4_balance_of_batch(accounts: Vec<Account>) -> Vec<Nat> {
+    if accounts.len() as u64 > MAX_BALANCES_PER_BATCH {
+        ic_cdk::trap(&format!(
+            "the number of accounts {} is above the allowed limit of {}",
+            accounts.len(),
+            MAX_BALANCES_PER_BATCH
+        ));
+    }
+    Access::with_ledger(|ledger| {
+        accounts
+            .iter()
+            .map(|account| ledger.balances().account_balance(account).into())
+            .collect()
+    })
+}
+
+#[query]
+#[candid_method(query)]
+fn icrc4_maximum_update_batch_size() -> Option<Nat> {
+    Some(Nat::from(MAX_TRANSFERS_PER_BATCH))
+}
+
+#[query]
+#[candid_method(query)]
+fn icrc4_maximum_query_batch_size() -> Option<Nat> {
+    Some(Nat::from(MAX_BALANCES_PER_BATCH))
+}
+
 #[update]
 #[candid_method(update)]
 async fn icrc2_transfer_from(arg: TransferFromArgs) -> Result<Nat, TransferFromError> {
@@ -785,10 +896,19 @@ fn supported_standards() -> Vec<StandardRecord> {
             name: "ICRC-3".to_string(),
             url: "https://github.com/dfinity/ICRC-1/tree/main/standards/ICRC-3".to_string(),
         },
+        StandardRecord {
+            name: "ICRC-4".to_string(),
+            url: "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-4/ICRC-4.md".to_string(),
+        },
         StandardRecord {
             name: "ICRC-10".to_string(),
             url: "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-10/ICRC-10.md".to_string(),
         },
+        StandardRecord {
+            name: "ICRC-103".to_string(),
+            url: "https://github.com/dfinity/ICRC/blob/main/ICRCs/ICRC-103/ICRC-103.md"
+                .to_string(),
+        },
         StandardRecord {
             name: "ICRC-21".to_string(),
             url: "https://github.com/dfinity/wg-identity-authentication/blob/main/topics/ICRC-21/icrc_21_consent_msg.md".to_string(),
@@ -931,6 +1051,53 @@ fn icrc2_allowance(arg: AllowanceArgs) -> Allowance {
     })
 }
 
+#[query]
+#[candid_method(query)]
+fn icrc103_get_allowances(arg: GetAllowancesArgs) -> Result<Allowances, GetAllowancesError> {
+    let from_account = arg.from_account.unwrap_or_else(|| Account {
+        owner: ic_cdk::api::caller(),
+        subaccount: None,
+    });
+    let start = match arg.prev_spender {
+        Some(prev_spender) => Bound::Excluded((from_account, prev_spender)),
+        None => Bound::Included((
+            from_account,
+            // The smallest account: the owner is the principal with the shortest encoding.
+            Account {
+                owner: Principal::from_slice(&[]),
+                subaccount: None,
+            },
+        )),
+    };
+    let max_results = arg
+        .take
+        .and_then(|take| take.0.to_u64())
+        .map(|take| take.min(MAX_TAKE_ALLOWANCES))
+        .unwrap_or(MAX_TAKE_ALLOWANCES) as usize;
+    Access::with_ledger(|ledger| {
+        let now = TimeStamp::from_nanos_since_unix_epoch(ic_cdk::api::time());
+        // The allowances of all subaccounts of the owner of `from_account` are listed
+        // (starting with `from_account`).
+        let allowances = ledger
+            .approvals()
+            .get_allowances(
+                start,
+                |(account, _)| account.owner == from_account.owner,
+                max_results,
+                now,
+            )
+            .into_iter()
+            .map(|((account, spender), allowance)| Allowance103 {
+                from_account: account,
+                to_spender: spender,
+                allowance: allowance.amount.into(),
+                expires_at: allowance.expires_at.map(|t| t.as_nanos_since_unix_epoch()),
+            })
+            .collect();
+        Ok(allowances)
+    })
+}
+
 #[query]
 #[candid_method(query)]
 fn icrc3_get_archives(args: GetArchivesArgs) -> GetArchivesResult {
@@ -465,6 +465,19 @@ fn test_approve_from_minter() {
     ic_ledger_suite_state_machine_tests::test_approve_from_minter(ledger_wasm(), encode_init_args);
 }
 
+#[test]
+fn test_icrc4_transfer_batch() {
+    ic_ledger_suite_state_machine_tests::test_icrc4_transfer_batch(ledger_wasm(), encode_init_args);
+}
+
+#[test]
+fn test_icrc103_get_allowances() {
+    ic_ledger_suite_state_machine_tests::test_icrc103_get_allowances(
+        ledger_wasm(),
+        encode_init_args,
+    );
+}
+
 #[test]
 fn test_transfer_from_smoke() {
     ic_ledger_suite_state_machine_tests::test_transfer_from_smoke(ledger_wasm(), encode_init_args);
@@ -1088,6 +1101,7 @@ fn test_icrc3_get_archives() {
         timestamp: 0,
         fee_collector: None,
         fee_collector_block_index: None,
+        batch_block_index: None,
     }
     .encode()
     .size_bytes();
@@ -9,7 +9,7 @@ use ic_ledger_canister_core::ledger::{LedgerContext, LedgerTransaction, TxApplyE
 use ic_ledger_core::{
     approvals::{AllowanceTable, HeapAllowancesData},
     balances::Balances,
-    block::{BlockType, EncodedBlock, FeeCollector},
+    block::{BlockIndex, BlockType, EncodedBlock, FeeCollector},
     timestamp::TimeStamp,
     tokens::TokensType,
 };
@@ -545,6 +545,11 @@ pub struct Block<Tokens: TokensType> {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "fee_col_block")]
     pub fee_collector_block_index: Option<u64>,
+
+    /// The index of the first block of the ICRC-4 batch this block belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "batch")]
+    pub batch_block_index: Option<u64>,
 }
 
 type TaggedBlock<Tokens> = Required<Block<Tokens>, 55799>;
@@ -615,6 +620,14 @@ impl<Tokens: TokensType> BlockType for Block<Tokens> {
             timestamp: timestamp.as_nanos_since_unix_epoch(),
             fee_collector,
             fee_collector_block_index,
+            batch_block_index: None,
+        }
+    }
+
+    fn with_batch_block_index(self, batch_block_index: BlockIndex) -> Self {
+        Self {
+            batch_block_index: Some(batch_block_index),
+            ..self
         }
     }
 }
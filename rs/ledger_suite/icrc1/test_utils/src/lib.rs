@@ -177,6 +177,7 @@ pub fn blocks_strategy<Tokens: TokensType>(
     let transaction_strategy = transaction_strategy(amount_strategy);
     let fee_collector_strategy = prop::option::of(account_strategy());
     let fee_collector_block_index_strategy = prop::option::of(prop::num::u64::ANY);
+    let batch_block_index_strategy = prop::option::of(prop::num::u64::ANY);
     let effective_fee_strategy = arb_small_amount::<Tokens>();
     let timestamp_strategy = Just({
         let end = SystemTime::now();
@@ -194,9 +195,17 @@ pub fn blocks_strategy<Tokens: TokensType>(
         timestamp_strategy,
         fee_collector_strategy,
         fee_collector_block_index_strategy,
+        batch_block_index_strategy,
     )
         .prop_map(
-            |(transaction, arb_fee, timestamp, fee_collector, fee_collector_block_index)| {
+            |(
+                transaction,
+                arb_fee,
+                timestamp,
+                fee_collector,
+                fee_collector_block_index,
+                batch_block_index,
+            )| {
                 let effective_fee = match transaction.operation {
                     Operation::Transfer { ref fee, .. } => fee.clone().is_none().then_some(arb_fee),
                     Operation::Approve { ref fee, .. } => fee.clone().is_none().then_some(arb_fee),
@@ -213,6 +222,7 @@ pub fn blocks_strategy<Tokens: TokensType>(
                             timestamp,
                             fee_collector,
                             fee_collector_block_index,
+                            batch_block_index,
                         }
                         .encode(),
                     )),
@@ -221,6 +231,7 @@ pub fn blocks_strategy<Tokens: TokensType>(
                     timestamp,
                     fee_collector,
                     fee_collector_block_index,
+                    batch_block_index,
                 }
             },
         )
@@ -1042,15 +1053,17 @@ where
         any::<u64>(),
         proptest::option::of(arb_account()),
         proptest::option::of(any::<u64>()),
+        proptest::option::of(any::<u64>()),
     )
         .prop_map(
-            |(parent_hash, transaction, effective_fee, ts, fee_col, fee_col_block)| Block {
+            |(parent_hash, transaction, effective_fee, ts, fee_col, fee_col_block, batch)| Block {
                 parent_hash: parent_hash.map(HashOf::new),
                 transaction,
                 effective_fee,
                 timestamp: ts,
                 fee_collector: fee_col,
                 fee_collector_block_index: fee_col_block,
+                batch_block_index: batch,
             },
         )
 }
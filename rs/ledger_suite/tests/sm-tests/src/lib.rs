@@ -31,6 +31,9 @@ use icrc_ledger_types::icrc::generic_metadata_value::MetadataValue as Value;
 use icrc_ledger_types::icrc::generic_value::Value as GenericValue;
 use icrc_ledger_types::icrc1::account::{Account, Subaccount};
 use icrc_ledger_types::icrc1::transfer::{Memo, TransferArg, TransferError};
+use icrc_ledger_types::icrc103::get_allowances::{
+    Allowances, GetAllowancesArgs, GetAllowancesError,
+};
 use icrc_ledger_types::icrc2::allowance::{Allowance, AllowanceArgs};
 use icrc_ledger_types::icrc2::approve::{ApproveArgs, ApproveError};
 use icrc_ledger_types::icrc2::transfer_from::{TransferFromArgs, TransferFromError};
@@ -51,6 +54,7 @@ use icrc_ledger_types::icrc3::transactions::GetTransactionsResponse;
 use icrc_ledger_types::icrc3::transactions::Transaction as Tx;
 use icrc_ledger_types::icrc3::transactions::TransactionRange;
 use icrc_ledger_types::icrc3::transactions::Transfer;
+use icrc_ledger_types::icrc4::transfer_batch::{TransferBatchError, TransferBatchResults};
 use num_traits::ToPrimitive;
 use proptest::prelude::*;
 use proptest::test_runner::{Config as TestRunnerConfig, TestCaseResult, TestRunner};
@@ -233,6 +237,26 @@ pub fn send_transfer(
     .map(|n| n.0.to_u64().unwrap())
 }
 
+pub fn send_transfer_batch(
+    env: &StateMachine,
+    ledger: CanisterId,
+    from: Principal,
+    args: &[TransferArg],
+) -> TransferBatchResults {
+    Decode!(
+        &env.execute_ingress_as(
+            PrincipalId(from),
+            ledger,
+            "icrc4_transfer_batch",
+            Encode!(args).unwrap()
+        )
+        .expect("failed to transfer funds in a batch")
+        .bytes(),
+        TransferBatchResults
+    )
+    .expect("failed to decode transfer_batch response")
+}
+
 pub fn system_time_to_nanos(t: SystemTime) -> u64 {
     t.duration_since(SystemTime::UNIX_EPOCH).unwrap().as_nanos() as u64
 }
@@ -637,6 +661,28 @@ pub fn send_approval(
     .map(|n| n.0.to_u64().unwrap())
 }
 
+pub fn get_allowances(
+    env: &StateMachine,
+    ledger: CanisterId,
+    caller: Principal,
+    arg: GetAllowancesArgs,
+) -> Allowances {
+    Decode!(
+        &env.query_as(
+            PrincipalId(caller),
+            ledger,
+            "icrc103_get_allowances",
+            Encode!(&arg)
+            .unwrap()
+        )
+        .expect("failed to query allowances")
+        .bytes(),
+        Result<Allowances, GetAllowancesError>
+    )
+    .expect("failed to decode icrc103_get_allowances response")
+    .expect("failed to get allowances")
+}
+
 pub fn send_transfer_from(
     env: &StateMachine,
     ledger: CanisterId,
@@ -849,15 +895,17 @@ fn arb_block<Tokens: TokensType>() -> impl Strategy<Value = Block<Tokens>> {
         any::<u64>(),
         proptest::option::of(arb_account()),
         proptest::option::of(any::<u64>()),
+        proptest::option::of(any::<u64>()),
     )
         .prop_map(
-            |(parent_hash, transaction, effective_fee, ts, fee_col, fee_col_block)| Block {
+            |(parent_hash, transaction, effective_fee, ts, fee_col, fee_col_block, batch)| Block {
                 parent_hash: parent_hash.map(HashOf::new),
                 transaction,
                 effective_fee,
                 timestamp: ts,
                 fee_collector: fee_col,
                 fee_collector_block_index: fee_col_block,
+                batch_block_index: batch,
             },
         )
 }
@@ -1086,10 +1134,113 @@ where
     standards.sort();
     assert_eq!(
         standards,
-        vec!["ICRC-1", "ICRC-10", "ICRC-2", "ICRC-21", "ICRC-3"]
+        vec!["ICRC-1", "ICRC-10", "ICRC-103", "ICRC-2", "ICRC-21", "ICRC-3", "ICRC-4"]
     );
 }
 
+pub fn test_icrc4_transfer_batch<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    let from = PrincipalId::new_user_test_id(1);
+    let to = Account::from(PrincipalId::new_user_test_id(2).0);
+    let (env, canister_id) = setup(
+        ledger_wasm,
+        encode_init_args,
+        vec![(Account::from(from.0), 100_000)],
+    );
+
+    let transfer_arg = TransferArg {
+        from_subaccount: None,
+        to,
+        fee: None,
+        created_at_time: Some(system_time_to_nanos(env.time())),
+        memo: Some(Memo::from(1)),
+        amount: Nat::from(10_000u64),
+    };
+    let args = vec![
+        transfer_arg.clone(),
+        // A duplicate of the first transfer.
+        transfer_arg.clone(),
+        TransferArg {
+            created_at_time: None,
+            memo: None,
+            amount: Nat::from(1_000_000u64),
+            ..transfer_arg.clone()
+        },
+        // A burn.
+        TransferArg {
+            to: MINTER,
+            created_at_time: None,
+            memo: None,
+            amount: Nat::from(20_000u64),
+            ..transfer_arg.clone()
+        },
+        // A transfer with a memo above the allowed limit.
+        TransferArg {
+            created_at_time: None,
+            memo: Some(Memo::from(vec![0u8; 33])),
+            ..transfer_arg
+        },
+    ];
+    let results = send_transfer_batch(&env, canister_id, from.0, &args);
+    assert_eq!(
+        results,
+        vec![
+            Some(Ok(Nat::from(1u64))),
+            Some(Err(TransferBatchError::Duplicate {
+                duplicate_of: Nat::from(1u64)
+            })),
+            Some(Err(TransferBatchError::InsufficientFunds {
+                balance: Nat::from(80_000u64)
+            })),
+            Some(Ok(Nat::from(2u64))),
+            Some(Err(TransferBatchError::GenericError {
+                error_code: Nat::from(0u64),
+                message: "the memo field size of 33 bytes is above the allowed limit of 32 bytes"
+                    .to_string()
+            })),
+        ]
+    );
+    assert_eq!(balance_of(&env, canister_id, from.0), 60_000);
+    assert_eq!(balance_of(&env, canister_id, to), 10_000);
+    let balances = Decode!(
+        &env.query(
+            canister_id,
+            "icrc4_balance_of_batch",
+            Encode!(&vec![Account::from(from.0), to]).unwrap()
+        )
+        .expect("failed to query balances")
+        .bytes(),
+        Vec<Nat>
+    )
+    .expect("failed to decode icrc4_balance_of_batch response");
+    assert_eq!(balances, vec![Nat::from(60_000u64), Nat::from(10_000u64)]);
+
+    // The transfers of a batch are recorded as regular blocks
+    // pointing to the first block of the batch.
+    let transactions = get_transactions(&env, canister_id.get().0, 1, 2).transactions;
+    assert_eq!(transactions[0].kind, "transfer");
+    assert_eq!(transactions[1].kind, "burn");
+    for block in get_blocks(&env, canister_id.get().0, 1, 2).blocks {
+        let batch = match block {
+            GenericValue::Map(fields) => fields.get("batch").cloned(),
+            block => panic!("unexpected block: {:?}", block),
+        };
+        assert_matches!(batch, Some(GenericValue::Nat64(1)));
+    }
+
+    // A batch with too many transfers is rejected as a whole.
+    let args = vec![args[0].clone(); 101];
+    assert_eq!(
+        send_transfer_batch(&env, canister_id, from.0, &args),
+        vec![Some(Err(TransferBatchError::TooManyRequests {
+            limit: Nat::from(100u64)
+        }))]
+    );
+    assert_eq!(balance_of(&env, canister_id, from.0), 60_000);
+}
+
 pub fn test_total_supply<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
 where
     T: CandidType,
@@ -3641,6 +3792,86 @@ where
     assert_eq!(balance_of(&env, canister_id, spender.0), 0);
 }
 
+pub fn test_icrc103_get_allowances<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
+where
+    T: CandidType,
+{
+    let from = PrincipalId::new_user_test_id(1);
+    let other = PrincipalId::new_user_test_id(2);
+    let from_sub_1 = Account {
+        owner: from.0,
+        subaccount: Some([1; 32]),
+    };
+    let mut spenders: Vec<Account> = (3..6)
+        .map(|id| Account::from(PrincipalId::new_user_test_id(id).0))
+        .collect();
+    spenders.sort();
+
+    let (env, canister_id) = setup(
+        ledger_wasm,
+        encode_init_args,
+        vec![
+            (Account::from(from.0), 100_000),
+            (from_sub_1, 100_000),
+            (Account::from(other.0), 100_000),
+        ],
+    );
+
+    for (i, spender) in spenders.iter().enumerate() {
+        let approve_args = default_approve_args(*spender, 10_000 * (i as u64 + 1));
+        send_approval(&env, canister_id, from.0, &approve_args).expect("approval failed");
+    }
+    let mut approve_args = default_approve_args(spenders[0], 50_000);
+    approve_args.from_subaccount = from_sub_1.subaccount;
+    send_approval(&env, canister_id, from.0, &approve_args).expect("approval failed");
+    // Allowances of other principals are not listed.
+    approve_args.from_subaccount = None;
+    send_approval(&env, canister_id, other.0, &approve_args).expect("approval failed");
+
+    let to_pairs = |allowances: Allowances| {
+        allowances
+            .into_iter()
+            .map(|allowance| (allowance.from_account, allowance.to_spender))
+            .collect::<Vec<_>>()
+    };
+    let mut expected: Vec<_> = spenders
+        .iter()
+        .map(|spender| (Account::from(from.0), *spender))
+        .collect();
+    expected.push((from_sub_1, spenders[0]));
+
+    // The allowances of all subaccounts of the caller are listed by default.
+    let allowances = get_allowances(&env, canister_id, from.0, GetAllowancesArgs::default());
+    assert_eq!(allowances[0].allowance, Nat::from(10_000u64));
+    assert_eq!(allowances[0].expires_at, None);
+    assert_eq!(to_pairs(allowances), expected);
+
+    // Pagination.
+    let args = GetAllowancesArgs {
+        take: Some(Nat::from(2u64)),
+        ..GetAllowancesArgs::default()
+    };
+    let first_page = to_pairs(get_allowances(&env, canister_id, from.0, args));
+    assert_eq!(first_page, expected[..2]);
+    let args = GetAllowancesArgs {
+        prev_spender: Some(first_page[1].1),
+        take: Some(Nat::from(2u64)),
+        ..GetAllowancesArgs::default()
+    };
+    let second_page = to_pairs(get_allowances(&env, canister_id, from.0, args));
+    assert_eq!(second_page, expected[2..]);
+
+    // Listing starts at the given account (for any caller).
+    let args = GetAllowancesArgs {
+        from_account: Some(from_sub_1),
+        ..GetAllowancesArgs::default()
+    };
+    assert_eq!(
+        to_pairs(get_allowances(&env, canister_id, other.0, args)),
+        expected[3..]
+    );
+}
+
 pub fn test_approve_from_minter<T>(ledger_wasm: Vec<u8>, encode_init_args: fn(InitArgs) -> T)
 where
     T: CandidType,
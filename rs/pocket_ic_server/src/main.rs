@@ -25,10 +25,14 @@ use ic_canister_sandbox_backend_lib::{
 use ic_crypto_iccsa::{public_key_bytes_from_der, types::SignatureBytes, verify};
 use ic_crypto_sha2::Sha256;
 use ic_crypto_utils_threshold_sig_der::parse_threshold_sig_key_from_der;
-use pocket_ic::common::rest::{BinaryBlob, BlobCompression, BlobId, RawVerifyCanisterSigArg};
+use pocket_ic::common::rest::{
+    BinaryBlob, BlobCompression, BlobId, RawVerifyCanisterSigArg, RecordedBlob,
+};
 use pocket_ic_server::state_api::routes::{handler_read_graph, timeout_or_default};
 use pocket_ic_server::state_api::{
-    routes::{http_gateway_routes, instances_routes, status, AppState, RouterExt},
+    routes::{
+        http_gateway_routes, instances_routes, record_instance_request, status, AppState, RouterExt,
+    },
     state::{ApiState, PocketIcApiStateBuilder},
 };
 use pocket_ic_server::BlobStore;
@@ -182,6 +186,10 @@ async fn start(runtime: Arc<Runtime>) {
             app_state.clone(),
             bump_last_request_timestamp,
         ))
+        .route_layer(middleware::from_fn_with_state(
+            app_state.clone(),
+            record_instance_request,
+        ))
         .with_state(app_state.clone());
 
     let mut api = OpenApi {
@@ -394,7 +402,11 @@ async fn get_blob_store_entry(
 
 async fn set_blob_store_entry(
     headers: HeaderMap,
-    State(AppState { blob_store, .. }): State<AppState>,
+    State(AppState {
+        api_state,
+        blob_store,
+        ..
+    }): State<AppState>,
     body: axum::body::Bytes,
 ) -> (StatusCode, String) {
     let content_encoding = headers.get(axum::http::header::CONTENT_ENCODING);
@@ -422,8 +434,17 @@ async fn set_blob_store_entry(
             },
         }
     };
-    let blob_id = hex::encode(blob_store.store(blob).await.0);
-    (StatusCode::OK, blob_id)
+    // Uploaded blobs are recorded by all instances in recording mode
+    // since blobs are not specific to an instance.
+    let recorded_blob = api_state.has_recordings().await.then(|| RecordedBlob {
+        data: blob.data.clone(),
+        gzip: blob.compression == BlobCompression::Gzip,
+    });
+    let blob_id = blob_store.store(blob).await;
+    if let Some(recorded_blob) = recorded_blob {
+        api_state.record_blob(blob_id.clone(), recorded_blob).await;
+    }
+    (StatusCode::OK, hex::encode(blob_id.0))
 }
 
 pub async fn verify_signature(
@@ -50,8 +50,9 @@ use ic_registry_proto_data_provider::ProtoRegistryDataProvider;
 use ic_registry_routing_table::{CanisterIdRange, RoutingTable, CANISTER_IDS_PER_SUBNET};
 use ic_registry_subnet_type::SubnetType;
 use ic_state_machine_tests::{
-    finalize_registry, StateMachine, StateMachineBuilder, StateMachineConfig, StateMachineStateDir,
-    SubmitIngressError, Subnets,
+    finalize_registry, CanisterCostMetrics, CanisterSnapshotData, CanisterSnapshotDataError,
+    ExecutedMessage, InterceptedCallError, StateMachine, StateMachineBuilder, StateMachineConfig,
+    StateMachineStateDir, SubmitIngressError, Subnets, TracedExecution, XNetResponseFault,
 };
 use ic_test_utilities_registry::add_subnet_list_record;
 use ic_types::batch::BlockmakerMetrics;
@@ -65,18 +66,22 @@ use ic_types::{
     },
     crypto::{BasicSig, BasicSigOf, CryptoResult, Signable},
     messages::{
-        CertificateDelegation, HttpCallContent, HttpRequestEnvelope, MessageId as OtherMessageId,
-        QueryResponseHash, ReplicaHealthStatus, SignedIngress,
+        CallbackId, CertificateDelegation, HttpCallContent, HttpRequestEnvelope,
+        MessageId as OtherMessageId, Payload as MsgPayload, QueryResponseHash, RejectContext,
+        ReplicaHealthStatus, SignedIngress,
     },
     time::GENESIS,
-    CanisterId, Height, NodeId, NumInstructions, PrincipalId, RegistryVersion, SubnetId,
+    CanisterId, Height, NodeId, NumInstructions, PrincipalId, RegistryVersion, SnapshotId,
+    SubnetId,
 };
 use ic_types::{NumBytes, Time};
 use ic_validator_ingress_message::StandaloneIngressSigVerifier;
 use itertools::Itertools;
 use pocket_ic::common::rest::{
-    self, BinaryBlob, BlobCompression, CanisterHttpHeader, CanisterHttpMethod, CanisterHttpRequest,
-    CanisterHttpResponse, ExtendedSubnetConfigSet, MockCanisterHttpResponse, RawAddCycles,
+    self, BinaryBlob, BlobCompression, CallReport, CanisterExecutionReport, CanisterHttpHeader,
+    CanisterHttpMethod, CanisterHttpRequest, CanisterHttpResponse, ExecutedMessageKind,
+    ExecutionReport, ExtendedSubnetConfigSet, InterceptedCall, InterceptedCallResponse,
+    MessageExecutionReport, MockCanisterHttpResponse, MockInterceptedCallResponse, RawAddCycles,
     RawCanisterCall, RawCanisterId, RawEffectivePrincipal, RawMessageId, RawSetStableMemory,
     SubnetInstructionConfig, SubnetKind, SubnetSpec, TickConfigs, Topology,
 };
@@ -87,7 +92,7 @@ use std::hash::Hash;
 use std::str::FromStr;
 use std::{
     cmp::max,
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     fs::{remove_file, File},
     io::{BufReader, Write},
     net::SocketAddr,
@@ -1561,6 +1566,244 @@ impl Operation for MockCanisterHttp {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct AddCallInterceptor {
+    pub canister_id: CanisterId,
+    pub method: Option<String>,
+}
+
+impl Operation for AddCallInterceptor {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        // The caller can be deployed on any subnet
+        // and thus the call interceptor is added to all subnets.
+        for subnet in pic.subnets.get_all() {
+            subnet
+                .state_machine
+                .intercept_calls(self.canister_id, self.method.clone());
+        }
+        OpOut::NoOutput
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "add_call_interceptor({},{:?})",
+            self.canister_id, self.method
+        ))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RemoveCallInterceptor {
+    pub canister_id: CanisterId,
+    pub method: Option<String>,
+}
+
+impl Operation for RemoveCallInterceptor {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        for subnet in pic.subnets.get_all() {
+            subnet
+                .state_machine
+                .remove_call_interceptor(self.canister_id, self.method.clone());
+        }
+        OpOut::NoOutput
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "remove_call_interceptor({},{:?})",
+            self.canister_id, self.method
+        ))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct GetInterceptedCalls;
+
+impl Operation for GetInterceptedCalls {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        let mut res = vec![];
+        for subnet in pic.subnets.get_all() {
+            let subnet_id = subnet.state_machine.get_subnet_id().get().0;
+            let mut cur: Vec<_> = subnet
+                .state_machine
+                .intercepted_calls()
+                .into_iter()
+                .map(|c| InterceptedCall {
+                    subnet_id,
+                    call_id: c.call_id,
+                    sender: c.request.sender.get().0,
+                    receiver: c.request.receiver.get().0,
+                    method: c.request.method_name.clone(),
+                    payload: c.request.method_payload.clone(),
+                    cycles: c.request.payment.get(),
+                })
+                .collect();
+            res.append(&mut cur);
+        }
+        OpOut::InterceptedCalls(res)
+    }
+
+    fn id(&self) -> OpId {
+        OpId("get_intercepted_calls".into())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MockInterceptedCall {
+    pub mock_intercepted_call_response: MockInterceptedCallResponse,
+}
+
+impl Operation for MockInterceptedCall {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        let mock = &self.mock_intercepted_call_response;
+        let payload = match &mock.response {
+            InterceptedCallResponse::InterceptedCallReply(data) => MsgPayload::Data(data.clone()),
+            InterceptedCallResponse::InterceptedCallReject(reject) => {
+                let Ok(reject_code) = ic_error_types::RejectCode::try_from(reject.reject_code)
+                else {
+                    return OpOut::Error(PocketIcError::InvalidRejectCode(reject.reject_code));
+                };
+                MsgPayload::Reject(RejectContext::new(reject_code, reject.message.clone()))
+            }
+        };
+        let subnet_id = ic_types::SubnetId::new(ic_types::PrincipalId(mock.subnet_id));
+        let Some(subnet) = pic.get_subnet_with_id(subnet_id) else {
+            return OpOut::Error(PocketIcError::SubnetNotFound(mock.subnet_id));
+        };
+        match subnet.respond_to_intercepted_call(mock.call_id, payload) {
+            Ok(()) => OpOut::NoOutput,
+            Err(InterceptedCallError::CallNotFound(call_id)) => OpOut::Error(
+                PocketIcError::InvalidInterceptedCallId((subnet_id, call_id)),
+            ),
+            Err(InterceptedCallError::InductionFailed(msg)) => {
+                OpOut::Error(PocketIcError::InterceptedCallResponseNotInducted(msg))
+            }
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "mock_intercepted_call({:?})",
+            self.mock_intercepted_call_response
+        ))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SetXNetPaused {
+    pub from_subnet: SubnetId,
+    pub to_subnet: SubnetId,
+    pub paused: bool,
+}
+
+impl Operation for SetXNetPaused {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        if pic.get_subnet_with_id(self.from_subnet).is_none() {
+            return OpOut::Error(PocketIcError::SubnetNotFound(self.from_subnet.get().0));
+        }
+        // XNet messages are pulled by the receiving subnet.
+        match pic.get_subnet_with_id(self.to_subnet) {
+            Some(subnet) => {
+                subnet.set_xnet_paused(self.from_subnet, self.paused);
+                OpOut::NoOutput
+            }
+            None => OpOut::Error(PocketIcError::SubnetNotFound(self.to_subnet.get().0)),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "set_xnet_paused({},{},{})",
+            self.from_subnet, self.to_subnet, self.paused
+        ))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SetXNetResponseFault {
+    pub from_subnet: SubnetId,
+    pub to_subnet: SubnetId,
+    pub fault: Option<rest::XNetResponseFault>,
+}
+
+impl Operation for SetXNetResponseFault {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        if pic.get_subnet_with_id(self.to_subnet).is_none() {
+            return OpOut::Error(PocketIcError::SubnetNotFound(self.to_subnet.get().0));
+        }
+        let fault = self.fault.map(|fault| match fault {
+            rest::XNetResponseFault::Drop => XNetResponseFault::Drop,
+            rest::XNetResponseFault::Delay { rounds } => XNetResponseFault::Delay(rounds),
+        });
+        // Responses are faulted by the subnet sending them.
+        match pic.get_subnet_with_id(self.from_subnet) {
+            Some(subnet) => {
+                subnet.set_xnet_response_fault(self.to_subnet, fault);
+                OpOut::NoOutput
+            }
+            None => OpOut::Error(PocketIcError::SubnetNotFound(self.from_subnet.get().0)),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "set_xnet_response_fault({},{},{:?})",
+            self.from_subnet, self.to_subnet, self.fault
+        ))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SetSubnetStalled {
+    pub subnet_id: SubnetId,
+    pub stalled: bool,
+}
+
+impl Operation for SetSubnetStalled {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        match pic.get_subnet_with_id(self.subnet_id) {
+            Some(subnet) => {
+                subnet.set_stalled(self.stalled);
+                OpOut::NoOutput
+            }
+            None => OpOut::Error(PocketIcError::SubnetNotFound(self.subnet_id.get().0)),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "set_subnet_stalled({},{})",
+            self.subnet_id, self.stalled
+        ))
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SetCanisterQueuesFull {
+    pub canister_id: CanisterId,
+    pub full: bool,
+}
+
+impl Operation for SetCanisterQueuesFull {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        // The caller can be deployed on any subnet
+        // and thus the canister's queues are treated as full on all subnets.
+        for subnet in pic.subnets.get_all() {
+            subnet
+                .state_machine
+                .set_canister_queues_full(self.canister_id, self.full);
+        }
+        OpOut::NoOutput
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "set_canister_queues_full({},{})",
+            self.canister_id, self.full
+        ))
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct PubKey {
     pub subnet_id: SubnetId,
@@ -1879,6 +2122,329 @@ impl Operation for Query {
     }
 }
 
+/// Returns the execution cost metrics of all canisters on all subnets.
+fn canister_cost_metrics(pic: &PocketIc) -> BTreeMap<CanisterId, (SubnetId, CanisterCostMetrics)> {
+    pic.subnets
+        .get_all()
+        .into_iter()
+        .flat_map(|subnet| {
+            let subnet_id = subnet.state_machine.get_subnet_id();
+            subnet
+                .state_machine
+                .canister_cost_metrics()
+                .into_iter()
+                .map(move |(canister_id, metrics)| (canister_id, (subnet_id, metrics)))
+        })
+        .collect()
+}
+
+/// Starts tracing the executions of canister messages on all subnets.
+fn start_execution_tracing(pic: &PocketIc) {
+    for subnet in pic.subnets.get_all() {
+        subnet.state_machine.start_execution_tracing();
+    }
+}
+
+/// Stops tracing the executions of canister messages on all subnets and returns
+/// the traced executions together with the subnet on which they happened.
+fn stop_execution_tracing(pic: &PocketIc) -> Vec<(SubnetId, TracedExecution)> {
+    pic.subnets
+        .get_all()
+        .into_iter()
+        .flat_map(|subnet| {
+            let subnet_id = subnet.state_machine.get_subnet_id();
+            subnet
+                .state_machine
+                .stop_execution_tracing()
+                .into_iter()
+                .map(move |execution| (subnet_id, execution))
+        })
+        .collect()
+}
+
+/// Aggregates the traced executions (slices) of a single message and returns
+/// its report together with the callbacks of the downstream calls it made.
+fn message_execution_report(
+    kind: ExecutedMessageKind,
+    executions: Vec<&TracedExecution>,
+) -> Option<(MessageExecutionReport, Vec<CallbackId>)> {
+    if executions.is_empty() {
+        return None;
+    }
+    let instructions_executed = executions
+        .iter()
+        .map(|execution| execution.instructions_used.get())
+        .sum();
+    let callbacks: Vec<_> = executions
+        .iter()
+        .flat_map(|execution| execution.callbacks.iter().cloned())
+        .collect();
+    let report = MessageExecutionReport {
+        kind,
+        instructions_executed,
+        calls_made: callbacks.len() as u64,
+    };
+    Some((report, callbacks))
+}
+
+/// Builds the call tree of a call executed by the given canister from the traced executions
+/// on all subnets. The executions of the call itself are selected by `is_call`.
+/// Calls to the management canister are not executed by a canister and thus
+/// only the response callbacks of such calls are part of the call tree.
+fn call_report(
+    executions: &[(SubnetId, TracedExecution)],
+    canister_id: CanisterId,
+    subnet_id: SubnetId,
+    method: String,
+    is_call: &dyn Fn(&ExecutedMessage) -> bool,
+) -> CallReport {
+    let mut messages = vec![];
+    let mut calls = vec![];
+    let mut callbacks = VecDeque::new();
+    let call_executions = executions
+        .iter()
+        .filter(|(_, execution)| {
+            execution.canister_id == canister_id && is_call(&execution.message)
+        })
+        .map(|(_, execution)| execution)
+        .collect();
+    if let Some((message, message_callbacks)) =
+        message_execution_report(ExecutedMessageKind::Call, call_executions)
+    {
+        messages.push(message);
+        callbacks.extend(message_callbacks);
+    }
+    while let Some(callback_id) = callbacks.pop_front() {
+        let is_downstream_call = |message: &ExecutedMessage| {
+            matches!(message, ExecutedMessage::Request { sender, sender_reply_callback, .. }
+                if *sender == canister_id && *sender_reply_callback == callback_id)
+        };
+        let callee =
+            executions
+                .iter()
+                .find_map(|(subnet_id, execution)| match &execution.message {
+                    ExecutedMessage::Request { method_name, .. }
+                        if is_downstream_call(&execution.message) =>
+                    {
+                        Some((execution.canister_id, *subnet_id, method_name.clone()))
+                    }
+                    _ => None,
+                });
+        if let Some((callee_id, callee_subnet_id, callee_method)) = callee {
+            calls.push(call_report(
+                executions,
+                callee_id,
+                callee_subnet_id,
+                callee_method,
+                &is_downstream_call,
+            ));
+        }
+        let response_executions = executions
+            .iter()
+            .filter(|(_, execution)| {
+                execution.canister_id == canister_id
+                    && execution.message
+                        == ExecutedMessage::Response {
+                            originator_reply_callback: callback_id,
+                        }
+            })
+            .map(|(_, execution)| execution)
+            .collect();
+        if let Some((message, message_callbacks)) =
+            message_execution_report(ExecutedMessageKind::Response, response_executions)
+        {
+            messages.push(message);
+            callbacks.extend(message_callbacks);
+        }
+    }
+    CallReport {
+        canister_id: canister_id.get().0,
+        subnet_id: subnet_id.get().0,
+        method,
+        messages,
+        calls,
+    }
+}
+
+/// Adds the instructions executed and the calls made by every call in the given call tree
+/// to the report of the canister executing the call.
+fn add_call_costs(call: &CallReport, reports: &mut Vec<CanisterExecutionReport>) {
+    let instructions_executed = call.instructions_executed();
+    let calls_made: u64 = call.messages.iter().map(|message| message.calls_made).sum();
+    match reports
+        .iter_mut()
+        .find(|report| report.canister_id == call.canister_id)
+    {
+        Some(report) => {
+            report.instructions_executed += instructions_executed;
+            report.calls_made += calls_made;
+        }
+        None => reports.push(CanisterExecutionReport {
+            canister_id: call.canister_id,
+            subnet_id: call.subnet_id,
+            instructions_executed,
+            cycles_consumed_by_use_case: BTreeMap::new(),
+            cycles_balance_change: 0,
+            wasm_memory_growth: 0,
+            stable_memory_growth: 0,
+            calls_made,
+        }),
+    }
+    for call in &call.calls {
+        add_call_costs(call, reports);
+    }
+}
+
+/// Returns a report for every canister in the given call tree (in the order in which
+/// the canisters appear in the call tree, i.e., the called canister comes first).
+/// The instructions executed and calls made are taken from the call tree while
+/// the cycles and memory are compared before and after executing the call.
+fn canister_execution_reports(
+    call: &CallReport,
+    before: &BTreeMap<CanisterId, (SubnetId, CanisterCostMetrics)>,
+    after: &BTreeMap<CanisterId, (SubnetId, CanisterCostMetrics)>,
+) -> Vec<CanisterExecutionReport> {
+    let mut reports = vec![];
+    add_call_costs(call, &mut reports);
+    for report in reports.iter_mut() {
+        let canister_id = CanisterId::unchecked_from_principal(PrincipalId(report.canister_id));
+        let metrics = |metrics: &BTreeMap<CanisterId, (SubnetId, CanisterCostMetrics)>| {
+            metrics
+                .get(&canister_id)
+                .map(|(_, metrics)| metrics.clone())
+                .unwrap_or_default()
+        };
+        let (before, after) = (metrics(before), metrics(after));
+        report.cycles_consumed_by_use_case = after
+            .consumed_cycles_by_use_cases
+            .iter()
+            .filter_map(|(use_case, consumed)| {
+                let consumed_before = before
+                    .consumed_cycles_by_use_cases
+                    .get(use_case)
+                    .map(|consumed| consumed.get())
+                    .unwrap_or_default();
+                let consumed = consumed.get().saturating_sub(consumed_before);
+                (consumed > 0).then(|| (use_case.as_str().to_string(), consumed))
+            })
+            .collect();
+        report.cycles_balance_change =
+            after.cycles_balance.get() as i128 - before.cycles_balance.get() as i128;
+        report.wasm_memory_growth =
+            after.wasm_memory_size.get() as i64 - before.wasm_memory_size.get() as i64;
+        report.stable_memory_growth =
+            after.stable_memory_size.get() as i64 - before.stable_memory_size.get() as i64;
+    }
+    reports
+}
+
+#[derive(Clone, Debug)]
+pub struct ExecuteIngressMessageWithReport(pub CanisterCall);
+
+impl Operation for ExecuteIngressMessageWithReport {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        let before = canister_cost_metrics(pic);
+        // Executions are traced outside of the replicated state so that the report
+        // only covers the messages executed on behalf of this ingress message
+        // even if other canisters execute messages (e.g., timers) in the meantime.
+        start_execution_tracing(pic);
+        let (message_id, out) = match SubmitIngressMessage(self.0.clone()).compute(pic) {
+            OpOut::MessageId((effective_principal, message_id)) => {
+                let msg_id = OtherMessageId::try_from(message_id.as_slice()).unwrap();
+                let out = AwaitIngressMessage(MessageId {
+                    effective_principal,
+                    msg_id: msg_id.clone(),
+                })
+                .compute(pic);
+                (Some(msg_id), out)
+            }
+            out => (None, out),
+        };
+        let executions = stop_execution_tracing(pic);
+        match (message_id, out) {
+            (Some(message_id), OpOut::CanisterResult(result)) => {
+                let is_call = |message: &ExecutedMessage| matches!(message, ExecutedMessage::Ingress { message_id: id, .. } if *id == message_id);
+                let subnet_id = match executions
+                    .iter()
+                    .find(|(_, execution)| is_call(&execution.message))
+                {
+                    Some((subnet_id, _)) => *subnet_id,
+                    None => match route_call(pic, self.0.clone()) {
+                        Ok(subnet) => subnet.get_subnet_id(),
+                        Err(e) => return OpOut::Error(PocketIcError::BadIngressMessage(e)),
+                    },
+                };
+                let call = call_report(
+                    &executions,
+                    self.0.canister_id,
+                    subnet_id,
+                    self.0.method.clone(),
+                    &is_call,
+                );
+                let after = canister_cost_metrics(pic);
+                OpOut::ExecutionReport(ExecutionReport {
+                    result,
+                    canisters: canister_execution_reports(&call, &before, &after),
+                    call: call.into(),
+                })
+            }
+            (_, out) => out,
+        }
+    }
+
+    fn id(&self) -> OpId {
+        let call_id = self.0.id();
+        OpId(format!("execute_update_with_report_{}", call_id.0))
+    }
+}
+
+pub struct QueryWithReport(pub CanisterCall);
+
+impl Operation for QueryWithReport {
+    fn compute(&self, pic: &mut PocketIc) -> OpOut {
+        let subnet = match route_call(pic, self.0.clone()) {
+            Ok(subnet) => subnet,
+            Err(e) => return OpOut::Error(PocketIcError::BadIngressMessage(e)),
+        };
+        // Queries (including composite query callees) are executed on a single subnet
+        // and do not persist any state changes so that only instructions are reported.
+        subnet.start_execution_tracing();
+        let out = Query(self.0.clone()).compute(pic);
+        let subnet_id = subnet.get_subnet_id();
+        let executions: Vec<_> = subnet
+            .stop_execution_tracing()
+            .into_iter()
+            .map(|execution| (subnet_id, execution))
+            .collect();
+        match out {
+            OpOut::CanisterResult(result) => {
+                let call = call_report(
+                    &executions,
+                    self.0.canister_id,
+                    subnet_id,
+                    self.0.method.clone(),
+                    &|message| matches!(message, ExecutedMessage::UserQuery { .. }),
+                );
+                OpOut::ExecutionReport(ExecutionReport {
+                    result,
+                    canisters: canister_execution_reports(
+                        &call,
+                        &BTreeMap::new(),
+                        &BTreeMap::new(),
+                    ),
+                    call: call.into(),
+                })
+            }
+            out => out,
+        }
+    }
+
+    fn id(&self) -> OpId {
+        let call_id = self.0.id();
+        OpId(format!("canister_query_with_report_{}", call_id.0))
+    }
+}
+
 pub struct DashboardRequest {}
 
 impl Operation for DashboardRequest {
@@ -2521,6 +3087,103 @@ impl Operation for GetStableMemory {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct DownloadCanisterSnapshot {
+    pub canister_id: CanisterId,
+    pub snapshot_id: SnapshotId,
+    pub snapshot_path: PathBuf,
+}
+
+impl Operation for DownloadCanisterSnapshot {
+    fn compute(&self, pocket_ic: &mut PocketIc) -> OpOut {
+        let Some(subnet) = pocket_ic.try_route_canister(self.canister_id) else {
+            return OpOut::Error(PocketIcError::CanisterNotFound(self.canister_id));
+        };
+        let Some(snapshot_data) = subnet.canister_snapshot_data(self.canister_id, self.snapshot_id)
+        else {
+            return OpOut::Error(PocketIcError::CanisterSnapshotNotFound(
+                self.snapshot_id.to_string(),
+            ));
+        };
+        let write_snapshot = || -> Result<(), String> {
+            let file = File::create(&self.snapshot_path).map_err(|e| e.to_string())?;
+            let mut encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+            serde_cbor::to_writer(&mut encoder, &snapshot_data).map_err(|e| e.to_string())?;
+            encoder.finish().map_err(|e| e.to_string())?;
+            Ok(())
+        };
+        match write_snapshot() {
+            Ok(()) => OpOut::NoOutput,
+            Err(e) => OpOut::Error(PocketIcError::CanisterSnapshotError(format!(
+                "Failed to write canister snapshot to {}: {}",
+                self.snapshot_path.display(),
+                e
+            ))),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "download_canister_snapshot({},{},{})",
+            self.canister_id,
+            self.snapshot_id,
+            self.snapshot_path.display()
+        ))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct UploadCanisterSnapshot {
+    pub canister_id: CanisterId,
+    pub snapshot_path: PathBuf,
+}
+
+impl Operation for UploadCanisterSnapshot {
+    fn compute(&self, pocket_ic: &mut PocketIc) -> OpOut {
+        let Some(subnet) = pocket_ic.try_route_canister(self.canister_id) else {
+            return OpOut::Error(PocketIcError::CanisterNotFound(self.canister_id));
+        };
+        let read_snapshot = || -> Result<CanisterSnapshotData, String> {
+            let file = File::open(&self.snapshot_path).map_err(|e| e.to_string())?;
+            let decoder = flate2::read::GzDecoder::new(BufReader::new(file));
+            serde_cbor::from_reader(decoder).map_err(|e| e.to_string())
+        };
+        let snapshot_data = match read_snapshot() {
+            Ok(snapshot_data) => snapshot_data,
+            Err(e) => {
+                return OpOut::Error(PocketIcError::CanisterSnapshotError(format!(
+                    "Failed to read canister snapshot from {}: {}",
+                    self.snapshot_path.display(),
+                    e
+                )))
+            }
+        };
+        match subnet.upload_canister_snapshot_data(self.canister_id, snapshot_data) {
+            Ok(snapshot_id) => OpOut::Bytes(snapshot_id.to_vec()),
+            Err(CanisterSnapshotDataError::CanisterNotFound(canister_id)) => {
+                OpOut::Error(PocketIcError::CanisterNotFound(canister_id))
+            }
+            Err(CanisterSnapshotDataError::TooManySnapshots(canister_id)) => {
+                OpOut::Error(PocketIcError::CanisterSnapshotError(format!(
+                    "Canister {} has reached the maximum number of snapshots",
+                    canister_id
+                )))
+            }
+            Err(CanisterSnapshotDataError::InvalidSnapshot(msg)) => OpOut::Error(
+                PocketIcError::CanisterSnapshotError(format!("Invalid canister snapshot: {}", msg)),
+            ),
+        }
+    }
+
+    fn id(&self) -> OpId {
+        OpId(format!(
+            "upload_canister_snapshot({},{})",
+            self.canister_id,
+            self.snapshot_path.display()
+        ))
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct GetControllers {
     pub canister_id: CanisterId,
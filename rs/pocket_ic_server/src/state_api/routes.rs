@@ -5,13 +5,19 @@
 /// body. This has to be canonicalized into a PocketIc Operation before we can
 /// deterministically update the PocketIc state machine.
 ///
-use super::state::{ApiState, OpOut, PocketIcError, StateLabel, UpdateReply};
+use super::state::{
+    ApiState, InstanceRecording, OpOut, PendingRecordedRequest, PocketIcError, StateLabel,
+    UpdateReply, PENDING_RECORDED_REQUEST,
+};
 use crate::pocket_ic::{
-    AddCycles, AwaitIngressMessage, CallRequest, CallRequestVersion, CanisterReadStateRequest,
-    DashboardRequest, GetCanisterHttp, GetControllers, GetCyclesBalance, GetStableMemory,
-    GetSubnet, GetTime, GetTopology, IngressMessageStatus, MockCanisterHttp, PubKey, Query,
-    QueryRequest, SetCertifiedTime, SetStableMemory, SetTime, StatusRequest, SubmitIngressMessage,
-    SubnetReadStateRequest, Tick,
+    AddCallInterceptor, AddCycles, AwaitIngressMessage, CallRequest, CallRequestVersion,
+    CanisterReadStateRequest, DashboardRequest, DownloadCanisterSnapshot,
+    ExecuteIngressMessageWithReport, GetCanisterHttp, GetControllers, GetCyclesBalance,
+    GetInterceptedCalls, GetStableMemory, GetSubnet, GetTime, GetTopology, IngressMessageStatus,
+    MockCanisterHttp, MockInterceptedCall, PubKey, Query, QueryRequest, QueryWithReport,
+    RemoveCallInterceptor, SetCanisterQueuesFull, SetCertifiedTime, SetStableMemory,
+    SetSubnetStalled, SetTime, SetXNetPaused, SetXNetResponseFault, StatusRequest,
+    SubmitIngressMessage, SubnetReadStateRequest, Tick, UploadCanisterSnapshot,
 };
 use crate::{async_trait, pocket_ic::PocketIc, BlobStore, InstanceId, OpId, Operation};
 use aide::{
@@ -34,13 +40,17 @@ use backoff::backoff::Backoff;
 use backoff::{ExponentialBackoff, ExponentialBackoffBuilder};
 use hyper::header;
 use ic_http_endpoints_public::cors_layer;
-use ic_types::{CanisterId, SubnetId};
+use ic_types::{CanisterId, SnapshotId, SubnetId};
 use pocket_ic::common::rest::{
-    self, ApiResponse, AutoProgressConfig, ExtendedSubnetConfigSet, HttpGatewayConfig,
-    HttpGatewayDetails, InstanceConfig, MockCanisterHttpResponse, RawAddCycles, RawCanisterCall,
-    RawCanisterHttpRequest, RawCanisterId, RawCanisterResult, RawCycles, RawIngressStatusArgs,
-    RawMessageId, RawMockCanisterHttpResponse, RawPrincipalId, RawSetStableMemory, RawStableMemory,
-    RawSubnetId, RawTime, TickConfigs, Topology,
+    self, ApiResponse, AutoProgressConfig, BinaryBlob, BlobCompression, BlobId,
+    ExtendedSubnetConfigSet, HttpGatewayConfig, HttpGatewayDetails, InstanceConfig,
+    MockCanisterHttpResponse, MockInterceptedCallResponse, RawAddCycles, RawCallInterceptor,
+    RawCanisterCall, RawCanisterHttpRequest, RawCanisterId, RawCanisterResult, RawCycles,
+    RawDownloadCanisterSnapshot, RawExecutionReport, RawIngressStatusArgs, RawInterceptedCall,
+    RawMessageId, RawMockCanisterHttpResponse, RawMockInterceptedCallResponse, RawPrincipalId,
+    RawSetCanisterQueuesFull, RawSetStableMemory, RawSetSubnetStalled, RawSetXNetPaused,
+    RawSetXNetResponseFault, RawStableMemory, RawSubnetId, RawTime, RawUploadCanisterSnapshot,
+    RecordedBlob, RecordedRequest, TickConfigs, Topology,
 };
 use pocket_ic::RejectResponse;
 use serde::Serialize;
@@ -73,12 +83,18 @@ where
 {
     ApiRouter::new()
         .directory_route("/query", post(handler_json_query))
+        .directory_route("/query_with_report", post(handler_json_query_with_report))
         .directory_route("/topology", get(handler_topology))
         .directory_route("/get_time", get(handler_get_time))
         .directory_route("/get_canister_http", get(handler_get_canister_http))
+        .directory_route("/get_intercepted_calls", get(handler_get_intercepted_calls))
         .directory_route("/get_controllers", post(handler_get_controllers))
         .directory_route("/get_cycles", post(handler_get_cycles))
         .directory_route("/get_stable_memory", post(handler_get_stable_memory))
+        .directory_route(
+            "/download_canister_snapshot",
+            post(handler_download_canister_snapshot),
+        )
         .directory_route("/get_subnet", post(handler_get_subnet))
         .directory_route("/pub_key", post(handler_pub_key))
         .directory_route("/ingress_status", post(handler_ingress_status))
@@ -98,12 +114,39 @@ where
             "/await_ingress_message",
             post(handler_await_ingress_message),
         )
+        .directory_route(
+            "/execute_ingress_message_with_report",
+            post(handler_execute_ingress_message_with_report),
+        )
         .directory_route("/set_time", post(handler_set_time))
         .directory_route("/set_certified_time", post(handler_set_certified_time))
         .directory_route("/add_cycles", post(handler_add_cycles))
         .directory_route("/set_stable_memory", post(handler_set_stable_memory))
+        .directory_route(
+            "/upload_canister_snapshot",
+            post(handler_upload_canister_snapshot),
+        )
         .directory_route("/tick", post(handler_tick))
         .directory_route("/mock_canister_http", post(handler_mock_canister_http))
+        .directory_route("/add_call_interceptor", post(handler_add_call_interceptor))
+        .directory_route(
+            "/remove_call_interceptor",
+            post(handler_remove_call_interceptor),
+        )
+        .directory_route(
+            "/mock_intercepted_call",
+            post(handler_mock_intercepted_call),
+        )
+        .directory_route("/set_xnet_paused", post(handler_set_xnet_paused))
+        .directory_route(
+            "/set_xnet_response_fault",
+            post(handler_set_xnet_response_fault),
+        )
+        .directory_route("/set_subnet_stalled", post(handler_set_subnet_stalled))
+        .directory_route(
+            "/set_canister_queues_full",
+            post(handler_set_canister_queues_full),
+        )
 }
 
 pub fn instance_api_v2_routes<S>() -> ApiRouter<S>
@@ -498,6 +541,28 @@ impl TryFrom<OpOut> for Vec<RawCanisterHttpRequest> {
     }
 }
 
+impl TryFrom<OpOut> for RawExecutionReport {
+    type Error = OpConversionError;
+    fn try_from(value: OpOut) -> Result<Self, Self::Error> {
+        match value {
+            OpOut::ExecutionReport(execution_report) => Ok(execution_report.into()),
+            _ => Err(OpConversionError),
+        }
+    }
+}
+
+impl TryFrom<OpOut> for Vec<RawInterceptedCall> {
+    type Error = OpConversionError;
+    fn try_from(value: OpOut) -> Result<Self, Self::Error> {
+        match value {
+            OpOut::InterceptedCalls(intercepted_calls) => {
+                Ok(intercepted_calls.into_iter().map(|c| c.into()).collect())
+            }
+            _ => Err(OpConversionError),
+        }
+    }
+}
+
 #[async_trait]
 impl FromOpOut for PocketHttpResponse {
     async fn from(value: OpOut) -> (StatusCode, ApiResponse<PocketHttpResponse>) {
@@ -549,6 +614,28 @@ pub async fn handler_json_query(
     }
 }
 
+pub async fn handler_json_query_with_report(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_canister_call): extract::Json<RawCanisterCall>,
+) -> (StatusCode, Json<ApiResponse<RawExecutionReport>>) {
+    let timeout = timeout_or_default(headers);
+    match crate::pocket_ic::CanisterCall::try_from(raw_canister_call) {
+        Ok(canister_call) => {
+            let query_op = QueryWithReport(canister_call);
+            let (code, response) = run_operation(api_state, instance_id, timeout, query_op).await;
+            (code, Json(response))
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("{:?}", e),
+            }),
+        ),
+    }
+}
+
 pub async fn handler_topology(
     State(AppState { api_state, .. }): State<AppState>,
     headers: HeaderMap,
@@ -600,6 +687,163 @@ pub async fn handler_mock_canister_http(
     (code, Json(response))
 }
 
+pub async fn handler_get_intercepted_calls(
+    State(AppState { api_state, .. }): State<AppState>,
+    headers: HeaderMap,
+    Path(instance_id): Path<InstanceId>,
+) -> (StatusCode, Json<ApiResponse<Vec<RawInterceptedCall>>>) {
+    let timeout = timeout_or_default(headers);
+    let op = GetInterceptedCalls {};
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
+pub async fn handler_mock_intercepted_call(
+    State(AppState { api_state, .. }): State<AppState>,
+    headers: HeaderMap,
+    Path(instance_id): Path<InstanceId>,
+    axum::extract::Json(raw_mock_intercepted_call_response): axum::extract::Json<
+        RawMockInterceptedCallResponse,
+    >,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let mock_intercepted_call_response: MockInterceptedCallResponse =
+        raw_mock_intercepted_call_response.into();
+    let op = MockInterceptedCall {
+        mock_intercepted_call_response,
+    };
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
+pub async fn handler_add_call_interceptor(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_call_interceptor): extract::Json<RawCallInterceptor>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    match CanisterId::try_from(raw_call_interceptor.canister_id.canister_id) {
+        Ok(canister_id) => {
+            let op = AddCallInterceptor {
+                canister_id,
+                method: raw_call_interceptor.method,
+            };
+            let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+            (code, Json(response))
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("{:?}", e),
+            }),
+        ),
+    }
+}
+
+pub async fn handler_remove_call_interceptor(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_call_interceptor): extract::Json<RawCallInterceptor>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    match CanisterId::try_from(raw_call_interceptor.canister_id.canister_id) {
+        Ok(canister_id) => {
+            let op = RemoveCallInterceptor {
+                canister_id,
+                method: raw_call_interceptor.method,
+            };
+            let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+            (code, Json(response))
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("{:?}", e),
+            }),
+        ),
+    }
+}
+
+fn raw_subnet_id_to_subnet_id(raw_subnet_id: RawSubnetId) -> SubnetId {
+    SubnetId::new(ic_types::PrincipalId(candid::Principal::from_slice(
+        &raw_subnet_id.subnet_id,
+    )))
+}
+
+pub async fn handler_set_xnet_paused(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_set_xnet_paused): extract::Json<RawSetXNetPaused>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let op = SetXNetPaused {
+        from_subnet: raw_subnet_id_to_subnet_id(raw_set_xnet_paused.from_subnet),
+        to_subnet: raw_subnet_id_to_subnet_id(raw_set_xnet_paused.to_subnet),
+        paused: raw_set_xnet_paused.paused,
+    };
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
+pub async fn handler_set_xnet_response_fault(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_set_xnet_response_fault): extract::Json<RawSetXNetResponseFault>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let op = SetXNetResponseFault {
+        from_subnet: raw_subnet_id_to_subnet_id(raw_set_xnet_response_fault.from_subnet),
+        to_subnet: raw_subnet_id_to_subnet_id(raw_set_xnet_response_fault.to_subnet),
+        fault: raw_set_xnet_response_fault.fault,
+    };
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
+pub async fn handler_set_subnet_stalled(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_set_subnet_stalled): extract::Json<RawSetSubnetStalled>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let op = SetSubnetStalled {
+        subnet_id: raw_subnet_id_to_subnet_id(raw_set_subnet_stalled.subnet_id),
+        stalled: raw_set_subnet_stalled.stalled,
+    };
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
+pub async fn handler_set_canister_queues_full(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_set_canister_queues_full): extract::Json<RawSetCanisterQueuesFull>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    match CanisterId::try_from(raw_set_canister_queues_full.canister_id.canister_id) {
+        Ok(canister_id) => {
+            let op = SetCanisterQueuesFull {
+                canister_id,
+                full: raw_set_canister_queues_full.full,
+            };
+            let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+            (code, Json(response))
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("{:?}", e),
+            }),
+        ),
+    }
+}
+
 pub async fn handler_get_controllers(
     State(AppState { api_state, .. }): State<AppState>,
     Path(instance_id): Path<InstanceId>,
@@ -666,6 +910,44 @@ pub async fn handler_get_stable_memory(
     }
 }
 
+pub async fn handler_download_canister_snapshot(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    axum::extract::Json(raw): axum::extract::Json<RawDownloadCanisterSnapshot>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let timeout = timeout_or_default(headers);
+    let canister_id = match CanisterId::try_from(raw.canister_id.canister_id) {
+        Ok(canister_id) => canister_id,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::Error {
+                    message: format!("{:?}", e),
+                }),
+            )
+        }
+    };
+    let snapshot_id = match SnapshotId::try_from(&raw.snapshot_id) {
+        Ok(snapshot_id) => snapshot_id,
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ApiResponse::Error {
+                    message: e.to_string(),
+                }),
+            )
+        }
+    };
+    let op = DownloadCanisterSnapshot {
+        canister_id,
+        snapshot_id,
+        snapshot_path: raw.snapshot_path,
+    };
+    let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+    (code, Json(response))
+}
+
 pub async fn handler_get_subnet(
     State(AppState { api_state, .. }): State<AppState>,
     Path(instance_id): Path<InstanceId>,
@@ -894,6 +1176,20 @@ async fn op_out_to_response(op_out: OpOut) -> Response {
             )),
         )
             .into_response(),
+        opout @ OpOut::ExecutionReport(_) => (
+            StatusCode::OK,
+            Json(ApiResponse::Success(
+                RawExecutionReport::try_from(opout).unwrap(),
+            )),
+        )
+            .into_response(),
+        opout @ OpOut::InterceptedCalls(_) => (
+            StatusCode::OK,
+            Json(ApiResponse::Success(
+                Vec::<RawInterceptedCall>::try_from(opout).unwrap(),
+            )),
+        )
+            .into_response(),
         OpOut::RawResponse(fut) => {
             let (status, headers, bytes) = fut.await;
             let code = StatusCode::from_u16(status).unwrap();
@@ -973,6 +1269,28 @@ pub async fn handler_submit_ingress_message(
     }
 }
 
+pub async fn handler_execute_ingress_message_with_report(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    extract::Json(raw_canister_call): extract::Json<RawCanisterCall>,
+) -> (StatusCode, Json<ApiResponse<RawExecutionReport>>) {
+    let timeout = timeout_or_default(headers);
+    match crate::pocket_ic::CanisterCall::try_from(raw_canister_call) {
+        Ok(canister_call) => {
+            let ingress_op = ExecuteIngressMessageWithReport(canister_call);
+            let (code, response) = run_operation(api_state, instance_id, timeout, ingress_op).await;
+            (code, Json(response))
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("{:?}", e),
+            }),
+        ),
+    }
+}
+
 pub async fn handler_await_ingress_message(
     State(AppState { api_state, .. }): State<AppState>,
     Path(instance_id): Path<InstanceId>,
@@ -1097,6 +1415,31 @@ pub async fn handler_set_stable_memory(
     }
 }
 
+pub async fn handler_upload_canister_snapshot(
+    State(AppState { api_state, .. }): State<AppState>,
+    Path(instance_id): Path<InstanceId>,
+    headers: HeaderMap,
+    axum::extract::Json(raw): axum::extract::Json<RawUploadCanisterSnapshot>,
+) -> (StatusCode, Json<ApiResponse<Vec<u8>>>) {
+    let timeout = timeout_or_default(headers);
+    match CanisterId::try_from(raw.canister_id.canister_id) {
+        Ok(canister_id) => {
+            let op = UploadCanisterSnapshot {
+                canister_id,
+                snapshot_path: raw.snapshot_path,
+            };
+            let (code, response) = run_operation(api_state, instance_id, timeout, op).await;
+            (code, Json(response))
+        }
+        Err(e) => (
+            StatusCode::BAD_REQUEST,
+            Json(ApiResponse::Error {
+                message: format!("{:?}", e),
+            }),
+        ),
+    }
+}
+
 pub async fn handler_tick(
     State(AppState { api_state, .. }): State<AppState>,
     Path(instance_id): Path<InstanceId>,
@@ -1190,6 +1533,30 @@ pub async fn create_instance(
         None
     };
 
+    let recording = if let Some(recording_path) = &instance_config.recording_path {
+        let recorded_instance_config = InstanceConfig {
+            recording_path: None,
+            ..instance_config.clone()
+        };
+        match InstanceRecording::new(recording_path, recorded_instance_config) {
+            Ok(recording) => Some(recording),
+            Err(e) => {
+                return (
+                    StatusCode::BAD_REQUEST,
+                    Json(rest::CreateInstanceResponse::Error {
+                        message: format!(
+                            "Failed to create recording file {}: {}",
+                            recording_path.display(),
+                            e
+                        ),
+                    }),
+                )
+            }
+        }
+    } else {
+        None
+    };
+
     let (instance_id, topology) = api_state
         .add_instance(move |seed| {
             PocketIc::new(
@@ -1203,6 +1570,9 @@ pub async fn create_instance(
             )
         })
         .await;
+    if let Some(recording) = recording {
+        api_state.add_recording(instance_id, recording).await;
+    }
     (
         StatusCode::CREATED,
         Json(rest::CreateInstanceResponse::Created {
@@ -1360,6 +1730,131 @@ fn make_plaintext_response(status: StatusCode, message: String) -> Response<Body
     resp
 }
 
+/// Returns the instance ID and the endpoint (relative to the instance URL)
+/// of a state-changing request to an instance, i.e., of a request to be recorded
+/// if the instance is in recording mode.
+fn recorded_instance_endpoint(path: &str) -> Option<(InstanceId, String)> {
+    let (instance_id, endpoint) = path.strip_prefix("/instances/")?.split_once('/')?;
+    let instance_id = instance_id.parse().ok()?;
+    let is_call = (endpoint.starts_with("api/v2/canister/")
+        || endpoint.starts_with("api/v3/canister/"))
+        && endpoint.ends_with("/call");
+    if endpoint.starts_with("update/") || is_call {
+        Some((instance_id, endpoint.to_string()))
+    } else {
+        None
+    }
+}
+
+/// Collects the IDs of all blobs referenced by a JSON-encoded request body,
+/// i.e., the values of all fields named `blob_id`.
+fn referenced_blob_ids(value: &serde_json::Value, blob_ids: &mut Vec<BlobId>) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (name, field) in fields {
+                if name == "blob_id" {
+                    if let Ok(blob_id) = serde_json::from_value::<BlobId>(field.clone()) {
+                        blob_ids.push(blob_id);
+                        continue;
+                    }
+                }
+                referenced_blob_ids(field, blob_ids);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                referenced_blob_ids(item, blob_ids);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Records state-changing requests to instances in recording mode.
+/// A request is recorded when it is dispatched to its instance
+/// (see `PENDING_RECORDED_REQUEST`) so that the recorded requests are in the order
+/// of their execution. Requests that were not executed because the instance was busy
+/// are not recorded since they are retried by the client.
+pub async fn record_instance_request(
+    State(AppState {
+        api_state,
+        blob_store,
+        ..
+    }): State<AppState>,
+    request: axum::extract::Request,
+    next: Next,
+) -> axum::response::Response {
+    let Some((instance_id, endpoint)) = recorded_instance_endpoint(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    if !api_state.is_recording(instance_id).await {
+        return next.run(request).await;
+    }
+
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let (parts, body) = request.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            return make_plaintext_response(
+                StatusCode::BAD_REQUEST,
+                format!("Failed to read request body: {}", e),
+            )
+        }
+    };
+    // Blobs are not specific to an instance and thus must be recorded
+    // together with the request using them (unless they have been recorded
+    // when they were uploaded already).
+    let mut blob_ids = vec![];
+    if let Ok(value) = serde_json::from_slice::<serde_json::Value>(&body) {
+        referenced_blob_ids(&value, &mut blob_ids);
+    }
+    let mut blobs = vec![];
+    for blob_id in blob_ids {
+        if let Some(BinaryBlob { data, compression }) = blob_store.fetch(blob_id.clone()).await {
+            let blob = RecordedBlob {
+                data,
+                gzip: compression == BlobCompression::Gzip,
+            };
+            blobs.push((blob_id, blob));
+        }
+    }
+    let pending = Arc::new(std::sync::Mutex::new(Some(PendingRecordedRequest {
+        instance_id,
+        blobs,
+        request: RecordedRequest {
+            endpoint,
+            content_type,
+            body: body.to_vec(),
+        },
+    })));
+
+    let response = PENDING_RECORDED_REQUEST
+        .scope(
+            pending.clone(),
+            next.run(axum::extract::Request::from_parts(parts, Body::from(body))),
+        )
+        .await;
+
+    // Requests that have not been dispatched to the instance as an operation
+    // (e.g., because they failed validation) are recorded after they have been handled.
+    let pending = pending.lock().unwrap().take();
+    if let Some(pending) = pending {
+        if !matches!(
+            response.status(),
+            StatusCode::CONFLICT | StatusCode::TOO_MANY_REQUESTS
+        ) {
+            api_state.record_request(pending).await;
+        }
+    }
+
+    response
+}
+
 pub async fn verify_cbor_content_header(
     request: axum::extract::Request,
     next: Next,
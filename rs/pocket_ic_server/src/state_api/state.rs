@@ -34,15 +34,18 @@ use ic_http_gateway::{CanisterRequest, HttpGatewayClient, HttpGatewayRequestArgs
 use ic_types::{canister_http::CanisterHttpRequestId, CanisterId, NodeId, PrincipalId, SubnetId};
 use itertools::Itertools;
 use pocket_ic::common::rest::{
-    CanisterHttpRequest, HttpGatewayBackend, HttpGatewayConfig, HttpGatewayDetails,
-    HttpGatewayInfo, Topology,
+    BlobId, CanisterHttpRequest, ExecutionReport, HttpGatewayBackend, HttpGatewayConfig,
+    HttpGatewayDetails, HttpGatewayInfo, InstanceConfig, InterceptedCall, RecordedBlob,
+    RecordedEntry, RecordedRequest, Topology,
 };
 use pocket_ic::RejectResponse;
 use reqwest::Url;
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
-    path::PathBuf,
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
     str::FromStr,
     sync::atomic::AtomicU64,
     sync::Arc,
@@ -126,6 +129,63 @@ struct Instance {
     state: InstanceState,
 }
 
+/// A recording of the state-changing requests to an instance
+/// stored as a file with one JSON-encoded `RecordedEntry` per line.
+pub struct InstanceRecording {
+    file: File,
+    // blobs that have already been recorded (a blob is recorded at most once)
+    recorded_blobs: HashSet<BlobId>,
+}
+
+impl InstanceRecording {
+    pub fn new(path: &Path, instance_config: InstanceConfig) -> std::io::Result<Self> {
+        let mut recording = Self {
+            file: File::create(path)?,
+            recorded_blobs: HashSet::new(),
+        };
+        recording.append(&RecordedEntry::Instance(instance_config))?;
+        Ok(recording)
+    }
+
+    fn append(&mut self, entry: &RecordedEntry) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(entry).map_err(std::io::Error::other)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+
+    fn append_blob(&mut self, blob_id: BlobId, blob: RecordedBlob) -> std::io::Result<()> {
+        if !self.recorded_blobs.contains(&blob_id) {
+            self.append(&RecordedEntry::Blob(blob))?;
+            self.recorded_blobs.insert(blob_id);
+        }
+        Ok(())
+    }
+
+    fn append_request(&mut self, pending: PendingRecordedRequest) -> std::io::Result<()> {
+        for (blob_id, blob) in pending.blobs {
+            self.append_blob(blob_id, blob)?;
+        }
+        self.append(&RecordedEntry::Request(pending.request))
+    }
+}
+
+/// A state-changing request to an instance in recording mode
+/// together with the blobs it uses.
+pub struct PendingRecordedRequest {
+    pub instance_id: InstanceId,
+    pub blobs: Vec<(BlobId, RecordedBlob)>,
+    pub request: RecordedRequest,
+}
+
+tokio::task_local! {
+    /// The request served by the current task that is to be recorded
+    /// once it is dispatched to its instance (see `record_instance_request`).
+    /// The request is taken out of the cell when it is recorded.
+    pub static PENDING_RECORDED_REQUEST: Arc<std::sync::Mutex<Option<PendingRecordedRequest>>>;
+}
+
+type Recordings = Arc<Mutex<HashMap<InstanceId, InstanceRecording>>>;
+
 struct HttpGateway {
     details: HttpGatewayDetails,
     shutdown_handle: Handle,
@@ -157,6 +217,8 @@ pub struct ApiState {
     port: Option<u16>,
     // HTTP gateway infos (`None` = stopped)
     http_gateways: Arc<RwLock<Vec<Option<HttpGateway>>>>,
+    // recordings of instances in recording mode
+    recordings: Recordings,
 }
 
 #[derive(Default)]
@@ -222,6 +284,7 @@ impl PocketIcApiStateBuilder {
             sync_wait_time,
             port: self.port,
             http_gateways: Arc::new(RwLock::new(Vec::new())),
+            recordings: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 }
@@ -242,6 +305,8 @@ pub enum OpOut {
     MessageId((EffectivePrincipal, Vec<u8>)),
     Topology(Topology),
     CanisterHttp(Vec<CanisterHttpRequest>),
+    InterceptedCalls(Vec<InterceptedCall>),
+    ExecutionReport(ExecutionReport),
 }
 
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Deserialize, Serialize)]
@@ -258,6 +323,10 @@ pub enum PocketIcError {
     Forbidden(String),
     BlockmakerNotFound(NodeId),
     BlockmakerContainedInFailed(NodeId),
+    InvalidInterceptedCallId((SubnetId, u64)),
+    InterceptedCallResponseNotInducted(String),
+    CanisterSnapshotNotFound(String),
+    CanisterSnapshotError(String),
 }
 
 impl std::fmt::Debug for OpOut {
@@ -313,6 +382,18 @@ impl std::fmt::Debug for OpOut {
                     actual, expected
                 )
             }
+            OpOut::Error(PocketIcError::InvalidInterceptedCallId((subnet_id, call_id))) => {
+                write!(f, "InvalidInterceptedCallId({},{})", subnet_id, call_id)
+            }
+            OpOut::Error(PocketIcError::InterceptedCallResponseNotInducted(msg)) => {
+                write!(f, "InterceptedCallResponseNotInducted({})", msg)
+            }
+            OpOut::Error(PocketIcError::CanisterSnapshotNotFound(snapshot_id)) => {
+                write!(f, "CanisterSnapshotNotFound({})", snapshot_id)
+            }
+            OpOut::Error(PocketIcError::CanisterSnapshotError(msg)) => {
+                write!(f, "CanisterSnapshotError({})", msg)
+            }
             OpOut::Error(PocketIcError::InvalidRejectCode(code)) => {
                 write!(f, "InvalidRejectCode({})", code)
             }
@@ -349,6 +430,12 @@ impl std::fmt::Debug for OpOut {
             OpOut::CanisterHttp(canister_http_reqeusts) => {
                 write!(f, "CanisterHttp({:?})", canister_http_reqeusts)
             }
+            OpOut::InterceptedCalls(intercepted_calls) => {
+                write!(f, "InterceptedCalls({:?})", intercepted_calls)
+            }
+            OpOut::ExecutionReport(execution_report) => {
+                write!(f, "ExecutionReport({:?})", execution_report)
+            }
         }
     }
 }
@@ -692,6 +779,7 @@ impl ApiState {
             match Self::update_instances_with_timeout(
                 instances.clone(),
                 graph.clone(),
+                None,
                 op.clone(),
                 instance_id,
                 AUTO_PROGRESS_OPERATION_TIMEOUT,
@@ -764,8 +852,78 @@ impl ApiState {
         (instance_id, topology)
     }
 
+    /// Puts the given instance into recording mode.
+    pub async fn add_recording(&self, instance_id: InstanceId, recording: InstanceRecording) {
+        self.recordings.lock().await.insert(instance_id, recording);
+    }
+
+    pub async fn is_recording(&self, instance_id: InstanceId) -> bool {
+        self.recordings.lock().await.contains_key(&instance_id)
+    }
+
+    pub async fn has_recordings(&self) -> bool {
+        !self.recordings.lock().await.is_empty()
+    }
+
+    /// Appends a blob uploaded to the blob store to the recordings of all instances in recording mode
+    /// since blobs are not specific to an instance.
+    pub async fn record_blob(&self, blob_id: BlobId, blob: RecordedBlob) {
+        for (instance_id, recording) in self.recordings.lock().await.iter_mut() {
+            if let Err(e) = recording.append_blob(blob_id.clone(), blob.clone()) {
+                error!(
+                    "Failed to record a blob for instance {}: {}",
+                    instance_id, e
+                );
+            }
+        }
+    }
+
+    /// Appends a request to the recording of its instance (if the instance is in recording mode).
+    /// Requests dispatched to an instance as an operation are recorded
+    /// when they are dispatched (see `PENDING_RECORDED_REQUEST`)
+    /// and thus this function is only used for the remaining requests.
+    pub async fn record_request(&self, pending: PendingRecordedRequest) {
+        Self::append_recorded_request(&self.recordings, pending).await;
+    }
+
+    async fn append_recorded_request(recordings: &Recordings, pending: PendingRecordedRequest) {
+        let instance_id = pending.instance_id;
+        if let Some(recording) = recordings.lock().await.get_mut(&instance_id) {
+            if let Err(e) = recording.append_request(pending) {
+                error!(
+                    "Failed to record a request to instance {}: {}",
+                    instance_id, e
+                );
+            }
+        }
+    }
+
+    /// Records the request served by the current task (if any) at the time
+    /// it is dispatched to the given instance so that the recorded requests
+    /// are in the order of their execution.
+    async fn record_dispatched_request(recordings: &Recordings, instance_id: InstanceId) {
+        let pending = PENDING_RECORDED_REQUEST
+            .try_with(|pending| {
+                let mut pending = pending.lock().unwrap();
+                if pending
+                    .as_ref()
+                    .is_some_and(|pending| pending.instance_id == instance_id)
+                {
+                    pending.take()
+                } else {
+                    None
+                }
+            })
+            .ok()
+            .flatten();
+        if let Some(pending) = pending {
+            Self::append_recorded_request(recordings, pending).await;
+        }
+    }
+
     pub async fn delete_instance(&self, instance_id: InstanceId) {
         self.stop_progress(instance_id).await;
+        self.recordings.lock().await.remove(&instance_id);
         loop {
             let instances = self.instances.read().await;
             let mut instance = instances[instance_id].lock().await;
@@ -1138,6 +1296,7 @@ impl ApiState {
         Self::update_instances_with_timeout(
             self.instances.clone(),
             self.graph.clone(),
+            Some(self.recordings.clone()),
             op,
             instance_id,
             sync_wait_time,
@@ -1150,6 +1309,7 @@ impl ApiState {
     async fn update_instances_with_timeout<O>(
         instances: Arc<RwLock<Vec<Mutex<Instance>>>>,
         graph: Arc<RwLock<HashMap<StateLabel, Computations>>>,
+        recordings: Option<Recordings>,
         op: Arc<O>,
         instance_id: InstanceId,
         sync_wait_time: Duration,
@@ -1198,6 +1358,12 @@ impl ApiState {
                         unreachable!()
                     };
 
+                    // The operation is dispatched while holding the instance lock
+                    // and thus operations are recorded in the order of their execution.
+                    if let Some(recordings) = recordings {
+                        Self::record_dispatched_request(&recordings, instance_id).await;
+                    }
+
                     let bg_task = {
                         let old_state_label = state_label.clone();
                         let op_id = op_id.clone();
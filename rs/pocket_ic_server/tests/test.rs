@@ -104,6 +104,7 @@ fn test_creation_of_instance_extended() {
         nonmainnet_features: false,
         log_level: None,
         bitcoind_addr: None,
+        recording_path: None,
     };
     let response = client
         .post(url.join("instances").unwrap())
@@ -143,6 +143,9 @@ pub struct GetSuccessorsResponseComplete {
     pub blocks: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
     #[prost(bytes = "vec", repeated, tag = "2")]
     pub next: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    /// BIP-158 basic filters of the blocks, in the same order as `blocks`.
+    #[prost(bytes = "vec", repeated, tag = "3")]
+    pub filters: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
 }
 /// A `GetSucceessors` reject response containing additional information about the rejection.
 #[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, ::prost::Message)]
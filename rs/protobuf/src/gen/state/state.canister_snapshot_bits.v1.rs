@@ -24,4 +24,8 @@ pub struct CanisterSnapshotBits {
     pub total_size: u64,
     #[prost(message, repeated, tag = "11")]
     pub exported_globals: ::prost::alloc::vec::Vec<super::super::canister_state_bits::v1::Global>,
+    /// The size of the Wasm module declared in the metadata uploaded by the user.
+    /// Only set for snapshots created from uploaded metadata.
+    #[prost(uint64, optional, tag = "12")]
+    pub uploaded_wasm_module_size: ::core::option::Option<u64>,
 }
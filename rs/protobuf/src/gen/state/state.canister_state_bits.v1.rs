@@ -541,6 +541,13 @@ pub struct CanisterLogRecord {
     pub content: ::prost::alloc::vec::Vec<u8>,
 }
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EnvironmentVariable {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub value: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct SnapshotId {
     #[prost(bytes = "vec", tag = "1")]
     pub content: ::prost::alloc::vec::Vec<u8>,
@@ -669,6 +676,9 @@ pub struct CanisterStateBits {
     /// canister.
     #[prost(message, optional, tag = "54")]
     pub tasks: ::core::option::Option<TaskQueue>,
+    /// Environment variables of the canister.
+    #[prost(message, repeated, tag = "55")]
+    pub environment_variables: ::prost::alloc::vec::Vec<EnvironmentVariable>,
     #[prost(oneof = "canister_state_bits::CanisterStatus", tags = "11, 12, 13")]
     pub canister_status: ::core::option::Option<canister_state_bits::CanisterStatus>,
 }
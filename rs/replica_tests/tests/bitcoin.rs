@@ -42,6 +42,7 @@ impl MockBitcoinAdapterBuilder {
             get_successors_response: Ok(BtcServiceGetSuccessorsResponse {
                 blocks: vec![],
                 next: vec![],
+                filters: vec![],
             }),
 
             send_transaction_response: Ok(BtcServiceSendTransactionResponse {}),
@@ -197,6 +198,7 @@ fn bitcoin_get_successors() {
             .with_get_successors_reply(BtcServiceGetSuccessorsResponse {
                 blocks: vec![],
                 next: vec![],
+                filters: vec![],
             })
             .build(),
         |runtime| {
@@ -220,6 +222,7 @@ fn bitcoin_get_successors() {
                 ic00::BitcoinGetSuccessorsResponse::Complete(GetSuccessorsResponseComplete {
                     blocks: vec![],
                     next: vec![],
+                    filters: vec![],
                 });
 
             assert_eq!(response, WasmResult::Reply(expected_response.encode()));
@@ -235,6 +238,7 @@ fn bitcoin_get_successors_pagination() {
             .with_get_successors_reply(BtcServiceGetSuccessorsResponse {
                 blocks: vec![vec![0; 4_000_000]],
                 next: vec![],
+                filters: vec![],
             })
             .build(),
         |runtime| {
@@ -258,6 +262,7 @@ fn bitcoin_get_successors_pagination() {
                     partial_block: vec![0; 2_000_000],
                     next: vec![],
                     remaining_follow_ups: 1,
+                    filters: vec![],
                 });
 
             assert_eq!(response, WasmResult::Reply(expected_response.encode()));
@@ -281,6 +286,7 @@ fn bitcoin_get_successors_pagination_invalid_adapter_request() {
             .with_get_successors_reply(BtcServiceGetSuccessorsResponse {
                 blocks: vec![vec![0; 4_000_000], vec![0]],
                 next: vec![],
+                filters: vec![],
             })
             .build(),
         |runtime| {
@@ -122,8 +122,11 @@ fn maybe_split_response(
         let block = &response.blocks[0];
         let mut follow_ups = vec![];
 
-        let first_response_block_size =
-            MAX_RESPONSE_SIZE.saturating_sub(response.count_next_bytes());
+        // The filter of the block is small compared to the block itself, so it's
+        // sent along with the first page.
+        let first_response_block_size = MAX_RESPONSE_SIZE
+            .saturating_sub(response.count_next_bytes())
+            .saturating_sub(response.count_filters_bytes());
         let mut i = first_response_block_size;
         while i < block.len() {
             let follow_up_length = min(MAX_RESPONSE_SIZE, block.len() - i);
@@ -141,6 +144,7 @@ fn maybe_split_response(
             partial_block: block[0..first_response_block_size].to_vec(),
             next: response.next,
             remaining_follow_ups,
+            filters: response.filters,
         };
 
         Ok((
@@ -162,6 +166,7 @@ mod test {
             maybe_split_response(GetSuccessorsResponseComplete {
                 blocks: vec![vec![0; MAX_RESPONSE_SIZE], vec![0]], // two blocks exceeding size.
                 next: vec![],
+                filters: vec![],
             }),
             Err(SplitError::NotOneBlock)
         );
@@ -170,6 +175,7 @@ mod test {
             maybe_split_response(GetSuccessorsResponseComplete {
                 blocks: vec![],
                 next: vec![vec![0; MAX_RESPONSE_SIZE + 1]],
+                filters: vec![],
             }),
             Err(SplitError::NotOneBlock)
         );
@@ -181,12 +187,14 @@ mod test {
             maybe_split_response(GetSuccessorsResponseComplete {
                 blocks: vec![vec![0; MAX_RESPONSE_SIZE + 1]],
                 next: vec![],
+                filters: vec![],
             }),
             Ok((
                 BitcoinGetSuccessorsResponse::Partial(GetSuccessorsResponsePartial {
                     partial_block: vec![0; MAX_RESPONSE_SIZE],
                     next: vec![],
-                    remaining_follow_ups: 1
+                    remaining_follow_ups: 1,
+                    filters: vec![],
                 }),
                 vec![vec![0]]
             ))
@@ -199,15 +207,37 @@ mod test {
             maybe_split_response(GetSuccessorsResponseComplete {
                 blocks: vec![vec![0; MAX_RESPONSE_SIZE * 2 + 1]],
                 next: vec![],
+                filters: vec![],
             }),
             Ok((
                 BitcoinGetSuccessorsResponse::Partial(GetSuccessorsResponsePartial {
                     partial_block: vec![0; MAX_RESPONSE_SIZE],
                     next: vec![],
-                    remaining_follow_ups: 2
+                    remaining_follow_ups: 2,
+                    filters: vec![],
                 }),
                 vec![vec![0; MAX_RESPONSE_SIZE], vec![0]]
             ))
         );
     }
+
+    #[test]
+    fn maybe_split_response_with_filter() {
+        assert_eq!(
+            maybe_split_response(GetSuccessorsResponseComplete {
+                blocks: vec![vec![0; MAX_RESPONSE_SIZE + 1]],
+                next: vec![],
+                filters: vec![vec![1; 10]],
+            }),
+            Ok((
+                BitcoinGetSuccessorsResponse::Partial(GetSuccessorsResponsePartial {
+                    partial_block: vec![0; MAX_RESPONSE_SIZE - 10],
+                    next: vec![],
+                    remaining_follow_ups: 1,
+                    filters: vec![vec![1; 10]],
+                }),
+                vec![vec![0; 11]]
+            ))
+        );
+    }
 }
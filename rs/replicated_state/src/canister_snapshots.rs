@@ -60,8 +60,13 @@ impl CanisterSnapshots {
     /// which represents the new backup accumulated since the last flush to the disk.
     pub fn push(&mut self, snapshot_id: SnapshotId, snapshot: Arc<CanisterSnapshot>) -> SnapshotId {
         let canister_id = snapshot.canister_id();
-        self.unflushed_changes
-            .push(SnapshotOperation::Backup(canister_id, snapshot_id));
+        let operation = match snapshot.source() {
+            SnapshotSource::TakenFromCanister => {
+                SnapshotOperation::Backup(canister_id, snapshot_id)
+            }
+            SnapshotSource::MetadataUpload { .. } => SnapshotOperation::UploadMetadata(snapshot_id),
+        };
+        self.unflushed_changes.push(operation);
         self.memory_usage += snapshot.size();
         self.snapshots.insert(snapshot_id, snapshot);
         let snapshot_ids = self.snapshot_ids.entry(canister_id).or_default();
@@ -79,6 +84,23 @@ impl CanisterSnapshots {
         self.snapshots.get_mut(&snapshot_id)
     }
 
+    /// Applies `f` to the canister snapshot identified by `snapshot_id`.
+    ///
+    /// Unlike `get_mut`, keeps the memory usage of all canister snapshots in sync
+    /// with the size of the snapshot after the update. Returns `None` if the
+    /// snapshot does not exist.
+    pub fn update<R>(
+        &mut self,
+        snapshot_id: SnapshotId,
+        f: impl FnOnce(&mut CanisterSnapshot) -> R,
+    ) -> Option<R> {
+        let snapshot = Arc::make_mut(self.snapshots.get_mut(&snapshot_id)?);
+        let old_size = snapshot.size();
+        let result = f(snapshot);
+        self.memory_usage = self.memory_usage - old_size + snapshot.size();
+        Some(result)
+    }
+
     /// Iterate over all snapshots.
     pub fn iter(&self) -> impl Iterator<Item = (&SnapshotId, &Arc<CanisterSnapshot>)> {
         self.snapshots.iter()
@@ -299,6 +321,17 @@ pub struct ExecutionStateSnapshot {
     pub wasm_memory: PageMemory,
 }
 
+/// Describes how a canister snapshot was created.
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum SnapshotSource {
+    /// The snapshot was taken from the state of the canister.
+    TakenFromCanister,
+    /// The snapshot was created from metadata uploaded by the user and its data
+    /// is uploaded in slices. The Wasm module grows as the slices are written,
+    /// up to the `wasm_module_size` declared in the metadata.
+    MetadataUpload { wasm_module_size: u64 },
+}
+
 /// Contains all information related to a canister snapshot.
 #[derive(Clone, Eq, PartialEq, Debug, ValidateEq)]
 pub struct CanisterSnapshot {
@@ -317,9 +350,12 @@ pub struct CanisterSnapshot {
     chunk_store: WasmChunkStore,
     #[validate_eq(CompareWithValidateEq)]
     execution_snapshot: ExecutionStateSnapshot,
+    /// How the snapshot was created.
+    source: SnapshotSource,
 }
 
 impl CanisterSnapshot {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         canister_id: CanisterId,
         taken_at_timestamp: Time,
@@ -328,6 +364,7 @@ impl CanisterSnapshot {
         chunk_store: WasmChunkStore,
         execution_snapshot: ExecutionStateSnapshot,
         size: NumBytes,
+        source: SnapshotSource,
     ) -> CanisterSnapshot {
         Self {
             canister_id,
@@ -337,6 +374,7 @@ impl CanisterSnapshot {
             chunk_store,
             execution_snapshot,
             size,
+            source,
         }
     }
 
@@ -365,6 +403,7 @@ impl CanisterSnapshot {
             chunk_store: canister.system_state.wasm_chunk_store.clone(),
             execution_snapshot,
             size: canister.snapshot_size_bytes(),
+            source: SnapshotSource::TakenFromCanister,
         })
     }
 
@@ -384,6 +423,21 @@ impl CanisterSnapshot {
         self.size
     }
 
+    pub fn source(&self) -> SnapshotSource {
+        self.source
+    }
+
+    /// Returns the size of the Wasm module of this snapshot.
+    ///
+    /// For an uploaded snapshot, this is the size declared in the metadata,
+    /// which the module only reaches once its last slice is written.
+    pub fn wasm_module_size(&self) -> u64 {
+        match self.source {
+            SnapshotSource::TakenFromCanister => self.canister_module().len() as u64,
+            SnapshotSource::MetadataUpload { wasm_module_size } => wasm_module_size,
+        }
+    }
+
     pub fn execution_snapshot(&self) -> &ExecutionStateSnapshot {
         &self.execution_snapshot
     }
@@ -420,6 +474,17 @@ impl CanisterSnapshot {
         &mut self.execution_snapshot
     }
 
+    /// Inserts a chunk into the chunk store of this snapshot and grows the size
+    /// of the snapshot by the memory taken by the chunk.
+    ///
+    /// Used when the snapshot data is uploaded by the user.
+    pub fn insert_chunk(&mut self, max_size: NumBytes, chunk: &[u8]) -> Result<[u8; 32], String> {
+        let old_memory_usage = self.chunk_store.memory_usage();
+        let hash = self.chunk_store.insert_chunk(max_size, chunk)?;
+        self.size = self.size - old_memory_usage + self.chunk_store.memory_usage();
+        Ok(hash)
+    }
+
     /// Returns the heap delta produced by this snapshot.
     ///
     /// The heap delta includes the delta of the wasm memory, stable memory and
@@ -452,6 +517,7 @@ pub enum SnapshotOperation {
     Delete(SnapshotId),
     Backup(CanisterId, SnapshotId),
     Restore(CanisterId, SnapshotId),
+    UploadMetadata(SnapshotId),
 }
 
 #[cfg(test)]
@@ -487,6 +553,7 @@ mod tests {
             WasmChunkStore::new_for_testing(),
             execution_snapshot,
             NumBytes::from(0),
+            SnapshotSource::TakenFromCanister,
         );
 
         let snapshot_id = SnapshotId::from((canister_id, local_id));
@@ -533,6 +600,27 @@ mod tests {
         assert_eq!(snapshot_manager.snapshot_ids.get(&canister_id), None);
     }
 
+    #[test]
+    fn test_push_uploaded_snapshot() {
+        let canister_id = canister_test_id(0);
+        let (snapshot_id, snapshot) = fake_canister_snapshot(canister_id, 1);
+        let snapshot = CanisterSnapshot {
+            source: SnapshotSource::MetadataUpload {
+                wasm_module_size: 10,
+            },
+            ..snapshot
+        };
+        assert_eq!(snapshot.wasm_module_size(), 10);
+        let mut snapshot_manager = CanisterSnapshots::default();
+
+        // Uploaded snapshots do not start out as a backup of the canister.
+        snapshot_manager.push(snapshot_id, Arc::<CanisterSnapshot>::new(snapshot));
+        assert_eq!(
+            snapshot_manager.take_unflushed_changes(),
+            vec![SnapshotOperation::UploadMetadata(snapshot_id)]
+        );
+    }
+
     #[test]
     fn test_construct_canister_snapshot_ids() {
         let snapshots: BTreeMap<_, _> = [
@@ -621,4 +709,39 @@ mod tests {
             NumBytes::from(0)
         );
     }
+
+    #[test]
+    fn test_memory_usage_correctly_updated_while_inserting_chunks() {
+        let canister_id = canister_test_id(0);
+        let (snapshot_id, snapshot) = fake_canister_snapshot(canister_id, 1);
+        let mut snapshot_manager = CanisterSnapshots::default();
+        snapshot_manager.push(snapshot_id, Arc::<CanisterSnapshot>::new(snapshot));
+        assert_eq!(snapshot_manager.memory_taken(), NumBytes::from(0));
+
+        // Inserting a chunk grows both the snapshot and the `memory_usage`.
+        let max_size = NumBytes::from(1024 * 1024 * 1024);
+        let hash = snapshot_manager
+            .update(snapshot_id, |snapshot| {
+                snapshot.insert_chunk(max_size, &[1, 2, 3])
+            })
+            .unwrap()
+            .unwrap();
+        let chunk_store = snapshot_manager.get(snapshot_id).unwrap().chunk_store();
+        assert!(chunk_store.keys().any(|key| *key == hash));
+        let expected_size = chunk_store.memory_usage();
+        assert_eq!(
+            snapshot_manager.get(snapshot_id).unwrap().size(),
+            expected_size
+        );
+        assert_eq!(snapshot_manager.memory_taken(), expected_size);
+        assert_eq!(
+            snapshot_manager.compute_memory_usage_by_canister(canister_id),
+            expected_size
+        );
+
+        // Updating a snapshot does not record a snapshot operation.
+        assert_eq!(snapshot_manager.take_unflushed_changes().len(), 1);
+        let unknown_snapshot_id = SnapshotId::from((canister_id, 2));
+        assert_eq!(snapshot_manager.update(unknown_snapshot_id, |_| ()), None);
+    }
 }
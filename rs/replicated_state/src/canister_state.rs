@@ -361,8 +361,8 @@ impl CanisterState {
     /// The amount of memory currently being used by the canister.
     ///
     /// This includes execution memory (heap, stable, globals, Wasm),
-    /// canister history memory, wasm chunk storage and snapshots that
-    /// belong to this canister.
+    /// canister history memory, wasm chunk storage, environment variables
+    /// and snapshots that belong to this canister.
     ///
     /// This amount is used to periodically charge the canister for the memory
     /// resources it consumes and can be used to calculate the canister's
@@ -371,6 +371,7 @@ impl CanisterState {
         self.execution_memory_usage()
             + self.canister_history_memory_usage()
             + self.wasm_chunk_store_memory_usage()
+            + self.environment_variables_memory_usage()
             + self.snapshots_memory_usage()
     }
 
@@ -437,6 +438,11 @@ impl CanisterState {
         self.system_state.wasm_chunk_store.memory_usage()
     }
 
+    /// Returns the memory usage of the environment variables in bytes.
+    pub fn environment_variables_memory_usage(&self) -> NumBytes {
+        self.system_state.environment_variables.memory_usage()
+    }
+
     pub fn snapshots_memory_usage(&self) -> NumBytes {
         self.system_state.snapshots_memory_usage
     }
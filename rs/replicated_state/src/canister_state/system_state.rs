@@ -35,8 +35,8 @@ use ic_types::methods::Callback;
 use ic_types::nominal_cycles::NominalCycles;
 use ic_types::time::CoarseTime;
 use ic_types::{
-    CanisterId, CanisterLog, CanisterTimer, Cycles, MemoryAllocation, NumBytes, NumInstructions,
-    PrincipalId, Time,
+    CanisterId, CanisterLog, CanisterTimer, Cycles, EnvironmentVariables, MemoryAllocation,
+    NumBytes, NumInstructions, PrincipalId, Time,
 };
 use ic_validate_eq::ValidateEq;
 use ic_validate_eq_derive::ValidateEq;
@@ -389,6 +389,10 @@ pub struct SystemState {
     /// See the interface specification for more information.
     pub wasm_memory_limit: Option<NumBytes>,
 
+    /// Environment variables of the canister, set by the controllers through
+    /// the canister settings.
+    pub environment_variables: EnvironmentVariables,
+
     /// Next local snapshot id.
     pub next_snapshot_id: u64,
 
@@ -778,6 +782,7 @@ impl SystemState {
             log_visibility: Default::default(),
             canister_log: Default::default(),
             wasm_memory_limit: None,
+            environment_variables: Default::default(),
             next_snapshot_id: 0,
             snapshots_memory_usage: NumBytes::new(0),
         }
@@ -807,6 +812,7 @@ impl SystemState {
         log_visibility: LogVisibilityV2,
         canister_log: CanisterLog,
         wasm_memory_limit: Option<NumBytes>,
+        environment_variables: EnvironmentVariables,
         next_snapshot_id: u64,
         snapshots_memory_usage: NumBytes,
         metrics: &dyn CheckpointLoadingMetrics,
@@ -836,6 +842,7 @@ impl SystemState {
             log_visibility,
             canister_log,
             wasm_memory_limit,
+            environment_variables,
             next_snapshot_id,
             snapshots_memory_usage,
         };
@@ -2316,6 +2323,7 @@ pub mod testing {
             log_visibility: Default::default(),
             canister_log: Default::default(),
             wasm_memory_limit: Default::default(),
+            environment_variables: Default::default(),
             next_snapshot_id: Default::default(),
             snapshots_memory_usage: Default::default(),
         };
@@ -17,8 +17,9 @@ pub use page_allocator::{
     PageDeltaSerialization, PageSerialization,
 };
 pub use storage::{
-    BaseFileSerialization, MergeCandidate, OverlayFileSerialization, Shard, StorageLayout,
-    StorageResult, StorageSerialization, MAX_NUMBER_OF_FILES,
+    logical_file_size, BaseFileSerialization, CompressionCandidate, LogicalFile, MergeCandidate,
+    OverlayFileSerialization, Shard, StorageLayout, StorageResult, StorageSerialization,
+    MAX_NUMBER_OF_FILES,
 };
 use storage::{OverlayFile, OverlayVersion, Storage};
 
@@ -39,12 +40,13 @@ const LABEL_OP: &str = "op";
 const LABEL_TYPE: &str = "type";
 const LABEL_OP_FLUSH: &str = "flush";
 const LABEL_OP_MERGE: &str = "merge";
+const LABEL_OP_COMPRESS: &str = "compress";
 const LABEL_TYPE_PAGE_DATA: &str = "data";
 const LABEL_TYPE_INDEX: &str = "index";
 
 #[derive(Clone)]
 pub struct StorageMetrics {
-    /// How many bytes are written as part of storage operations, broken down by data vs index and merge vs flush.
+    /// How many bytes are written as part of storage operations, broken down by data vs index and merge vs flush vs compress.
     write_bytes: IntCounterVec,
     /// Timings of how long it takes to write overlay files.
     write_duration: HistogramVec,
@@ -56,17 +58,21 @@ pub struct StorageMetrics {
     num_files_by_shard: Histogram,
     /// The storage overhead of a shard before merging.
     storage_overhead_by_shard: Histogram,
+    /// Number of overlay files compressed.
+    num_compressed_files: IntCounter,
+    /// Number of bytes saved on disk by compressing overlay files.
+    compression_saved_bytes: IntCounter,
 }
 
 impl StorageMetrics {
     pub fn new(metrics_registry: &MetricsRegistry) -> Self {
         let write_bytes = metrics_registry.int_counter_vec(
             "storage_layer_write_bytes",
-            "Number of bytes written to disk, broken down by data vs index and merge vs flush.",
+            "Number of bytes written to disk, broken down by data vs index and merge vs flush vs compress.",
             &[LABEL_OP, LABEL_TYPE],
         );
 
-        for op in &[LABEL_OP_FLUSH, LABEL_OP_MERGE] {
+        for op in &[LABEL_OP_FLUSH, LABEL_OP_MERGE, LABEL_OP_COMPRESS] {
             for tp in &[LABEL_TYPE_PAGE_DATA, LABEL_TYPE_INDEX] {
                 write_bytes.with_label_values(&[*op, *tp]);
             }
@@ -74,13 +80,13 @@ impl StorageMetrics {
 
         let write_duration = metrics_registry.histogram_vec(
             "storage_layer_write_duration_seconds",
-            "Duration of write operation ('flush', 'merge').",
+            "Duration of write operation ('flush', 'merge', 'compress').",
             // 100µs, 200µs, 500µs, 1ms, 2ms, 5ms, 10ms, 20ms, 50ms, …, 100s, 200s, 500s
             decimal_buckets(-4, 2),
             &[LABEL_OP],
         );
 
-        for tp in &[LABEL_OP_FLUSH, LABEL_OP_MERGE] {
+        for tp in &[LABEL_OP_FLUSH, LABEL_OP_MERGE, LABEL_OP_COMPRESS] {
             write_duration.with_label_values(&[*tp]);
         }
 
@@ -111,6 +117,16 @@ impl StorageMetrics {
             ],
         );
 
+        let num_compressed_files = metrics_registry.int_counter(
+            "storage_layer_num_compressed_files",
+            "Number of overlay files of cold shards compressed on disk.",
+        );
+
+        let compression_saved_bytes = metrics_registry.int_counter(
+            "storage_layer_compression_saved_bytes",
+            "Number of bytes saved on disk by compressing overlay files of cold shards.",
+        );
+
         Self {
             write_bytes,
             write_duration,
@@ -118,6 +134,8 @@ impl StorageMetrics {
             num_merged_files,
             num_files_by_shard,
             storage_overhead_by_shard,
+            num_compressed_files,
+            compression_saved_bytes,
         }
     }
 }
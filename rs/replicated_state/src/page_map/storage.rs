@@ -2,10 +2,13 @@
 //! represented on disk, without any parts of a PageMap which are purely represented in memory.
 
 use std::{
+    borrow::Cow,
     collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
     fs::{File, OpenOptions},
     io::{Read, Seek, SeekFrom, Write},
     ops::{Deref, DerefMut, Range},
+    os::unix::fs::FileExt,
     path::{Path, PathBuf},
     sync::{Arc, Mutex, OnceLock},
 };
@@ -13,13 +16,13 @@ use std::{
 use crate::page_map::{
     checkpoint::{Checkpoint, Mapping, ZEROED_PAGE},
     CheckpointSerialization, MappingSerialization, MemoryInstruction, MemoryInstructions,
-    MemoryMapOrData, PageDelta, PersistenceError, StorageMetrics, LABEL_OP_FLUSH, LABEL_OP_MERGE,
-    LABEL_TYPE_INDEX, LABEL_TYPE_PAGE_DATA,
+    MemoryMapOrData, PageDelta, PersistenceError, StorageMetrics, LABEL_OP_COMPRESS,
+    LABEL_OP_FLUSH, LABEL_OP_MERGE, LABEL_TYPE_INDEX, LABEL_TYPE_PAGE_DATA,
 };
 
 use bit_vec::BitVec;
 use ic_config::state_manager::LsmtConfig;
-use ic_sys::{PageBytes, PageIndex, PAGE_SIZE};
+use ic_sys::{mmap::ScopedMmap, PageBytes, PageIndex, PAGE_SIZE};
 use ic_types::Height;
 use itertools::{izip, Itertools};
 use phantom_newtype::{AmountOf, Id};
@@ -40,11 +43,29 @@ const MAX_COPY_MEMORY_INSTRUCTION: u64 = 10;
 const CURRENT_OVERLAY_VERSION: OverlayVersion = OverlayVersion::V0;
 
 /// The maximum supported overlay version for reading.
-const MAX_SUPPORTED_OVERLAY_VERSION: OverlayVersion = OverlayVersion::V0;
+const MAX_SUPPORTED_OVERLAY_VERSION: OverlayVersion = OverlayVersion::V1;
 
 /// Buffer size, in bytes, for writing data to disk.
 const BUF_SIZE: usize = 16 * 1024 * 1024;
 
+/// Number of pages compressed into a single zstd frame of a compressed overlay file.
+/// A frame covers 1 MiB of page data, the same as a manifest chunk, so that reading a single
+/// chunk of a compressed overlay decompresses at most two frames.
+/// DO NOT CHANGE, as it would break reading compressed overlays written before.
+const COMPRESSED_FRAME_NUM_PAGES: usize = 256;
+
+/// Number of bytes of page data in a full frame of a compressed overlay file.
+const COMPRESSED_FRAME_NUM_BYTES: usize = COMPRESSED_FRAME_NUM_PAGES * PAGE_SIZE;
+
+/// The zstd compression level for compressed overlay files.
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// The file extension of overlay files, see `StateLayout`.
+const OVERLAY_EXTENSION: &str = "overlay";
+
+/// The file extension of a compressed overlay while it is being written.
+const COMPRESSION_TMP_EXTENSION: &str = "compress_tmp";
+
 #[derive(
     Copy,
     Clone,
@@ -84,6 +105,26 @@ pub enum OverlayVersion {
     /// Note that the version, size and index are at the end, so that data pages are aligned with the page
     /// size, which is required to mmap them.
     V0 = 0,
+    /// A compressed overlay file, used for shards that have not been written to for a while. Its
+    /// logical content is the `V0` overlay file containing the same pages, and all readers (`PageMap`,
+    /// manifest computation and state sync) only ever observe the logical content.
+    ///
+    /// The file consists of 6 sections (from back to front):
+    /// 1. Version: A single 32 bit little-endian unsigned integer containg the OverlayVersion.
+    /// 2. Size: A 64 bit little-endian unsigned integer containing the number of pages in the overlay
+    ///          file.
+    /// 3. Number of ranges: A 64 bit little-endian unsigned integer containing the number of
+    ///                      ranges in the index.
+    /// 4. Index: Same as for `V0`.
+    /// 5. Frame table: For each frame, the offset past its last byte in the file as a 64 bit
+    ///                 little-endian unsigned integer.
+    /// 6. Frames: The data of the pages, split into runs of `COMPRESSED_FRAME_NUM_PAGES` pages
+    ///            (the last run may be shorter), each compressed as a separate zstd frame.
+    ///
+    /// Example: An overlay containing pages 5,6, and 10
+    ///          [zstd(Data5 Data6 Data10)]   [end0]        [[5,7,0][10,11,2]]         [2]               [3]                 [1]
+    ///               Frames               Frame table    Index (2*3*8 bytes)   Ranges (8 bytes)   Size (8 bytes)    Version (4 bytes)
+    V1 = 1,
 }
 
 /// Number of bytes to store the OverlayVersion.
@@ -95,6 +136,12 @@ const SIZE_NUM_BYTES: usize = 8;
 /// Number of bytes storing a range in an overlay file.
 const PAGE_INDEX_RANGE_NUM_BYTES: usize = 24;
 
+/// Number of bytes storing the number of ranges in a compressed overlay file.
+const NUM_RANGES_NUM_BYTES: usize = 8;
+
+/// Number of bytes storing the end of a frame in a compressed overlay file.
+const FRAME_END_NUM_BYTES: usize = 8;
+
 impl std::convert::TryFrom<u32> for OverlayVersion {
     type Error = ();
 
@@ -162,8 +209,65 @@ pub(crate) struct StorageImpl {
 }
 
 /// Validate that the overlay files are loadable.
+///
+/// Compressed overlays are validated without decompressing their data, see
+/// `CompressedOverlayFile`.
 pub fn validate(storage_layout: &dyn StorageLayout) -> Result<(), PersistenceError> {
-    StorageImpl::load(storage_layout)?;
+    let overlay_paths = existing_overlays(storage_layout)?;
+    let mut range_by_shard = BTreeMap::<Shard, Range<PageIndex>>::new();
+    for path in overlay_paths.iter() {
+        add_to_range_by_shard(
+            &mut range_by_shard,
+            storage_layout.overlay_shard(path).unwrap(),
+            OverlayFile::load(path)?.page_range(),
+        );
+    }
+    check_sharding(&range_by_shard, &overlay_paths)?;
+    if storage_layout.base().exists() {
+        Checkpoint::open(&storage_layout.base())?;
+    }
+    Ok(())
+}
+
+/// All existing overlays of `storage_layout`.
+fn existing_overlays(storage_layout: &dyn StorageLayout) -> Result<Vec<PathBuf>, PersistenceError> {
+    storage_layout
+        .existing_overlays()
+        .map_err(|err| PersistenceError::FileSystemError {
+            path: "".to_string(),
+            context: "Failed to get overlays".to_string(),
+            internal_error: err.to_string(),
+        })
+}
+
+/// Extends the range of pages covered by `shard` in `range_by_shard` by `page_range`.
+fn add_to_range_by_shard(
+    range_by_shard: &mut BTreeMap<Shard, Range<PageIndex>>,
+    shard: Shard,
+    page_range: Range<PageIndex>,
+) {
+    range_by_shard
+        .entry(shard)
+        .and_modify(|ref mut range| {
+            range.start = std::cmp::min(range.start, page_range.start);
+            range.end = std::cmp::max(range.end, page_range.end);
+        })
+        .or_insert(page_range);
+}
+
+/// Checks that the ranges of pages covered by different shards don't overlap.
+fn check_sharding(
+    range_by_shard: &BTreeMap<Shard, Range<PageIndex>>,
+    overlay_paths: &[PathBuf],
+) -> Result<(), PersistenceError> {
+    for prev_next in range_by_shard.values().collect::<Vec<_>>().windows(2) {
+        if prev_next[0].end > prev_next[1].start {
+            return Err(PersistenceError::InvalidOverlay {
+                path: overlay_paths[0].display().to_string(),
+                message: "Overlapping sharding".to_string(),
+            });
+        }
+    }
     Ok(())
 }
 
@@ -254,33 +358,15 @@ impl StorageImpl {
         } else {
             None
         };
-        let overlay_paths = storage_layout.existing_overlays().map_err(|err| {
-            PersistenceError::FileSystemError {
-                path: "".to_string(),
-                context: "Failed to get overlays".to_string(),
-                internal_error: err.to_string(),
-            }
-        })?;
+        let overlay_paths = existing_overlays(storage_layout)?;
         let mut shards_with_overlays = BTreeSet::<Shard>::new();
         let mut range_by_shard = BTreeMap::<Shard, Range<PageIndex>>::new();
         let mut base_overlays = Vec::<OverlayFile>::new();
         let mut overlays = Vec::<OverlayFile>::new();
         for path in overlay_paths.iter() {
             let overlay = OverlayFile::load(path)?;
-            let start_page_index = overlay
-                .index_iter()
-                .next()
-                .expect("Verified overlay cannot be empty")
-                .start_page;
-            let last_page_index = PageIndex::new(overlay.end_logical_pages() as u64);
             let shard = storage_layout.overlay_shard(path).unwrap();
-            range_by_shard
-                .entry(shard)
-                .and_modify(|ref mut range| {
-                    range.start = std::cmp::min(range.start, start_page_index);
-                    range.end = std::cmp::max(range.end, last_page_index);
-                })
-                .or_insert(start_page_index..last_page_index);
+            add_to_range_by_shard(&mut range_by_shard, shard, overlay.page_range());
             // For each shard the lowest height version is a base, if it can be loaded fast.
             // It can be mmapped fast if it contains a single range, hence one mmap.
             if base_path.is_none()
@@ -293,14 +379,7 @@ impl StorageImpl {
             }
             shards_with_overlays.insert(shard);
         }
-        for prev_next in range_by_shard.values().collect::<Vec<_>>().windows(2) {
-            if prev_next[0].end > prev_next[1].start {
-                return Err(PersistenceError::InvalidOverlay {
-                    path: overlay_paths[0].display().to_string(),
-                    message: "Overlapping sharding".to_string(),
-                });
-            }
-        }
+        check_sharding(&range_by_shard, &overlay_paths)?;
 
         let base = if let Some(base) = base_path.as_deref().map(Checkpoint::open).transpose()? {
             assert!(base_overlays.is_empty());
@@ -412,9 +491,12 @@ impl StorageImpl {
 /// A single overlay file describing a not necessarily exhaustive set of pages.
 #[derive(Clone)]
 pub(crate) struct OverlayFile {
-    /// A memory map of the entire file.
+    /// A memory map of the entire (logical) file.
     /// Invariant: `mapping` satisfies `check_correctness(&mapping)`.
-    mapping: Arc<Mapping>,
+    /// For a compressed overlay, it is only initialized when the page data is first accessed.
+    mapping: Arc<OnceLock<Mapping>>,
+    /// The compressed file this overlay was loaded from, if any.
+    compressed: Option<Arc<CompressedOverlayFile>>,
 }
 
 impl OverlayFile {
@@ -435,7 +517,7 @@ impl OverlayFile {
                 },
             )
             .map(|(index, offset)| {
-                let page = get_page_in_mapping(&self.mapping, offset);
+                let page = get_page_in_mapping(self.mapping(), offset);
                 // In a validated mapping, all file_indices from the index are within range.
                 assert!(page.is_some());
                 (index, page.unwrap().as_slice())
@@ -446,7 +528,7 @@ impl OverlayFile {
     /// Returns `None` for pages not contained in this overlay.
     fn get_page(&self, page_index: PageIndex) -> Option<&PageBytes> {
         let position = self.get_file_index(page_index)?;
-        get_page_in_mapping(&self.mapping, position)
+        get_page_in_mapping(self.mapping(), position)
     }
 
     /// Write a new overlay to the destination specified by `storage_layout` containing
@@ -514,17 +596,44 @@ impl OverlayFile {
             },
         )?;
 
+        if mapping.as_slice().len() >= VERSION_NUM_BYTES
+            && try_version(&mapping) == Ok(OverlayVersion::V1)
+        {
+            let compressed = CompressedOverlay::parse(mapping.as_slice(), path)?;
+            return Ok(Self {
+                mapping: Default::default(),
+                compressed: Some(Arc::new(CompressedOverlayFile {
+                    path: path.to_path_buf(),
+                    mapping,
+                    overlay: compressed,
+                })),
+            });
+        }
+
         check_mapping_correctness(&mapping, path)?;
 
         Ok(Self {
-            mapping: Arc::new(mapping),
+            mapping: Arc::new(OnceLock::from(mapping)),
+            compressed: None,
+        })
+    }
+
+    /// The memory map of the entire (logical) file.
+    /// A compressed overlay is decompressed at the first call, see `CompressedOverlayFile`.
+    fn mapping(&self) -> &Mapping {
+        self.mapping.get_or_init(|| {
+            self.compressed
+                .as_ref()
+                .expect("Overlay file without mapping must be compressed")
+                .decompress()
+                .expect("Failed to decompress overlay")
         })
     }
 
     /// Serialize the loaded overlay file for communication with sandboxes.
     pub fn serialize(&self) -> OverlayFileSerialization {
         OverlayFileSerialization {
-            mapping: self.mapping.serialize(),
+            mapping: self.mapping().serialize(),
         }
     }
 
@@ -542,19 +651,26 @@ impl OverlayFile {
         )?;
 
         Ok(Self {
-            mapping: Arc::new(mapping),
+            mapping: Arc::new(OnceLock::from(mapping)),
+            compressed: None,
         })
     }
 
     /// Number of pages in this overlay file containing data.
-    #[allow(dead_code)]
     fn num_pages(&self) -> usize {
-        num_pages(&self.mapping)
+        match &self.compressed {
+            Some(compressed) => compressed.overlay.num_pages,
+            None => num_pages(self.mapping()),
+        }
     }
 
     /// The index as a slice.
+    /// For compressed overlays, it is read without decompressing the page data.
     fn index_slice(&self) -> &[[[u8; 8]; 3]] {
-        index_slice(&self.mapping)
+        match &self.compressed {
+            Some(compressed) => compressed.overlay.index_slice(),
+            None => index_slice(self.mapping()),
+        }
     }
 
     /// The number of logical pages covered by this overlay file, i.e. the largest `PageIndex`
@@ -565,6 +681,11 @@ impl OverlayFile {
             .get() as usize
     }
 
+    /// The range from the smallest to the largest `PageIndex` contained in this overlay file.
+    fn page_range(&self) -> Range<PageIndex> {
+        index_page_range(self.index_slice())
+    }
+
     /// For base overlays we mmap all content in constructor.
     fn get_base_memory_instructions(&self) -> MemoryInstructions {
         assert_eq!(self.index_iter().count(), 1);
@@ -574,7 +695,7 @@ impl OverlayFile {
             instructions: vec![(
                 page_index_range.start_page..page_index_range.end_page,
                 MemoryMapOrData::MemoryMap(
-                    self.mapping.file_descriptor().clone(),
+                    self.mapping().file_descriptor().clone(),
                     page_index_range.start_file_index.get() as usize,
                 ),
             )],
@@ -657,7 +778,7 @@ impl OverlayFile {
                 let offset = page_index_range.start_file_index.get() as usize * PAGE_SIZE;
                 result.push((
                     page_index_range.start_page..page_index_range.end_page,
-                    MemoryMapOrData::MemoryMap(self.mapping.file_descriptor().clone(), offset),
+                    MemoryMapOrData::MemoryMap(self.mapping().file_descriptor().clone(), offset),
                 ));
             } else if needed_pages > 0 {
                 // We copy the needed pages individually.
@@ -669,7 +790,7 @@ impl OverlayFile {
                     {
                         continue;
                     }
-                    let page = get_page_in_mapping(&self.mapping, file_index);
+                    let page = get_page_in_mapping(self.mapping(), file_index);
                     // In a valid overlay file the file index is within range.
                     debug_assert!(page.is_some());
                     result.push((
@@ -690,7 +811,10 @@ impl OverlayFile {
     /// The overlay version contained in the file.
     #[allow(dead_code)]
     fn version(&self) -> OverlayVersion {
-        let result = try_version(&self.mapping);
+        if self.compressed.is_some() {
+            return OverlayVersion::V1;
+        }
+        let result = try_version(self.mapping());
 
         // We verify that this unwrap succeeds while loading the overlay.
         debug_assert!(result.is_ok());
@@ -839,7 +963,16 @@ fn check_mapping_correctness(mapping: &Mapping, path: &Path) -> Result<(), Persi
         }
     };
 
-    let slice = index_slice(mapping);
+    check_index_correctness(index_slice(mapping), num_pages(mapping), path)
+}
+
+/// Check that the `index` of an overlay containing `num_pages` pages of data is valid, see
+/// `check_mapping_correctness`.
+fn check_index_correctness(
+    slice: &[[[u8; 8]; 3]],
+    num_pages: usize,
+    path: &Path,
+) -> Result<(), PersistenceError> {
     // The first range should start at file_index 0
     if !slice.is_empty() {
         let entry = PageIndexRange::from(&slice[0]);
@@ -855,7 +988,7 @@ fn check_mapping_correctness(mapping: &Mapping, path: &Path) -> Result<(), Persi
     }
     for i in 0..slice.len() {
         let next_file_index = if i == slice.len() - 1 {
-            FileIndex::from(num_pages(mapping) as u64)
+            FileIndex::from(num_pages as u64)
         } else {
             PageIndexRange::from(&slice[i + 1]).start_file_index
         };
@@ -889,11 +1022,7 @@ fn check_mapping_correctness(mapping: &Mapping, path: &Path) -> Result<(), Persi
                 message: format!(
                     "Broken overlay file: PageIndexRange[{}], entry: {:?}, next_file_index: {}, \
                          next_page_index: {:?}, num_pages: {}",
-                    i,
-                    entry,
-                    next_file_index,
-                    next_page_index,
-                    num_pages(mapping)
+                    i, entry, next_file_index, next_page_index, num_pages
                 ),
             });
         }
@@ -901,6 +1030,499 @@ fn check_mapping_correctness(mapping: &Mapping, path: &Path) -> Result<(), Persi
     Ok(())
 }
 
+/// The range from the smallest to the largest `PageIndex` contained in a (valid, non-empty) index.
+fn index_page_range(index: &[[[u8; 8]; 3]]) -> Range<PageIndex> {
+    let first = PageIndexRange::from(index.first().expect("Verified overlay cannot be empty"));
+    let last = PageIndexRange::from(index.last().expect("Verified overlay cannot be empty"));
+    first.start_page..last.end_page
+}
+
+/// Reads a little-endian `u64` from `slice` ending at offset `end`.
+fn read_u64_ending_at(slice: &[u8], end: usize) -> u64 {
+    u64::from_le_bytes(slice[(end - 8)..end].try_into().unwrap())
+}
+
+/// Whether the `path` has the extension of an overlay file.
+fn has_overlay_extension(path: &Path) -> bool {
+    path.extension() == Some(OsStr::new(OVERLAY_EXTENSION))
+}
+
+/// The parsed and validated frame table and index of a compressed (`OverlayVersion::V1`)
+/// overlay file. The frames themselves are only decompressed on demand.
+struct CompressedOverlay {
+    /// Number of pages in the overlay file.
+    num_pages: usize,
+    /// For each frame, the offset past its last byte in the file.
+    frame_ends: Vec<usize>,
+    /// The part of the logical (`OverlayVersion::V0`) file following the page data, i.e. the
+    /// index, size and version.
+    logical_tail: Vec<u8>,
+}
+
+impl CompressedOverlay {
+    /// Parse the compressed overlay file with content `slice`.
+    /// Returns an error if the frame table or the index are malformed.
+    fn parse(slice: &[u8], path: &Path) -> Result<Self, PersistenceError> {
+        let invalid = |message: &str| PersistenceError::InvalidOverlay {
+            path: path.display().to_string(),
+            message: message.to_string(),
+        };
+        let footer_num_bytes = VERSION_NUM_BYTES + SIZE_NUM_BYTES + NUM_RANGES_NUM_BYTES;
+        if slice.len() < footer_num_bytes {
+            return Err(invalid("No footer provided in compressed overlay file"));
+        }
+        let num_pages = read_u64_ending_at(slice, slice.len() - VERSION_NUM_BYTES) as usize;
+        let num_ranges =
+            read_u64_ending_at(slice, slice.len() - VERSION_NUM_BYTES - SIZE_NUM_BYTES) as usize;
+        if num_ranges == 0 {
+            return Err(invalid("No index provided in compressed overlay file"));
+        }
+        let index_start = num_ranges
+            .checked_mul(PAGE_INDEX_RANGE_NUM_BYTES)
+            .and_then(|index_num_bytes| {
+                (slice.len() - footer_num_bytes).checked_sub(index_num_bytes)
+            })
+            .ok_or_else(|| invalid("Invalid index length"))?;
+        let num_frames = num_pages.div_ceil(COMPRESSED_FRAME_NUM_PAGES);
+        let frame_table_start = num_frames
+            .checked_mul(FRAME_END_NUM_BYTES)
+            .and_then(|frame_table_num_bytes| index_start.checked_sub(frame_table_num_bytes))
+            .ok_or_else(|| invalid("Invalid frame table length"))?;
+
+        let frame_ends: Vec<usize> = slice[frame_table_start..index_start]
+            .chunks_exact(FRAME_END_NUM_BYTES)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()) as usize)
+            .collect();
+        let mut frame_start = 0;
+        for frame_end in frame_ends.iter() {
+            if *frame_end <= frame_start {
+                return Err(invalid("Frames in compressed overlay file are not ordered"));
+            }
+            frame_start = *frame_end;
+        }
+        if frame_start != frame_table_start {
+            return Err(invalid(
+                "Frames in compressed overlay file don't end at the frame table",
+            ));
+        }
+
+        let index = &slice[index_start..(slice.len() - footer_num_bytes)];
+        let mut logical_tail = Vec::with_capacity(index.len() + SIZE_NUM_BYTES + VERSION_NUM_BYTES);
+        logical_tail.extend_from_slice(index);
+        logical_tail.extend_from_slice(&(num_pages as u64).to_le_bytes());
+        logical_tail.extend_from_slice(&(OverlayVersion::V0 as u32).to_le_bytes());
+
+        let result = Self {
+            num_pages,
+            frame_ends,
+            logical_tail,
+        };
+        check_index_correctness(result.index_slice(), num_pages, path)?;
+        Ok(result)
+    }
+
+    /// The index as a slice, see `index_slice`.
+    fn index_slice(&self) -> &[[[u8; 8]; 3]] {
+        let index =
+            &self.logical_tail[..(self.logical_tail.len() - VERSION_NUM_BYTES - SIZE_NUM_BYTES)];
+        // Safety: `[[u8; 8]; 3]` has no alignment requirement and the index length is a multiple
+        // of its size by construction.
+        let (prefix, slice, suffix) = unsafe { index.align_to::<[[u8; 8]; 3]>() };
+        assert!(prefix.is_empty());
+        assert!(suffix.is_empty());
+        slice
+    }
+
+    /// Number of bytes of page data in the logical file.
+    fn data_len(&self) -> usize {
+        self.num_pages * PAGE_SIZE
+    }
+
+    /// Length of the logical file.
+    fn logical_len(&self) -> usize {
+        self.data_len() + self.logical_tail.len()
+    }
+
+    /// Decompress the frame with index `frame` of the compressed overlay file with content `slice`.
+    fn decompress_frame(&self, slice: &[u8], frame: usize) -> std::io::Result<Vec<u8>> {
+        let start = if frame == 0 {
+            0
+        } else {
+            self.frame_ends[frame - 1]
+        };
+        let expected_len =
+            (self.data_len() - frame * COMPRESSED_FRAME_NUM_BYTES).min(COMPRESSED_FRAME_NUM_BYTES);
+        let data = zstd::bulk::decompress(&slice[start..self.frame_ends[frame]], expected_len)?;
+        if data.len() != expected_len {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Frame {} decompressed to {} bytes, expected {}",
+                    frame,
+                    data.len(),
+                    expected_len
+                ),
+            ));
+        }
+        Ok(data)
+    }
+
+    /// Write the logical file of the compressed overlay file with content `slice` to `file`.
+    fn write_logical(&self, slice: &[u8], file: &mut File) -> std::io::Result<()> {
+        for frame in 0..self.frame_ends.len() {
+            file.write_all(&self.decompress_frame(slice, frame)?)?;
+        }
+        file.write_all(&self.logical_tail)
+    }
+
+    /// Read the bytes in `range` of the logical file of the compressed overlay file with content
+    /// `slice`. Only the frames overlapping with `range` are decompressed.
+    fn read_logical(&self, slice: &[u8], range: Range<usize>) -> std::io::Result<Vec<u8>> {
+        let mut result = Vec::with_capacity(range.len());
+        if range.start < self.data_len() {
+            let first_frame = range.start / COMPRESSED_FRAME_NUM_BYTES;
+            let last_frame = (range.end.min(self.data_len()) - 1) / COMPRESSED_FRAME_NUM_BYTES;
+            for frame in first_frame..=last_frame {
+                let frame_start = frame * COMPRESSED_FRAME_NUM_BYTES;
+                let data = self.decompress_frame(slice, frame)?;
+                let from = range.start.max(frame_start) - frame_start;
+                let to = range.end.min(frame_start + data.len()) - frame_start;
+                result.extend_from_slice(&data[from..to]);
+            }
+        }
+        if range.end > self.data_len() {
+            let from = range.start.max(self.data_len()) - self.data_len();
+            result.extend_from_slice(&self.logical_tail[from..(range.end - self.data_len())]);
+        }
+        Ok(result)
+    }
+}
+
+/// A compressed overlay file loaded by `OverlayFile::load`.
+///
+/// Loading only parses its index, so that loading a `PageMap` with cold shards stays cheap when
+/// their pages are never accessed. The pages are decompressed at the first access into an unnamed
+/// file on disk laid out as an `OverlayVersion::V0` overlay, which is then mapped like any
+/// uncompressed overlay. This way compression is transparent to `PageMap`, including the sandbox
+/// that maps the file by its descriptor, and a cold shard does not pin its data in memory.
+struct CompressedOverlayFile {
+    /// Path of the compressed overlay file.
+    path: PathBuf,
+    /// A memory map of the compressed overlay file.
+    mapping: Mapping,
+    /// The parsed frame table and index of the compressed overlay file.
+    overlay: CompressedOverlay,
+}
+
+impl CompressedOverlayFile {
+    /// Decompress the overlay into an unnamed file and map it.
+    fn decompress(&self) -> Result<Mapping, PersistenceError> {
+        let path = self.path.as_path();
+        let mut file =
+            create_decompressed_file(path).map_err(|err| PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to create file for decompressed overlay".to_string(),
+                internal_error: err.to_string(),
+            })?;
+        self.overlay
+            .write_logical(self.mapping.as_slice(), &mut file)
+            .map_err(|err| PersistenceError::FileSystemError {
+                path: path.display().to_string(),
+                context: "Failed to decompress overlay".to_string(),
+                internal_error: err.to_string(),
+            })?;
+        let mapping = Mapping::new(file, self.overlay.logical_len(), Some(path))?.ok_or(
+            PersistenceError::InvalidOverlay {
+                path: path.display().to_string(),
+                message: "Empty mapping for decompressed overlay; zero num_pages?".to_string(),
+            },
+        )?;
+
+        check_mapping_correctness(&mapping, path)?;
+
+        Ok(mapping)
+    }
+}
+
+/// Read-only access to the logical content of a checkpoint file.
+///
+/// The logical content of a compressed overlay file is the uncompressed overlay file containing
+/// the same pages; any other file is its own logical content. The manifest and state sync operate
+/// on logical content, so compressing an overlay changes neither the state hash nor the chunks
+/// served to other replicas.
+pub struct LogicalFile {
+    mmap: ScopedMmap,
+    compressed: Option<CompressedOverlay>,
+}
+
+impl LogicalFile {
+    /// Open the file at `path`.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let mmap = ScopedMmap::from_path(path)?;
+        let slice = mmap.as_slice();
+        let compressed = if has_overlay_extension(path)
+            && slice.len() >= VERSION_NUM_BYTES
+            && slice[(slice.len() - VERSION_NUM_BYTES)..]
+                == (OverlayVersion::V1 as u32).to_le_bytes()
+        {
+            Some(CompressedOverlay::parse(slice, path).map_err(|err| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+            })?)
+        } else {
+            None
+        };
+        Ok(Self { mmap, compressed })
+    }
+
+    /// Length of the logical content in bytes.
+    pub fn len(&self) -> usize {
+        match &self.compressed {
+            None => self.mmap.len(),
+            Some(compressed) => compressed.logical_len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether the file is a compressed overlay, i.e. its logical content differs from the bytes
+    /// on disk.
+    pub fn is_compressed(&self) -> bool {
+        self.compressed.is_some()
+    }
+
+    /// Read the bytes in `range` of the logical content.
+    pub fn read(&self, range: Range<usize>) -> std::io::Result<Cow<'_, [u8]>> {
+        if range.start > range.end || range.end > self.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Range {:?} is out of bounds of file with length {}",
+                    range,
+                    self.len()
+                ),
+            ));
+        }
+        match &self.compressed {
+            None => Ok(Cow::Borrowed(&self.mmap.as_slice()[range])),
+            Some(_) if range.is_empty() => Ok(Cow::Borrowed(&[])),
+            Some(compressed) => Ok(Cow::Owned(
+                compressed.read_logical(self.mmap.as_slice(), range)?,
+            )),
+        }
+    }
+}
+
+/// Length of the logical content of the file at `path`, see `LogicalFile`.
+/// For compressed overlays only the footer is read.
+pub fn logical_file_size(path: &Path) -> std::io::Result<u64> {
+    let len = std::fs::metadata(path)?.len();
+    if !has_overlay_extension(path) || len < VERSION_NUM_BYTES as u64 {
+        return Ok(len);
+    }
+    let file = File::open(path)?;
+    if read_raw_version(&file, len)? != OverlayVersion::V1 as u32 {
+        return Ok(len);
+    }
+    let mut footer = [0u8; NUM_RANGES_NUM_BYTES + SIZE_NUM_BYTES + VERSION_NUM_BYTES];
+    let footer_offset = len.checked_sub(footer.len() as u64).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "No footer provided in compressed overlay file {}",
+                path.display()
+            ),
+        )
+    })?;
+    file.read_exact_at(&mut footer, footer_offset)?;
+    let num_ranges = read_u64_ending_at(&footer, NUM_RANGES_NUM_BYTES);
+    let num_pages = read_u64_ending_at(&footer, NUM_RANGES_NUM_BYTES + SIZE_NUM_BYTES);
+    Ok(num_pages * PAGE_SIZE as u64
+        + num_ranges * PAGE_INDEX_RANGE_NUM_BYTES as u64
+        + (SIZE_NUM_BYTES + VERSION_NUM_BYTES) as u64)
+}
+
+/// Whether the file at `path` is a compressed overlay.
+fn is_compressed_overlay(path: &Path) -> std::io::Result<bool> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    Ok(has_overlay_extension(path)
+        && len >= VERSION_NUM_BYTES as u64
+        && read_raw_version(&file, len)? == OverlayVersion::V1 as u32)
+}
+
+/// Read the version number at the end of the overlay `file` of length `len`.
+fn read_raw_version(file: &File, len: u64) -> std::io::Result<u32> {
+    let mut version_buf = [0u8; VERSION_NUM_BYTES];
+    file.read_exact_at(&mut version_buf, len - VERSION_NUM_BYTES as u64)?;
+    Ok(u32::from_le_bytes(version_buf))
+}
+
+/// Create a file to hold the data of the decompressed overlay at `path`.
+/// The file is created on disk in the directory of the compressed overlay, so it does not take up
+/// memory. It has no name, so it is never listed among the files of the checkpoint, and it is
+/// removed once the last mapping of it is dropped.
+fn create_decompressed_file(path: &Path) -> std::io::Result<File> {
+    match path.parent() {
+        Some(dir) => tempfile::tempfile_in(dir),
+        None => tempfile::tempfile(),
+    }
+}
+
+/// `CompressionCandidate` is an uncompressed overlay of a shard that has not been written to for
+/// at least `lsmt_config.compress_cold_shards_after_heights` heights.
+///
+/// Applying it rewrites the overlay in the `OverlayVersion::V1` format under the same name,
+/// preserving its logical content. Unlike merging, compression therefore does not need to be
+/// deterministic across replicas.
+#[derive(Clone, Debug)]
+pub struct CompressionCandidate {
+    /// Overlay file to compress.
+    path: PathBuf,
+    /// Height at which the shard of the overlay was last written to.
+    last_written: Height,
+    /// Size of the overlay file on disk.
+    size_bytes: u64,
+}
+
+impl CompressionCandidate {
+    /// Height at which the shard of the overlay was last written to.
+    pub fn last_written(&self) -> Height {
+        self.last_written
+    }
+
+    /// Size of the overlay file, i.e. the size to read from disk and compress.
+    pub fn size_bytes(&self) -> u64 {
+        self.size_bytes
+    }
+
+    /// Create a `CompressionCandidate` for each uncompressed overlay of the shards of `layout`
+    /// that have not been written to for at least `lsmt_config.compress_cold_shards_after_heights`
+    /// heights before `height`.
+    pub fn new(
+        layout: &dyn StorageLayout,
+        height: Height,
+        lsmt_config: &LsmtConfig,
+    ) -> StorageResult<Vec<CompressionCandidate>> {
+        let Some(min_age) = lsmt_config.compress_cold_shards_after_heights else {
+            return Ok(Vec::new());
+        };
+        let mut overlays_by_shard = BTreeMap::<Shard, Vec<PathBuf>>::new();
+        for overlay in layout.existing_overlays()? {
+            overlays_by_shard
+                .entry(layout.overlay_shard(&overlay)?)
+                .or_default()
+                .push(overlay);
+        }
+        let mut result = Vec::new();
+        for overlays in overlays_by_shard.values() {
+            // Overlays are sorted by height, so the last one is the most recently written.
+            let last_written = layout.overlay_height(overlays.last().unwrap())?;
+            if height.get().saturating_sub(last_written.get()) < min_age {
+                continue;
+            }
+            for overlay in overlays {
+                let to_storage_err = |err: std::io::Error| {
+                    Box::new(PersistenceError::FileSystemError {
+                        path: overlay.display().to_string(),
+                        context: "Failed to read overlay version".to_string(),
+                        internal_error: err.to_string(),
+                    }) as Box<dyn std::error::Error + Send>
+                };
+                if is_compressed_overlay(overlay).map_err(to_storage_err)? {
+                    continue;
+                }
+                result.push(CompressionCandidate {
+                    path: overlay.clone(),
+                    last_written,
+                    size_bytes: std::fs::metadata(overlay).map_err(to_storage_err)?.len(),
+                });
+            }
+        }
+        Ok(result)
+    }
+
+    /// Compress the overlay.
+    pub fn apply(&self, metrics: &StorageMetrics) -> Result<(), PersistenceError> {
+        compress_overlay(&self.path, metrics)
+    }
+}
+
+/// Rewrite the uncompressed overlay at `path` in the compressed `OverlayVersion::V1` format.
+/// The compressed file is written next to it and then renamed over the original.
+fn compress_overlay(path: &Path, metrics: &StorageMetrics) -> Result<(), PersistenceError> {
+    let _timer = metrics
+        .write_duration
+        .with_label_values(&[LABEL_OP_COMPRESS])
+        .start_timer();
+    let overlay = OverlayFile::load(path)?;
+    let slice = overlay.mapping().as_slice();
+    let data_len = overlay.num_pages() * PAGE_SIZE;
+    let index = &slice[data_len..(slice.len() - VERSION_NUM_BYTES - SIZE_NUM_BYTES)];
+
+    let tmp_path = path.with_extension(COMPRESSION_TMP_EXTENSION);
+    let map_err = |err: std::io::Error| PersistenceError::FileSystemError {
+        path: tmp_path.display().to_string(),
+        context: format!("Failed to write compressed overlay file {}", path.display()),
+        internal_error: err.to_string(),
+    };
+    if tmp_path.exists() {
+        std::fs::remove_file(&tmp_path).map_err(map_err)?;
+    }
+    let mut file = create_file_for_write(&tmp_path)?;
+
+    let mut frame_ends = Vec::with_capacity(data_len.div_ceil(COMPRESSED_FRAME_NUM_BYTES));
+    let mut frames_len = 0;
+    let mut buf: Vec<u8> = Vec::with_capacity(BUF_SIZE);
+    for frame in slice[..data_len].chunks(COMPRESSED_FRAME_NUM_BYTES) {
+        let compressed = zstd::bulk::compress(frame, COMPRESSION_LEVEL).map_err(map_err)?;
+        if buf.len() + compressed.len() > BUF_SIZE {
+            file.write_all(&buf).map_err(map_err)?;
+            buf.clear();
+        }
+        buf.extend_from_slice(&compressed);
+        frames_len += compressed.len();
+        frame_ends.push(frames_len as u64);
+    }
+    file.write_all(&buf).map_err(map_err)?;
+    let mut footer = Vec::with_capacity(
+        frame_ends.len() * FRAME_END_NUM_BYTES
+            + index.len()
+            + NUM_RANGES_NUM_BYTES
+            + SIZE_NUM_BYTES
+            + VERSION_NUM_BYTES,
+    );
+    for frame_end in frame_ends {
+        footer.extend_from_slice(&frame_end.to_le_bytes());
+    }
+    footer.extend_from_slice(index);
+    footer.extend_from_slice(&((index.len() / PAGE_INDEX_RANGE_NUM_BYTES) as u64).to_le_bytes());
+    footer.extend_from_slice(&(overlay.num_pages() as u64).to_le_bytes());
+    footer.extend_from_slice(&(OverlayVersion::V1 as u32).to_le_bytes());
+    file.write_all(&footer).map_err(map_err)?;
+    drop(file);
+
+    let mut permissions = tmp_path.metadata().map_err(map_err)?.permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&tmp_path, permissions).map_err(map_err)?;
+    std::fs::rename(&tmp_path, path).map_err(map_err)?;
+
+    metrics
+        .write_bytes
+        .with_label_values(&[LABEL_OP_COMPRESS, LABEL_TYPE_PAGE_DATA])
+        .inc_by(frames_len as u64);
+    metrics
+        .write_bytes
+        .with_label_values(&[LABEL_OP_COMPRESS, LABEL_TYPE_INDEX])
+        .inc_by(footer.len() as u64);
+    metrics.num_compressed_files.inc();
+    metrics
+        .compression_saved_bytes
+        .inc_by(slice.len().saturating_sub(frames_len + footer.len()) as u64);
+    Ok(())
+}
+
 /// Too large files are hard to write within one checkpoint interval, so we split them into multiple
 /// shards. E.g. if we need 400 GiB stable memory, we can write it as 8x50GiB files.
 /// If a certain range has no data, we don't create the shard. E.g. if the 400GiB file shaded by
@@ -928,7 +1550,19 @@ pub trait StorageLayout {
 }
 
 impl dyn StorageLayout + '_ {
+    /// Total logical size of all files, i.e. compressed overlays count with their uncompressed
+    /// size. Merge decisions are based on logical sizes, as they must be identical on all
+    /// replicas while compression is a local optimization.
     pub fn storage_size_bytes(&self) -> StorageResult<u64> {
+        let mut result = 0;
+        for path in self.existing_files()? {
+            result += existing_file_logical_size(&path)?;
+        }
+        Ok(result)
+    }
+
+    /// Total size of all files on disk.
+    pub fn physical_storage_size_bytes(&self) -> StorageResult<u64> {
         let mut result = 0;
         for path in self.existing_files()? {
             result += std::fs::metadata(&path)
@@ -1014,7 +1648,7 @@ impl dyn StorageLayout + '_ {
         file.seek(SeekFrom::End(-(VERSION_NUM_BYTES as i64)))
             .map_err(to_storage_err)?;
         file.read_exact(&mut version_buf).map_err(to_storage_err)?;
-        static_assertions::const_assert_eq!(MAX_SUPPORTED_OVERLAY_VERSION as u32, 0);
+        static_assertions::const_assert_eq!(MAX_SUPPORTED_OVERLAY_VERSION as u32, 1);
         let version = u32::from_le_bytes(version_buf);
         if version > MAX_SUPPORTED_OVERLAY_VERSION as u32 {
             return Err(Box::new(PersistenceError::VersionMismatch {
@@ -1023,10 +1657,16 @@ impl dyn StorageLayout + '_ {
                 supported: MAX_SUPPORTED_OVERLAY_VERSION,
             }) as Box<dyn std::error::Error + Send>);
         }
+        // Compressed overlays store the number of ranges between the index and the size.
+        let footer_num_bytes = if version == OverlayVersion::V1 as u32 {
+            VERSION_NUM_BYTES + SIZE_NUM_BYTES + NUM_RANGES_NUM_BYTES
+        } else {
+            VERSION_NUM_BYTES + SIZE_NUM_BYTES
+        };
 
         let mut last_page_index_range_buf = [[0u8; 8]; 3];
         file.seek(SeekFrom::End(
-            -((VERSION_NUM_BYTES + SIZE_NUM_BYTES + PAGE_INDEX_RANGE_NUM_BYTES) as i64),
+            -((footer_num_bytes + PAGE_INDEX_RANGE_NUM_BYTES) as i64),
         ))
         .map_err(to_storage_err)?;
         file.read_exact(last_page_index_range_buf.as_flattened_mut())
@@ -1036,6 +1676,17 @@ impl dyn StorageLayout + '_ {
     }
 }
 
+/// Logical size of an existing file, see `logical_file_size`.
+fn existing_file_logical_size(path: &Path) -> StorageResult<u64> {
+    logical_file_size(path).map_err(|err: _| {
+        Box::new(PersistenceError::FileSystemError {
+            path: path.display().to_string(),
+            context: format!("Failed get existing file length: {}", path.display()),
+            internal_error: err.to_string(),
+        }) as Box<dyn std::error::Error + Send>
+    })
+}
+
 /// Whether to merge into a base file or an overlay.
 #[derive(Clone, Eq, PartialEq, Debug)]
 enum MergeDestination {
@@ -1356,20 +2007,7 @@ impl MergeCandidate {
             let existing_files = layout.existing_files_with_shard(shard)?;
             let file_lengths: Vec<u64> = existing_files
                 .iter()
-                .map(|path| {
-                    Ok(std::fs::metadata(path)
-                        .map_err(|err: _| {
-                            Box::new(PersistenceError::FileSystemError {
-                                path: path.display().to_string(),
-                                context: format!(
-                                    "Failed get existing file length: {}",
-                                    path.display()
-                                ),
-                                internal_error: err.to_string(),
-                            }) as Box<dyn std::error::Error + Send>
-                        })?
-                        .len())
-                })
+                .map(|path| existing_file_logical_size(path))
                 .collect::<StorageResult<_>>()?;
             let existing_overlays = &existing_files[existing_base.iter().len()..];
 
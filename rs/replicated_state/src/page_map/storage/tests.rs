@@ -9,9 +9,10 @@ use std::{
 
 use crate::page_map::{
     storage::{
-        validate, Checkpoint, FileIndex, MergeCandidate, MergeDestination, OverlayFile,
-        PageIndexRange, Shard, Storage, StorageLayout, CURRENT_OVERLAY_VERSION,
-        PAGE_INDEX_RANGE_NUM_BYTES, SIZE_NUM_BYTES, VERSION_NUM_BYTES,
+        logical_file_size, validate, BaseFile, Checkpoint, CompressionCandidate, FileIndex,
+        LogicalFile, MergeCandidate, MergeDestination, OverlayFile, OverlayVersion, PageIndexRange,
+        Shard, Storage, StorageLayout, CURRENT_OVERLAY_VERSION, PAGE_INDEX_RANGE_NUM_BYTES,
+        SIZE_NUM_BYTES, VERSION_NUM_BYTES,
     },
     test_utils::{base_only_storage_layout, ShardedTestStorageLayout, TestStorageLayout},
     FileDescriptor, MemoryInstructions, MemoryMapOrData, PageAllocator, PageDelta, PageMap,
@@ -23,7 +24,7 @@ use ic_config::state_manager::LsmtConfig;
 use ic_metrics::MetricsRegistry;
 use ic_sys::{PageIndex, PAGE_SIZE};
 use ic_test_utilities_io::{make_mutable, make_readonly, write_all_at};
-use ic_test_utilities_metrics::fetch_int_counter_vec;
+use ic_test_utilities_metrics::{fetch_int_counter, fetch_int_counter_vec};
 use ic_types::Height;
 use tempfile::{tempdir, Builder, TempDir};
 
@@ -482,11 +483,15 @@ fn write_overlay(
 fn lsmt_config_unsharded() -> LsmtConfig {
     LsmtConfig {
         shard_num_pages: u64::MAX,
+        compress_cold_shards_after_heights: None,
     }
 }
 
 fn lsmt_config_sharded() -> LsmtConfig {
-    LsmtConfig { shard_num_pages: 3 }
+    LsmtConfig {
+        shard_num_pages: 3,
+        compress_cold_shards_after_heights: None,
+    }
 }
 
 /// This function applies `instructions` to a new `Storage` in a temporary directory.
@@ -905,7 +910,10 @@ fn wrong_shard_pages_is_an_error() {
             WriteOverlay((0..9).collect::<Vec<_>>()),
             WriteOverlay((0..9).collect::<Vec<_>>()),
         ],
-        &LsmtConfig { shard_num_pages: 4 },
+        &LsmtConfig {
+            shard_num_pages: 4,
+            compress_cold_shards_after_heights: None,
+        },
         &tempdir,
     );
     let merge_candidates = MergeCandidate::new(
@@ -916,7 +924,10 @@ fn wrong_shard_pages_is_an_error() {
         },
         Height::from(0),
         9, /* num_pages */
-        &LsmtConfig { shard_num_pages: 3 },
+        &LsmtConfig {
+            shard_num_pages: 3,
+            compress_cold_shards_after_heights: None,
+        },
         &StorageMetrics::new(&MetricsRegistry::new()),
     )
     .unwrap();
@@ -1062,6 +1073,7 @@ fn test_make_merge_candidates_to_overlay() {
     let tempdir = tempdir().unwrap();
     let lsmt_config = LsmtConfig {
         shard_num_pages: 15,
+        compress_cold_shards_after_heights: None,
     };
 
     // 000002 |xx|
@@ -1334,7 +1346,10 @@ fn can_write_shards() {
 
     write_overlays_and_verify_with_tempdir(
         instructions,
-        &LsmtConfig { shard_num_pages: 1 },
+        &LsmtConfig {
+            shard_num_pages: 1,
+            compress_cold_shards_after_heights: None,
+        },
         &tempdir,
     );
     let files = storage_files(tempdir.path());
@@ -1356,7 +1371,10 @@ fn overlapping_shards_is_an_error() {
 
     write_overlays_and_verify_with_tempdir(
         instructions,
-        &LsmtConfig { shard_num_pages: 1 },
+        &LsmtConfig {
+            shard_num_pages: 1,
+            compress_cold_shards_after_heights: None,
+        },
         &tempdir,
     );
     let files = storage_files(tempdir.path());
@@ -1515,6 +1533,279 @@ fn overlapping_page_ranges() {
     );
 }
 
+fn sharded_test_layout(dir: &Path) -> ShardedTestStorageLayout {
+    ShardedTestStorageLayout {
+        dir_path: dir.to_path_buf(),
+        base: dir.join("vmemory_0.bin"),
+        overlay_suffix: "vmemory_0.overlay".to_owned(),
+    }
+}
+
+/// Compresses all the cold overlays of `layout`.
+fn compress_cold_overlays(
+    layout: &dyn StorageLayout,
+    height: Height,
+    lsmt_config: &LsmtConfig,
+    metrics: &StorageMetrics,
+) {
+    for candidate in CompressionCandidate::new(layout, height, lsmt_config).unwrap() {
+        candidate.apply(metrics).unwrap();
+    }
+}
+
+#[test]
+fn compressed_overlays_have_same_logical_content() {
+    let tempdir = tempdir().unwrap();
+    // The first overlay spans more than one compressed frame.
+    write_overlays_and_verify_with_tempdir(
+        vec![
+            WriteOverlay((0..300).collect()),
+            WriteOverlay(vec![1, 5, 700]),
+        ],
+        &lsmt_config_unsharded(),
+        &tempdir,
+    );
+    let layout = sharded_test_layout(tempdir.path());
+    let files_before = storage_files(tempdir.path());
+    assert_eq!(files_before.overlays.len(), 2);
+    let contents_before: Vec<Vec<u8>> = files_before
+        .overlays
+        .iter()
+        .map(|path| std::fs::read(path).unwrap())
+        .collect();
+    let buffer_before = storage_as_buffer(&Storage::lazy_load(Box::new(layout.clone())).unwrap());
+
+    let metrics_registry = MetricsRegistry::new();
+    let metrics = StorageMetrics::new(&metrics_registry);
+    compress_cold_overlays(
+        &layout,
+        Height::new(10),
+        &LsmtConfig {
+            shard_num_pages: u64::MAX,
+            compress_cold_shards_after_heights: Some(5),
+        },
+        &metrics,
+    );
+
+    assert_eq!(storage_files(tempdir.path()), files_before);
+    for (path, content) in files_before.overlays.iter().zip(contents_before.iter()) {
+        let file = LogicalFile::open(path).unwrap();
+        assert!(file.is_compressed());
+        assert_eq!(file.len(), content.len());
+        assert_eq!(
+            file.read(0..file.len()).unwrap().as_ref(),
+            content.as_slice()
+        );
+        // Reads across frame boundaries.
+        let range = (PAGE_SIZE * 255 + 17)..(PAGE_SIZE * 257);
+        if range.end <= content.len() {
+            assert_eq!(file.read(range.clone()).unwrap().as_ref(), &content[range]);
+        }
+        assert_eq!(logical_file_size(path).unwrap(), content.len() as u64);
+        assert!(std::fs::metadata(path).unwrap().len() < content.len() as u64);
+    }
+
+    let layout_dyn: &dyn StorageLayout = &layout;
+    assert!(
+        layout_dyn.physical_storage_size_bytes().unwrap()
+            < layout_dyn.storage_size_bytes().unwrap()
+    );
+    assert_eq!(
+        layout_dyn.storage_size_bytes().unwrap(),
+        contents_before.iter().map(|c| c.len() as u64).sum::<u64>()
+    );
+    assert!(validate(&layout).is_ok());
+    let num_dir_entries = std::fs::read_dir(tempdir.path()).unwrap().count();
+    let storage = Storage::lazy_load(Box::new(layout.clone())).unwrap();
+    assert_eq!(buffer_before, storage_as_buffer(&storage));
+    // The decompressed overlays are held in unnamed files that don't show up in the directory.
+    assert_eq!(
+        std::fs::read_dir(tempdir.path()).unwrap().count(),
+        num_dir_entries
+    );
+    assert_eq!(
+        fetch_int_counter(&metrics_registry, "storage_layer_num_compressed_files"),
+        Some(2)
+    );
+    assert!(
+        fetch_int_counter(&metrics_registry, "storage_layer_compression_saved_bytes").unwrap() > 0
+    );
+}
+
+#[test]
+fn compressed_overlays_are_decompressed_on_first_access() {
+    let tempdir = tempdir().unwrap();
+    write_overlays_and_verify_with_tempdir(
+        vec![
+            WriteOverlay((0..300).collect()),
+            WriteOverlay(vec![1, 5, 700]),
+        ],
+        &lsmt_config_unsharded(),
+        &tempdir,
+    );
+    let layout = sharded_test_layout(tempdir.path());
+    let buffer_before = storage_as_buffer(&Storage::lazy_load(Box::new(layout.clone())).unwrap());
+    compress_cold_overlays(
+        &layout,
+        Height::new(10),
+        &LsmtConfig {
+            shard_num_pages: u64::MAX,
+            compress_cold_shards_after_heights: Some(5),
+        },
+        &StorageMetrics::new(&MetricsRegistry::new()),
+    );
+
+    let storage = Storage::lazy_load(Box::new(layout.clone())).unwrap();
+    assert_eq!(storage.num_logical_pages(), 701);
+    let storage_impl = storage.init_or_die();
+    let base_overlay = match &storage_impl.base {
+        BaseFile::Overlay(overlays) => {
+            assert_eq!(overlays.len(), 1);
+            &overlays[0]
+        }
+        BaseFile::Base(_) => panic!("Expected the base to be an overlay"),
+    };
+    assert_eq!(storage_impl.overlays.len(), 1);
+    let top_overlay = &storage_impl.overlays[0];
+    // Loading the storage only parses the indices of the compressed overlays.
+    assert!(base_overlay.mapping.get().is_none());
+    assert!(top_overlay.mapping.get().is_none());
+    assert_eq!(base_overlay.version(), OverlayVersion::V1);
+
+    // Only the overlay containing the page is decompressed.
+    assert_eq!(
+        storage.get_page(PageIndex::new(700)).as_slice(),
+        &buffer_before[(700 * PAGE_SIZE)..(701 * PAGE_SIZE)]
+    );
+    assert!(base_overlay.mapping.get().is_none());
+    assert!(top_overlay.mapping.get().is_some());
+
+    assert_eq!(
+        storage.get_page(PageIndex::new(100)).as_slice(),
+        &buffer_before[(100 * PAGE_SIZE)..(101 * PAGE_SIZE)]
+    );
+    assert!(base_overlay.mapping.get().is_some());
+    assert_eq!(buffer_before, storage_as_buffer(&storage));
+}
+
+#[test]
+fn only_cold_shards_are_compressed() {
+    let tempdir = tempdir().unwrap();
+    // Shard 0 is last written at height 0, shard 1 at height 1.
+    write_overlays_and_verify_with_tempdir(
+        vec![WriteOverlay(vec![0, 1, 3, 4]), WriteOverlay(vec![4])],
+        &lsmt_config_sharded(),
+        &tempdir,
+    );
+    let layout = sharded_test_layout(tempdir.path());
+    let buffer_before = storage_as_buffer(&Storage::lazy_load(Box::new(layout.clone())).unwrap());
+
+    let metrics = StorageMetrics::new(&MetricsRegistry::new());
+    compress_cold_overlays(
+        &layout,
+        Height::new(2),
+        &LsmtConfig {
+            shard_num_pages: 3,
+            compress_cold_shards_after_heights: Some(2),
+        },
+        &metrics,
+    );
+
+    let is_compressed = |name: &str| {
+        LogicalFile::open(&tempdir.path().join(name))
+            .unwrap()
+            .is_compressed()
+    };
+    assert!(is_compressed("000000_000_vmemory_0.overlay"));
+    assert!(!is_compressed("000000_001_vmemory_0.overlay"));
+    assert!(!is_compressed("000001_001_vmemory_0.overlay"));
+    assert!(validate(&layout).is_ok());
+    assert_eq!(
+        buffer_before,
+        storage_as_buffer(&Storage::lazy_load(Box::new(layout.clone())).unwrap())
+    );
+
+    // Without a threshold nothing is compressed.
+    compress_cold_overlays(&layout, Height::new(100), &lsmt_config_sharded(), &metrics);
+    assert!(!is_compressed("000001_001_vmemory_0.overlay"));
+}
+
+#[test]
+fn compression_candidates_skip_compressed_overlays() {
+    let tempdir = tempdir().unwrap();
+    // Shard 0 is last written at height 1, shard 1 at height 0.
+    write_overlays_and_verify_with_tempdir(
+        vec![WriteOverlay(vec![0, 3]), WriteOverlay(vec![1])],
+        &lsmt_config_sharded(),
+        &tempdir,
+    );
+    let layout = sharded_test_layout(tempdir.path());
+    let lsmt_config = LsmtConfig {
+        shard_num_pages: 3,
+        compress_cold_shards_after_heights: Some(1),
+    };
+
+    let candidates = CompressionCandidate::new(&layout, Height::new(2), &lsmt_config).unwrap();
+    assert_eq!(candidates.len(), 3);
+    let mut last_written: Vec<_> = candidates.iter().map(|c| c.last_written()).collect();
+    last_written.sort();
+    assert_eq!(
+        last_written,
+        vec![Height::new(0), Height::new(1), Height::new(1)]
+    );
+    for candidate in candidates.iter() {
+        assert_eq!(
+            candidate.size_bytes(),
+            expected_overlay_file_size(1 /* num_pages */, 1 /* num_ranges */)
+        );
+    }
+
+    // Compressed overlays are no longer candidates.
+    candidates[0]
+        .apply(&StorageMetrics::new(&MetricsRegistry::new()))
+        .unwrap();
+    assert_eq!(
+        CompressionCandidate::new(&layout, Height::new(2), &lsmt_config)
+            .unwrap()
+            .len(),
+        2
+    );
+}
+
+#[test]
+fn can_merge_compressed_overlays() {
+    let tempdir = tempdir().unwrap();
+    write_overlays_and_verify_with_tempdir(
+        vec![WriteOverlay(vec![0, 1, 2]), WriteOverlay(vec![2, 3])],
+        &lsmt_config_unsharded(),
+        &tempdir,
+    );
+    let layout = sharded_test_layout(tempdir.path());
+    let buffer_before = storage_as_buffer(&Storage::lazy_load(Box::new(layout.clone())).unwrap());
+    let metrics = StorageMetrics::new(&MetricsRegistry::new());
+    compress_cold_overlays(
+        &layout,
+        Height::new(1),
+        &LsmtConfig {
+            shard_num_pages: u64::MAX,
+            compress_cold_shards_after_heights: Some(0),
+        },
+        &metrics,
+    );
+
+    let merges = MergeCandidate::merge_to_base(&layout, 4).unwrap();
+    for merge in merges {
+        merge.apply(&metrics).unwrap();
+    }
+    let files = storage_files(tempdir.path());
+    assert!(files.base.is_some());
+    assert!(files.overlays.is_empty());
+    assert_eq!(
+        buffer_before,
+        storage_as_buffer(&Storage::lazy_load(Box::new(layout)).unwrap())
+    );
+}
+
 #[cfg(not(feature = "fuzzing_code"))]
 mod proptest_tests {
     use super::*;
@@ -142,6 +142,7 @@ fn persisted_map_is_equivalent_to_the_original() {
                 height,
                 &LsmtConfig {
                     shard_num_pages: u64::MAX,
+                    compress_cold_shards_after_heights: None,
                 },
                 metrics,
             )
@@ -236,6 +237,7 @@ fn can_persist_and_load_an_empty_page_map() {
             Height::new(0),
             &LsmtConfig {
                 shard_num_pages: u64::MAX,
+                compress_cold_shards_after_heights: None,
             },
             &metrics,
         )
@@ -468,6 +470,7 @@ fn get_memory_instructions_returns_deltas() {
             Height::new(0),
             &LsmtConfig {
                 shard_num_pages: u64::MAX,
+                compress_cold_shards_after_heights: None,
             },
             &metrics,
         )
@@ -595,6 +598,7 @@ fn get_memory_instructions_ignores_base_file() {
     let metrics = StorageMetrics::new(&MetricsRegistry::new());
     let lsmt_config = LsmtConfig {
         shard_num_pages: u64::MAX,
+        compress_cold_shards_after_heights: None,
     };
     let tempdir = Builder::new().prefix("page_map_test").tempdir().unwrap();
     let storage_layout = ShardedTestStorageLayout {
@@ -644,6 +648,7 @@ fn get_memory_instructions_stops_at_instructions_outside_min_range() {
     let metrics = StorageMetrics::new(&MetricsRegistry::new());
     let lsmt_config = LsmtConfig {
         shard_num_pages: u64::MAX,
+        compress_cold_shards_after_heights: None,
     };
     let tempdir = Builder::new().prefix("page_map_test").tempdir().unwrap();
     let storage_layout = ShardedTestStorageLayout {
@@ -715,6 +720,7 @@ fn get_memory_instructions_extends_mmap_past_min_range() {
     let metrics = StorageMetrics::new(&MetricsRegistry::new());
     let lsmt_config = LsmtConfig {
         shard_num_pages: u64::MAX,
+        compress_cold_shards_after_heights: None,
     };
     let tempdir = Builder::new().prefix("page_map_test").tempdir().unwrap();
     let storage_layout = ShardedTestStorageLayout {
@@ -671,6 +671,7 @@ fn insert_bitcoin_response_non_matching() {
                 GetSuccessorsResponseComplete {
                     blocks: vec![],
                     next: vec![],
+                    filters: vec![],
                 },
             ),
             callback_id: 0,
@@ -698,6 +699,7 @@ fn insert_bitcoin_response() {
     let response = GetSuccessorsResponseComplete {
         blocks: vec![],
         next: vec![],
+        filters: vec![],
     };
 
     state
@@ -122,6 +122,8 @@ pub struct IcrcBlock {
     pub timestamp: u64,
     pub fee_collector: Option<Account>,
     pub fee_collector_block_index: Option<u64>,
+    /// The index of the first block of the ICRC-4 batch this block belongs to.
+    pub batch_block_index: Option<u64>,
 }
 
 impl IcrcBlock {
@@ -157,6 +159,7 @@ impl TryFrom<Value> for IcrcBlock {
         let effective_fee = get_opt_field::<Nat>(&map, &[], "fee")?;
         let fee_collector = get_opt_field::<Account>(&map, &[], "fee_col")?;
         let fee_collector_block_index = get_opt_field::<u64>(&map, &[], "fee_col_block")?;
+        let batch_block_index = get_opt_field::<u64>(&map, &[], "batch")?;
         let transaction = map.get("tx").ok_or(anyhow!("Missing field 'tx'"))?.clone();
         let transaction = IcrcTransaction::try_from(transaction)?;
 
@@ -167,6 +170,7 @@ impl TryFrom<Value> for IcrcBlock {
             timestamp,
             fee_collector,
             fee_collector_block_index,
+            batch_block_index,
         })
     }
 }
@@ -193,6 +197,12 @@ impl From<IcrcBlock> for Value {
                 Value::Nat(Nat::from(fee_col_block)),
             );
         }
+        if let Some(batch_block_index) = block.batch_block_index {
+            map.insert(
+                "batch".to_string(),
+                Value::Nat(Nat::from(batch_block_index)),
+            );
+        }
         Self::Map(map)
     }
 }
@@ -674,6 +684,7 @@ mod tests {
             any::<u64>(),                                                         // timestamp
             option::of(arb_account()),                                            // fee_col
             option::of(any::<u64>()), // fee_col_block_index
+            option::of(any::<u64>()), // batch_block_index
         )
             .prop_map(
                 |(
@@ -683,6 +694,7 @@ mod tests {
                     timestamp,
                     fee_collector,
                     fee_collector_block_index,
+                    batch_block_index,
                 )| IcrcBlock {
                     parent_hash,
                     transaction,
@@ -690,6 +702,7 @@ mod tests {
                     timestamp,
                     fee_collector,
                     fee_collector_block_index,
+                    batch_block_index,
                 },
             )
     }
@@ -785,6 +798,10 @@ mod tests {
             block.fee_collector_block_index, rosetta_block.fee_collector_block_index,
             "fee_collector_block_index",
         );
+        assert_eq!(
+            block.batch_block_index, rosetta_block.batch_block_index,
+            "batch_block_index",
+        );
         compare_transactions(block.transaction, rosetta_block.transaction);
     }
 
@@ -293,6 +293,9 @@ pub struct BlockMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fee_collector_block_index: Option<u64>,
 
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch_block_index: Option<u64>,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     pub effective_fee: Option<Amount>,
 
@@ -332,6 +335,7 @@ impl BlockMetadata {
         Ok(Self {
             fee_collector: block.fee_collector.map(|collector| collector.into()),
             fee_collector_block_index: block.fee_collector_block_index,
+            batch_block_index: block.batch_block_index,
             block_created_at_nano_seconds: block.timestamp,
             effective_fee: block
                 .effective_fee
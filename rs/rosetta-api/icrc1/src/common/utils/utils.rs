@@ -649,6 +649,7 @@ pub fn rosetta_core_block_to_icrc1_block(
             )
         },
         fee_collector_block_index: block_metadata.fee_collector_block_index,
+        batch_block_index: block_metadata.batch_block_index,
     })
 }
 
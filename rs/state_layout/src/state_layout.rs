@@ -11,6 +11,7 @@ use ic_protobuf::{
     },
 };
 use ic_replicated_state::{
+    canister_snapshots::SnapshotSource,
     canister_state::{
         execution_state::{NextScheduledMethod, WasmMetadata},
         system_state::{
@@ -23,8 +24,8 @@ use ic_replicated_state::{
 use ic_sys::{fs::sync_path, mmap::ScopedMmap};
 use ic_types::{
     batch::TotalQueryStats, nominal_cycles::NominalCycles, AccumulatedPriority, CanisterId,
-    CanisterLog, ComputeAllocation, Cycles, ExecutionRound, Height, LongExecutionMode,
-    MemoryAllocation, NumInstructions, PrincipalId, SnapshotId, Time,
+    CanisterLog, ComputeAllocation, Cycles, EnvironmentVariables, ExecutionRound, Height,
+    LongExecutionMode, MemoryAllocation, NumInstructions, PrincipalId, SnapshotId, Time,
 };
 use ic_utils::thread::maybe_parallel_map;
 use ic_wasm_types::{CanisterModule, WasmHash};
@@ -174,6 +175,7 @@ pub struct CanisterStateBits {
     pub log_visibility: LogVisibilityV2,
     pub canister_log: CanisterLog,
     pub wasm_memory_limit: Option<NumBytes>,
+    pub environment_variables: EnvironmentVariables,
     pub next_snapshot_id: u64,
     pub snapshots_memory_usage: NumBytes,
     pub task_queue: TaskQueue,
@@ -205,6 +207,8 @@ pub struct CanisterSnapshotBits {
     pub total_size: NumBytes,
     /// State of the exported Wasm globals.
     pub exported_globals: Vec<Global>,
+    /// How the snapshot was created.
+    pub source: SnapshotSource,
 }
 
 #[derive(Clone)]
@@ -2391,6 +2395,16 @@ impl From<CanisterStateBits> for pb_canister_state_bits::CanisterStateBits {
                 .collect(),
             next_canister_log_record_idx: item.canister_log.next_idx(),
             wasm_memory_limit: item.wasm_memory_limit.map(|v| v.get()),
+            environment_variables: item
+                .environment_variables
+                .iter()
+                .map(
+                    |(name, value)| pb_canister_state_bits::EnvironmentVariable {
+                        name: name.clone(),
+                        value: value.clone(),
+                    },
+                )
+                .collect(),
             next_snapshot_id: item.next_snapshot_id,
             snapshots_memory_usage: item.snapshots_memory_usage.get(),
             on_low_wasm_memory_hook_status: Some(
@@ -2568,6 +2582,11 @@ impl TryFrom<pb_canister_state_bits::CanisterStateBits> for CanisterStateBits {
                     .collect(),
             ),
             wasm_memory_limit: value.wasm_memory_limit.map(NumBytes::from),
+            environment_variables: value
+                .environment_variables
+                .into_iter()
+                .map(|variable| (variable.name, variable.value))
+                .collect(),
             next_snapshot_id: value.next_snapshot_id,
             snapshots_memory_usage: NumBytes::from(value.snapshots_memory_usage),
             task_queue,
@@ -2659,6 +2678,10 @@ impl From<CanisterSnapshotBits> for pb_canister_snapshot_bits::CanisterSnapshotB
                 .iter()
                 .map(|global| global.into())
                 .collect(),
+            uploaded_wasm_module_size: match item.source {
+                SnapshotSource::TakenFromCanister => None,
+                SnapshotSource::MetadataUpload { wasm_module_size } => Some(wasm_module_size),
+            },
         }
     }
 }
@@ -2705,6 +2728,10 @@ impl TryFrom<pb_canister_snapshot_bits::CanisterSnapshotBits> for CanisterSnapsh
             wasm_memory_size: NumWasmPages::from(item.wasm_memory_size as usize),
             total_size: NumBytes::from(item.total_size),
             exported_globals,
+            source: match item.uploaded_wasm_module_size {
+                None => SnapshotSource::TakenFromCanister,
+                Some(wasm_module_size) => SnapshotSource::MetadataUpload { wasm_module_size },
+            },
         })
     }
 }
@@ -57,6 +57,7 @@ fn default_canister_state_bits() -> CanisterStateBits {
         log_visibility: Default::default(),
         canister_log: Default::default(),
         wasm_memory_limit: None,
+        environment_variables: Default::default(),
         next_snapshot_id: 0,
         snapshots_memory_usage: NumBytes::from(0),
     }
@@ -199,6 +200,29 @@ fn test_encode_decode_non_empty_history() {
     assert_eq!(canister_state_bits.canister_history, canister_history);
 }
 
+#[test]
+fn test_encode_decode_environment_variables() {
+    let environment_variables: EnvironmentVariables = [
+        ("NETWORK".to_string(), "mainnet".to_string()),
+        ("API_URL".to_string(), "https://example.com".to_string()),
+    ]
+    .into_iter()
+    .collect();
+
+    let canister_state_bits = CanisterStateBits {
+        environment_variables: environment_variables.clone(),
+        ..default_canister_state_bits()
+    };
+
+    let pb_bits = pb_canister_state_bits::CanisterStateBits::from(canister_state_bits);
+    let canister_state_bits = CanisterStateBits::try_from(pb_bits).unwrap();
+
+    assert_eq!(
+        canister_state_bits.environment_variables,
+        environment_variables
+    );
+}
+
 #[test]
 fn test_canister_snapshots_decode() {
     let canister_id = canister_test_id(7);
@@ -214,6 +238,7 @@ fn test_canister_snapshots_decode() {
         wasm_memory_size: NumWasmPages::new(10),
         total_size: NumBytes::new(100),
         exported_globals: vec![Global::I32(1), Global::I64(2), Global::F64(0.1)],
+        source: SnapshotSource::TakenFromCanister,
     };
 
     let pb_bits =
@@ -221,6 +246,19 @@ fn test_canister_snapshots_decode() {
     let new_canister_snapshot_bits = CanisterSnapshotBits::try_from(pb_bits).unwrap();
 
     assert_eq!(canister_snapshot_bits, new_canister_snapshot_bits);
+
+    let uploaded_canister_snapshot_bits = CanisterSnapshotBits {
+        source: SnapshotSource::MetadataUpload {
+            wasm_module_size: 3,
+        },
+        ..canister_snapshot_bits
+    };
+    let pb_bits = pb_canister_snapshot_bits::CanisterSnapshotBits::from(
+        uploaded_canister_snapshot_bits.clone(),
+    );
+    let new_canister_snapshot_bits = CanisterSnapshotBits::try_from(pb_bits).unwrap();
+
+    assert_eq!(uploaded_canister_snapshot_bits, new_canister_snapshot_bits);
 }
 
 #[test]
@@ -6,7 +6,7 @@ use ic_btc_consensus::BitcoinPayloadBuilder;
 use ic_config::{
     adapters::AdaptersConfig,
     bitcoin_payload_builder_config::Config as BitcoinPayloadBuilderConfig,
-    execution_environment::Config as HypervisorConfig,
+    execution_environment::{Config as HypervisorConfig, MAX_NUMBER_OF_SNAPSHOTS_PER_CANISTER},
     message_routing::{MAX_STREAM_MESSAGES, TARGET_STREAM_SIZE_BYTES},
     state_manager::LsmtConfig,
     subnet_config::SubnetConfig,
@@ -23,7 +23,8 @@ use ic_crypto_tree_hash::{sparse_labeled_tree_from_paths, Label, Path as Labeled
 use ic_crypto_utils_threshold_sig_der::threshold_sig_public_key_to_der;
 use ic_cycles_account_manager::CyclesAccountManager;
 pub use ic_error_types::{ErrorCode, UserError};
-use ic_execution_environment::{ExecutionServices, IngressHistoryReaderImpl};
+pub use ic_execution_environment::{ExecutedMessage, TracedExecution};
+use ic_execution_environment::{ExecutionServices, ExecutionTracer, IngressHistoryReaderImpl};
 use ic_http_endpoints_public::{metrics::HttpHandlerMetrics, IngressWatcher, IngressWatcherHandle};
 use ic_https_outcalls_consensus::payload_builder::CanisterHttpPayloadBuilderImpl;
 use ic_ingress_manager::{IngressManager, RandomStateKind};
@@ -33,7 +34,10 @@ use ic_interfaces::{
     certification::{Verifier, VerifierError},
     consensus::{PayloadBuilder as ConsensusPayloadBuilder, PayloadValidationError},
     consensus_pool::ConsensusTime,
-    execution_environment::{IngressFilterService, IngressHistoryReader, QueryExecutionService},
+    execution_environment::{
+        ChainKeyData, ExecutionRoundSummary, ExecutionRoundType, IngressFilterService,
+        IngressHistoryReader, QueryExecutionService, RegistryExecutionSettings, Scheduler,
+    },
     ingress_pool::{
         IngressPool, IngressPoolObject, PoolSection, UnvalidatedIngressArtifact,
         ValidatedIngressArtifact,
@@ -97,10 +101,15 @@ use ic_registry_subnet_features::{
 };
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    canister_state::{system_state::CyclesUseCase, NumWasmPages, WASM_PAGE_SIZE_IN_BYTES},
+    canister_snapshots::{CanisterSnapshot, ExecutionStateSnapshot, PageMemory, SnapshotSource},
+    canister_state::{
+        system_state::{wasm_chunk_store::WasmChunkStore, CyclesUseCase},
+        NumWasmPages, WASM_PAGE_SIZE_IN_BYTES,
+    },
     metadata_state::subnet_call_context_manager::{SignWithThresholdContext, ThresholdArguments},
     page_map::Buffer,
-    CheckpointLoadingMetrics, Memory, PageMap, ReplicatedState,
+    replicated_state::{PeekableOutputIterator, ReplicatedStateMessageRouting},
+    CheckpointLoadingMetrics, Global, Memory, PageMap, ReplicatedState,
 };
 use ic_state_layout::{CheckpointLayout, ReadOnly};
 use ic_state_manager::StateManagerImpl;
@@ -135,14 +144,14 @@ use ic_types::{
     malicious_flags::MaliciousFlags,
     messages::{
         Blob, Certificate, CertificateDelegation, HttpCallContent, HttpCanisterUpdate,
-        HttpRequestEnvelope, Payload as MsgPayload, Query, QuerySource, RejectContext,
-        SignedIngress, EXPECTED_MESSAGE_ID_LENGTH,
+        HttpRequestEnvelope, Payload as MsgPayload, Query, QuerySource, RejectContext, Request,
+        RequestOrResponse, Response, SignedIngress, EXPECTED_MESSAGE_ID_LENGTH, NO_DEADLINE,
     },
     signature::ThresholdSignature,
-    time::GENESIS,
+    time::{CoarseTime, GENESIS},
     xnet::{CertifiedStreamSlice, StreamIndex},
-    CanisterLog, CountBytes, CryptoHashOfPartialState, Height, NodeId, Randomness, RegistryVersion,
-    ReplicaVersion,
+    CanisterLog, CountBytes, CryptoHashOfPartialState, ExecutionRound, Height, NodeId, Randomness,
+    RegistryVersion, ReplicaVersion, SnapshotId,
 };
 use ic_types::{
     canister_http::{
@@ -151,9 +160,11 @@ use ic_types::{
     crypto::threshold_sig::ThresholdSigPublicKey,
     ingress::{IngressState, IngressStatus},
     messages::{CallbackId, MessageId},
+    nominal_cycles::NominalCycles,
     time::Time,
     CanisterId, CryptoHashOfState, Cycles, NumBytes, PrincipalId, SubnetId, UserId,
 };
+use ic_wasm_types::CanisterModule;
 use ic_xnet_payload_builder::{
     certified_slice_pool::CertifiedSlicePool, refill_stream_slice_indices, RefillTaskHandle,
     XNetPayloadBuilderImpl, XNetPayloadBuilderMetrics, XNetSlicePoolImpl,
@@ -612,6 +623,8 @@ struct PocketXNetImpl {
     pool: Arc<Mutex<CertifiedSlicePool>>,
     /// The subnet ID of the `StateMachine` for which the XNet layer is mocked.
     own_subnet_id: SubnetId,
+    /// Faults injected into the `StateMachine` for which the XNet layer is mocked.
+    fault_injection: Arc<Mutex<FaultInjection>>,
 }
 
 impl PocketXNetImpl {
@@ -619,11 +632,13 @@ impl PocketXNetImpl {
         subnets: Arc<dyn Subnets>,
         pool: Arc<Mutex<CertifiedSlicePool>>,
         own_subnet_id: SubnetId,
+        fault_injection: Arc<Mutex<FaultInjection>>,
     ) -> Self {
         Self {
             subnets,
             pool,
             own_subnet_id,
+            fault_injection,
         }
     }
 
@@ -631,7 +646,16 @@ impl PocketXNetImpl {
         let refill_stream_slice_indices =
             refill_stream_slice_indices(self.pool.clone(), self.own_subnet_id);
 
+        let paused_xnet_subnets = self
+            .fault_injection
+            .lock()
+            .unwrap()
+            .paused_xnet_subnets
+            .clone();
         for (subnet_id, indices) in refill_stream_slice_indices {
+            if paused_xnet_subnets.contains(&subnet_id) {
+                continue;
+            }
             let sm = self.subnets.get(subnet_id).unwrap();
             match sm.generate_certified_stream_slice(
                 self.own_subnet_id,
@@ -758,6 +782,340 @@ impl BatchPayloadBuilder for PocketQueryStatsPayloadBuilderImpl {
     }
 }
 
+/// A request from a canister that was intercepted before being routed to its receiver
+/// because it matched a call interceptor (see `StateMachine::intercept_calls`).
+#[derive(Clone, Debug)]
+pub struct InterceptedCall {
+    pub call_id: u64,
+    pub request: Arc<Request>,
+}
+
+/// Errors returned by `StateMachine::respond_to_intercepted_call`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum InterceptedCallError {
+    /// There is no pending intercepted call with the given ID.
+    CallNotFound(u64),
+    /// The response could not be inducted into the input queue of the caller,
+    /// e.g., because the caller has been deleted in the meantime.
+    InductionFailed(String),
+}
+
+/// The content of a canister snapshot in a form that does not depend on
+/// the state of a particular `StateMachine` and thus can be persisted and
+/// imported into another `StateMachine` (see `StateMachine::canister_snapshot_data`
+/// and `StateMachine::upload_canister_snapshot_data`).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CanisterSnapshotData {
+    pub taken_at_timestamp: u64,
+    pub canister_version: u64,
+    #[serde(with = "serde_bytes")]
+    pub wasm_module: Vec<u8>,
+    pub exported_globals: Vec<Global>,
+    /// The Wasm memory; its length is a multiple of the Wasm page size.
+    #[serde(with = "serde_bytes")]
+    pub wasm_memory: Vec<u8>,
+    /// The stable memory; its length is a multiple of the Wasm page size.
+    #[serde(with = "serde_bytes")]
+    pub stable_memory: Vec<u8>,
+    #[serde(with = "serde_bytes")]
+    pub certified_data: Vec<u8>,
+    /// The chunks stored in the Wasm chunk store.
+    pub wasm_chunk_store: Vec<serde_bytes::ByteBuf>,
+}
+
+/// Errors returned by `StateMachine::upload_canister_snapshot_data`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum CanisterSnapshotDataError {
+    /// The canister does not exist.
+    CanisterNotFound(CanisterId),
+    /// The canister already has the maximum number of snapshots.
+    TooManySnapshots(CanisterId),
+    /// The snapshot content is invalid.
+    InvalidSnapshot(String),
+}
+
+/// Execution cost metrics of a canister (see `StateMachine::canister_cost_metrics`).
+///
+/// All counters are cumulative over the lifetime of the canister and thus
+/// the cost of a particular call is obtained by comparing the metrics
+/// before and after executing the call. The instructions executed
+/// by individual messages are traced separately
+/// (see `StateMachine::start_execution_tracing`).
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct CanisterCostMetrics {
+    pub consumed_cycles_by_use_cases: BTreeMap<CyclesUseCase, NominalCycles>,
+    pub cycles_balance: Cycles,
+    pub wasm_memory_size: NumBytes,
+    pub stable_memory_size: NumBytes,
+}
+
+/// Call interceptors and intercepted calls shared between
+/// a `StateMachine` and its `PocketScheduler`.
+#[derive(Default)]
+struct CallInterception {
+    /// A call is intercepted if its receiver and method name match a registered interceptor
+    /// or if its receiver matches a registered interceptor without a method name.
+    interceptors: BTreeSet<(CanisterId, Option<String>)>,
+    intercepted_calls: BTreeMap<u64, Arc<Request>>,
+    next_call_id: u64,
+}
+
+impl CallInterception {
+    fn is_intercepted(&self, request: &Request) -> bool {
+        self.interceptors.contains(&(request.receiver, None))
+            || self
+                .interceptors
+                .contains(&(request.receiver, Some(request.method_name.clone())))
+    }
+}
+
+/// A fault affecting responses sent to canisters on a particular (different) subnet
+/// (see `StateMachine::set_xnet_response_fault`).
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum XNetResponseFault {
+    /// Every response is replaced by a reject response:
+    /// with reject code `SYS_UNKNOWN` for best-effort calls
+    /// and with reject code `SYS_TRANSIENT` for guaranteed response calls.
+    Drop,
+    /// Every response is routed with a delay of the given number of rounds.
+    Delay(u64),
+}
+
+/// A response delayed by `XNetResponseFault::Delay`.
+struct DelayedResponse {
+    rounds: u64,
+    subnet_id: SubnetId,
+    response: Arc<Response>,
+}
+
+/// Faults injected into a `StateMachine` and shared with its `PocketScheduler`
+/// (affecting messages produced by the canisters on this subnet)
+/// and its `PocketXNetImpl` (affecting messages inducted from other subnets).
+#[derive(Default)]
+struct FaultInjection {
+    /// Subnets from which no XNet messages are inducted.
+    paused_xnet_subnets: BTreeSet<SubnetId>,
+    /// Faults affecting responses to canisters on the given subnets.
+    response_faults: BTreeMap<SubnetId, XNetResponseFault>,
+    delayed_responses: Vec<DelayedResponse>,
+    /// Canisters whose input queues are treated as full, i.e.,
+    /// requests to these canisters are rejected with reject code `SYS_TRANSIENT`.
+    full_queues: BTreeSet<CanisterId>,
+    /// No rounds are executed while a subnet is stalled.
+    stalled: bool,
+}
+
+impl FaultInjection {
+    fn affects_output(&self) -> bool {
+        !self.response_faults.is_empty()
+            || !self.delayed_responses.is_empty()
+            || !self.full_queues.is_empty()
+    }
+}
+
+/// Returns a reject response to the given request or in place of the given response.
+fn reject_response(
+    originator: CanisterId,
+    respondent: CanisterId,
+    originator_reply_callback: CallbackId,
+    refund: Cycles,
+    deadline: CoarseTime,
+    reject_code: RejectCode,
+    message: String,
+) -> Response {
+    Response {
+        originator,
+        respondent,
+        originator_reply_callback,
+        refund,
+        response_payload: MsgPayload::Reject(RejectContext::new(reject_code, message)),
+        deadline,
+    }
+}
+
+/// Appends a response to the stream to the given subnet
+/// (bypassing the output queue of its respondent).
+fn push_to_stream(state: &mut ReplicatedState, subnet_id: SubnetId, response: Arc<Response>) {
+    let mut streams = state.take_streams();
+    streams
+        .entry(subnet_id)
+        .or_default()
+        .push(RequestOrResponse::Response(response));
+    state.put_streams(streams);
+}
+
+/// The action applied to a message at the head of an output queue by `PocketScheduler`.
+enum OutputAction {
+    Route,
+    Intercept,
+    RejectRequest,
+    ApplyResponseFault(SubnetId, XNetResponseFault),
+}
+
+/// A custom `Scheduler` that executes a round using the given `Scheduler`
+/// and then removes all requests matching a call interceptor from the output queues
+/// so that they are not routed by the stream builder (which runs after execution
+/// when processing a batch).
+///
+/// Only requests that are not inducted by the scheduler directly
+/// (i.e., requests to canisters that are not hosted on this subnet)
+/// can be intercepted.
+///
+/// The same applies to injected faults: requests to canisters whose queues
+/// are treated as full are rejected and responses to canisters on other subnets
+/// are dropped or delayed.
+struct PocketScheduler {
+    scheduler: Box<dyn Scheduler<State = ReplicatedState>>,
+    call_interception: Arc<Mutex<CallInterception>>,
+    fault_injection: Arc<Mutex<FaultInjection>>,
+}
+
+impl Scheduler for PocketScheduler {
+    type State = ReplicatedState;
+
+    fn execute_round(
+        &self,
+        state: ReplicatedState,
+        randomness: Randomness,
+        chain_key_data: ChainKeyData,
+        replica_version: &ReplicaVersion,
+        current_round: ExecutionRound,
+        round_summary: Option<ExecutionRoundSummary>,
+        current_round_type: ExecutionRoundType,
+        registry_settings: &RegistryExecutionSettings,
+    ) -> ReplicatedState {
+        let mut state = self.scheduler.execute_round(
+            state,
+            randomness,
+            chain_key_data,
+            replica_version,
+            current_round,
+            round_summary,
+            current_round_type,
+            registry_settings,
+        );
+
+        let mut call_interception = self.call_interception.lock().unwrap();
+        let mut fault_injection = self.fault_injection.lock().unwrap();
+        if call_interception.interceptors.is_empty() && !fault_injection.affects_output() {
+            return state;
+        }
+
+        // Delayed responses become due after the given number of rounds.
+        let (due_responses, delayed_responses): (Vec<_>, Vec<_>) =
+            std::mem::take(&mut fault_injection.delayed_responses)
+                .into_iter()
+                .map(|delayed| DelayedResponse {
+                    rounds: delayed.rounds.saturating_sub(1),
+                    ..delayed
+                })
+                .partition(|delayed| delayed.rounds == 0);
+        fault_injection.delayed_responses = delayed_responses;
+        for due in due_responses {
+            push_to_stream(&mut state, due.subnet_id, due.response);
+        }
+
+        let own_subnet_id = state.metadata.own_subnet_id;
+        let routing_table = state.metadata.network_topology.routing_table.clone();
+        let mut rejected_requests = vec![];
+        let mut dropped_responses = vec![];
+        let mut output_iter = state.output_into_iter();
+        while let Some(msg) = output_iter.peek() {
+            let action = match msg {
+                RequestOrResponse::Request(request)
+                    if call_interception.is_intercepted(request) =>
+                {
+                    OutputAction::Intercept
+                }
+                RequestOrResponse::Request(request)
+                    if fault_injection.full_queues.contains(&request.receiver) =>
+                {
+                    OutputAction::RejectRequest
+                }
+                RequestOrResponse::Response(response) => {
+                    match routing_table.route(response.originator.get()) {
+                        Some(subnet_id) if subnet_id != own_subnet_id => fault_injection
+                            .response_faults
+                            .get(&subnet_id)
+                            .map(|fault| OutputAction::ApplyResponseFault(subnet_id, *fault))
+                            .unwrap_or(OutputAction::Route),
+                        _ => OutputAction::Route,
+                    }
+                }
+                _ => OutputAction::Route,
+            };
+            if let OutputAction::Route = action {
+                // Requests in an output queue must be routed in order
+                // and thus we stop at the first message that is routed as usual.
+                output_iter.exclude_queue();
+                continue;
+            }
+            match (action, output_iter.next()) {
+                (OutputAction::Intercept, Some(RequestOrResponse::Request(request))) => {
+                    let call_id = call_interception.next_call_id;
+                    call_interception.next_call_id += 1;
+                    call_interception.intercepted_calls.insert(call_id, request);
+                }
+                (OutputAction::RejectRequest, Some(RequestOrResponse::Request(request))) => {
+                    rejected_requests.push(request);
+                }
+                (
+                    OutputAction::ApplyResponseFault(subnet_id, fault),
+                    Some(RequestOrResponse::Response(response)),
+                ) => match fault {
+                    XNetResponseFault::Drop => dropped_responses.push((subnet_id, response)),
+                    XNetResponseFault::Delay(rounds) => {
+                        fault_injection.delayed_responses.push(DelayedResponse {
+                            rounds,
+                            subnet_id,
+                            response,
+                        })
+                    }
+                },
+                _ => unreachable!("The message at the head of the output queue changed."),
+            }
+        }
+        drop(output_iter);
+
+        for request in rejected_requests {
+            let response = reject_response(
+                request.sender,
+                request.receiver,
+                request.sender_reply_callback,
+                request.payment,
+                request.deadline,
+                RejectCode::SysTransient,
+                format!("Canister {} input queue full", request.receiver),
+            );
+            let mut subnet_available_guaranteed_response_memory = i64::MAX;
+            // The caller is hosted on this subnet and has a slot reserved for the response.
+            let _ = state.push_input(
+                RequestOrResponse::Response(Arc::new(response)),
+                &mut subnet_available_guaranteed_response_memory,
+            );
+        }
+        for (subnet_id, response) in dropped_responses {
+            let reject_code = if response.deadline == NO_DEADLINE {
+                RejectCode::SysTransient
+            } else {
+                RejectCode::SysUnknown
+            };
+            let response = reject_response(
+                response.originator,
+                response.respondent,
+                response.originator_reply_callback,
+                response.refund,
+                response.deadline,
+                reject_code,
+                format!("Response from canister {} dropped", response.respondent),
+            );
+            push_to_stream(&mut state, subnet_id, Arc::new(response));
+        }
+
+        state
+    }
+}
+
 /// A replica node of the subnet with the corresponding `StateMachine`.
 pub struct StateMachineNode {
     pub node_id: NodeId,
@@ -854,6 +1212,9 @@ pub struct StateMachine {
     query_stats_payload_builder: Arc<PocketQueryStatsPayloadBuilderImpl>,
     vetkd_payload_builder: Arc<dyn BatchPayloadBuilder>,
     remove_old_states: bool,
+    call_interception: Arc<Mutex<CallInterception>>,
+    fault_injection: Arc<Mutex<FaultInjection>>,
+    execution_tracer: Arc<ExecutionTracer>,
     // This field must be the last one so that the temporary directory is deleted at the very end.
     state_dir: Box<dyn StateMachineStateDir>,
     // DO NOT PUT ANY FIELDS AFTER `state_dir`!!!
@@ -1297,7 +1658,12 @@ impl StateMachineBuilder {
 
         // Put `PocketXNetImpl` into `StateMachine`
         // which contains no `PocketXNetImpl` after creation.
-        let pocket_xnet_impl = PocketXNetImpl::new(subnets, certified_slice_pool, subnet_id);
+        let pocket_xnet_impl = PocketXNetImpl::new(
+            subnets,
+            certified_slice_pool,
+            subnet_id,
+            sm.fault_injection.clone(),
+        );
         *sm.pocket_xnet.write().unwrap() = Some(pocket_xnet_impl);
         // Instantiate a `PayloadBuilderImpl` and put it into `StateMachine`
         // which contains no `PayloadBuilderImpl` after creation.
@@ -1365,7 +1731,13 @@ impl StateMachine {
     /// and execute a round with this payload.
     /// Note that only ingress messages submitted via `Self::submit_ingress`
     /// will be considered during payload building.
+    /// No round is executed if this `StateMachine` is stalled
+    /// (see `Self::set_stalled`).
     pub fn do_execute_round(&self, blockmaker_metrics: Option<BlockmakerMetrics>) {
+        if self.fault_injection.lock().unwrap().stalled {
+            return;
+        }
+
         // Make sure the latest state is certified and fetch it from `StateManager`.
         self.certify_latest_state();
         let certified_height = self.state_manager.latest_certified_height();
@@ -1413,7 +1785,17 @@ impl StateMachine {
 
         // Convert payload produced by `PayloadBuilderImpl` into `PayloadBuilder`
         // used by the function `Self::execute_payload` of the `StateMachine`.
-        let xnet_payload = batch_payload.xnet.clone();
+        let mut xnet_payload = batch_payload.xnet.clone();
+        // Stream slices pooled before XNet message delivery got paused must not be inducted.
+        let paused_xnet_subnets = self
+            .fault_injection
+            .lock()
+            .unwrap()
+            .paused_xnet_subnets
+            .clone();
+        xnet_payload
+            .stream_slices
+            .retain(|subnet_id, _| !paused_xnet_subnets.contains(subnet_id));
         let ingress = &batch_payload.ingress;
         let ingress_messages = ingress.clone().try_into().unwrap();
         let (http_responses, _) =
@@ -1659,11 +2041,19 @@ impl StateMachine {
             )
         });
 
+        let call_interception = Arc::new(Mutex::new(CallInterception::default()));
+        let fault_injection = Arc::new(Mutex::new(FaultInjection::default()));
+        let scheduler = Box::new(PocketScheduler {
+            scheduler: execution_services.scheduler,
+            call_interception: call_interception.clone(),
+            fault_injection: fault_injection.clone(),
+        });
+
         let message_routing = SyncMessageRouting::new(
             Arc::clone(&state_manager) as _,
             Arc::clone(&state_manager) as _,
             Arc::clone(&execution_services.ingress_history_writer) as _,
-            execution_services.scheduler,
+            scheduler,
             hypervisor_config,
             cycles_account_manager.clone(),
             subnet_id,
@@ -1865,6 +2255,9 @@ impl StateMachine {
             query_stats_payload_builder: pocket_query_stats_payload_builder,
             vetkd_payload_builder,
             remove_old_states,
+            call_interception,
+            fault_injection,
+            execution_tracer: execution_services.execution_tracer,
         }
     }
 
@@ -3672,6 +4065,143 @@ impl StateMachine {
         );
     }
 
+    /// Returns the content of the specified canister snapshot or `None`
+    /// if the canister has no snapshot with the given ID.
+    pub fn canister_snapshot_data(
+        &self,
+        canister_id: CanisterId,
+        snapshot_id: SnapshotId,
+    ) -> Option<CanisterSnapshotData> {
+        let replicated_state = self.state_manager.get_latest_state().take();
+        let snapshot = replicated_state.canister_snapshots.get(snapshot_id)?;
+        if snapshot.canister_id() != canister_id {
+            return None;
+        }
+
+        fn page_memory_bytes(memory: &PageMemory) -> Vec<u8> {
+            let mut dst = vec![0u8; memory.size.get() * WASM_PAGE_SIZE_IN_BYTES];
+            let buffer = Buffer::new(memory.page_map.clone());
+            buffer.read(&mut dst, 0);
+            dst
+        }
+
+        let chunk_store = snapshot.chunk_store();
+        let wasm_chunk_store = chunk_store
+            .keys()
+            .map(|hash| {
+                let chunk: Vec<u8> = chunk_store
+                    .get_chunk_data(hash)
+                    .unwrap()
+                    .flat_map(|page| page.iter().copied())
+                    .collect();
+                serde_bytes::ByteBuf::from(chunk)
+            })
+            .collect();
+
+        Some(CanisterSnapshotData {
+            taken_at_timestamp: snapshot.taken_at_timestamp().as_nanos_since_unix_epoch(),
+            canister_version: snapshot.canister_version(),
+            wasm_module: snapshot.canister_module().as_slice().to_vec(),
+            exported_globals: snapshot.exported_globals().clone(),
+            wasm_memory: page_memory_bytes(snapshot.wasm_memory()),
+            stable_memory: page_memory_bytes(snapshot.stable_memory()),
+            certified_data: snapshot.certified_data().clone(),
+            wasm_chunk_store,
+        })
+    }
+
+    /// Creates a new snapshot of the specified canister with the given content
+    /// and returns its ID. The canister's own state is not affected.
+    ///
+    /// The snapshot content can be obtained from a (possibly different) canister
+    /// on a (possibly different) `StateMachine` using `canister_snapshot_data`.
+    pub fn upload_canister_snapshot_data(
+        &self,
+        canister_id: CanisterId,
+        data: CanisterSnapshotData,
+    ) -> Result<SnapshotId, CanisterSnapshotDataError> {
+        fn page_memory(bytes: &[u8]) -> Result<PageMemory, CanisterSnapshotDataError> {
+            if bytes.len() % WASM_PAGE_SIZE_IN_BYTES != 0 {
+                return Err(CanisterSnapshotDataError::InvalidSnapshot(format!(
+                    "Memory size {} is not a multiple of the Wasm page size {}.",
+                    bytes.len(),
+                    WASM_PAGE_SIZE_IN_BYTES
+                )));
+            }
+            Ok(PageMemory {
+                page_map: PageMap::from(bytes),
+                size: NumWasmPages::new(bytes.len() / WASM_PAGE_SIZE_IN_BYTES),
+            })
+        }
+
+        let wasm_memory = page_memory(&data.wasm_memory)?;
+        let stable_memory = page_memory(&data.stable_memory)?;
+        let mut chunk_store = WasmChunkStore::new(self.state_manager.get_fd_factory());
+        let wasm_chunk_store_max_size = HypervisorConfig::default().wasm_chunk_store_max_size;
+        for chunk in &data.wasm_chunk_store {
+            chunk_store
+                .insert_chunk(wasm_chunk_store_max_size, chunk)
+                .map_err(CanisterSnapshotDataError::InvalidSnapshot)?;
+        }
+        let size = NumBytes::from(
+            ((wasm_memory.size.get() + stable_memory.size.get()) * WASM_PAGE_SIZE_IN_BYTES
+                + data.exported_globals.len() * std::mem::size_of::<u64>()
+                + data.wasm_module.len()
+                + data.certified_data.len()) as u64,
+        ) + chunk_store.memory_usage();
+        // Like snapshots created from uploaded metadata, the snapshot does not
+        // start out as a backup of the files of the canister.
+        let source = SnapshotSource::MetadataUpload {
+            wasm_module_size: data.wasm_module.len() as u64,
+        };
+        let execution_snapshot = ExecutionStateSnapshot {
+            wasm_binary: CanisterModule::new(data.wasm_module),
+            exported_globals: data.exported_globals,
+            stable_memory,
+            wasm_memory,
+        };
+        let snapshot = CanisterSnapshot::new(
+            canister_id,
+            Time::from_nanos_since_unix_epoch(data.taken_at_timestamp),
+            data.canister_version,
+            data.certified_data,
+            chunk_store,
+            execution_snapshot,
+            size,
+            source,
+        );
+
+        let (height, mut replicated_state) = self.state_manager.take_tip();
+        let result = if replicated_state.canister_state(&canister_id).is_none() {
+            Err(CanisterSnapshotDataError::CanisterNotFound(canister_id))
+        } else if replicated_state
+            .canister_snapshots
+            .count_by_canister(&canister_id)
+            >= MAX_NUMBER_OF_SNAPSHOTS_PER_CANISTER
+        {
+            Err(CanisterSnapshotDataError::TooManySnapshots(canister_id))
+        } else {
+            let canister_state = replicated_state.canister_state_mut(&canister_id).unwrap();
+            let snapshot_id =
+                SnapshotId::from((canister_id, canister_state.new_local_snapshot_id()));
+            canister_state.system_state.snapshots_memory_usage = canister_state
+                .system_state
+                .snapshots_memory_usage
+                .saturating_add(&size);
+            replicated_state
+                .canister_snapshots
+                .push(snapshot_id, Arc::new(snapshot));
+            Ok(snapshot_id)
+        };
+        self.state_manager.commit_and_certify(
+            replicated_state,
+            height.increment(),
+            CertificationScope::Metadata,
+            None,
+        );
+        result
+    }
+
     /// Returns the query stats of the specified canister.
     ///
     /// # Panics
@@ -3723,6 +4253,52 @@ impl StateMachine {
             .get()
     }
 
+    /// Starts tracing the executions of canister messages (in replicated
+    /// and non-replicated mode) on this subnet. The traced executions
+    /// are kept outside of the replicated state and returned
+    /// by `Self::stop_execution_tracing`.
+    pub fn start_execution_tracing(&self) {
+        self.execution_tracer.start();
+    }
+
+    /// Stops tracing the executions of canister messages on this subnet
+    /// and returns the executions traced since `Self::start_execution_tracing`.
+    pub fn stop_execution_tracing(&self) -> Vec<TracedExecution> {
+        self.execution_tracer.stop()
+    }
+
+    /// Returns the execution cost metrics of all canisters on this subnet.
+    pub fn canister_cost_metrics(&self) -> BTreeMap<CanisterId, CanisterCostMetrics> {
+        let state = self.state_manager.get_latest_state().take();
+        state
+            .canisters_iter()
+            .map(|canister_state| {
+                let system_state = &canister_state.system_state;
+                let memory_size = |size: NumWasmPages| {
+                    NumBytes::from((size.get() * WASM_PAGE_SIZE_IN_BYTES) as u64)
+                };
+                let metrics = CanisterCostMetrics {
+                    consumed_cycles_by_use_cases: system_state
+                        .canister_metrics
+                        .get_consumed_cycles_by_use_cases()
+                        .clone(),
+                    cycles_balance: system_state.balance(),
+                    wasm_memory_size: canister_state
+                        .execution_state
+                        .as_ref()
+                        .map(|es| memory_size(es.wasm_memory.size))
+                        .unwrap_or_default(),
+                    stable_memory_size: canister_state
+                        .execution_state
+                        .as_ref()
+                        .map(|es| memory_size(es.stable_memory.size))
+                        .unwrap_or_default(),
+                };
+                (canister_state.canister_id(), metrics)
+            })
+            .collect()
+    }
+
     /// Tops up the specified canister with cycle amount and returns the resulting cycle balance.
     ///
     /// # Panics
@@ -3786,6 +4362,142 @@ impl StateMachine {
             .collect()
     }
 
+    /// Registers a call interceptor: requests from canisters on this subnet
+    /// to the specified receiver (and method, if provided) are not routed
+    /// to the receiver anymore, but can be retrieved using `Self::intercepted_calls`
+    /// and responded to using `Self::respond_to_intercepted_call`.
+    ///
+    /// Note that requests to canisters hosted on this subnet
+    /// (including the management canister) cannot be intercepted:
+    /// such requests are inducted by the scheduler within the round
+    /// in which they are made and thus never reach the output queues
+    /// inspected after the round. To intercept calls to a canister, make sure
+    /// that its canister ID is not routed to the subnet of the caller.
+    pub fn intercept_calls(&self, receiver: CanisterId, method_name: Option<String>) {
+        self.call_interception
+            .lock()
+            .unwrap()
+            .interceptors
+            .insert((receiver, method_name));
+    }
+
+    /// Removes a call interceptor registered using `Self::intercept_calls`.
+    /// Calls that have already been intercepted remain pending.
+    pub fn remove_call_interceptor(&self, receiver: CanisterId, method_name: Option<String>) {
+        self.call_interception
+            .lock()
+            .unwrap()
+            .interceptors
+            .remove(&(receiver, method_name));
+    }
+
+    /// Returns the intercepted calls that have not been responded to yet.
+    pub fn intercepted_calls(&self) -> Vec<InterceptedCall> {
+        self.call_interception
+            .lock()
+            .unwrap()
+            .intercepted_calls
+            .iter()
+            .map(|(call_id, request)| InterceptedCall {
+                call_id: *call_id,
+                request: request.clone(),
+            })
+            .collect()
+    }
+
+    /// Responds to an intercepted call with the given payload on behalf of its receiver
+    /// by inducting a response into the input queue of the caller.
+    /// The full payment attached to the call is refunded to the caller.
+    ///
+    /// The response is executed by the caller in a subsequent round.
+    pub fn respond_to_intercepted_call(
+        &self,
+        call_id: u64,
+        payload: MsgPayload,
+    ) -> Result<(), InterceptedCallError> {
+        let mut call_interception = self.call_interception.lock().unwrap();
+        // The call is only removed once the response has been inducted successfully
+        // so that a failed induction can be retried.
+        let request = call_interception
+            .intercepted_calls
+            .get(&call_id)
+            .cloned()
+            .ok_or(InterceptedCallError::CallNotFound(call_id))?;
+        let response = Response {
+            originator: request.sender,
+            respondent: request.receiver,
+            originator_reply_callback: request.sender_reply_callback,
+            refund: request.payment,
+            response_payload: payload,
+            deadline: request.deadline,
+        };
+
+        let (height, mut state) = self.state_manager.take_tip();
+        let mut subnet_available_guaranteed_response_memory = i64::MAX;
+        let result = state.push_input(
+            RequestOrResponse::Response(Arc::new(response)),
+            &mut subnet_available_guaranteed_response_memory,
+        );
+        self.state_manager.commit_and_certify(
+            state,
+            height.increment(),
+            CertificationScope::Metadata,
+            None,
+        );
+        match result {
+            Ok(_) => {
+                call_interception.intercepted_calls.remove(&call_id);
+                Ok(())
+            }
+            Err((err, _)) => Err(InterceptedCallError::InductionFailed(err.to_string())),
+        }
+    }
+
+    /// Pauses (`paused == true`) or resumes (`paused == false`) the induction
+    /// of XNet messages from the given subnet into this subnet.
+    /// While paused, messages from the given subnet remain in its outgoing stream.
+    pub fn set_xnet_paused(&self, from_subnet: SubnetId, paused: bool) {
+        let mut fault_injection = self.fault_injection.lock().unwrap();
+        if paused {
+            fault_injection.paused_xnet_subnets.insert(from_subnet);
+        } else {
+            fault_injection.paused_xnet_subnets.remove(&from_subnet);
+        }
+    }
+
+    /// Sets (or clears, if `fault` is `None`) a fault affecting responses
+    /// from canisters on this subnet to canisters on the given (different) subnet.
+    /// Clearing a fault does not release responses that are already delayed.
+    ///
+    /// Note that responses to canisters hosted on this subnet are not affected.
+    pub fn set_xnet_response_fault(&self, to_subnet: SubnetId, fault: Option<XNetResponseFault>) {
+        let mut fault_injection = self.fault_injection.lock().unwrap();
+        match fault {
+            Some(fault) => fault_injection.response_faults.insert(to_subnet, fault),
+            None => fault_injection.response_faults.remove(&to_subnet),
+        };
+    }
+
+    /// Stalls (`stalled == true`) or resumes (`stalled == false`) this subnet.
+    /// No rounds are executed by `Self::execute_round` while this subnet is stalled.
+    pub fn set_stalled(&self, stalled: bool) {
+        self.fault_injection.lock().unwrap().stalled = stalled;
+    }
+
+    /// Treats the input queues of the given canister as full (`full == true`) or not (`full == false`):
+    /// requests from canisters on this subnet to the given canister are rejected
+    /// with reject code `SYS_TRANSIENT` instead of being routed to the canister.
+    ///
+    /// Note that requests to canisters hosted on this subnet are not affected.
+    pub fn set_canister_queues_full(&self, canister_id: CanisterId, full: bool) {
+        let mut fault_injection = self.fault_injection.lock().unwrap();
+        if full {
+            fault_injection.full_queues.insert(canister_id);
+        } else {
+            fault_injection.full_queues.remove(&canister_id);
+        }
+    }
+
     /// Returns the size estimate of canisters heap delta in bytes.
     pub fn heap_delta_estimate_bytes(&self) -> u64 {
         let state = self.state_manager.get_latest_state().take();
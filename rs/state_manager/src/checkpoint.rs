@@ -4,6 +4,7 @@ use ic_logger::error;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::canister_snapshots::{
     CanisterSnapshot, CanisterSnapshots, ExecutionStateSnapshot, PageMemory, SnapshotOperation,
+    SnapshotSource,
 };
 use ic_replicated_state::canister_state::system_state::wasm_chunk_store::WasmChunkStore;
 use ic_replicated_state::page_map::{storage::validate, PageAllocatorFileDescriptor};
@@ -21,7 +22,8 @@ use ic_types::batch::RawQueryStats;
 use ic_types::{CanisterTimer, Height, Time};
 use ic_utils::thread::maybe_parallel_map;
 use ic_validate_eq::ValidateEq;
-use std::collections::BTreeMap;
+use ic_wasm_types::CanisterModule;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::{identity, TryFrom};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -375,28 +377,49 @@ pub(crate) fn flush_canister_snapshots_and_page_maps(
     // This way each operation is executed exactly once, independent of how many times `flush_page_maps` is called.
     let snapshot_operations = tip_state.canister_snapshots.take_unflushed_changes();
 
-    for op in &snapshot_operations {
-        // Only CanisterSnapshots that are new since the last flush will have PageMaps that need to be flushed. They will
-        // have a corresponding Backup in the snapshot operations list.
-        if let SnapshotOperation::Backup(_canister_id, snapshot_id) = op {
-            // If we can't find the CanisterSnapshot they must have been already deleted again. Nothing to flush in this case.
-            if let Some(canister_snapshot) = tip_state.canister_snapshots.get_mut(*snapshot_id) {
-                let new_snapshot = Arc::make_mut(canister_snapshot);
-
-                add_to_pagemaps_and_strip(
-                    PageMapType::SnapshotWasmChunkStore(*snapshot_id),
-                    new_snapshot.chunk_store_mut().page_map_mut(),
-                );
-                add_to_pagemaps_and_strip(
-                    PageMapType::SnapshotWasmMemory(*snapshot_id),
-                    &mut new_snapshot.execution_snapshot_mut().wasm_memory.page_map,
-                );
-                add_to_pagemaps_and_strip(
-                    PageMapType::SnapshotStableMemory(*snapshot_id),
-                    &mut new_snapshot.execution_snapshot_mut().stable_memory.page_map,
-                );
-            }
+    // CanisterSnapshots that are new since the last flush will have PageMaps that need to be flushed. They will
+    // have a corresponding Backup or UploadMetadata in the snapshot operations list. If we can't find the
+    // CanisterSnapshot they must have been already deleted again.
+    let new_snapshot_ids: BTreeSet<SnapshotId> = snapshot_operations
+        .iter()
+        .filter_map(|op| match op {
+            SnapshotOperation::Backup(_canister_id, snapshot_id)
+            | SnapshotOperation::UploadMetadata(snapshot_id) => Some(*snapshot_id),
+            SnapshotOperation::Delete(_) | SnapshotOperation::Restore(_, _) => None,
+        })
+        .collect();
+
+    for (snapshot_id, canister_snapshot) in tip_state.canister_snapshots.iter_mut() {
+        // Existing CanisterSnapshots only have PageMaps to flush if their data was uploaded since the last flush.
+        let has_unflushed_delta = !canister_snapshot
+            .chunk_store()
+            .page_map()
+            .unflushed_delta_is_empty()
+            || !canister_snapshot
+                .wasm_memory()
+                .page_map
+                .unflushed_delta_is_empty()
+            || !canister_snapshot
+                .stable_memory()
+                .page_map
+                .unflushed_delta_is_empty();
+        if !new_snapshot_ids.contains(snapshot_id) && !has_unflushed_delta {
+            continue;
         }
+        let new_snapshot = Arc::make_mut(canister_snapshot);
+
+        add_to_pagemaps_and_strip(
+            PageMapType::SnapshotWasmChunkStore(*snapshot_id),
+            new_snapshot.chunk_store_mut().page_map_mut(),
+        );
+        add_to_pagemaps_and_strip(
+            PageMapType::SnapshotWasmMemory(*snapshot_id),
+            &mut new_snapshot.execution_snapshot_mut().wasm_memory.page_map,
+        );
+        add_to_pagemaps_and_strip(
+            PageMapType::SnapshotStableMemory(*snapshot_id),
+            &mut new_snapshot.execution_snapshot_mut().stable_memory.page_map,
+        );
     }
 
     tip_channel
@@ -880,6 +903,7 @@ pub fn load_canister_state(
         canister_state_bits.log_visibility,
         canister_state_bits.canister_log,
         canister_state_bits.wasm_memory_limit,
+        canister_state_bits.environment_variables,
         canister_state_bits.next_snapshot_id,
         canister_state_bits.snapshots_memory_usage,
         metrics,
@@ -977,9 +1001,19 @@ pub fn load_snapshot(
         durations.insert("snapshot_stable_memory", starting_time.elapsed());
 
         let starting_time = Instant::now();
-        let wasm_binary = snapshot_layout
-            .wasm()
-            .deserialize(canister_snapshot_bits.binary_hash)?;
+        let wasm_binary = match canister_snapshot_bits.source {
+            // No slice of the wasm binary of this uploaded snapshot has been written yet.
+            SnapshotSource::MetadataUpload { .. }
+                if !snapshot_layout.wasm().raw_path().exists() =>
+            {
+                CanisterModule::new(vec![])
+            }
+            SnapshotSource::TakenFromCanister | SnapshotSource::MetadataUpload { .. } => {
+                snapshot_layout
+                    .wasm()
+                    .deserialize(canister_snapshot_bits.binary_hash)?
+            }
+        };
         durations.insert("snapshot_canister_module", starting_time.elapsed());
 
         let exported_globals = canister_snapshot_bits.exported_globals.clone();
@@ -1013,6 +1047,7 @@ pub fn load_snapshot(
         wasm_chunk_store,
         execution_snapshot,
         canister_snapshot_bits.total_size,
+        canister_snapshot_bits.source,
     );
 
     let metrics = LoadCanisterMetrics { durations };
@@ -254,6 +254,7 @@ impl CheckpointMetrics {
 #[derive(Clone)]
 pub struct MergeMetrics {
     disk_size_bytes: IntGauge,
+    physical_disk_size_bytes: IntGauge,
     memory_size_bytes: IntGauge,
     estimated_storage_savings_bytes: Histogram,
     num_page_maps_merged: HistogramVec,
@@ -266,6 +267,11 @@ impl MergeMetrics {
             "Number of bytes of on disk for all PageMaps, measured before merging.",
         );
 
+        let physical_disk_size_bytes = metrics_registry.int_gauge(
+            "state_manager_merge_physical_disk_size_bytes",
+            "Number of bytes actually used on disk for all PageMaps, i.e. with compressed overlays counted at their compressed size, measured before merging.",
+        );
+
         let memory_size_bytes = metrics_registry.int_gauge(
             "state_manager_merge_memory_size_bytes",
             "Number of bytes of memory for all PageMaps, not counting duplicate data in overlays, measured before merging.",
@@ -288,6 +294,7 @@ impl MergeMetrics {
 
         Self {
             disk_size_bytes,
+            physical_disk_size_bytes,
             memory_size_bytes,
             estimated_storage_savings_bytes,
             num_page_maps_merged,
@@ -24,8 +24,9 @@ use hash::{chunk_hasher, file_hasher, manifest_hasher, ManifestHash};
 use ic_crypto_sha2::Sha256;
 use ic_logger::{error, fatal, replica_logger::no_op_logger, ReplicaLogger};
 use ic_metrics::MetricsRegistry;
+use ic_replicated_state::page_map::{logical_file_size, LogicalFile};
 use ic_state_layout::{CheckpointLayout, ReadOnly, CANISTER_FILE, UNVERIFIED_CHECKPOINT_MARKER};
-use ic_sys::{mmap::ScopedMmap, PAGE_SIZE};
+use ic_sys::PAGE_SIZE;
 use ic_types::{crypto::CryptoHash, state_sync::StateSyncVersion, CryptoHashOfState, Height};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
@@ -341,7 +342,7 @@ fn build_chunk_table_parallel(
     // and close the corresponding file.
     // This way we keep the number of files opened at the same time
     // low (it doesn't exceed the number of the threads).
-    let file_cache: Arc<Mutex<HashMap<u32, Weak<ScopedMmap>>>> =
+    let file_cache: Arc<Mutex<HashMap<u32, Weak<LogicalFile>>>> =
         Arc::new(Mutex::new(HashMap::new()));
 
     // Compute real chunk hashes in parallel.
@@ -355,33 +356,35 @@ fn build_chunk_table_parallel(
             let file_cache = Arc::clone(&file_cache);
             scope.execute(move || {
                 let recompute_chunk_hash = || {
-                    let mmap: Arc<ScopedMmap> = if file_size > max_chunk_size as u64 {
+                    let file: Arc<LogicalFile> = if file_size > max_chunk_size as u64 {
                         // We only use the file cache if there is more than one chunk in the file,
                         // otherwise the synchronization cost is unnecessary.
                         let mut cache = file_cache.lock().unwrap();
                         match cache.get(&chunk_info.file_index).and_then(Weak::upgrade) {
-                            Some(mmap) => mmap,
+                            Some(file) => file,
                             None => {
-                                let mmap = Arc::new(
-                                    ScopedMmap::from_path(&file_path)
-                                        .unwrap_or_else(|e| fatal!(log, "failed to mmap file {}: {}", file_path.display(), e)),
+                                let file = Arc::new(
+                                    LogicalFile::open(&file_path)
+                                        .unwrap_or_else(|e| fatal!(log, "failed to open file {}: {}", file_path.display(), e)),
                                 );
-                                cache.insert(chunk_info.file_index, Arc::downgrade(&mmap));
-                                mmap
+                                cache.insert(chunk_info.file_index, Arc::downgrade(&file));
+                                file
                             }
                         }
                     } else {
                         Arc::new(
-                            ScopedMmap::from_path(&file_path)
-                                .unwrap_or_else(|e| fatal!(log, "failed to mmap file {}: {}", file_path.display(), e))
+                            LogicalFile::open(&file_path)
+                                .unwrap_or_else(|e| fatal!(log, "failed to open file {}: {}", file_path.display(), e))
                         )
                     };
-                    let data = mmap.as_slice();
 
                     let mut hasher = chunk_hasher();
                     let chunk_start = chunk_info.offset as usize;
                     let chunk_end = chunk_start + chunk_info.size_bytes as usize;
-                    hasher.write(&data[chunk_start..chunk_end]);
+                    let data = file
+                        .read(chunk_start..chunk_end)
+                        .unwrap_or_else(|e| fatal!(log, "failed to read file {}: {}", file_path.display(), e));
+                    hasher.write(&data);
                     hasher.finish()
                 };
 
@@ -534,9 +537,9 @@ fn build_chunk_table_sequential(
             });
         };
 
-        let mmap = ScopedMmap::from_path(root.join(&relative_path)).expect("failed to open file");
-        let data = mmap.as_slice();
-        compute_file_chunk_hashes(data);
+        let file = LogicalFile::open(&root.join(&relative_path)).expect("failed to open file");
+        let data = file.read(0..file.len()).expect("failed to read file");
+        compute_file_chunk_hashes(data.as_ref());
     }
 
     assert_eq!(chunk_table.len(), chunk_actions.len());
@@ -561,7 +564,14 @@ fn files_with_sizes(
         })?;
 
     if metadata.is_file() {
-        files.push(FileWithSize(relative_path, metadata.len()))
+        // Compressed overlays are hashed based on their logical content.
+        let size_bytes =
+            logical_file_size(&absolute_path).map_err(|io_err| CheckpointError::IoError {
+                path: absolute_path.clone(),
+                message: "failed to get logical file size".to_string(),
+                io_err: io_err.to_string(),
+            })?;
+        files.push(FileWithSize(relative_path, size_bytes))
     } else {
         assert!(
             metadata.is_dir(),
@@ -13,9 +13,9 @@ use crate::{
 };
 use ic_interfaces::p2p::state_sync::{AddChunkError, Chunk, ChunkId, Chunkable};
 use ic_logger::{debug, error, fatal, info, trace, warn, ReplicaLogger};
+use ic_replicated_state::page_map::LogicalFile;
 use ic_state_layout::utils::do_copy_overwrite;
 use ic_state_layout::{error::LayoutError, CheckpointLayout, ReadOnly, RwPolicy, StateLayout};
-use ic_sys::mmap::ScopedMmap;
 use ic_types::{malicious_flags::MaliciousFlags, CryptoHashOfState, Height};
 use std::os::unix::fs::FileExt;
 use std::path::{Path, PathBuf};
@@ -322,22 +322,11 @@ impl IncompleteState {
                                 err
                             )
                         });
-                        let src_len = src
-                            .metadata()
-                            .unwrap_or_else(|err| {
-                                fatal!(
-                                    log,
-                                    "Failed to get metadata of file {}: {}",
-                                    src_path.display(),
-                                    err
-                                )
-                            })
-                            .len() as usize;
-
-                        let src_map = ScopedMmap::from_readonly_file(&src, src_len).unwrap_or_else(|err| {
+                        // Chunks are validated and copied based on the logical content of the
+                        // file, i.e. compressed overlays are decompressed.
+                        let src_file = LogicalFile::open(&src_path).unwrap_or_else(|err| {
                             fatal!(log, "Failed to mmap file {}: {}", src_path.display(), err)
                         });
-                        let src_data = src_map.as_slice();
 
                         let old_chunk_range = crate::manifest::file_chunk_range(
                             &manifest_old.chunk_table,
@@ -359,7 +348,7 @@ impl IncompleteState {
                             let new_chunk_idx = new_chunk_range.start + chunk_offset;
                             let byte_range = chunk.byte_range();
 
-                            if src_data.len() < byte_range.end {
+                            if src_file.len() < byte_range.end {
                                 warn!(
                                     log,
                                     "Local chunk {} ({}@{}—{}) is out of range (file len = {}), \
@@ -368,7 +357,7 @@ impl IncompleteState {
                                     src_path.display(),
                                     byte_range.start,
                                     byte_range.end,
-                                    src_data.len(),
+                                    src_file.len(),
                                     new_chunk_idx + FILE_CHUNK_ID_OFFSET
                                 );
                                 bad_chunks.push(idx);
@@ -386,11 +375,16 @@ impl IncompleteState {
                                 continue;
                             }
 
-                            if let Err(err) = crate::manifest::validate_chunk(
-                                idx,
-                                &src_data[byte_range.clone()],
-                                manifest_old,
-                            ) {
+                            let validation_result = match src_file.read(byte_range.clone()) {
+                                Ok(src_data) => crate::manifest::validate_chunk(
+                                    idx,
+                                    &src_data,
+                                    manifest_old,
+                                )
+                                .map_err(|err| err.to_string()),
+                                Err(err) => Err(err.to_string()),
+                            };
+                            if let Err(err) = validation_result {
                                 warn!(
                                     log,
                                     "Local chunk {} ({}@{}–{}) doesn't pass validation: {}, \
@@ -419,7 +413,7 @@ impl IncompleteState {
                         }
 
                         if bad_chunks.is_empty()
-                            && src_data.len()
+                            && src_file.len()
                                 == manifest_old.file_table[*old_index].size_bytes as usize
                         {
                             // All the hash sums and the file size match, so we can
@@ -461,37 +455,19 @@ impl IncompleteState {
 
                                 let chunk = &manifest_old.chunk_table[idx];
 
-                                #[cfg(target_os = "linux")]
-                                {
-                                    // The source and the destination offsets are the same because we are copying
-                                    // over uncorrupted chunks of the file into the new checkpoint.
-                                    let src_offset = chunk.offset as i64;
-                                    let dst_offset = chunk.offset as i64;
-
-                                    ic_sys::fs::copy_file_range_all(
-                                        &src,
-                                        src_offset,
-                                        &dst,
-                                        dst_offset,
-                                        chunk.size_bytes as usize
-                                    ).unwrap_or_else(|err| {
+                                if src_file.is_compressed() || cfg!(not(target_os = "linux")) {
+                                    let data = src_file.read(chunk.byte_range()).unwrap_or_else(|err| {
                                         fatal!(
                                             log,
-                                            "Failed to copy file range from {} => {} (offset = {}, size = {}): {}",
-                                            src_path.display(),
-                                            dst_path.display(),
+                                            "Failed to read chunk (offset = {}, size = {}) from file {}: {}",
                                             chunk.offset,
                                             chunk.size_bytes,
+                                            src_path.display(),
                                             err
                                         )
                                     });
-                                }
-
-                                #[cfg(not(target_os = "linux"))]
-                                {
-                                    let data = &src_data[chunk.byte_range()];
 
-                                    dst.write_all_at(data, chunk.offset).unwrap_or_else(|err| {
+                                    dst.write_all_at(&data, chunk.offset).unwrap_or_else(|err| {
                                         fatal!(
                                             log,
                                             "Failed to write chunk (offset = {}, size = {}) to file {}: {}",
@@ -501,6 +477,32 @@ impl IncompleteState {
                                             err
                                         )
                                     });
+                                } else {
+                                    #[cfg(target_os = "linux")]
+                                    {
+                                        // The source and the destination offsets are the same because we are copying
+                                        // over uncorrupted chunks of the file into the new checkpoint.
+                                        let src_offset = chunk.offset as i64;
+                                        let dst_offset = chunk.offset as i64;
+
+                                        ic_sys::fs::copy_file_range_all(
+                                            &src,
+                                            src_offset,
+                                            &dst,
+                                            dst_offset,
+                                            chunk.size_bytes as usize
+                                        ).unwrap_or_else(|err| {
+                                            fatal!(
+                                                log,
+                                                "Failed to copy file range from {} => {} (offset = {}, size = {}): {}",
+                                                src_path.display(),
+                                                dst_path.display(),
+                                                chunk.offset,
+                                                chunk.size_bytes,
+                                                err
+                                            )
+                                        });
+                                    }
                                 }
                                 metrics.remaining.sub(1);
                             }
@@ -603,17 +605,6 @@ impl IncompleteState {
                         )
                     });
 
-                    let src_len = src
-                        .metadata()
-                        .unwrap_or_else(|err| {
-                            fatal!(
-                                log,
-                                "Failed to get metadata of file {}: {}",
-                                src_path.display(),
-                                err
-                            )
-                        })
-                        .len() as usize;
                     let dst = std::fs::OpenOptions::new()
                         .write(true)
                         .create(false)
@@ -622,7 +613,9 @@ impl IncompleteState {
                             fatal!(log, "Failed to open file {}: {}", dst_path.display(), err)
                         });
 
-                    let src_map = ScopedMmap::from_readonly_file(&src, src_len).unwrap_or_else(|err| {
+                    // Chunks are validated and copied based on the logical content of the file,
+                    // i.e. compressed overlays are decompressed.
+                    let src_file = LogicalFile::open(&src_path).unwrap_or_else(|err| {
                         fatal!(log, "Failed to mmap file {}: {}", src_path.display(), err)
                     });
 
@@ -634,7 +627,7 @@ impl IncompleteState {
                         let src_chunk = &manifest_old.chunk_table[*src_chunk_index];
                         let byte_range = src_chunk.byte_range();
 
-                        if src_map.len() < byte_range.end {
+                        if src_file.len() < byte_range.end {
                             warn!(
                                 log,
                                 "Local chunk {} ({}@{}—{}) is out of range (file len = {}), \
@@ -643,21 +636,37 @@ impl IncompleteState {
                                 src_path.display(),
                                 byte_range.start,
                                 byte_range.end,
-                                src_map.len(),
+                                src_file.len(),
                                 *dst_chunk_index + FILE_CHUNK_ID_OFFSET
                             );
                             corrupted_chunks.lock().unwrap().push(*dst_chunk_index + FILE_CHUNK_ID_OFFSET);
                             continue;
                         }
-                        #[cfg(not(target_os = "linux"))]
-                        let src_data = &src_map.as_slice()[byte_range];
+                        let use_copy_file_range =
+                            cfg!(target_os = "linux") && !src_file.is_compressed();
+                        let src_data = if validate_data || ALWAYS_VALIDATE || !use_copy_file_range {
+                            match src_file.read(byte_range) {
+                                Ok(src_data) => Some(src_data),
+                                Err(err) => {
+                                    warn!(
+                                        log,
+                                        "Failed to read local chunk {} ({}): {}, will request chunk {} instead",
+                                        *src_chunk_index,
+                                        src_path.display(),
+                                        err,
+                                        *dst_chunk_index + FILE_CHUNK_ID_OFFSET
+                                    );
+                                    corrupted_chunks.lock().unwrap().push(*dst_chunk_index + FILE_CHUNK_ID_OFFSET);
+                                    continue;
+                                }
+                            }
+                        } else {
+                            None
+                        };
                         if validate_data || ALWAYS_VALIDATE {
-                            #[cfg(target_os = "linux")]
-                            let src_data = &src_map.as_slice()[byte_range];
-
                             if let Err(err) = crate::manifest::validate_chunk(
                                 *dst_chunk_index,
-                                src_data,
+                                src_data.as_deref().unwrap(),
                                 manifest_new,
                             ) {
                                 let byte_range = src_chunk.byte_range();
@@ -690,34 +699,33 @@ impl IncompleteState {
                                 continue;
                             }
                         }
-                        #[cfg(target_os = "linux")]
-                        {
-                            let src_offset = src_chunk.offset as i64;
-                            let dst_offset = dst_chunk.offset as i64;
-
-                            ic_sys::fs::copy_file_range_all(
-                                &src,
-                                src_offset,
-                                &dst,
-                                dst_offset,
-                                dst_chunk.size_bytes as usize,
-                            )
-                                .unwrap_or_else(|err| {
-                                    fatal!(
-                                        log,
-                                        "Failed to copy file range from {} => {} (offset = {}, size = {}): {}",
-                                        src_path.display(),
-                                        dst_path.display(),
-                                        dst_chunk.offset,
-                                        dst_chunk.size_bytes,
-                                        err
-                                    )
-                                });
-                        }
-
-                        #[cfg(not(target_os = "linux"))]
-                        {
-                            dst.write_all_at(src_data, dst_chunk.offset)
+                        if use_copy_file_range {
+                            #[cfg(target_os = "linux")]
+                            {
+                                let src_offset = src_chunk.offset as i64;
+                                let dst_offset = dst_chunk.offset as i64;
+
+                                ic_sys::fs::copy_file_range_all(
+                                    &src,
+                                    src_offset,
+                                    &dst,
+                                    dst_offset,
+                                    dst_chunk.size_bytes as usize,
+                                )
+                                    .unwrap_or_else(|err| {
+                                        fatal!(
+                                            log,
+                                            "Failed to copy file range from {} => {} (offset = {}, size = {}): {}",
+                                            src_path.display(),
+                                            dst_path.display(),
+                                            dst_chunk.offset,
+                                            dst_chunk.size_bytes,
+                                            err
+                                        )
+                                    });
+                            }
+                        } else {
+                            dst.write_all_at(src_data.as_deref().unwrap(), dst_chunk.offset)
                                 .unwrap_or_else(|err| {
                                     fatal!(
                                         log,
@@ -477,17 +477,19 @@ impl StateSyncMessage {
 
         #[cfg(target_family = "unix")]
         {
-            use std::os::unix::fs::FileExt;
+            use ic_replicated_state::page_map::LogicalFile;
 
             let get_single_chunk = |chunk_index: usize| -> Option<Vec<u8>> {
                 let chunk = self.manifest.chunk_table.get(chunk_index).cloned()?;
                 let path = self
                     .checkpoint_root
                     .join(&self.manifest.file_table[chunk.file_index as usize].relative_path);
-                let mut buf = vec![0; chunk.size_bytes as usize];
-                let f = std::fs::File::open(path).ok()?;
-                f.read_exact_at(&mut buf[..], chunk.offset).ok()?;
-                Some(buf)
+                // Chunks are served from the logical content, i.e. compressed overlays are
+                // decompressed.
+                let f = LogicalFile::open(&path).ok()?;
+                let start = chunk.offset as usize;
+                let buf = f.read(start..(start + chunk.size_bytes as usize)).ok()?;
+                Some(buf.into_owned())
             };
 
             let mut payload: Vec<u8> = Vec::new();
@@ -19,7 +19,9 @@ use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::page_map::PageAllocatorFileDescriptor;
 use ic_replicated_state::{
     canister_snapshots::{CanisterSnapshot, SnapshotOperation},
-    page_map::{MergeCandidate, StorageMetrics, StorageResult, MAX_NUMBER_OF_FILES},
+    page_map::{
+        CompressionCandidate, MergeCandidate, StorageMetrics, StorageResult, MAX_NUMBER_OF_FILES,
+    },
 };
 use ic_replicated_state::{
     page_map::{StorageLayout, PAGE_SIZE},
@@ -42,6 +44,11 @@ use std::time::Instant;
 /// there are 2 overlays created each checkpoint.
 const NUMBER_OF_FILES_HARD_LIMIT: usize = MAX_NUMBER_OF_FILES + 8;
 
+/// Maximum size of the overlays to compress at each checkpoint, so that compressing a large
+/// number of cold shards at once does not delay the checkpoint. The remaining cold overlays are
+/// compressed at subsequent checkpoints.
+const MAX_COMPRESSION_BYTES_PER_CHECKPOINT: u64 = 1 << 30;
+
 #[derive(Clone, Debug, Default)]
 struct CheckpointState {
     // Latest height of the pagemaps update; Height(0) is always present as the default state.
@@ -350,6 +357,15 @@ pub(crate) fn spawn_tip_thread(
                                 &lsmt_config,
                                 &metrics,
                             );
+                            compress_cold_page_maps(
+                                &mut tip_handler,
+                                &pagemaptypes,
+                                height,
+                                &mut thread_pool,
+                                &log,
+                                &lsmt_config,
+                                &metrics,
+                            );
                         }
 
                         TipRequest::Wait { sender } => {
@@ -441,6 +457,9 @@ fn flush_snapshot_changes<T>(
             SnapshotOperation::Restore(canister_id, snapshot_id) => {
                 restore(log, layout, canister_id, snapshot_id)?;
             }
+            SnapshotOperation::UploadMetadata(snapshot_id) => {
+                create_empty_snapshot(layout, snapshot_id)?;
+            }
         }
     }
 
@@ -484,6 +503,24 @@ fn backup<T>(
     Ok(())
 }
 
+/// Represent the creation of a snapshot from uploaded metadata on disk.
+/// Unlike for a backup, the `PageMaps` of such a `CanisterSnapshot` start out empty rather than as copies of the canister's,
+/// so the snapshot directory is created without any files in it. The uploaded data is later flushed as part of
+/// `FlushPageMapDelta` and the wasm binary is written at the next checkpoint.
+fn create_empty_snapshot<T>(
+    layout: &CheckpointLayout<RwPolicy<T>>,
+    snapshot_id: SnapshotId,
+) -> Result<(), LayoutError> {
+    let snapshot_layout = layout.snapshot(&snapshot_id)?;
+
+    snapshot_layout.vmemory_0().delete_files()?;
+    snapshot_layout.stable_memory().delete_files()?;
+    snapshot_layout.wasm_chunk_store().delete_files()?;
+    snapshot_layout.wasm().try_delete_file()?;
+
+    Ok(())
+}
+
 /// Represent a restore operation on disk.
 /// When a restore is triggered, execution creates a `CanisterState` from a `CanisterSnapshot` by copying all its `PageMaps` as well as its wasm binary.
 /// This function will run at an unspecified point afterwards (but before the next checkpoint) and it copies all files the snapshot had in the tip
@@ -524,6 +561,7 @@ fn restore<T>(
 
 struct StorageInfo {
     disk_size: u64,
+    physical_disk_size: u64,
     mem_size: u64,
 }
 
@@ -531,6 +569,7 @@ impl StorageInfo {
     fn add(&self, rhs: &StorageInfo) -> StorageInfo {
         StorageInfo {
             disk_size: self.disk_size + rhs.disk_size,
+            physical_disk_size: self.physical_disk_size + rhs.physical_disk_size,
             mem_size: self.mem_size + rhs.mem_size,
         }
     }
@@ -555,6 +594,7 @@ fn merge_candidates_and_storage_info(
             |page_map_type| -> StorageResult<(Vec<MergeCandidate>, StorageInfo)> {
                 let mut storage_info = StorageInfo {
                     disk_size: 0,
+                    physical_disk_size: 0,
                     mem_size: 0,
                 };
                 let pm_layout = page_map_type
@@ -562,6 +602,8 @@ fn merge_candidates_and_storage_info(
                     .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send>)?;
                 storage_info.disk_size +=
                     (&pm_layout as &dyn StorageLayout).storage_size_bytes()?;
+                storage_info.physical_disk_size +=
+                    (&pm_layout as &dyn StorageLayout).physical_storage_size_bytes()?;
                 let num_pages = (&pm_layout as &dyn StorageLayout).memory_size_pages()?;
                 storage_info.mem_size += (num_pages * PAGE_SIZE) as u64;
                 Ok((
@@ -579,6 +621,7 @@ fn merge_candidates_and_storage_info(
     let mut merge_candidates = Vec::new();
     let mut storage_info = StorageInfo {
         disk_size: 0,
+        physical_disk_size: 0,
         mem_size: 0,
     };
     for merge_candidate_with_storage_info in merge_candidates_with_storage_info.into_iter() {
@@ -738,6 +781,10 @@ fn merge(
         .merge_metrics
         .disk_size_bytes
         .set(storage_info.disk_size as i64);
+    metrics
+        .merge_metrics
+        .physical_disk_size_bytes
+        .set(storage_info.physical_disk_size as i64);
     metrics
         .merge_metrics
         .memory_size_bytes
@@ -762,6 +809,78 @@ fn merge(
     });
 }
 
+/// Compress the overlays of shards that have not been written to for
+/// `lsmt_config.compress_cold_shards_after_heights` heights.
+///
+/// Compression only changes how overlays are stored on disk, not their logical content, so unlike
+/// `merge` it does not affect the manifest and does not need to be deterministic.
+///
+/// The coldest overlays are compressed first, until `MAX_COMPRESSION_BYTES_PER_CHECKPOINT` is
+/// reached, so at most `MAX_COMPRESSION_BYTES_PER_CHECKPOINT` plus the size of one overlay is
+/// compressed per checkpoint.
+fn compress_cold_page_maps(
+    tip_handler: &mut TipHandler,
+    pagemaptypes: &[PageMapType],
+    height: Height,
+    thread_pool: &mut scoped_threadpool::Pool,
+    log: &ReplicaLogger,
+    lsmt_config: &LsmtConfig,
+    metrics: &StateManagerMetrics,
+) {
+    if lsmt_config.compress_cold_shards_after_heights.is_none() {
+        return;
+    }
+    let _timer = request_timer(metrics, "compress_cold_page_maps");
+    let layout = &tip_handler.tip(height).unwrap_or_else(|err| {
+        fatal!(log, "Failed to get tip to compress cold PageMaps: {}", err);
+    });
+    let candidates_by_page_map = parallel_map(thread_pool, pagemaptypes.iter(), |page_map_type| {
+        let pm_layout = page_map_type
+            .layout(layout)
+            .map_err(|err| Box::new(err) as Box<dyn std::error::Error + Send>)?;
+        CompressionCandidate::new(&pm_layout, height, lsmt_config)
+    });
+    let mut compression_candidates = Vec::new();
+    for candidates in candidates_by_page_map.into_iter() {
+        compression_candidates.append(&mut candidates.unwrap_or_else(|err| {
+            fatal!(log, "Failed to get CompressionCandidates: {}", err);
+        }));
+    }
+
+    compression_candidates.sort_by_key(|c| c.last_written());
+    let num_candidates = compression_candidates.len();
+    let scheduled_compressions: Vec<_> = compression_candidates
+        .into_iter()
+        .scan(0, |state, c| {
+            if *state >= MAX_COMPRESSION_BYTES_PER_CHECKPOINT {
+                None
+            } else {
+                *state += c.size_bytes();
+                Some(c)
+            }
+        })
+        .collect();
+    info!(
+        log,
+        "Compressing {} overlays out of {}; size: {}",
+        scheduled_compressions.len(),
+        num_candidates,
+        scheduled_compressions
+            .iter()
+            .map(|c| c.size_bytes())
+            .sum::<u64>(),
+    );
+
+    let results = parallel_map(thread_pool, scheduled_compressions.iter(), |c| {
+        c.apply(&metrics.storage_metrics)
+    });
+    for result in results.into_iter() {
+        result.unwrap_or_else(|err| {
+            fatal!(log, "Failed to compress cold PageMaps: {}", err);
+        });
+    }
+}
+
 fn serialize_to_tip(
     log: &ReplicaLogger,
     state: &ReplicatedState,
@@ -981,6 +1100,7 @@ fn serialize_canister_to_tip(
             log_visibility: canister_state.system_state.log_visibility.clone(),
             canister_log: canister_state.system_state.canister_log.clone(),
             wasm_memory_limit: canister_state.system_state.wasm_memory_limit,
+            environment_variables: canister_state.system_state.environment_variables.clone(),
             next_snapshot_id: canister_state.system_state.next_snapshot_id,
             snapshots_memory_usage: canister_state.system_state.snapshots_memory_usage,
         }
@@ -1013,13 +1133,18 @@ fn serialize_snapshot_to_tip(
             wasm_memory_size: canister_snapshot.wasm_memory().size,
             total_size: canister_snapshot.size(),
             exported_globals: canister_snapshot.exported_globals().clone(),
+            source: canister_snapshot.source(),
         }
         .into(),
     )?;
 
     // Like for canisters, the wasm binary is either already present on disk, or it is new and needs to be written.
+    // The wasm binary of an uploaded snapshot is empty until its first slice is uploaded, and an empty file cannot
+    // be loaded, so no file is written in that case.
     let wasm_binary = canister_snapshot.canister_module();
-    if wasm_binary.file().is_none() {
+    if wasm_binary.is_empty() {
+        snapshot_layout.wasm().try_delete_file()?;
+    } else if wasm_binary.file().is_none() {
         snapshot_layout.wasm().serialize(wasm_binary)?;
     } else {
         // During `flush_page_maps` we created copied this file from the canister directory.
@@ -556,10 +556,12 @@ fn state_manager_test_with_state_sync_and_verifier_result<
     F: FnOnce(&MetricsRegistry, Arc<StateManagerImpl>, StateSync),
 >(
     should_pass_verification: bool,
+    lsmt_config: LsmtConfig,
     f: F,
 ) {
     let tmp = tmpdir("sm");
-    let config = Config::new(tmp.path().into());
+    let mut config = Config::new(tmp.path().into());
+    config.lsmt_config = lsmt_config;
     let metrics_registry = MetricsRegistry::new();
     let own_subnet = subnet_test_id(42);
     let verifier: Arc<dyn Verifier> = if should_pass_verification {
@@ -636,7 +638,16 @@ pub fn state_manager_test_with_state_sync<
 >(
     f: F,
 ) {
-    state_manager_test_with_state_sync_and_verifier_result(true, f)
+    state_manager_test_with_state_sync_and_verifier_result(true, lsmt_config_default(), f)
+}
+
+pub fn state_manager_test_with_state_sync_and_lsmt<
+    F: FnOnce(&MetricsRegistry, Arc<StateManagerImpl>, StateSync),
+>(
+    lsmt_config: LsmtConfig,
+    f: F,
+) {
+    state_manager_test_with_state_sync_and_verifier_result(true, lsmt_config, f)
 }
 
 pub fn state_manager_restart_test_deleting_metadata<Test>(test: Test)
@@ -683,12 +694,16 @@ where
 }
 
 pub fn lsmt_with_sharding() -> LsmtConfig {
-    LsmtConfig { shard_num_pages: 1 }
+    LsmtConfig {
+        shard_num_pages: 1,
+        compress_cold_shards_after_heights: None,
+    }
 }
 
 pub fn lsmt_without_sharding() -> LsmtConfig {
     LsmtConfig {
         shard_num_pages: u64::MAX,
+        compress_cold_shards_after_heights: None,
     }
 }
 
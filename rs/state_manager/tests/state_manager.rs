@@ -1,6 +1,6 @@
 use assert_matches::assert_matches;
 use ic_base_types::SnapshotId;
-use ic_config::state_manager::{lsmt_config_default, Config};
+use ic_config::state_manager::{lsmt_config_default, Config, LsmtConfig};
 use ic_crypto_tree_hash::{
     flatmap, sparse_labeled_tree_from_paths, Label, LabeledTree, LookupStatus, MixedHashTree,
     Path as LabelPath,
@@ -18,10 +18,10 @@ use ic_metrics::MetricsRegistry;
 use ic_registry_subnet_features::SubnetFeatures;
 use ic_registry_subnet_type::SubnetType;
 use ic_replicated_state::{
-    canister_snapshots::CanisterSnapshot,
+    canister_snapshots::{CanisterSnapshot, ExecutionStateSnapshot, PageMemory, SnapshotSource},
     canister_state::{execution_state::WasmBinary, system_state::wasm_chunk_store::WasmChunkStore},
     metadata_state::ApiBoundaryNodeEntry,
-    page_map::{PageIndex, Shard, StorageLayout},
+    page_map::{LogicalFile, PageIndex, Shard, StorageLayout},
     testing::ReplicatedStateTesting,
     ExecutionState, ExportedFunctions, Memory, NetworkTopology, NumWasmPages, PageMap,
     ReplicatedState, Stream, SubnetTopology,
@@ -64,6 +64,7 @@ use ic_types::{
     CanisterId, CryptoHashOfPartialState, CryptoHashOfState, Height, NodeId, NumBytes, PrincipalId,
 };
 use ic_types::{epoch_from_height, QueryStatsEpoch};
+use ic_wasm_types::CanisterModule;
 use maplit::{btreemap, btreeset};
 use nix::sys::time::TimeValLike;
 use nix::sys::{
@@ -2380,6 +2381,100 @@ fn can_do_simple_state_sync_transfer() {
     })
 }
 
+#[test]
+fn can_state_sync_compressed_overlays() {
+    // Writes to the memory of a canister at height 1 only, so that its overlays are compressed
+    // after the checkpoint at height 2 and the checkpoint at height 3 contains them compressed.
+    fn populate_state(state_manager: &StateManagerImpl) -> CryptoHashOfState {
+        let (_height, mut state) = state_manager.take_tip();
+        insert_dummy_canister(&mut state, canister_test_id(100));
+        let canister_state = state.canister_state_mut(&canister_test_id(100)).unwrap();
+        let execution_state = canister_state.execution_state.as_mut().unwrap();
+        execution_state.wasm_memory.page_map.update(&[
+            (PageIndex::new(1), &[99u8; PAGE_SIZE]),
+            (PageIndex::new(300), &[100u8; PAGE_SIZE]),
+        ]);
+        state_manager.commit_and_certify(state, height(1), CertificationScope::Full, None);
+
+        for h in 2..=3 {
+            let (_height, state) = state_manager.take_tip();
+            state_manager.commit_and_certify(state, height(h), CertificationScope::Full, None);
+        }
+        wait_for_checkpoint(state_manager, height(3))
+    }
+
+    let mut uncompressed_hash = None;
+    state_manager_test(|_metrics, state_manager| {
+        uncompressed_hash = Some(populate_state(&state_manager));
+    });
+
+    state_manager_test_with_state_sync_and_lsmt(
+        LsmtConfig {
+            shard_num_pages: u64::MAX,
+            compress_cold_shards_after_heights: Some(1),
+        },
+        |src_metrics, src_state_manager, src_state_sync| {
+            let hash = populate_state(&src_state_manager);
+            // The manifest is computed from the logical content of the compressed overlays.
+            assert_eq!(Some(&hash), uncompressed_hash.as_ref());
+
+            let overlays = src_state_manager
+                .state_layout()
+                .checkpoint_verified(height(3))
+                .unwrap()
+                .canister(&canister_test_id(100))
+                .unwrap()
+                .vmemory_0()
+                .existing_overlays()
+                .unwrap();
+            assert!(!overlays.is_empty());
+            for overlay in overlays.iter() {
+                assert!(LogicalFile::open(overlay).unwrap().is_compressed());
+            }
+
+            let id = StateSyncArtifactId {
+                height: height(3),
+                hash: hash.get(),
+            };
+            let state = src_state_manager.get_latest_state().take();
+            let msg = src_state_sync
+                .get(&id)
+                .expect("failed to get state sync messages");
+            // Serving the state sync leaves the overlays compressed.
+            for overlay in overlays.iter() {
+                assert!(LogicalFile::open(overlay).unwrap().is_compressed());
+            }
+            assert_error_counters(src_metrics);
+
+            state_manager_test_with_state_sync(|dst_metrics, dst_state_manager, dst_state_sync| {
+                let chunkable =
+                    set_fetch_state_and_start_start_sync(&dst_state_manager, &dst_state_sync, &id);
+
+                pipe_state_sync(msg, chunkable);
+
+                let recovered_state = dst_state_manager
+                    .get_state_at(height(3))
+                    .expect("Destination state manager didn't receive the state")
+                    .take();
+                assert_eq!(state, recovered_state);
+                let page_map = &recovered_state
+                    .canister_state(&canister_test_id(100))
+                    .unwrap()
+                    .execution_state
+                    .as_ref()
+                    .unwrap()
+                    .wasm_memory
+                    .page_map;
+                assert_eq!(page_map.get_page(PageIndex::new(1)), &[99u8; PAGE_SIZE]);
+                assert_eq!(page_map.get_page(PageIndex::new(300)), &[100u8; PAGE_SIZE]);
+
+                assert_error_counters(dst_metrics);
+                assert_no_remaining_chunks(dst_metrics);
+            })
+        },
+    );
+}
+
 #[test]
 fn test_start_and_cancel_state_sync() {
     state_manager_test_with_state_sync(|src_metrics, src_state_manager, src_state_sync| {
@@ -6123,6 +6218,102 @@ fn can_create_and_restore_snapshot() {
     can_create_and_restore_snapshot_impl(CertificationScope::Full);
 }
 
+#[test]
+fn uploaded_snapshot_starts_out_with_empty_files() {
+    fn uploaded_snapshot_starts_out_with_empty_files_impl(certification_scope: CertificationScope) {
+        state_manager_test(|metrics, state_manager| {
+            let canister_id = canister_test_id(100);
+
+            // Install a canister and give it some initial state
+            let (_height, mut state) = state_manager.take_tip();
+            insert_dummy_canister(&mut state, canister_id);
+            let canister_state = state.canister_state_mut(&canister_id).unwrap();
+            let execution_state = canister_state.execution_state.as_mut().unwrap();
+            execution_state
+                .wasm_memory
+                .page_map
+                .update(&[(PageIndex::new(0), &[1u8; PAGE_SIZE])]);
+            execution_state
+                .stable_memory
+                .page_map
+                .update(&[(PageIndex::new(0), &[2u8; PAGE_SIZE])]);
+            state_manager.commit_and_certify(state, height(1), CertificationScope::Full, None);
+
+            // Create a snapshot from uploaded metadata and only write its second pages
+            // and a slice of its wasm module.
+            let (_height, mut state) = state_manager.take_tip();
+            let mut wasm_binary = CanisterModule::new(vec![]);
+            wasm_binary.write(&[3u8; 3], 2);
+            let mut wasm_memory = PageMap::new_for_testing();
+            wasm_memory.update(&[(PageIndex::new(1), &[4u8; PAGE_SIZE])]);
+            let mut stable_memory = PageMap::new_for_testing();
+            stable_memory.update(&[(PageIndex::new(1), &[5u8; PAGE_SIZE])]);
+            let execution_snapshot = ExecutionStateSnapshot {
+                wasm_binary,
+                exported_globals: vec![],
+                stable_memory: PageMemory {
+                    page_map: stable_memory,
+                    size: NumWasmPages::new(1),
+                },
+                wasm_memory: PageMemory {
+                    page_map: wasm_memory,
+                    size: NumWasmPages::new(1),
+                },
+            };
+            let new_snapshot = CanisterSnapshot::new(
+                canister_id,
+                state.time(),
+                0,
+                vec![],
+                WasmChunkStore::new_for_testing(),
+                execution_snapshot,
+                NumBytes::from(0),
+                SnapshotSource::MetadataUpload {
+                    wasm_module_size: 10,
+                },
+            );
+            let snapshot_id = SnapshotId::from((canister_id, 0));
+            state
+                .canister_snapshots
+                .push(snapshot_id, Arc::new(new_snapshot));
+
+            // Verify that none of the data of the canister leaks into the snapshot
+            // across a couple of checkpoints.
+            let verify_state = |state: &ReplicatedState| {
+                let snapshot = state.canister_snapshots.get(snapshot_id).unwrap();
+                assert_eq!(
+                    snapshot.source(),
+                    SnapshotSource::MetadataUpload {
+                        wasm_module_size: 10
+                    }
+                );
+                assert_eq!(snapshot.canister_module().as_slice(), &[0, 0, 3, 3, 3]);
+                for (page_map, value) in [
+                    (&snapshot.wasm_memory().page_map, 4u8),
+                    (&snapshot.stable_memory().page_map, 5u8),
+                ] {
+                    assert_eq!(page_map.get_page(PageIndex::new(0)), &[0u8; PAGE_SIZE]);
+                    assert_eq!(page_map.get_page(PageIndex::new(1)), &[value; PAGE_SIZE]);
+                }
+            };
+
+            verify_state(&state);
+            state_manager.commit_and_certify(state, height(2), certification_scope.clone(), None);
+
+            for h in 3..6 {
+                let (_height, state) = state_manager.take_tip();
+                verify_state(&state);
+                state_manager.commit_and_certify(state, height(h), CertificationScope::Full, None);
+            }
+
+            assert_error_counters(metrics);
+        });
+    }
+
+    uploaded_snapshot_starts_out_with_empty_files_impl(CertificationScope::Metadata);
+    uploaded_snapshot_starts_out_with_empty_files_impl(CertificationScope::Full);
+}
+
 #[test]
 fn restore_heap_from_snapshot() {
     let env = StateMachineBuilder::new().build();
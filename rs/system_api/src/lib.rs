@@ -28,8 +28,9 @@ use ic_types::{
     ingress::WasmResult,
     messages::{CallContextId, RejectContext, Request, MAX_INTER_CANISTER_PAYLOAD_IN_BYTES},
     methods::{SystemMethod, WasmClosure},
-    CanisterId, CanisterLog, CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumBytes,
-    NumInstructions, NumOsPages, PrincipalId, SubnetId, Time, MAX_STABLE_MEMORY_IN_BYTES,
+    CanisterId, CanisterLog, CanisterTimer, ComputeAllocation, Cycles, EnvironmentVariables,
+    MemoryAllocation, NumBytes, NumInstructions, NumOsPages, PrincipalId, SubnetId, Time,
+    MAX_STABLE_MEMORY_IN_BYTES,
 };
 use ic_utils::deterministic_operations::deterministic_copy_from_slice;
 use ic_wasm_types::doc_ref;
@@ -44,6 +45,7 @@ use std::{
     convert::{From, TryFrom},
     rc::Rc,
     str,
+    time::Duration,
 };
 
 pub mod cycles_balance_change;
@@ -55,7 +57,7 @@ mod stable_memory;
 
 pub const MULTIPLIER_MAX_SIZE_LOCAL_SUBNET: u64 = 5;
 const MAX_NON_REPLICATED_QUERY_REPLY_SIZE: NumBytes = NumBytes::new(3 << 20);
-const CERTIFIED_DATA_MAX_LENGTH: usize = 32;
+pub const CERTIFIED_DATA_MAX_LENGTH: usize = 32;
 
 // Enables tracing of system calls for local debugging.
 const TRACE_SYSCALLS: bool = false;
@@ -2875,6 +2877,35 @@ impl SystemApi for SystemApiImpl {
         result
     }
 
+    fn ic0_set_query_cache_max_age(&mut self, max_age: Duration) -> HypervisorResult<()> {
+        self.call_counters.set_query_cache_max_age += 1;
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_set_query_cache_max_age")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Update { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::InspectMessage { .. } => {
+                // The hint only has an effect on non-replicated queries,
+                // but it's allowed everywhere, so the same code can run
+                // as both a query and an update.
+                let max_age = match self.call_counters.query_cache_max_age {
+                    Some(prev_max_age) => prev_max_age.min(max_age),
+                    None => max_age,
+                };
+                self.call_counters.query_cache_max_age = Some(max_age);
+                Ok(())
+            }
+        };
+        trace_syscall!(self, SetQueryCacheMaxAge, result, max_age);
+        result
+    }
+
     fn ic0_performance_counter(
         &self,
         performance_counter_type: PerformanceCounterType,
@@ -3989,6 +4020,308 @@ impl SystemApi for SystemApiImpl {
 
         result
     }
+
+    fn ic0_env_var_count(&self) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_count")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                Ok(self.sandbox_safe_system_state.environment_variables().len())
+            }
+        };
+
+        trace_syscall!(self, EnvVarCount, result);
+        result
+    }
+
+    fn ic0_env_var_name_size(&self, index: usize) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_name_size")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => env_var_name_at(
+                self.sandbox_safe_system_state.environment_variables(),
+                "ic0_env_var_name_size",
+                index,
+            )
+            .map(|name| name.len()),
+        };
+
+        trace_syscall!(self, EnvVarNameSize, index, result);
+        result
+    }
+
+    fn ic0_env_var_name_copy(
+        &self,
+        index: usize,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_name_copy")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                let name = env_var_name_at(
+                    self.sandbox_safe_system_state.environment_variables(),
+                    "ic0_env_var_name_copy",
+                    index,
+                )?;
+                copy_to_heap(
+                    "ic0.env_var_name_copy",
+                    name.as_bytes(),
+                    dst,
+                    offset,
+                    size,
+                    heap,
+                )
+            }
+        };
+
+        trace_syscall!(
+            self,
+            EnvVarNameCopy,
+            result,
+            index,
+            dst,
+            offset,
+            size,
+            summarize(heap, dst, size)
+        );
+        result
+    }
+
+    fn ic0_env_var_name_exists(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<i32> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_name_exists")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => env_var_value(
+                self.sandbox_safe_system_state.environment_variables(),
+                "ic0.env_var_name_exists",
+                name_src,
+                name_size,
+                heap,
+            )
+            .map(|value| value.is_some().into()),
+        };
+
+        trace_syscall!(
+            self,
+            EnvVarNameExists,
+            name_src,
+            name_size,
+            summarize(heap, name_src, name_size),
+            result
+        );
+        result
+    }
+
+    fn ic0_env_var_value_size(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        heap: &[u8],
+    ) -> HypervisorResult<usize> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_value_size")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => env_var_value(
+                self.sandbox_safe_system_state.environment_variables(),
+                "ic0.env_var_value_size",
+                name_src,
+                name_size,
+                heap,
+            )
+            .and_then(|value| {
+                value
+                    .map(|value| value.len())
+                    .ok_or_else(|| unknown_env_var_error("ic0_env_var_value_size"))
+            }),
+        };
+
+        trace_syscall!(
+            self,
+            EnvVarValueSize,
+            name_src,
+            name_size,
+            summarize(heap, name_src, name_size),
+            result
+        );
+        result
+    }
+
+    fn ic0_env_var_value_copy(
+        &self,
+        name_src: usize,
+        name_size: usize,
+        dst: usize,
+        offset: usize,
+        size: usize,
+        heap: &mut [u8],
+    ) -> HypervisorResult<()> {
+        let result = match &self.api_type {
+            ApiType::Start { .. } => Err(self.error_for("ic0_env_var_value_copy")),
+            ApiType::Init { .. }
+            | ApiType::SystemTask { .. }
+            | ApiType::Cleanup { .. }
+            | ApiType::Update { .. }
+            | ApiType::ReplicatedQuery { .. }
+            | ApiType::NonReplicatedQuery { .. }
+            | ApiType::ReplyCallback { .. }
+            | ApiType::RejectCallback { .. }
+            | ApiType::PreUpgrade { .. }
+            | ApiType::InspectMessage { .. } => {
+                let value = env_var_value(
+                    self.sandbox_safe_system_state.environment_variables(),
+                    "ic0.env_var_value_copy",
+                    name_src,
+                    name_size,
+                    heap,
+                )?
+                .ok_or_else(|| unknown_env_var_error("ic0_env_var_value_copy"))?;
+                copy_to_heap(
+                    "ic0.env_var_value_copy",
+                    value.as_bytes(),
+                    dst,
+                    offset,
+                    size,
+                    heap,
+                )
+            }
+        };
+
+        trace_syscall!(
+            self,
+            EnvVarValueCopy,
+            result,
+            name_src,
+            name_size,
+            dst,
+            offset,
+            size,
+            summarize(heap, dst, size)
+        );
+        result
+    }
+}
+
+/// Returns the name of the environment variable at the given index or an
+/// error if the index is out of bounds.
+fn env_var_name_at<'a>(
+    variables: &'a EnvironmentVariables,
+    method_name: &str,
+    index: usize,
+) -> HypervisorResult<&'a str> {
+    variables
+        .name_at(index)
+        .ok_or_else(|| ToolchainContractViolation {
+            error: format!(
+                "{} failed because the index {} is out of bounds. \
+                The canister has {} environment variables.",
+                method_name,
+                index,
+                variables.len()
+            ),
+        })
+}
+
+/// Reads the name of an environment variable from the heap and returns the
+/// value of the variable if it exists.
+fn env_var_value<'a>(
+    variables: &'a EnvironmentVariables,
+    ctx: &str,
+    name_src: usize,
+    name_size: usize,
+    heap: &[u8],
+) -> HypervisorResult<Option<&'a str>> {
+    let name = valid_subslice(
+        ctx,
+        InternalAddress::new(name_src),
+        InternalAddress::new(name_size),
+        heap,
+    )?;
+    Ok(std::str::from_utf8(name)
+        .ok()
+        .and_then(|name| variables.get(name)))
+}
+
+fn unknown_env_var_error(method_name: &str) -> HypervisorError {
+    ToolchainContractViolation {
+        error: format!(
+            "{} failed because the environment variable does not exist.",
+            method_name
+        ),
+    }
+}
+
+/// Copies `size` bytes of `data` starting at `offset` to the heap at `dst`.
+fn copy_to_heap(
+    ctx: &str,
+    data: &[u8],
+    dst: usize,
+    offset: usize,
+    size: usize,
+    heap: &mut [u8],
+) -> HypervisorResult<()> {
+    valid_subslice(
+        &format!("{} heap", ctx),
+        InternalAddress::new(dst),
+        InternalAddress::new(size),
+        heap,
+    )?;
+    let slice = valid_subslice(
+        &format!("{} data", ctx),
+        InternalAddress::new(offset),
+        InternalAddress::new(size),
+        data,
+    )?;
+    deterministic_copy_from_slice(&mut heap[dst..dst + size], slice);
+    Ok(())
 }
 
 /// The default implementation of the `OutOfInstructionHandler` trait.
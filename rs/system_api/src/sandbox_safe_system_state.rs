@@ -27,7 +27,8 @@ use ic_types::{
     messages::{CallContextId, CallbackId, RejectContext, Request, RequestMetadata, NO_DEADLINE},
     methods::Callback,
     time::CoarseTime,
-    CanisterLog, CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumInstructions, Time,
+    CanisterLog, CanisterTimer, ComputeAllocation, Cycles, EnvironmentVariables, MemoryAllocation,
+    NumInstructions, Time,
 };
 use ic_wasm_types::WasmEngineError;
 use serde::{Deserialize, Serialize};
@@ -277,7 +278,10 @@ impl SystemStateModifications {
             | Ok(Ic00Method::TakeCanisterSnapshot)
             | Ok(Ic00Method::ListCanisterSnapshots)
             | Ok(Ic00Method::DeleteCanisterSnapshot)
-            | Ok(Ic00Method::ReadCanisterSnapshotMetadata) => Ok(None),
+            | Ok(Ic00Method::ReadCanisterSnapshotMetadata)
+            | Ok(Ic00Method::ReadCanisterSnapshotData)
+            | Ok(Ic00Method::UploadCanisterSnapshotMetadata)
+            | Ok(Ic00Method::UploadCanisterSnapshotData) => Ok(None),
             Err(_) => Err(UserError::new(
                 ErrorCode::CanisterMethodNotFound,
                 format!("Management canister has no method '{}'", msg.method_name),
@@ -610,6 +614,7 @@ pub struct SandboxSafeSystemState {
     global_timer: CanisterTimer,
     canister_version: u64,
     controllers: BTreeSet<PrincipalId>,
+    environment_variables: EnvironmentVariables,
     pub(super) request_metadata: RequestMetadata,
     caller: Option<PrincipalId>,
     pub is_wasm64_execution: bool,
@@ -644,6 +649,7 @@ impl SandboxSafeSystemState {
         global_timer: CanisterTimer,
         canister_version: u64,
         controllers: BTreeSet<PrincipalId>,
+        environment_variables: EnvironmentVariables,
         request_metadata: RequestMetadata,
         caller: Option<PrincipalId>,
         next_canister_log_record_idx: u64,
@@ -681,6 +687,7 @@ impl SandboxSafeSystemState {
             global_timer,
             canister_version,
             controllers,
+            environment_variables,
             request_metadata,
             caller,
             is_wasm64_execution,
@@ -795,6 +802,7 @@ impl SandboxSafeSystemState {
             system_state.global_timer,
             system_state.canister_version,
             system_state.controllers.clone(),
+            system_state.environment_variables.clone(),
             request_metadata,
             caller,
             system_state.canister_log.next_idx(),
@@ -815,6 +823,10 @@ impl SandboxSafeSystemState {
         self.canister_version
     }
 
+    pub fn environment_variables(&self) -> &EnvironmentVariables {
+        &self.environment_variables
+    }
+
     pub fn set_global_timer(&mut self, timer: CanisterTimer) {
         // Update both sandbox global timer and the changes.
         self.system_state_modifications.new_global_timer = Some(timer);
@@ -1439,8 +1451,8 @@ mod tests {
     use ic_types::{
         messages::{RequestMetadata, NO_DEADLINE},
         time::CoarseTime,
-        CanisterTimer, ComputeAllocation, Cycles, MemoryAllocation, NumBytes, NumInstructions,
-        Time,
+        CanisterTimer, ComputeAllocation, Cycles, EnvironmentVariables, MemoryAllocation, NumBytes,
+        NumInstructions, Time,
     };
 
     use crate::{
@@ -1542,6 +1554,7 @@ mod tests {
             CanisterTimer::Inactive,
             0,
             BTreeSet::new(),
+            EnvironmentVariables::default(),
             RequestMetadata::new(0, Time::from_nanos_since_unix_epoch(0)),
             None,
             0,
@@ -1593,6 +1606,7 @@ mod tests {
             CanisterTimer::Inactive,
             0,
             BTreeSet::new(),
+            EnvironmentVariables::default(),
             RequestMetadata::new(0, Time::from_nanos_since_unix_epoch(0)),
             None,
             0,
@@ -44,6 +44,7 @@ use std::{
     convert::From,
     panic::{catch_unwind, UnwindSafe},
     rc::Rc,
+    time::Duration,
 };
 use strum::IntoEnumIterator;
 
@@ -61,6 +62,16 @@ fn get_system_state_with_cycles(cycles_amount: Cycles) -> SystemState {
     )
 }
 
+/// Returns a system state with a single environment variable whose name `*`
+/// matches the contents of the heaps used in the availability tests.
+fn get_system_state_with_environment_variables() -> SystemState {
+    let mut system_state = get_system_state();
+    system_state.environment_variables = [("*".to_string(), "value".to_string())]
+        .into_iter()
+        .collect();
+    system_state
+}
+
 fn assert_api_supported<T>(res: HypervisorResult<T>) {
     res.unwrap();
 }
@@ -297,6 +308,7 @@ fn is_supported(api_type: SystemApiCallId, context: &str) -> bool {
         SystemApiCallId::Time => vec!["*"],
         SystemApiCallId::GlobalTimerSet => vec!["I", "G", "U", "Ry", "Rt", "C", "T"],
         SystemApiCallId::PerformanceCounter => vec!["*", "s"],
+        SystemApiCallId::SetQueryCacheMaxAge => vec!["*"],
         SystemApiCallId::IsController => vec!["*", "s"],
         SystemApiCallId::InReplicatedExecution => vec!["*", "s"],
         SystemApiCallId::CostCall => vec!["*", "s"],
@@ -311,6 +323,12 @@ fn is_supported(api_type: SystemApiCallId, context: &str) -> bool {
         SystemApiCallId::MintCycles128 => vec!["U", "Ry", "Rt", "T"],
         SystemApiCallId::SubnetSelfSize => vec!["*"],
         SystemApiCallId::SubnetSelfCopy => vec!["*"],
+        SystemApiCallId::EnvVarCount => vec!["*"],
+        SystemApiCallId::EnvVarNameSize => vec!["*"],
+        SystemApiCallId::EnvVarNameCopy => vec!["*"],
+        SystemApiCallId::EnvVarNameExists => vec!["*"],
+        SystemApiCallId::EnvVarValueSize => vec!["*"],
+        SystemApiCallId::EnvVarValueCopy => vec!["*"],
     };
     // the semantics of "*" is to cover all modes except for "s"
     matrix.get(&api_type).unwrap().contains(&context)
@@ -617,6 +635,16 @@ fn api_availability_test(
                 context,
             );
         }
+        SystemApiCallId::SetQueryCacheMaxAge => {
+            assert_api_availability(
+                |mut api| api.ic0_set_query_cache_max_age(Duration::from_secs(1)),
+                api_type,
+                &system_state,
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
         SystemApiCallId::PerformanceCounter => {
             assert_api_availability(
                 |api| api.ic0_performance_counter(PerformanceCounterType::Instructions(0.into())),
@@ -827,6 +855,66 @@ fn api_availability_test(
                 context,
             );
         }
+        SystemApiCallId::EnvVarCount => {
+            assert_api_availability(
+                |api| api.ic0_env_var_count(),
+                api_type,
+                &get_system_state_with_environment_variables(),
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarNameSize => {
+            assert_api_availability(
+                |api| api.ic0_env_var_name_size(0),
+                api_type,
+                &get_system_state_with_environment_variables(),
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarNameCopy => {
+            assert_api_availability(
+                |api| api.ic0_env_var_name_copy(0, 0, 0, 1, &mut [42; 128]),
+                api_type,
+                &get_system_state_with_environment_variables(),
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarNameExists => {
+            assert_api_availability(
+                |api| api.ic0_env_var_name_exists(0, 1, &[42; 128]),
+                api_type,
+                &get_system_state_with_environment_variables(),
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarValueSize => {
+            assert_api_availability(
+                |api| api.ic0_env_var_value_size(0, 1, &[42; 128]),
+                api_type,
+                &get_system_state_with_environment_variables(),
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
+        SystemApiCallId::EnvVarValueCopy => {
+            assert_api_availability(
+                |api| api.ic0_env_var_value_copy(0, 1, 0, 0, 5, &mut [42; 128]),
+                api_type,
+                &get_system_state_with_environment_variables(),
+                cycles_account_manager,
+                api_type_enum,
+                context,
+            );
+        }
         // stable API is tested separately
         SystemApiCallId::StableGrow
         | SystemApiCallId::StableRead
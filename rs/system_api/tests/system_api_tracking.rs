@@ -3,6 +3,7 @@ use ic_system_api::NonReplicatedQueryKind;
 use ic_test_utilities_embedders::WasmtimeInstanceBuilder;
 use ic_test_utilities_types::ids::{subnet_test_id, user_test_id};
 use ic_types::time::UNIX_EPOCH;
+use std::time::Duration;
 
 fn call_counters_on_ok_call(wat: &str) -> SystemApiCallCounters {
     let mut instance = WasmtimeInstanceBuilder::new()
@@ -119,6 +120,31 @@ fn track_time() {
     assert_eq!(call_counters.time, 1);
 }
 
+#[test]
+fn track_set_query_cache_max_age() {
+    let wat = r#"(module
+                (import "ic0" "set_query_cache_max_age"
+                    (func $ic0_set_query_cache_max_age (param i64))
+                )
+                (memory 1)
+                (func (export "canister_composite_query call_system_api")
+                    (call $ic0_set_query_cache_max_age (i64.const 7))
+                    (call $ic0_set_query_cache_max_age (i64.const 3))
+                    (call $ic0_set_query_cache_max_age (i64.const 5))
+                )
+            )"#;
+    let call_counters = call_counters_on_ok_call(wat);
+    assert_eq!(call_counters.set_query_cache_max_age, 3);
+    // The smallest max age applies.
+    assert_eq!(
+        call_counters.query_cache_max_age,
+        Some(Duration::from_secs(3))
+    );
+    let call_counters = call_counters_on_err_call(wat);
+    assert_eq!(call_counters.set_query_cache_max_age, 1);
+    assert_eq!(call_counters.query_cache_max_age, None);
+}
+
 #[test]
 fn track_other() {
     let wat = r#"(module
@@ -132,6 +158,8 @@ fn track_other() {
     assert_eq!(call_counters.canister_cycle_balance, 0);
     assert_eq!(call_counters.canister_cycle_balance128, 0);
     assert_eq!(call_counters.time, 0);
+    assert_eq!(call_counters.set_query_cache_max_age, 0);
+    assert_eq!(call_counters.query_cache_max_age, None);
     let call_counters = call_counters_on_err_call(wat);
     assert_eq!(call_counters.canister_cycle_balance, 0);
     assert_eq!(call_counters.canister_cycle_balance128, 0);
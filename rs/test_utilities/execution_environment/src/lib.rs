@@ -30,8 +30,8 @@ use ic_logger::{replica_logger::no_op_logger, ReplicaLogger};
 use ic_management_canister_types_private::{
     CanisterIdRecord, CanisterInstallMode, CanisterInstallModeV2, CanisterSettingsArgs,
     CanisterSettingsArgsBuilder, CanisterStatusType, CanisterUpgradeOptions, EmptyBlob,
-    InstallCodeArgs, InstallCodeArgsV2, LogVisibilityV2, MasterPublicKeyId, Method, Payload,
-    ProvisionalCreateCanisterWithCyclesArgs, UpdateSettingsArgs,
+    EnvironmentVariable, InstallCodeArgs, InstallCodeArgsV2, LogVisibilityV2, MasterPublicKeyId,
+    Method, Payload, ProvisionalCreateCanisterWithCyclesArgs, UpdateSettingsArgs,
 };
 use ic_metrics::MetricsRegistry;
 use ic_registry_provisional_whitelist::ProvisionalWhitelist;
@@ -646,6 +646,23 @@ impl ExecutionTest {
         self.subnet_message(Method::UpdateSettings, payload)
     }
 
+    /// Replaces the environment variables of the canister.
+    pub fn canister_update_environment_variables(
+        &mut self,
+        canister_id: CanisterId,
+        environment_variables: Vec<EnvironmentVariable>,
+    ) -> Result<WasmResult, UserError> {
+        let payload = UpdateSettingsArgs {
+            canister_id: canister_id.into(),
+            settings: CanisterSettingsArgsBuilder::new()
+                .with_environment_variables(environment_variables)
+                .build(),
+            sender_canister_version: None,
+        }
+        .encode();
+        self.subnet_message(Method::UpdateSettings, payload)
+    }
+
     pub fn canister_update_wasm_memory_limit_and_wasm_memory_threshold(
         &mut self,
         canister_id: CanisterId,
@@ -2420,6 +2437,7 @@ impl ExecutionTestBuilder {
             self.instruction_limit_without_dts,
             Arc::clone(&cycles_account_manager),
             query_stats_collector,
+            Arc::clone(exec_env.execution_tracer()),
         );
         ExecutionTest {
             state: Some(state),
@@ -49,6 +49,7 @@ pub fn test_ckbtc_addresses(env: TestEnv) {
         let arg = GetBtcAddressArgs {
             owner: None,
             subaccount: None,
+            address_type: None,
         };
         let arg = Encode!(&arg).expect("Error while encoding arg.");
         let res = agent
@@ -70,6 +71,7 @@ pub fn test_ckbtc_addresses(env: TestEnv) {
         let arg = GetBtcAddressArgs {
             owner: None,
             subaccount: None,
+            address_type: None,
         };
         let arg = Encode!(&arg).expect("Error while encoding argument.");
         let res = agent
@@ -197,6 +199,7 @@ async fn test_update_balance(agent: &CkBtcMinterAgent) {
     let args = UpdateBalanceArgs {
         owner: None,
         subaccount: Some(subaccount),
+        address_type: None,
     };
     let res = agent
         .update_balance(args)
@@ -135,6 +135,7 @@ pub fn test_btc_checker(env: TestEnv) {
             .update_balance(UpdateBalanceArgs {
                 owner: None,
                 subaccount: Some(subaccount1),
+                address_type: None,
             })
             .await
             .expect("Error while calling update_balance")
@@ -164,6 +165,7 @@ pub fn test_btc_checker(env: TestEnv) {
             .update_balance(UpdateBalanceArgs {
                 owner: None,
                 subaccount: Some(subaccount1),
+                address_type: None,
             })
             .await
             .expect("Error while calling update_balance");
@@ -185,6 +187,7 @@ pub fn test_btc_checker(env: TestEnv) {
             .update_balance(UpdateBalanceArgs {
                 owner: None,
                 subaccount: Some(subaccount1),
+                address_type: None,
             })
             .await
             .expect("Error while calling update_balance")
@@ -229,6 +232,7 @@ pub fn test_btc_checker(env: TestEnv) {
             .update_balance(UpdateBalanceArgs {
                 owner: None,
                 subaccount: Some(subaccount1),
+                address_type: None,
             })
             .await
             .expect("Error while calling update_balance")
@@ -245,6 +249,7 @@ pub fn test_btc_checker(env: TestEnv) {
             .update_balance(UpdateBalanceArgs {
                 owner: None,
                 subaccount: Some(subaccount1),
+                address_type: None,
             })
             .await
             .expect("Error while calling update_balance")
@@ -298,6 +298,7 @@ pub fn test_update_balance(env: TestEnv) {
             .update_balance(UpdateBalanceArgs {
                 owner: Some(caller),
                 subaccount: Some(subaccount3),
+                address_type: None,
             })
             .await
             .expect("Error while calling update_balance")
@@ -0,0 +1,83 @@
+use crate::NumBytes;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Environment variables of a canister.
+///
+/// They are set by the controllers in the canister settings and can be read
+/// by the canister through the system API. The variables are kept sorted by
+/// name, which determines their index in the system API.
+#[derive(Clone, Eq, PartialEq, Debug, Default, Deserialize, Serialize)]
+pub struct EnvironmentVariables {
+    variables: BTreeMap<String, String>,
+}
+
+impl EnvironmentVariables {
+    pub fn new(variables: BTreeMap<String, String>) -> Self {
+        Self { variables }
+    }
+
+    /// Returns the number of environment variables.
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    /// Returns the name of the variable at the given index in the sorted
+    /// order of names.
+    pub fn name_at(&self, index: usize) -> Option<&str> {
+        self.variables.keys().nth(index).map(String::as_str)
+    }
+
+    /// Returns the value of the variable with the given name.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(String::as_str)
+    }
+
+    /// Returns the memory used by the names and the values of the variables.
+    pub fn memory_usage(&self) -> NumBytes {
+        NumBytes::from(
+            self.variables
+                .iter()
+                .map(|(name, value)| (name.len() + value.len()) as u64)
+                .sum::<u64>(),
+        )
+    }
+
+    /// Returns an iterator over the variables sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &String)> {
+        self.variables.iter()
+    }
+}
+
+impl FromIterator<(String, String)> for EnvironmentVariables {
+    fn from_iter<T: IntoIterator<Item = (String, String)>>(iter: T) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variables_are_indexed_by_sorted_name() {
+        let variables: EnvironmentVariables = [
+            ("B".to_string(), "2".to_string()),
+            ("A".to_string(), "1".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(variables.len(), 2);
+        assert_eq!(variables.name_at(0), Some("A"));
+        assert_eq!(variables.name_at(1), Some("B"));
+        assert_eq!(variables.name_at(2), None);
+        assert_eq!(variables.get("B"), Some("2"));
+        assert_eq!(variables.get("C"), None);
+        assert_eq!(variables.memory_usage(), NumBytes::from(4));
+    }
+}
@@ -523,7 +523,8 @@ impl ExhaustiveSet for BitcoinAdapterResponseWrapper {
         let successors = blobs.into_iter().map(|blob| {
             BitcoinAdapterResponseWrapper::GetSuccessorsResponse(GetSuccessorsResponseComplete {
                 blocks: blob.clone(),
-                next: blob,
+                next: blob.clone(),
+                filters: blob,
             })
         });
         let transactions = std::iter::once(BitcoinAdapterResponseWrapper::SendTransactionResponse(
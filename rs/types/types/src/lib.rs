@@ -69,6 +69,7 @@ pub mod canister_http;
 pub mod canister_log;
 pub mod consensus;
 pub mod crypto;
+pub mod environment_variables;
 pub mod funds;
 pub mod hostos_version;
 pub mod ingress;
@@ -89,6 +90,7 @@ pub mod xnet;
 pub mod exhaustive;
 
 pub use crate::canister_log::{CanisterLog, MAX_ALLOWED_CANISTER_LOG_BUFFER_SIZE};
+pub use crate::environment_variables::EnvironmentVariables;
 pub use crate::replica_version::ReplicaVersion;
 pub use crate::time::Time;
 pub use funds::*;
@@ -9,7 +9,6 @@ pub use errors::{
 use ic_types::MemoryDiskBytes;
 use ic_utils::byte_slice_fmt::truncate_and_format;
 use ic_validate_eq::ValidateEq;
-use ic_validate_eq_derive::ValidateEq;
 use std::{
     fmt,
     path::{Path, PathBuf},
@@ -52,13 +51,13 @@ impl BinaryEncodedWasm {
 ///   * Gzip-compressed Wasm modules (magic number \1f\8b\08)
 // We don't derive `Serialize` and `Deserialize` because this is a binary that is serialized by
 // writing it to a file when creating checkpoints.
-#[derive(Clone, ValidateEq)]
+#[derive(Clone)]
 pub struct CanisterModule {
     // The Wasm binary.
-    #[validate_eq(Ignore)]
     module: ModuleStorage,
-    // The Sha256 hash of the binary.
-    module_hash: [u8; WASM_HASH_LENGTH],
+    // The Sha256 hash of the binary or `None` if the binary was modified by
+    // `write` since the hash was last computed.
+    module_hash: Option<[u8; WASM_HASH_LENGTH]>,
 }
 
 impl CanisterModule {
@@ -67,7 +66,7 @@ impl CanisterModule {
         let module_hash = ic_crypto_sha2::Sha256::hash(module.as_slice());
         Self {
             module,
-            module_hash,
+            module_hash: Some(module_hash),
         }
     }
 
@@ -79,7 +78,7 @@ impl CanisterModule {
             module_hash.map_or_else(|| ic_crypto_sha2::Sha256::hash(module.as_slice()), |h| h.0);
         Ok(Self {
             module,
-            module_hash,
+            module_hash: Some(module_hash),
         })
     }
 
@@ -111,8 +110,55 @@ impl CanisterModule {
     }
 
     /// Returns the Sha256 hash of this Wasm module.
+    ///
+    /// The hash is recomputed on every call if the module was modified by
+    /// `write` and `finalize_hash` wasn't called since.
     pub fn module_hash(&self) -> [u8; WASM_HASH_LENGTH] {
         self.module_hash
+            .unwrap_or_else(|| ic_crypto_sha2::Sha256::hash(self.as_slice()))
+    }
+
+    /// Overwrites the bytes of this module at the given `offset` with `buf`,
+    /// padding the module with zeros if it is shorter than `offset + buf.len()`.
+    ///
+    /// The bytes are patched in place unless the module is backed by a file or
+    /// shared with another `CanisterModule`, in which case it is copied once.
+    /// The hash of the module is not recomputed, so that writing a module
+    /// slice by slice stays linear in its size: call `finalize_hash` once the
+    /// module is complete.
+    pub fn write(&mut self, buf: &[u8], offset: usize) {
+        if let ModuleStorage::File(_, mmap) = &self.module {
+            self.module = ModuleStorage::Memory(Arc::new(mmap.as_slice().to_vec()));
+        }
+        let ModuleStorage::Memory(shared) = &mut self.module else {
+            unreachable!("The module was copied into memory above");
+        };
+        let bytes = Arc::make_mut(shared);
+        let end = offset + buf.len();
+        if bytes.len() < end {
+            bytes.resize(end, 0);
+        }
+        bytes[offset..end].copy_from_slice(buf);
+        self.module_hash = None;
+    }
+
+    /// Computes the hash of this module if it was modified by `write` since
+    /// the hash was last computed.
+    pub fn finalize_hash(&mut self) {
+        if self.module_hash.is_none() {
+            self.module_hash = Some(ic_crypto_sha2::Sha256::hash(self.as_slice()));
+        }
+    }
+}
+
+impl ValidateEq for CanisterModule {
+    fn validate_eq(&self, rhs: &Self) -> Result<(), String> {
+        // The binary itself is not compared, and a hash that was not computed
+        // yet is not a divergence.
+        if self.module_hash() != rhs.module_hash() {
+            return Err("module_hash".to_string());
+        }
+        Ok(())
     }
 }
 
@@ -248,3 +294,20 @@ impl ModuleStorage {
         }
     }
 }
+
+#[test]
+fn canister_module_write_pads_and_patches_in_place() {
+    let mut module = CanisterModule::new(vec![]);
+    module.write(&[1, 2], 3);
+    assert_eq!(module.as_slice(), &[0, 0, 0, 1, 2]);
+    let shared = module.to_shared_vec();
+    module.write(&[3], 0);
+    assert_eq!(module.as_slice(), &[3, 0, 0, 1, 2]);
+    // The shared copy is not modified.
+    assert_eq!(shared.as_slice(), &[0, 0, 0, 1, 2]);
+    let expected = CanisterModule::new(vec![3, 0, 0, 1, 2]);
+    assert_eq!(module, expected);
+    assert_eq!(module.validate_eq(&expected), Ok(()));
+    module.finalize_hash();
+    assert_eq!(module.module_hash, Some(expected.module_hash()));
+}